@@ -0,0 +1,231 @@
+// Replays a game from the server archive in the terminal.
+//
+// The game is fetched via the regular client protocol, so this works with any running server that
+// has game history enabled. Use arrow keys to navigate the game, "q" to quit.
+
+use std::{io, panic};
+
+use bughouse_chess::client::{ClientError, ClientState};
+use bughouse_chess::utc_time::UtcDateTime;
+use crossterm::style::{self, Stylize};
+use crossterm::{cursor, event as term_event, execute, terminal};
+use scopeguard::defer;
+use tungstenite::protocol;
+
+use crate::bughouse_prelude::*;
+use crate::client_main::{connect, restore_terminal, writeln_raw};
+use crate::{network, tui};
+
+
+pub struct ArchiveViewerConfig {
+    pub server_address: String,
+    pub game_id: i64,
+}
+
+pub fn run(config: ArchiveViewerConfig) -> io::Result<()> {
+    let mut socket_in = connect(&config.server_address)?;
+    let mut socket_out = network::clone_websocket(&socket_in, protocol::Role::Client);
+    let mut client_state = ClientState::new("Terminal".to_owned(), "?".to_owned());
+    let fetch_result = fetch_archive_game(
+        &mut client_state,
+        config.game_id,
+        |event| network::write_obj(&mut socket_out, &event).map_err(|err| format!("{err:?}")),
+        || network::read_obj(&mut socket_in).map_err(|err| format!("{err:?}")),
+    );
+    if let Err(err) = fetch_result {
+        eprintln!("{err}");
+        return Ok(());
+    }
+
+    let std_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        restore_terminal();
+        std_panic_hook(panic_info);
+    }));
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    defer! { restore_terminal(); };
+    loop {
+        execute!(stdout, cursor::MoveTo(0, 0), terminal::Clear(terminal::ClearType::All))?;
+        writeln_raw(&mut stdout, render_archive_game(&client_state))?;
+        if let term_event::Event::Key(event) = term_event::read()? {
+            let destination = match event.code {
+                term_event::KeyCode::Left => WaybackDestination::Previous,
+                term_event::KeyCode::Right => WaybackDestination::Next,
+                term_event::KeyCode::Home => WaybackDestination::First,
+                term_event::KeyCode::End => WaybackDestination::Last,
+                term_event::KeyCode::Char('q') | term_event::KeyCode::Esc => return Ok(()),
+                _ => continue,
+            };
+            client_state.wayback_to(destination, None);
+        }
+    }
+}
+
+// Requests the game from the server and processes server events until the game is loaded.
+//
+// Networking is abstracted away in order to make the function testable.
+fn fetch_archive_game(
+    client_state: &mut ClientState, game_id: i64,
+    mut send: impl FnMut(BughouseClientEvent) -> Result<(), String>,
+    mut receive: impl FnMut() -> Result<BughouseServerEvent, String>,
+) -> Result<(), String> {
+    client_state
+        .view_archive_game_content(game_id)
+        .map_err(|err| describe_fetch_error(game_id, None, err))?;
+    loop {
+        while let Some(event) = client_state.next_outgoing_event() {
+            send(event)?;
+        }
+        if client_state.game_state().is_some() {
+            return Ok(());
+        }
+        let event = receive()?;
+        let game_start_time = match &event {
+            BughouseServerEvent::ArchiveGameBpgn { bpgn, .. } => {
+                pgn::import_meta_from_bpgn(bpgn).ok().map(|meta| meta.game_start_time)
+            }
+            _ => None,
+        };
+        client_state
+            .process_server_event(event)
+            .map_err(|err| describe_fetch_error(game_id, game_start_time, err))?;
+    }
+}
+
+fn describe_fetch_error(
+    game_id: i64, game_start_time: Option<UtcDateTime>, err: ClientError,
+) -> String {
+    match err {
+        // The only internal error that can happen here is failing to parse the BPGN.
+        ClientError::Internal(message) => match game_start_time {
+            Some(t) if !pgn::game_view_available(t) => format!(
+                "{message}\nGame {game_id} cannot be viewed: games played before {} are not \
+                supported.",
+                pgn::GAME_VIEW_AVAILABLE_SINCE.date()
+            ),
+            _ => format!("{message}\nGame {game_id} cannot be viewed."),
+        },
+        ClientError::Ignorable(message)
+        | ClientError::KickedFromMatch(message)
+        | ClientError::Fatal(message) => message,
+    }
+}
+
+fn render_archive_game(client_state: &ClientState) -> String {
+    let Some(game_state) = client_state.game_state() else {
+        return "No game loaded".to_owned();
+    };
+    let alt_game = &game_state.alt_game;
+    let game = alt_game.local_game();
+    let position = match alt_game.wayback_state() {
+        WaybackState::Active(index) => format!("Turn {}", index.0 + 1),
+        WaybackState::Enabled(_) | WaybackState::Disabled => "Final position".to_owned(),
+    };
+    format!(
+        "{}\n\n{}\n{}\n{}",
+        tui::render_bughouse_game(&game, alt_game.my_id(), GameInstant::game_start()),
+        position,
+        format!("Game over: {:?}", alt_game.status()).with(style::Color::Magenta),
+        "←/→: previous/next turn, Home/End: first/last turn, q: quit".with(style::Color::DarkGrey),
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    use bughouse_chess::pgn::{BpgnExportFormat, BpgnMetadata};
+    use bughouse_chess::role::Role;
+    use bughouse_chess::test_util::{replay_bughouse_log, sample_bughouse_players};
+
+    use super::*;
+
+    const GAME_ID: i64 = 42;
+
+    fn sample_bpgn() -> String {
+        let rules = Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules::bughouse_international5(),
+        };
+        let mut game =
+            BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+        replay_bughouse_log(&mut game, "1A.e4 1a.e5 1B.d4", Duration::from_millis(100)).unwrap();
+        game.set_status(
+            BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation),
+            GameInstant::from_duration(Duration::from_secs(1)),
+        );
+        let meta = BpgnMetadata {
+            game_start_time: UtcDateTime::now(),
+            round: 1,
+        };
+        pgn::export_to_bpgn(BpgnExportFormat::default(), &game, meta)
+    }
+
+    // Returns the events sent by the client.
+    fn fetch_with_response(
+        client_state: &mut ClientState, response: BughouseServerEvent,
+    ) -> (Vec<BughouseClientEvent>, Result<(), String>) {
+        let mut sent = vec![];
+        let mut responses = VecDeque::from([response]);
+        let result = fetch_archive_game(
+            client_state,
+            GAME_ID,
+            |event| {
+                sent.push(event);
+                Ok(())
+            },
+            || responses.pop_front().ok_or_else(|| "Connection closed".to_owned()),
+        );
+        (sent, result)
+    }
+
+    #[test]
+    fn fetch_and_render() {
+        let mut client_state = ClientState::new("Terminal".to_owned(), "?".to_owned());
+        let (sent, result) =
+            fetch_with_response(&mut client_state, BughouseServerEvent::ArchiveGameBpgn {
+                game_id: GAME_ID,
+                bpgn: sample_bpgn(),
+            });
+        result.unwrap();
+        assert!(matches!(sent[..], [BughouseClientEvent::GetArchiveGameBpgn {
+            game_id: GAME_ID
+        }]));
+        let rendered = render_archive_game(&client_state);
+        assert!(rendered.contains("Alice"));
+        assert!(rendered.contains("Final position"));
+
+        client_state.wayback_to(WaybackDestination::First, None);
+        assert!(render_archive_game(&client_state).contains("Turn "));
+    }
+
+    #[test]
+    fn fetch_unparsable_game() {
+        let mut client_state = ClientState::new("Terminal".to_owned(), "?".to_owned());
+        let (_, result) =
+            fetch_with_response(&mut client_state, BughouseServerEvent::ArchiveGameBpgn {
+                game_id: GAME_ID,
+                bpgn: "[Event \"Bughouse Match\"]\n[UTCDate \"2022.05.01\"]\n\
+                    [UTCTime \"10:00:00\"]\n[Round \"1\"]\n1A. e4"
+                    .to_owned(),
+            });
+        let err = result.unwrap_err();
+        assert!(err.contains("games played before 2023-03-01 are not supported"), "{err}");
+    }
+
+    #[test]
+    fn fetch_error() {
+        let mut client_state = ClientState::new("Terminal".to_owned(), "?".to_owned());
+        let (_, result) = fetch_with_response(
+            &mut client_state,
+            BughouseServerEvent::Rejection(BughouseServerRejection::ErrorFetchingData {
+                message: "No such game".to_owned(),
+            }),
+        );
+        assert_eq!(result.unwrap_err(), "Error fetching data: No such game");
+    }
+}
@@ -10,7 +10,6 @@ use time::OffsetDateTime;
 
 use crate::auth;
 use crate::http_server_state::*;
-use crate::prod_server_helpers::validate_player_name;
 use crate::secret_persistence::{Account, DeletedAccount, LiveAccount};
 use crate::server_config::AllowedOrigin;
 
@@ -126,7 +125,8 @@ pub async fn handle_signup<DB: Send + Sync + 'static>(
     let SignupData { user_name, email, password } = req.body_form().await?;
     let email = if email.is_empty() { None } else { Some(email) };
 
-    validate_player_name(&user_name)
+    req.state()
+        .validate_player_name(&user_name)
         .map_err(|err| tide::Error::from_str(StatusCode::Forbidden, err))?;
 
     let existing_account = req.state().secret_db.account_by_user_name(&user_name).await?;
@@ -429,7 +429,8 @@ pub async fn handle_finish_signup_with_google<DB: Send + Sync + 'static>(
 ) -> tide::Result {
     let FinishSignupWithGoogleData { user_name } = req.body_form().await?;
 
-    validate_player_name(&user_name)
+    req.state()
+        .validate_player_name(&user_name)
         .map_err(|err| tide::Error::from_str(StatusCode::Forbidden, err))?;
 
     let existing_account = req.state().secret_db.account_by_user_name(&user_name).await?;
@@ -487,7 +488,8 @@ pub async fn handle_finish_signup_with_lichess<DB: Send + Sync + 'static>(
 ) -> tide::Result {
     let FinishSignupWithLichessData { user_name } = req.body_form().await?;
 
-    validate_player_name(&user_name)
+    req.state()
+        .validate_player_name(&user_name)
         .map_err(|err| tide::Error::from_str(StatusCode::Forbidden, err))?;
 
     let existing_account = req.state().secret_db.account_by_user_name(&user_name).await?;
@@ -712,9 +714,9 @@ pub async fn handle_check_player_name<DB>(
         .body_string()
         .await
         .map_err(|err| tide::Error::from_str(StatusCode::BadRequest, err))?;
-    match validate_player_name(&player_name) {
+    match req.state().validate_player_name(&player_name) {
         Ok(_) => Ok("".into()),
-        Err(message) => Ok(message.into()),
+        Err(err) => Ok(err.to_string().into()),
     }
 }
 
@@ -725,11 +727,11 @@ pub async fn handle_check_new_user_name<DB>(
         .body_string()
         .await
         .map_err(|err| tide::Error::from_str(StatusCode::BadRequest, err))?;
-    match validate_player_name(&user_name) {
+    match req.state().validate_player_name(&user_name) {
         Ok(_) => match req.state().secret_db.account_by_user_name(&user_name).await? {
             Some(_) => Ok(format!("Username '{}' is already taken.", &user_name).into()),
             None => Ok("".into()),
         },
-        Err(message) => Ok(message.into()),
+        Err(err) => Ok(err.to_string().into()),
     }
 }
@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bughouse_chess::pgn::BpgnMetadata;
+use bughouse_chess::server_hooks::ServerHooks;
+use bughouse_chess::utc_time::UtcDateTime;
+use itertools::Itertools;
+use log::error;
+use strum::IntoEnumIterator;
+use time::OffsetDateTime;
+
+use crate::bughouse_prelude::*;
+
+
+// Writes BPGN of each finished or interrupted game into a separate file. Other requests are forwarded to the
+// inner hooks (normally, `DatabaseServerHooks`), if any.
+pub struct BpgnArchiveServerHooks {
+    dir: PathBuf,
+    inner: Option<Arc<dyn ServerHooks + Send + Sync>>,
+}
+
+impl BpgnArchiveServerHooks {
+    pub fn new(
+        dir: PathBuf, inner: Option<Arc<dyn ServerHooks + Send + Sync>>,
+    ) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir, inner })
+    }
+
+    fn write_game(
+        &self, game: &BughouseGame, game_start_time: UtcDateTime, round: u64,
+    ) -> io::Result<PathBuf> {
+        let bpgn_meta = BpgnMetadata { game_start_time, round };
+        let bpgn = pgn::export_to_bpgn(pgn::BpgnExportFormat::default(), game, bpgn_meta);
+        let base_name = archive_file_base_name(game, game_start_time);
+        // Not checking `exists` beforehand: `create_new` is atomic, so it also handles the case
+        // when two games with the same name finish simultaneously.
+        for index in 1.. {
+            let file_name = if index == 1 {
+                format!("{base_name}.bpgn")
+            } else {
+                format!("{base_name}-{index}.bpgn")
+            };
+            let path = self.dir.join(file_name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(bpgn.as_bytes())?;
+                    return Ok(path);
+                }
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        unreachable!()
+    }
+}
+
+#[async_trait]
+impl ServerHooks for BpgnArchiveServerHooks {
+    async fn record_client_performance(&self, perf: &BughouseClientPerformance) {
+        if let Some(inner) = &self.inner {
+            inner.record_client_performance(perf).await;
+        }
+    }
+
+    async fn record_finished_game(
+        &self, game: &BughouseGame, registered_users: &HashSet<String>,
+        game_start_time: UtcDateTime, game_end_time: UtcDateTime, round: u64,
+    ) {
+        if let Err(e) = self.write_game(game, game_start_time, round) {
+            error!("Error writing game BPGN to {}: {}", self.dir.display(), e);
+        }
+        if let Some(inner) = &self.inner {
+            inner
+                .record_finished_game(game, registered_users, game_start_time, game_end_time, round)
+                .await;
+        }
+    }
+
+    async fn record_interrupted_game(
+        &self, game: &BughouseGame, game_start_time: UtcDateTime,
+        game_interruption_time: UtcDateTime, round: u64,
+    ) {
+        // Interrupted games are archived alongside finished ones. The result tag tells them apart.
+        if let Err(e) = self.write_game(game, game_start_time, round) {
+            error!("Error writing game BPGN to {}: {}", self.dir.display(), e);
+        }
+        if let Some(inner) = &self.inner {
+            inner
+                .record_interrupted_game(game, game_start_time, game_interruption_time, round)
+                .await;
+        }
+    }
+
+    async fn get_games_by_user(
+        &self, user_name: &str,
+    ) -> Result<Vec<FinishedGameDescription>, String> {
+        match &self.inner {
+            Some(inner) => inner.get_games_by_user(user_name).await,
+            None => Err("Game history not available".to_owned()),
+        }
+    }
+
+    async fn get_game_bpgn(&self, game_id: i64) -> Result<String, String> {
+        match &self.inner {
+            Some(inner) => inner.get_game_bpgn(game_id).await,
+            None => Err("Game history not available".to_owned()),
+        }
+    }
+
+    async fn get_player_rating(&self, user_name: &str, rules: &ChessRules) -> Option<u32> {
+        match &self.inner {
+            Some(inner) => inner.get_player_rating(user_name, rules).await,
+            None => None,
+        }
+    }
+}
+
+// Example: "2024-03-18_21-01-37_Alice-Dave_vs_Bob-Charlie".
+fn archive_file_base_name(game: &BughouseGame, game_start_time: UtcDateTime) -> String {
+    let time = OffsetDateTime::from(game_start_time)
+        .format(time::macros::format_description!(
+            "[year]-[month]-[day]_[hour]-[minute]-[second]"
+        ))
+        .unwrap();
+    let teams = Team::iter()
+        .map(|team| {
+            BughouseBoard::iter()
+                .map(|board_idx| {
+                    let force = get_bughouse_force(team, board_idx);
+                    sanitize_file_name_part(game.board(board_idx).player_name(force))
+                })
+                .dedup()
+                .join("-")
+        })
+        .join("_vs_");
+    format!("{time}_{teams}")
+}
+
+// Keeps the name readable while making sure it cannot escape the directory or confuse the shell.
+fn sanitize_file_name_part(s: &str) -> String {
+    s.chars().map(|ch| if ch.is_alphanumeric() { ch } else { '_' }).collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bughouse_chess::role::Role;
+    use bughouse_chess::test_util::sample_bughouse_players;
+
+    use super::*;
+
+    fn is_safe_file_name(name: &str) -> bool {
+        name.chars().all(|ch| ch.is_alphanumeric() || "-_.".contains(ch))
+    }
+
+    fn finished_game(players: &[PlayerInGame]) -> BughouseGame {
+        let rules = Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules::bughouse_international5(),
+        };
+        let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, players);
+        let envoy = BughouseEnvoy {
+            board_idx: BughouseBoard::A,
+            force: Force::White,
+        };
+        game.try_turn_by_envoy(
+            envoy,
+            &TurnInput::Algebraic("e4".to_owned()),
+            TurnMode::InOrder,
+            GameInstant::from_duration(Duration::from_secs(1)),
+        )
+        .unwrap();
+        game.set_status(
+            BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation),
+            GameInstant::from_duration(Duration::from_secs(2)),
+        );
+        game
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("bughouse-archive-test-{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn finished_game_produces_parseable_bpgn() {
+        let dir = temp_dir();
+        let hooks = BpgnArchiveServerHooks::new(dir.clone(), None).unwrap();
+        let mut players = sample_bughouse_players();
+        players[1].name = "../Bob".to_owned();
+        let game = finished_game(&players);
+        let start_time = UtcDateTime::from(time::macros::datetime!(2024-03-18 21:01:37));
+        for _ in 0..2 {
+            async_std::task::block_on(hooks.record_finished_game(
+                &game,
+                &HashSet::new(),
+                start_time,
+                start_time,
+                1,
+            ));
+        }
+
+        let file_names: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .sorted()
+            .collect();
+        assert_eq!(file_names, [
+            "2024-03-18_21-01-37_Alice-Dave_vs____Bob-Charlie-2.bpgn",
+            "2024-03-18_21-01-37_Alice-Dave_vs____Bob-Charlie.bpgn",
+        ]);
+        for file_name in file_names {
+            assert!(is_safe_file_name(&file_name));
+            let bpgn = fs::read_to_string(dir.join(file_name)).unwrap();
+            let (imported, _) = pgn::import_from_bpgn(&bpgn, Role::ServerOrStandalone).unwrap();
+            assert_eq!(imported.status(), game.status());
+            assert_eq!(imported.turn_log().len(), 1);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
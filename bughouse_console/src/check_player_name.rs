@@ -1,10 +1,10 @@
 use std::io;
 
-use crate::prod_server_helpers::validate_player_name;
+use bughouse_chess::player_name::{MAX_PLAYER_NAME_LENGTH, validate_player_name};
 
 
 pub fn run(user_name: &str) -> io::Result<()> {
-    match validate_player_name(user_name) {
+    match validate_player_name(user_name, MAX_PLAYER_NAME_LENGTH) {
         Ok(_) => {
             println!("OK");
             Ok(())
@@ -1,8 +1,8 @@
 // TODO: Rename to client_tui (or client_console).
 // TODO: Allow all commands (including "next game" and others).
 
-use std::net::{TcpStream, ToSocketAddrs};
-use std::sync::mpsc;
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex, mpsc};
 use std::time::Duration;
 use std::{fmt, io, panic, thread};
 
@@ -12,7 +12,8 @@ use crossterm::{cursor, event as term_event, execute, terminal};
 use instant::Instant;
 use itertools::Itertools;
 use scopeguard::defer;
-use tungstenite::protocol;
+use strum::IntoEnumIterator;
+use tungstenite::{WebSocket, protocol};
 use url::Url;
 
 use crate::bughouse_prelude::*;
@@ -26,13 +27,76 @@ pub struct ClientConfig {
     pub player_name: String,
 }
 
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(10);
+
 enum IncomingEvent {
+    Connected,
+    Disconnected,
     Network(BughouseServerEvent),
     Terminal(term_event::Event),
     Tick,
 }
 
-fn writeln_raw(stdout: &mut io::Stdout, v: impl fmt::Display) -> io::Result<()> {
+enum CommandOutcome {
+    Done,
+    Output(String),
+    Quit,
+}
+
+// Delay before the next reconnection attempt. Grows exponentially and resets after a successful
+// connection.
+struct ReconnectBackoff {
+    next_delay: Duration,
+}
+
+impl ReconnectBackoff {
+    fn new() -> Self { ReconnectBackoff { next_delay: RECONNECT_INITIAL_DELAY } }
+    fn reset(&mut self) { self.next_delay = RECONNECT_INITIAL_DELAY; }
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next_delay;
+        self.next_delay = (self.next_delay * 2).min(RECONNECT_MAX_DELAY);
+        delay
+    }
+}
+
+pub(crate) fn connect(server_address: &str) -> io::Result<WebSocket<TcpStream>> {
+    let server_addr = (server_address, network::PORT).to_socket_addrs()?.collect_vec();
+    let stream = TcpStream::connect(&server_addr[..])?;
+    // Improvement potential: Test if nodelay helps. Should it be set on both sides or just one?
+    //   net_stream.set_nodelay(true)?;
+    let ws_request = Url::parse(&format!("ws://{}", server_address)).unwrap();
+    let (socket, _) =
+        tungstenite::client(ws_request, stream).map_err(|err| io::Error::other(err.to_string()))?;
+    Ok(socket)
+}
+
+// Keeps the client connected to the server: (re)connects with a backoff, reports connection
+// status changes and calls `serve` for each connection. `serve` should block until the connection
+// is broken. Returns when the receiving side of `tx` is dropped.
+//
+// Networking and sleeping are abstracted away in order to make the function testable.
+fn maintain_connection<C>(
+    mut connect: impl FnMut() -> io::Result<C>, mut serve: impl FnMut(C),
+    mut sleep: impl FnMut(Duration), tx: &mpsc::Sender<IncomingEvent>,
+) {
+    let mut backoff = ReconnectBackoff::new();
+    loop {
+        if let Ok(connection) = connect() {
+            backoff.reset();
+            if tx.send(IncomingEvent::Connected).is_err() {
+                return;
+            }
+            serve(connection);
+        }
+        if tx.send(IncomingEvent::Disconnected).is_err() {
+            return;
+        }
+        sleep(backoff.next_delay());
+    }
+}
+
+pub(crate) fn writeln_raw(stdout: &mut io::Stdout, v: impl fmt::Display) -> io::Result<()> {
     let s = v.to_string();
     // Note. Not using `lines()` because it removes trailing new line.
     for line in s.split('\n') {
@@ -41,9 +105,67 @@ fn writeln_raw(stdout: &mut io::Stdout, v: impl fmt::Display) -> io::Result<()>
     Ok(())
 }
 
+fn execute_command(client_state: &mut ClientState, cmd: &str) -> Result<CommandOutcome, String> {
+    match cmd {
+        "quit" => {
+            client_state.leave_server();
+            Ok(CommandOutcome::Quit)
+        }
+        "resign" => {
+            client_state.resign();
+            Ok(CommandOutcome::Done)
+        }
+        "resign-board" => {
+            client_state.resign_board();
+            Ok(CommandOutcome::Done)
+        }
+        "fen" => Ok(CommandOutcome::Output(boards_fen(&current_game(client_state)?))),
+        "reserve" => Ok(CommandOutcome::Output(boards_reserve(&current_game(client_state)?))),
+        _ => Err(format!("Unknown command: '{}'", cmd)),
+    }
+}
+
+fn current_game(client_state: &ClientState) -> Result<BughouseGame, String> {
+    let game_state = client_state.game_state().ok_or_else(|| "No game in progress".to_owned())?;
+    Ok(game_state.alt_game.local_game().clone())
+}
+
+fn boards_fen(game: &BughouseGame) -> String {
+    BughouseBoard::iter()
+        .map(|board_idx| {
+            format!("{board_idx:?}: {}", fen::board_to_shredder_fen(game.board(board_idx)))
+        })
+        .join("\n")
+}
+
+fn boards_reserve(game: &BughouseGame) -> String {
+    BughouseBoard::iter()
+        .map(|board_idx| {
+            let board = game.board(board_idx);
+            let reserves = Force::iter()
+                .map(|force| {
+                    let pieces = board
+                        .reserve(force)
+                        .iter()
+                        .filter(|(piece_kind, _)| !piece_kind.is_neutral())
+                        .map(|(piece_kind, &count)| {
+                            String::from(piece_to_ascii(piece_kind, force.into()))
+                                .repeat(count.into())
+                        })
+                        .join("");
+                    let pieces = if pieces.is_empty() { "-".to_owned() } else { pieces };
+                    format!("{force:?} {pieces}")
+                })
+                .join(", ");
+            format!("{board_idx:?}: {reserves}")
+        })
+        .join("\n")
+}
+
 fn render(
     stdout: &mut io::Stdout, app_start_time: Instant, client_state: &ClientState,
-    keyboard_input: &str, command_error: &Option<String>,
+    keyboard_input: &str, command_output: &Option<String>, command_error: &Option<String>,
+    connection_message: Option<&str>,
 ) -> io::Result<()> {
     let now = Instant::now();
     execute!(stdout, cursor::MoveTo(0, 0))?;
@@ -82,6 +204,7 @@ fn render(
                 };
                 writeln_raw(stdout, format!("  {} {}", symbol.with(color), p.name))?;
             }
+            writeln_raw(stdout, format!("\n{}", mtch.rules.to_human_readable()))?;
         }
     } else {
         execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
@@ -104,6 +227,12 @@ fn render(
     if let Some(msg) = additional_message {
         writeln_raw(stdout, msg)?;
     }
+    if let Some(msg) = connection_message {
+        writeln_raw(stdout, msg.with(style::Color::Yellow))?;
+    }
+    if let Some(output) = command_output {
+        writeln_raw(stdout, output)?;
+    }
     if let Some(err) = command_error {
         writeln_raw(stdout, err.clone().with(style::Color::Red))?;
     }
@@ -113,18 +242,7 @@ fn render(
 pub fn run(config: ClientConfig) -> io::Result<()> {
     let match_id = config.match_id.trim().to_owned();
     let my_name = config.player_name.trim().to_owned();
-    let server_addr = (config.server_address.as_str(), network::PORT)
-        .to_socket_addrs()
-        .unwrap()
-        .collect_vec();
-    println!("Connecting to {:?}...", server_addr);
-    let stream = TcpStream::connect(&server_addr[..])?;
-    // Improvement potential: Test if nodelay helps. Should it be set on both sides or just one?
-    //   net_stream.set_nodelay(true)?;
-    let ws_request = Url::parse(&format!("ws://{}", config.server_address)).unwrap();
-    let (mut socket_in, _) = tungstenite::client(ws_request, stream).unwrap();
-    let mut socket_out = network::clone_websocket(&socket_in, protocol::Role::Client);
-    std::mem::drop(config);
+    let server_address = config.server_address;
 
     let std_panic_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
@@ -140,14 +258,29 @@ pub fn run(config: ClientConfig) -> io::Result<()> {
     let app_start_time = Instant::now();
 
     let (tx, rx) = mpsc::channel();
+    let tx_connection = tx.clone();
     let tx_net = tx.clone();
     let tx_local = tx.clone();
     let tx_tick = tx;
+    // Outgoing socket for the current connection; `None` while disconnected.
+    let socket_out: Arc<Mutex<Option<WebSocket<TcpStream>>>> = Arc::new(Mutex::new(None));
+    let socket_out_net = Arc::clone(&socket_out);
     thread::spawn(move || {
-        loop {
-            let ev = network::read_obj(&mut socket_in).unwrap();
-            tx_net.send(IncomingEvent::Network(ev)).unwrap();
-        }
+        maintain_connection(
+            || connect(&server_address),
+            |mut socket_in| {
+                *socket_out_net.lock().unwrap() =
+                    Some(network::clone_websocket(&socket_in, protocol::Role::Client));
+                while let Ok(ev) = network::read_obj(&mut socket_in) {
+                    if tx_net.send(IncomingEvent::Network(ev)).is_err() {
+                        break;
+                    }
+                }
+                *socket_out_net.lock().unwrap() = None;
+            },
+            thread::sleep,
+            &tx_connection,
+        );
     });
     thread::spawn(move || {
         loop {
@@ -165,7 +298,13 @@ pub fn run(config: ClientConfig) -> io::Result<()> {
     let (server_tx, server_rx) = mpsc::channel();
     thread::spawn(move || {
         for ev in server_rx {
-            network::write_obj(&mut socket_out, &ev).unwrap();
+            // Events sent while disconnected are dropped: the state is restored via hot reconnect.
+            if let Some(socket) = socket_out.lock().unwrap().as_mut() {
+                if network::write_obj(socket, &ev).is_err() {
+                    // Unblock the reading thread, so that it could start reconnecting.
+                    _ = socket.get_ref().shutdown(Shutdown::Both);
+                }
+            }
         }
     });
 
@@ -177,11 +316,24 @@ pub fn run(config: ClientConfig) -> io::Result<()> {
     // using `tzdata` crate, but it's unmaintained.
     let mut client_state = ClientState::new(user_agent, time_zone);
     let mut keyboard_input = String::new();
+    let mut command_output = None;
     let mut command_error = None;
+    let mut connection_message = Some("Connecting...");
     client_state.set_guest_player_name(Some(my_name.to_owned()));
-    client_state.join(match_id);
     for event in rx {
         match event {
+            IncomingEvent::Connected => {
+                connection_message = None;
+                client_state.hot_reconnect();
+                if client_state.mtch().is_none() {
+                    client_state.join(match_id.clone());
+                }
+            }
+            IncomingEvent::Disconnected => {
+                if connection_message.is_none() {
+                    connection_message = Some("Reconnecting...");
+                }
+            }
             IncomingEvent::Network(event) => {
                 client_state.process_server_event(event).unwrap();
             }
@@ -196,17 +348,17 @@ pub fn run(config: ClientConfig) -> io::Result<()> {
                         }
                         term_event::KeyCode::Enter => {
                             let mut keep_input = false;
+                            command_output = None;
                             if let Some(cmd) = keyboard_input.strip_prefix('/') {
-                                match cmd {
-                                    "quit" => {
-                                        client_state.leave_server();
-                                        return Ok(());
-                                    }
-                                    "resign" => {
-                                        client_state.resign();
+                                command_error = None;
+                                match execute_command(&mut client_state, cmd) {
+                                    Ok(CommandOutcome::Done) => {}
+                                    Ok(CommandOutcome::Output(output)) => {
+                                        command_output = Some(output);
                                     }
-                                    _ => {
-                                        command_error = Some(format!("Unknown command: '{}'", cmd));
+                                    Ok(CommandOutcome::Quit) => return Ok(()),
+                                    Err(err) => {
+                                        command_error = Some(err);
                                     }
                                 }
                             } else {
@@ -256,12 +408,109 @@ pub fn run(config: ClientConfig) -> io::Result<()> {
                 _ => {}
             }
         }
-        render(&mut stdout, app_start_time, &client_state, &keyboard_input, &command_error)?;
+        render(
+            &mut stdout,
+            app_start_time,
+            &client_state,
+            &keyboard_input,
+            &command_output,
+            &command_error,
+            connection_message,
+        )?;
     }
     panic!("Unexpected end of events stream");
 }
 
-fn restore_terminal() {
+pub(crate) fn restore_terminal() {
     _ = terminal::disable_raw_mode();
     _ = execute!(io::stdout(), terminal::LeaveAlternateScreen);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnect_backoff() {
+        let mut backoff = ReconnectBackoff::new();
+        let delays = (0..7).map(|_| backoff.next_delay().as_millis()).collect_vec();
+        assert_eq!(delays, [500, 1000, 2000, 4000, 8000, 10000, 10000]);
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), RECONNECT_INITIAL_DELAY);
+    }
+
+    #[test]
+    fn reconnect_after_failures() {
+        let (tx, rx) = mpsc::channel();
+        let sleeps = Arc::new(Mutex::new(vec![]));
+        let sleeps_clone = Arc::clone(&sleeps);
+        let handle = thread::spawn(move || {
+            let mut attempt = 0;
+            maintain_connection(
+                || {
+                    attempt += 1;
+                    if attempt == 3 {
+                        Ok(())
+                    } else {
+                        Err(io::Error::other("no connection"))
+                    }
+                },
+                |()| {},
+                |delay| sleeps_clone.lock().unwrap().push(delay.as_millis()),
+                &tx,
+            );
+        });
+        let events = rx
+            .iter()
+            .take(5)
+            .map(|ev| match ev {
+                IncomingEvent::Connected => "connected",
+                IncomingEvent::Disconnected => "disconnected",
+                _ => panic!("unexpected event"),
+            })
+            .collect_vec();
+        drop(rx);
+        handle.join().unwrap();
+        assert_eq!(events, [
+            "disconnected",
+            "disconnected",
+            "connected",
+            "disconnected",
+            "disconnected"
+        ]);
+        assert_eq!(sleeps.lock().unwrap()[..4], [500, 1000, 500, 1000]);
+    }
+
+    #[test]
+    fn command_dispatch() {
+        let mut client_state = ClientState::new("Terminal".to_owned(), "?".to_owned());
+        assert!(matches!(execute_command(&mut client_state, "resign"), Ok(CommandOutcome::Done)));
+        assert_eq!(
+            execute_command(&mut client_state, "fen").err(),
+            Some("No game in progress".to_owned())
+        );
+        assert_eq!(
+            execute_command(&mut client_state, "reserve").err(),
+            Some("No game in progress".to_owned())
+        );
+        assert_eq!(
+            execute_command(&mut client_state, "foo").err(),
+            Some("Unknown command: 'foo'".to_owned())
+        );
+        assert!(matches!(execute_command(&mut client_state, "quit"), Ok(CommandOutcome::Quit)));
+    }
+
+    #[test]
+    fn fen_and_reserve_output() {
+        let rules = Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules::bughouse_international5(),
+        };
+        let players = bughouse_chess::test_util::sample_bughouse_players();
+        let game = BughouseGame::new(rules, bughouse_chess::role::Role::Client, &players);
+        let start_fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah - 0 1";
+        assert_eq!(boards_fen(&game), format!("A: {start_fen}\nB: {start_fen}"));
+        assert_eq!(boards_reserve(&game), "A: White -, Black -\nB: White -, Black -");
+    }
+}
@@ -39,7 +39,7 @@ pub struct UnimplementedDatabase {}
 #[async_trait]
 impl DatabaseReader for UnimplementedDatabase {
     async fn finished_games(
-        &self, _: Range<OffsetDateTime>, _: bool,
+        &self, _: Range<OffsetDateTime>, _: bool, _: bool,
     ) -> Result<Vec<(RowId, GameResultRow)>, anyhow::Error> {
         Err(anyhow::Error::msg("finished_games() unimplemented"))
     }
@@ -93,7 +93,7 @@ where
     usize: sqlx::ColumnIndex<DB::Row>,
 {
     async fn finished_games(
-        &self, game_end_time_range: Range<OffsetDateTime>, only_rated: bool,
+        &self, game_end_time_range: Range<OffsetDateTime>, only_rated: bool, with_pgn: bool,
     ) -> Result<Vec<(RowId, GameResultRow)>, anyhow::Error> {
         let deserialize_competitor = |s: &String| Competitor::deserialize(s);
         let rows = sqlx::query::<DB>(
@@ -108,6 +108,7 @@ where
                 player_blue_a,
                 player_blue_b,
                 result,
+                CASE WHEN $4 THEN game_pgn ELSE '' END AS game_pgn,
                 rated
              FROM finished_games
              WHERE
@@ -119,6 +120,7 @@ where
         .bind(game_end_time_range.start)
         .bind(game_end_time_range.end)
         .bind(!only_rated)
+        .bind(with_pgn)
         .fetch_all(&self.pool)
         .await?;
         let (oks, errs): (Vec<_>, _) = rows
@@ -152,7 +154,7 @@ where
                         player_blue_a: deserialize_competitor(&row.try_get("player_blue_a")?)?,
                         player_blue_b: deserialize_competitor(&row.try_get("player_blue_b")?)?,
                         result: row.try_get("result")?,
-                        game_pgn: String::new(),
+                        game_pgn: row.try_get("game_pgn")?,
                         rated: row.try_get("rated")?,
                     },
                 ))
@@ -302,6 +304,20 @@ where
         )
         .execute(&self.pool)
         .await?;
+        sqlx::query(
+            format!(
+                "CREATE TABLE IF NOT EXISTS interrupted_games (
+                {rowid_column_definition}
+                git_version TEXT,
+                invocation_id TEXT,
+                game_start_time TIMESTAMP,
+                game_interruption_time TIMESTAMP,
+                game_pgn TEXT)",
+            )
+            .as_str(),
+        )
+        .execute(&self.pool)
+        .await?;
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS client_performance (
             git_version TEXT,
@@ -361,6 +377,33 @@ where
         .await?;
         Ok(())
     }
+    async fn add_interrupted_game(&self, row: InterruptedGameRow) -> anyhow::Result<()> {
+        sqlx::query(
+            "INSERT INTO interrupted_games (
+            git_version,
+            invocation_id,
+            game_start_time,
+            game_interruption_time,
+            game_pgn)
+            VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(row.git_version)
+        .bind(row.invocation_id)
+        .bind(row.game_start_time)
+        .bind(row.game_interruption_time)
+        .bind(row.game_pgn)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    async fn update_game_pgn(&self, rowid: RowId, game_pgn: String) -> anyhow::Result<()> {
+        sqlx::query("UPDATE finished_games SET game_pgn = $1 WHERE rowid = $2")
+            .bind(game_pgn)
+            .bind(rowid.id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
     // TODO: Save time when performance was recorded.
     async fn add_client_performance(
         &self, perf: &BughouseClientPerformance, invocation_id: &str,
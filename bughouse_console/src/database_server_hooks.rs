@@ -1,5 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use async_std::sync::Mutex;
 use async_trait::async_trait;
 use bughouse_chess::my_git_version;
 use bughouse_chess::pgn::BpgnMetadata;
@@ -13,11 +14,15 @@ use time::OffsetDateTime;
 
 use crate::bughouse_prelude::*;
 use crate::competitor::Competitor;
+use crate::game_stats::{ComputeMetaStats, GroupStats, RawStats, rating_pool};
 use crate::persistence::*;
 
 pub struct DatabaseServerHooks<DB> {
     invocation_id: String,
     db: DB,
+    // Stats for each rating pool, see `rating_pool`. Computed from the game history on first use,
+    // then updated as rated games finish.
+    rating_stats: Mutex<Option<HashMap<String, GroupStats<RawStats>>>>,
 }
 
 impl<DB: DatabaseWriter> DatabaseServerHooks<DB> {
@@ -26,6 +31,7 @@ impl<DB: DatabaseWriter> DatabaseServerHooks<DB> {
         Ok(Self {
             invocation_id: uuid::Uuid::new_v4().to_string(),
             db,
+            rating_stats: Mutex::new(None),
         })
     }
 }
@@ -48,11 +54,40 @@ impl<DB: Send + Sync + DatabaseReader + DatabaseWriter> ServerHooks for Database
             error!("Error extracting game result from:\n{:#?}", game);
             return;
         };
+        // Hold the lock while writing the game, so that the stats cannot be computed from a history
+        // that misses it.
+        let mut rating_stats = self.rating_stats.lock().await;
+        if row.rated
+            && let Some(rating_stats) = rating_stats.as_mut()
+        {
+            let pool = rating_pool(game.chess_rules());
+            if let Err(e) = rating_stats.entry(pool).or_default().update(&row, ComputeMetaStats::No)
+            {
+                error!("Error updating player stats: {}", e);
+            }
+        }
         if let Err(e) = self.db.add_finished_game(row).await {
             error!("Error persisting game result: {}", e);
         }
     }
 
+    async fn record_interrupted_game(
+        &self, game: &BughouseGame, game_start_time: UtcDateTime,
+        game_interruption_time: UtcDateTime, round: u64,
+    ) {
+        let bpgn_meta = BpgnMetadata { game_start_time, round };
+        let row = InterruptedGameRow {
+            git_version: my_git_version!().to_owned(),
+            invocation_id: self.invocation_id.to_string(),
+            game_start_time: Some(game_start_time.into()),
+            game_interruption_time: Some(game_interruption_time.into()),
+            game_pgn: pgn::export_to_bpgn(pgn::BpgnExportFormat::default(), game, bpgn_meta),
+        };
+        if let Err(e) = self.db.add_interrupted_game(row).await {
+            error!("Error persisting interrupted game: {}", e);
+        }
+    }
+
     async fn get_games_by_user(
         &self, user_name: &str,
     ) -> Result<Vec<FinishedGameDescription>, String> {
@@ -60,7 +95,7 @@ impl<DB: Send + Sync + DatabaseReader + DatabaseWriter> ServerHooks for Database
         // TODO: Optimized SQL query to fetch only games by a given player.
         let rows = self
             .db
-            .finished_games(full_time_range, false)
+            .finished_games(full_time_range, /*only_rated=*/ false, /*with_pgn=*/ false)
             .await
             .map_err(|err| format!("Error reading game history: {err:?}"))?;
         let games = rows
@@ -122,6 +157,34 @@ impl<DB: Send + Sync + DatabaseReader + DatabaseWriter> ServerHooks for Database
             .await
             .map_err(|err| format!("Error fetching game BPGN: {err:?}"))
     }
+
+    async fn get_player_rating(&self, user_name: &str, rules: &ChessRules) -> Option<u32> {
+        let mut rating_stats = self.rating_stats.lock().await;
+        if rating_stats.is_none() {
+            let full_time_range = OffsetDateTime::UNIX_EPOCH..OffsetDateTime::now_utc();
+            let games = match self
+                .db
+                .finished_games(full_time_range, /*only_rated=*/ true, /*with_pgn=*/ true)
+                .await
+            {
+                Ok(games) => games,
+                Err(e) => {
+                    error!("Error reading game history: {}", e);
+                    return None;
+                }
+            };
+            match GroupStats::<RawStats>::from_games_by_pool(games, ComputeMetaStats::No) {
+                Ok(stats) => *rating_stats = Some(stats),
+                Err(e) => {
+                    error!("Error computing player stats: {}", e);
+                    return None;
+                }
+            }
+        }
+        let stats = rating_stats.as_ref()?.get(&rating_pool(rules))?;
+        let rating = stats.per_player.get(user_name)?.rating?;
+        Some(rating.rating.round() as u32)
+    }
 }
 
 impl<DB: DatabaseWriter> DatabaseServerHooks<DB> {
@@ -0,0 +1,194 @@
+// Export of finished games in portable formats, e.g. for tournament tooling or spreadsheets.
+
+use std::borrow::Cow;
+
+use bughouse_chess::pgn;
+use itertools::Itertools;
+use serde::Serialize;
+use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
+
+use crate::persistence::{GameResultRow, RowId};
+
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+// Column order is a part of the API: only append new columns to the end.
+// Must be in sync with `ExportedGame` fields.
+const CSV_COLUMNS: [&str; 14] = [
+    "game_id",
+    "start_time",
+    "end_time",
+    "rated",
+    "red_a",
+    "red_b",
+    "blue_a",
+    "blue_b",
+    "result",
+    "time_control",
+    "variants",
+    "promotion",
+    "drop_aggression",
+    "pawn_drop_ranks",
+];
+
+// Rules fields are `None` if the rules could not be parsed from the game PGN.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedGame {
+    pub game_id: i64,
+    pub start_time: Option<String>, // RFC 3339
+    pub end_time: Option<String>,   // RFC 3339
+    pub rated: bool,
+    pub red_a: String,
+    pub red_b: String,
+    pub blue_a: String,
+    pub blue_b: String,
+    pub result: String,            // "red", "blue" or "draw"
+    pub time_control: Option<u64>, // starting time in seconds
+    pub variants: Option<String>,
+    pub promotion: Option<String>,
+    pub drop_aggression: Option<String>,
+    pub pawn_drop_ranks: Option<String>,
+}
+
+impl ExportFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!("Unknown export format: \"{s}\", expected \"csv\" or \"json\"")),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=UTF-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+}
+
+impl ExportedGame {
+    pub fn new(rowid: RowId, game: &GameResultRow) -> Self {
+        let format_time = |t: Option<OffsetDateTime>| t.and_then(|t| t.format(&Rfc3339).ok());
+        let result = match game.result.as_str() {
+            "VICTORY_RED" => "red".to_owned(),
+            "VICTORY_BLUE" => "blue".to_owned(),
+            "DRAW" => "draw".to_owned(),
+            s => s.to_lowercase(),
+        };
+        let rules = pgn::import_rules_from_bpgn(&game.game_pgn).ok();
+        let chess_rules = rules.as_ref().map(|r| &r.chess_rules);
+        let bughouse_rules = rules.as_ref().and_then(|r| r.bughouse_rules());
+        ExportedGame {
+            game_id: rowid.id,
+            start_time: format_time(game.game_start_time),
+            end_time: format_time(game.game_end_time),
+            rated: game.rated,
+            red_a: game.player_red_a.name().to_owned(),
+            red_b: game.player_red_b.name().to_owned(),
+            blue_a: game.player_blue_a.name().to_owned(),
+            blue_b: game.player_blue_b.name().to_owned(),
+            result,
+            time_control: chess_rules.map(|r| r.time_control.starting_time.as_secs()),
+            variants: chess_rules.map(|r| r.variants().iter().map(|v| v.to_pgn()).join(" ")),
+            promotion: bughouse_rules.map(|r| r.promotion.to_pgn().to_owned()),
+            drop_aggression: bughouse_rules.map(|r| r.drop_aggression.to_pgn().to_owned()),
+            pawn_drop_ranks: bughouse_rules.map(|r| r.pawn_drop_ranks.to_pgn()),
+        }
+    }
+
+    fn csv_record(&self) -> [String; CSV_COLUMNS.len()] {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        [
+            self.game_id.to_string(),
+            opt(&self.start_time),
+            opt(&self.end_time),
+            self.rated.to_string(),
+            self.red_a.clone(),
+            self.red_b.clone(),
+            self.blue_a.clone(),
+            self.blue_b.clone(),
+            self.result.clone(),
+            self.time_control.map_or(String::new(), |t| t.to_string()),
+            opt(&self.variants),
+            opt(&self.promotion),
+            opt(&self.drop_aggression),
+            opt(&self.pawn_drop_ranks),
+        ]
+    }
+}
+
+// Quotes the field according to RFC 4180 if necessary.
+fn csv_escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+fn csv_line<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut line = fields.into_iter().map(csv_escape).join(",");
+    line.push_str("\r\n");
+    line
+}
+
+pub fn export_csv(games: &[ExportedGame]) -> String {
+    let mut csv = csv_line(CSV_COLUMNS);
+    for game in games {
+        csv.push_str(&csv_line(game.csv_record().iter().map(String::as_str)));
+    }
+    csv
+}
+
+pub fn export_json(games: &[ExportedGame]) -> serde_json::Result<String> {
+    serde_json::to_string(games)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(csv_escape("alice"), "alice");
+        assert_eq!(csv_escape("alice, bob"), "\"alice, bob\"");
+        assert_eq!(csv_escape("the \"best\""), "\"the \"\"best\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn csv_export() {
+        let game = ExportedGame {
+            game_id: 7,
+            start_time: Some("2024-01-01T12:00:00Z".to_owned()),
+            end_time: None,
+            rated: true,
+            red_a: "Smith, John".to_owned(),
+            red_b: "\"Q\"".to_owned(),
+            blue_a: "c".to_owned(),
+            blue_b: "d".to_owned(),
+            result: "red".to_owned(),
+            time_control: Some(300),
+            variants: Some(String::new()),
+            promotion: None,
+            drop_aggression: None,
+            pawn_drop_ranks: None,
+        };
+        let csv = export_csv(&[game]);
+        let lines = csv.split("\r\n").collect_vec();
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            "7,2024-01-01T12:00:00Z,,true,\"Smith, John\",\"\"\"Q\"\"\",c,d,red,300,,,,"
+        );
+        assert_eq!(lines[2], "");
+    }
+}
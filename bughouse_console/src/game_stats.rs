@@ -1,14 +1,25 @@
 // TODO: process stats incrementally and persist in the DB.
 
-use std::collections::HashMap;
-
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bughouse_chess::clock::{GameInstant, TimeMeasurement};
+use bughouse_chess::pgn;
+use bughouse_chess::role::Role;
+use bughouse_chess::rules::ChessRules;
+use enum_map::enum_map;
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use log::error;
 use prometheus::{Histogram, register_histogram};
+use serde::Serialize;
 use skillratings::Outcomes;
 use skillratings::elo::{self, EloConfig, EloRating, elo};
 use skillratings::weng_lin::{self, WengLinConfig, WengLinRating, weng_lin, weng_lin_two_teams};
 use time::OffsetDateTime;
+use time::format_description::well_known::Rfc3339;
 
 use crate::persistence::{GameResultRow, RowId};
 
@@ -368,6 +379,104 @@ where
         timer.observe_duration();
         Ok(all_stats)
     }
+
+    // Computes stats separately for each rating pool, see `rating_pool`. Games that don't have
+    // parsable rules are skipped, so games must be loaded with PGNs.
+    pub fn from_games_by_pool(
+        games: impl IntoIterator<Item = (RowId, GameResultRow)>,
+        compute_meta_stats: ComputeMetaStats,
+    ) -> anyhow::Result<HashMap<String, Self>>
+    where
+        Stats: Default,
+    {
+        games
+            .into_iter()
+            .filter_map(|(rowid, game)| match game_rating_pool(&game) {
+                Ok(pool) => Some((pool, (rowid, game))),
+                Err(err) => {
+                    error!("Ignoring game {} with unparsable rules: {}", rowid.id, err);
+                    None
+                }
+            })
+            .into_group_map()
+            .into_iter()
+            .map(|(pool, games)| Ok((pool, Self::from_games(games, compute_meta_stats)?)))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RatingPoint {
+    pub time: Option<String>, // RFC 3339
+    pub rating: f64,
+    pub uncertainty: f64,
+}
+
+pub type RatingHistory = HashMap<String, Vec<RatingPoint>>;
+
+// Returns rating after each game for every player.
+pub fn rating_history(stats: &GroupStats<Vec<RawStats>>) -> RatingHistory {
+    stats
+        .per_player
+        .iter()
+        .map(|(player, points)| {
+            let points = points
+                .iter()
+                .filter_map(|p| {
+                    let rating = p.rating?;
+                    Some(RatingPoint {
+                        time: p.last_update.and_then(|t| t.format(&Rfc3339).ok()),
+                        rating: rating.rating,
+                        uncertainty: rating.uncertainty,
+                    })
+                })
+                .collect();
+            (player.clone(), points)
+        })
+        .collect()
+}
+
+// Rating history for all players, recomputed only when new games are finished.
+#[derive(Default)]
+pub struct RatingHistoryCache {
+    // Keyed by the end time of the latest game.
+    cached: Mutex<Option<(Option<OffsetDateTime>, Arc<RatingHistory>)>>,
+}
+
+impl RatingHistoryCache {
+    pub fn new() -> Self { Self::default() }
+
+    // `games` must be all rated games in chronological order.
+    pub fn get(&self, games: Vec<(RowId, GameResultRow)>) -> anyhow::Result<Arc<RatingHistory>> {
+        let latest_game_time = games.last().and_then(|(_, game)| game.game_end_time);
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((time, history)) = cached.as_ref() {
+            if *time == latest_game_time {
+                return Ok(Arc::clone(history));
+            }
+        }
+        let stats = GroupStats::<Vec<RawStats>>::from_games(games, ComputeMetaStats::No)?;
+        let history = Arc::new(rating_history(&stats));
+        *cached = Some((latest_game_time, Arc::clone(&history)));
+        Ok(history)
+    }
+}
+
+// Returns rating pool identifier. Games with different variants or time controls are rated
+// separately. The identifier is URL-safe, e.g. "bughouse-duckchess-300".
+pub fn rating_pool(rules: &ChessRules) -> String {
+    let mut parts = vec!["bughouse".to_owned()];
+    parts.extend(rules.variants().into_iter().map(|v| v.to_pgn().to_lowercase()));
+    parts.push(rules.time_control.starting_time.as_secs().to_string());
+    if !rules.time_control.increment.is_zero() {
+        parts.push(format!("inc{}", rules.time_control.increment.as_secs()));
+    }
+    parts.join("-")
+}
+
+fn game_rating_pool(game: &GameResultRow) -> Result<String, String> {
+    let rules = pgn::import_rules_from_bpgn(&game.game_pgn)?;
+    Ok(rating_pool(&rules.chess_rules))
 }
 
 fn predictor_loss_function(expected1: f64, expected2: f64, actual1: f64, actual2: f64) -> f64 {
@@ -391,3 +500,259 @@ where
 pub fn map_arr_ref<T, V, F: Fn(&T) -> V>(input: &[T; 2], f: F) -> [V; 2] {
     [f(&input[0]), f(&input[1])]
 }
+
+
+// Move time statistics for one player.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MoveTimes {
+    pub num_moves: usize,
+    // The values below are `None` if the player made no moves or the BPGN has no timestamps.
+    pub average: Option<Duration>,
+    pub median: Option<Duration>,
+    pub longest: Option<Duration>,
+}
+
+impl MoveTimes {
+    fn new(think_times: Vec<Option<Duration>>) -> Self {
+        let num_moves = think_times.len();
+        let think_times: Option<Vec<_>> = think_times.into_iter().collect();
+        let Some(mut think_times) = think_times.filter(|t| !t.is_empty()) else {
+            return MoveTimes {
+                num_moves,
+                average: None,
+                median: None,
+                longest: None,
+            };
+        };
+        think_times.sort();
+        let mid = num_moves / 2;
+        let median = if num_moves % 2 == 1 {
+            think_times[mid]
+        } else {
+            (think_times[mid - 1] + think_times[mid]) / 2
+        };
+        MoveTimes {
+            num_moves,
+            average: Some(think_times.iter().sum::<Duration>() / num_moves as u32),
+            median: Some(median),
+            longest: think_times.last().copied(),
+        }
+    }
+}
+
+// Computes move times for each player. A move time is the time since the previous turn on the same
+// board (or since the game start). The result is sorted by player name.
+pub fn move_times_from_bpgn(bpgn: &str) -> Result<Vec<(String, MoveTimes)>, String> {
+    let (game, _) = pgn::import_from_bpgn(bpgn, Role::ServerOrStandalone)?;
+    let mut think_times: BTreeMap<String, Vec<Option<Duration>>> =
+        game.players().into_iter().map(|p| (p.name, vec![])).collect();
+    let mut last_turn_time = enum_map! { _ => GameInstant::game_start() };
+    for turn in game.turn_log() {
+        let board_idx = turn.envoy.board_idx;
+        let player_name = game.board(board_idx).player_name(turn.envoy.force);
+        // Unknown if either timestamp is missing.
+        let think_time = turn
+            .time
+            .duration_since(last_turn_time[board_idx], TimeMeasurement::Approximate)
+            .as_millis()
+            .into_inner()
+            .map(Duration::from_millis);
+        think_times.entry(player_name.to_owned()).or_default().push(think_time);
+        last_turn_time[board_idx] = turn.time;
+    }
+    Ok(think_times
+        .into_iter()
+        .map(|(name, times)| (name, MoveTimes::new(times)))
+        .collect())
+}
+
+pub fn move_times_table(move_times: &[(String, MoveTimes)]) -> String {
+    let format_duration = |d: Option<Duration>| match d {
+        Some(d) => format!("{:.1}s", d.as_secs_f64()),
+        None => "n/a".to_owned(),
+    };
+    let name_width = move_times.iter().map(|(name, _)| name.len()).chain([6]).max().unwrap();
+    let mut table = format!(
+        "{:<name_width$}  {:>5}  {:>7}  {:>7}  {:>7}\n",
+        "Player", "Moves", "Average", "Median", "Longest"
+    );
+    for (name, t) in move_times {
+        table += &format!(
+            "{:<name_width$}  {:>5}  {:>7}  {:>7}  {:>7}\n",
+            name,
+            t.num_moves,
+            format_duration(t.average),
+            format_duration(t.median),
+            format_duration(t.longest),
+        );
+    }
+    table
+}
+
+// Reads a BPGN from stdin and prints move times for each player.
+pub fn run_move_times() -> io::Result<()> {
+    let mut bpgn = String::new();
+    io::stdin().read_to_string(&mut bpgn)?;
+    let move_times = match move_times_from_bpgn(&bpgn) {
+        Ok(move_times) => move_times,
+        Err(err) => {
+            eprintln!("Error reading BPGN: {}", err);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+    };
+    print!("{}", move_times_table(&move_times));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bughouse_chess::board::{TurnInput, TurnMode};
+    use bughouse_chess::envoy;
+    use bughouse_chess::game::{BughouseGame, single_player};
+    use bughouse_chess::pgn::{BpgnExportFormat, BpgnMetadata, BpgnTimeFormat};
+    use bughouse_chess::rules::{MatchRules, Rules};
+    use bughouse_chess::utc_time::UtcDateTime;
+
+    use super::*;
+    use crate::competitor::Competitor;
+
+    fn game(
+        variant: &str, time_control: u64, winners: [&str; 2], losers: [&str; 2],
+    ) -> GameResultRow {
+        let user = |name: &str| Competitor::User(name.to_owned());
+        GameResultRow {
+            git_version: String::new(),
+            invocation_id: String::new(),
+            game_start_time: None,
+            game_end_time: None,
+            player_red_a: user(winners[0]),
+            player_red_b: user(winners[1]),
+            player_blue_a: user(losers[0]),
+            player_blue_b: user(losers[1]),
+            result: "VICTORY_RED".to_owned(),
+            game_pgn: format!(
+                "[Event \"Rated Bughouse Match\"]\n\
+                 [TimeControl \"{time_control}\"]\n\
+                 [Variant \"{variant}\"]\n"
+            ),
+            rated: true,
+        }
+    }
+
+    #[test]
+    fn separate_rating_pools() {
+        let games = [
+            game("Bughouse", 300, ["a", "b"], ["c", "d"]),
+            game("Bughouse", 300, ["a", "b"], ["c", "d"]),
+            game("Bughouse DuckChess", 300, ["c", "d"], ["a", "b"]),
+            game("Bughouse", 120, ["a", "c"], ["b", "d"]),
+        ];
+        let games = games.into_iter().enumerate().map(|(id, g)| (RowId { id: id as i64 }, g));
+        let pools =
+            GroupStats::<RawStats>::from_games_by_pool(games, ComputeMetaStats::No).unwrap();
+        assert_eq!(pools.keys().sorted().collect_vec(), [
+            "bughouse-120",
+            "bughouse-300",
+            "bughouse-duckchess-300"
+        ]);
+
+        let classic = &pools["bughouse-300"];
+        assert_eq!(classic.per_player["a"].wins, 2);
+        assert_eq!(classic.per_player["a"].losses, 0);
+        assert_eq!(classic.per_player["c"].losses, 2);
+
+        let duck = &pools["bughouse-duckchess-300"];
+        assert_eq!(duck.per_player["a"].wins, 0);
+        assert_eq!(duck.per_player["a"].losses, 1);
+        assert!(duck.per_player["c"].rating.unwrap().rating > 1500.0);
+        assert!(duck.per_player["a"].rating.unwrap().rating < 1500.0);
+
+        let blitz = &pools["bughouse-120"];
+        assert_eq!(blitz.per_player.len(), 4);
+        assert_eq!(blitz.per_player["b"].losses, 1);
+    }
+
+    #[test]
+    fn rating_history_per_game() {
+        let games = [
+            game("Bughouse", 300, ["a", "b"], ["c", "d"]),
+            game("Bughouse", 300, ["a", "c"], ["b", "d"]),
+            game("Bughouse", 300, ["b", "c"], ["d", "e"]),
+        ];
+        let games = games
+            .into_iter()
+            .enumerate()
+            .map(|(id, g)| (RowId { id: id as i64 }, g))
+            .collect_vec();
+        let history = RatingHistoryCache::new().get(games).unwrap();
+        assert_eq!(history["a"].len(), 2);
+        assert_eq!(history["b"].len(), 3);
+        assert_eq!(history["e"].len(), 1);
+        assert!(history["a"][1].rating > history["a"][0].rating);
+        assert!(history["e"][0].rating < 1500.0);
+    }
+
+    fn timestamped_game_bpgn(time_format: BpgnTimeFormat) -> String {
+        let rules = Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules::bughouse_international5(),
+        };
+        let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &[
+            single_player("alice", envoy!(White A)),
+            single_player("bob", envoy!(Black B)),
+            single_player("charlie", envoy!(Black A)),
+            single_player("dave", envoy!(White B)),
+        ]);
+        let turns = [
+            (envoy!(White A), "e4", 1),
+            (envoy!(White B), "d4", 3),
+            (envoy!(Black A), "e5", 4),
+            (envoy!(White A), "Nf3", 6),
+            (envoy!(Black A), "Nc6", 7),
+            (envoy!(White A), "Bc4", 13),
+        ];
+        for (envoy, algebraic, secs) in turns {
+            let input = TurnInput::Algebraic(algebraic.to_owned());
+            let time = GameInstant::from_duration(Duration::from_secs(secs));
+            game.try_turn_by_envoy(envoy, &input, TurnMode::InOrder, time).unwrap();
+        }
+        let meta = BpgnMetadata {
+            game_start_time: UtcDateTime::now(),
+            round: 1,
+        };
+        pgn::export_to_bpgn(BpgnExportFormat { time_format }, &game, meta)
+    }
+
+    #[test]
+    fn move_times() {
+        let bpgn = timestamped_game_bpgn(BpgnTimeFormat::Timestamp);
+        let move_times: HashMap<_, _> = move_times_from_bpgn(&bpgn).unwrap().into_iter().collect();
+        let secs = |s: u64| Some(Duration::from_secs(s));
+        assert_eq!(move_times["alice"], MoveTimes {
+            num_moves: 3,
+            average: secs(3),
+            median: secs(2),
+            longest: secs(6),
+        });
+        assert_eq!(move_times["charlie"], MoveTimes {
+            num_moves: 2,
+            average: secs(2),
+            median: secs(2),
+            longest: secs(3),
+        });
+        assert_eq!(move_times["dave"].average, secs(3));
+        assert_eq!(move_times["bob"].num_moves, 0);
+        assert_eq!(move_times["bob"].average, None);
+    }
+
+    #[test]
+    fn move_times_without_timestamps() {
+        let bpgn = timestamped_game_bpgn(BpgnTimeFormat::NoTime);
+        let move_times = move_times_from_bpgn(&bpgn).unwrap();
+        let (name, alice) = &move_times[0];
+        assert_eq!(name, "alice");
+        assert_eq!(alice.num_moves, 3);
+        assert_eq!(alice.average, None);
+        assert!(move_times_table(&move_times).contains("n/a"));
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::Arc;
 
 use async_std::sync::Mutex;
+use bughouse_chess::player_name::{PlayerNameError, validate_player_name};
 use bughouse_chess::server;
 use bughouse_chess::session_store::{SessionId, SessionStore};
 use http_types::StatusCode;
 use url::Url;
 
+use crate::game_stats::RatingHistoryCache;
 use crate::secret_persistence::SecretDatabaseRW;
 
 pub struct HttpServerStateImpl<DB> {
@@ -18,6 +20,8 @@ pub struct HttpServerStateImpl<DB> {
     pub static_content_url_prefix: String,
     pub session_store: Arc<Mutex<SessionStore>>,
     pub server_info: Arc<Mutex<server::ServerInfo>>,
+    pub rating_history_cache: RatingHistoryCache,
+    pub max_player_name_length: usize,
 }
 
 pub type HttpServerState<DB> = Arc<HttpServerStateImpl<DB>>;
@@ -31,9 +35,15 @@ where
     fn db(&self) -> &Self::DB { &self.db }
 
     fn static_content_url_prefix(&self) -> &str { &self.static_content_url_prefix }
+
+    fn rating_history_cache(&self) -> &RatingHistoryCache { &self.rating_history_cache }
 }
 
 impl<DB> HttpServerStateImpl<DB> {
+    pub fn validate_player_name(&self, name: &str) -> Result<(), PlayerNameError> {
+        validate_player_name(name, self.max_player_name_length)
+    }
+
     pub fn upgrade_auth_callback(&self, callback: &mut Url) -> tide::Result<()> {
         if self.auth_callback_is_https {
             callback.set_scheme("https").map_err(|()| {
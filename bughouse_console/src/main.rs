@@ -27,20 +27,23 @@ extern crate bughouse_chess;
 pub mod network;
 pub mod tui;
 
+mod archive_viewer;
 mod auth;
 mod auth_handlers_tide;
+mod bpgn_archive_server_hooks;
 mod bughouse_prelude;
-mod censor;
 mod check_player_name;
 mod client_main;
 mod client_performance_stats;
 mod competitor;
 mod database;
 mod database_server_hooks;
+mod game_export;
 mod game_stats;
 mod history_graphs;
 mod http_server_state;
 mod load_test;
+mod migrate_bpgn;
 mod persistence;
 mod process_bpgn;
 mod prod_server_helpers;
@@ -80,6 +83,12 @@ fn main() -> io::Result<()> {
                 .arg(arg!(<match_id> "Match ID"))
                 .arg(arg!(<player_name> "Player name")),
         )
+        .subcommand(
+            Command::new("archive")
+                .about("Replay a game from the server archive")
+                .arg(arg!(<server_address> "Server address"))
+                .arg(arg!(<game_id> "Archive game ID").value_parser(clap::value_parser!(i64))),
+        )
         .subcommand(
             Command::new("load-test")
                 .about("Load test a given server")
@@ -111,6 +120,18 @@ fn main() -> io::Result<()> {
                 )
                 .arg(arg!(--"remove-timestamps" "Removes turn timestamps and GameDuration tag.")),
         )
+        .subcommand(
+            Command::new("bpgn-move-times")
+                .about("Reads a BPGN from stdin and prints move time statistics for each player."),
+        )
+        .subcommand(
+            Command::new("bpgn-migrate")
+                .about(
+                    "Upgrades all BPGNs in the game database to the current format in place. \
+                    Reports BPGNs that could not be upgraded.",
+                )
+                .arg(arg!(<config_file> "Path to the server configuration file with the database")),
+        )
         .subcommand(
             Command::new("check-name")
                 .about("Verifies whether a player name is valid (but not necessarily free).")
@@ -130,6 +151,12 @@ fn main() -> io::Result<()> {
             match_id: sub_matches.get_one::<String>("match_id").unwrap().clone(),
             player_name: sub_matches.get_one::<String>("player_name").unwrap().clone(),
         }),
+        Some(("archive", sub_matches)) => {
+            archive_viewer::run(archive_viewer::ArchiveViewerConfig {
+                server_address: sub_matches.get_one::<String>("server_address").unwrap().clone(),
+                game_id: *sub_matches.get_one::<i64>("game_id").unwrap(),
+            })
+        }
         Some(("stress-test", sub_matches)) => stress_test::run(stress_test::StressTestConfig {
             target: sub_matches.get_one::<String>("target").unwrap().clone(),
         }),
@@ -145,6 +172,11 @@ fn main() -> io::Result<()> {
             },
             remove_timestamps: sub_matches.get_flag("remove-timestamps"),
         }),
+        Some(("bpgn-move-times", _)) => game_stats::run_move_times(),
+        Some(("bpgn-migrate", sub_matches)) => migrate_bpgn::run(migrate_bpgn::MigrateBpgnConfig {
+            database_options: read_config_file(sub_matches.get_one("config_file").unwrap())
+                .database_options,
+        }),
         Some(("check-name", sub_matches)) => {
             check_player_name::run(&sub_matches.get_one::<String>("player_name").unwrap().clone())
         }
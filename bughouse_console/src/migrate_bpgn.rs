@@ -0,0 +1,221 @@
+// Upgrades BPGNs stored in the database to the current format.
+//
+// Each game is imported and exported again, which normalizes the header and the turn notation. If
+// the import fails, we try to fill in missing tags that can be derived from other tags and retry.
+// Migrated BPGNs are written back in place. Games that cannot be migrated are left untouched and
+// reported. Migration is idempotent, so it can be safely re-run.
+
+use std::io;
+
+use bughouse_chess::pgn::{self, BpgnExportFormat};
+use bughouse_chess::role::Role;
+use time::OffsetDateTime;
+
+use crate::database::SqlxDatabase;
+use crate::persistence::{DatabaseReader, DatabaseWriter};
+use crate::server_config::DatabaseOptions;
+
+
+pub struct MigrateBpgnConfig {
+    pub database_options: DatabaseOptions,
+}
+
+pub fn run(config: MigrateBpgnConfig) -> io::Result<()> {
+    async_std::task::block_on(migrate_database_from_options(config.database_options))
+        .map_err(io::Error::other)
+}
+
+async fn migrate_database_from_options(options: DatabaseOptions) -> anyhow::Result<()> {
+    match options {
+        DatabaseOptions::NoDatabase => {
+            Err(anyhow::Error::msg("BPGN migration requires a database"))
+        }
+        DatabaseOptions::Sqlite(address) => {
+            migrate_database(&SqlxDatabase::<sqlx::Sqlite>::new(&address).await?).await
+        }
+        DatabaseOptions::Postgres(address) => {
+            migrate_database(&SqlxDatabase::<sqlx::Postgres>::new(&address).await?).await
+        }
+    }
+}
+
+async fn migrate_database(db: &(impl DatabaseReader + DatabaseWriter)) -> anyhow::Result<()> {
+    let games = db
+        .finished_games(OffsetDateTime::UNIX_EPOCH..OffsetDateTime::now_utc(), false, true)
+        .await?;
+    let mut num_migrated = 0;
+    let mut num_unchanged = 0;
+    let mut unrecoverable = vec![];
+    for (rowid, row) in games {
+        match migrate_bpgn(&row.game_pgn) {
+            Ok(bpgn_out) if bpgn_out == row.game_pgn => num_unchanged += 1,
+            Ok(bpgn_out) => {
+                db.update_game_pgn(rowid, bpgn_out).await?;
+                num_migrated += 1;
+            }
+            Err(err) => unrecoverable.push((rowid, err)),
+        }
+    }
+    println!("Migrated {num_migrated} BPGNs, {num_unchanged} already up to date.");
+    if !unrecoverable.is_empty() {
+        println!("Unrecoverable BPGNs ({}):", unrecoverable.len());
+        for (rowid, err) in unrecoverable {
+            println!("  game {}: {}", rowid.id, err);
+        }
+    }
+    Ok(())
+}
+
+pub fn migrate_bpgn(bpgn: &str) -> Result<String, String> {
+    let (game, meta) = match pgn::import_from_bpgn(bpgn, Role::ServerOrStandalone) {
+        Ok(game) => game,
+        Err(err) => {
+            let Some(fixed_bpgn) = fill_derivable_tags(bpgn) else {
+                return Err(err);
+            };
+            pgn::import_from_bpgn(&fixed_bpgn, Role::ServerOrStandalone)?
+        }
+    };
+    Ok(pgn::export_to_bpgn(BpgnExportFormat::default(), &game, meta))
+}
+
+// Adds tags that are missing in older BPGNs but can be restored from the rest of the header.
+// Returns `None` if there was nothing to add.
+fn fill_derivable_tags(bpgn: &str) -> Option<String> {
+    let lines: Vec<_> = bpgn.lines().collect();
+    let header_len = lines
+        .iter()
+        .position(|line| {
+            let line = line.trim();
+            !line.is_empty() && !line.starts_with('[')
+        })
+        .unwrap_or(lines.len());
+    let tags: Vec<_> = lines[..header_len].iter().filter_map(|line| parse_tag(line)).collect();
+    let has_tag = |name: &str| tags.iter().any(|(n, _)| *n == name);
+    let tag_value = |name: &str| tags.iter().find(|(n, _)| *n == name).map(|(_, v)| *v);
+
+    let mut new_tags = vec![];
+    if !has_tag("UTCDate") {
+        if let Some(date) = tag_value("Date") {
+            new_tags.push(("UTCDate", date));
+        }
+    }
+    if !has_tag("UTCTime") {
+        if let Some(time) = tag_value("Time") {
+            new_tags.push(("UTCTime", time));
+        }
+    }
+    if !has_tag("Variant") {
+        // Before the "Variant" tag was introduced, FEN was only written for Fischer random. The
+        // starting position would be lost on export if we recovered the game as classic chess.
+        let variant = if has_tag("FEN") {
+            "Chess960 Bughouse"
+        } else {
+            "Bughouse"
+        };
+        new_tags.push(("Variant", variant));
+    }
+    if !has_tag("SetUp") && has_tag("FEN") {
+        new_tags.push(("SetUp", "1"));
+    }
+    if new_tags.is_empty() {
+        return None;
+    }
+
+    let mut out = String::new();
+    for line in &lines[..header_len] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for (name, value) in new_tags {
+        out.push_str(&format!("[{} \"{}\"]\n", name, value));
+    }
+    for line in &lines[header_len..] {
+        out.push_str(line);
+        out.push('\n');
+    }
+    Some(out)
+}
+
+fn parse_tag(line: &str) -> Option<(&str, &str)> {
+    let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name, value))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT_BPGN: &str = r#"[Event "Unrated Bughouse Match"]
+[Site "bughouse.pro"]
+[UTCDate "2024.03.18"]
+[UTCTime "21:01:37"]
+[Round "2"]
+[WhiteA "Alice"]
+[BlackA "Bob"]
+[WhiteB "Charlie"]
+[BlackB "Dave"]
+[TimeControl "300"]
+[Variant "Bughouse"]
+[Result "*"]
+1A. e4 {[ts=1.000]} 1a. e5 {[ts=2.000]} 1B. d4 {[ts=3.000]}
+"#;
+
+    const OLD_BPGN: &str = r#"[Event "Unrated Bughouse Match"]
+[Site "bughouse.pro"]
+[Date "2023.03.01"]
+[Time "19:33:14"]
+[Round "1"]
+[WhiteA "Alice"]
+[BlackA "Bob"]
+[WhiteB "Charlie"]
+[BlackB "Dave"]
+[TimeControl "300"]
+[FEN "nrbbnqkr/pppppppp/8/8/8/8/PPPPPPPP/NRBBNQKR w BHbh - 0 1 | nrbbnqkr/pppppppp/8/8/8/8/PPPPPPPP/NRBBNQKR w BHbh - 0 1"]
+[Result "*"]
+1A. e4 1B. e4 1b. e5 1a. b6
+"#;
+
+    const BROKEN_BPGN: &str = r#"[Event "Unrated Bughouse Match"]
+[Site "bughouse.pro"]
+[UTCDate "2024.03.18"]
+[UTCTime "21:01:37"]
+[Round "1"]
+[WhiteA "Alice"]
+[BlackA "Bob"]
+[WhiteB "Charlie"]
+[BlackB "Dave"]
+[TimeControl "300"]
+[Variant "Bughouse"]
+[Result "*"]
+1A. e5
+"#;
+
+    #[test]
+    fn migrate_current_bpgn() {
+        let migrated = migrate_bpgn(CURRENT_BPGN).unwrap();
+        assert!(migrated.contains("[Round \"2\"]"));
+        assert!(migrated.contains("1a. e5 {[ts=2.000]}"));
+        assert_eq!(migrate_bpgn(&migrated).unwrap(), migrated);
+    }
+
+    #[test]
+    fn migrate_old_bpgn() {
+        assert!(pgn::import_from_bpgn(OLD_BPGN, Role::ServerOrStandalone).is_err());
+        let migrated = migrate_bpgn(OLD_BPGN).unwrap();
+        assert!(migrated.contains("[UTCDate \"2023.03.01\"]"));
+        assert!(migrated.contains("[UTCTime \"19:33:14\"]"));
+        assert!(migrated.contains("[SetUp \"1\"]"));
+        assert!(migrated.contains("nrbbnqkr/pppppppp/8/8/8/8/PPPPPPPP/NRBBNQKR"));
+        assert!(migrated.contains("1a. b6"));
+        assert_eq!(migrate_bpgn(&migrated).unwrap(), migrated);
+    }
+
+    #[test]
+    fn migrate_broken_bpgn() {
+        assert!(migrate_bpgn(BROKEN_BPGN).is_err());
+    }
+}
@@ -3,6 +3,7 @@
 
 use std::io;
 use std::net::TcpStream;
+use std::time::Duration;
 
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
@@ -15,6 +16,7 @@ pub const PORT: u16 = 14361;
 #[derive(Debug)]
 pub enum CommunicationError {
     ConnectionClosed,
+    Timeout,
     Socket(tungstenite::Error),
     Serde(serde_json::Error),
     BughouseProtocol(String),
@@ -66,6 +68,20 @@ where
     }
 }
 
+// Like `read_obj_async`, but gives up if nothing arrives within `timeout`. Used to detect half-open
+// connections: healthy clients send pings regularly.
+pub async fn read_obj_async_with_timeout<T, S>(
+    socket: &mut S, timeout: Duration,
+) -> Result<T, CommunicationError>
+where
+    T: de::DeserializeOwned,
+    S: StreamExt<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    async_std::future::timeout(timeout, read_obj_async(socket))
+        .await
+        .map_err(|_| CommunicationError::Timeout)?
+}
+
 // TODO: Instead of cloning the socket, consider calling TcpStream.set_nonblocking on the
 //   underlying stream and doing read/writes in the same thread.
 pub fn clone_websocket(socket: &WebSocket<TcpStream>, role: Role) -> WebSocket<TcpStream> {
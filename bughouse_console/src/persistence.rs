@@ -22,6 +22,17 @@ pub struct GameResultRow {
     pub rated: bool,
 }
 
+// A game that was still in progress when the server was forcefully shut down. Kept separately from
+// finished games, because it has no result and thus cannot affect ratings.
+#[derive(Debug)]
+pub struct InterruptedGameRow {
+    pub git_version: String,
+    pub invocation_id: String,
+    pub game_start_time: Option<OffsetDateTime>,
+    pub game_interruption_time: Option<OffsetDateTime>,
+    pub game_pgn: String,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct RowId {
     pub id: i64,
@@ -29,8 +40,9 @@ pub struct RowId {
 
 #[async_trait]
 pub trait DatabaseReader {
+    // PGNs are large, so they are only loaded when `with_pgn` is set. Otherwise `game_pgn` is empty.
     async fn finished_games(
-        &self, game_end_time_range: Range<OffsetDateTime>, only_rated: bool,
+        &self, game_end_time_range: Range<OffsetDateTime>, only_rated: bool, with_pgn: bool,
     ) -> Result<Vec<(RowId, GameResultRow)>, anyhow::Error>;
     async fn pgn(&self, rowid: RowId) -> Result<String, anyhow::Error>;
     async fn client_performance(&self) -> Result<Vec<ClientPerformanceRecord>, anyhow::Error>;
@@ -40,6 +52,8 @@ pub trait DatabaseReader {
 pub trait DatabaseWriter {
     async fn create_tables(&self) -> anyhow::Result<()>;
     async fn add_finished_game(&self, row: GameResultRow) -> anyhow::Result<()>;
+    async fn add_interrupted_game(&self, row: InterruptedGameRow) -> anyhow::Result<()>;
+    async fn update_game_pgn(&self, rowid: RowId, game_pgn: String) -> anyhow::Result<()>;
     async fn add_client_performance(
         &self, perf: &BughouseClientPerformance, invocation_id: &str,
     ) -> anyhow::Result<()>;
@@ -87,4 +87,13 @@ pub struct ServerConfig {
     pub check_git_version: bool,
     #[serde(with = "humantime_serde")]
     pub max_starting_time: Option<Duration>,
+    // Must not exceed `MAX_PLAYER_NAME_LENGTH`.
+    pub max_player_name_length: usize,
+    pub max_spectators_per_match: usize,
+    #[serde(with = "humantime_serde")]
+    pub client_heartbeat_timeout: Duration,
+    pub max_chat_messages_per_match: usize,
+    // If set, BPGN of every finished game is also saved to this directory, one file per game.
+    #[serde(default)]
+    pub bpgn_archive_dir: Option<String>,
 }
@@ -9,6 +9,7 @@ use async_std::channel;
 use async_std::sync::Mutex;
 use async_tungstenite::WebSocketStream;
 use bughouse_chess::event::BughouseServerEvent;
+use bughouse_chess::player_name::MAX_PLAYER_NAME_LENGTH;
 use bughouse_chess::server::*;
 use bughouse_chess::server_hooks::ServerHooks;
 use bughouse_chess::session::Session;
@@ -25,7 +26,9 @@ use time::OffsetDateTime;
 use tungstenite::protocol;
 
 use crate::auth_handlers_tide::*;
+use crate::bpgn_archive_server_hooks::*;
 use crate::database_server_hooks::*;
+use crate::game_stats::RatingHistoryCache;
 use crate::http_server_state::*;
 use crate::network::{self, CommunicationError};
 use crate::persistence::DatabaseReader;
@@ -60,6 +63,7 @@ async fn handle_connection<
         None
     };
 
+    let heartbeat_timeout = clients.heartbeat_timeout();
     let client_id = clients.add_client(client_tx, session_id.clone(), peer_addr.to_string()).await;
     let clients_remover1 = ClientRemover {
         client_id,
@@ -73,7 +77,7 @@ async fn handle_connection<
     // Client -> Server
     async_std::task::spawn(async move {
         loop {
-            match network::read_obj_async(&mut stream_rx).await {
+            match network::read_obj_async_with_timeout(&mut stream_rx, heartbeat_timeout).await {
                 Ok(ev) => {
                     tx.send(IncomingEvent::Network(client_id, ev)).await.unwrap();
                 }
@@ -83,6 +87,9 @@ async fn handle_connection<
                             CommunicationError::ConnectionClosed => {
                                 info!("Client {} disconnected", logging_id)
                             }
+                            CommunicationError::Timeout => {
+                                info!("Client {} disconnected due to missed heartbeats", logging_id)
+                            }
                             err => warn!(
                                 "Client {} disconnected due to read error: {:?}",
                                 logging_id, err
@@ -136,6 +143,8 @@ async fn run_tide<DB: Sync + Send + 'static + DatabaseReader>(
         static_content_url_prefix: config.static_content_url_prefix,
         session_store,
         server_info,
+        rating_history_cache: RatingHistoryCache::new(),
+        max_player_name_length: config.max_player_name_length,
     }));
 
     if let SessionOptions::WithSessions { secret, expire_in } = config.session_options {
@@ -181,6 +190,8 @@ async fn run_tide<DB: Sync + Send + 'static + DatabaseReader>(
 
     app.at("/dyn/metrics").get(handle_metrics);
     app.at("/dyn/server").get(handle_server_info);
+    app.at("/dyn/live-games").get(handle_live_games);
+    app.at("/dyn/matches").get(handle_open_matches);
 
     crate::stats_handlers_tide::Handlers::<HttpServerState<DB>>::register_handlers(&mut app);
 
@@ -274,10 +285,18 @@ pub async fn run(config: ServerConfig) {
         !sessions_required(&config) || config.session_options != SessionOptions::NoSessions,
         "Authentication is enabled while sessions are not."
     );
+    assert!(
+        config.max_player_name_length <= MAX_PLAYER_NAME_LENGTH,
+        "Maximum player name length cannot exceed {MAX_PLAYER_NAME_LENGTH}."
+    );
 
     let options = ServerOptions {
         check_git_version: config.check_git_version,
         max_starting_time: config.max_starting_time,
+        max_player_name_length: config.max_player_name_length,
+        max_spectators_per_match: config.max_spectators_per_match,
+        client_heartbeat_timeout: config.client_heartbeat_timeout,
+        max_chat_messages_per_match: config.max_chat_messages_per_match,
     };
 
     // Limited buffer for data streaming from clients into the server.
@@ -323,6 +342,14 @@ pub async fn run(config: ServerConfig) {
             Some(Arc::new(h) as Arc<dyn ServerHooks + Send + Sync>)
         }
     };
+    let hooks = match &config.bpgn_archive_dir {
+        None => hooks,
+        Some(dir) => {
+            let h = BpgnArchiveServerHooks::new(dir.into(), hooks)
+                .unwrap_or_else(|_| panic!("Cannot create BPGN archive directory {dir}"));
+            Some(Arc::new(h) as Arc<dyn ServerHooks + Send + Sync>)
+        }
+    };
 
     let secret_database = make_database(&config.secret_database_options).await.unwrap();
     let _ = secret_database.create_tables().await.map_err(|err| {
@@ -366,6 +393,9 @@ pub async fn run(config: ServerConfig) {
         });
     }
 
+    let helpers = ProdServerHelpers {
+        max_player_name_length: options.max_player_name_length,
+    };
     let session_store_copy = Arc::clone(&session_store);
     async_std::task::spawn(async move {
         let mut server_state = ServerState::new(
@@ -373,7 +403,7 @@ pub async fn run(config: ServerConfig) {
             clients_copy,
             session_store_copy,
             server_info_copy,
-            Arc::new(ProdServerHelpers {}),
+            Arc::new(helpers),
             hooks,
         );
 
@@ -480,6 +510,23 @@ async fn handle_metrics<DB>(_req: tide::Request<HttpServerState<DB>>) -> tide::R
     Ok(resp)
 }
 
+// Games in progress in public matches as JSON, e.g. for a "games in progress" widget.
+async fn handle_live_games<DB>(req: tide::Request<HttpServerState<DB>>) -> tide::Result {
+    let live_games = req.state().server_info.lock().await.live_games.clone();
+    let mut resp = tide::Response::new(StatusCode::Ok);
+    resp.set_content_type(http_types::Mime::from("application/json"));
+    resp.set_body(serde_json::to_string(&live_games)?);
+    Ok(resp)
+}
+
+async fn handle_open_matches<DB>(req: tide::Request<HttpServerState<DB>>) -> tide::Result {
+    let open_matches = req.state().server_info.lock().await.open_matches.clone();
+    let mut resp = tide::Response::new(StatusCode::Ok);
+    resp.set_content_type(http_types::Mime::from("application/json"));
+    resp.set_body(serde_json::to_string(&open_matches)?);
+    Ok(resp)
+}
+
 async fn handle_server_info<DB>(req: tide::Request<HttpServerState<DB>>) -> tide::Result {
     let info = req.state().server_info.lock().await.clone();
     let h: String = html! {
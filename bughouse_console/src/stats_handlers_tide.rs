@@ -1,12 +1,14 @@
 // TODO: streaming support + APIs.
 use itertools::Itertools;
+use serde::Deserialize;
 use tide::http::Mime;
 use tide::{Request, Response, StatusCode};
 use tide_jsx::*;
 use time::OffsetDateTime;
 
 use crate::client_performance_stats::{self, ClientPerformanceStats};
-use crate::game_stats::{ComputeMetaStats, GroupStats, RawStats};
+use crate::game_export::{self, ExportFormat, ExportedGame};
+use crate::game_stats::{ComputeMetaStats, GroupStats, RatingHistoryCache, RawStats};
 use crate::history_graphs;
 use crate::persistence::{self, DatabaseReader};
 
@@ -14,6 +16,7 @@ pub trait SuitableServerState: Sync + Send + Clone + 'static {
     type DB: Sync + Send + DatabaseReader;
     fn db(&self) -> &Self::DB;
     fn static_content_url_prefix(&self) -> &str;
+    fn rating_history_cache(&self) -> &RatingHistoryCache;
 }
 
 // Purely type-level construct to avoid making every handler function generic.
@@ -26,14 +29,20 @@ impl<ST: SuitableServerState> Handlers<ST> {
     pub fn register_handlers(app: &mut tide::Server<ST>) {
         app.at("/dyn/games").get(Self::handle_games);
         app.at("/dyn/pgn/:rowid").get(Self::hanle_pgn);
-        app.at("/dyn/stats").get(|r| Self::handle_stats(r, None));
-        app.at("/dyn/stats/:duration").get(Self::handle_stats_with_duration);
+        app.at("/dyn/export").get(Self::handle_export);
+        app.at("/dyn/stats").get(|r| Self::handle_stats(r, None, None));
+        // Accepts either a duration or a rating pool (see `game_stats::rating_pool`).
+        app.at("/dyn/stats/:duration_or_pool")
+            .get(Self::handle_stats_with_duration_or_pool);
+        app.at("/dyn/stats/:pool/:duration")
+            .get(Self::handle_stats_with_pool_and_duration);
         app.at("/dyn/history")
             .get(|req| Self::handle_history(req, history_graphs::XAxis::Date));
         app.at("/dyn/history/pergame")
             .get(|req| Self::handle_history(req, history_graphs::XAxis::UpdateIndex));
         app.at("/dyn/history/pertime")
             .get(|req| Self::handle_history(req, history_graphs::XAxis::Timestamp));
+        app.at("/dyn/rating_history/:player").get(Self::handle_rating_history);
         app.at("/dyn/meta/history").get(|req| Self::handle_meta_stats_history(req));
         app.at("/dyn/client-perf").get(|req| Self::handle_client_performance_stats(req));
 
@@ -54,6 +63,7 @@ impl<ST: SuitableServerState> Handlers<ST> {
             req.state().db(),
             OffsetDateTime::UNIX_EPOCH..OffsetDateTime::now_utc(),
             /*only_rated=*/ false,
+            /*with_pgn=*/ false,
         )
         .await
         .map_err(anyhow::Error::from)?;
@@ -129,13 +139,58 @@ impl<ST: SuitableServerState> Handlers<ST> {
         Ok(resp)
     }
 
-    async fn handle_stats_with_duration(req: Request<ST>) -> tide::Result {
-        let duration_str = req.param("duration")?;
-        let duration = humantime::parse_duration(duration_str)?;
-        Self::handle_stats(req, Some(duration.try_into()?)).await
+    // Query parameters:
+    //   - `format`: "csv" (default) or "json";
+    //   - `duration`: only export games that ended within this period, e.g. "30days".
+    async fn handle_export(req: Request<ST>) -> tide::Result {
+        let query: ExportQuery = req.query()?;
+        let format = match query.format.as_deref() {
+            None => ExportFormat::Csv,
+            Some(s) => ExportFormat::parse(s)
+                .map_err(|err| tide::Error::from_str(StatusCode::BadRequest, err))?,
+        };
+        let now = OffsetDateTime::now_utc();
+        let range_start = match query.duration {
+            None => OffsetDateTime::UNIX_EPOCH,
+            Some(d) => now.saturating_sub(humantime::parse_duration(&d)?.try_into()?),
+        };
+        let games = req
+            .state()
+            .db()
+            .finished_games(range_start..now, /*only_rated=*/ false, /*with_pgn=*/ true)
+            .await
+            .map_err(anyhow::Error::from)?;
+        let games = games.iter().map(|(rowid, game)| ExportedGame::new(*rowid, game)).collect_vec();
+        let body = match format {
+            ExportFormat::Csv => game_export::export_csv(&games),
+            ExportFormat::Json => game_export::export_json(&games)?,
+        };
+        let mut resp = Response::new(StatusCode::Ok);
+        resp.set_content_type(Mime::from(format.content_type()));
+        resp.set_body(body);
+        Ok(resp)
+    }
+
+    async fn handle_stats_with_duration_or_pool(req: Request<ST>) -> tide::Result {
+        let param = req.param("duration_or_pool")?;
+        match humantime::parse_duration(param) {
+            Ok(duration) => Self::handle_stats(req, Some(duration.try_into()?), None).await,
+            Err(_) => {
+                let pool = param.to_owned();
+                Self::handle_stats(req, None, Some(pool)).await
+            }
+        }
     }
 
-    async fn handle_stats(req: Request<ST>, lookback: Option<time::Duration>) -> tide::Result {
+    async fn handle_stats_with_pool_and_duration(req: Request<ST>) -> tide::Result {
+        let pool = req.param("pool")?.to_owned();
+        let duration = humantime::parse_duration(req.param("duration")?)?;
+        Self::handle_stats(req, Some(duration.try_into()?), Some(pool)).await
+    }
+
+    async fn handle_stats(
+        req: Request<ST>, lookback: Option<time::Duration>, pool: Option<String>,
+    ) -> tide::Result {
         let now = OffsetDateTime::now_utc();
         let range_start = match lookback {
             None => OffsetDateTime::UNIX_EPOCH,
@@ -144,11 +199,20 @@ impl<ST: SuitableServerState> Handlers<ST> {
         let games = req
             .state()
             .db()
-            .finished_games(range_start..now, /*only_rated=*/ true)
+            .finished_games(
+                range_start..now,
+                /*only_rated=*/ true,
+                /*with_pgn=*/ pool.is_some(),
+            )
             .await
             .map_err(anyhow::Error::from)?;
 
-        let all_stats = GroupStats::from_games(games, ComputeMetaStats::No)?;
+        let all_stats = match pool {
+            None => GroupStats::from_games(games, ComputeMetaStats::No)?,
+            Some(pool) => GroupStats::from_games_by_pool(games, ComputeMetaStats::No)?
+                .remove(&pool)
+                .unwrap_or_default(),
+        };
 
         let mut final_player_stats = process_stats(all_stats.per_player.into_iter());
         let mut final_team_stats = process_stats(
@@ -291,7 +355,11 @@ impl<ST: SuitableServerState> Handlers<ST> {
         let games = req
             .state()
             .db()
-            .finished_games(OffsetDateTime::UNIX_EPOCH..now, /*only_rated=*/ true)
+            .finished_games(
+                OffsetDateTime::UNIX_EPOCH..now,
+                /*only_rated=*/ true,
+                /*with_pgn=*/ false,
+            )
             .await
             .map_err(anyhow::Error::from)?;
 
@@ -324,12 +392,43 @@ impl<ST: SuitableServerState> Handlers<ST> {
         Ok(resp)
     }
 
+    // Returns player rating after each of their rated games as JSON.
+    async fn handle_rating_history(req: Request<ST>) -> tide::Result {
+        let player = req.param("player")?.to_owned();
+        let now = OffsetDateTime::now_utc();
+        let games = req
+            .state()
+            .db()
+            .finished_games(
+                OffsetDateTime::UNIX_EPOCH..now,
+                /*only_rated=*/ true,
+                /*with_pgn=*/ false,
+            )
+            .await
+            .map_err(anyhow::Error::from)?;
+        let history = req.state().rating_history_cache().get(games)?;
+        let Some(points) = history.get(&player) else {
+            return Err(tide::Error::from_str(
+                StatusCode::NotFound,
+                format!("No rated games for player \"{player}\""),
+            ));
+        };
+        let mut resp = Response::new(StatusCode::Ok);
+        resp.set_content_type(Mime::from("application/json"));
+        resp.set_body(serde_json::to_string(points)?);
+        Ok(resp)
+    }
+
     async fn handle_meta_stats_history(req: Request<ST>) -> tide::Result {
         let now = OffsetDateTime::now_utc();
         let games = req
             .state()
             .db()
-            .finished_games(OffsetDateTime::UNIX_EPOCH..now, /*only_rated=*/ true)
+            .finished_games(
+                OffsetDateTime::UNIX_EPOCH..now,
+                /*only_rated=*/ true,
+                /*with_pgn=*/ false,
+            )
             .await
             .map_err(anyhow::Error::from)?;
 
@@ -446,6 +545,12 @@ td.centered {
 "#;
 }
 
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+    duration: Option<String>,
+}
+
 #[derive(Debug, Clone, Default)]
 struct FinalStats {
     name: String,
@@ -101,7 +101,26 @@ fn random_rules(rng: &mut rand::rngs::ThreadRng) -> Rules {
                 duck_chess: rng.r#gen::<bool>(),
                 atomic_chess: rng.r#gen::<bool>(),
                 fog_of_war: rng.r#gen::<bool>(),
-                time_control: TimeControl { starting_time: Duration::from_secs(300) },
+                fog_of_war_visibility: match rng.gen_range(0..3) {
+                    0 => FogOfWarVisibility::Movement,
+                    1 => FogOfWarVisibility::Radius(rng.gen_range(1..=3)),
+                    2 => FogOfWarVisibility::Hybrid(rng.gen_range(1..=3)),
+                    _ => unreachable!(),
+                },
+                fog_of_war_hide_reserves: rng.r#gen::<bool>(),
+                castling_through_check: rng.r#gen::<bool>(),
+                castling: match rng.gen_range(0..3) {
+                    0 => Castling::Allowed,
+                    1 => Castling::KingMoveOnly,
+                    2 => Castling::Forbidden,
+                    _ => unreachable!(),
+                },
+                time_control: TimeControl {
+                    starting_time: Duration::from_secs(300),
+                    increment: Duration::from_secs(rng.gen_range(0..=2)),
+                    no_increment_turns: rng.gen_range(0..=3),
+                },
+                piece_values: PieceValues::default(),
                 bughouse_rules: Some(BughouseRules {
                     koedem: rng.r#gen::<bool>(),
                     // Improvement potential: Test other promotion strategies.
@@ -118,6 +137,43 @@ fn random_rules(rng: &mut rand::rngs::ThreadRng) -> Rules {
                         3 => DropAggression::MateAllowed,
                         _ => unreachable!(),
                     },
+                    no_drop_turns: rng.gen_range(0..=3),
+                    no_interposing_drops: rng.r#gen::<bool>(),
+                    simultaneous_flag: match rng.gen_range(0..2) {
+                        0 => SimultaneousFlagPolicy::Draw,
+                        1 => SimultaneousFlagPolicy::MaterialAdvantage,
+                        _ => unreachable!(),
+                    },
+                    stalemate: match rng.gen_range(0..2) {
+                        0 => Stalemate::Wait,
+                        1 => Stalemate::Draw,
+                        _ => unreachable!(),
+                    },
+                    board_resignation: rng.r#gen::<bool>(),
+                    piece_cap: match rng.gen_range(0..3) {
+                        0 => None,
+                        1 => Some(PieceCap {
+                            max_pieces: rng.gen_range(16..=24),
+                            policy: PieceCapPolicy::Refuse,
+                        }),
+                        2 => Some(PieceCap {
+                            max_pieces: rng.gen_range(16..=24),
+                            policy: PieceCapPolicy::Discard,
+                        }),
+                        _ => unreachable!(),
+                    },
+                    delayed_start: match rng.gen_range(0..3) {
+                        0 => None,
+                        1 => Some(DelayedStart {
+                            team: Team::Red,
+                            delay: Duration::from_secs(rng.gen_range(1..=30)),
+                        }),
+                        2 => Some(DelayedStart {
+                            team: Team::Blue,
+                            delay: Duration::from_secs(rng.gen_range(1..=30)),
+                        }),
+                        _ => unreachable!(),
+                    },
                 }),
             },
         };
@@ -52,16 +52,15 @@ fn render_reserve(reserve: &Reserve, force: Force, board_width: usize) -> String
 }
 
 fn render_bughouse_board(
-    board: &Board, now: GameInstant, view_board: DisplayBoard, perspective: Perspective,
+    board: &Board, now: GameInstant, view_board: DisplayBoard, board_view: &BughouseBoardView,
 ) -> String {
     use self::Force::*;
-    let orientation = get_board_orientation(view_board, perspective);
     let board_width = (board.shape().num_cols as usize + 2) * 3;
     format!(
         "{}\n{}{}{}\n{}",
         render_header(board.clock(), board.player_name(Black), Black, now, view_board, board_width),
         render_reserve(board.reserve(Black), Black, board_width),
-        render_grid(board.grid(), orientation),
+        render_grid(board.grid(), board_view),
         render_reserve(board.reserve(White), White, board_width),
         render_header(board.clock(), board.player_name(White), White, now, view_board, board_width),
     )
@@ -71,12 +70,13 @@ pub fn render_bughouse_game(
     game: &BughouseGame, my_id: BughouseParticipant, now: GameInstant,
 ) -> String {
     use DisplayBoard::*;
-    let perspective = Perspective::for_participant(my_id);
-    let primary_idx = get_board_index(Primary, perspective);
-    let secondary_idx = get_board_index(Secondary, perspective);
-    let board_primary = render_bughouse_board(game.board(primary_idx), now, Primary, perspective);
-    let board_secondary =
-        render_bughouse_board(game.board(secondary_idx), now, Secondary, perspective);
+    let view = BughouseGameView::for_participant(game, my_id);
+    let render_board = |display_board| {
+        let board_view = view.board(display_board);
+        render_bughouse_board(game.board(board_view.board_idx), now, display_board, board_view)
+    };
+    let board_primary = render_board(Primary);
+    let board_secondary = render_board(Secondary);
     board_primary
         .lines()
         .zip(board_secondary.lines())
@@ -84,11 +84,13 @@ pub fn render_bughouse_game(
         .join("\n")
 }
 
-fn render_grid(grid: &Grid, orientation: BoardOrientation) -> String {
+fn render_grid(grid: &Grid, board_view: &BughouseBoardView) -> String {
     let colors = [
         Style::new().color256(233).on_color256(222),
         Style::new().color256(233).on_color256(230),
     ];
+    let fog_color = Style::new().on_color256(244);
+    let orientation = board_view.orientation;
     let board_shape = grid.shape();
     let mut ret = String::new();
     for y in (-1)..=(board_shape.num_cols as i32) {
@@ -117,13 +119,17 @@ fn render_grid(grid: &Grid, orientation: BoardOrientation) -> String {
                         orientation,
                     )
                     .unwrap();
-                    let color_idx = (coord.row.to_zero_based() + coord.col.to_zero_based()) % 2;
-                    colors[usize::try_from(color_idx).unwrap()]
-                        .apply_to(format_square(match grid[coord] {
-                            Some(piece) => piece_to_pictogram(piece.kind, piece.force),
-                            None => ' ',
-                        }))
-                        .to_string()
+                    if board_view.is_visible(coord) {
+                        let color_idx = (coord.row.to_zero_based() + coord.col.to_zero_based()) % 2;
+                        colors[usize::try_from(color_idx).unwrap()]
+                            .apply_to(format_square(match grid[coord] {
+                                Some(piece) => piece_to_pictogram(piece.kind, piece.force),
+                                None => ' ',
+                            }))
+                            .to_string()
+                    } else {
+                        fog_color.apply_to(format_square(' ')).to_string()
+                    }
                 }
             };
             ret.push_str(&square);
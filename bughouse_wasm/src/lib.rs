@@ -26,17 +26,21 @@ mod web_util;
 
 use core::panic;
 use std::cmp::{self, Ordering};
-use std::collections::HashSet;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::str::FromStr;
+use std::time::Duration;
 
 use analysis_engine::{
     ANALYSIS_BOARD_IDX, ANALYSIS_ENGINE_NAME_BLACK, ANALYSIS_ENGINE_NAME_WHITE, EngineStatus,
-    FsfAnalysisEngine,
+    FsfAnalysisEngine, TurnGlyph,
 };
 use bughouse_chess::client::*;
 use bughouse_chess::client_chat::cannot_start_game_message;
+use bughouse_chess::client_settings::{
+    CLIENT_SETTINGS, ClientSettings, DEFAULT_BOARD_SCALE, clamp_board_scale,
+};
 use bughouse_chess::lobby::*;
 use bughouse_chess::meter::*;
 use bughouse_chess::session::*;
@@ -44,7 +48,7 @@ use enum_map::{EnumMap, enum_map};
 use instant::Instant;
 use itertools::Itertools;
 use strum::{EnumIter, IntoEnumIterator};
-use time::macros::{datetime, format_description, offset};
+use time::macros::{format_description, offset};
 use time::{OffsetDateTime, UtcOffset};
 use wasm_bindgen::prelude::*;
 use web_document::{WebDocument, web_document};
@@ -61,6 +65,20 @@ const RESERVE_HEIGHT: f64 = 1.5; // total reserve area height, in squares
 const RESERVE_PADDING: f64 = 0.25; // padding between board and reserve, in squares
 const TOTAL_FOG_TILES: u64 = 3;
 const FOG_TILE_SIZE: f64 = 1.2;
+const DEFAULT_TIME_TROUBLE_THRESHOLD: Duration = Duration::from_secs(10);
+const MAX_TIME_TROUBLE_THRESHOLD_SECS: u64 = 20; // matches `TimeBreakdown::LowTime` range
+const MAX_FRAME_BUDGET_MS: u64 = 10_000;
+const MAX_SIMULATED_LATENCY_MS: u64 = 10_000;
+const RESERVE_RESTOCK_FLASH_DURATION: Duration = Duration::from_millis(800); // matches CSS animation
+const PARTICIPANT_CHANGE_HIGHLIGHT_DURATION: Duration = Duration::from_millis(1500);
+
+// Theme tokens accepted by `WebClient::set_theme_colors` and CSS custom properties they control.
+// CSS provides fallbacks for unset properties, so the default theme lives in CSS only.
+const THEME_COLOR_TOKENS: [(&str, &str); 3] = [
+    ("board_light", "--color-board-light"),
+    ("board_dark", "--color-board-dark"),
+    ("highlight", "--color-latest-turn-highlight"),
+];
 
 #[wasm_bindgen(getter_with_clone)]
 pub struct IgnorableError {
@@ -130,17 +148,43 @@ pub struct JsEventGameOver {
     pub result: String,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsEventGameReview {
+    pub text: String,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsEventMatchCompleted {
+    pub text: String,
+}
+
 #[wasm_bindgen(getter_with_clone)]
 pub struct JsEventPlaySound {
     pub audio: String,
     pub pan: f64,
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsEventReserveRestocked {
+    pub pan: f64,
+    pub pieces: String, // human-readable, e.g. "bishop and knight"
+}
+
 #[wasm_bindgen]
 pub struct JsEventArchiveGameLoaded {
     pub game_id: i64,
 }
 
+#[wasm_bindgen]
+pub struct JsEventServerShuttingDown {
+    pub reconnect_in_ms: f64,
+}
+
+#[wasm_bindgen]
+pub struct JsEventSlowFrame {
+    pub ms: f64,
+}
+
 
 #[wasm_bindgen]
 pub struct WebClient {
@@ -148,6 +192,31 @@ pub struct WebClient {
     //   is only relevant during game phase, add a generic `UserData` parameter to
     //   `MatchState::Game`. Could move `chalk_canvas` there, for example.
     state: ClientState,
+    move_input_method: MoveInputMethod,
+    coordinate_style: CoordinateStyle,
+    // Clocks of relevant players flash when below this threshold. `None` means disabled.
+    time_trouble_threshold: Option<Duration>,
+    // Whether to keep piece nodes when only board orientation changes, so that JS could animate
+    // the flip. When off, the board is always re-rendered from scratch.
+    board_flip_animation: bool,
+    board_flip_pending: bool,
+    // Whether to highlight absolutely pinned pieces and their pinners.
+    show_pins: bool,
+    // Whether to update turn highlights in place, touching only the squares that changed. When
+    // off, highlights are rebuilt from scratch on every update, which can flicker.
+    incremental_turn_highlights: bool,
+    // Whether to flash reserve pieces that arrived from the partner's board.
+    reserve_restock_flash: bool,
+    // Whether to describe turns made by other players in a live region for screen readers.
+    move_announcements: bool,
+    // Reserve pieces that have recently arrived and when that happened.
+    restocked_reserve_highlights: Option<(Instant, Vec<ReservePieceHighlight>)>,
+    // Participants that have recently joined or changed their state and when that happened.
+    participant_highlights: HashMap<String, Instant>,
+    // Whether to scroll turn logs to bottom when a turn is made.
+    turn_log_scroll: TurnLogScroll,
+    // Breakdown of `update_state` by section.
+    frame_timings: FrameTimings,
 }
 
 #[wasm_bindgen]
@@ -155,6 +224,19 @@ impl WebClient {
     pub fn new_client(user_agent: String, time_zone: String) -> JsResult<WebClient> {
         Ok(WebClient {
             state: ClientState::new(user_agent, time_zone),
+            move_input_method: MoveInputMethod::Any,
+            coordinate_style: CoordinateStyle::Edge,
+            time_trouble_threshold: Some(DEFAULT_TIME_TROUBLE_THRESHOLD),
+            board_flip_animation: false,
+            board_flip_pending: false,
+            show_pins: false,
+            incremental_turn_highlights: true,
+            reserve_restock_flash: true,
+            move_announcements: false,
+            restocked_reserve_highlights: None,
+            participant_highlights: HashMap::new(),
+            turn_log_scroll: TurnLogScroll::new(),
+            frame_timings: FrameTimings::new(),
         })
     }
 
@@ -230,6 +312,8 @@ impl WebClient {
         };
         if mtch.is_archive_game_view() {
             "archive".to_owned()
+        } else if mtch.is_study() {
+            "study".to_owned()
         } else if let Some(game_state) = &mtch.game_state {
             if game_state.alt_game.is_active() {
                 "active".to_owned()
@@ -268,6 +352,10 @@ impl WebClient {
                 ParticipantsError::RatedDoublePlay => {
                     "Playing on two boards is only allowed in unrated matches"
                 }
+                ParticipantsError::RatedGuestPlayer => {
+                    "Only registered users can play in rated matches"
+                }
+                ParticipantsError::GuestPlayer => "Only registered users can play in this match",
             },
         }
         .to_owned()
@@ -275,6 +363,24 @@ impl WebClient {
     pub fn lobby_countdown_seconds_left(&self) -> Option<u32> {
         self.state.first_game_countdown_left().map(|d| d.as_secs_f64().ceil() as u32)
     }
+    pub fn chat_cooldown_seconds_left(&self) -> Option<u32> {
+        self.state.chat_cooldown_left().map(|d| d.as_secs_f64().ceil() as u32)
+    }
+    pub fn lobby_scheduled_start(&self) -> Option<String> {
+        let left = self.state.scheduled_start_left()?;
+        let time_offset = UtcOffset::current_local_offset().unwrap_or(offset!(UTC));
+        let start = (OffsetDateTime::now_utc() + left).to_offset(time_offset);
+        let start = start
+            .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+            .unwrap();
+        let s = left.as_secs();
+        Some(format!(
+            "Scheduled for {start}, starting in {}:{:02}:{:02}",
+            s / 3600,
+            s / 60 % 60,
+            s % 60
+        ))
+    }
 
     pub fn init_new_match_rules_body(&self) -> JsResult<()> {
         let server_options = self.state.server_options().ok_or_else(|| rust_error!())?;
@@ -283,15 +389,57 @@ impl WebClient {
         Ok(())
     }
 
+    pub fn has_last_match_rules(&self) -> bool { self.state.clone_match_rules().is_ok() }
+
+    // Should be called after `init_new_match_rules_body`. Rating is controlled by JS.
+    pub fn prefill_rules_from_current_match(&self) -> JsResult<()> {
+        let rules = self.state.clone_match_rules().map_err(|message| IgnorableError { message })?;
+        rules_ui::new_match_apply_full_rules(&rules)
+    }
+
+    // Share codes allow to reproduce match rules exactly. Like with
+    // `prefill_rules_from_current_match`, rating is controlled by JS.
+    pub fn new_match_rules_share_code(&self) -> JsResult<String> {
+        Ok(rules_ui::new_match_rules()?.to_share_code())
+    }
+    pub fn apply_rules_share_code(&self, code: &str) -> JsResult<()> {
+        let rules = Rules::from_share_code(code).map_err(|message| IgnorableError { message })?;
+        rules_ui::new_match_apply_full_rules(&rules)
+    }
+
     pub fn set_guest_player_name(&mut self, player_name: Option<String>) -> JsResult<()> {
         // Can never be certain if JS passes an empty string or null.
         let player_name = player_name.filter(|s| !s.is_empty());
+        if let Some(player_name) = &player_name {
+            self.state
+                .validate_guest_player_name(player_name)
+                .map_err(|err| IgnorableError { message: err.to_string() })?;
+        }
         self.state.set_guest_player_name(player_name);
         Ok(())
     }
-    pub fn new_match(&mut self) -> JsResult<()> {
+    pub fn set_anonymous_guest(&mut self, anonymous: bool) {
+        self.state.set_anonymous_guest(anonymous);
+    }
+    // `scheduled_start_ms` is a Unix timestamp in milliseconds, as returned by `Date.getTime()`.
+    pub fn new_match(&mut self, scheduled_start_ms: Option<f64>) -> JsResult<()> {
         let rules = rules_ui::new_match_rules()?;
-        self.state.new_match(rules);
+        if rules.match_rules.requires_registration() && self.state.session().user_info().is_none() {
+            return Err(IgnorableError {
+                message: "Please sign in to create a match for registered users only.".to_owned(),
+            }
+            .into());
+        }
+        match scheduled_start_ms {
+            None => self.state.new_match(rules),
+            Some(ms) => {
+                let start = OffsetDateTime::from_unix_timestamp_nanos(ms as i128 * 1_000_000)
+                    .map_err(|_| IgnorableError {
+                        message: "Invalid scheduled start time.".to_owned(),
+                    })?;
+                self.state.new_scheduled_match(rules, start.into());
+            }
+        }
         Ok(())
     }
 
@@ -299,13 +447,48 @@ impl WebClient {
         self.state.join(match_id);
         Ok(())
     }
+    pub fn spectate(&mut self, match_id: String) { self.state.spectate(match_id); }
+    pub fn is_spectator(&self) -> bool { self.state.is_spectator() }
+    pub fn match_id(&self) -> Option<String> { self.state.match_id().cloned() }
+    pub fn rules_summary(&self) -> Option<String> { self.state.rules_summary() }
+    pub fn follow_player(&mut self, player_name: Option<String>) {
+        self.state.follow_player(player_name);
+    }
+    // Accepts "off" or piece letters, e.g. "NNP" for two knights and a pawn.
+    pub fn set_handicap(&mut self, player_name: String, pieces: &str) -> JsResult<()> {
+        let mut handicap = Reserve::default();
+        if pieces != "off" {
+            for ch in pieces.chars() {
+                let piece_kind = PieceKind::from_algebraic_char(ch)
+                    .ok_or_else(|| rust_error!("Invalid handicap piece: {ch:?}"))?;
+                handicap[piece_kind] = handicap[piece_kind].saturating_add(1);
+            }
+        }
+        self.state.set_handicap(player_name, handicap);
+        Ok(())
+    }
+    pub fn transfer_match_ownership(&mut self, to: String) {
+        self.state.transfer_match_ownership(to);
+    }
     pub fn resign(&mut self) { self.state.resign(); }
+    pub fn can_resign_board(&self) -> bool { self.state.can_resign_board() }
+    pub fn resign_board(&mut self) { self.state.resign_board(); }
+    pub fn request_abort(&mut self) { self.state.request_abort(); }
     pub fn is_ready(&self) -> bool { self.state.is_ready().unwrap_or(false) }
     pub fn toggle_ready(&mut self) {
         if let Some(is_ready) = self.state.is_ready() {
             self.state.set_ready(!is_ready);
         }
     }
+    pub fn request_rematch(&mut self, swap: &str) -> JsResult<()> {
+        let swap = match swap {
+            "boards" => SeatSwap::Boards,
+            "colors" => SeatSwap::Colors,
+            _ => return Err(format!(r#"Invalid seat swap: "{swap}""#).into()),
+        };
+        self.state.request_rematch(swap);
+        Ok(())
+    }
     pub fn next_faction(&mut self) { self.change_faction(|f| f + 1); }
     pub fn previous_faction(&mut self) { self.change_faction(|f| f - 1); }
     pub fn leave_match(&mut self) -> JsResult<()> {
@@ -359,18 +542,29 @@ impl WebClient {
     // clicking pieces that are (temporarily) shifted or oversized. We need `click_board` because
     // the right way to click on empty squares. Introducing an element for each empty square would
     // not work, because for example fog tiles are larger than the squares.
-    pub fn click_element(&mut self, source: &str) -> JsResult<()> {
+    //
+    // With `auto_steal`, a steal promotion is completed automatically when possible (see
+    // `AlteredGame::auto_steal_promotion`).
+    pub fn click_element(&mut self, source: &str, auto_steal: bool) -> JsResult<()> {
         let Some(alt_game) = self.state.alt_game_mut() else {
             return Ok(());
         };
         let (display_board_idx, loc) = parse_location_id(source)
             .ok_or_else(|| rust_error!("Illegal click source: {source:?}"))?;
         let board_idx = get_board_index(display_board_idx, alt_game.perspective());
-        let turn_or_error = alt_game.click(board_idx, loc);
+        let mut turn_or_error = alt_game.click(board_idx, loc);
+        if auto_steal && turn_or_error == TurnInputResult::Noop {
+            turn_or_error = alt_game.auto_steal_promotion();
+        }
+        if self.move_input_method == MoveInputMethod::DragOnly {
+            alt_game.abort_click_move();
+        }
         self.state.apply_turn_or_error(turn_or_error);
         Ok(())
     }
-    pub fn click_board(&mut self, board_id: &str, x: f64, y: f64) -> JsResult<()> {
+    pub fn click_board(
+        &mut self, board_id: &str, x: f64, y: f64, auto_steal: bool,
+    ) -> JsResult<()> {
         // Note: cannot use "data-bughouse-location" attribute: squares are not the click targets
         // when obscured by the fog tiles.
         let Some(alt_game) = self.state.alt_game_mut() else {
@@ -385,7 +579,13 @@ impl WebClient {
         let board_idx = get_board_index(display_board_idx, alt_game.perspective());
         let board_orientation = get_board_orientation(display_board_idx, alt_game.perspective());
         let coord = from_display_coord(display_coord, board_shape, board_orientation).unwrap();
-        let turn_or_error = alt_game.click(board_idx, Location::Square(coord));
+        let mut turn_or_error = alt_game.click(board_idx, Location::Square(coord));
+        if auto_steal && turn_or_error == TurnInputResult::Noop {
+            turn_or_error = alt_game.auto_steal_promotion();
+        }
+        if self.move_input_method == MoveInputMethod::DragOnly {
+            alt_game.abort_click_move();
+        }
         self.state.apply_turn_or_error(turn_or_error);
         Ok(())
     }
@@ -425,6 +625,9 @@ impl WebClient {
     }
 
     pub fn start_drag_piece(&mut self, source: &str) -> JsResult<String> {
+        if !self.drag_enabled() {
+            return Ok("abort".to_owned());
+        }
         let (display_board_idx, source) = parse_location_id(source)
             .ok_or_else(|| rust_error!("Illegal drag source: {source:?}"))?;
         let alt_game = self.state.alt_game_mut().ok_or_else(|| rust_error!())?;
@@ -454,7 +657,9 @@ impl WebClient {
         )
     }
 
-    pub fn drag_piece_drop(&mut self, board_id: &str, x: f64, y: f64) -> JsResult<()> {
+    pub fn drag_piece_drop(
+        &mut self, board_id: &str, x: f64, y: f64, auto_steal: bool,
+    ) -> JsResult<()> {
         let Some(alt_game) = self.state.alt_game_mut() else {
             return Ok(());
         };
@@ -467,7 +672,10 @@ impl WebClient {
                 get_board_orientation(display_board_idx, alt_game.perspective());
             let dest_coord =
                 from_display_coord(dest_display, board_shape, board_orientation).unwrap();
-            let turn_or_error = alt_game.drag_piece_drop(board_idx, dest_coord);
+            let mut turn_or_error = alt_game.drag_piece_drop(board_idx, dest_coord);
+            if auto_steal && turn_or_error == TurnInputResult::Noop {
+                turn_or_error = alt_game.auto_steal_promotion();
+            }
             self.state.apply_turn_or_error(turn_or_error);
         } else {
             alt_game.abort_drag_piece();
@@ -475,6 +683,237 @@ impl WebClient {
         Ok(())
     }
 
+    pub fn set_move_input_method(&mut self, method: &str) -> JsResult<()> {
+        self.move_input_method = match method {
+            "any" => MoveInputMethod::Any,
+            "drag" => MoveInputMethod::DragOnly,
+            "click" => MoveInputMethod::ClickOnly,
+            _ => return Err(rust_error!("Invalid move input method: {method:?}")),
+        };
+        if self.move_input_method == MoveInputMethod::DragOnly {
+            self.abort_click_move()?;
+        }
+        Ok(())
+    }
+
+    pub fn drag_enabled(&self) -> bool { self.move_input_method != MoveInputMethod::ClickOnly }
+
+    // Accepts "off" or a whole number of seconds.
+    pub fn set_time_trouble_threshold(&mut self, threshold: &str) -> JsResult<()> {
+        self.time_trouble_threshold = match threshold {
+            "off" => None,
+            _ => match threshold.parse::<u64>() {
+                Ok(secs) if (1..=MAX_TIME_TROUBLE_THRESHOLD_SECS).contains(&secs) => {
+                    Some(Duration::from_secs(secs))
+                }
+                _ => return Err(rust_error!("Invalid time trouble threshold: {threshold:?}")),
+            },
+        };
+        Ok(())
+    }
+
+    // Accepts "off" or the maximum `update_state` duration in milliseconds.
+    pub fn set_frame_budget(&mut self, budget: &str) -> JsResult<()> {
+        let budget = match budget {
+            "off" => None,
+            _ => match budget.parse::<u64>() {
+                Ok(ms) if (1..=MAX_FRAME_BUDGET_MS).contains(&ms) => {
+                    Some(Duration::from_millis(ms))
+                }
+                _ => return Err(rust_error!("Invalid frame budget: {budget:?}")),
+            },
+        };
+        self.state.set_frame_budget(budget);
+        Ok(())
+    }
+
+    // Debug setting. Accepts "off" or the delay for outgoing events in milliseconds.
+    pub fn set_simulated_latency(&mut self, latency: &str) -> JsResult<()> {
+        let latency = match latency {
+            "off" => None,
+            _ => match latency.parse::<u64>() {
+                Ok(ms) if (1..=MAX_SIMULATED_LATENCY_MS).contains(&ms) => {
+                    Some(Duration::from_millis(ms))
+                }
+                _ => return Err(rust_error!("Invalid simulated latency: {latency:?}")),
+            },
+        };
+        self.state.set_simulated_latency(latency);
+        Ok(())
+    }
+
+    // Accepts a JSON object mapping theme tokens (see `THEME_COLOR_TOKENS`) to hex colors, e.g.
+    // `{"board_dark": "#769656"}`. Omitted tokens are reset to defaults.
+    pub fn set_theme_colors(&self, colors: &str) -> JsResult<()> {
+        let colors = parse_theme_colors(colors)?;
+        let style = web_document().body()?.style();
+        for (token, property) in THEME_COLOR_TOKENS {
+            match colors.get(token) {
+                Some(color) => style.set_property(property, color)?,
+                None => {
+                    style.remove_property(property)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Scales both boards together with their reserves. The scale is clamped to the supported range
+    // and persisted. Returns the applied value.
+    pub fn set_board_scale(&self, scale: f64) -> JsResult<f64> {
+        let scale = clamp_board_scale(scale);
+        local_storage()?.set_item("board-scale", &scale.to_string())?;
+        apply_board_scale(scale)?;
+        Ok(scale)
+    }
+
+    pub fn apply_stored_board_scale(&self) -> JsResult<()> {
+        let scale = local_storage()?
+            .get_item("board-scale")?
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_BOARD_SCALE);
+        apply_board_scale(clamp_board_scale(scale))
+    }
+
+    // Returns client-side settings from local storage as a shareable string.
+    pub fn export_settings(&self) -> JsResult<String> {
+        let storage = local_storage()?;
+        let mut settings = ClientSettings::new();
+        for &key in CLIENT_SETTINGS {
+            if let Some(value) = storage.get_item(key)? {
+                // Skip values that are no longer valid, e.g. stored by an older client.
+                let _ = settings.set(key, value);
+            }
+        }
+        Ok(settings.export())
+    }
+
+    // Validates a string produced by `export_settings` and writes the settings to local storage.
+    // Settings absent from the string are left intact. The caller must re-apply stored settings.
+    pub fn import_settings(&self, s: &str) -> JsResult<()> {
+        let settings = ClientSettings::import(s)
+            .map_err(|message| JsValue::from(IgnorableError { message }))?;
+        if let Some(colors) = settings.get("theme-colors") {
+            parse_theme_colors(colors)?;
+        }
+        let storage = local_storage()?;
+        for (key, value) in settings.iter() {
+            storage.set_item(key, value)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_coordinate_style(&mut self, style: &str) -> JsResult<()> {
+        self.coordinate_style = match style {
+            "off" => CoordinateStyle::Off,
+            "edge" => CoordinateStyle::Edge,
+            "all" => CoordinateStyle::AllSquares,
+            _ => return Err(rust_error!("Invalid coordinate style: {style:?}")),
+        };
+        let GameState { alt_game, .. } = self.state.displayed_game_state();
+        for board_idx in DisplayBoard::iter() {
+            // The grid does not exist until the page is initialized.
+            if let Some(layer) = web_document().get_element_by_id(&square_grid_layer_id(board_idx))
+            {
+                layer.remove_all_children();
+                render_grid(
+                    board_idx,
+                    alt_game.board_shape(),
+                    alt_game.perspective(),
+                    self.coordinate_style,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    // Drops have no source square, so they are easy to miss in fast play. The marker style adds an
+    // outline to drop highlights on both boards.
+    pub fn set_drop_highlight_style(&mut self, style: &str) -> JsResult<()> {
+        let marker = match style {
+            "fill" => false,
+            "marker" => true,
+            _ => return Err(rust_error!("Invalid drop highlight style: {style:?}")),
+        };
+        web_document()
+            .body()?
+            .class_list()
+            .toggle_with_force("drop-highlight-marker", marker)?;
+        Ok(())
+    }
+
+    // Whether observers can see my rating next to my name.
+    pub fn set_show_rating(&mut self, show_rating: bool) {
+        self.state.set_show_rating(show_rating);
+    }
+
+    pub fn set_board_flip_animation(&mut self, enabled: bool) {
+        self.board_flip_animation = enabled;
+    }
+
+    pub fn set_incremental_turn_highlights(&mut self, enabled: bool) {
+        self.incremental_turn_highlights = enabled;
+    }
+
+    // Returns the new value.
+    pub fn toggle_pins(&mut self) -> bool {
+        self.show_pins = !self.show_pins;
+        self.show_pins
+    }
+
+    pub fn toggle_scratch_mode(&mut self) {
+        match self.state.toggle_scratch_mode() {
+            Ok(true) => self.state.show_command_result(
+                "Scratch mode: explore variations for both sides. Your moves are not sent. Type \
+                 /scratch again to return to the game."
+                    .to_owned(),
+            ),
+            Ok(false) => self.state.show_command_result("Back to the game.".to_owned()),
+            Err(err) => self.state.show_command_error(err),
+        }
+    }
+
+    pub fn set_reserve_restock_flash(&mut self, enabled: bool) {
+        self.reserve_restock_flash = enabled;
+        if !enabled {
+            self.restocked_reserve_highlights = None;
+        }
+    }
+
+    pub fn set_move_announcements(&mut self, enabled: bool) -> JsResult<()> {
+        self.move_announcements = enabled;
+        if !enabled {
+            announce_move("")?;
+        }
+        Ok(())
+    }
+
+    // When enabled, a turn log scrolled up by the user stays in place until they scroll back to
+    // the bottom. When disabled, turn logs always jump to the latest turn.
+    pub fn set_turn_log_freeze(&mut self, enabled: bool) {
+        self.turn_log_scroll.set_freeze_when_scrolled_up(enabled);
+    }
+
+    // Called from JS when a turn log is scrolled. Checking whether the log is at bottom requires
+    // a layout, so JS does it in the scroll handler rather than during `update_state`.
+    pub fn on_log_scroll(&mut self, board_id: &str, at_bottom: bool) -> JsResult<()> {
+        let display_board = parse_board_id(board_id)?;
+        self.turn_log_scroll.on_scroll(display_board, at_bottom);
+        Ok(())
+    }
+
+    // Returns whether a board has been flipped in place since the last call. Boards being flipped
+    // have "board-flipping" class: JS should remove it when the animation is over.
+    pub fn take_board_flip(&mut self) -> bool { std::mem::take(&mut self.board_flip_pending) }
+
+    // Cancels a half-made click-click move, if any. Does not affect drags and promotion choices.
+    pub fn abort_click_move(&mut self) -> JsResult<()> {
+        if let Some(alt_game) = self.state.alt_game_mut() {
+            alt_game.abort_click_move();
+        }
+        Ok(())
+    }
+
     pub fn abort_drag_piece(&mut self) -> JsResult<()> {
         if let Some(alt_game) = self.state.alt_game_mut() {
             if alt_game.piece_drag_state() != PieceDragState::NoDrag {
@@ -655,13 +1094,28 @@ impl WebClient {
                 .to_owned();
                 Ok(JsEventGameOver { result }.into())
             }
+            Some(NotableEvent::GameReviewReady(review)) => {
+                let Some(GameState { alt_game, .. }) = self.state.game_state() else {
+                    return Err(rust_error!());
+                };
+                let text = game_review_text(&review, alt_game.game_confirmed());
+                Ok(JsEventGameReview { text }.into())
+            }
             Some(NotableEvent::TurnMade(envoy)) => {
                 let Some(GameState { alt_game, .. }) = self.state.game_state() else {
                     return Err(rust_error!());
                 };
                 let display_board_idx =
                     get_display_board_index(envoy.board_idx, alt_game.perspective());
-                scroll_log_to_bottom(display_board_idx)?;
+                if self.turn_log_scroll.follow_new_turns(display_board_idx) {
+                    scroll_log_to_bottom(display_board_idx)?;
+                }
+                if self.move_announcements
+                    && !alt_game.my_id().plays_for(envoy)
+                    && let Some(text) = self.state.last_move_announcement(envoy.board_idx)
+                {
+                    announce_move(&text)?;
+                }
                 if alt_game.my_id().plays_on_board(envoy.board_idx)
                     || alt_game.my_id().is_observer()
                 {
@@ -673,11 +1127,16 @@ impl WebClient {
                 }
                 Ok(JsEventNoop {}.into())
             }
-            Some(NotableEvent::MyReserveRestocked(board_idx)) => Ok(JsEventPlaySound {
-                audio: "reserve_restocked".to_owned(),
-                pan: self.get_game_audio_pan(board_idx)?,
+            Some(NotableEvent::MyReserveRestocked(board_idx, pieces)) => {
+                if self.reserve_restock_flash {
+                    self.flash_restocked_reserve(board_idx, &pieces)?;
+                }
+                Ok(JsEventReserveRestocked {
+                    pan: self.get_game_audio_pan(board_idx)?,
+                    pieces: restocked_pieces_text(&pieces),
+                }
+                .into())
             }
-            .into()),
             Some(NotableEvent::PieceStolen) => Ok(JsEventPlaySound {
                 audio: "piece_stolen".to_owned(),
                 pan: 0.,
@@ -689,7 +1148,13 @@ impl WebClient {
             }
             .into()),
             Some(NotableEvent::WaybackStateUpdated(wayback)) => {
+                let left_wayback = self.turn_log_scroll.on_wayback(wayback.active());
                 scroll_to_wayback_turn(wayback);
+                if left_wayback {
+                    for display_board_idx in DisplayBoard::iter() {
+                        scroll_log_to_bottom(display_board_idx)?;
+                    }
+                }
                 Ok(JsEventNoop {}.into())
             }
             Some(NotableEvent::GotArchiveGameList(games)) => {
@@ -705,6 +1170,47 @@ impl WebClient {
                 highlight_archive_game_row(game_id)?;
                 Ok(JsEventArchiveGameLoaded { game_id }.into())
             }
+            Some(NotableEvent::FactionChangeApplied(faction)) => {
+                let message = if faction.is_player() {
+                    "You are playing starting from this game."
+                } else {
+                    "You are observing starting from this game."
+                };
+                self.state.show_command_result(message.to_owned());
+                Ok(JsEventNoop {}.into())
+            }
+            Some(NotableEvent::ServerShuttingDown(reconnect_in)) => {
+                self.state.show_command_result(format!(
+                    "The server is restarting. Reconnecting in {} seconds...",
+                    reconnect_in.as_secs()
+                ));
+                Ok(JsEventServerShuttingDown {
+                    reconnect_in_ms: reconnect_in.as_millis() as f64,
+                }
+                .into())
+            }
+            Some(NotableEvent::ParticipantChanged(change)) => {
+                self.participant_highlights
+                    .retain(|_, since| since.elapsed() < PARTICIPANT_CHANGE_HIGHLIGHT_DURATION);
+                match change {
+                    ParticipantChange::Joined(name)
+                    | ParticipantChange::FactionChanged(name, _)
+                    | ParticipantChange::ReadinessChanged(name, _) => {
+                        self.participant_highlights.insert(name, Instant::now());
+                    }
+                    ParticipantChange::Left(name) => {
+                        self.participant_highlights.remove(&name);
+                    }
+                }
+                Ok(JsEventNoop {}.into())
+            }
+            Some(NotableEvent::MatchCompleted(result)) => {
+                let text = match_result_text(&result);
+                Ok(JsEventMatchCompleted { text }.into())
+            }
+            Some(NotableEvent::SlowFrame(duration)) => {
+                Ok(JsEventSlowFrame { ms: duration.as_secs_f64() * 1000.0 }.into())
+            }
             None => Ok(JsValue::NULL),
         }
     }
@@ -717,7 +1223,7 @@ impl WebClient {
 
     pub fn refresh(&mut self) { self.state.refresh(); }
 
-    pub fn init_page(&self) -> JsResult<()> {
+    pub fn init_page(&mut self) -> JsResult<()> {
         generate_svg_markers()?;
         render_archive_game_list(None, None)?;
         web_chat::render_chat_reference_dialog()?;
@@ -725,10 +1231,15 @@ impl WebClient {
         Ok(())
     }
 
-    fn init_game_view(&self, need_reset_chat: bool) -> JsResult<()> {
+    fn init_game_view(&mut self, need_reset_chat: bool) -> JsResult<()> {
         let GameState { alt_game, .. } = self.state.displayed_game_state();
         let my_id = alt_game.my_id();
-        render_boards(alt_game.board_shape(), alt_game.perspective())?;
+        let flipped = render_boards(
+            alt_game.board_shape(),
+            alt_game.perspective(),
+            self.coordinate_style,
+            self.board_flip_animation,
+        )?;
         setup_participation_mode(my_id)?;
         if need_reset_chat {
             reset_chat()?;
@@ -736,22 +1247,34 @@ impl WebClient {
         // TODO: Actualize chat tooltip for game archive.
         // Improvement potential. Add an <hr> style separator between games in chat.
         web_chat::render_chat_reference_tooltip(my_id, self.state.team_chat_enabled())?;
+        self.turn_log_scroll.reset();
+        self.state.expect_heavy_frame();
         for display_board_idx in DisplayBoard::iter() {
             scroll_log_to_bottom(display_board_idx)?;
         }
+        self.board_flip_pending |= flipped;
         Ok(())
     }
 
-    pub fn update_state(&self) -> JsResult<()> {
+    pub fn update_state(&mut self) -> JsResult<()> {
+        let start = Instant::now();
+        let result = self.update_state_impl();
+        self.state.record_update_state_duration(start.elapsed());
+        result
+    }
+
+    fn update_state_impl(&self) -> JsResult<()> {
         let document = web_document();
         let GameState { is_demo, alt_game, .. } = self.state.displayed_game_state();
         let game = alt_game.local_game();
         let hash_seed;
         let mtch = self.state.mtch();
+        let lobby_start = Instant::now();
         if let Some(mtch) = mtch {
             hash_seed = match &mtch.origin {
                 MatchOrigin::ActiveMatch(match_id) => match_id.clone(),
                 MatchOrigin::ArchiveGame(game_id) => game_id.to_string(),
+                MatchOrigin::Study => String::new(),
             };
             update_lobby(mtch)?;
         } else {
@@ -761,23 +1284,28 @@ impl WebClient {
             let mtch = mtch.unwrap();
             let show_readiness = !game.status().is_active() && mtch.is_active_match();
             update_participants_and_scores(&mtch.scores, &mtch.participants, show_readiness)?;
+            self.highlight_changed_participants()?;
             update_cannot_start_alert(mtch)?;
         } else {
             update_participants_and_scores(&None, &[], false)?;
             set_cannot_start_alert(None)?;
         }
+        self.frame_timings.record("lobby", lobby_start.elapsed());
         let board_shape = alt_game.board_shape();
         let my_id = alt_game.my_id();
         let perspective = alt_game.perspective();
         let wayback = alt_game.wayback_state();
+        let boards_start = Instant::now();
         for (board_idx, board) in game.boards() {
             let my_force = my_id.envoy_for(board_idx).map(|e| e.force);
             let is_my_duck_turn = alt_game.is_my_duck_turn(board_idx);
             let is_piece_draggable = |piece_force: PieceForce| {
                 !is_demo
-                    && my_id
-                        .envoy_for(board_idx)
-                        .is_some_and(|e| piece_force.is_owned_by_or_neutral(e.force))
+                    && (alt_game.is_study_mode()
+                        || alt_game.is_scratch_mode()
+                        || my_id
+                            .envoy_for(board_idx)
+                            .is_some_and(|e| piece_force.is_owned_by_or_neutral(e.force)))
             };
             let is_glowing_steal = |coord: Coord| {
                 let Some((input_board_idx, partial_input)) = alt_game.partial_turn_input() else {
@@ -849,17 +1377,15 @@ impl WebClient {
                 {
                     let node_id = square_id(display_board_idx, coord);
                     let node = document.ensure_svg_node("use", &node_id, &piece_layer, |node| {
-                        const SIZE: f64 = 1.0;
-                        let shift = (SIZE - 1.0) / 2.0;
-                        let pos = DisplayFCoord::square_pivot(display_coord);
-                        node.set_attribute("x", &(pos.x - shift).to_string())?;
-                        node.set_attribute("y", &(pos.y - shift).to_string())?;
+                        set_square_node_position(node, display_coord)?;
                         node.set_attribute("data-bughouse-location", &node_id)?;
                         Ok(())
                     })?;
                     if !fog_cover_area.contains(&coord)
                         && let Some(piece) = grid[coord]
                     {
+                        // Note. No broken king for `VictoryReason::KingCaptured`: the king is
+                        // already gone from the board.
                         let filename = if let ChessGameStatus::Victory(winner, reason) =
                             board.status()
                             && reason == VictoryReason::Checkmate
@@ -908,10 +1434,13 @@ impl WebClient {
                         DisplayBoard::Primary => IconPosition::Right,
                         DisplayBoard::Secondary => IconPosition::Left,
                     };
+                    // Ratings are for observers: players have better things to focus on.
+                    let show_rating = !my_id.is_player();
                     let name_content = participant_node(
                         player,
                         ParticipantItemLocation::Board,
                         show_readiness,
+                        show_rating,
                         p_icon_position,
                     )?;
                     p_node.replace_children_with_node_1(&name_content);
@@ -921,8 +1450,14 @@ impl WebClient {
                 let is_draggable = is_piece_draggable(force.into());
                 use ReservePresentation::*;
                 let reserve_presentation = if *is_demo { Demo } else { Normal };
+                let hidden_reserve = Reserve::default();
+                let reserve = if alt_game.is_reserve_hidden(board_idx, force) {
+                    &hidden_reserve
+                } else {
+                    board.reserve(force)
+                };
                 update_reserve(
-                    board.reserve(force),
+                    reserve,
                     force,
                     display_board_idx,
                     player_idx,
@@ -930,20 +1465,61 @@ impl WebClient {
                     reserve_presentation,
                     game.chess_rules(),
                 )?;
+                document
+                    .get_existing_element_by_id(&reserve_node_id(display_board_idx, player_idx))?
+                    .class_list()
+                    .toggle_with_force("reserve-locked", board.is_no_drop_phase())?;
             }
             board_node.class_list().toggle_with_force("duck-turn", is_my_duck_turn)?;
             board_node.class_list().toggle_with_force("wayback", wayback.active())?;
-            update_turn_log(&game, my_id, board_idx, display_board_idx, &wayback)?;
+            let is_focused = self.state.focused_board() == Some(display_board_idx);
+            board_node.class_list().toggle_with_force("focused", is_focused)?;
+            for player_idx in DisplayPlayer::iter() {
+                document
+                    .get_existing_element_by_id(&clock_container_node_id(
+                        display_board_idx,
+                        player_idx,
+                    ))?
+                    .class_list()
+                    .toggle_with_force("clock-container-focused", is_focused)?;
+            }
         }
-        self.update_clock()?;
-        self.update_turn_highlights()?;
+        self.frame_timings.record("boards", boards_start.elapsed());
+        self.frame_timings.measure("turn_log", || {
+            for board_idx in BughouseBoard::iter() {
+                let display_board_idx = get_display_board_index(board_idx, perspective);
+                update_turn_log(
+                    &game,
+                    self.state.displayed_game_state(),
+                    my_id,
+                    board_idx,
+                    display_board_idx,
+                    &wayback,
+                )?;
+            }
+            JsResult::Ok(())
+        })?;
+        self.frame_timings.measure("clock", || self.update_clock())?;
+        // Separate sections allow to compare the two modes via `/frame-timings`.
+        let turn_highlights_section = if self.incremental_turn_highlights {
+            "turn_highlights"
+        } else {
+            "turn_highlights_full"
+        };
+        self.frame_timings
+            .measure(turn_highlights_section, || self.update_turn_highlights())?;
         document
             .body()?
             .class_list()
-            .toggle_with_force("active-player", is_clock_ticking(&game, my_id))?;
-        self.repaint_chalk()?;
-        self.update_chat()?;
-        self.update_evaluation_bars()?;
+            .toggle_with_force("active-player", !game.ticking_envoys(my_id).is_empty())?;
+        document
+            .body()?
+            .class_list()
+            .toggle_with_force("scratch-mode", alt_game.is_scratch_mode())?;
+        self.frame_timings.measure("chalk", || self.repaint_chalk())?;
+        self.frame_timings.measure("chat", || self.update_chat())?;
+        self.frame_timings
+            .measure("evaluation_bars", || self.update_evaluation_bars())?;
         Ok(())
     }
 
@@ -968,12 +1544,16 @@ impl WebClient {
                     BughouseParticipant::Observer(_) => true,
                 };
                 let diff = show_diff.then(|| clock.difference_for(force, other_clock, game_now));
-                render_clock(
-                    clock.showing_for(force, game_now),
-                    diff,
-                    display_board_idx,
-                    player_idx,
-                )?;
+                let showing = clock.showing_for(force, game_now);
+                let alert_relevant = match alt_game.my_id() {
+                    BughouseParticipant::Player(player) => {
+                        player.plays_for(BughouseEnvoy { board_idx, force })
+                    }
+                    BughouseParticipant::Observer(_) => true,
+                };
+                let time_trouble = alert_relevant
+                    && self.time_trouble_threshold.is_some_and(|t| showing.in_time_trouble(t));
+                render_clock(showing, diff, time_trouble, display_board_idx, player_idx)?;
             }
         }
         Ok(())
@@ -988,6 +1568,12 @@ impl WebClient {
             .join("\n")
     }
 
+    // Returns JSON mapping `update_state` sections to their timings: the duration in the last frame
+    // and the distribution over all frames, in microseconds.
+    pub fn frame_timings(&self) -> String {
+        serde_json::to_string(&self.frame_timings.report()).unwrap()
+    }
+
     pub fn shared_wayback_enabled(&self) -> bool { self.state.shared_wayback_enabled() }
     pub fn toggle_shared_wayback(&mut self) {
         self.state.set_shared_wayback(!self.shared_wayback_enabled());
@@ -999,23 +1585,50 @@ impl WebClient {
         Ok(())
     }
 
+    // Accepts "primary", "secondary" or "none".
+    pub fn focus_board(&mut self, board_id: &str) -> JsResult<()> {
+        let display_board = match board_id {
+            "none" => None,
+            _ => Some(parse_board_id(board_id)?),
+        };
+        self.state.focus_board(display_board);
+        Ok(())
+    }
+    // Called when a clock is tapped: on touch devices this is the quickest way to choose the
+    // board for keyboard input.
+    pub fn set_active_board(&mut self, board_id: &str) -> JsResult<()> {
+        self.state.focus_board(Some(parse_board_id(board_id)?));
+        Ok(())
+    }
+    pub fn focused_board(&self) -> String {
+        self.state.focused_board().map_or("none", board_id).to_owned()
+    }
+    pub fn focus_next_board(&mut self) -> String {
+        board_id(self.state.focus_next_board()).to_owned()
+    }
+
     pub fn on_vertical_arrow_key_down(
         &mut self, key: &str, ctrl: bool, shift: bool, alt: bool,
     ) -> JsResult<()> {
         let Some(GameState { alt_game, .. }) = self.state.game_state() else {
             return Ok(());
         };
+        // Shift and Alt pick a board; holding both navigates through my own turns instead.
+        // Meta is not used, because OS shortcuts tend to take Meta+Arrow.
+        let my_turns = shift && alt;
         let display_board_idx = match (shift, alt) {
-            (false, false) => None,
+            (false, false) | (true, true) => self.state.focused_board(),
             (true, false) => Some(DisplayBoard::Primary),
-            (_, true) => Some(DisplayBoard::Secondary),
+            (false, true) => Some(DisplayBoard::Secondary),
         };
         let board_idx = display_board_idx.map(|idx| get_board_index(idx, alt_game.perspective()));
-        let destination = match (key, ctrl) {
-            ("ArrowDown", false) => WaybackDestination::Next,
-            ("ArrowDown", true) => WaybackDestination::Last,
-            ("ArrowUp", false) => WaybackDestination::Previous,
-            ("ArrowUp", true) => WaybackDestination::First,
+        let destination = match (key, ctrl, my_turns) {
+            ("ArrowDown", _, true) => WaybackDestination::MyNext,
+            ("ArrowDown", false, false) => WaybackDestination::Next,
+            ("ArrowDown", true, false) => WaybackDestination::Last,
+            ("ArrowUp", _, true) => WaybackDestination::MyPrevious,
+            ("ArrowUp", false, false) => WaybackDestination::Previous,
+            ("ArrowUp", true, false) => WaybackDestination::First,
             _ => return Ok(()),
         };
         self.state.wayback_to(destination, board_idx);
@@ -1046,6 +1659,51 @@ impl WebClient {
 
     pub fn get_game_bpgn(&mut self) -> Option<String> { self.state.get_game_bpgn() }
 
+    // Accepts "primary", "secondary" or "both".
+    pub fn move_list_text(&self, board_id: &str) -> JsResult<Option<String>> {
+        if board_id == "both" {
+            return Ok(self.state.move_list_text_both_boards());
+        }
+        let display_board = parse_board_id(board_id)?;
+        let Some(GameState { alt_game, .. }) = self.state.game_state() else {
+            return Ok(None);
+        };
+        let board_idx = get_board_index(display_board, alt_game.perspective());
+        Ok(self.state.move_list_text(board_idx))
+    }
+
+    pub fn last_move_announcement(&self, board_id: &str) -> JsResult<Option<String>> {
+        let display_board = parse_board_id(board_id)?;
+        let Some(GameState { alt_game, .. }) = self.state.game_state() else {
+            return Ok(None);
+        };
+        let board_idx = get_board_index(display_board, alt_game.perspective());
+        Ok(self.state.last_move_announcement(board_idx))
+    }
+
+    // Uses the rules of the current match if any.
+    pub fn enter_study_mode(&mut self) -> JsResult<()> {
+        let rules = self.state.mtch().map(|mtch| mtch.rules.clone()).unwrap_or_else(|| Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules::bughouse_international5(),
+        });
+        self.state.enter_study_mode(rules);
+        self.init_game_view(true)?;
+        Ok(())
+    }
+
+    pub fn set_study_reserve(
+        &mut self, board_id: &str, force: &str, piece: &str, amount: u8,
+    ) -> JsResult<()> {
+        let display_board = parse_board_id(board_id)?;
+        let force = parse_force_id(force)?;
+        let piece_kind = PieceKind::from_algebraic_ignore_case(piece)
+            .ok_or_else(|| format!(r#"Invalid piece: "{piece}""#))?;
+        self.state
+            .set_study_reserve(display_board, force, piece_kind, amount)
+            .map_err(client_error_to_js)
+    }
+
     fn change_faction(&mut self, faction_modifier: impl Fn(i32) -> i32) {
         let Some(mtch) = self.state.mtch() else {
             return;
@@ -1157,38 +1815,105 @@ impl WebClient {
     // Must be called after `update_reserve`, because the latter will recreate reserve nodes and
     // thus remove highlight classes.
     fn update_turn_highlights(&self) -> JsResult<()> {
-        // Optimization potential: do not reset highlights that stay in place.
-        web_document().purge_class_name("reserve-highlight")?;
-        clear_square_highlight_layer(SquareHighlightLayer::Turn)?;
-        clear_square_highlight_layer(SquareHighlightLayer::TurnAbove)?;
         let GameState { alt_game, .. } = self.state.displayed_game_state();
         let board_shape = alt_game.board_shape();
         let perspective = alt_game.perspective();
         let highlights = alt_game.turn_highlights();
+        let mut square_highlights = vec![];
         for h in highlights.square_highlights {
-            let class = square_highlight_class_id(&h);
             let display_board_idx = get_display_board_index(h.board_idx, perspective);
-            let orientation = get_board_orientation(display_board_idx, perspective);
-            let layer = turn_highlight_layer(h.layer);
-            let display_coord = to_display_coord(h.coord, board_shape, orientation);
-            set_square_highlight(
-                None,
-                &class,
-                layer,
+            square_highlights.push(TurnSquareHighlight {
+                class: square_highlight_class_id(&h),
+                layer: turn_highlight_layer(h.layer),
                 display_board_idx,
-                Some(display_coord),
-                board_shape,
-                orientation,
-            )?;
+                coord: h.coord,
+            });
+        }
+        if self.show_pins {
+            for (board_idx, board) in alt_game.local_game().boards() {
+                let display_board_idx = get_display_board_index(board_idx, perspective);
+                for pin in board.pinned_pieces() {
+                    for (coord, class) in [
+                        (pin.pinned, "pinned-highlight"),
+                        (pin.pinner, "pinner-highlight"),
+                    ] {
+                        square_highlights.push(TurnSquareHighlight {
+                            class: class.to_owned(),
+                            layer: SquareHighlightLayer::Turn,
+                            display_board_idx,
+                            coord,
+                        });
+                    }
+                }
+            }
         }
+        let mut reserve_highlight_ids = HashSet::new();
         for h in highlights.reserve_piece_highlights {
             let display_board_idx = get_display_board_index(h.board_idx, perspective);
-            let node = web_document().get_existing_element_by_id(&reserve_piece_id(
+            reserve_highlight_ids.insert(reserve_piece_id(
                 display_board_idx,
                 h.force,
                 h.piece_kind,
-            ))?;
-            node.class_list().add_1("reserve-highlight")?;
+            ));
+        }
+        let mut reserve_restocked_ids = HashSet::new();
+        if let Some((since, ref highlights)) = self.restocked_reserve_highlights
+            && since.elapsed() < RESERVE_RESTOCK_FLASH_DURATION
+        {
+            for h in highlights {
+                let display_board_idx = get_display_board_index(h.board_idx, perspective);
+                reserve_restocked_ids.insert(reserve_piece_id(
+                    display_board_idx,
+                    h.force,
+                    h.piece_kind,
+                ));
+            }
+        }
+
+        let document = web_document();
+        if self.incremental_turn_highlights {
+            // Only touch highlights that actually changed. Node IDs encode the layer, so a
+            // highlight moving between the below-fog and above-fog layers is recreated.
+            let ids: HashSet<_> = square_highlights.iter().map(|h| h.id()).collect();
+            for layer in [SquareHighlightLayer::Turn, SquareHighlightLayer::TurnAbove] {
+                for board_idx in DisplayBoard::iter() {
+                    let layer_node = document
+                        .get_existing_element_by_id(&square_highlight_layer_id(layer, board_idx))?;
+                    for node in layer_node.children().into_iterator().collect_vec() {
+                        if !ids.contains(&node.id()) {
+                            node.remove();
+                        }
+                    }
+                }
+            }
+            for h in &square_highlights {
+                let id = h.id();
+                if document.get_element_by_id(&id).is_none() {
+                    h.set(Some(&id), board_shape, perspective)?;
+                }
+            }
+            sync_class_name("reserve-highlight", &reserve_highlight_ids)?;
+            sync_class_name("reserve-restocked", &reserve_restocked_ids)?;
+        } else {
+            document.purge_class_name("reserve-highlight")?;
+            document.purge_class_name("reserve-restocked")?;
+            clear_square_highlight_layer(SquareHighlightLayer::Turn)?;
+            clear_square_highlight_layer(SquareHighlightLayer::TurnAbove)?;
+            for h in &square_highlights {
+                h.set(None, board_shape, perspective)?;
+            }
+            for id in &reserve_highlight_ids {
+                document
+                    .get_existing_element_by_id(id)?
+                    .class_list()
+                    .add_1("reserve-highlight")?;
+            }
+            for id in &reserve_restocked_ids {
+                // The piece could have been dropped already.
+                if let Some(node) = document.get_element_by_id(id) {
+                    node.class_list().add_1("reserve-restocked")?;
+                }
+            }
         }
         Ok(())
     }
@@ -1207,8 +1932,48 @@ impl WebClient {
     }
 
     fn update_evaluation_bars(&self) -> JsResult<()> {
-        let GameState { alt_game, evaluation_percentages, .. } = self.state.displayed_game_state();
-        update_evaluation_bars(evaluation_percentages, alt_game.perspective())?;
+        let evaluation_percentages = self.state.evaluation_percentages();
+        let perspective = self.state.displayed_game_state().alt_game.perspective();
+        update_evaluation_bars(&evaluation_percentages, perspective)?;
+        Ok(())
+    }
+
+    fn flash_restocked_reserve(
+        &mut self, board_idx: BughouseBoard, pieces: &[PieceKind],
+    ) -> JsResult<()> {
+        let Some(GameState { alt_game, .. }) = self.state.game_state() else {
+            return Err(rust_error!());
+        };
+        let Some(envoy) = alt_game.my_id().envoy_for(board_idx) else {
+            return Ok(());
+        };
+        let highlights = pieces
+            .iter()
+            .unique()
+            .map(|&piece_kind| ReservePieceHighlight {
+                board_idx,
+                force: envoy.force,
+                piece_kind,
+            })
+            .collect();
+        self.restocked_reserve_highlights = Some((Instant::now(), highlights));
+        self.update_turn_highlights()
+    }
+
+    // Participant nodes are rebuilt on every update, so the highlight is a static style that is
+    // re-applied until it expires rather than a one-off animation. Only the nodes of participants
+    // that have recently changed are touched: fresh nodes come without the highlight anyway.
+    fn highlight_changed_participants(&self) -> JsResult<()> {
+        let document = web_document();
+        for (name, since) in self.participant_highlights.iter() {
+            if since.elapsed() >= PARTICIPANT_CHANGE_HIGHLIGHT_DURATION {
+                continue;
+            }
+            let selector = format!("[data-participant-name='{name}']");
+            for node in document.query_selector_all(&selector)? {
+                node.dyn_into::<web_sys::Element>()?.class_list().add_1("participant-changed")?;
+            }
+        }
         Ok(())
     }
 
@@ -1221,6 +1986,20 @@ impl WebClient {
     }
 }
 
+// Example: "bishop and knight", "2 pawns".
+fn restocked_pieces_text(pieces: &[PieceKind]) -> String {
+    pieces
+        .iter()
+        .counts()
+        .into_iter()
+        .sorted()
+        .map(|(piece_kind, count)| {
+            let name = format!("{piece_kind:?}").to_lowercase();
+            if count == 1 { name } else { format!("{count} {name}s") }
+        })
+        .join(" and ")
+}
+
 fn calculate_hash<T: Hash>(t: &T) -> u64 {
     let mut s = DefaultHasher::new();
     t.hash(&mut s);
@@ -1236,6 +2015,29 @@ fn client_error_to_js(err: ClientError) -> JsValue {
     }
 }
 
+// Allows to disable one of the move input methods in order to avoid accidental moves. Clicks are
+// still used for placing the duck and choosing promotion targets regardless.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum MoveInputMethod {
+    Any,
+    DragOnly,  // clicking a piece does not start a click-click move
+    ClickOnly, // moving the mouse while holding the button does not start a drag
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum CoordinateStyle {
+    Off,
+    Edge,       // row captions on the left edge, column captions on the bottom edge
+    AllSquares, // full square name on every square
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum GridCaption {
+    Row,
+    Col,
+    Square,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
 enum TurnRecordBoard {
     Main,
@@ -1340,6 +2142,9 @@ fn make_match_caption_body(mtch: &Match) -> JsResult<web_sys::Element> {
     if let Some(match_id) = mtch.match_id() {
         node.append_text_span(match_id, ["lobby-match-id"])?;
     }
+    node.new_child_element("div")?
+        .with_classes(["lobby-match-summary"])?
+        .set_text_content(Some(&mtch.rules.describe()));
     Ok(node)
 }
 
@@ -1370,7 +2175,8 @@ fn update_match_list(matches: &[MatchDescription]) -> JsResult<()> {
                 Auto,
                 concat!(
                     "Drop aggression:\nM — mate drop allowed;\n",
-                    "NM — mate drop forbidden;\nNC – check drop forbidden."
+                    "NM — mate drop forbidden;\nNC – check drop forbidden.\n",
+                    "✕ — blocking check by drop forbidden."
                 ),
             )?;
         tr.new_child_element("th")?
@@ -1403,12 +2209,16 @@ fn update_match_list(matches: &[MatchDescription]) -> JsResult<()> {
             Some(RulesPreset::Modern) => "Modern",
             None => "Custom",
         };
-        let drop_aggression = match bughouse_rules.drop_aggression {
+        let mut drop_aggression = match bughouse_rules.drop_aggression {
             DropAggression::NoCheck => "NC",
             DropAggression::NoChessMate => "NM",
             DropAggression::NoBughouseMate => "NB",
             DropAggression::MateAllowed => "M",
-        };
+        }
+        .to_owned();
+        if bughouse_rules.no_interposing_drops {
+            drop_aggression.push('✕');
+        }
         let promotion = match chess_rules.promotion() {
             Promotion::Discard => "D",
             Promotion::Upgrade => "Upg",
@@ -1427,7 +2237,7 @@ fn update_match_list(matches: &[MatchDescription]) -> JsResult<()> {
             .with_more_text(&chess_rules.time_control.to_string())?;
         tr.new_child_element("td")?
             .with_more_text(&bughouse_rules.pawn_drop_ranks.to_human_readable())?;
-        tr.new_child_element("td")?.with_more_text(drop_aggression)?;
+        tr.new_child_element("td")?.with_more_text(&drop_aggression)?;
         tr.new_child_element("td")?.with_more_text(promotion)?;
         {
             // TODO: Variant names tooltip.
@@ -1480,6 +2290,60 @@ fn update_lobby(mtch: &Match) -> JsResult<()> {
     Ok(())
 }
 
+struct TurnSquareHighlight {
+    class: String,
+    layer: SquareHighlightLayer,
+    display_board_idx: DisplayBoard,
+    coord: Coord,
+}
+
+impl TurnSquareHighlight {
+    fn id(&self) -> String {
+        format!(
+            "{}-{}-{}",
+            square_highlight_layer_id(self.layer, self.display_board_idx),
+            self.coord.to_id(),
+            self.class
+        )
+    }
+
+    fn set(
+        &self, id: Option<&str>, board_shape: BoardShape, perspective: Perspective,
+    ) -> JsResult<()> {
+        let orientation = get_board_orientation(self.display_board_idx, perspective);
+        let display_coord = to_display_coord(self.coord, board_shape, orientation);
+        set_square_highlight(
+            id,
+            &self.class,
+            self.layer,
+            self.display_board_idx,
+            Some(display_coord),
+            board_shape,
+            orientation,
+        )
+    }
+}
+
+// Makes sure that `class` is set exactly on elements with the given IDs. Unlike purging the class
+// and adding it back, doesn't touch elements that keep the class.
+fn sync_class_name(class: &str, ids: &HashSet<String>) -> JsResult<()> {
+    let document = web_document();
+    for node in document.get_elements_by_class_name(class).collect_vec() {
+        if !ids.contains(&node.id()) {
+            node.class_list().remove_1(class)?;
+        }
+    }
+    for id in ids {
+        // The piece could have been dropped already.
+        if let Some(node) = document.get_element_by_id(id)
+            && !node.class_list().contains(class)
+        {
+            node.class_list().add_1(class)?;
+        }
+    }
+    Ok(())
+}
+
 // Note. If present, `id` must be unique across both boards.
 fn set_square_highlight(
     id: Option<&str>, class: &str, layer: SquareHighlightLayer, board_idx: DisplayBoard,
@@ -1589,8 +2453,9 @@ fn participant_status_icon(
     }
 }
 
+// Rating is shown only if `show_rating` is set and the server sent the rating: players may hide it.
 fn participant_node(
-    p: &Participant, location: ParticipantItemLocation, show_readiness: bool,
+    p: &Participant, location: ParticipantItemLocation, show_readiness: bool, show_rating: bool,
     icon_position: IconPosition,
 ) -> JsResult<web_sys::Element> {
     let location_class = match location {
@@ -1598,8 +2463,8 @@ fn participant_node(
         ParticipantItemLocation::Score => "score-participant-name",
     };
     let width = estimate_text_width(&p.name)?;
-    // Context. Player name limit is 20 characters. 'W' is the widest allowed character. String
-    // consisting of 'W' repeated 20 times is estimated to be 180px.
+    // Context. Player name limit is `MAX_PLAYER_NAME_LENGTH` = 20 characters. 'W' is the widest
+    // allowed character. String consisting of 'W' repeated 20 times is estimated to be 180px.
     let width_class = match width {
         140.. => "participant-name-xxxl",
         120.. => "participant-name-xxl",
@@ -1612,15 +2477,19 @@ fn participant_node(
         IconPosition::Right => "participant-status-icon-right",
     };
     let node = web_document().create_element("div")?.with_classes(["participant-item"])?;
+    node.set_attribute("data-participant-name", &p.name)?;
     if let Some(icon_node) = participant_status_icon(p, show_readiness, location)? {
         icon_node.class_list().add_2("participant-status-icon", icon_class)?;
         node.append_child(&icon_node)?;
     }
-    node.new_child_element("div")?.with_text_content(&p.name).with_classes([
+    let name_node = node.new_child_element("div")?.with_text_content(&p.name).with_classes([
         "participant-name",
         location_class,
         width_class,
     ])?;
+    if show_rating && let Some(rating) = p.rating {
+        name_node.append_text_span(&format!(" {rating}"), ["participant-rating"])?;
+    }
     Ok(node)
 }
 
@@ -1724,8 +2593,21 @@ fn add_lobby_participant_node(
         };
         let name_node = document.create_element("div")?;
         name_node.set_attribute("class", &format!("lobby-name {width_class}"))?;
+        name_node.set_attribute("data-participant-name", &p.name)?;
         add_relation_class(&name_node)?;
         name_node.set_text_content(Some(&p.name));
+        if p.handicap.values().any(|&n| n > 0) {
+            let mut handicap = "+".to_owned();
+            for (piece_kind, &amount) in p.handicap.iter() {
+                for _ in 0..amount {
+                    handicap.push(piece_to_pictogram(piece_kind, PieceForce::White));
+                }
+            }
+            name_node
+                .new_child_element("span")?
+                .with_classes(["lobby-handicap"])?
+                .with_text_content(&handicap);
+        }
         parent.append_child(&name_node)?;
     }
     {
@@ -1937,19 +2819,101 @@ fn render_upgrade_promotion_selector(
     Ok(())
 }
 
-// Differs from `BughouseGame::envoy_is_active` in that it returns false for White before game start.
-fn is_clock_ticking(game: &BughouseGame, participant_id: BughouseParticipant) -> bool {
-    for envoy in participant_id.envoys() {
-        if game.board(envoy.board_idx).clock().active_force() == Some(envoy.force) {
-            return true;
+// All board-related sizes in CSS are derived from square size, which includes the scale. Reserve
+// and fog tile geometry inside SVG is measured in squares, so it follows the container.
+fn apply_board_scale(scale: f64) -> JsResult<()> {
+    let style = web_document().body()?.style();
+    style.set_property("--board-scale", &scale.to_string())?;
+    style.set_property("--reserve-height-in-squares", &RESERVE_HEIGHT.to_string())?;
+    Ok(())
+}
+
+fn parse_theme_colors(colors: &str) -> JsResult<HashMap<String, String>> {
+    let invalid = |message: String| JsValue::from(IgnorableError { message });
+    let colors: HashMap<String, String> = serde_json::from_str(colors)
+        .map_err(|err| invalid(format!("Invalid theme colors: {err}")))?;
+    for (token, color) in &colors {
+        if !THEME_COLOR_TOKENS.iter().any(|(t, _)| t == token) {
+            return Err(invalid(format!("Unknown theme color: {token}")));
+        }
+        if !is_valid_hex_color(color) {
+            return Err(invalid(format!("Invalid color for {token}: {color}")));
         }
     }
-    false
+    Ok(colors)
+}
+
+fn local_storage() -> JsResult<web_sys::Storage> {
+    web_sys::window()
+        .unwrap()
+        .local_storage()?
+        .ok_or_else(|| rust_error!("Local storage is not available"))
+}
+
+// Accepts "#rgb" and "#rrggbb".
+fn is_valid_hex_color(color: &str) -> bool {
+    let Some(digits) = color.strip_prefix('#') else {
+        return false;
+    };
+    matches!(digits.len(), 3 | 6) && digits.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+fn game_review_text(review: &GameReview, game: &BughouseGame) -> String {
+    let mut lines = vec![review.outcome.to_readable_string(game.chess_rules())];
+    if review.is_minimal() {
+        // Not much happened: skip the stats, they would only look silly.
+        let total_turns = review.total_turns();
+        let s = if total_turns == 1 { "" } else { "s" };
+        lines.push(format!("The game ended after {total_turns} turn{s}."));
+        return lines.join("\n");
+    }
+    if let Ok(duration) = Duration::try_from(review.duration) {
+        let duration = Duration::from_secs(duration.as_secs());
+        lines.push(format!("Duration: {}", duration_to_mss(duration)));
+    }
+    for board_idx in BughouseBoard::iter() {
+        let board = game.board(board_idx);
+        let (min_balance, max_balance) = review.material_balance_range[board_idx];
+        let material = if max_balance == 0 && min_balance == 0 {
+            "material was even".to_owned()
+        } else if max_balance >= -min_balance {
+            format!("biggest lead: {} +{}", board.player_name(Force::White), max_balance)
+        } else {
+            format!("biggest lead: {} +{}", board.player_name(Force::Black), -min_balance)
+        };
+        let num_turns = review.num_turns[board_idx];
+        lines.push(format!("Board {board_idx:?}: {num_turns} turns, {material}"));
+    }
+    if let Some(moment) = review.time_pressure {
+        let name = game.board(moment.envoy.board_idx).player_name(moment.envoy.force);
+        let seconds = moment.time_left.as_millis() as f64 / 1000.0;
+        lines.push(format!("Closest call: {name} moved with {seconds:.1}s left"));
+    }
+    lines.join("\n")
+}
+
+fn match_result_text(result: &MatchResult) -> String {
+    let contestant_name = |contestant: &Contestant| match contestant {
+        Contestant::Team(Team::Red) => "Red team".to_owned(),
+        Contestant::Team(Team::Blue) => "Blue team".to_owned(),
+        Contestant::Player(name) => name.clone(),
+    };
+    let winners = result.winners.iter().map(contestant_name).join(", ");
+    let mut lines = vec![if result.winners.len() > 1 {
+        format!("The match is over. Shared victory: {winners}.")
+    } else {
+        format!("The match is over. Winner: {winners}.")
+    }];
+    for (place, standing) in result.standings.iter().enumerate() {
+        let name = contestant_name(&standing.contestant);
+        lines.push(format!("{}. {name}: {}", place + 1, standing.score.as_f64()));
+    }
+    lines.join("\n")
 }
 
 fn render_clock(
-    showing: ClockShowing, diff: Option<ClockDifference>, display_board_idx: DisplayBoard,
-    player_idx: DisplayPlayer,
+    showing: ClockShowing, diff: Option<ClockDifference>, time_trouble: bool,
+    display_board_idx: DisplayBoard, player_idx: DisplayPlayer,
 ) -> JsResult<()> {
     let document = web_document();
     let clock_node =
@@ -1967,6 +2931,9 @@ fn render_clock(
         if matches!(showing.time_breakdown, TimeBreakdown::LowTime { .. }) {
             classes.push("clock-low-time");
         }
+        if time_trouble {
+            classes.push("clock-time-trouble");
+        }
     }
     clock_node.set_attribute("class", &classes.join(" "))?;
 
@@ -2025,6 +2992,7 @@ fn update_participants_and_scores(
                         p,
                         ParticipantItemLocation::Score,
                         show_readiness,
+                        false,
                         IconPosition::Left,
                     )?;
                     let tr = table.new_child_element("tr")?;
@@ -2069,6 +3037,7 @@ fn update_participants_and_scores(
                     p,
                     ParticipantItemLocation::Score,
                     show_readiness,
+                    false,
                     IconPosition::Left,
                 )?;
                 let tr = table.new_child_element("tr")?;
@@ -2095,7 +3064,7 @@ fn update_participants_and_scores(
     for p in observers {
         let node = observers_node.new_child_element("div")?;
         let p_node =
-            participant_node(p, ParticipantItemLocation::Score, false, IconPosition::Left)?;
+            participant_node(p, ParticipantItemLocation::Score, false, false, IconPosition::Left)?;
         node.append_child(&p_node)?;
     }
 
@@ -2105,11 +3074,17 @@ fn update_participants_and_scores(
     Ok(())
 }
 
-fn render_boards(board_shape: BoardShape, perspective: Perspective) -> JsResult<()> {
+// Returns whether any board has been flipped in place.
+fn render_boards(
+    board_shape: BoardShape, perspective: Perspective, coordinate_style: CoordinateStyle,
+    flip_in_place: bool,
+) -> JsResult<bool> {
+    let mut flipped = false;
     for board_idx in DisplayBoard::iter() {
-        render_board(board_idx, board_shape, perspective)?;
+        flipped |=
+            render_board(board_idx, board_shape, perspective, coordinate_style, flip_in_place)?;
     }
-    Ok(())
+    Ok(flipped)
 }
 
 fn update_evaluation_bars(
@@ -2140,8 +3115,8 @@ fn update_evaluation_bars(
 }
 
 fn update_turn_log(
-    game: &BughouseGame, my_id: BughouseParticipant, board_idx: BughouseBoard,
-    display_board_idx: DisplayBoard, wayback: &WaybackState,
+    game: &BughouseGame, game_state: &GameState, my_id: BughouseParticipant,
+    board_idx: BughouseBoard, display_board_idx: DisplayBoard, wayback: &WaybackState,
 ) -> JsResult<()> {
     let board_shape = game.board_shape();
     let document = web_document();
@@ -2154,8 +3129,9 @@ fn update_turn_log(
     log_node.remove_all_children();
 
     let mut prev_number = 0;
-    for record in game.turn_log().iter() {
-        let index = record.index;
+    for san in game.move_list_san(AlgebraicCharset::AuxiliaryUnicode) {
+        let index = san.index;
+        let record = game.turn_record(index);
         let line_node = document.create_element("div")?;
         if Some(index) == wayback.display_turn_index() {
             if wayback.active() {
@@ -2166,23 +3142,20 @@ fn update_turn_log(
         }
         line_node.set_attribute("data-turn-index", &index.to_string())?;
 
-        if record.envoy.board_idx == board_idx {
-            let force = record.envoy.force;
+        if san.envoy.board_idx == board_idx {
+            let force = san.envoy.force;
             let mut turn_number_str = String::new();
-            if prev_number != record.local_number {
-                turn_number_str = format!("{}.", record.local_number);
-                prev_number = record.local_number;
+            if prev_number != san.number {
+                turn_number_str = format!("{}.", san.number);
+                prev_number = san.number;
             }
             let is_in_fog = game.chess_rules().fog_of_war
                 && game.is_active()
-                && my_id.as_player().is_some_and(|p| p.team() != record.envoy.team());
+                && my_id.as_player().is_some_and(|p| p.team() != san.envoy.team());
             let algebraic = if is_in_fog {
                 record.turn_expanded.algebraic.format_in_the_fog(board_shape)
             } else {
-                record
-                    .turn_expanded
-                    .algebraic
-                    .format(board_shape, AlgebraicCharset::AuxiliaryUnicode)
+                san.algebraic
             };
             let captures = record.turn_expanded.captures.clone();
             let (algebraic, captures) = match record.mode {
@@ -2191,14 +3164,16 @@ fn update_turn_log(
                 // Don't show captures for preturns: too unpredictable and messes with braces:
                 TurnMode::Preturn => (format!("({})", algebraic), vec![]),
             };
+            let glyph = game_state.turn_glyph(record);
+            let glyph_str = glyph.map_or("", |g| g.to_str());
 
             const LOG_PIECE_WIDTH: u32 = 5;
             // The "one plus" part nicely accounts for the fact that there is a separator in
             // addition to captured pieces and, on the one hand, it is smaller that a single piece,
             // but on the other hand, pieces overlap, so the very first piece takes more space than
             // each next one.
-            let width_estimate =
-                estimate_text_width(&algebraic)? + LOG_PIECE_WIDTH * (1 + captures.len() as u32);
+            let width_estimate = estimate_text_width(&format!("{algebraic}{glyph_str}"))?
+                + LOG_PIECE_WIDTH * (1 + captures.len() as u32);
             let width_class = match width_estimate {
                 55.. => "log-record-xl",
                 50.. => "log-record-l",
@@ -2214,6 +3189,13 @@ fn update_turn_log(
 
             line_node.append_text_span(&turn_number_str, ["log-turn-number"])?;
             line_node.append_text_span(&algebraic, ["log-algebraic"])?;
+            if let Some(glyph) = glyph {
+                let glyph_class = match glyph {
+                    TurnGlyph::Brilliant | TurnGlyph::Good => "log-glyph-good",
+                    TurnGlyph::Mistake | TurnGlyph::Blunder => "log-glyph-bad",
+                };
+                line_node.append_text_span(glyph_str, ["log-glyph", glyph_class])?;
+            }
 
             if !captures.is_empty() {
                 line_node.append_text_span("·", ["log-capture-separator"])?;
@@ -2259,15 +3241,15 @@ fn update_turn_log(
         }
         log_node.append_child(&line_node)?;
     }
-    // Note. The log will be scrolled to bottom whenever a turn is made on a given board (see
-    // `NotableEvent::TurnMade` handler). Another strategy would've been to keep the log scrolled
-    // to bottom if it was already there. I found two ways of doing this, but unfortunately none
-    // of them works well:
-    //   - This could done in JS, using this code to find if an element is at bottom:
+    // Note. The log will be scrolled to bottom whenever a turn is made on a given board, unless
+    // the user has scrolled it up (see `NotableEvent::TurnMade` handler and `TurnLogScroll`).
+    // Two other approaches have been tried, but unfortunately none of them works well:
+    //   - Checking whether the log is at bottom here, using
     //        e.scroll_top() >= e.scroll_height() - e.client_height() - 1
     //     (as https://developer.mozilla.org/en-US/docs/Web/API/Element/scrollHeight#determine_if_an_element_has_been_totally_scrolled suggests)
     //     But the test is very slow. It made the entire `update_state` an order of magnitued slower,
-    //     increasing update time from 1-10 ms to 10-100 ms.
+    //     increasing update time from 1-10 ms to 10-100 ms. Now the same test is done in JS scroll
+    //     handler, which is called much less often.
     //   - This could be done in CSS, via `scroll-snap-type`:
     //       https://stackoverflow.com/a/60546366/3092679
     //     but the snap range is too large (especially in Firefox), so it becomes very hard to browse
@@ -2275,6 +3257,13 @@ fn update_turn_log(
     Ok(())
 }
 
+// The element is an ARIA live region, so screen readers read out every change.
+fn announce_move(text: &str) -> JsResult<()> {
+    let node = web_document().get_existing_element_by_id("move-announcement")?;
+    node.set_text_content(Some(text));
+    Ok(())
+}
+
 fn scroll_to_wayback_turn(wayback: WaybackState) {
     for turn_record_board in TurnRecordBoard::iter() {
         let node = wayback.display_turn_index().and_then(|index| {
@@ -2325,8 +3314,29 @@ fn set_cannot_start_alert(alert: Option<&str>) -> JsResult<()> {
     Ok(())
 }
 
+// Returns captions to show on the square.
+fn grid_captions(
+    display_coord: DisplayCoord, board_shape: BoardShape, coordinate_style: CoordinateStyle,
+) -> Vec<GridCaption> {
+    match coordinate_style {
+        CoordinateStyle::Off => vec![],
+        CoordinateStyle::Edge => {
+            let mut captions = vec![];
+            if display_coord.x == 0 {
+                captions.push(GridCaption::Row);
+            }
+            if display_coord.y == board_shape.num_rows as i8 - 1 {
+                captions.push(GridCaption::Col);
+            }
+            captions
+        }
+        CoordinateStyle::AllSquares => vec![GridCaption::Square],
+    }
+}
+
 fn render_grid(
     board_idx: DisplayBoard, board_shape: BoardShape, perspective: Perspective,
+    coordinate_style: CoordinateStyle,
 ) -> JsResult<()> {
     let text_h_padding = 0.07;
     let text_v_padding = 0.09;
@@ -2345,21 +3355,26 @@ fn render_grid(
             sq.set_attribute("height", "1")?;
             sq.set_attribute("class", square_color_class(coord.color()))?;
             layer.append_child(&sq)?;
-            if display_coord.x == 0 {
-                let caption = document.create_svg_element("text")?;
-                caption.set_text_content(Some(&String::from(row.to_algebraic(board_shape))));
-                caption.set_attribute("x", &(x + text_h_padding).to_string())?;
-                caption.set_attribute("y", &(y + text_v_padding).to_string())?;
-                caption.set_attribute("dominant-baseline", "hanging")?;
-                caption.set_attribute("class", square_text_color_class(coord.color()))?;
-                layer.append_child(&caption)?;
-            }
-            if display_coord.y == board_shape.num_rows as i8 - 1 {
+            for caption_kind in grid_captions(display_coord, board_shape, coordinate_style) {
                 let caption = document.create_svg_element("text")?;
-                caption.set_text_content(Some(&String::from(col.to_algebraic(board_shape))));
-                caption.set_attribute("x", &(x + 1.0 - text_h_padding).to_string())?;
-                caption.set_attribute("y", &(y + 1.0 - text_v_padding).to_string())?;
-                caption.set_attribute("text-anchor", "end")?;
+                let text = match caption_kind {
+                    GridCaption::Row => String::from(row.to_algebraic(board_shape)),
+                    GridCaption::Col => String::from(col.to_algebraic(board_shape)),
+                    GridCaption::Square => coord.to_algebraic(board_shape),
+                };
+                caption.set_text_content(Some(&text));
+                match caption_kind {
+                    GridCaption::Row => {
+                        caption.set_attribute("x", &(x + text_h_padding).to_string())?;
+                        caption.set_attribute("y", &(y + text_v_padding).to_string())?;
+                        caption.set_attribute("dominant-baseline", "hanging")?;
+                    }
+                    GridCaption::Col | GridCaption::Square => {
+                        caption.set_attribute("x", &(x + 1.0 - text_h_padding).to_string())?;
+                        caption.set_attribute("y", &(y + 1.0 - text_v_padding).to_string())?;
+                        caption.set_attribute("text-anchor", "end")?;
+                    }
+                }
                 caption.set_attribute("class", square_text_color_class(coord.color()))?;
                 layer.append_child(&caption)?;
             }
@@ -2368,9 +3383,52 @@ fn render_grid(
     Ok(())
 }
 
-fn render_board(
+fn set_square_node_position(node: &web_sys::Element, display_coord: DisplayCoord) -> JsResult<()> {
+    const SIZE: f64 = 1.0;
+    let shift = (SIZE - 1.0) / 2.0;
+    let pos = DisplayFCoord::square_pivot(display_coord);
+    node.set_attribute("x", &(pos.x - shift).to_string())?;
+    node.set_attribute("y", &(pos.y - shift).to_string())?;
+    Ok(())
+}
+
+// Re-renders the board for the new orientation while keeping piece nodes, so that CSS transitions
+// could animate piece movement. Other layers are either static and re-rendered here, or dynamic
+// and cleared here to be re-rendered by `update_state` and `repaint_chalk`.
+fn flip_board_in_place(
     board_idx: DisplayBoard, board_shape: BoardShape, perspective: Perspective,
+    coordinate_style: CoordinateStyle,
 ) -> JsResult<()> {
+    let document = web_document();
+    let board_orientation = get_board_orientation(board_idx, perspective);
+    let layers = [
+        square_grid_layer_id(board_idx),
+        square_highlight_layer_id(SquareHighlightLayer::Turn, board_idx),
+        chalk_highlight_layer_id(board_idx),
+        fog_of_war_layer_id(board_idx),
+        square_highlight_layer_id(SquareHighlightLayer::TurnAbove, board_idx),
+        square_highlight_layer_id(SquareHighlightLayer::Ephemeral, board_idx),
+        chalk_drawing_layer_id(board_idx),
+        promotion_target_layer_id(board_idx),
+    ];
+    for layer_id in layers {
+        document.get_existing_element_by_id(&layer_id)?.remove_all_children();
+    }
+    render_grid(board_idx, board_shape, perspective, coordinate_style)?;
+    for coord in board_shape.coords() {
+        if let Some(node) = document.get_element_by_id(&square_id(board_idx, coord)) {
+            let display_coord = to_display_coord(coord, board_shape, board_orientation);
+            set_square_node_position(&node, display_coord)?;
+        }
+    }
+    Ok(())
+}
+
+// Returns true if the board has been flipped in place rather than re-rendered from scratch.
+fn render_board(
+    board_idx: DisplayBoard, board_shape: BoardShape, perspective: Perspective,
+    coordinate_style: CoordinateStyle, flip_in_place: bool,
+) -> JsResult<bool> {
     let BoardShape { num_rows, num_cols } = board_shape;
     let make_board_rect = |document: &WebDocument| -> JsResult<web_sys::Element> {
         let rect = document.create_svg_element("rect")?;
@@ -2384,7 +3442,24 @@ fn render_board(
 
     let document = web_document();
     let svg = document.get_existing_element_by_id(&board_node_id(board_idx))?;
+    // Remember what is currently rendered in order to detect orientation-only changes.
+    let shape_attr = format!("{num_cols}x{num_rows}");
+    let board_attr = format!("{:?}", get_board_index(board_idx, perspective));
+    let orientation_attr = format!("{:?}", get_board_orientation(board_idx, perspective));
+    if flip_in_place
+        && svg.get_attribute("data-shape").as_ref() == Some(&shape_attr)
+        && svg.get_attribute("data-board").as_ref() == Some(&board_attr)
+        && svg.get_attribute("data-orientation").is_some_and(|o| o != orientation_attr)
+    {
+        flip_board_in_place(board_idx, board_shape, perspective, coordinate_style)?;
+        svg.set_attribute("data-orientation", &orientation_attr)?;
+        svg.class_list().add_1("board-flipping")?;
+        return Ok(true);
+    }
     svg.set_attribute("viewBox", &format!("0 0 {num_cols} {num_rows}"))?;
+    svg.set_attribute("data-shape", &shape_attr)?;
+    svg.set_attribute("data-board", &board_attr)?;
+    svg.set_attribute("data-orientation", &orientation_attr)?;
     svg.remove_all_children();
 
     let add_layer = |id: String,
@@ -2418,7 +3493,7 @@ fn render_board(
     svg.append_child(&shadow)?;
 
     add_layer(square_grid_layer_id(board_idx), ShapeRendering::CrispEdges, PointerEvents::Auto)?;
-    render_grid(board_idx, board_shape, perspective)?;
+    render_grid(board_idx, board_shape, perspective, coordinate_style)?;
 
     let border = make_board_rect(&document)?;
     border.set_attribute("class", "board-border")?;
@@ -2466,7 +3541,7 @@ fn render_board(
         reserve_container.set_attribute("viewBox", &format!("0 0 {num_cols} {RESERVE_HEIGHT}"))?;
         reserve_container.append_child(&reserve)?;
     }
-    Ok(())
+    Ok(false)
 }
 
 fn reset_chat() -> JsResult<()> {
@@ -2565,8 +3640,7 @@ fn render_archive_game_list(
         {
             let time_offset = UtcOffset::current_local_offset().unwrap_or(offset!(UTC));
             let game_start_utc = OffsetDateTime::from(game.game_start_time);
-            // Before this date our BPGNs didn't contain enough information to parse the game.
-            game_view_available = game_start_utc >= datetime!(2023-03-01 00:00:00 UTC);
+            game_view_available = pgn::game_view_available(game.game_start_time);
             let game_start_local = game_start_utc.to_offset(time_offset);
             let today = OffsetDateTime::now_utc().to_offset(time_offset);
             let start_time = if game_start_local.date() == today.date() {
@@ -2752,6 +3826,10 @@ fn reserve_node_id(board_idx: DisplayBoard, player_idx: DisplayPlayer) -> String
     format!("reserve-group-{}-{}", board_id(board_idx), player_id(player_idx))
 }
 
+fn clock_container_node_id(board_idx: DisplayBoard, player_idx: DisplayPlayer) -> String {
+    format!("clock-container-{}-{}", board_id(board_idx), player_id(player_idx))
+}
+
 fn clock_node_id(board_idx: DisplayBoard, player_idx: DisplayPlayer) -> String {
     format!("clock-{}-{}", board_id(board_idx), player_id(player_idx))
 }
@@ -2965,3 +4043,37 @@ fn get_audio_pan(my_id: BughouseParticipant, display_board_idx: DisplayBoard) ->
         (Player(DoublePlayer(_)) | Observer(_), DisplayBoard::Secondary) => Ok(1.),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_caption_count() {
+        let board_shape = BoardShape::standard();
+        let num_captions = |coordinate_style| {
+            let mut count = 0;
+            for x in 0..board_shape.num_cols as i8 {
+                for y in 0..board_shape.num_rows as i8 {
+                    let display_coord = DisplayCoord { x, y };
+                    count += grid_captions(display_coord, board_shape, coordinate_style).len();
+                }
+            }
+            count
+        };
+        assert_eq!(num_captions(CoordinateStyle::Off), 0);
+        assert_eq!(num_captions(CoordinateStyle::Edge), 16);
+        assert_eq!(num_captions(CoordinateStyle::AllSquares), 64);
+    }
+
+    #[test]
+    fn hex_color_validation() {
+        assert!(is_valid_hex_color("#e59349"));
+        assert!(is_valid_hex_color("#ABC"));
+        assert!(!is_valid_hex_color("e59349"));
+        assert!(!is_valid_hex_color("#e5934"));
+        assert!(!is_valid_hex_color("#e5934g"));
+        assert!(!is_valid_hex_color("red"));
+        assert!(!is_valid_hex_color("#fff; background: url(x)"));
+    }
+}
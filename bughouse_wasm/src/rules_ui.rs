@@ -19,15 +19,35 @@ use crate::web_iterators::IntoHtmlCollectionIterator;
 use crate::{IgnorableError, rust_error};
 
 const RATING: &str = "rating"; // filled by JSs
+const REGISTERED_ONLY: &str = "registered_only";
+const RATED_GAME_CHAT: &str = "rated_game_chat";
+const SEATING: &str = "seating";
+const RATED_PRETURNS: &str = "rated_preturns";
+const CHAT_SLOW_MODE: &str = "chat_slow_mode";
+const ABORT_WINDOW: &str = "abort_window";
+const MATCH_TARGET: &str = "match_target";
 const FAIRY_PIECES: &str = "fairy_pieces";
 const STARTING_POSITION: &str = "starting_position";
 const DUCK_CHESS: &str = "duck_chess";
 const FOG_OF_WAR: &str = "fog_of_war";
+const FOG_OF_WAR_VISIBILITY: &str = "fog_of_war_visibility";
+const FOG_OF_WAR_RESERVES: &str = "fog_of_war_reserves";
 const KOEDEM: &str = "koedem";
 const STARTING_TIME: &str = "starting_time";
+const INCREMENT: &str = "increment";
+const NO_INCREMENT_TURNS: &str = "no_increment_turns";
 const PROMOTION: &str = "promotion";
 const PAWN_DROP_RANKS: &str = "pawn_drop_ranks";
 const DROP_AGGRESSION: &str = "drop_aggression";
+const NO_DROP_TURNS: &str = "no_drop_turns";
+const INTERPOSING_DROPS: &str = "interposing_drops";
+const BOARD_RESIGNATION: &str = "board_resignation";
+const SIMULTANEOUS_FLAG: &str = "simultaneous_flag";
+const STALEMATE: &str = "stalemate";
+const CASTLING_THROUGH_CHECK: &str = "castling_through_check";
+const CASTLING: &str = "castling";
+const PIECE_CAP: &str = "piece_cap";
+const DELAYED_START: &str = "delayed_start";
 
 const PLACEHOLDER_ICON: &str = r##"<svg class="rule-variant-icon"></svg>"##;
 
@@ -465,8 +485,49 @@ fn fog_of_war_tooltip() -> JsResult<Vec<web_sys::Element>> {
             .create_element("p")?
             .with_more_text_i("Fog of war (Dark chess).")?
             .with_more_text(
-                " You only see squares where your pieces can legally move.
-            You can drop pieces into the fog of war at your own risk.",
+                " You only see squares near your pieces: by default, squares where
+            your pieces can legally move. You can drop pieces into the fog of war
+            at your own risk.",
+            )?,
+    ])
+}
+
+fn fog_of_war_visibility_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Legal moves.")?
+            .with_more_text(" You see squares where your pieces can legally move.")?,
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Radius.")?
+            .with_more_text(
+                " You see squares within the given number of king steps from your pieces,
+            regardless of how the pieces move.",
+            )?,
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Legal moves and radius.")?
+            .with_more_text(" You see both.")?,
+    ])
+}
+
+fn fog_of_war_reserves_visible_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Visible.")?
+            .with_more_text(" You can see which pieces your opponents have in reserve.")?,
+    ])
+}
+fn fog_of_war_reserves_hidden_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Hidden.")?
+            .with_more_text(
+                " You only see your own and your partner's reserves. Opponents' drops
+            can come as a surprise.",
             )?,
     ])
 }
@@ -485,10 +546,11 @@ fn koedem_tooltip() -> JsResult<Vec<web_sys::Element>> {
 }
 
 fn starting_time_tooltip(max_starting_time: Option<Duration>) -> JsResult<Vec<web_sys::Element>> {
-    let mut paragraphs =
-        vec![web_document().create_element("p")?.with_text_content(
-            "Starting time in “m:ss” format. There are no increments or delays.",
-        )];
+    let mut paragraphs = vec![
+        web_document()
+            .create_element("p")?
+            .with_text_content("Starting time in “m:ss” format. There are no delays."),
+    ];
     if let Some(max_starting_time) = max_starting_time {
         paragraphs.push(web_document().create_element("p")?.with_text_content(&format!(
             "Must not exceed {}.",
@@ -498,6 +560,54 @@ fn starting_time_tooltip(max_starting_time: Option<Duration>) -> JsResult<Vec<we
     Ok(paragraphs)
 }
 
+fn registered_only_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Whether guests can join the match. Rated matches are always for registered users only.",
+    )])
+}
+
+fn rated_game_chat_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Which chat messages players see during a rated game. Limiting the chat prevents \
+        coaching. All messages are revealed when the game is over. Ignored in unrated matches.",
+    )])
+}
+
+fn rated_preturns_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Whether players can premove in rated games. Disabling premoves makes fast games about \
+        thinking rather than queueing moves. Ignored in unrated matches.",
+    )])
+}
+
+fn chat_slow_mode_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Each participant can send at most one chat message per the given interval.",
+    )])
+}
+
+fn abort_window_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Allows to abort a game that started by mistake. Both teams must agree (type /abort in \
+        chat). An aborted game has no result and does not count towards ratings.",
+    )])
+}
+
+fn match_target_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "When the match ends. After that the winner is announced and no more games can be played. \
+        Ties are broken by fewer games played, then by more victories, then by the most recent \
+        victory.",
+    )])
+}
+
+fn winner_stays_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Winner stays: the team that won keeps their seats and challengers replace the losers. \
+        Rotate: everybody gets equal play time. Only applies when teams are not fixed.",
+    )])
+}
+
 fn promotion_upgrade_tooltip() -> JsResult<Vec<web_sys::Element>> {
     Ok(vec![
         web_document()
@@ -543,7 +653,8 @@ fn promotion_steal_specific_tooltip(rules: &ChessRules) -> JsResult<Vec<web_sys:
         web_document().create_element("p")?.with_more_text_i("Steal.")?.with_more_text(
             &format!(
                 " Expropriate your partner opponent's piece when promoting a pawn!
-                Can only steal a piece from the board, not from reserve. {exposure}",
+                Can only steal a piece from the board, not from reserve. {exposure}
+                Hold Shift when moving the pawn to steal the most valuable piece automatically.",
             ),
         )?,
     ])
@@ -589,6 +700,136 @@ fn drop_aggression_mate_allowed_tooltip() -> JsResult<Vec<web_sys::Element>> {
     ])
 }
 
+fn interposing_drops_allowed_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Allowed.")?
+            .with_more_text(" A check can be blocked by dropping a piece.")?,
+    ])
+}
+fn interposing_drops_forbidden_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Forbidden.")?
+            .with_more_text(
+                " A check cannot be blocked by dropping a piece: the king must move or
+            the checking piece must be captured.",
+            )?,
+    ])
+}
+
+fn board_resignation_forbidden_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Forbidden.")?
+            .with_more_text(" Resigning ends the game for both boards.")?,
+    ])
+}
+fn board_resignation_allowed_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Allowed.")?
+            .with_more_text(
+                " A player can resign their board only. The game goes on until the other board is
+            over, then the team that won more boards wins.",
+            )?,
+    ])
+}
+
+fn castling_through_check_forbidden_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Forbidden.")?
+            .with_more_text(
+                " As in regular chess, the king cannot castle out of, through, or into check.",
+            )?,
+    ])
+}
+fn castling_through_check_allowed_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Allowed.")?
+            .with_more_text(
+                " The king can castle out of or through check. Castling into check is still
+            illegal.",
+            )?,
+    ])
+}
+
+fn castling_allowed_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Allowed.")?
+            .with_more_text(
+                " Castle by moving the king two squares towards the rook or by moving the king
+            onto the rook.",
+            )?,
+    ])
+}
+fn castling_king_move_only_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("King move only.")?
+            .with_more_text(
+                " Castle by moving the king two squares towards the rook. Moving the king onto
+            the rook is not castling.",
+            )?,
+    ])
+}
+fn castling_forbidden_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Forbidden.")?
+            .with_more_text(" Castling is not allowed.")?,
+    ])
+}
+
+fn simultaneous_flag_draw_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document().create_element("p")?.with_more_text_i("Draw.")?.with_more_text(
+            " If players from different teams run out of time at the same moment, the game is
+            a draw.",
+        )?,
+    ])
+}
+fn simultaneous_flag_material_advantage_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document()
+            .create_element("p")?
+            .with_more_text_i("Material advantage.")?
+            .with_more_text(
+                " If players from different teams run out of time at the same moment, the team
+            with more material on both boards combined wins. Equal material is a draw.",
+            )?,
+    ])
+}
+
+fn stalemate_wait_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document().create_element("p")?.with_more_text_i("Wait.")?.with_more_text(
+            " A player with no legal moves or drops waits for the partner to pass a piece.
+            If that doesn't happen in time, the player loses on time.",
+        )?,
+    ])
+}
+fn stalemate_draw_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![
+        web_document().create_element("p")?.with_more_text_i("Draw.")?.with_more_text(
+            " If a player is not in check and has no legal moves or drops, the game is a
+            draw.",
+        )?,
+    ])
+}
+
 // Improvement potential: Update based on the current board shape.
 fn pawn_drop_rank_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
     let first = web_document().create_element("p")?.with_more_text(
@@ -629,6 +870,78 @@ fn pawn_drop_rank_specific_tooltip(
     Ok(vec![web_document().create_element("p")?.with_more_text(&message)?])
 }
 
+fn increment_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_text_content(
+        "Seconds added to the clock after each move. Zero means no increment.",
+    )])
+}
+
+fn no_increment_turns_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(
+        "Number of moves at the start of the game played without increment. Discourages premove
+        spamming in the opening. Counted separately on each board.",
+    )?])
+}
+fn no_increment_turns_specific_tooltip(no_increment_turns: u32) -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(&format!(
+        "The first {no_increment_turns} moves on each board are played without increment.
+        Increment is granted starting from move {}.",
+        no_increment_turns + 1
+    ))?])
+}
+
+fn no_drop_turns_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(
+        "Number of moves at the start of the game played without drops, like in regular chess.
+        Captured pieces still go to reserve and can be dropped after the warmup.
+        Zero means drops are allowed from the first move.",
+    )?])
+}
+fn no_drop_turns_specific_tooltip(no_drop_turns: u32) -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(&format!(
+        "The first {no_drop_turns} moves on each board are played without drops.
+        Captured pieces still go to reserve. Drops are allowed starting from move {}.",
+        no_drop_turns + 1
+    ))?])
+}
+
+fn piece_cap_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(
+        "Limits the total number of pieces each player can have on the board and in reserve.
+        Prevents stalling by hoarding pieces for drops.
+        “Refuse” means a capture is illegal if a player would exceed the limit.
+        “Discard” means the capture is allowed, but excess pieces are removed from the game.",
+    )?])
+}
+fn piece_cap_specific_tooltip(piece_cap: PieceCap) -> JsResult<Vec<web_sys::Element>> {
+    let policy = match piece_cap.policy {
+        PieceCapPolicy::Refuse => "A capture that would exceed this is illegal.",
+        PieceCapPolicy::Discard => "Captured pieces beyond this are removed from the game.",
+    };
+    Ok(vec![web_document().create_element("p")?.with_more_text(&format!(
+        "Each player can have at most {} pieces on the board and in reserve. {policy}",
+        piece_cap.max_pieces
+    ))?])
+}
+
+fn delayed_start_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(
+        "Clock handicap. Clocks of the chosen team start later than usual on both boards:
+        until the delay is over, the team can think for free. The other team's clocks run from
+        the first move as usual. Not available in rated matches.",
+    )?])
+}
+fn delayed_start_specific_tooltip(delayed_start: DelayedStart) -> JsResult<Vec<web_sys::Element>> {
+    Ok(vec![web_document().create_element("p")?.with_more_text(&format!(
+        "Clocks of the {} team don't run during the first {} seconds of the game.",
+        match delayed_start.team {
+            Team::Red => "red",
+            Team::Blue => "blue",
+        },
+        delayed_start.delay.as_secs()
+    ))?])
+}
+
 fn regicide_general_tooltip() -> JsResult<Vec<web_sys::Element>> {
     let regicide_variants = ChessVariant::iter()
         .filter(|v| v.enables_regicide())
@@ -787,6 +1100,42 @@ pub fn make_new_match_rules_body(server_options: &ServerOptions) -> JsResult<()>
             )?)?)?
             .to_elements()?,
     )?;
+    details_node.append_children(
+        RuleNode::new(INCREMENT, "Increment")
+            .with_input_text("[0-9]{1,2}", "0")?
+            .with_tooltip(combine_elements(increment_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(NO_INCREMENT_TURNS, "Moves without increment")
+            .with_input_text("[0-9]{1,2}", "0")?
+            .with_tooltip(combine_elements(no_increment_turns_general_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(FOG_OF_WAR_VISIBILITY, "Fog of war visibility")
+            .with_input_select([
+                ("movement", "Legal moves"),
+                ("radius-1", "Radius 1"),
+                ("radius-2", "Radius 2"),
+                ("hybrid-1", "Legal moves and radius 1"),
+            ])?
+            .with_tooltip(combine_elements(fog_of_war_visibility_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(FOG_OF_WAR_RESERVES, "Opponent reserves in fog")
+            .with_input_select([("visible", "Visible"), ("hidden", "Hidden")])?
+            .with_tooltip(combine_elements(
+                [
+                    fog_of_war_reserves_visible_tooltip()?,
+                    fog_of_war_reserves_hidden_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
     details_node.append_children(
         RuleNode::new(PROMOTION, "Promotion")
             .with_input_select([("upgrade", "Upgrade"), ("steal", "Steal")])?
@@ -824,6 +1173,175 @@ pub fn make_new_match_rules_body(server_options: &ServerOptions) -> JsResult<()>
             )?)?
             .to_elements()?,
     )?;
+    details_node.append_children(
+        RuleNode::new(INTERPOSING_DROPS, "Blocking check by drop")
+            .with_input_select([("allowed", "Allowed"), ("forbidden", "Forbidden")])?
+            .with_tooltip(combine_elements(
+                [
+                    interposing_drops_allowed_tooltip()?,
+                    interposing_drops_forbidden_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(NO_DROP_TURNS, "Moves without drops")
+            .with_input_text("[0-9]{1,2}", "0")?
+            .with_tooltip(combine_elements(no_drop_turns_general_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(BOARD_RESIGNATION, "Board resignation")
+            .with_input_select([("forbidden", "Forbidden"), ("allowed", "Allowed")])?
+            .with_tooltip(combine_elements(
+                [
+                    board_resignation_forbidden_tooltip()?,
+                    board_resignation_allowed_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(CASTLING, "Castling")
+            .with_input_select([
+                ("allowed", "Allowed"),
+                ("king-move-only", "King move only"),
+                ("forbidden", "Forbidden"),
+            ])?
+            .with_tooltip(combine_elements(
+                [
+                    castling_allowed_tooltip()?,
+                    castling_king_move_only_tooltip()?,
+                    castling_forbidden_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(CASTLING_THROUGH_CHECK, "Castling through check")
+            .with_input_select([("forbidden", "Forbidden"), ("allowed", "Allowed")])?
+            .with_tooltip(combine_elements(
+                [
+                    castling_through_check_forbidden_tooltip()?,
+                    castling_through_check_allowed_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(SIMULTANEOUS_FLAG, "Simultaneous flags")
+            .with_input_select([("draw", "Draw"), ("material", "Material advantage")])?
+            .with_tooltip(combine_elements(
+                [
+                    simultaneous_flag_draw_tooltip()?,
+                    simultaneous_flag_material_advantage_tooltip()?,
+                ]
+                .into_iter()
+                .flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(STALEMATE, "Stalemate")
+            .with_input_select([("wait", "Wait"), ("draw", "Draw")])?
+            .with_tooltip(combine_elements(
+                [stalemate_wait_tooltip()?, stalemate_draw_tooltip()?].into_iter().flatten(),
+            )?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(PIECE_CAP, "Piece cap")
+            .with_input_select([
+                ("off", "Off"),
+                ("20 Refuse", "20, refuse capture"),
+                ("20 Discard", "20, discard excess"),
+                ("24 Refuse", "24, refuse capture"),
+                ("24 Discard", "24, discard excess"),
+            ])?
+            .with_tooltip(combine_elements(piece_cap_general_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(DELAYED_START, "Delayed start")
+            .with_input_select([
+                ("off", "Off"),
+                ("Red 30", "Red team, 30 s"),
+                ("Red 60", "Red team, 60 s"),
+                ("Blue 30", "Blue team, 30 s"),
+                ("Blue 60", "Blue team, 60 s"),
+            ])?
+            .with_tooltip(combine_elements(delayed_start_general_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(REGISTERED_ONLY, "Players")
+            .with_input_select([("anyone", "Anyone"), ("registered", "Registered only")])?
+            .with_tooltip(combine_elements(registered_only_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(RATED_GAME_CHAT, "Chat in rated games")
+            .with_input_select([
+                ("everyone", "Everyone"),
+                ("team", "Team only"),
+                ("muted", "Muted"),
+            ])?
+            .with_tooltip(combine_elements(rated_game_chat_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(RATED_PRETURNS, "Premoves in rated games")
+            .with_input_select([("allowed", "Allowed"), ("disabled", "Disabled")])?
+            .with_tooltip(combine_elements(rated_preturns_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(CHAT_SLOW_MODE, "Chat slow mode")
+            .with_input_select([
+                ("0", "Off"),
+                ("10", "10 s"),
+                ("30", "30 s"),
+                ("60", "1 min"),
+            ])?
+            .with_tooltip(combine_elements(chat_slow_mode_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(ABORT_WINDOW, "Abort")
+            .with_input_select([
+                ("off", "Not allowed"),
+                ("2", "First 2 turns"),
+                ("5", "First 5 turns"),
+            ])?
+            .with_tooltip(combine_elements(abort_window_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(MATCH_TARGET, "Match length")
+            .with_input_select([
+                ("off", "Unlimited"),
+                ("points:3", "First to 3 points"),
+                ("points:5", "First to 5 points"),
+                ("games:5", "5 games"),
+                ("games:10", "10 games"),
+            ])?
+            .with_tooltip(combine_elements(match_target_tooltip()?)?)?
+            .to_elements()?,
+    )?;
+    details_node.append_children(
+        RuleNode::new(SEATING, "Seating")
+            .with_input_select([("rotate", "Rotate"), ("winner_stays", "Winner stays")])?
+            .with_tooltip(combine_elements(winner_stays_tooltip()?)?)?
+            .to_elements()?,
+    )?;
     {
         let node = document.create_element("div")?.with_classes([
             "grid-col-span-2",
@@ -909,6 +1427,30 @@ pub fn make_readonly_rules_body(rules: &Rules) -> JsResult<web_sys::Element> {
         rules.chess_rules.time_control.to_string(),
         Some(combine_elements(starting_time_tooltip(None)?)?),
     ));
+    let time_control = &rules.chess_rules.time_control;
+    if !time_control.increment.is_zero() && time_control.no_increment_turns > 0 {
+        rule_rows.push((
+            "Moves without increment",
+            time_control.no_increment_turns.to_string(),
+            Some(combine_elements(no_increment_turns_specific_tooltip(
+                time_control.no_increment_turns,
+            )?)?),
+        ));
+    }
+    if rules.chess_rules.fog_of_war {
+        rule_rows.push((
+            "Fog of war visibility",
+            rules.chess_rules.fog_of_war_visibility.to_human_readable(),
+            Some(combine_elements(fog_of_war_visibility_tooltip()?)?),
+        ));
+        if rules.chess_rules.fog_of_war_hide_reserves {
+            rule_rows.push((
+                "Opponent reserves",
+                "Hidden".to_owned(),
+                Some(combine_elements(fog_of_war_reserves_hidden_tooltip()?)?),
+            ));
+        }
+    }
     if let Some(bughouse_rules) = rules.bughouse_rules() {
         let promotion_tooltip = match bughouse_rules.promotion {
             Promotion::Upgrade => promotion_upgrade_tooltip()?,
@@ -929,6 +1471,99 @@ pub fn make_readonly_rules_body(rules: &Rules) -> JsResult<web_sys::Element> {
                 bughouse_rules.pawn_drop_ranks.max,
             )?)?),
         ));
+        if bughouse_rules.no_drop_turns > 0 {
+            rule_rows.push((
+                "Moves without drops",
+                bughouse_rules.no_drop_turns.to_string(),
+                Some(combine_elements(no_drop_turns_specific_tooltip(
+                    bughouse_rules.no_drop_turns,
+                )?)?),
+            ));
+        }
+        if bughouse_rules.simultaneous_flag != SimultaneousFlagPolicy::Draw {
+            rule_rows.push((
+                "Simultaneous flags",
+                bughouse_rules.simultaneous_flag.to_human_readable().to_owned(),
+                Some(combine_elements(simultaneous_flag_material_advantage_tooltip()?)?),
+            ));
+        }
+        if bughouse_rules.stalemate != Stalemate::Wait {
+            rule_rows.push((
+                "Stalemate",
+                bughouse_rules.stalemate.to_human_readable().to_owned(),
+                Some(combine_elements(stalemate_draw_tooltip()?)?),
+            ));
+        }
+        if bughouse_rules.board_resignation {
+            rule_rows.push((
+                "Board resignation",
+                "Allowed".to_owned(),
+                Some(combine_elements(board_resignation_allowed_tooltip()?)?),
+            ));
+        }
+        if let Some(piece_cap) = bughouse_rules.piece_cap {
+            rule_rows.push((
+                "Piece cap",
+                piece_cap.to_human_readable(),
+                Some(combine_elements(piece_cap_specific_tooltip(piece_cap)?)?),
+            ));
+        }
+        if let Some(delayed_start) = bughouse_rules.delayed_start {
+            rule_rows.push((
+                "Delayed start",
+                delayed_start.to_human_readable(),
+                Some(combine_elements(delayed_start_specific_tooltip(delayed_start)?)?),
+            ));
+        }
+    }
+    if rules.match_rules.requires_registration() {
+        rule_rows.push((
+            "Players",
+            "Registered only".to_owned(),
+            Some(combine_elements(registered_only_tooltip()?)?),
+        ));
+    }
+    if rules.match_rules.limits_game_chat() {
+        rule_rows.push((
+            "Chat during games",
+            rules.match_rules.rated_game_chat.to_human_readable().to_owned(),
+            Some(combine_elements(rated_game_chat_tooltip()?)?),
+        ));
+    }
+    if let Some(interval) = rules.match_rules.chat_slow_mode {
+        rule_rows.push((
+            "Chat slow mode",
+            format!("{} s", interval.as_secs()),
+            Some(combine_elements(chat_slow_mode_tooltip()?)?),
+        ));
+    }
+    if let Some(limit) = rules.match_rules.preturn_limit() {
+        rule_rows.push((
+            "Premoves",
+            preturn_limit_to_human_readable(limit),
+            Some(combine_elements(rated_preturns_tooltip()?)?),
+        ));
+    }
+    if let Some(window) = rules.match_rules.abort_window {
+        rule_rows.push((
+            "Abort",
+            abort_window_to_human_readable(window),
+            Some(combine_elements(abort_window_tooltip()?)?),
+        ));
+    }
+    if let Some(target) = rules.match_rules.match_target {
+        rule_rows.push((
+            "Match length",
+            target.to_human_readable(),
+            Some(combine_elements(match_target_tooltip()?)?),
+        ));
+    }
+    if rules.match_rules.winner_stays {
+        rule_rows.push((
+            "Seating",
+            "Winner stays".to_owned(),
+            Some(combine_elements(winner_stays_tooltip()?)?),
+        ));
     }
     if rules.chess_rules.regicide() {
         rule_rows.push((
@@ -949,8 +1584,34 @@ pub fn make_readonly_rules_body(rules: &Rules) -> JsResult<web_sys::Element> {
                 bughouse_rules.drop_aggression.to_human_readable().to_owned(),
                 Some(combine_elements(drop_aggression_tooltip)?),
             ));
+            if bughouse_rules.no_interposing_drops {
+                rule_rows.push((
+                    "Blocking check by drop",
+                    "Forbidden".to_owned(),
+                    Some(combine_elements(interposing_drops_forbidden_tooltip()?)?),
+                ));
+            }
+        }
+        if rules.chess_rules.castling_through_check {
+            rule_rows.push((
+                "Castling through check",
+                "Allowed".to_owned(),
+                Some(combine_elements(castling_through_check_allowed_tooltip()?)?),
+            ));
         }
     }
+    if rules.chess_rules.castling != Castling::Allowed {
+        let castling_tooltip = match rules.chess_rules.castling {
+            Castling::Allowed => castling_allowed_tooltip()?,
+            Castling::KingMoveOnly => castling_king_move_only_tooltip()?,
+            Castling::Forbidden => castling_forbidden_tooltip()?,
+        };
+        rule_rows.push((
+            "Castling",
+            rules.chess_rules.castling.to_human_readable().to_owned(),
+            Some(combine_elements(castling_tooltip)?),
+        ));
+    }
     for (caption, value, tooltip) in rule_rows {
         let tr = table.new_child_element("tr")?;
         {
@@ -1008,6 +1669,20 @@ pub fn update_new_match_rules_body() -> JsResult<()> {
     for node in web_document().get_elements_by_class_name(&rule_setting_class(DROP_AGGRESSION)) {
         node.set_displayed(!regicide)?;
     }
+    for node in
+        web_document().get_elements_by_class_name(&rule_setting_class(CASTLING_THROUGH_CHECK))
+    {
+        node.set_displayed(!regicide)?;
+    }
+    for node in
+        web_document().get_elements_by_class_name(&rule_setting_class(FOG_OF_WAR_VISIBILITY))
+    {
+        node.set_displayed(fog_of_war)?;
+    }
+    for node in web_document().get_elements_by_class_name(&rule_setting_class(FOG_OF_WAR_RESERVES))
+    {
+        node.set_displayed(fog_of_war)?;
+    }
 
     let preset = new_match_rules().map_or(None, |r| r.chess_rules.get_preset());
     activate_preset_button(preset)?;
@@ -1048,6 +1723,20 @@ pub fn new_match_rules() -> JsResult<Rules> {
     let atomic_chess = false;
 
     // Other chess rules
+    let fog_of_war_visibility =
+        match details.get(FOG_OF_WAR_VISIBILITY).as_string().unwrap().as_str() {
+            "movement" => FogOfWarVisibility::Movement,
+            "radius-1" => FogOfWarVisibility::Radius(1),
+            "radius-2" => FogOfWarVisibility::Radius(2),
+            "hybrid-1" => FogOfWarVisibility::Hybrid(1),
+            s => return Err(format!("Invalid fog of war visibility: {s}").into()),
+        };
+    let fog_of_war_hide_reserves =
+        match details.get(FOG_OF_WAR_RESERVES).as_string().unwrap().as_str() {
+            "visible" => false,
+            "hidden" => true,
+            s => return Err(format!("Invalid fog of war reserves option: {s}").into()),
+        };
     let promotion = match details.get(PROMOTION).as_string().unwrap().as_str() {
         "upgrade" => Promotion::Upgrade,
         "discard" => Promotion::Discard,
@@ -1061,14 +1750,68 @@ pub fn new_match_rules() -> JsResult<Rules> {
         "mate-allowed" => DropAggression::MateAllowed,
         s => return Err(format!("Invalid drop aggression: {s}").into()),
     };
+    let no_interposing_drops = match details.get(INTERPOSING_DROPS).as_string().unwrap().as_str() {
+        "allowed" => false,
+        "forbidden" => true,
+        s => return Err(format!("Invalid interposing drops option: {s}").into()),
+    };
     let starting_time = details.get(STARTING_TIME).as_string().unwrap();
     let Some(starting_time) = duration_from_mss(&starting_time) else {
         return Err(format!("Invalid starting time: {starting_time}").into());
     };
+    let increment = details.get(INCREMENT).as_string().unwrap();
+    let Ok(increment) = increment.parse() else {
+        return Err(format!("Invalid increment: {increment}").into());
+    };
+    let no_increment_turns = details.get(NO_INCREMENT_TURNS).as_string().unwrap();
+    let Ok(no_increment_turns) = no_increment_turns.parse() else {
+        return Err(
+            format!("Invalid number of moves without increment: {no_increment_turns}").into()
+        );
+    };
     let pawn_drop_ranks = details.get(PAWN_DROP_RANKS).as_string().unwrap();
     let Ok(pawn_drop_ranks) = PawnDropRanks::from_pgn(&pawn_drop_ranks) else {
         return Err(format!("Invalid pawn drop ranks: {pawn_drop_ranks}").into());
     };
+    let no_drop_turns = details.get(NO_DROP_TURNS).as_string().unwrap();
+    let Ok(no_drop_turns) = no_drop_turns.parse() else {
+        return Err(format!("Invalid number of moves without drops: {no_drop_turns}").into());
+    };
+    let board_resignation = match details.get(BOARD_RESIGNATION).as_string().unwrap().as_str() {
+        "forbidden" => false,
+        "allowed" => true,
+        s => return Err(format!("Invalid board resignation option: {s}").into()),
+    };
+    let castling = match details.get(CASTLING).as_string().unwrap().as_str() {
+        "allowed" => Castling::Allowed,
+        "king-move-only" => Castling::KingMoveOnly,
+        "forbidden" => Castling::Forbidden,
+        s => return Err(format!("Invalid castling option: {s}").into()),
+    };
+    let castling_through_check =
+        match details.get(CASTLING_THROUGH_CHECK).as_string().unwrap().as_str() {
+            "forbidden" => false,
+            "allowed" => true,
+            s => return Err(format!("Invalid castling through check option: {s}").into()),
+        };
+    let simultaneous_flag = match details.get(SIMULTANEOUS_FLAG).as_string().unwrap().as_str() {
+        "draw" => SimultaneousFlagPolicy::Draw,
+        "material" => SimultaneousFlagPolicy::MaterialAdvantage,
+        s => return Err(format!("Invalid simultaneous flag policy: {s}").into()),
+    };
+    let stalemate = match details.get(STALEMATE).as_string().unwrap().as_str() {
+        "wait" => Stalemate::Wait,
+        "draw" => Stalemate::Draw,
+        s => return Err(format!("Invalid stalemate option: {s}").into()),
+    };
+    let piece_cap = match details.get(PIECE_CAP).as_string().unwrap().as_str() {
+        "off" => None,
+        s => Some(PieceCap::from_pgn(s).map_err(|_| format!("Invalid piece cap: {s}"))?),
+    };
+    let delayed_start = match details.get(DELAYED_START).as_string().unwrap().as_str() {
+        "off" => None,
+        s => Some(DelayedStart::from_pgn(s).map_err(|_| format!("Invalid delayed start: {s}"))?),
+    };
 
     // Non-chess rules
     let rated = match details.get(RATING).as_string().unwrap().as_str() {
@@ -1077,25 +1820,98 @@ pub fn new_match_rules() -> JsResult<Rules> {
         s => return Err(format!("Invalid rating: {s}").into()),
     };
     let public = true; // Make this configurable.
+    let registered_only = match details.get(REGISTERED_ONLY).as_string().unwrap().as_str() {
+        "anyone" => false,
+        "registered" => true,
+        s => return Err(format!("Invalid player requirement: {s}").into()),
+    };
+    let rated_game_chat = match details.get(RATED_GAME_CHAT).as_string().unwrap().as_str() {
+        "everyone" => RatedGameChat::Everyone,
+        "team" => RatedGameChat::TeamOnly,
+        "muted" => RatedGameChat::Muted,
+        s => return Err(format!("Invalid chat setting: {s}").into()),
+    };
+    let rated_preturn_limit = match details.get(RATED_PRETURNS).as_string().unwrap().as_str() {
+        "allowed" => None,
+        "disabled" => Some(0),
+        s => return Err(format!("Invalid premove setting: {s}").into()),
+    };
+    let chat_slow_mode = match details.get(CHAT_SLOW_MODE).as_string().unwrap().as_str() {
+        "0" => None,
+        s => Some(Duration::from_secs(
+            s.parse().map_err(|_| format!("Invalid chat slow mode: {s}"))?,
+        )),
+    };
+    let abort_window = match details.get(ABORT_WINDOW).as_string().unwrap().as_str() {
+        "off" => None,
+        s => Some(s.parse().map_err(|_| format!("Invalid abort setting: {s}"))?),
+    };
+    let match_target = match details.get(MATCH_TARGET).as_string().unwrap().as_str() {
+        "off" => None,
+        s => {
+            let invalid = || format!("Invalid match length: {s}");
+            let (kind, value) = s.split_once(':').ok_or_else(invalid)?;
+            let value = value.parse().map_err(|_| invalid())?;
+            match kind {
+                "points" => Some(MatchTarget::Points(value)),
+                "games" => Some(MatchTarget::Games(value)),
+                _ => return Err(invalid().into()),
+            }
+        }
+    };
+    let winner_stays = match details.get(SEATING).as_string().unwrap().as_str() {
+        "rotate" => false,
+        "winner_stays" => true,
+        s => return Err(format!("Invalid seating: {s}").into()),
+    };
 
     // Combine everything together
-    let match_rules = MatchRules { rated, public };
+    let match_rules = MatchRules {
+        rated,
+        public,
+        registered_only,
+        rated_game_chat,
+        winner_stays,
+        rated_preturn_limit,
+        chat_slow_mode,
+        abort_window,
+        match_target,
+    };
     let mut chess_rules = ChessRules {
         fairy_pieces,
         starting_position,
         duck_chess,
         atomic_chess,
         fog_of_war,
-        time_control: TimeControl { starting_time },
+        fog_of_war_visibility,
+        fog_of_war_hide_reserves,
+        castling_through_check,
+        castling,
+        time_control: TimeControl {
+            starting_time,
+            increment: Duration::from_secs(increment),
+            no_increment_turns,
+        },
+        piece_values: PieceValues::default(),
         bughouse_rules: Some(BughouseRules {
             koedem,
             promotion,
             pawn_drop_ranks,
             drop_aggression,
+            no_drop_turns,
+            no_interposing_drops,
+            simultaneous_flag,
+            stalemate,
+            board_resignation: board_resignation && !koedem,
+            piece_cap,
+            delayed_start,
         }),
     };
     if chess_rules.regicide() {
-        chess_rules.bughouse_rules.as_mut().unwrap().drop_aggression = DropAggression::MateAllowed;
+        chess_rules.castling_through_check = false;
+        let bughouse_rules = chess_rules.bughouse_rules.as_mut().unwrap();
+        bughouse_rules.drop_aggression = DropAggression::MateAllowed;
+        bughouse_rules.no_interposing_drops = false;
     }
     let rules = Rules { match_rules, chess_rules };
     if let Err(message) = rules.verify() {
@@ -1104,6 +1920,42 @@ pub fn new_match_rules() -> JsResult<Rules> {
     Ok(rules)
 }
 
+// Applies both chess and match rules, except for rating.
+pub fn new_match_apply_full_rules(rules: &Rules) -> JsResult<()> {
+    let match_rules = &rules.match_rules;
+    set_select_value(REGISTERED_ONLY, match match_rules.registered_only {
+        false => "anyone",
+        true => "registered",
+    })?;
+    set_select_value(RATED_GAME_CHAT, match match_rules.rated_game_chat {
+        RatedGameChat::Everyone => "everyone",
+        RatedGameChat::TeamOnly => "team",
+        RatedGameChat::Muted => "muted",
+    })?;
+    set_select_value(RATED_PRETURNS, match match_rules.rated_preturn_limit {
+        None => "allowed",
+        Some(_) => "disabled",
+    })?;
+    set_select_value(
+        CHAT_SLOW_MODE,
+        &match_rules.chat_slow_mode.map_or(0, |d| d.as_secs()).to_string(),
+    )?;
+    set_select_value(
+        ABORT_WINDOW,
+        &match_rules.abort_window.map_or_else(|| "off".to_owned(), |w| w.to_string()),
+    )?;
+    set_select_value(MATCH_TARGET, &match match_rules.match_target {
+        None => "off".to_owned(),
+        Some(MatchTarget::Points(n)) => format!("points:{n}"),
+        Some(MatchTarget::Games(n)) => format!("games:{n}"),
+    })?;
+    set_select_value(SEATING, match match_rules.winner_stays {
+        false => "rotate",
+        true => "winner_stays",
+    })?;
+    new_match_apply_rules(&rules.chess_rules)
+}
+
 fn new_match_apply_preset(preset: RulesPreset) -> JsResult<()> {
     new_match_apply_rules(&ChessRules::from_preset(preset))
 }
@@ -1135,6 +1987,28 @@ fn new_match_apply_rules(rules: &ChessRules) -> JsResult<()> {
     })?;
 
     // Other chess rules
+    set_select_value(CASTLING, match rules.castling {
+        Castling::Allowed => "allowed",
+        Castling::KingMoveOnly => "king-move-only",
+        Castling::Forbidden => "forbidden",
+    })?;
+    set_select_value(CASTLING_THROUGH_CHECK, match rules.castling_through_check {
+        false => "forbidden",
+        true => "allowed",
+    })?;
+    set_select_value(FOG_OF_WAR_VISIBILITY, match rules.fog_of_war_visibility {
+        FogOfWarVisibility::Movement => "movement",
+        FogOfWarVisibility::Radius(1) => "radius-1",
+        FogOfWarVisibility::Radius(2) => "radius-2",
+        FogOfWarVisibility::Hybrid(1) => "hybrid-1",
+        visibility => {
+            return Err(format!("Unsupported fog of war visibility: {visibility:?}").into());
+        }
+    })?;
+    set_select_value(FOG_OF_WAR_RESERVES, match rules.fog_of_war_hide_reserves {
+        false => "visible",
+        true => "hidden",
+    })?;
     set_select_value(PROMOTION, match bughouse_rules.promotion {
         Promotion::Upgrade => "upgrade",
         Promotion::Discard => "discard",
@@ -1147,9 +2021,36 @@ fn new_match_apply_rules(rules: &ChessRules) -> JsResult<()> {
         DropAggression::MateAllowed => "mate-allowed",
     })?;
     set_input_value(STARTING_TIME, &duration_to_mss(rules.time_control.starting_time))?;
+    set_input_value(INCREMENT, &rules.time_control.increment.as_secs().to_string())?;
+    set_input_value(NO_INCREMENT_TURNS, &rules.time_control.no_increment_turns.to_string())?;
     set_input_value(PAWN_DROP_RANKS, &bughouse_rules.pawn_drop_ranks.to_pgn())?;
+    set_select_value(INTERPOSING_DROPS, match bughouse_rules.no_interposing_drops {
+        false => "allowed",
+        true => "forbidden",
+    })?;
+    set_input_value(NO_DROP_TURNS, &bughouse_rules.no_drop_turns.to_string())?;
+    set_select_value(BOARD_RESIGNATION, match bughouse_rules.board_resignation {
+        false => "forbidden",
+        true => "allowed",
+    })?;
+    set_select_value(SIMULTANEOUS_FLAG, match bughouse_rules.simultaneous_flag {
+        SimultaneousFlagPolicy::Draw => "draw",
+        SimultaneousFlagPolicy::MaterialAdvantage => "material",
+    })?;
+    set_select_value(STALEMATE, match bughouse_rules.stalemate {
+        Stalemate::Wait => "wait",
+        Stalemate::Draw => "draw",
+    })?;
+    set_select_value(
+        PIECE_CAP,
+        &bughouse_rules.piece_cap.map_or_else(|| "off".to_owned(), |cap| cap.to_pgn()),
+    )?;
+    set_select_value(
+        DELAYED_START,
+        &bughouse_rules.delayed_start.map_or_else(|| "off".to_owned(), |d| d.to_pgn()),
+    )?;
 
-    // Non-chess rules (like RATING) are out of scope.
+    // Non-chess rules are applied by `new_match_apply_full_rules`.
 
     // Final touches
     update_new_match_rules_body()?;
@@ -173,6 +173,10 @@ pub fn render_chat_reference_tooltip(
                 [Command(">"), Notation("notation")].as_slice(),
                 "make move on the right board",
             ));
+            lines.push((
+                [Command("="), Notation("notation")].as_slice(),
+                "make move on the focused board",
+            ));
         }
     }
 
@@ -225,15 +229,150 @@ pub fn render_chat_reference_dialog() -> JsResult<()> {
     ]);
     line_groups.push(vec![
         ([Command("/resign")].as_slice(), ["Resign from the game."].as_slice()),
+        (
+            [Command("/resign-board")].as_slice(),
+            ["Resign your board only. Requires board resignation to be allowed."].as_slice(),
+        ),
+        (
+            [Command("/abort")].as_slice(),
+            ["Offer or agree to abort the game. Only possible early in the game."].as_slice(),
+        ),
         (
             [Command("/ready")].as_slice(),
             ["Toggle readiness for the next game."].as_slice(),
         ),
+        (
+            [Command("/rematch"), Message(" boards|colors")].as_slice(),
+            ["Play the next game with the same players, swapping boards or colors."].as_slice(),
+        ),
+        (
+            [Command("/rules-text")].as_slice(),
+            ["Copy the match rules as plain text."].as_slice(),
+        ),
+        (
+            [Command("/moves"), Message(" primary|secondary|both")].as_slice(),
+            ["Copy the move list of one board or of both boards."].as_slice(),
+        ),
+        (
+            [Command("/spectate-link")].as_slice(),
+            ["Copy a read-only link for watching the match without joining."].as_slice(),
+        ),
+        (
+            [Command("/follow"), Recipient(" name")].as_slice(),
+            ["Spectate the player's next game when they start one in another match."].as_slice(),
+        ),
+        ([Command("/unfollow")].as_slice(), ["Stop following the player."].as_slice()),
+        (
+            [
+                Command("/handicap"),
+                Recipient(" name"),
+                Message(" off|<pieces>"),
+            ]
+            .as_slice(),
+            ["Give a player extra reserve pieces in the next games, e.g. NNP."].as_slice(),
+        ),
+        (
+            [Command("/transfer-ownership"), Recipient(" name")].as_slice(),
+            ["Make another participant the match owner."].as_slice(),
+        ),
+        (
+            [Command("/pins")].as_slice(),
+            ["Toggle highlighting of pinned pieces (disabled in fog of war)."].as_slice(),
+        ),
+        (
+            [Command("/scratch")].as_slice(),
+            ["Toggle scratch mode: try out moves for both sides without sending them."].as_slice(),
+        ),
+        (
+            [Command("/study")].as_slice(),
+            ["Open a local board where you move freely for all sides on both boards."].as_slice(),
+        ),
+        (
+            [Command("/reserve"), Message(" board color piece amount")].as_slice(),
+            ["Set the number of pieces in reserve in study mode."].as_slice(),
+        ),
         ([Command("/h")].as_slice(), ["Show this reference."].as_slice()),
         (
             [Command("/tooltip")].as_slice(),
             ["Toggle tooltip with the short version of this reference."].as_slice(),
         ),
+        (
+            [Command("/input"), Message(" any|drag|click")].as_slice(),
+            ["Allow moving pieces by both dragging and clicking, or only one of them."].as_slice(),
+        ),
+        (
+            [Command("/flip-animation"), Message(" on|off")].as_slice(),
+            ["Animate the board when it is flipped."].as_slice(),
+        ),
+        (
+            [Command("/highlight-updates"), Message(" incremental|full")].as_slice(),
+            ["Update move highlights in place, or rebuild them on every frame."].as_slice(),
+        ),
+        (
+            [Command("/log-scroll"), Message(" freeze|follow")].as_slice(),
+            ["Keep a scrolled-up turn log in place, or always jump to the latest turn."].as_slice(),
+        ),
+        (
+            [
+                Command("/restock-alert"),
+                Message(" sound|flash|announce|both"),
+            ]
+            .as_slice(),
+            ["When a piece arrives to your reserve, also flash it and/or say its name."].as_slice(),
+        ),
+        (
+            [Command("/coordinates"), Message(" off|edge|all")].as_slice(),
+            ["Show square coordinates: none, on board edges, or on every square."].as_slice(),
+        ),
+        (
+            [Command("/drop-highlight"), Message(" fill|marker")].as_slice(),
+            ["Highlight drop squares like moves, or add a marker to set drops apart."].as_slice(),
+        ),
+        (
+            [Command("/rating"), Message(" show|hide")].as_slice(),
+            ["Show or hide your rating from observers."].as_slice(),
+        ),
+        (
+            [Command("/announce-moves"), Message(" on|off")].as_slice(),
+            ["Describe other players' moves to screen readers."].as_slice(),
+        ),
+        (
+            [Command("/timetrouble"), Message(" off|<seconds>")].as_slice(),
+            ["Flash clocks you care about when below the threshold (up to 20 seconds)."].as_slice(),
+        ),
+        (
+            [Command("/focus"), Message(" primary|secondary|next|off")].as_slice(),
+            [
+                "Focus a board for keyboard commands. Arrows left/right or tapping a clock also \
+                 switch focus.",
+            ]
+            .as_slice(),
+        ),
+        (
+            [Command("/board-scale"), Message(" <factor>")].as_slice(),
+            ["Resize the boards, e.g. 1.2 for 20% larger (from 0.5 to 2)."].as_slice(),
+        ),
+        (
+            [Command("/frame-budget"), Message(" off|<milliseconds>")].as_slice(),
+            ["Log a warning to the browser console when rendering a frame takes longer."]
+                .as_slice(),
+        ),
+        (
+            [
+                Command("/boardcolors"),
+                Message(" <light> <dark> <highlight>"),
+            ]
+            .as_slice(),
+            ["Set board colors as hex, e.g. #eeeed2 #769656 #baca44, or \"reset\"."].as_slice(),
+        ),
+        (
+            [Command("/export-settings")].as_slice(),
+            ["Copy settings to clipboard to use them on another device."].as_slice(),
+        ),
+        (
+            [Command("/import-settings"), Message(" <settings>")].as_slice(),
+            ["Apply settings copied with /export-settings."].as_slice(),
+        ),
     ]);
     line_groups.push(vec![
         (
@@ -252,6 +391,10 @@ pub fn render_chat_reference_dialog() -> JsResult<()> {
             [Command(">"), Notation("-")].as_slice(),
             ["Undo premove on the right board (if playing on two boards)."].as_slice(),
         ),
+        (
+            [Command("="), Notation("notation")].as_slice(),
+            ["Make move on the focused board (the left one if none is focused)."].as_slice(),
+        ),
     ]);
     line_groups.push(vec![(
         [].as_slice(),
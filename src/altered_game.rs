@@ -31,7 +31,7 @@ use crate::game::{
     TurnRecord, TurnRecordExpanded, get_bughouse_force,
 };
 use crate::piece::{CastleDirection, PieceForce, PieceId, PieceKind, PieceOnBoard, PieceOrigin};
-use crate::rules::{BughouseRules, ChessRules, Promotion};
+use crate::rules::{BughouseRules, Castling, ChessRules, Promotion};
 
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -149,6 +149,10 @@ pub enum WaybackDestination {
     Next,
     First,
     Last,
+    // Like `Previous`/`Next`, but only stop at turns made by the current participant. Observers
+    // don't have their own turns, so for them these are equivalent to `Previous`/`Next`.
+    MyPrevious,
+    MyNext,
 }
 
 impl From<Result<(), TurnError>> for TurnInputResult {
@@ -182,6 +186,9 @@ impl WaybackState {
 pub struct AlteredGame {
     // All local actions are assumed to be made on behalf of this player. Constant.
     my_id: BughouseParticipant,
+    // Local analysis mode: the user moves for both sides on both boards, turn order is not
+    // enforced and there is no server. Turns are applied to `game_confirmed` directly. Constant.
+    study_mode: bool,
     // State as it has been confirmed by the server.
     game_confirmed: Dirty<BughouseGame>,
     // Partial turn input (e.g. move of a pawn to the last rank without a promotion choice).
@@ -198,6 +205,11 @@ pub struct AlteredGame {
     local_turns: Dirty<Vec<TurnRecord>>,
     // Historical position that the user is currently viewing.
     wayback_turn_index: Dirty<Option<TurnIndex>>,
+    // Scratch mode: the user explores variations during a live game. Turns are made for whichever
+    // side is to move, applied on top of `game_confirmed` and never sent to the server. Local turns
+    // and preturns are kept intact, but not shown while in scratch mode. `None` if not in scratch
+    // mode. Any real update (a turn from the server or a local turn) exits scratch mode.
+    scratch_turns: Dirty<Option<Vec<TurnRecord>>>,
     // Data determistically computed based on the other fields. Don't use this field directly, even
     // internally! Use `derived_data()` instead, which ensures that the data is up-to-date.
     derived_data: RefCell<DerivedData>,
@@ -218,22 +230,37 @@ struct DerivedData {
 
 impl AlteredGame {
     pub fn new(my_id: BughouseParticipant, game_confirmed: BughouseGame) -> Self {
+        Self::new_impl(my_id, false, game_confirmed)
+    }
+
+    pub fn new_study(game: BughouseGame) -> Self {
+        Self::new_impl(BughouseParticipant::default_observer(), true, game)
+    }
+
+    fn new_impl(
+        my_id: BughouseParticipant, study_mode: bool, game_confirmed: BughouseGame,
+    ) -> Self {
         let partial_turn_input = None;
         let local_turns = Vec::new();
         let wayback_turn_index = None;
+        let scratch_turns: Option<Vec<TurnRecord>> = None;
         let derived_data = compute_derived_data(
             my_id,
+            study_mode,
             &game_confirmed,
             partial_turn_input,
             &local_turns,
             wayback_turn_index,
+            scratch_turns.as_deref(),
         );
         AlteredGame {
             my_id,
+            study_mode,
             game_confirmed: Dirty::new(game_confirmed),
             partial_turn_input: Dirty::new(partial_turn_input),
             local_turns: Dirty::new(local_turns),
             wayback_turn_index: Dirty::new(wayback_turn_index),
+            scratch_turns: Dirty::new(scratch_turns),
             derived_data: RefCell::new(derived_data),
         }
     }
@@ -254,12 +281,21 @@ impl AlteredGame {
     pub fn set_status(&mut self, status: BughouseGameStatus, time: GameInstant) {
         assert!(!status.is_active());
         self.game_confirmed.get_mut().set_status(status, time);
+        self.exit_scratch_mode();
         self.reset_local_changes();
     }
 
+    pub fn apply_board_resignation(&mut self, envoy: BughouseEnvoy, time: GameInstant) {
+        self.exit_scratch_mode();
+        self.game_confirmed.get_mut().resign_board(envoy, time);
+        self.local_turns.get_mut().retain(|r| r.envoy.board_idx != envoy.board_idx);
+        self.revise_local_turns();
+    }
+
     pub fn apply_remote_turn(
         &mut self, envoy: BughouseEnvoy, turn_input: &TurnInput, time: GameInstant,
     ) -> Result<ApplyRemoteTurnResult, TurnError> {
+        self.exit_scratch_mode();
         let mut original_game_confirmed = (*self.game_confirmed).clone();
         self.game_confirmed.get_mut().try_turn_by_envoy(
             envoy,
@@ -315,6 +351,7 @@ impl AlteredGame {
     }
 
     pub fn my_id(&self) -> BughouseParticipant { self.my_id }
+    pub fn is_study_mode(&self) -> bool { self.study_mode }
     pub fn perspective(&self) -> Perspective { Perspective::for_participant(self.my_id) }
     pub fn game_confirmed(&self) -> &BughouseGame { &self.game_confirmed }
 
@@ -339,7 +376,7 @@ impl AlteredGame {
             if !self.is_active() {
                 break 'duck None;
             }
-            let Some(envoy) = self.my_id.envoy_for(board_idx) else {
+            let Some(envoy) = self.input_envoy(board_idx) else {
                 break 'duck None;
             };
             let local_game = self.local_game();
@@ -364,12 +401,23 @@ impl AlteredGame {
         if !self.is_active() {
             return false;
         }
-        let Some(envoy) = self.my_id.envoy_for(board_idx) else {
+        let Some(envoy) = self.input_envoy(board_idx) else {
             return false;
         };
         self.local_game().board(board_idx).is_duck_turn(envoy.force)
     }
 
+    // In scratch mode the user moves for the side to move.
+    fn input_envoy(&self, board_idx: BughouseBoard) -> Option<BughouseEnvoy> {
+        if self.is_scratch_mode() {
+            let force = self.local_game().board(board_idx).active_force();
+            Some(BughouseEnvoy { board_idx, force })
+        } else {
+            self.my_id.envoy_for(board_idx)
+        }
+    }
+
+    // Covers both boards, so a double player sees preturns on each board they play on.
     pub fn turn_highlights(&self) -> TurnHighlights {
         let my_id = self.my_id;
         let game = self.local_game();
@@ -454,7 +502,22 @@ impl AlteredGame {
         }
     }
 
-    pub fn see_though_fog(&self) -> bool { !self.is_active() || self.my_id.is_observer() }
+    pub fn see_though_fog(&self) -> bool { see_though_fog(&self.game_confirmed, self.my_id) }
+
+    // Whether the reserve of `force` on `board_idx` is hidden from the user by fog of war. Players
+    // always see their own and their partner's reserves. Display-only: the reserve is still known
+    // locally, see `ChessRules::fog_of_war_hide_reserves`.
+    pub fn is_reserve_hidden(&self, board_idx: BughouseBoard, force: Force) -> bool {
+        let chess_rules = self.chess_rules();
+        if !chess_rules.fog_of_war || !chess_rules.fog_of_war_hide_reserves || self.see_though_fog()
+        {
+            return false;
+        }
+        match self.my_id {
+            BughouseParticipant::Player(id) => get_bughouse_force(id.team(), board_idx) != force,
+            BughouseParticipant::Observer(_) => false,
+        }
+    }
 
     pub fn try_local_turn(
         &mut self, board_idx: BughouseBoard, turn_input: TurnInput, time: GameInstant,
@@ -465,12 +528,17 @@ impl AlteredGame {
         if self.wayback_turn_index.is_some() {
             return Err(TurnError::WaybackIsActive);
         }
-        if self.num_preturns_on_board(board_idx) >= self.chess_rules().max_preturns_per_board() {
+        // A real turn always applies to the real position.
+        self.exit_scratch_mode();
+        let mut game = self.local_game().clone();
+        let mode = game.turn_mode_for_envoy(envoy)?;
+        if mode == TurnMode::Preturn
+            && self.num_preturns_on_board(board_idx)
+                >= self.game_confirmed().rules().max_preturns_per_board()
+        {
             return Err(TurnError::PreturnLimitReached);
         }
         self.partial_turn_input.set(None);
-        let mut game = self.local_game().clone();
-        let mode = game.turn_mode_for_envoy(envoy)?;
         game.try_turn_by_envoy(envoy, &turn_input, mode, time)?;
         // Note: cannot use `game.turn_log().last()` here! It will change the input method, and this
         // can cause subtle differences in preturn execution. For example, when making algebraic
@@ -481,6 +549,96 @@ impl AlteredGame {
         Ok(mode)
     }
 
+    // Study mode counterpart of `try_local_turn`. The turn is applied immediately. If the active
+    // force cannot make the turn, it is tried on behalf of the other side. Drag&drop turns are
+    // never reinterpreted: picking up a piece already gave the turn to its owner.
+    pub fn try_study_turn(
+        &mut self, board_idx: BughouseBoard, turn_input: TurnInput,
+    ) -> Result<(), TurnError> {
+        assert!(self.study_mode);
+        if self.wayback_turn_index.is_some() {
+            return Err(TurnError::WaybackIsActive);
+        }
+        self.partial_turn_input.set(None);
+        // Clocks are not used in study mode, so the time never moves forward.
+        let time = GameInstant::game_start();
+        let mut game = (*self.game_confirmed).clone();
+        if let Err(err) = game.try_turn(board_idx, &turn_input, TurnMode::InOrder, time) {
+            if matches!(turn_input, TurnInput::DragDrop(_)) {
+                return Err(err);
+            }
+            game = (*self.game_confirmed).clone();
+            let board = game.board_mut(board_idx);
+            board.set_active_force(board.active_force().opponent());
+            game.try_turn(board_idx, &turn_input, TurnMode::InOrder, time)
+                .map_err(|_| err)?;
+        }
+        *self.game_confirmed.get_mut() = game;
+        Ok(())
+    }
+
+    // Study mode only: sets the number of pieces of the given kind in the reserve.
+    pub fn set_study_reserve(
+        &mut self, board_idx: BughouseBoard, force: Force, piece_kind: PieceKind, amount: u8,
+    ) {
+        assert!(self.study_mode);
+        self.partial_turn_input.set(None);
+        self.game_confirmed.get_mut().board_mut(board_idx).reserve_mut(force)[piece_kind] = amount;
+    }
+
+    // Scratch mode is not available in fog of war: exploring the position would reveal hidden
+    // pieces.
+    pub fn can_enter_scratch_mode(&self) -> bool {
+        self.is_active() && !self.study_mode && !self.chess_rules().fog_of_war
+    }
+    pub fn is_scratch_mode(&self) -> bool { self.scratch_turns.is_some() }
+
+    // Returns whether scratch mode is now active.
+    pub fn enter_scratch_mode(&mut self) -> bool {
+        if !self.can_enter_scratch_mode() {
+            return false;
+        }
+        if !self.is_scratch_mode() {
+            self.partial_turn_input.set(None);
+            *self.scratch_turns.get_mut() = Some(vec![]);
+        }
+        true
+    }
+
+    pub fn exit_scratch_mode(&mut self) {
+        if self.is_scratch_mode() {
+            self.partial_turn_input.set(None);
+            *self.scratch_turns.get_mut() = None;
+        }
+    }
+
+    // Goes back to the real position without leaving scratch mode.
+    pub fn clear_scratch_turns(&mut self) {
+        if self.is_scratch_mode() {
+            self.partial_turn_input.set(None);
+            *self.scratch_turns.get_mut() = Some(vec![]);
+        }
+    }
+
+    // Scratch mode counterpart of `try_local_turn`. The turn is made by the side to move.
+    pub fn try_scratch_turn(
+        &mut self, board_idx: BughouseBoard, turn_input: TurnInput, time: GameInstant,
+    ) -> Result<(), TurnError> {
+        let Some(scratch_turns) = &*self.scratch_turns else {
+            return Err(TurnError::NoTurnInProgress);
+        };
+        self.partial_turn_input.set(None);
+        let mut game = scratch_game(&self.game_confirmed, scratch_turns);
+        let envoy = BughouseEnvoy {
+            board_idx,
+            force: game.board(board_idx).active_force(),
+        };
+        game.try_turn_by_envoy(envoy, &turn_input, TurnMode::InOrder, time)?;
+        let record = TurnRecord { envoy, turn_input, time };
+        self.scratch_turns.get_mut().as_mut().unwrap().push(record);
+        Ok(())
+    }
+
     pub fn wayback_state(&self) -> WaybackState {
         if self.is_active() {
             WaybackState::Disabled
@@ -504,11 +662,20 @@ impl AlteredGame {
         let old_index = self
             .wayback_turn_index
             .or_else(|| self.game_confirmed.last_turn_record().map(|r| r.index))?;
+        let my_id = self.my_id;
+        let destination = match destination {
+            WaybackDestination::MyPrevious if my_id.is_observer() => WaybackDestination::Previous,
+            WaybackDestination::MyNext if my_id.is_observer() => WaybackDestination::Next,
+            _ => destination,
+        };
+        let only_mine =
+            matches!(destination, WaybackDestination::MyPrevious | WaybackDestination::MyNext);
         let mut iter = self
             .game_confirmed
             .turn_log()
             .iter()
             .filter(move |r| board_idx.is_none_or(|b| r.envoy.board_idx == b))
+            .filter(move |r| !only_mine || my_id.plays_for(r.envoy))
             .map(|r| r.index);
         let new_index = match destination {
             WaybackDestination::Index(index) => index,
@@ -529,12 +696,55 @@ impl AlteredGame {
             WaybackDestination::Next => iter.find_or_last(|index| *index > old_index),
             WaybackDestination::First => iter.nth(0),
             WaybackDestination::Last => iter.rev().nth(0),
+            // Unlike `Previous`, stop at the turn even if it is already visible on the board: the
+            // point is to review each of my turns in turn. Stay in place if there is nowhere to go.
+            WaybackDestination::MyPrevious => {
+                iter.rev().find(|index| *index < old_index).or(Some(old_index))
+            }
+            WaybackDestination::MyNext => iter.find(|index| *index > old_index).or(Some(old_index)),
         };
         let at_end = new_index == self.game_confirmed.last_turn_record().map(|r| r.index);
         self.wayback_turn_index.set(if at_end { None } else { new_index });
         *self.wayback_turn_index
     }
 
+    // Completes a steal promotion in progress by stealing the most valuable piece that can be
+    // legally stolen. Returns `Noop` if there is no steal promotion in progress or if there is
+    // nothing to steal: the user should choose the target manually in this case.
+    pub fn auto_steal_promotion(&mut self) -> TurnInputResult {
+        let Some((board_idx, PartialTurnInput::StealPromotion { from, to })) =
+            *self.partial_turn_input
+        else {
+            return TurnInputResult::Noop;
+        };
+        let Some(target) = self.best_steal_target(board_idx, to) else {
+            return TurnInputResult::Noop;
+        };
+        let full_input = TurnInput::DragDrop(Turn::Move(TurnMove {
+            from,
+            to,
+            promote_to: Some(PromotionTarget::Steal(target)),
+        }));
+        self.partial_turn_input.set(None);
+        TurnInputResult::Turn((board_idx, full_input))
+    }
+
+    fn best_steal_target(
+        &self, board_idx: BughouseBoard, promotion_square: Coord,
+    ) -> Option<(PieceKind, PieceOrigin, PieceId)> {
+        let game = self.local_game();
+        let pawn = game.board(board_idx).grid()[promotion_square]?;
+        let thief = Force::try_from(pawn.force).ok()?;
+        let other_board = game.board(board_idx.other());
+        let chess_rules = self.chess_rules();
+        self.board_shape()
+            .coords()
+            .filter(|&coord| other_board.stealing_result(coord, thief).is_ok())
+            .filter_map(|coord| other_board.grid()[coord])
+            .max_by_key(|piece| piece.kind.value(chess_rules))
+            .map(|piece| (piece.kind, piece.origin, piece.id))
+    }
+
     pub fn choose_promotion_upgrade(&mut self, piece_kind: PieceKind) -> TurnInputResult {
         if let Some((input_board_idx, partial_input)) = *self.partial_turn_input {
             match partial_input {
@@ -706,6 +916,12 @@ impl AlteredGame {
         }
     }
 
+    pub fn abort_click_move(&mut self) {
+        if matches!(*self.partial_turn_input, Some((_, PartialTurnInput::ClickMove { .. }))) {
+            self.partial_turn_input.set(None);
+        }
+    }
+
     // Stop drag and returns turn on success. The client should then manually apply this
     // turn via `make_turn`.
     pub fn drag_piece_drop(&mut self, board_idx: BughouseBoard, dest: Coord) -> TurnInputResult {
@@ -764,8 +980,13 @@ impl AlteredGame {
                 if let Ok(force) = piece_force.try_into() {
                     let first_row = SubjectiveRow::first().to_row(board_shape, force);
                     let last_row = SubjectiveRow::last(board_shape).to_row(board_shape, force);
+                    // With `Castling::KingMoveOnly`, dragging the king onto a rook is never castling.
+                    let onto_rook = self.chess_rules().castling == Castling::KingMoveOnly
+                        && self.local_game().board(board_idx).grid()[dest]
+                            .is_some_and(|p| p.force == piece_force && p.kind == PieceKind::Rook);
                     is_castling = piece_kind == King
                         && (d_col.abs() >= 2)
+                        && !onto_rook
                         && (source_coord.row == first_row && dest.row == first_row);
                     is_promotion = piece_kind == Pawn && dest.row == last_row;
                 }
@@ -897,7 +1118,9 @@ impl AlteredGame {
         *self.local_turns.get_mut() = local_turns;
 
         // Q. Should we abort partial turn if a preceding local turn was cancelled?
-        if apply_partial_turn(*self.partial_turn_input, self.my_id, &mut game).is_err() {
+        if apply_partial_turn(*self.partial_turn_input, self.my_id, self.free_input(), &mut game)
+            .is_err()
+        {
             // Partial turn invalidated. Possible reasons: dragged piece was captured by opponent;
             // dragged piece depended on a (pre)turn that was cancelled.
             self.invalidate_partial_turn();
@@ -922,14 +1145,25 @@ impl AlteredGame {
         &mut self, board_idx: BughouseBoard, input: PartialTurnInput,
     ) -> Result<(), TurnError> {
         self.partial_turn_input.set(None);
+        if self.study_mode
+            && let PartialTurnInput::Drag(turn) | PartialTurnInput::ClickMove(turn) = input
+            && let Ok(force) = Force::try_from(turn.piece_force)
+        {
+            // The user moves for both sides: picking up a piece gives the turn to its owner.
+            self.game_confirmed.get_mut().board_mut(board_idx).set_active_force(force);
+        }
         let mut game = self.local_game().clone();
-        let result = apply_partial_turn(Some((board_idx, input)), self.my_id, &mut game);
+        let result =
+            apply_partial_turn(Some((board_idx, input)), self.my_id, self.free_input(), &mut game);
         if result.is_ok() {
             self.partial_turn_input.set(Some((board_idx, input)));
         }
         result
     }
 
+    // Whether the user can make turns for any side rather than only for their own envoys.
+    fn free_input(&self) -> bool { self.study_mode || self.is_scratch_mode() }
+
     fn invalidate_partial_turn(&mut self) {
         let &mut Some((_, ref mut input)) = self.partial_turn_input.get_mut() else {
             return;
@@ -965,6 +1199,7 @@ impl AlteredGame {
             | self.partial_turn_input.take_dirt()
             | self.local_turns.take_dirt()
             | self.wayback_turn_index.take_dirt()
+            | self.scratch_turns.take_dirt()
         {
             // Note. It's possible to have a more granular approach here. We could've checked which
             // particular dirty flags have changes and only recompute the data that depends on those
@@ -973,10 +1208,12 @@ impl AlteredGame {
             // setting up a dependency tracking framework seems like an overkill.
             self.derived_data.replace(compute_derived_data(
                 self.my_id,
+                self.study_mode,
                 &self.game_confirmed,
                 *self.partial_turn_input,
                 &self.local_turns,
                 *self.wayback_turn_index,
+                self.scratch_turns.as_deref(),
             ));
         }
         self.derived_data.borrow()
@@ -1012,10 +1249,25 @@ fn board_turn_log_modulo_wayback(
 }
 
 fn compute_derived_data(
-    my_id: BughouseParticipant, game_confirmed: &BughouseGame,
+    my_id: BughouseParticipant, study_mode: bool, game_confirmed: &BughouseGame,
     partial_turn_input: Option<(BughouseBoard, PartialTurnInput)>, local_turns: &[TurnRecord],
-    wayback_turn_index: Option<TurnIndex>,
+    wayback_turn_index: Option<TurnIndex>, scratch_turns: Option<&[TurnRecord]>,
 ) -> DerivedData {
+    if let Some(scratch_turns) = scratch_turns {
+        assert!(wayback_turn_index.is_none());
+        let mut local_game = scratch_game(game_confirmed, scratch_turns);
+        // Unwrap ok: partial turn correctness has already been verified.
+        apply_partial_turn(partial_turn_input, my_id, true, &mut local_game).unwrap();
+        let fog_of_war_area = EnumMap::from_fn(|board_idx| {
+            compute_fog_of_war_area(game_confirmed, &local_game, board_idx, my_id)
+        });
+        return DerivedData {
+            local_game,
+            true_local_game: None,
+            fog_of_war_area,
+        };
+    }
+
     let mut true_local_game = None;
     let mut local_game_inorder_turns = game_confirmed.clone();
     if let Some(wayback_turn_index) = wayback_turn_index {
@@ -1050,7 +1302,7 @@ fn compute_derived_data(
         local_game.apply_turn_record(turn_record, mode).unwrap();
     }
     // Unwrap ok: partial turn correctness has already been verified.
-    apply_partial_turn(partial_turn_input, my_id, &mut local_game).unwrap();
+    apply_partial_turn(partial_turn_input, my_id, study_mode, &mut local_game).unwrap();
 
     let fog_of_war_area = EnumMap::from_fn(|board_idx| {
         compute_fog_of_war_area(&local_game_inorder_turns, &local_game, board_idx, my_id)
@@ -1063,15 +1315,34 @@ fn compute_derived_data(
     }
 }
 
+// Game with scratch turns applied on top of the confirmed position. Clocks are kept from the
+// confirmed position: exploring variations should not affect the time shown to the player.
+fn scratch_game(game_confirmed: &BughouseGame, scratch_turns: &[TurnRecord]) -> BughouseGame {
+    let mut game = game_confirmed.clone();
+    for turn_record in scratch_turns {
+        // Unwrap ok: turn correctness has already been verified.
+        game.apply_turn_record(turn_record, TurnMode::InOrder).unwrap();
+    }
+    for board_idx in BughouseBoard::iter() {
+        *game.board_mut(board_idx).clock_mut() = game_confirmed.board(board_idx).clock().clone();
+    }
+    game
+}
+
 fn apply_partial_turn(
     partial_turn_input: Option<(BughouseBoard, PartialTurnInput)>, my_id: BughouseParticipant,
-    game: &mut BughouseGame,
+    study_mode: bool, game: &mut BughouseGame,
 ) -> Result<(), TurnError> {
     let Some((board_idx, input)) = partial_turn_input else {
         return Ok(());
     };
-    let Some(envoy) = my_id.envoy_for(board_idx) else {
-        return Err(TurnError::NotPlayer);
+    let envoy = if study_mode {
+        BughouseEnvoy {
+            board_idx,
+            force: game.board(board_idx).active_force(),
+        }
+    } else {
+        my_id.envoy_for(board_idx).ok_or(TurnError::NotPlayer)?
     };
     let is_drag = matches!(input, PartialTurnInput::Drag(_));
     match input {
@@ -1150,7 +1421,13 @@ fn apply_wayback(wayback_turn_idx: TurnIndex, game: &BughouseGame) -> BughouseGa
     replay_game
 }
 
-fn compute_fog_of_war_area(
+// Players see only what their pieces see, including on the partner's board. Observers see
+// everything, and so does everybody once the game is over.
+pub fn see_though_fog(game: &BughouseGame, my_id: BughouseParticipant) -> bool {
+    !game.is_active() || my_id.is_observer()
+}
+
+pub(crate) fn compute_fog_of_war_area(
     local_game_full_turns: &BughouseGame, local_game: &BughouseGame, board_idx: BughouseBoard,
     my_id: BughouseParticipant,
 ) -> HashSet<Coord> {
@@ -42,6 +42,15 @@ pub enum AnalysisScore {
     MateDelivered(i32), // -1 or 1
 }
 
+// Turn quality annotation in the turn log, like on chess sites.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TurnGlyph {
+    Brilliant, // "!!"
+    Good,      // "!"
+    Mistake,   // "?"
+    Blunder,   // "??"
+}
+
 #[derive(Clone, Debug)]
 pub struct AnalysisInfo {
     pub score: AnalysisScore,
@@ -103,6 +112,41 @@ impl AnalysisScore {
     }
 }
 
+impl TurnGlyph {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            TurnGlyph::Brilliant => "!!",
+            TurnGlyph::Good => "!",
+            TurnGlyph::Mistake => "?",
+            TurnGlyph::Blunder => "??",
+        }
+    }
+}
+
+// Evaluation swing thresholds, in percentage points of `AnalysisScore::to_percent_score`.
+const TURN_GLYPH_MINOR_SWING: f64 = 10.0;
+const TURN_GLYPH_MAJOR_SWING: f64 = 20.0;
+
+// Classifies a turn made by `force` based on the evaluation (see `to_percent_score`) before and
+// after the turn.
+pub fn classify_turn(force: Force, eval_before: f64, eval_after: f64) -> Option<TurnGlyph> {
+    let gain = match force {
+        Force::White => eval_after - eval_before,
+        Force::Black => eval_before - eval_after,
+    };
+    if gain >= TURN_GLYPH_MAJOR_SWING {
+        Some(TurnGlyph::Brilliant)
+    } else if gain >= TURN_GLYPH_MINOR_SWING {
+        Some(TurnGlyph::Good)
+    } else if gain <= -TURN_GLYPH_MAJOR_SWING {
+        Some(TurnGlyph::Blunder)
+    } else if gain <= -TURN_GLYPH_MINOR_SWING {
+        Some(TurnGlyph::Mistake)
+    } else {
+        None
+    }
+}
+
 impl FsfAnalysisEngine {
     pub fn new(post_message: Box<dyn Fn(&str)>) -> Self {
         post_message("uci");
@@ -341,6 +385,7 @@ fn get_fsf_variant(rules: &ChessRules) -> Option<(&'static str, bool)> {
         || rules.fog_of_war
         || rules.promotion() != Promotion::Upgrade
         || bughouse_rules.drop_aggression != DropAggression::MateAllowed
+        || bughouse_rules.no_interposing_drops
         || bughouse_rules.pawn_drop_ranks != PawnDropRanks::from_one_based(2, 7)
     {
         return None;
@@ -1,4 +1,4 @@
-// Improvement potential. Chess draws: dead position, stalemate, fifty-move rule.
+// Improvement potential. Chess draws: dead position, fifty-move rule.
 
 #![allow(unused_parens)]
 
@@ -24,7 +24,8 @@ use crate::piece::{
 };
 use crate::role::Role;
 use crate::rules::{
-    BughouseRules, ChessRules, DropAggression, FairyPieces, MatchRules, Promotion, Rules,
+    BughouseRules, Castling, ChessRules, DropAggression, FairyPieces, FogOfWarVisibility,
+    MatchRules, PieceCapPolicy, Promotion, Rules, Stalemate,
 };
 use crate::starter::{
     BoardSetup, EffectiveStartingPosition, generate_starting_grid, starting_piece_row,
@@ -181,6 +182,17 @@ fn visibility_from(
         .collect()
 }
 
+// Squares within `radius` king steps from `from`.
+fn visibility_within_radius(shape: BoardShape, from: Coord, radius: u8) -> Vec<Coord> {
+    shape
+        .coords()
+        .filter(|&to| {
+            let (d_row, d_col) = to - from;
+            d_row.unsigned_abs().max(d_col.unsigned_abs()) <= radius
+        })
+        .collect()
+}
+
 fn move_destinations(
     rules: &ChessRules, grid: &Grid, from: Coord, en_passant_target: Option<Coord>,
 ) -> Vec<Coord> {
@@ -197,9 +209,8 @@ fn move_destinations(
 // Generates castling moves. The UI allows to drag the king any number of squares >=2 or even 1
 // square if the rook stand right next to it, but we generate one move per direction to reduce
 // clutter.
-// TODO: Exclude moves when the path is blocked.
 fn castling_destinations(
-    grid: &Grid, from: Coord, castling_rights: &BoardCastlingRights,
+    rules: &ChessRules, grid: &Grid, from: Coord, castling_rights: &BoardCastlingRights,
 ) -> Vec<Coord> {
     let Some(piece) = grid[from] else {
         return vec![];
@@ -212,13 +223,44 @@ fn castling_destinations(
     };
     let mut destinations = vec![];
     for (dir, rook_col) in castling_rights[force] {
-        if rook_col.is_some() {
+        if let Some(rook_col) = rook_col
+            && is_castling_path_clear(rules, grid, from, rook_col, dir)
+        {
             destinations.push(castling_destination(grid.shape(), from, dir))
         }
     }
     destinations
 }
 
+// Returns final king and rook columns.
+fn castling_target_cols(rules: &ChessRules, dir: CastleDirection) -> (Col, Col) {
+    match rules.fairy_pieces {
+        FairyPieces::NoFairy | FairyPieces::Accolade => match dir {
+            CastleDirection::ASide => (Col::C, Col::D),
+            CastleDirection::HSide => (Col::G, Col::F),
+        },
+        FairyPieces::Capablanca => match dir {
+            CastleDirection::ASide => (Col::C, Col::D),
+            CastleDirection::HSide => (Col::I, Col::H),
+        },
+    }
+}
+
+// Checks that all squares between and including initial and final king and rook positions are
+// empty, except for the castling king and rook themselves.
+fn is_castling_path_clear(
+    rules: &ChessRules, grid: &Grid, king_from: Coord, rook_col: Col, dir: CastleDirection,
+) -> bool {
+    let row = king_from.row;
+    let rook_from = Coord::new(row, rook_col);
+    let (king_to_col, rook_to_col) = castling_target_cols(rules, dir);
+    let cols = [king_from.col, king_to_col, rook_col, rook_to_col];
+    col_range_inclusive(iter_minmax(cols.into_iter()).unwrap()).all(|col| {
+        let pos = Coord::new(row, col);
+        pos == king_from || pos == rook_from || grid[pos].is_none()
+    })
+}
+
 fn castling_destination(shape: BoardShape, from: Coord, dir: CastleDirection) -> Coord {
     let d = match dir {
         CastleDirection::ASide => -1,
@@ -234,11 +276,19 @@ fn castling_destination(shape: BoardShape, from: Coord, dir: CastleDirection) ->
     Coord::new(from.row, to_col)
 }
 
-// TODO: Exclude moves when the path is blocked.
-fn castling_moves(castling_rights: &EnvoyCastlingRights) -> Vec<Turn> {
+fn castling_moves(
+    rules: &ChessRules, grid: &Grid, king_from: Coord, castling_rights: &EnvoyCastlingRights,
+) -> Vec<Turn> {
+    if rules.castling == Castling::Forbidden {
+        return vec![];
+    }
     castling_rights
         .iter()
-        .filter_map(|(dir, col)| col.map(|_| Turn::Castle(dir)))
+        .filter_map(|(dir, col)| {
+            let rook_col = (*col)?;
+            is_castling_path_clear(rules, grid, king_from, rook_col, dir)
+                .then_some(Turn::Castle(dir))
+        })
         .collect()
 }
 
@@ -251,6 +301,7 @@ fn king_force(grid: &Grid, king_pos: Coord) -> Force {
 // Grid is guaratneed to be returned intact.
 fn is_chess_mate_to(
     rules: &ChessRules, grid: &mut Grid, king_pos: Coord, en_passant_target: Option<Coord>,
+    capture_room: EnumMap<Force, Option<u32>>,
 ) -> bool {
     if !is_check_to(rules, grid, king_pos) {
         return false;
@@ -261,6 +312,10 @@ fn is_chess_mate_to(
             if piece.force == force.into() {
                 for to in move_destinations(rules, grid, from, en_passant_target) {
                     let capture_or = get_capture(grid, from, to, en_passant_target);
+                    // The only pieces that can be captured here belong to the attacking side.
+                    if capture_or.is_some() && capture_room[force.opponent()] == Some(0) {
+                        continue;
+                    }
                     // Zero out capture separately because of en passant.
                     let mut grid = grid.maybe_scoped_set(capture_or.map(|pos| (pos, None)));
                     let mut grid = grid.scoped_set(from, None);
@@ -279,9 +334,10 @@ fn is_chess_mate_to(
 // Grid is guaratneed to be returned intact.
 fn is_bughouse_mate_to(
     rules: &ChessRules, grid: &mut Grid, king_pos: Coord, en_passant_target: Option<Coord>,
+    capture_room: EnumMap<Force, Option<u32>>,
 ) -> bool {
     let force = king_force(grid, king_pos);
-    if !is_chess_mate_to(rules, grid, king_pos, en_passant_target) {
+    if !is_chess_mate_to(rules, grid, king_pos, en_passant_target, capture_room) {
         return false;
     }
     for pos in grid.shape().coords() {
@@ -334,6 +390,68 @@ fn attacker_set(
     ret
 }
 
+// Returns the pieces of a given force that attack (or defend) a given square. Unlike
+// `attacker_set`, the square is treated as capturable regardless of what occupies it. A pawn that
+// could take the square's occupant en passant is included as well.
+fn attackers_of(
+    grid: &Grid, pos: Coord, by: PieceForce, en_passant_target: Option<Coord>,
+) -> Vec<Coord> {
+    if by == PieceForce::Neutral {
+        // Duck cannot capture.
+        return vec![];
+    }
+    let attacks = |from: Coord, to: Coord| {
+        generic_reachability_modulo_destination_square(grid, from, to, Capturing::Yes).ok()
+    };
+    grid.shape()
+        .coords()
+        .filter(|&from| {
+            let Some(piece) = grid[from] else {
+                return false;
+            };
+            if piece.force != by {
+                return false;
+            }
+            if attacks(from, pos) {
+                return true;
+            }
+            if let Some(en_passant_target) = en_passant_target {
+                if get_capture(grid, from, en_passant_target, Some(en_passant_target)) == Some(pos)
+                    && attacks(from, en_passant_target)
+                {
+                    return true;
+                }
+            }
+            false
+        })
+        .collect()
+}
+
+// Tests whether neither side can checkmate by any sequence of legal moves. Recognizes only classic
+// cases: bare kings, a single minor piece, or any number of bishops all on squares of the same
+// color. Conservative: any other piece (including fairy pieces and the duck) means the position is
+// not considered dead. Only applies to boards without reserves, i.e. not to bughouse.
+fn is_insufficient_material(grid: &Grid) -> bool {
+    let mut minor_pieces = vec![];
+    for coord in grid.shape().coords() {
+        if let Some(piece) = grid[coord] {
+            match piece.kind {
+                PieceKind::King => {}
+                PieceKind::Knight | PieceKind::Bishop => minor_pieces.push((piece.kind, coord)),
+                _ => return false,
+            }
+        }
+    }
+    let square_color = |coord: Coord| (coord.row.to_zero_based() + coord.col.to_zero_based()) % 2;
+    match minor_pieces.as_slice() {
+        [] | [_] => true,
+        [(_, first), rest @ ..] => {
+            minor_pieces.iter().all(|&(kind, _)| kind == PieceKind::Bishop)
+                && rest.iter().all(|&(_, coord)| square_color(coord) == square_color(*first))
+        }
+    }
+}
+
 fn reachability(
     rules: &ChessRules, grid: &Grid, from: Coord, to: Coord, capturing: bool,
 ) -> Reachability {
@@ -349,6 +467,21 @@ fn is_reachable_for_premove(rules: &ChessRules, grid: &Grid, from: Coord, to: Co
     }
 }
 
+// Pre-drops may target a square occupied by an enemy piece: it could be moved away before the
+// pre-drop is executed. A square occupied by our own piece cannot be vacated in time, unless an
+// earlier preturn has already moved the piece away, which is reflected in `grid`.
+fn is_droppable_for_predrop(
+    rules: &ChessRules, grid: &Grid, piece: PieceOnBoard, to: Coord,
+) -> bool {
+    match grid[to] {
+        None => true,
+        Some(dst_piece) if dst_piece.force == piece.force => {
+            combine_pieces(rules, PieceId::tmp(), piece, dst_piece).is_some()
+        }
+        Some(_) => true,
+    }
+}
+
 // Tests that the piece can move in such a way and that the path is free.
 // Does not support castling.
 fn generic_reachability(
@@ -515,12 +648,15 @@ fn piece_to_captured(pos: Coord, piece: PieceOnBoard) -> impl Iterator<Item = Ca
 }
 
 fn initial_castling_rights(
-    fairy_pieces: FairyPieces, starting_position: &EffectiveStartingPosition,
+    rules: &ChessRules, starting_position: &EffectiveStartingPosition,
 ) -> EnvoyCastlingRights {
-    let row = starting_piece_row(fairy_pieces, starting_position);
+    let mut rights = enum_map! { _ => None };
+    if rules.castling == Castling::Forbidden {
+        return rights;
+    }
+    let row = starting_piece_row(rules.fairy_pieces, starting_position);
     let king_pos = row.iter().position(|&p| p == PieceKind::King).unwrap();
     let king_col = Col::from_zero_based(king_pos.try_into().unwrap());
-    let mut rights = enum_map! { _ => None };
     for (col, &piece) in row.iter().enumerate() {
         let col = Col::from_zero_based(col.try_into().unwrap());
         if piece == PieceKind::Rook {
@@ -563,6 +699,15 @@ pub enum Reachability {
     Impossible,
 }
 
+// Absolute pin: `pinned` cannot leave the line between `pinner` and `king` without exposing the
+// king. The pinned piece may still move along the line, including capturing the pinner.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Pin {
+    pub pinned: Coord,
+    pub pinner: Coord,
+    pub king: Coord,
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct CastlingRelocations {
     pub king: (Coord, Coord),
@@ -712,8 +857,10 @@ pub enum TurnMode {
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum VictoryReason {
-    Checkmate, // means "checkmake", "king lost" (if regicide) or "all kings lost" (if Koedem)
+    Checkmate,
+    KingCaptured, // for regicide variants; means "all kings lost" if Koedem
     Flag,
+    SimultaneousFlagMaterial, // for bughouse with `SimultaneousFlagPolicy::MaterialAdvantage`
     Resignation,
     UnknownVictory, // for parsing PGN
 }
@@ -723,7 +870,11 @@ pub enum DrawReason {
     SimultaneousCheckmate, // for atomic chess
     SimultaneousFlag,      // for bughouse
     ThreefoldRepetition,
-    UnknownDraw, // for parsing PGN
+    InsufficientMaterial, // for chess without drops
+    Stalemate,            // for chess or bughouse with `Stalemate::Draw`
+    Aborted,              // by agreement early in the game; the game does not count
+    SplitBoards,          // for bughouse with board resignation: each team won a board
+    UnknownDraw,          // for parsing PGN
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -751,16 +902,20 @@ pub enum TurnError {
     UnprotectedKing,
     CastlingPieceHasMoved,
     CannotCastleDroppedKing,
+    CastlingForbidden,
     BadPromotionType,
     MustPromoteHere,
     CannotPromoteHere,
     InvalidUpgradePromotionTarget,
     InvalidStealPromotionTarget,
     DropRequiresBughouse,
+    DropsNotYetAllowed,
     DropPieceMissing,
     InvalidPawnDropRank,
     DropBlocked,
     DropAggression,
+    InterposingDrop,
+    PieceCapExceeded,
     StealTargetMissing,
     StealTargetInvalid,
     ExposingKingByStealing,
@@ -782,6 +937,16 @@ pub enum TurnError {
     WaybackIsActive,
 }
 
+// Returned by `Board::disambiguate_san`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AmbiguityError {
+    // Several pieces can legally make the move. Contains their starting squares: the notation
+    // must include a file and/or a rank to tell them apart.
+    Ambiguous(Vec<Coord>),
+    // No piece can make the move.
+    Illegal(TurnError),
+}
+
 pub type Reserve = EnumMap<PieceKind, u8>;
 
 pub type EnvoyCastlingRights = EnumMap<CastleDirection, Option<Col>>;
@@ -832,6 +997,10 @@ pub struct Board {
     castling_rights: BoardCastlingRights,
     en_passant_target: Option<Coord>,
     reserves: EnumMap<Force, Reserve>,
+    // How many more pieces of each force can be captured here under `PieceCapPolicy::Refuse`,
+    // given that captured pieces go to the other board. `None` means no limit. Maintained by
+    // `BughouseGame`, see `reserve_room`.
+    capture_room: EnumMap<Force, Option<u32>>,
     total_drops: u32, // total number of drops from both sides
     position_count: HashMap<PositionForRepetitionDraw, u32>,
     clock: Clock,
@@ -848,8 +1017,7 @@ impl Board {
         let mut next_piece_id = PieceId::new();
         let grid =
             generate_starting_grid(&rules.chess_rules, starting_position, &mut next_piece_id);
-        let each_castling_rights =
-            initial_castling_rights(rules.chess_rules.fairy_pieces, starting_position);
+        let each_castling_rights = initial_castling_rights(&rules.chess_rules, starting_position);
         let castling_rights = enum_map! { _ => each_castling_rights };
         let reserves = enum_map! { _ => enum_map!{ _ => 0 } };
         let setup = BoardSetup {
@@ -880,6 +1048,10 @@ impl Board {
                 reserves[Force::White][PieceKind::Duck] = 1;
             }
         }
+        let mut castling_rights = setup.castling_rights;
+        if rules.chess_rules.castling == Castling::Forbidden {
+            castling_rights = BoardCastlingRights::default();
+        }
         let mut board = Board {
             rules,
             role,
@@ -887,9 +1059,10 @@ impl Board {
             status: ChessGameStatus::Active,
             grid: setup.grid,
             next_piece_id: setup.next_piece_id,
-            castling_rights: setup.castling_rights,
+            castling_rights,
             en_passant_target: setup.en_passant_target,
             reserves,
+            capture_room: enum_map! { _ => None },
             total_drops: 0,
             position_count: HashMap::new(),
             clock: Clock::new(time_control, time_measurement),
@@ -938,18 +1111,76 @@ impl Board {
     pub fn castling_rights(&self) -> &BoardCastlingRights { &self.castling_rights }
     pub fn en_passant_target(&self) -> Option<Coord> { self.en_passant_target }
     pub fn reserve(&self, force: Force) -> &Reserve { &self.reserves[force] }
+    // Pieces on the board and in reserve. This is what `PieceCap` limits.
+    pub fn num_pieces(&self, force: Force) -> u32 {
+        let on_board = self
+            .grid
+            .shape()
+            .coords()
+            .filter(|&pos| self.grid[pos].is_some_and(|p| p.force == force.into()))
+            .count() as u32;
+        let in_reserve = self.reserves[force].values().map(|&n| u32::from(n)).sum::<u32>();
+        on_board + in_reserve
+    }
+    // How many more pieces of each force this board can receive under `PieceCapPolicy::Refuse`.
+    pub fn reserve_room(&self) -> EnumMap<Force, Option<u32>> {
+        let max_pieces = self
+            .bughouse_rules()
+            .and_then(|r| r.piece_cap)
+            .filter(|cap| cap.policy == PieceCapPolicy::Refuse)
+            .map(|cap| u32::from(cap.max_pieces));
+        enum_map! { force => max_pieces.map(|max| max.saturating_sub(self.num_pieces(force))) }
+    }
+    pub fn set_capture_room(&mut self, capture_room: EnumMap<Force, Option<u32>>) {
+        self.capture_room = capture_room;
+    }
     pub fn reserve_mut(&mut self, force: Force) -> &mut Reserve { &mut self.reserves[force] }
     pub fn reserves(&self) -> &EnumMap<Force, Reserve> { &self.reserves }
     pub fn clock(&self) -> &Clock { &self.clock }
     pub fn clock_mut(&mut self) -> &mut Clock { &mut self.clock }
     pub fn full_turn_index(&self) -> u32 { self.full_turn_index }
     pub fn active_force(&self) -> Force { self.active_force }
+    // Gives the turn to `force` without making a move. Used in study mode, where turn order is
+    // not enforced. En passant is only possible immediately after the pawn move, so passing the
+    // turn voids it.
+    pub fn set_active_force(&mut self, force: Force) {
+        if force != self.active_force {
+            self.active_force = force;
+            self.en_passant_target = None;
+        }
+    }
     pub fn is_duck_turn(&self, force: Force) -> bool { self.is_duck_turn[force] }
     pub fn duck_position(&self) -> Option<Coord> {
         find_piece(&self.grid, |p| p.kind == PieceKind::Duck)
     }
 
     pub fn is_bughouse(&self) -> bool { self.bughouse_rules().is_some() }
+    // Whether the board is still in the warmup phase when drops are forbidden.
+    pub fn is_no_drop_phase(&self) -> bool {
+        self.bughouse_rules()
+            .is_some_and(|bughouse_rules| self.full_turn_index <= bughouse_rules.no_drop_turns)
+    }
+    // Total value of the pieces on the board and in reserve, see `PieceKind::value`.
+    pub fn material(&self, force: Force) -> u32 {
+        let rules = self.chess_rules();
+        let on_board: u32 = self
+            .grid
+            .shape()
+            .coords()
+            .filter_map(|coord| self.grid[coord])
+            .filter(|piece| piece.force == force.into())
+            .map(|piece| piece.kind.value(rules))
+            .sum();
+        let in_reserve: u32 = self.reserves[force]
+            .iter()
+            .map(|(kind, &amount)| kind.value(rules) * amount as u32)
+            .sum();
+        on_board + in_reserve
+    }
+    // Positive if White is ahead in material.
+    pub fn material_balance(&self) -> i32 {
+        self.material(Force::White) as i32 - self.material(Force::Black) as i32
+    }
     pub fn turn_owner(&self, mode: TurnMode) -> Force {
         match mode {
             TurnMode::InOrder | TurnMode::Virtual => self.active_force,
@@ -960,7 +1191,7 @@ impl Board {
     pub fn reset_threefold_repetition_draw(&mut self) { self.position_count.clear(); }
 
     pub fn start_clock(&mut self, now: GameInstant) {
-        if !self.clock.is_active() {
+        if self.status == ChessGameStatus::Active && !self.clock.is_active() {
             self.clock.new_turn(self.active_force, now);
         }
     }
@@ -977,6 +1208,12 @@ impl Board {
                 ChessGameStatus::Victory(self.active_force.opponent(), VictoryReason::Flag);
         }
     }
+    // Concedes this board only. See `BughouseGame::resign_board`.
+    pub fn resign(&mut self, force: Force, now: GameInstant) {
+        assert_eq!(self.status, ChessGameStatus::Active);
+        self.status = ChessGameStatus::Victory(force.opponent(), VictoryReason::Resignation);
+        self.clock.stop(now);
+    }
 
     // Checks whether a turn is legal, including check and mate related conditions.
     pub fn is_turn_legal(&self, turn: Turn, mode: TurnMode) -> bool {
@@ -1012,6 +1249,70 @@ impl Board {
 
     pub fn find_king(&self, force: Force) -> Option<Coord> { find_king(&self.grid, force) }
 
+    // Always false in regicide variants: there are no checks there.
+    pub fn is_in_check(&self, force: Force) -> bool {
+        if self.chess_rules().regicide() {
+            return false;
+        }
+        self.find_king(force)
+            .is_some_and(|king_pos| is_check_to(self.chess_rules(), &self.grid, king_pos))
+    }
+
+    // Returns all pieces of force `by` that attack `pos` or, if `pos` is occupied by a piece of the
+    // same force, defend it. Check and mate are not taken into account.
+    pub fn attackers_of(&self, pos: Coord, by: PieceForce) -> Vec<Coord> {
+        attackers_of(&self.grid, pos, by, self.en_passant_target)
+    }
+
+    // Returns absolute pins for both sides. A piece is pinned if removing it from the board would
+    // expose the king to an attacker. Always empty in regicide variants: without checks, shielding
+    // the king is not mandatory. This includes fog of war, so pins never reveal hidden pieces.
+    pub fn pinned_pieces(&self) -> Vec<Pin> {
+        if self.chess_rules().regicide() {
+            return vec![];
+        }
+        let mut pins = vec![];
+        for force in Force::iter() {
+            let Some(king) = self.find_king(force) else {
+                continue;
+            };
+            let king_attackers =
+                |grid: &Grid| attackers_of(grid, king, force.opponent().into(), None);
+            let direct_attackers = king_attackers(&self.grid);
+            for pos in self.shape().coords() {
+                let Some(piece) = self.grid[pos] else {
+                    continue;
+                };
+                if piece.force != force.into() || piece.kind == PieceKind::King {
+                    continue;
+                }
+                let mut grid = self.grid.clone();
+                grid[pos] = None;
+                for pinner in king_attackers(&grid) {
+                    if !direct_attackers.contains(&pinner) {
+                        pins.push(Pin { pinned: pos, pinner, king });
+                    }
+                }
+            }
+        }
+        pins
+    }
+
+    // Drops may only use pieces actually present in the reserve. This guards against the client
+    // and the server disagreeing on reserve contents. Virtual and preturn drops may rely on pieces
+    // that will be captured on the other board later; such turns are re-checked when executed.
+    fn verify_drop_available(
+        &self, force: Force, piece_kind: PieceKind, mode: TurnMode,
+    ) -> Result<(), TurnError> {
+        if self.reserves[force][piece_kind] > 0 {
+            return Ok(());
+        }
+        match mode {
+            TurnMode::InOrder => Err(TurnError::DropPieceMissing),
+            TurnMode::Virtual | TurnMode::Preturn => Ok(()),
+        }
+    }
+
     pub fn destination_reachability(&self, from: Coord, to: Coord, mode: TurnMode) -> Reachability {
         match mode {
             TurnMode::InOrder | TurnMode::Virtual => {
@@ -1036,7 +1337,12 @@ impl Board {
         //   - Return two separate lists: in-order turn moves + preturn moves.
         let mut ret =
             move_destinations(self.chess_rules(), &self.grid, from, self.en_passant_target);
-        ret.extend(castling_destinations(&self.grid, from, &self.castling_rights));
+        ret.extend(castling_destinations(
+            self.chess_rules(),
+            &self.grid,
+            from,
+            &self.castling_rights,
+        ));
         ret
     }
 
@@ -1074,7 +1380,12 @@ impl Board {
                 ret.push(Turn::Move(TurnMove { from, to, promote_to }));
             }
             if piece.kind == PieceKind::King {
-                ret.extend(castling_moves(&self.castling_rights[force]));
+                ret.extend(castling_moves(
+                    self.chess_rules(),
+                    &self.grid,
+                    from,
+                    &self.castling_rights[force],
+                ));
             }
         }
         ret
@@ -1110,6 +1421,153 @@ impl Board {
         ret
     }
 
+    // Whether `force` is not in check, but has no legal moves or drops. Always false in regicide
+    // variants: the king is allowed to walk into an attack there. A pawn that could promote by
+    // stealing counts as a legal move, since it depends on the other board.
+    pub fn is_stalemate(&self, force: Force) -> bool {
+        if self.chess_rules().regicide() || self.is_in_check(force) {
+            return false;
+        }
+        let mut board = self.clone();
+        board.set_active_force(force);
+        if board.chess_rules().promotion() == Promotion::Steal {
+            let last_row = SubjectiveRow::last(board.shape()).to_row(board.shape(), force);
+            let can_promote = board.shape().coords().any(|from| {
+                board.grid[from]
+                    .is_some_and(|p| p.kind == PieceKind::Pawn && p.force == force.into())
+                    && move_destinations(
+                        board.chess_rules(),
+                        &board.grid,
+                        from,
+                        board.en_passant_target,
+                    )
+                    .into_iter()
+                    .any(|to| to.row == last_row)
+            });
+            if can_promote {
+                return false;
+            }
+        }
+        let mut potential_turns = board.potential_moves();
+        potential_turns.extend(board.potential_drops());
+        !potential_turns
+            .into_iter()
+            .any(|turn| board.is_turn_legal(turn, TurnMode::InOrder))
+    }
+
+    // Returns all legal moves and drops by `force` that give check. Shares the limitations of
+    // `potential_moves` and `potential_drops`. Always empty in regicide variants, including fog of
+    // war, since there are no checks there.
+    pub fn checking_moves(&self, force: Force) -> Vec<Turn> {
+        if self.chess_rules().regicide() {
+            return vec![];
+        }
+        let mut board = self.clone();
+        board.set_active_force(force);
+        let Some(opponent_king_pos) = board.find_king(force.opponent()) else {
+            return vec![];
+        };
+        let mut potential_turns = board.potential_moves();
+        potential_turns.extend(board.potential_drops());
+        potential_turns
+            .into_iter()
+            .filter(|&turn| {
+                let Ok(TurnOutcome { new_grid, .. }) = board.turn_outcome(turn, TurnMode::InOrder)
+                else {
+                    return false;
+                };
+                is_check_to(board.chess_rules(), &new_grid, opponent_king_pos)
+            })
+            .collect()
+    }
+
+    // Returns all legal drops by `force` that get its king out of check. A drop cannot capture the
+    // checking piece, so these are always interpositions; in particular, there are none if the
+    // king is attacked by a contact piece or by several pieces at once. Shares the limitations of
+    // `potential_drops`. Always empty in regicide variants, including fog of war, since there are
+    // no checks there.
+    pub fn check_relieving_drops(&self, force: Force) -> Vec<Turn> {
+        if self.chess_rules().regicide() {
+            return vec![];
+        }
+        let Some(king_pos) = self.find_king(force) else {
+            return vec![];
+        };
+        let checkers = attacker_set(self.chess_rules(), &self.grid, king_pos, None)
+            .into_iter()
+            .filter(|&from| self.grid[from].is_some_and(|p| p.force == force.opponent().into()))
+            .count();
+        if checkers != 1 {
+            return vec![];
+        }
+        let mut board = self.clone();
+        board.set_active_force(force);
+        board
+            .potential_drops()
+            .into_iter()
+            .filter(|&turn| board.is_turn_legal(turn, TurnMode::InOrder))
+            .collect()
+    }
+
+    // Returns whether the active force can force a repetition by giving check on every turn, no
+    // matter how the opponent responds. Looks at most `max_checks` checks ahead. Lines where the
+    // opponent is mated or left without legal turns also count. Analysis only: doesn't affect the
+    // game. Bughouse interactions are ignored: reserves are not replenished by partner captures.
+    // Shares the limitations of `checking_moves`; always false in duck chess and with stealing
+    // promotion.
+    pub fn can_force_perpetual_check(&self, max_checks: u32) -> bool {
+        if self.status != ChessGameStatus::Active
+            || self.chess_rules().duck_chess
+            || self.chess_rules().promotion() == Promotion::Steal
+        {
+            return false;
+        }
+        let mut positions = vec![self.position_for_repetition_draw()];
+        self.forces_perpetual_check(self.active_force, max_checks, &mut positions)
+    }
+
+    fn forces_perpetual_check(
+        &self, attacker: Force, checks_left: u32, positions: &mut Vec<PositionForRepetitionDraw>,
+    ) -> bool {
+        if checks_left == 0 {
+            return false;
+        }
+        // Only the order of turns matters here, so keep the clock still.
+        let now = self.clock.turn_start().unwrap_or(GameInstant::game_start());
+        let resolution = |board: &Board| match board.status {
+            ChessGameStatus::Active => None,
+            ChessGameStatus::Victory(force, _) => Some(force == attacker),
+            ChessGameStatus::Draw(reason) => Some(reason == DrawReason::ThreefoldRepetition),
+        };
+        self.checking_moves(attacker).into_iter().any(|check| {
+            let mut board = self.clone();
+            board.try_turn(check, TurnMode::InOrder, now).unwrap();
+            if let Some(attacker_succeeded) = resolution(&board) {
+                return attacker_succeeded;
+            }
+            let mut replies = board.potential_moves();
+            replies.extend(board.potential_drops());
+            replies
+                .into_iter()
+                .filter(|&reply| board.is_turn_legal(reply, TurnMode::InOrder))
+                .all(|reply| {
+                    let mut board = board.clone();
+                    board.try_turn(reply, TurnMode::InOrder, now).unwrap();
+                    if let Some(attacker_succeeded) = resolution(&board) {
+                        return attacker_succeeded;
+                    }
+                    let position = board.position_for_repetition_draw();
+                    if positions.contains(&position) {
+                        return true;
+                    }
+                    positions.push(position);
+                    let ret = board.forces_perpetual_check(attacker, checks_left - 1, positions);
+                    positions.pop();
+                    ret
+                })
+        })
+    }
+
     pub fn castling_relocation(
         &self, force: Force, dir: CastleDirection,
     ) -> Option<(Coord, Coord)> {
@@ -1160,12 +1618,21 @@ impl Board {
             if let Some(piece) = self.grid[from] {
                 if piece.force == force.into() {
                     ret.insert(from);
-                    ret.extend(visibility_from(
-                        self.chess_rules(),
-                        &self.grid,
-                        from,
-                        self.en_passant_target,
-                    ))
+                    let visibility = self.chess_rules().fog_of_war_visibility;
+                    if let FogOfWarVisibility::Movement | FogOfWarVisibility::Hybrid(_) = visibility
+                    {
+                        ret.extend(visibility_from(
+                            self.chess_rules(),
+                            &self.grid,
+                            from,
+                            self.en_passant_target,
+                        ));
+                    }
+                    if let FogOfWarVisibility::Radius(radius) | FogOfWarVisibility::Hybrid(radius) =
+                        visibility
+                    {
+                        ret.extend(visibility_within_radius(self.shape(), from, radius));
+                    }
                 } else if piece.force == PieceForce::Neutral {
                     ret.insert(from);
                 }
@@ -1174,11 +1641,8 @@ impl Board {
         ret
     }
 
-    fn log_position_for_repetition_draw(&mut self) {
-        if self.role == Role::Client {
-            return;
-        }
-        let position_for_repetition_draw = PositionForRepetitionDraw {
+    fn position_for_repetition_draw(&self) -> PositionForRepetitionDraw {
+        PositionForRepetitionDraw {
             grid: self
                 .grid
                 .map(|piece| PieceForRepetitionDraw { kind: piece.kind, force: piece.force }),
@@ -1186,7 +1650,14 @@ impl Board {
             castling_rights: self.castling_rights,
             en_passant_target: self.en_passant_target,
             total_drops: self.total_drops,
-        };
+        }
+    }
+
+    fn log_position_for_repetition_draw(&mut self) {
+        if self.role == Role::Client {
+            return;
+        }
+        let position_for_repetition_draw = self.position_for_repetition_draw();
         let num_repetition = self.position_count.entry(position_for_repetition_draw).or_insert(0);
         *num_repetition += 1;
         if *num_repetition >= 3 {
@@ -1194,6 +1665,32 @@ impl Board {
         }
     }
 
+    fn test_insufficient_material(&mut self) {
+        // In bughouse the partner can always pass more material. In regicide variants a king can
+        // be lost by walking into an attack, so the position is never dead.
+        if self.status != ChessGameStatus::Active
+            || self.is_bughouse()
+            || self.chess_rules().regicide()
+        {
+            return;
+        }
+        if is_insufficient_material(&self.grid) {
+            self.status = ChessGameStatus::Draw(DrawReason::InsufficientMaterial);
+        }
+    }
+
+    fn test_stalemate(&mut self) {
+        // In bughouse the partner could pass a piece to drop, so by default the player just waits.
+        if self.status != ChessGameStatus::Active
+            || self.bughouse_rules().is_some_and(|r| r.stalemate != Stalemate::Draw)
+        {
+            return;
+        }
+        if self.is_stalemate(self.active_force) {
+            self.status = ChessGameStatus::Draw(DrawReason::Stalemate);
+        }
+    }
+
     fn update_turn_stage_and_active_force(&mut self, mode: TurnMode) {
         let force = self.turn_owner(mode);
         let next_active_force = match mode {
@@ -1264,12 +1761,15 @@ impl Board {
                 }
                 if !self.chess_rules().regicide() {
                     let opponent_king_pos = find_king(&self.grid, force.opponent()).unwrap();
-                    if self.is_bughouse() {
+                    // Bughouse mate takes into account that the opponent could block the check
+                    // with a drop later. This doesn't apply if interposing drops are forbidden.
+                    if self.bughouse_rules().is_some_and(|r| !r.no_interposing_drops) {
                         if is_bughouse_mate_to(
                             &self.rules.chess_rules,
                             &mut self.grid,
                             opponent_king_pos,
                             self.en_passant_target,
+                            self.capture_room,
                         ) {
                             self.status = ChessGameStatus::Victory(force, VictoryReason::Checkmate);
                         }
@@ -1279,6 +1779,7 @@ impl Board {
                             &mut self.grid,
                             opponent_king_pos,
                             self.en_passant_target,
+                            self.capture_room,
                         ) {
                             self.status = ChessGameStatus::Victory(force, VictoryReason::Checkmate);
                         }
@@ -1299,7 +1800,7 @@ impl Board {
                                 let loser: Force = captured_kings[0].force.try_into().unwrap();
                                 self.status = ChessGameStatus::Victory(
                                     loser.opponent(),
-                                    VictoryReason::Checkmate,
+                                    VictoryReason::KingCaptured,
                                 );
                             }
                             2 => {
@@ -1312,9 +1813,15 @@ impl Board {
                         }
                     }
                 }
+                let full_turn_index = self.full_turn_index;
                 self.update_turn_stage_and_active_force(mode);
                 self.clock.new_turn(self.active_force, now);
+                if self.active_force != force {
+                    self.clock.add_increment(force, full_turn_index);
+                }
                 self.log_position_for_repetition_draw();
+                self.test_insufficient_material();
+                self.test_stalemate();
             }
             TurnMode::Preturn => {
                 self.en_passant_target = None;
@@ -1331,6 +1838,11 @@ impl Board {
             }
             TurnMode::Preturn => {}
         }
+        match mode {
+            // The other board may change before virtual turns and preturns are executed.
+            TurnMode::InOrder => self.verify_capture_room(&outcome.facts)?,
+            TurnMode::Virtual | TurnMode::Preturn => {}
+        }
         Ok(outcome)
     }
 
@@ -1350,6 +1862,15 @@ impl Board {
         }
         if let Turn::Drop(_) = turn {
             let bughouse_rules = self.bughouse_rules().unwrap(); // unwrap ok: tested earlier
+            if bughouse_rules.no_interposing_drops {
+                // The drop didn't leave the king in check, so it's interposing if the king was in
+                // check before.
+                if let Some(old_king_pos) = find_king(&self.grid, force)
+                    && is_check_to(self.chess_rules(), &self.grid, old_king_pos)
+                {
+                    return Err(TurnError::InterposingDrop);
+                }
+            }
             let drop_legal = match bughouse_rules.drop_aggression {
                 DropAggression::NoCheck => {
                     !is_check_to(self.chess_rules(), new_grid, opponent_king_pos)
@@ -1359,12 +1880,25 @@ impl Board {
                     new_grid,
                     opponent_king_pos,
                     self.en_passant_target,
+                    self.capture_room,
                 ),
+                // Without interposing drops, bughouse mate is the same as chess mate. See also the
+                // game status check in `apply_turn`.
+                DropAggression::NoBughouseMate if bughouse_rules.no_interposing_drops => {
+                    !is_chess_mate_to(
+                        self.chess_rules(),
+                        new_grid,
+                        opponent_king_pos,
+                        self.en_passant_target,
+                        self.capture_room,
+                    )
+                }
                 DropAggression::NoBughouseMate => !is_bughouse_mate_to(
                     self.chess_rules(),
                     new_grid,
                     opponent_king_pos,
                     self.en_passant_target,
+                    self.capture_room,
                 ),
                 DropAggression::MateAllowed => true,
             };
@@ -1375,6 +1909,17 @@ impl Board {
         Ok(())
     }
 
+    fn verify_capture_room(&self, facts: &TurnFacts) -> Result<(), TurnError> {
+        for force in Force::iter() {
+            let num_captured =
+                facts.captures.iter().filter(|c| c.force == force.into()).count() as u32;
+            if self.capture_room[force].is_some_and(|room| num_captured > room) {
+                return Err(TurnError::PieceCapExceeded);
+            }
+        }
+        Ok(())
+    }
+
     fn turn_outcome_no_check_test(
         &self, turn: Turn, mode: TurnMode,
     ) -> Result<TurnOutcome, TurnError> {
@@ -1564,18 +2109,22 @@ impl Board {
                 if self.is_duck_turn[force] {
                     return Err(TurnError::MustPlaceDuck);
                 }
+                if self.is_no_drop_phase() {
+                    match mode {
+                        TurnMode::InOrder | TurnMode::Virtual => {
+                            return Err(TurnError::DropsNotYetAllowed);
+                        }
+                        // Will be checked again when the preturn is executed.
+                        TurnMode::Preturn => {}
+                    }
+                }
                 let to_subjective_row = SubjectiveRow::from_row(self.shape(), drop.to.row, force);
                 if drop.piece_kind == PieceKind::Pawn
                     && !bughouse_rules.pawn_drop_ranks.contains(to_subjective_row)
                 {
                     return Err(TurnError::InvalidPawnDropRank);
                 }
-                if self.reserves[force][drop.piece_kind] < 1 {
-                    match mode {
-                        TurnMode::InOrder => return Err(TurnError::DropPieceMissing),
-                        TurnMode::Virtual | TurnMode::Preturn => {}
-                    }
-                }
+                self.verify_drop_available(force, drop.piece_kind, mode)?;
                 let piece_force = drop.piece_kind.reserve_piece_force(force);
                 let mut new_piece = PieceOnBoard::new(
                     next_piece_id.inc(),
@@ -1593,7 +2142,11 @@ impl Board {
                             TurnMode::InOrder | TurnMode::Virtual => {
                                 return Err(TurnError::DropBlocked);
                             }
-                            TurnMode::Preturn => {}
+                            TurnMode::Preturn => {
+                                if !is_droppable_for_predrop(rules, &new_grid, new_piece, drop.to) {
+                                    return Err(TurnError::DropBlocked);
+                                }
+                            }
                         }
                     }
                 }
@@ -1605,9 +2158,7 @@ impl Board {
                 //   - Castle successful.
                 //   - Cannot castle when king has moved.
                 //   - Cannot castle when rook has moved.
-                //   - Cannot castle when there are pieces in between.
                 //   - King cannot starts in a checked square.
-                //   - King cannot pass through a checked square.
                 //   - King cannot ends up in a checked square.
                 //   - Cannot castle if rook was captured and another one was
                 //     dropped on its place.
@@ -1617,6 +2168,9 @@ impl Board {
                 //      both when it's possible (the other rook is further away)
                 //      and impossible (the other rook is in the way).
 
+                if rules.castling == Castling::Forbidden {
+                    return Err(TurnError::CastlingForbidden);
+                }
                 if self.is_duck_turn[force] {
                     return Err(TurnError::MustPlaceDuck);
                 }
@@ -1640,34 +2194,26 @@ impl Board {
                 let rook = new_grid[rook_from].take();
                 assert!(matches!(rook, Some(PieceOnBoard { kind: PieceKind::Rook, .. })));
 
-                let (king_to_col, rook_to_col) = match rules.fairy_pieces {
-                    FairyPieces::NoFairy | FairyPieces::Accolade => match dir {
-                        CastleDirection::ASide => (Col::C, Col::D),
-                        CastleDirection::HSide => (Col::G, Col::F),
-                    },
-                    FairyPieces::Capablanca => match dir {
-                        CastleDirection::ASide => (Col::C, Col::D),
-                        CastleDirection::HSide => (Col::I, Col::H),
-                    },
-                };
+                let (king_to_col, rook_to_col) = castling_target_cols(rules, dir);
                 let king_to = Coord::new(row, king_to_col);
                 let rook_to = Coord::new(row, rook_to_col);
 
                 match mode {
                     TurnMode::InOrder | TurnMode::Virtual => {
-                        let cols = [king_from.col, king_to.col, rook_from.col, rook_to.col];
-                        for col in col_range_inclusive(iter_minmax(cols.into_iter()).unwrap()) {
-                            if new_grid[Coord::new(row, col)].is_some() {
-                                return Err(TurnError::PathBlocked);
-                            }
+                        if !is_castling_path_clear(rules, &new_grid, king_from, rook_col, dir) {
+                            return Err(TurnError::PathBlocked);
                         }
 
-                        let cols = [king_from.col, king_to.col];
-                        for col in col_range_inclusive(iter_minmax(cols.into_iter()).unwrap()) {
-                            let pos = Coord::new(row, col);
-                            let new_grid = new_grid.scoped_set(pos, king);
-                            if !rules.regicide() && is_check_to(rules, &new_grid, pos) {
-                                return Err(TurnError::UnprotectedKing);
+                        // The final square is always verified: either here or in
+                        // `verify_check_and_drop_aggression`.
+                        if !rules.regicide() && !rules.castling_through_check {
+                            let cols = [king_from.col, king_to.col];
+                            for col in col_range_inclusive(iter_minmax(cols.into_iter()).unwrap()) {
+                                let pos = Coord::new(row, col);
+                                let new_grid = new_grid.scoped_set(pos, king);
+                                if is_check_to(rules, &new_grid, pos) {
+                                    return Err(TurnError::UnprotectedKing);
+                                }
                             }
                         }
                     }
@@ -1739,6 +2285,11 @@ impl Board {
         Ok(TurnOutcome { new_grid, facts })
     }
 
+    // Determines what the turn would do without applying it. Prefer `try_turn` when possible.
+    pub fn turn_facts(&self, turn: Turn, mode: TurnMode) -> Result<TurnFacts, TurnError> {
+        Ok(self.turn_outcome(turn, mode)?.facts)
+    }
+
     // Tells whether `turn` can be executed on the other board.
     pub fn verify_sibling_turn(
         &self, turn: Turn, mode: TurnMode, turn_owner: Force,
@@ -1776,10 +2327,20 @@ impl Board {
 
     // Applies changes caused by the turn on the other board.
     pub fn apply_sibling_turn(&mut self, facts: &TurnFacts, mode: TurnMode) {
+        let discard_above = self
+            .bughouse_rules()
+            .and_then(|r| r.piece_cap)
+            .filter(|cap| cap.policy == PieceCapPolicy::Discard)
+            .map(|cap| u32::from(cap.max_pieces));
         for capture in &facts.captures {
             assert!(capture.piece_kind.reservable(self.chess_rules()) != PieceReservable::Never);
             // Unwrap ok: duck cannot be captured.
             let force = capture.force.try_into().unwrap();
+            if let Some(max_pieces) = discard_above
+                && self.num_pieces(force) >= max_pieces
+            {
+                continue;
+            }
             self.reserves[force][capture.piece_kind] += 1;
         }
         for steal in &facts.steals {
@@ -1814,8 +2375,12 @@ impl Board {
                     let onto_rook = self.grid[mv.to].is_some_and(|dst_piece| {
                         dst_piece.force == force.into() && dst_piece.kind == PieceKind::Rook
                     });
+                    let castling_gesture = match self.chess_rules().castling {
+                        Castling::Allowed | Castling::Forbidden => (d_col.abs() >= 2) || onto_rook,
+                        Castling::KingMoveOnly => (d_col.abs() >= 2) && !onto_rook,
+                    };
                     let is_castling = piece.kind == PieceKind::King
-                        && ((d_col.abs() >= 2) || onto_rook)
+                        && castling_gesture
                         && (mv.from.row == first_row && mv.to.row == first_row);
                     if is_castling {
                         if piece.origin == PieceOrigin::Innate {
@@ -1963,6 +2528,63 @@ impl Board {
         }
     }
 
+    // Matches algebraic notation to a turn of the active player. Unlike `algebraic_to_turn`, this
+    // follows SAN semantics for disambiguation: only legal moves are taken into account, so e.g.
+    // a pinned knight doesn't make the notation ambiguous.
+    pub fn disambiguate_san(&self, san: &str) -> Result<Turn, AmbiguityError> {
+        let mode = TurnMode::InOrder;
+        let legal_turn = |algebraic: &AlgebraicTurn| -> Result<Turn, TurnError> {
+            let turn = self.algebraic_to_turn(algebraic, mode, None)?;
+            self.turn_outcome(turn, mode)?;
+            Ok(turn)
+        };
+        let algebraic =
+            AlgebraicTurn::parse(san).ok_or(AmbiguityError::Illegal(TurnError::InvalidNotation))?;
+        let AlgebraicTurn::Move(mv) = &algebraic else {
+            return legal_turn(&algebraic).map_err(AmbiguityError::Illegal);
+        };
+        let force = self.active_force();
+        let mut candidates = vec![];
+        let mut errors = vec![];
+        for from in self.shape().coords() {
+            let Some(piece) = self.grid[from] else {
+                continue;
+            };
+            if piece.force != force.into()
+                || piece.kind != mv.piece_kind
+                || mv.from_row.is_some_and(|row| row != from.row)
+                || mv.from_col.is_some_and(|col| col != from.col)
+            {
+                continue;
+            }
+            let explicit_mv = AlgebraicTurn::Move(AlgebraicMove {
+                from_row: Some(from.row),
+                from_col: Some(from.col),
+                ..mv.clone()
+            });
+            match legal_turn(&explicit_mv) {
+                Ok(turn) => candidates.push((from, turn)),
+                Err(err) => errors.push(err),
+            }
+        }
+        match candidates.as_slice() {
+            [] => {
+                // Report the error for the piece that came closest to making the move.
+                let error_rank = |err: &TurnError| match err {
+                    TurnError::ImpossibleTrajectory => 0,
+                    TurnError::PathBlocked => 1,
+                    _ => 2,
+                };
+                let err = errors.into_iter().max_by_key(error_rank);
+                Err(AmbiguityError::Illegal(err.unwrap_or(TurnError::PieceMissing)))
+            }
+            [(_, turn)] => Ok(*turn),
+            _ => Err(AmbiguityError::Ambiguous(
+                candidates.into_iter().map(|(from, _)| from).collect(),
+            )),
+        }
+    }
+
     // Renders turn as algebraic notation, PGN-style, see
     //   http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
     //
@@ -31,6 +31,9 @@ pub enum ChatMessageBody {
     GameOver {
         outcome: GameOutcome,
     },
+    AbortRequested {
+        participant: String,
+    },
     NextGamePlayers {
         players: Vec<PlayerInGame>,
     },
@@ -6,6 +6,7 @@
 
 use std::collections::{HashMap, VecDeque};
 use std::time::Duration;
+use std::{fmt, mem};
 
 use enum_map::{EnumMap, enum_map};
 use hdrhistogram::Histogram;
@@ -14,16 +15,17 @@ use itertools::Itertools;
 use lru::LruCache;
 use strum::IntoEnumIterator;
 
+use crate::algebraic::AlgebraicCharset;
 use crate::altered_game::{
     AlteredGame, ApplyRemoteTurnResult, TurnConfirmation, TurnInputResult, WaybackDestination,
     WaybackState,
 };
 use crate::analysis_engine::{
     ANALYSIS_BOARD_IDX, ANALYSIS_ENGINE_NAME_BLACK, ANALYSIS_ENGINE_NAME_WHITE, AnalysisEngine,
-    AnalysisInfo, EngineStatus,
+    AnalysisInfo, AnalysisScore, EngineStatus, TurnGlyph, classify_turn,
 };
-use crate::board::{Board, Turn, TurnDrop, TurnError, TurnInput, TurnMode, TurnMove};
-use crate::chalk::{ChalkCanvas, ChalkMark, Chalkboard};
+use crate::board::{Board, Reserve, Turn, TurnDrop, TurnError, TurnInput, TurnMode, TurnMove};
+use crate::chalk::{ChalkCanvas, ChalkDrawing, ChalkMark, Chalkboard};
 use crate::chat::{ChatMessage, ChatMessageBody, ChatRecipient};
 use crate::client_chat::{ClientChat, SystemMessageClass};
 use crate::clock::{GameDuration, GameInstant, WallGameTimePair, duration_to_mss};
@@ -35,18 +37,20 @@ use crate::event::{
 use crate::force::Force;
 use crate::game::{
     BughouseBoard, BughouseEnvoy, BughouseGame, BughouseGameStatus, BughouseParticipant,
-    BughousePlayer, PlayerInGame, PlayerRelation, TurnIndex, TurnRecord, TurnRecordExpanded,
+    BughousePlayer, GameReview, PlayerInGame, PlayerRelation, SanMove, TurnIndex, TurnRecord,
+    TurnRecordExpanded,
 };
 use crate::half_integer::HalfU32;
-use crate::lobby::Teaming;
-use crate::meter::{Meter, MeterBox, MeterStats};
+use crate::lobby::{SeatSwap, SeatSwapError, Teaming};
+use crate::meter::{FrameBudget, Meter, MeterBox, MeterStats};
 use crate::pgn::import_from_bpgn;
 use crate::piece::PieceKind;
 use crate::ping_pong::{ActiveConnectionMonitor, ActiveConnectionStatus};
 use crate::player::{Faction, Participant, PlayerSchedulingPriority};
+use crate::player_name::{MAX_PLAYER_NAME_LENGTH, PlayerNameError, validate_player_name};
 use crate::role::Role;
 use crate::rules::{ChessRules, DropAggression, FIRST_GAME_COUNTDOWN_DURATION, MatchRules, Rules};
-use crate::scores::Scores;
+use crate::scores::{MatchResult, Scores};
 use crate::session::Session;
 use crate::starter::EffectiveStartingPosition;
 use crate::utc_time::UtcDateTime;
@@ -62,13 +66,38 @@ pub enum NotableEvent {
     MatchStarted(String), // contains MatchID
     GameStarted,
     GameOver(SubjectiveGameResult),
+    // Follows `GameOver`. Sent only for live games, not when loading archive games.
+    GameReviewReady(GameReview),
     TurnMade(BughouseEnvoy),
-    MyReserveRestocked(BughouseBoard),
+    // Contains the board where the reserve was restocked and the pieces that arrived. A single
+    // capture can bring several pieces, e.g. when a combined piece falls apart.
+    MyReserveRestocked(BughouseBoard, Vec<PieceKind>),
     PieceStolen,
     LowTime(BughouseBoard),
     WaybackStateUpdated(WaybackState),
     GotArchiveGameList(Vec<FinishedGameDescription>),
     ArchiveGameLoaded(i64),
+    // Switching between playing and observing is deferred until the next game. Sent when a game
+    // starts with a different role than the previous one. Contains the new faction.
+    FactionChangeApplied(Faction),
+    // The server is about to exit. Contains suggested delay before reconnecting. Match state is
+    // kept, so that `hot_reconnect` could rejoin the match if the server still has it.
+    ServerShuttingDown(Duration),
+    // `update_state` took longer than the frame budget. Contains the frame duration. Rate-limited.
+    SlowFrame(Duration),
+    // Sent in addition to the full participant list update, so that the UI could highlight the
+    // affected participant. Not sent for the initial participant list after joining a match.
+    ParticipantChanged(ParticipantChange),
+    // The match has reached its target. Also sent when joining a match that is already complete.
+    MatchCompleted(MatchResult),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParticipantChange {
+    Joined(String),
+    Left(String),
+    FactionChanged(String, Faction),
+    ReadinessChanged(String, bool),
 }
 
 #[derive(Clone, Debug)]
@@ -87,6 +116,42 @@ pub enum ClientError {
 #[derive(Debug)]
 pub struct ServerOptions {
     pub max_starting_time: Option<Duration>,
+    pub max_player_name_length: usize,
+}
+
+// Reason why a server would refuse to create a match with given rules.
+#[derive(Clone, Debug)]
+pub enum RulesVerificationError {
+    Invalid(String),
+    MaxStartingTimeExceeded { requested: Duration, allowed: Duration },
+}
+
+impl fmt::Display for RulesVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulesVerificationError::Invalid(message) => write!(f, "{message}"),
+            RulesVerificationError::MaxStartingTimeExceeded { allowed, .. } => {
+                write!(f, "Maximum allowed starting time is {}.", duration_to_mss(*allowed))
+            }
+        }
+    }
+}
+
+impl ServerOptions {
+    // Used both by the server when creating a match and by the client beforehand.
+    pub fn verify_rules(&self, rules: &Rules) -> Result<(), RulesVerificationError> {
+        rules.verify().map_err(RulesVerificationError::Invalid)?;
+        if let Some(max_starting_time) = self.max_starting_time {
+            let starting_time = rules.chess_rules.time_control.starting_time;
+            if starting_time > max_starting_time {
+                return Err(RulesVerificationError::MaxStartingTimeExceeded {
+                    requested: starting_time,
+                    allowed: max_starting_time,
+                });
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -103,12 +168,18 @@ pub struct GameState {
     pub chalkboard: Chalkboard,
     // Canvas for the current client to draw on.
     pub chalk_canvas: ChalkCanvas,
+    // Own chalk drawings for positions other than the displayed one. After the game is over,
+    // drawings are bound to the position in wayback, so that they reappear when the user returns.
+    chalk_by_position: HashMap<TurnIndex, ChalkDrawing>,
     // Whether engine analysis is on. If this is disabled, we will still load and the engine and
     // communicate with it (e.g. to find out if it's ready to analyse a given game), but no
     // heavy-lifting will be done.
     pub analysis_enabled: bool,
     // Evaluation by the engine: 100% is White totally winning, 0% is Black totally winning.
     pub evaluation_percentages: EnumMap<BughouseBoard, Option<f64>>,
+    // Evaluations retained for all analysed positions, keyed by the index of the last turn on the
+    // same board (`None` for the starting position). Used to annotate turns in the turn log.
+    evaluation_history: EnumMap<BughouseBoard, HashMap<Option<TurnIndex>, f64>>,
     // Whether wayback state is shared with other players who enabled sharing.
     shared_wayback_enabled: bool,
     // Turn index seen by those who enabled shared wayback state (regardless of whether it is
@@ -122,10 +193,37 @@ pub struct GameState {
     awaiting_turn_confirmation_since: EnumMap<BughouseBoard, Option<Instant>>,
 }
 
+impl GameState {
+    // Annotation for a turn based on the engine evaluations before and after it. Requires both
+    // positions to have been analysed, so only turns on the analysis board are annotated.
+    pub fn turn_glyph(&self, record: &TurnRecordExpanded) -> Option<TurnGlyph> {
+        let board_idx = record.envoy.board_idx;
+        if !self.analysis_enabled
+            || record.mode != TurnMode::InOrder
+            || board_idx != get_board_index(ANALYSIS_BOARD_IDX, self.alt_game.perspective())
+        {
+            return None;
+        }
+        let prev_turn_index = self
+            .alt_game
+            .local_game()
+            .turn_log()
+            .iter()
+            .rev()
+            .find(|r| r.index < record.index && r.envoy.board_idx == board_idx)
+            .map(|r| r.index);
+        let history = &self.evaluation_history[board_idx];
+        let before = *history.get(&prev_turn_index)?;
+        let after = *history.get(&Some(record.index))?;
+        classify_turn(record.envoy.force, before, after)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum MatchOrigin {
     ActiveMatch(String), // Match ID
     ArchiveGame(i64),    // Game ID
+    Study,               // Local analysis board, see `AlteredGame::new_study`
 }
 
 #[derive(Debug)]
@@ -133,18 +231,31 @@ pub struct Match {
     pub origin: MatchOrigin,
     pub my_name: String,
     pub my_faction: Faction,
+    // Faction at the start of the current game, if any.
+    pub game_faction: Option<Faction>,
+    // Read-only view of an active match: we are not a participant, cannot chat or take actions.
+    pub is_spectator: bool,
     // Rules applied in every game of the match.
     pub rules: Rules,
     // All players including those not participating in the current game.
     pub participants: Vec<Participant>,
+    // Participant who can moderate the match.
+    pub owner: Option<String>,
     // Scores from the past matches.
     pub scores: Option<Scores>,
+    // Final standings. `Some` if the match has reached its target and is over.
+    pub result: Option<MatchResult>,
     // Whether this client is ready to start a new game.
     pub is_ready: bool,
-    // If `Some`, the first game is going to start after the countdown.
-    pub first_game_countdown_since: Option<Instant>,
+    // If `Some`, the first game is going to start after the countdown. The value is provided by the
+    // server, which owns the countdown.
+    pub first_game_countdown_left: Option<Duration>,
+    // If `Some`, the first game is scheduled to start at this time.
+    pub scheduled_start: Option<Instant>,
     // Chat box content. Includes messages from other players and system messages.
     pub chat: ClientChat,
+    // When this client last sent a chat message. Used to enforce `MatchRules::chat_slow_mode`.
+    last_chat_message_sent: Option<Instant>,
     // Active game or latest game.
     pub game_state: Option<GameState>,
     // Shown before the first game starts.
@@ -156,13 +267,27 @@ enum MatchState {
     NotConnected,
     Creating { my_name: String },
     Joining { match_id: String, my_name: String },
+    JoiningAsSpectator { match_id: String },
     LoadingArchiveGame { game_id: i64 },
     Connected(Match),
 }
 
 struct Connection {
-    outgoing_events: VecDeque<BughouseClientEvent>,
+    // Events are stored together with the time they were sent.
+    outgoing_events: VecDeque<(Instant, BughouseClientEvent)>,
+    // Debug setting: hold outgoing events for this long before dispatching them. Allows to
+    // exercise preturn and lag handling without a bad network. The server timestamps turns on
+    // arrival, so the delay is charged to the player's clock just like real network latency.
+    simulated_latency: Option<Duration>,
+    // Events that were dispatched, but could've been lost if the connection dropped. The server
+    // answers every `Ping` with a `Pong` and WebSocket preserves event order, so receiving a
+    // `Pong` means that everything up to the corresponding `Ping` has been delivered.
+    unacknowledged_events: VecDeque<BughouseClientEvent>,
     health_monitor: ActiveConnectionMonitor,
+    // Read-only clients can only spectate, e.g. for tournament broadcast overlays. Any event that
+    // could affect a match is dropped here, so input methods are no-ops. Chat and chalkboard
+    // updates are dropped as well to keep the state minimal.
+    read_only: bool,
 }
 
 impl Match {
@@ -170,15 +295,16 @@ impl Match {
     pub fn is_archive_game_view(&self) -> bool {
         matches!(self.origin, MatchOrigin::ArchiveGame(_))
     }
+    pub fn is_study(&self) -> bool { matches!(self.origin, MatchOrigin::Study) }
     pub fn match_id(&self) -> Option<&String> {
         match &self.origin {
             MatchOrigin::ActiveMatch(match_id) => Some(match_id),
-            MatchOrigin::ArchiveGame(_) => None,
+            MatchOrigin::ArchiveGame(_) | MatchOrigin::Study => None,
         }
     }
     pub fn archive_game_id(&self) -> Option<i64> {
         match &self.origin {
-            MatchOrigin::ActiveMatch(_) => None,
+            MatchOrigin::ActiveMatch(_) | MatchOrigin::Study => None,
             MatchOrigin::ArchiveGame(game_id) => Some(*game_id),
         }
     }
@@ -214,11 +340,49 @@ impl Connection {
     fn new(now: Instant) -> Self {
         Connection {
             outgoing_events: VecDeque::new(),
+            simulated_latency: None,
+            unacknowledged_events: VecDeque::new(),
             health_monitor: ActiveConnectionMonitor::new(now),
+            read_only: false,
+        }
+    }
+    fn new_read_only(now: Instant) -> Self { Connection { read_only: true, ..Self::new(now) } }
+
+    fn send(&mut self, event: BughouseClientEvent) {
+        if self.read_only && !allowed_for_read_only(&event) {
+            return;
+        }
+        self.outgoing_events.push_back((Instant::now(), event));
+    }
+
+    fn next_outgoing_event(&mut self, now: Instant) -> Option<BughouseClientEvent> {
+        let (sent, _) = self.outgoing_events.front()?;
+        if let Some(latency) = self.simulated_latency
+            && now < *sent + latency
+        {
+            return None;
+        }
+        let (_, event) = self.outgoing_events.pop_front()?;
+        self.unacknowledged_events.push_back(event.clone());
+        Some(event)
+    }
+
+    fn acknowledge_ping(&mut self) {
+        if let Some(pos) = self
+            .unacknowledged_events
+            .iter()
+            .position(|e| matches!(e, BughouseClientEvent::Ping))
+        {
+            self.unacknowledged_events.drain(..=pos);
         }
     }
 
-    fn send(&mut self, event: BughouseClientEvent) { self.outgoing_events.push_back(event); }
+    // Returns all events that the server might not have received, in the original order.
+    fn take_pending_events(&mut self) -> Vec<BughouseClientEvent> {
+        let unacknowledged = mem::take(&mut self.unacknowledged_events);
+        let queued = mem::take(&mut self.outgoing_events).into_iter().map(|(_, event)| event);
+        unacknowledged.into_iter().chain(queued).collect()
+    }
 }
 
 pub struct ClientState {
@@ -236,13 +400,23 @@ pub struct ClientState {
     // confirmation time would not be recorded. This is completely fine, since we only need the
     // general feeling of how quickly the turns are confirmed, not a complete log.
     turn_confirmed_meter: Meter,
+    update_state_meter: Meter,
+    frame_budget: FrameBudget,
     is_performance_reporting_enabled: bool,
     session: Session,
     guest_player_name: Option<String>, // used only to create/join match
+    anonymous_guest: bool,             // used only to create/join match
+    followed_player: Option<String>,   // spectate the player's games in other matches
+    watched_matches: Vec<String>,      // matches to flip between as a spectator
     game_archive_cache: LruCache<i64, String>, // game_id -> BPGN
     default_setup_demo_state: GameState, // shown before the match starts
+    last_match_rules: Option<Rules>,   // kept after leaving the match to create a similar one
+    show_rating: bool,                 // whether other participants can see my rating
+    focused_board: Option<DisplayBoard>, // target for keyboard commands without explicit board
 }
 
+const DEFAULT_FRAME_BUDGET: Duration = Duration::from_millis(200);
+
 const LOW_TIME_WARNING_THRESHOLDS: &[Duration] = &[
     Duration::from_secs(20),
     Duration::from_secs(10),
@@ -264,6 +438,7 @@ impl ClientState {
         let mut meter_box = MeterBox::new();
         let ping_meter = meter_box.meter("ping".to_owned());
         let turn_confirmed_meter = meter_box.meter("turn_confirmation".to_owned());
+        let update_state_meter = meter_box.meter("update_state".to_owned());
         let default_setup_demo_state = make_setup_demo_state(Rules {
             match_rules: MatchRules::unrated_public(),
             chess_rules: ChessRules::bughouse_modern(),
@@ -279,11 +454,27 @@ impl ClientState {
             meter_box,
             ping_meter,
             turn_confirmed_meter,
+            update_state_meter,
+            frame_budget: FrameBudget::new(Some(DEFAULT_FRAME_BUDGET)),
             is_performance_reporting_enabled: true,
             session: Session::Unknown,
             guest_player_name: None,
+            anonymous_guest: false,
+            followed_player: None,
+            watched_matches: Vec::new(),
             game_archive_cache: LruCache::new(GAME_ARCHIVE_CACHE_SIZE.try_into().unwrap()),
             default_setup_demo_state,
+            last_match_rules: None,
+            show_rating: true,
+            focused_board: None,
+        }
+    }
+
+    pub fn new_read_only(user_agent: String, time_zone: String) -> Self {
+        ClientState {
+            connection: Connection::new_read_only(Instant::now()),
+            is_performance_reporting_enabled: false,
+            ..Self::new(user_agent, time_zone)
         }
     }
 
@@ -291,14 +482,26 @@ impl ClientState {
         self.is_performance_reporting_enabled = false;
     }
 
+    pub fn is_read_only(&self) -> bool { self.connection.read_only }
+
     pub fn session(&self) -> &Session { &self.session }
     pub fn server_options(&self) -> Option<&ServerOptions> { self.server_options.as_ref() }
     pub fn mtch(&self) -> Option<&Match> { self.match_state.get() }
     fn mtch_mut(&mut self) -> Option<&mut Match> { self.match_state.get_mut() }
     fn is_active_match(&self) -> bool { self.mtch().is_some_and(|mtch| mtch.is_active_match()) }
+    // Spectating helpers must never pull the user out of a match they are participating in.
+    fn is_match_participant(&self) -> bool {
+        matches!(self.match_state, MatchState::Creating { .. } | MatchState::Joining { .. })
+            || (self.is_active_match() && !self.is_spectator())
+    }
     pub fn is_ready(&self) -> Option<bool> { self.mtch().map(|m| m.is_ready) }
+    pub fn is_spectator(&self) -> bool { self.mtch().is_some_and(|m| m.is_spectator) }
     pub fn match_id(&self) -> Option<&String> { self.mtch().and_then(|m| m.match_id()) }
     pub fn archive_game_id(&self) -> Option<i64> { self.mtch().and_then(|m| m.archive_game_id()) }
+    // Multi-line plain text description of the current match rules.
+    pub fn rules_summary(&self) -> Option<String> {
+        self.mtch().map(|m| m.rules.to_human_readable())
+    }
     // TODO: Should we ever use `game_state` externally? Consider: remove `game_state`, make
     // `Match::game_state` private, always use `displayed_game_state`.
     pub fn game_state(&self) -> Option<&GameState> { self.match_state.game_state() }
@@ -308,6 +511,19 @@ impl ClientState {
             .unwrap_or(&self.default_setup_demo_state)
     }
     fn game_state_mut(&mut self) -> Option<&mut GameState> { self.match_state.game_state_mut() }
+    // Engine evaluation, if any. Falls back to material balance when analysis is enabled, but there
+    // is no engine.
+    pub fn evaluation_percentages(&self) -> EnumMap<BughouseBoard, Option<f64>> {
+        let game_state = self.displayed_game_state();
+        if !game_state.analysis_enabled || self.analysis_engine.is_some() {
+            return game_state.evaluation_percentages;
+        }
+        let game = game_state.alt_game.local_game();
+        enum_map! { board_idx => {
+            let balance = game.board(board_idx).material_balance();
+            Some(AnalysisScore::Centipawn(balance * 100).to_percent_score())
+        }}
+    }
     // TODO: Reduce public mutability. This is used only for drag&drop, so limit the mutable API to that.
     pub fn alt_game_mut(&mut self) -> Option<&mut AlteredGame> {
         self.game_state_mut().map(|s| &mut s.alt_game)
@@ -328,7 +544,9 @@ impl ClientState {
             MatchState::NotConnected { .. } => None,
             MatchState::Creating { my_name } => Some(my_name),
             MatchState::Joining { my_name, .. } => Some(my_name),
+            MatchState::JoiningAsSpectator { .. } => None,
             MatchState::LoadingArchiveGame { .. } => None,
+            MatchState::Connected(Match { is_spectator: true, .. }) => None,
             MatchState::Connected(Match { my_name, .. }) => Some(my_name),
         }
     }
@@ -399,6 +617,7 @@ impl ClientState {
             ref mut alt_game,
             ref mut analysis_enabled,
             ref mut evaluation_percentages,
+            ref mut evaluation_history,
             ..
         } = self.match_state.game_state_mut()?;
         let board_idx = get_board_index(display_board, alt_game.perspective());
@@ -410,7 +629,13 @@ impl ClientState {
         if !*analysis_enabled {
             return None;
         }
-        evaluation_percentages[board_idx] = Some(info.score.to_percent_score());
+        let evaluation = info.score.to_percent_score();
+        evaluation_percentages[board_idx] = Some(evaluation);
+        let last_turn = game.turn_log().iter().rev().find(|r| r.envoy.board_idx == board_idx);
+        // Don't keep evaluations for positions with preturns: they may never happen.
+        if last_turn.is_none_or(|r| r.mode == TurnMode::InOrder) {
+            evaluation_history[board_idx].insert(last_turn.map(|r| r.index), evaluation);
+        }
 
         if let Some((_, next_turn, _)) = info.best_line.first() {
             let mark = match *next_turn {
@@ -467,11 +692,22 @@ impl ClientState {
     }
 
     pub fn first_game_countdown_left(&self) -> Option<Duration> {
-        self.mtch().and_then(|m| {
-            m.first_game_countdown_since.map(|t| {
-                FIRST_GAME_COUNTDOWN_DURATION.saturating_sub(Instant::now().duration_since(t))
-            })
-        })
+        self.mtch().and_then(|m| m.first_game_countdown_left)
+    }
+
+    pub fn chat_cooldown_left(&self) -> Option<Duration> {
+        let mtch = self.mtch()?;
+        let interval = mtch.rules.match_rules.chat_slow_mode?;
+        let last_sent = mtch.last_chat_message_sent?;
+        let left = interval.saturating_sub(Instant::now().duration_since(last_sent));
+        (!left.is_zero()).then_some(left)
+    }
+
+    pub fn scheduled_start_left(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.mtch()
+            .and_then(|m| m.scheduled_start)
+            .map(|t| if t > now { t - now } else { Duration::ZERO })
     }
 
     pub fn chalk_canvas(&self) -> Option<&ChalkCanvas> {
@@ -490,6 +726,23 @@ impl ClientState {
         self.meter_box.consume_histograms()
     }
 
+    pub fn set_simulated_latency(&mut self, latency: Option<Duration>) {
+        self.connection.simulated_latency = latency;
+    }
+
+    pub fn set_frame_budget(&mut self, budget: Option<Duration>) {
+        self.frame_budget.set_budget(budget);
+    }
+    // Exempts the next frame from the budget check. Should be called before one-off heavy
+    // operations, e.g. when the game view is built from scratch.
+    pub fn expect_heavy_frame(&mut self) { self.frame_budget.expect_heavy_frame(); }
+    pub fn record_update_state_duration(&mut self, duration: Duration) {
+        self.update_state_meter.record_duration(duration);
+        if self.frame_budget.check(duration, Instant::now()) {
+            self.notable_event_queue.push_back(NotableEvent::SlowFrame(duration));
+        }
+    }
+
     pub fn got_server_welcome(&self) -> bool { self.server_options.is_some() }
 
     pub fn current_turnaround_time(&self) -> Duration {
@@ -497,28 +750,68 @@ impl ClientState {
         self.connection.health_monitor.current_turnaround_time(now)
     }
 
-    fn finalize_my_name_for_match(&self) -> String {
+    // Returns player name and whether to join anonymously. The name is empty for anonymous guests:
+    // they get one from the server in `MatchWelcome`.
+    fn finalize_my_name_for_match(&self) -> (String, bool) {
         // Let user name take priority: the user might have entered guest player name first and then
         // gone back and logged in.
         if let Some(user_info) = self.session.user_info() {
-            return user_info.user_name.clone();
+            return (user_info.user_name.clone(), false);
+        }
+        if self.anonymous_guest {
+            return (String::new(), true);
         }
         if let Some(guest_player_name) = &self.guest_player_name {
-            return guest_player_name.clone();
+            return (guest_player_name.clone(), false);
         }
         panic!("Cannot determine player name: not logged in and no guest name set.");
     }
     pub fn set_guest_player_name(&mut self, player_name: Option<String>) {
-        // TODO: Verify name locally and return an error instantly if it's obvious.
-        // TODO: Verify name on the server and return an error if it's taken or banned.
+        // TODO: Verify name on the server and return an error if it's taken.
         self.guest_player_name = player_name;
     }
-
-    pub fn new_match(&mut self, rules: Rules) {
-        let my_name = self.finalize_my_name_for_match();
+    // Applies the same checks as the server. Should be called before `set_guest_player_name`, so
+    // that obviously invalid names are rejected without a round trip.
+    pub fn validate_guest_player_name(&self, player_name: &str) -> Result<(), PlayerNameError> {
+        let max_length = self
+            .server_options
+            .as_ref()
+            .map_or(MAX_PLAYER_NAME_LENGTH, |options| options.max_player_name_length);
+        validate_player_name(player_name, max_length)
+    }
+    // Anonymous guests appear as "Guest-A", "Guest-B", etc. The label is assigned by the server and
+    // stays the same for the entire match. Ignored for registered users.
+    pub fn set_anonymous_guest(&mut self, anonymous: bool) { self.anonymous_guest = anonymous; }
+
+    pub fn new_match(&mut self, rules: Rules) { self.new_match_impl(rules, None); }
+    // Rules of the current or the most recent match, for creating a new match with the same rules.
+    // Fails if the rules are not accepted by the server anymore.
+    pub fn clone_match_rules(&self) -> Result<Rules, String> {
+        let rules = self.last_match_rules.clone().ok_or_else(|| "No previous match".to_owned())?;
+        let server_options =
+            self.server_options.as_ref().ok_or_else(|| "Not connected".to_owned())?;
+        server_options.verify_rules(&rules).map_err(|err| err.to_string())?;
+        Ok(rules)
+    }
+    // Creates a match where the first game starts at the given time.
+    pub fn new_scheduled_match(&mut self, rules: Rules, scheduled_start: UtcDateTime) {
+        self.new_match_impl(rules, Some(scheduled_start));
+    }
+    fn new_match_impl(&mut self, rules: Rules, scheduled_start: Option<UtcDateTime>) {
+        // Read-only clients only spectate, hence they can never act as participants. `Connection`
+        // would drop the event anyway, but the match state must not change either.
+        if self.is_read_only() {
+            return;
+        }
+        let (my_name, anonymous) = self.finalize_my_name_for_match();
         self.match_state = MatchState::Creating { my_name: my_name.clone() };
-        self.connection
-            .send(BughouseClientEvent::NewMatch { rules, player_name: my_name });
+        self.connection.send(BughouseClientEvent::NewMatch {
+            rules,
+            player_name: my_name,
+            anonymous,
+            show_rating: self.show_rating,
+            scheduled_start,
+        });
     }
     // Should be called in one the two situations:
     //   - Connecting to a match during a normal app flow;
@@ -526,13 +819,87 @@ impl ClientState {
     //     refreshes the browser tab. If network connection was lost, but a client object is still
     //     alive, use `hot_reconnect` instead.
     pub fn join(&mut self, match_id: String) {
-        let my_name = self.finalize_my_name_for_match();
+        if self.is_read_only() {
+            return;
+        }
+        let (my_name, anonymous) = self.finalize_my_name_for_match();
         self.connection.send(BughouseClientEvent::Join {
             match_id: match_id.clone(),
             player_name: my_name.clone(),
+            anonymous,
+            show_rating: self.show_rating,
         });
         self.match_state = MatchState::Joining { match_id, my_name };
     }
+    // Watch a match without joining it. Doesn't require a player name.
+    pub fn spectate(&mut self, match_id: String) {
+        self.connection
+            .send(BughouseClientEvent::Spectate { match_id: match_id.clone() });
+        self.match_state = MatchState::JoiningAsSpectator { match_id };
+    }
+    // Automatically spectate the player's games in other matches. Setting a new player replaces
+    // the old one; `None` stops following.
+    pub fn followed_player(&self) -> Option<&String> { self.followed_player.as_ref() }
+    pub fn follow_player(&mut self, player_name: Option<String>) {
+        self.followed_player = player_name.clone();
+        self.connection.send(BughouseClientEvent::FollowPlayer { player_name });
+    }
+    // Watched matches stay in the list until removed explicitly, even after the game is over: the
+    // final position remains available. Switching between them re-joins as a spectator, so match
+    // state (game, chat, clocks) is fetched anew each time.
+    pub fn watched_matches(&self) -> &[String] { &self.watched_matches }
+    // Adds the match to the watched list and shows it.
+    pub fn watch_match(&mut self, match_id: String) {
+        if !self.watched_matches.contains(&match_id) {
+            self.watched_matches.push(match_id.clone());
+        }
+        self.show_watched_match(match_id);
+    }
+    pub fn unwatch_match(&mut self, match_id: &str) {
+        let Some(pos) = self.watched_matches.iter().position(|id| id == match_id) else {
+            return;
+        };
+        self.watched_matches.remove(pos);
+        if self.spectated_match_id().is_some_and(|id| id == match_id) {
+            if self.watched_matches.is_empty() {
+                self.leave_match();
+            } else {
+                let next = self.watched_matches[pos % self.watched_matches.len()].clone();
+                self.show_watched_match(next);
+            }
+        }
+    }
+    pub fn next_watched_match(&mut self) { self.step_watched_match(1); }
+    pub fn previous_watched_match(&mut self) { self.step_watched_match(-1); }
+    fn step_watched_match(&mut self, step: isize) {
+        let num_matches = self.watched_matches.len();
+        if num_matches == 0 {
+            return;
+        }
+        let current = self
+            .spectated_match_id()
+            .and_then(|id| self.watched_matches.iter().position(|m| m == id));
+        let pos = match current {
+            Some(pos) => (pos as isize + step).rem_euclid(num_matches as isize) as usize,
+            None => 0,
+        };
+        self.show_watched_match(self.watched_matches[pos].clone());
+    }
+    fn show_watched_match(&mut self, match_id: String) {
+        if self.is_match_participant() || self.spectated_match_id() == Some(&match_id) {
+            return;
+        }
+        self.leave_match();
+        self.spectate(match_id);
+    }
+    // Includes the match we are about to spectate, but haven't received from the server yet.
+    fn spectated_match_id(&self) -> Option<&String> {
+        match &self.match_state {
+            MatchState::JoiningAsSpectator { match_id } => Some(match_id),
+            MatchState::Connected(mtch) if mtch.is_spectator => mtch.match_id(),
+            _ => None,
+        }
+    }
     // Hot reconnect should be called when WebSocket connection was lost due to network issues, but
     // the client object is still alive. Re-establishes connection while giving un uninterrupted
     // experience to the user. For example, it's possible to continue making and cancelling turns
@@ -540,46 +907,76 @@ impl ClientState {
     pub fn hot_reconnect(&mut self) {
         self.server_options = None;
         self.notable_event_queue.clear();
-        self.connection.outgoing_events.retain(|message| match message {
-            // Remove events that have anything to do with the active match.
-            BughouseClientEvent::NewMatch { .. }
-            | BughouseClientEvent::Join { .. }
-            | BughouseClientEvent::HotReconnect { .. }
-            | BughouseClientEvent::SetFaction { .. }
-            | BughouseClientEvent::SetTurns { .. }
-            | BughouseClientEvent::MakeTurn { .. }
-            | BughouseClientEvent::CancelPreturn { .. }
-            | BughouseClientEvent::Resign
-            | BughouseClientEvent::SetReady { .. }
-            | BughouseClientEvent::LeaveMatch
-            | BughouseClientEvent::LeaveServer
-            | BughouseClientEvent::SendChatMessage { .. }
-            | BughouseClientEvent::UpdateChalkDrawing { .. }
-            | BughouseClientEvent::SetSharedWayback { .. }
-            | BughouseClientEvent::Ping => false,
-            BughouseClientEvent::GetArchiveGameList
-            | BughouseClientEvent::GetArchiveGameBpgn { .. }
-            | BughouseClientEvent::ReportPerformace(_)
-            | BughouseClientEvent::ReportError(_) => true,
-        });
+        let pending_events = self.connection.take_pending_events();
         self.connection.health_monitor.reset();
-        let Some(mtch) = self.mtch() else {
-            return;
-        };
-        let Some(match_id) = mtch.match_id() else {
-            return;
-        };
-        let my_name = self.my_name().unwrap().to_owned();
-        self.connection.send(BughouseClientEvent::HotReconnect {
-            match_id: match_id.clone(),
-            player_name: my_name,
-        });
+        if let Some(player_name) = &self.followed_player {
+            let player_name = Some(player_name.clone());
+            self.connection.send(BughouseClientEvent::FollowPlayer { player_name });
+        }
+        let mut in_match = false;
+        if let Some(mtch) = self.mtch()
+            && let Some(match_id) = mtch.match_id()
+        {
+            if mtch.is_spectator {
+                self.connection
+                    .send(BughouseClientEvent::Spectate { match_id: match_id.clone() });
+            } else {
+                let my_name = self.my_name().unwrap().to_owned();
+                self.connection.send(BughouseClientEvent::HotReconnect {
+                    match_id: match_id.clone(),
+                    player_name: my_name,
+                    show_rating: self.show_rating,
+                });
+                in_match = true;
+            }
+        }
+        // Replay events that could've been lost. The server ignores duplicates where it matters
+        // (e.g. chat messages), and game turns are resynced via `SetTurns` after `GameStarted`.
+        for event in pending_events {
+            let replay = match event {
+                BughouseClientEvent::NewMatch { .. }
+                | BughouseClientEvent::Join { .. }
+                | BughouseClientEvent::HotReconnect { .. }
+                | BughouseClientEvent::Spectate { .. }
+                | BughouseClientEvent::SetTurns { .. }
+                | BughouseClientEvent::MakeTurn { .. }
+                | BughouseClientEvent::CancelPreturn { .. }
+                | BughouseClientEvent::FollowPlayer { .. }
+                | BughouseClientEvent::LeaveMatch
+                | BughouseClientEvent::LeaveServer
+                | BughouseClientEvent::Ping => false,
+                // Not idempotent: a repeated transfer would be rejected, since we are no longer
+                // the owner. Better to let the user retry.
+                BughouseClientEvent::TransferMatchOwnership { .. } => false,
+                BughouseClientEvent::SetFaction { .. }
+                | BughouseClientEvent::Resign
+                | BughouseClientEvent::ResignBoard
+                | BughouseClientEvent::RequestAbort
+                | BughouseClientEvent::SetReady { .. }
+                | BughouseClientEvent::SetShowRating { .. }
+                | BughouseClientEvent::RequestRematch { .. }
+                | BughouseClientEvent::SetHandicap { .. }
+                | BughouseClientEvent::SendChatMessage { .. }
+                | BughouseClientEvent::UpdateChalkDrawing { .. }
+                | BughouseClientEvent::SetSharedWayback { .. } => in_match,
+                BughouseClientEvent::GetArchiveGameList
+                | BughouseClientEvent::GetArchiveGameBpgn { .. }
+                | BughouseClientEvent::ReportPerformace(_)
+                | BughouseClientEvent::ReportError(_) => true,
+            };
+            if replay {
+                self.connection.send(event);
+            }
+        }
     }
     pub fn set_faction(&mut self, faction: Faction) {
+        if self.is_read_only() {
+            return;
+        }
         let Some(mtch) = self.mtch_mut() else {
             return;
         };
-        if !mtch.is_active_match() {
+        if !mtch.is_active_match() || mtch.is_spectator {
             return;
         }
         mtch.my_faction = faction;
@@ -594,16 +991,86 @@ impl ClientState {
         }
         self.connection.send(BughouseClientEvent::Resign);
     }
+    pub fn can_resign_board(&self) -> bool {
+        let Some(game_state) = self.game_state() else {
+            return false;
+        };
+        let alt_game = &game_state.alt_game;
+        let game = alt_game.game_confirmed();
+        let Some(envoy) = alt_game.my_id().as_player().and_then(|p| p.as_single_player()) else {
+            return false;
+        };
+        game.bughouse_rules().board_resignation
+            && game.is_active()
+            && !game.is_board_resigned(envoy.board_idx)
+    }
+    pub fn resign_board(&mut self) {
+        if !self.can_resign_board() {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::ResignBoard);
+    }
+    pub fn request_abort(&mut self) {
+        let Some(game_state) = self.game_state() else {
+            return;
+        };
+        if !game_state.alt_game.my_id().is_player() {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::RequestAbort);
+    }
     pub fn set_ready(&mut self, is_ready: bool) {
+        if self.is_read_only() {
+            return;
+        }
         let Some(mtch) = self.mtch_mut() else {
             return;
         };
-        if !mtch.is_active_match() || mtch.has_active_game() {
+        if !mtch.is_active_match() || mtch.is_spectator || mtch.has_active_game() {
             return;
         }
         mtch.is_ready = is_ready;
         self.connection.send(BughouseClientEvent::SetReady { is_ready });
     }
+    // Applies to the current match and all matches joined afterwards.
+    pub fn set_show_rating(&mut self, show_rating: bool) {
+        self.show_rating = show_rating;
+        let Some(mtch) = self.mtch() else {
+            return;
+        };
+        if !mtch.is_active_match() || mtch.is_spectator {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::SetShowRating { show_rating });
+    }
+    pub fn set_handicap(&mut self, player_name: String, handicap: Reserve) {
+        let Some(mtch) = self.mtch() else {
+            return;
+        };
+        if !mtch.is_active_match() || mtch.is_spectator {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::SetHandicap { player_name, handicap });
+    }
+    pub fn transfer_match_ownership(&mut self, to: String) {
+        let Some(mtch) = self.mtch() else {
+            return;
+        };
+        if !mtch.is_active_match() || mtch.is_spectator {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::TransferMatchOwnership { to });
+    }
+    // Unlike `set_ready`, doesn't update readiness locally: the request can be rejected.
+    pub fn request_rematch(&mut self, swap: SeatSwap) {
+        let Some(mtch) = self.mtch() else {
+            return;
+        };
+        if !mtch.is_active_match() || mtch.is_spectator || mtch.has_active_game() {
+            return;
+        }
+        self.connection.send(BughouseClientEvent::RequestRematch { swap });
+    }
     pub fn leave_match(&mut self) {
         if let Some(engine) = &mut self.analysis_engine {
             engine.stop();
@@ -612,9 +1079,13 @@ impl ClientState {
         match &self.match_state {
             NotConnected
             | LoadingArchiveGame { .. }
-            | Connected(Match { origin: MatchOrigin::ArchiveGame(_), .. }) => {}
+            | Connected(Match {
+                origin: MatchOrigin::ArchiveGame(_) | MatchOrigin::Study,
+                ..
+            }) => {}
             Creating { .. }
             | Joining { .. }
+            | JoiningAsSpectator { .. }
             | Connected(Match { origin: MatchOrigin::ActiveMatch(_), .. }) => {
                 self.connection.send(BughouseClientEvent::LeaveMatch);
             }
@@ -645,12 +1116,28 @@ impl ClientState {
         self.update_low_time_warnings(true);
     }
 
+    // Board focus lets keyboard users target a board without holding modifier keys. When no board
+    // is focused, keyboard commands behave as before: wayback applies to both boards and "="
+    // turns go to the primary board.
+    pub fn focused_board(&self) -> Option<DisplayBoard> { self.focused_board }
+    pub fn focus_board(&mut self, display_board: Option<DisplayBoard>) {
+        self.focused_board = display_board;
+    }
+    pub fn focus_next_board(&mut self) -> DisplayBoard {
+        let next = match self.focused_board {
+            None | Some(DisplayBoard::Secondary) => DisplayBoard::Primary,
+            Some(DisplayBoard::Primary) => DisplayBoard::Secondary,
+        };
+        self.focused_board = Some(next);
+        next
+    }
+
     // Tries to execute as a "make turn" command. Returns `Some` if input was interpreted as a turn
     // command, regardless of whether the command was successful.
     //
     // Turn command consists of:
     //   1. Board notation: "<" for the left board (the only option unless double-playing), ">" for
-    //      the right board.
+    //      the right board, "=" for the focused board (the left board if none is focused).
     //   2. Algebraic turn notation or "-" to cancel pending preturn.
     //
     // Improvement potential. Add an option to treat algebraic notations as turns instead of chat
@@ -661,6 +1148,8 @@ impl ClientState {
             (DisplayBoard::Primary, suffix)
         } else if let Some(suffix) = turn_command.strip_prefix('>') {
             (DisplayBoard::Secondary, suffix)
+        } else if let Some(suffix) = turn_command.strip_prefix('=') {
+            (self.focused_board.unwrap_or(DisplayBoard::Primary), suffix)
         } else {
             return None;
         };
@@ -736,6 +1225,9 @@ impl ClientState {
             ..
         } = game_state;
         let board_idx = get_board_index(display_board, alt_game.perspective());
+        if alt_game.is_study_mode() {
+            return alt_game.try_study_turn(board_idx, turn_input);
+        }
         let my_envoy = alt_game.my_id().envoy_for(board_idx).ok_or(TurnError::NotPlayer)?;
         let now = Instant::now();
         let game_now = GameInstant::from_pair_game_maybe_active(*time_pair, now);
@@ -755,6 +1247,9 @@ impl ClientState {
     pub fn make_turn(
         &mut self, display_board: DisplayBoard, turn_input: TurnInput,
     ) -> Result<(), TurnError> {
+        if self.is_read_only() {
+            return Err(TurnError::NotPlayer);
+        }
         let turn_result = self.make_turn_impl(display_board, turn_input);
         self.show_turn_result(turn_result);
         turn_result
@@ -765,6 +1260,15 @@ impl ClientState {
             return;
         };
         match turn_or_error {
+            TurnInputResult::Turn((board_idx, turn_input)) if alt_game.is_scratch_mode() => {
+                let game_now = GameInstant::from_pair_game_maybe_active(
+                    self.game_state().unwrap().time_pair,
+                    Instant::now(),
+                );
+                let alt_game = self.alt_game_mut().unwrap();
+                let turn_result = alt_game.try_scratch_turn(board_idx, turn_input, game_now);
+                self.show_turn_result(turn_result);
+            }
             TurnInputResult::Turn((board_idx, turn_input)) => {
                 let display_board = get_display_board_index(board_idx, alt_game.perspective());
                 _ = self.make_turn(display_board, turn_input);
@@ -774,7 +1278,27 @@ impl ClientState {
         }
     }
 
+    // Turns made on the board in scratch mode are not sent to the server. Turns entered by
+    // other means (e.g. turn commands) are always real and exit scratch mode.
+    // Returns whether scratch mode is now active.
+    pub fn toggle_scratch_mode(&mut self) -> Result<bool, String> {
+        let Some(alt_game) = self.alt_game_mut() else {
+            return Err("No game in progress".to_owned());
+        };
+        if alt_game.is_scratch_mode() {
+            alt_game.exit_scratch_mode();
+            Ok(false)
+        } else if alt_game.enter_scratch_mode() {
+            Ok(true)
+        } else {
+            Err("Scratch mode is only available during a live game without fog of war".to_owned())
+        }
+    }
+
     pub fn cancel_preturn(&mut self, display_board: DisplayBoard) {
+        if self.is_read_only() {
+            return;
+        }
         self.show_turn_result(Ok(()));
         let Some(alt_game) = self.alt_game_mut() else {
             return;
@@ -831,10 +1355,14 @@ impl ClientState {
             return;
         }
         let team_chat_enabled = self.team_chat_enabled();
+        if let Some(cooldown_left) = self.chat_cooldown_left() {
+            self.show_command_error(chat_slow_mode_message(cooldown_left));
+            return;
+        }
         let Some(mtch) = self.mtch_mut() else {
             return;
         };
-        if !mtch.is_active_match() {
+        if !mtch.is_active_match() || mtch.is_spectator {
             return;
         }
         match &recipient {
@@ -850,6 +1378,7 @@ impl ClientState {
             }
         };
         let message = mtch.chat.add_local(recipient, text).clone();
+        mtch.last_chat_message_sent = Some(Instant::now());
         self.connection.send(BughouseClientEvent::SendChatMessage { message });
     }
 
@@ -889,17 +1418,39 @@ impl ClientState {
 
     pub fn process_server_event(&mut self, event: BughouseServerEvent) -> Result<(), ClientError> {
         use BughouseServerEvent::*;
+        if self.mtch().is_none() && is_match_event(&event) {
+            // Could be from an old match, see `process_lobby_updated`. This happens routinely
+            // when switching between spectated matches.
+            return Ok(());
+        }
         match event {
             Rejection(rejection) => self.process_rejection(rejection),
-            ServerWelcome { expected_git_version, max_starting_time } => {
-                self.process_server_welcome(expected_git_version, max_starting_time)
-            }
+            ServerWelcome {
+                expected_git_version,
+                max_starting_time,
+                max_player_name_length,
+            } => self.process_server_welcome(
+                expected_git_version,
+                max_starting_time,
+                max_player_name_length,
+            ),
             UpdateSession { session } => self.process_update_session(session),
             MatchList { matches } => self.process_match_list(matches),
-            MatchWelcome { match_id, rules } => self.process_match_welcome(match_id, rules),
-            LobbyUpdated { participants, countdown_elapsed } => {
-                self.process_lobby_updated(participants, countdown_elapsed)
+            MatchWelcome { match_id, rules, my_name } => {
+                self.process_match_welcome(match_id, rules, my_name)
             }
+            LobbyUpdated {
+                participants,
+                owner,
+                countdown_elapsed,
+                scheduled_start_in,
+            } => self.process_lobby_updated(
+                participants,
+                owner,
+                countdown_elapsed,
+                scheduled_start_in,
+            ),
+            FirstGameCountdown { time_left } => self.process_first_game_countdown(time_left),
             GameStarted {
                 game_index,
                 starting_position,
@@ -921,22 +1472,38 @@ impl ClientState {
             ChatMessages { messages, confirmed_local_message_id } => {
                 self.process_chat_messages(messages, confirmed_local_message_id)
             }
+            MatchCompleted { result } => self.process_match_completed(result),
             ChalkboardUpdated { chalkboard } => self.process_chalkboard_updated(chalkboard),
             SharedWaybackUpdated { turn_index } => self.process_shared_wayback_updated(turn_index),
             ArchiveGameList { games } => self.process_archive_game_list(games),
             ArchiveGameBpgn { game_id, bpgn } => self.process_archive_game_bpgn(game_id, bpgn),
+            FollowedPlayerGameStarted { player_name, match_id } => {
+                self.process_followed_player_game_started(player_name, match_id)
+            }
+            ServerShuttingDown { reconnect_in } => self.process_server_shutting_down(reconnect_in),
             Pong => self.process_pong(),
         }
     }
 
     pub fn next_outgoing_event(&mut self) -> Option<BughouseClientEvent> {
-        self.connection.outgoing_events.pop_front()
+        self.next_outgoing_event_at(Instant::now())
+    }
+    pub fn next_outgoing_event_at(&mut self, now: Instant) -> Option<BughouseClientEvent> {
+        self.connection.next_outgoing_event(now)
     }
     pub fn next_notable_event(&mut self) -> Option<NotableEvent> {
         self.notable_event_queue.pop_front()
     }
 
     fn process_rejection(&mut self, rejection: BughouseServerRejection) -> Result<(), ClientError> {
+        if let BughouseServerRejection::ChatSlowMode { local_message_id, wait } = rejection {
+            // Not an error from the user's perspective: just a reminder to slow down.
+            if let Some(mtch) = self.mtch_mut() {
+                mtch.chat.remove_rejected_local(local_message_id);
+            }
+            self.show_command_error(chat_slow_mode_message(wait));
+            return Ok(());
+        }
         // TODO: Fix the messages containing "browser tab" for the console client.
         let error = match rejection {
             BughouseServerRejection::MaxStartingTimeExceeded { allowed, .. } => {
@@ -947,6 +1514,12 @@ impl ClientState {
                     duration_to_mss(allowed)
                 ))
             }
+            BughouseServerRejection::InvalidScheduledStart { max_delay } => {
+                ClientError::Ignorable(format!(
+                    "Scheduled start must be in the future, but no more than {} days ahead.",
+                    max_delay.as_secs() / (24 * 3600)
+                ))
+            }
             BughouseServerRejection::NoSuchMatch { match_id } => {
                 ClientError::Ignorable(format!("Match {match_id} does not exist."))
             }
@@ -974,9 +1547,51 @@ impl ClientState {
                 "Guests cannot join rated matches. Please register an account and join again."
                     .to_owned(),
             ),
+            BughouseServerRejection::GuestInRegisteredOnlyMatch => ClientError::Ignorable(
+                "This match is for registered users only. Please register an account and join \
+                again."
+                    .to_owned(),
+            ),
+            BughouseServerRejection::FactionChangeLeavesTeamEmpty => ClientError::Ignorable(
+                "Cannot leave the team: it would have no players left.".to_owned(),
+            ),
+            BughouseServerRejection::NotMatchOwner => {
+                ClientError::Ignorable("Only the match owner can do this.".to_owned())
+            }
+            BughouseServerRejection::NoSuchParticipant { player_name } => {
+                ClientError::Ignorable(format!("{player_name} is not in the match."))
+            }
+            BughouseServerRejection::InvalidHandicap { reason } => {
+                ClientError::Ignorable(format!("Cannot set handicap: {reason}"))
+            }
+            BughouseServerRejection::CannotSwapSeats { error } => {
+                ClientError::Ignorable(match error {
+                    SeatSwapError::PlayerLeft => {
+                        "Cannot rematch: not all players from the last game are playing.".to_owned()
+                    }
+                    SeatSwapError::FixedTeam => {
+                        "Cannot rematch: the new seats contradict chosen teams.".to_owned()
+                    }
+                })
+            }
+            BughouseServerRejection::TooManySpectators => ClientError::Ignorable(
+                "This match has too many spectators. Please try again later.".to_owned(),
+            ),
             BughouseServerRejection::MustRegisterForGameArchive => {
                 ClientError::Ignorable("Please log in to view your game history.".to_owned())
             }
+            BughouseServerRejection::AbortNotAllowed { window } => {
+                ClientError::Ignorable(match window {
+                    None => "Games cannot be aborted in this match.".to_owned(),
+                    Some(window) => format!(
+                        "Games can only be aborted during the first {window} turns on each board."
+                    ),
+                })
+            }
+            BughouseServerRejection::BoardResignationNotAllowed => ClientError::Ignorable(
+                "Resigning a single board is not allowed in this match.".to_owned(),
+            ),
+            BughouseServerRejection::ChatSlowMode { .. } => unreachable!(),
             BughouseServerRejection::ErrorFetchingData { message } => {
                 ClientError::Ignorable(format!("Error fetching data: {message}"))
             }
@@ -995,6 +1610,7 @@ impl ClientState {
     }
     fn process_server_welcome(
         &mut self, expected_git_version: Option<String>, max_starting_time: Option<Duration>,
+        max_player_name_length: usize,
     ) -> Result<(), ClientError> {
         if let Some(expected_git_version) = expected_git_version {
             let my_version = my_git_version!();
@@ -1009,7 +1625,10 @@ impl ClientState {
                 )));
             }
         }
-        self.server_options = Some(ServerOptions { max_starting_time });
+        self.server_options = Some(ServerOptions {
+            max_starting_time,
+            max_player_name_length,
+        });
         // Trigger `update_session` in JS: it checks both server options and session.
         self.notable_event_queue.push_back(NotableEvent::SessionUpdated);
         Ok(())
@@ -1023,7 +1642,9 @@ impl ClientState {
         self.notable_event_queue.push_back(NotableEvent::MatchListUpdated(matches));
         Ok(())
     }
-    fn process_match_welcome(&mut self, match_id: String, rules: Rules) -> Result<(), ClientError> {
+    fn process_match_welcome(
+        &mut self, match_id: String, rules: Rules, server_my_name: Option<String>,
+    ) -> Result<(), ClientError> {
         if let Some(mtch) = self.mtch_mut()
             && let Some(current_match_id) = mtch.match_id()
         {
@@ -1034,22 +1655,26 @@ impl ClientState {
                 ));
             }
             assert_eq!(mtch.rules, rules);
-            // TODO: Send faction and ready status if they changed while we were
-            // disconnected from the server.
+            if let Some(server_my_name) = server_my_name {
+                mtch.my_name = server_my_name;
+            }
         } else {
-            let my_name = match &self.match_state {
-                MatchState::Creating { my_name } => my_name.clone(),
-                MatchState::Joining { match_id: id, my_name } => {
-                    if match_id != *id {
-                        // Ignore: on a slow internet connection it is possible that we tried to
-                        // connect to one match, went back and tried to connect to another match
-                        // while the first request was still being processed.
-                        return Ok(());
-                    }
-                    my_name.clone()
+            let (my_name, is_spectator) = match &self.match_state {
+                MatchState::Creating { my_name } => (my_name.clone(), false),
+                MatchState::Joining { match_id: id, .. }
+                | MatchState::JoiningAsSpectator { match_id: id }
+                    if match_id != *id =>
+                {
+                    // Ignore: on a slow internet connection it is possible that we tried to
+                    // connect to one match, went back and tried to connect to another match
+                    // while the first request was still being processed.
+                    return Ok(());
                 }
+                MatchState::Joining { my_name, .. } => (my_name.clone(), false),
+                MatchState::JoiningAsSpectator { .. } => (String::new(), true),
                 _ => return Err(internal_client_error!()),
             };
+            let my_name = server_my_name.unwrap_or(my_name);
             if let Some(engine) = &mut self.analysis_engine {
                 engine.new_match(&rules);
             }
@@ -1059,24 +1684,32 @@ impl ClientState {
             // in a `LobbyUpdated` event.
             let my_faction = Faction::Observer;
             let setup_demo_state = make_setup_demo_state(rules.clone());
+            self.last_match_rules = Some(rules.clone());
             self.match_state = MatchState::Connected(Match {
                 origin: MatchOrigin::ActiveMatch(match_id),
                 my_name,
                 my_faction,
+                game_faction: None,
+                is_spectator,
                 rules,
                 participants: Vec::new(),
+                owner: None,
                 scores: None,
+                result: None,
                 is_ready: false,
-                first_game_countdown_since: None,
+                first_game_countdown_left: None,
+                scheduled_start: None,
                 game_state: None,
                 chat: ClientChat::new(),
+                last_chat_message_sent: None,
                 setup_demo_state,
             });
         }
         Ok(())
     }
     fn process_lobby_updated(
-        &mut self, participants: Vec<Participant>, countdown_elapsed: Option<Duration>,
+        &mut self, participants: Vec<Participant>, owner: Option<String>,
+        countdown_elapsed: Option<Duration>, scheduled_start_in: Option<Duration>,
     ) -> Result<(), ClientError> {
         let now = Instant::now();
         let Some(mtch) = self.mtch_mut() else {
@@ -1087,11 +1720,30 @@ impl ClientState {
             //   - Annotate each event with a unique match ID.
             return Ok(());
         };
-        let me = participants.iter().find(|p| p.name == mtch.my_name).unwrap();
-        mtch.is_ready = me.is_ready;
-        mtch.my_faction = me.faction;
+        if !mtch.is_spectator {
+            let me = participants.iter().find(|p| p.name == mtch.my_name).unwrap();
+            mtch.is_ready = me.is_ready;
+            mtch.my_faction = me.faction;
+        }
+        let changes = if mtch.participants.is_empty() {
+            vec![]
+        } else {
+            diff_participants(&mtch.participants, &participants)
+        };
         mtch.participants = participants;
-        mtch.first_game_countdown_since = countdown_elapsed.map(|t| now - t);
+        mtch.owner = owner;
+        mtch.first_game_countdown_left =
+            countdown_elapsed.map(|t| FIRST_GAME_COUNTDOWN_DURATION.saturating_sub(t));
+        mtch.scheduled_start = scheduled_start_in.map(|t| now + t);
+        self.notable_event_queue
+            .extend(changes.into_iter().map(NotableEvent::ParticipantChanged));
+        Ok(())
+    }
+    fn process_first_game_countdown(&mut self, time_left: Duration) -> Result<(), ClientError> {
+        // Could be from an old match, see `process_lobby_updated`.
+        if let Some(mtch) = self.mtch_mut() {
+            mtch.first_game_countdown_left = Some(time_left);
+        }
         Ok(())
     }
     fn process_game_started(
@@ -1101,6 +1753,7 @@ impl ClientState {
     ) -> Result<(), ClientError> {
         let now = Instant::now();
         let mtch = self.mtch_mut().ok_or_else(|| internal_client_error!())?;
+        mtch.first_game_countdown_left = None;
         if let Some(game_state) = mtch.game_state.as_mut() {
             if game_state.game_index == game_index {
                 // This is a hot reconnect.
@@ -1124,11 +1777,6 @@ impl ClientState {
                         .collect_vec();
                     self.connection.send(BughouseClientEvent::SetTurns { turns });
                 }
-                let mtch = self.mtch_mut().unwrap();
-                let local_message = mtch.chat.local_messages().cloned().collect_vec();
-                for message in local_message {
-                    self.connection.send(BughouseClientEvent::SendChatMessage { message });
-                }
                 self.send_chalk_drawing_update();
                 // No `NotableEvent::GameStarted`: it is used to reset the UI, while we want
                 // to make reconnection experience seemless.
@@ -1136,6 +1784,9 @@ impl ClientState {
             }
         }
         // This is a new game or a cold reconnect.
+        let old_game_faction = mtch.game_faction.replace(mtch.my_faction);
+        let faction_change_applied = !mtch.is_spectator
+            && old_game_faction.is_some_and(|f| f.is_player() != mtch.my_faction.is_player());
         let time_pair = time.map(|t| WallGameTimePair::new(now, t));
         mtch.scores = Some(scores);
         let game = BughouseGame::new_with_starting_position(
@@ -1159,8 +1810,10 @@ impl ClientState {
             time_pair,
             chalkboard: Chalkboard::new(),
             chalk_canvas: ChalkCanvas::new(board_shape, perspective),
+            chalk_by_position: HashMap::new(),
             analysis_enabled: false,
             evaluation_percentages: enum_map! { _ => None },
+            evaluation_history: enum_map! { _ => HashMap::new() },
             shared_wayback_enabled: false,
             shared_wayback_turn_index: None,
             updates_applied: 0,
@@ -1178,7 +1831,7 @@ impl ClientState {
             let now = Instant::now();
             let game_now = GameInstant::from_pair_game_maybe_active(time_pair, now);
             // Unwrap ok: we just created the `game_state`.
-            let alt_game = self.alt_game_mut().unwrap();
+            let alt_game = &mut self.game_state_mut().unwrap().alt_game;
             // Unwrap ok: this is a preturn made by this very client before reconnection.
             let mode = alt_game.try_local_turn(board_idx, preturn, game_now).unwrap();
             assert_eq!(mode, TurnMode::Preturn);
@@ -1187,6 +1840,11 @@ impl ClientState {
             engine.new_game();
         }
         self.notable_event_queue.push_back(NotableEvent::GameStarted);
+        if faction_change_applied {
+            let my_faction = self.mtch().unwrap().my_faction;
+            self.notable_event_queue
+                .push_back(NotableEvent::FactionChangeApplied(my_faction));
+        }
         self.update_low_time_warnings(false);
         Ok(())
     }
@@ -1199,6 +1857,9 @@ impl ClientState {
     fn process_chat_messages(
         &mut self, messages: Vec<ChatMessage>, confirmed_local_message_id: u64,
     ) -> Result<(), ClientError> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         let mtch = self.mtch_mut().ok_or_else(|| internal_client_error!())?;
         mtch.chat.remove_confirmed_local(confirmed_local_message_id);
         for message in messages {
@@ -1206,7 +1867,16 @@ impl ClientState {
         }
         Ok(())
     }
+    fn process_match_completed(&mut self, result: MatchResult) -> Result<(), ClientError> {
+        let mtch = self.mtch_mut().ok_or_else(|| internal_client_error!())?;
+        mtch.result = Some(result.clone());
+        self.notable_event_queue.push_back(NotableEvent::MatchCompleted(result));
+        Ok(())
+    }
     fn process_chalkboard_updated(&mut self, chalkboard: Chalkboard) -> Result<(), ClientError> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         let mtch = self.mtch_mut().ok_or_else(|| internal_client_error!())?;
         let game_state = mtch.game_state.as_mut().ok_or_else(|| internal_client_error!())?;
         game_state.chalkboard = chalkboard;
@@ -1238,8 +1908,27 @@ impl ClientState {
         self.game_archive_cache.put(game_id, bpgn);
         Ok(())
     }
+    fn process_followed_player_game_started(
+        &mut self, player_name: String, match_id: String,
+    ) -> Result<(), ClientError> {
+        if self.followed_player.as_ref() != Some(&player_name) {
+            return Ok(());
+        }
+        if self.is_match_participant() || self.match_id() == Some(&match_id) {
+            return Ok(());
+        }
+        self.leave_match();
+        self.spectate(match_id);
+        Ok(())
+    }
+    fn process_server_shutting_down(&mut self, reconnect_in: Duration) -> Result<(), ClientError> {
+        self.notable_event_queue
+            .push_back(NotableEvent::ServerShuttingDown(reconnect_in));
+        Ok(())
+    }
     fn process_pong(&mut self) -> Result<(), ClientError> {
         let now = Instant::now();
+        self.connection.acknowledge_ping();
         if let Some(ping_duration) = self.connection.health_monitor.register_pong(now) {
             self.ping_meter.record_duration(ping_duration);
         }
@@ -1255,6 +1944,19 @@ impl ClientState {
             GameUpdate::TurnMade { turn_record } => {
                 self.apply_remote_turn(turn_record, generate_notable_events)
             }
+            GameUpdate::BoardResigned { time, envoy } => {
+                let alt_game = &mut game_state.alt_game;
+                if !alt_game.is_active()
+                    || alt_game.game_confirmed().is_board_resigned(envoy.board_idx)
+                {
+                    return Err(internal_client_error!(
+                        "Cannot resign board {:?}: already over",
+                        envoy.board_idx
+                    ));
+                }
+                alt_game.apply_board_resignation(envoy, time);
+                Ok(())
+            }
             GameUpdate::GameOver { time, game_status, scores } => {
                 self.apply_game_over(time, game_status, scores, generate_notable_events)
             }
@@ -1317,9 +2019,12 @@ impl ClientState {
                 // confirmed by the server.
                 self.notable_event_queue.push_back(NotableEvent::TurnMade(envoy));
             }
-            if participant_reserve_restocked(alt_game.my_id(), &turn_record) {
-                self.notable_event_queue
-                    .push_back(NotableEvent::MyReserveRestocked(envoy.board_idx.other()));
+            let restocked = restocked_pieces(alt_game.my_id(), &turn_record);
+            if !restocked.is_empty() {
+                self.notable_event_queue.push_back(NotableEvent::MyReserveRestocked(
+                    envoy.board_idx.other(),
+                    restocked,
+                ));
             }
             if !turn_record.turn_expanded.steals.is_empty() {
                 self.notable_event_queue.push_back(NotableEvent::PieceStolen);
@@ -1367,7 +2072,9 @@ impl ClientState {
             } else {
                 SubjectiveGameResult::Observation
             };
+            let review = alt_game.game_confirmed().review();
             self.notable_event_queue.push_back(NotableEvent::GameOver(game_status));
+            self.notable_event_queue.push_back(NotableEvent::GameReviewReady(review));
             // Note. It would make more sense to send performanse stats on leave, but there doesn't
             // seem to be a way to do this reliably, especially on mobile.
             self.report_performance();
@@ -1379,6 +2086,9 @@ impl ClientState {
     where
         F: FnOnce(&mut Chalkboard, String, BughouseBoard),
     {
+        if self.is_read_only() {
+            return;
+        }
         let Some(mtch) = self.mtch_mut() else {
             return;
         };
@@ -1397,7 +2107,7 @@ impl ClientState {
         let Some(mtch) = self.mtch_mut() else {
             return;
         };
-        if !mtch.is_active_match() {
+        if !mtch.is_active_match() || mtch.is_spectator {
             return;
         }
         let Some(ref mut game_state) = mtch.game_state else {
@@ -1414,7 +2124,7 @@ impl ClientState {
         self.game_state().is_some_and(|s| s.shared_wayback_enabled)
     }
     pub fn set_shared_wayback(&mut self, enabled: bool) {
-        if !self.is_active_match() {
+        if !self.is_active_match() || self.is_spectator() {
             return;
         }
         let mut wayback_to_turn = None;
@@ -1456,15 +2166,40 @@ impl ClientState {
         if alt_game.is_active() {
             return Err(());
         }
+        let old_position = alt_game.wayback_state().display_turn_index();
         let turn_index = alt_game.wayback_to(destination, board_idx);
         let wayback = alt_game.wayback_state();
         if *analysis_enabled && let Some(engine) = &mut self.analysis_engine {
             let analysis_board_idx = get_board_index(ANALYSIS_BOARD_IDX, alt_game.perspective());
             engine.analyze_position(&alt_game.true_local_game(), analysis_board_idx);
         }
+        self.rebind_chalk_to_position(old_position, wayback.display_turn_index());
         self.notable_event_queue.push_back(NotableEvent::WaybackStateUpdated(wayback));
         Ok(turn_index)
     }
+    // Stashes own chalk drawing for the old position and restores the one for the new position.
+    fn rebind_chalk_to_position(
+        &mut self, old_position: Option<TurnIndex>, new_position: Option<TurnIndex>,
+    ) {
+        if old_position == new_position {
+            return;
+        }
+        let Some(mtch) = self.mtch_mut() else {
+            return;
+        };
+        let Some(ref mut game_state) = mtch.game_state else {
+            return;
+        };
+        let drawing = game_state.chalkboard.drawings_by(&mtch.my_name).cloned().unwrap_or_default();
+        if let Some(old_position) = old_position {
+            game_state.chalk_by_position.insert(old_position, drawing);
+        }
+        let new_drawing = new_position
+            .and_then(|index| game_state.chalk_by_position.remove(&index))
+            .unwrap_or_default();
+        game_state.chalkboard.set_drawing(mtch.my_name.clone(), new_drawing);
+        self.send_chalk_drawing_update();
+    }
 
     pub fn view_archive_game_list(&mut self) {
         self.connection.send(BughouseClientEvent::GetArchiveGameList);
@@ -1481,6 +2216,108 @@ impl ClientState {
         }
     }
 
+    // Opens a local board where the user can move freely for all sides. Leaves the current match,
+    // if any.
+    pub fn enter_study_mode(&mut self, rules: Rules) {
+        self.leave_match();
+        let mut game =
+            BughouseGame::new(rules.clone(), Role::Client, &BughouseGame::stub_players());
+        for board_idx in BughouseBoard::iter() {
+            game.board_mut(board_idx).clock_mut().erase_time();
+        }
+        let alt_game = AlteredGame::new_study(game);
+        let board_shape = alt_game.board_shape();
+        let perspective = alt_game.perspective();
+        let game_state = GameState {
+            is_demo: false,
+            game_index: 0,
+            alt_game,
+            time_pair: None,
+            chalkboard: Chalkboard::new(),
+            chalk_canvas: ChalkCanvas::new(board_shape, perspective),
+            chalk_by_position: HashMap::new(),
+            analysis_enabled: false,
+            evaluation_percentages: enum_map! { _ => None },
+            evaluation_history: enum_map! { _ => HashMap::new() },
+            shared_wayback_enabled: false,
+            shared_wayback_turn_index: None,
+            updates_applied: 0,
+            next_low_time_warning_idx: enum_map! { _ => 0 },
+            awaiting_turn_confirmation_since: enum_map! { _ => None },
+        };
+        if let Some(engine) = &mut self.analysis_engine {
+            engine.new_match(&rules);
+            engine.new_game();
+        }
+        let setup_demo_state = make_setup_demo_state(rules.clone()); // wouldn't be used
+        self.match_state = MatchState::Connected(Match {
+            origin: MatchOrigin::Study,
+            my_name: String::new(),
+            my_faction: Faction::Observer,
+            game_faction: None,
+            is_spectator: false,
+            rules,
+            participants: Vec::new(),
+            owner: None,
+            scores: None,
+            result: None,
+            is_ready: false,
+            first_game_countdown_left: None,
+            scheduled_start: None,
+            game_state: Some(game_state),
+            chat: ClientChat::new(),
+            last_chat_message_sent: None,
+            setup_demo_state,
+        });
+    }
+
+    pub fn set_study_reserve(
+        &mut self, display_board: DisplayBoard, force: Force, piece_kind: PieceKind, amount: u8,
+    ) -> Result<(), ClientError> {
+        let Some(mtch) = self.mtch_mut().filter(|mtch| mtch.is_study()) else {
+            return Err(ClientError::Ignorable("Not in study mode".to_owned()));
+        };
+        let alt_game = &mut mtch.game_state.as_mut().unwrap().alt_game;
+        let board_idx = get_board_index(display_board, alt_game.perspective());
+        alt_game.set_study_reserve(board_idx, force, piece_kind, amount);
+        Ok(())
+    }
+
+    // Paste-friendly move list for one board, e.g. "1. e4 e5 2. Nf3 Nc6". `None` if there is no
+    // game or if the moves are hidden by fog of war.
+    pub fn move_list_text(&self, board_idx: BughouseBoard) -> Option<String> {
+        let moves = self.confirmed_move_list()?;
+        Some(format_board_move_list(
+            moves.into_iter().filter(|m| m.envoy.board_idx == board_idx),
+        ))
+    }
+    // Both boards interleaved in the order the turns were made, e.g. "1A.e4 1B.d4 1a.d5".
+    pub fn move_list_text_both_boards(&self) -> Option<String> {
+        Some(self.confirmed_move_list()?.iter().join(" "))
+    }
+    fn confirmed_move_list(&self) -> Option<Vec<SanMove>> {
+        let alt_game = &self.game_state()?.alt_game;
+        let game = alt_game.game_confirmed();
+        if game.chess_rules().fog_of_war && game.is_active() && alt_game.my_id().is_player() {
+            return None;
+        }
+        Some(game.move_list_san(AlgebraicCharset::Ascii))
+    }
+
+    // Describes the latest confirmed turn on the board for screen readers. In fog of war, turns by
+    // the opponents are not announced while the game is active.
+    pub fn last_move_announcement(&self, board_idx: BughouseBoard) -> Option<String> {
+        let alt_game = &self.game_state()?.alt_game;
+        let game = alt_game.game_confirmed();
+        if game.chess_rules().fog_of_war && game.is_active() {
+            let record = game.turn_log().iter().rev().find(|r| r.envoy.board_idx == board_idx)?;
+            if alt_game.my_id().as_player().is_some_and(|p| p.team() != record.envoy.team()) {
+                return None;
+            }
+        }
+        game.last_turn_announcement(board_idx)
+    }
+
     pub fn get_game_bpgn(&mut self) -> Option<String> {
         let mtch = self.mtch()?;
         let game_id = mtch.archive_game_id()?;
@@ -1509,6 +2346,9 @@ impl ClientState {
                 scheduling_priority: PlayerSchedulingPriority::default(),
                 is_online: true,
                 is_ready: false,
+                handicap: Reserve::default(),
+                rating: None,
+                show_rating: true,
             })
             .collect_vec();
 
@@ -1543,8 +2383,10 @@ impl ClientState {
             time_pair: None,
             chalkboard: Chalkboard::new(),
             chalk_canvas: ChalkCanvas::new(board_shape, perspective),
+            chalk_by_position: HashMap::new(),
             analysis_enabled: false,
             evaluation_percentages: enum_map! { _ => None },
+            evaluation_history: enum_map! { _ => HashMap::new() },
             shared_wayback_enabled: false,
             shared_wayback_turn_index: None,
             updates_applied: 0,
@@ -1569,13 +2411,19 @@ impl ClientState {
             origin: MatchOrigin::ArchiveGame(game_id),
             my_name: String::new(),
             my_faction: Faction::Observer,
+            game_faction: None,
+            is_spectator: false,
             rules,
             participants,
+            owner: None,
             scores: Some(scores),
+            result: None,
             is_ready: false,
-            first_game_countdown_since: None,
+            first_game_countdown_left: None,
+            scheduled_start: None,
             game_state: Some(game_state),
             chat,
+            last_chat_message_sent: None,
             setup_demo_state,
         });
         self.notable_event_queue.push_back(NotableEvent::ArchiveGameLoaded(game_id));
@@ -1639,6 +2487,24 @@ impl ClientState {
     }
 }
 
+// Turn number is written before White's turn, or with an ellipsis if a number starts with Black.
+fn format_board_move_list(moves: impl IntoIterator<Item = SanMove>) -> String {
+    let mut words = vec![];
+    let mut prev_number = None;
+    for m in moves {
+        if prev_number != Some(m.number) {
+            let dots = match m.envoy.force {
+                Force::White => ".",
+                Force::Black => "...",
+            };
+            words.push(format!("{}{dots}", m.number));
+            prev_number = Some(m.number);
+        }
+        words.push(m.algebraic);
+    }
+    words.join(" ")
+}
+
 fn make_setup_demo_state(rules: Rules) -> GameState {
     let starting_position = EffectiveStartingPosition::manual_duplicate(&Board::new_setup_demo(
         rules.clone(),
@@ -1667,8 +2533,10 @@ fn make_setup_demo_state(rules: Rules) -> GameState {
         time_pair: None,
         chalkboard: Chalkboard::new(),
         chalk_canvas: ChalkCanvas::new(board_shape, perspective),
+        chalk_by_position: HashMap::new(),
         analysis_enabled: false,
         evaluation_percentages: enum_map! { _ => None },
+        evaluation_history: enum_map! { _ => HashMap::new() },
         shared_wayback_enabled: false,
         shared_wayback_turn_index: None,
         updates_applied: 0,
@@ -1677,18 +2545,49 @@ fn make_setup_demo_state(rules: Rules) -> GameState {
     }
 }
 
-fn participant_reserve_restocked(
+// Returns pieces that the turn added to the participant's reserve on the other board.
+fn restocked_pieces(
     participant_id: BughouseParticipant, turn_record: &TurnRecordExpanded,
-) -> bool {
-    turn_record.turn_expanded.captures.iter().any(|c| {
-        let Ok(force) = c.force.try_into() else {
-            return false;
-        };
-        participant_id.plays_for(BughouseEnvoy {
-            force,
-            board_idx: turn_record.envoy.board_idx.other(),
+) -> Vec<PieceKind> {
+    turn_record
+        .turn_expanded
+        .captures
+        .iter()
+        .filter(|c| {
+            let Ok(force) = c.force.try_into() else {
+                return false;
+            };
+            participant_id.plays_for(BughouseEnvoy {
+                force,
+                board_idx: turn_record.envoy.board_idx.other(),
+            })
         })
-    })
+        .map(|c| c.piece_kind)
+        .collect()
+}
+
+// Participants are matched by name, which is fixed for the entire match.
+fn diff_participants(old: &[Participant], new: &[Participant]) -> Vec<ParticipantChange> {
+    let mut changes = vec![];
+    for p in new {
+        match old.iter().find(|old_p| old_p.name == p.name) {
+            None => changes.push(ParticipantChange::Joined(p.name.clone())),
+            Some(old_p) => {
+                if old_p.faction != p.faction {
+                    changes.push(ParticipantChange::FactionChanged(p.name.clone(), p.faction));
+                }
+                if old_p.is_ready != p.is_ready {
+                    changes.push(ParticipantChange::ReadinessChanged(p.name.clone(), p.is_ready));
+                }
+            }
+        }
+    }
+    for old_p in old {
+        if !new.iter().any(|p| p.name == old_p.name) {
+            changes.push(ParticipantChange::Left(old_p.name.clone()));
+        }
+    }
+    changes
 }
 
 fn my_time_left(
@@ -1700,6 +2599,68 @@ fn my_time_left(
         .map(|e| alt_game.local_game().board(e.board_idx).clock().time_left(e.force, now))
 }
 
+// Events that are only meaningful within the match we are connected to.
+fn is_match_event(event: &BughouseServerEvent) -> bool {
+    match event {
+        BughouseServerEvent::LobbyUpdated { .. }
+        | BughouseServerEvent::FirstGameCountdown { .. }
+        | BughouseServerEvent::GameStarted { .. }
+        | BughouseServerEvent::GameUpdated { .. }
+        | BughouseServerEvent::ChatMessages { .. }
+        | BughouseServerEvent::MatchCompleted { .. }
+        | BughouseServerEvent::ChalkboardUpdated { .. }
+        | BughouseServerEvent::SharedWaybackUpdated { .. } => true,
+        BughouseServerEvent::Rejection(_)
+        | BughouseServerEvent::ServerWelcome { .. }
+        | BughouseServerEvent::UpdateSession { .. }
+        | BughouseServerEvent::MatchList { .. }
+        | BughouseServerEvent::MatchWelcome { .. }
+        | BughouseServerEvent::ArchiveGameList { .. }
+        | BughouseServerEvent::ArchiveGameBpgn { .. }
+        | BughouseServerEvent::FollowedPlayerGameStarted { .. }
+        | BughouseServerEvent::ServerShuttingDown { .. }
+        | BughouseServerEvent::Pong => false,
+    }
+}
+
+// Events that read-only clients may send: spectating and connection management.
+fn allowed_for_read_only(event: &BughouseClientEvent) -> bool {
+    match event {
+        BughouseClientEvent::Spectate { .. }
+        | BughouseClientEvent::FollowPlayer { .. }
+        | BughouseClientEvent::LeaveMatch
+        | BughouseClientEvent::LeaveServer
+        | BughouseClientEvent::GetArchiveGameList
+        | BughouseClientEvent::GetArchiveGameBpgn { .. }
+        | BughouseClientEvent::ReportPerformace(_)
+        | BughouseClientEvent::ReportError(_)
+        | BughouseClientEvent::Ping => true,
+        BughouseClientEvent::NewMatch { .. }
+        | BughouseClientEvent::Join { .. }
+        | BughouseClientEvent::HotReconnect { .. }
+        | BughouseClientEvent::SetFaction { .. }
+        | BughouseClientEvent::SetTurns { .. }
+        | BughouseClientEvent::MakeTurn { .. }
+        | BughouseClientEvent::CancelPreturn { .. }
+        | BughouseClientEvent::Resign
+        | BughouseClientEvent::ResignBoard
+        | BughouseClientEvent::RequestAbort
+        | BughouseClientEvent::SetReady { .. }
+        | BughouseClientEvent::SetShowRating { .. }
+        | BughouseClientEvent::RequestRematch { .. }
+        | BughouseClientEvent::SetHandicap { .. }
+        | BughouseClientEvent::TransferMatchOwnership { .. }
+        | BughouseClientEvent::SendChatMessage { .. }
+        | BughouseClientEvent::UpdateChalkDrawing { .. }
+        | BughouseClientEvent::SetSharedWayback { .. } => false,
+    }
+}
+
+fn chat_slow_mode_message(cooldown_left: Duration) -> String {
+    let seconds = cooldown_left.as_secs_f64().ceil() as u64;
+    format!("Slow mode is on. You can send the next message in {seconds} s.")
+}
+
 // Improvement potential. Add TurnError payload to make error messages even more useful.
 fn turn_error_message(err: TurnError, rules: &ChessRules) -> Option<String> {
     // We return `None` for errors that are either internal or trivial.
@@ -1732,6 +2693,7 @@ fn turn_error_message(err: TurnError, rules: &ChessRules) -> Option<String> {
         TurnError::UnprotectedKing => Some("King is unprotected.".to_owned()),
         TurnError::CastlingPieceHasMoved => Some("Cannot castle: piece has moved.".to_owned()),
         TurnError::CannotCastleDroppedKing => Some("Cannot castle: king was dropped.".to_owned()),
+        TurnError::CastlingForbidden => Some("Castling is not allowed in this game.".to_owned()),
         TurnError::BadPromotionType => Some(format!(
             "Bad promotion type, expected “{}”",
             rules.promotion().to_human_readable()
@@ -1741,6 +2703,10 @@ fn turn_error_message(err: TurnError, rules: &ChessRules) -> Option<String> {
         TurnError::InvalidUpgradePromotionTarget => Some("Invalid promotion target".to_owned()),
         TurnError::InvalidStealPromotionTarget => Some("Invalid steal target".to_owned()),
         TurnError::DropRequiresBughouse => None,
+        TurnError::DropsNotYetAllowed => Some(format!(
+            "Drops are allowed starting from move {}.",
+            bughouse_rules().no_drop_turns + 1
+        )),
         TurnError::DropPieceMissing => Some("Reserve piece is missing.".to_owned()),
         TurnError::InvalidPawnDropRank => Some(format!(
             "Pawns must be dropped on ranks {} from the player",
@@ -1748,6 +2714,15 @@ fn turn_error_message(err: TurnError, rules: &ChessRules) -> Option<String> {
         )),
         TurnError::DropBlocked => None,
         TurnError::DropAggression => Some(drop_aggression().to_owned()),
+        TurnError::InterposingDrop => {
+            Some("Cannot drop pieces to block a check: move the king or capture.".to_owned())
+        }
+        TurnError::PieceCapExceeded => bughouse_rules().piece_cap.map(|cap| {
+            format!(
+                "Cannot capture: reserve would exceed the cap of {} pieces per player.",
+                cap.max_pieces
+            )
+        }),
         TurnError::StealTargetMissing => Some("Steal target is missing.".to_owned()),
         TurnError::StealTargetInvalid => Some("Steal target is invalid.".to_owned()),
         TurnError::ExposingKingByStealing => Some("Cannot expose king by stealing.".to_owned()),
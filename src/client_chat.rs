@@ -89,14 +89,19 @@ impl ClientChat {
         .collect()
     }
 
+    // Messages normally arrive in order, but messages withheld during a game (see `RatedGameChat`)
+    // arrive later and must be inserted in the middle.
     pub fn add_static(&mut self, message: ChatMessage) {
-        if let Some(latest) = self.static_messages.back() {
-            if message.message_id <= latest.message_id {
-                return;
-            }
+        let pos = self.static_messages.partition_point(|m| m.message_id < message.message_id);
+        if self
+            .static_messages
+            .get(pos)
+            .is_some_and(|m| m.message_id == message.message_id)
+        {
+            return;
         }
         self.remove_ephemeral();
-        self.static_messages.push_back(message);
+        self.static_messages.insert(pos, message);
         while self.static_messages.len() > MAX_CHAT_MESSAGES {
             self.static_messages.pop_front();
         }
@@ -126,6 +131,9 @@ impl ClientChat {
     pub fn remove_confirmed_local(&mut self, confirmed_local_message_id: u64) {
         self.local_messages.retain(|m| m.local_message_id > confirmed_local_message_id)
     }
+    pub fn remove_rejected_local(&mut self, local_message_id: u64) {
+        self.local_messages.retain(|m| m.local_message_id != local_message_id)
+    }
     pub fn remove_ephemeral(&mut self) { self.ephemeral_message = None; }
 }
 
@@ -194,6 +202,16 @@ fn static_message_to_item(
                 flash: highlight,
             })
         }
+        ChatMessageBody::AbortRequested { participant } => Some(ChatItem {
+            id,
+            durability: ChatItemDurability::Static,
+            text: format!("{participant} offers to abort the game (type /abort to agree)"),
+            sender: Some(ChatParty::System(SystemMessageClass::Info)),
+            recipient: None,
+            dimmed: old_game,
+            prominent: !old_game,
+            flash: false,
+        }),
         ChatMessageBody::NextGamePlayers { players } => {
             if old_game {
                 // Improvement potential. Consider if this is confusing. Can we reduce chat clutter
@@ -238,6 +256,8 @@ pub fn cannot_start_game_message(error: ParticipantsError) -> &'static str {
         ParticipantsError::NotEnoughPlayers => "Not enough players",
         ParticipantsError::EmptyTeam => "A team is empty",
         ParticipantsError::RatedDoublePlay => "Cannot play on two boards in rated",
+        ParticipantsError::RatedGuestPlayer => "Only registered users can play rated games",
+        ParticipantsError::GuestPlayer => "Only registered users can play in this match",
     }
 }
 
@@ -0,0 +1,123 @@
+// Client-side preferences that can be exported as a string and imported on another device.
+//
+// Settings are kept as raw strings, exactly as stored in browser local storage. Only keys listed in
+// `CLIENT_SETTINGS` are exported. Unknown keys are ignored on import, so that a string exported by
+// a newer client could still be used with an older one.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+
+// Increment when the meaning of existing keys changes. Adding keys doesn't require a new version.
+pub const CLIENT_SETTINGS_VERSION: u32 = 1;
+
+const CLIENT_SETTINGS_PREFIX: &str = "bughouse-settings-v";
+
+// Local storage keys that are exported. Must match `Storage` in `index.js`.
+pub const CLIENT_SETTINGS: &[&str] = &[
+    "chat-reference-tooltip",
+    "move-input-method",
+    "board-flip-animation",
+    "highlight-updates",
+    "turn-log-scroll",
+    "reserve-restock-alert",
+    "coordinate-style",
+    "drop-highlight",
+    "show-rating",
+    "move-announcements",
+    "time-trouble-threshold",
+    "frame-budget",
+    "theme-colors",
+    "board-scale",
+];
+
+// Board size relative to the default. Applies to both boards and reserves.
+pub const DEFAULT_BOARD_SCALE: f64 = 1.0;
+pub const MIN_BOARD_SCALE: f64 = 0.5;
+pub const MAX_BOARD_SCALE: f64 = 2.0;
+
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ClientSettings {
+    values: BTreeMap<String, String>,
+}
+
+impl ClientSettings {
+    pub fn new() -> Self { Self::default() }
+
+    pub fn get(&self, key: &str) -> Option<&str> { self.values.get(key).map(String::as_str) }
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    // Unknown keys are silently dropped.
+    pub fn set(&mut self, key: &str, value: String) -> Result<(), String> {
+        if !CLIENT_SETTINGS.contains(&key) {
+            return Ok(());
+        }
+        if !is_valid_value(key, &value) {
+            return Err(format!("Invalid value for setting \"{key}\": \"{value}\""));
+        }
+        self.values.insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    pub fn export(&self) -> String {
+        let values = serde_json::to_string(&self.values).unwrap();
+        format!("{CLIENT_SETTINGS_PREFIX}{CLIENT_SETTINGS_VERSION}:{values}")
+    }
+
+    pub fn import(s: &str) -> Result<Self, String> {
+        let invalid_format = || "Invalid settings string".to_owned();
+        let s = s.trim().strip_prefix(CLIENT_SETTINGS_PREFIX).ok_or_else(invalid_format)?;
+        let (version, values) = s.split_once(':').ok_or_else(invalid_format)?;
+        let version: u32 = version.parse().map_err(|_| invalid_format())?;
+        if version > CLIENT_SETTINGS_VERSION {
+            return Err("Settings were exported by a newer version of the client".to_owned());
+        }
+        let values: BTreeMap<String, String> =
+            serde_json::from_str(values).map_err(|_| invalid_format())?;
+        let mut settings = ClientSettings::new();
+        for (key, value) in values {
+            settings.set(&key, value)?;
+        }
+        Ok(settings)
+    }
+}
+
+// Brings board scale into the supported range. Falls back to the default if the scale is NaN.
+pub fn clamp_board_scale(scale: f64) -> f64 {
+    if scale.is_nan() {
+        DEFAULT_BOARD_SCALE
+    } else {
+        scale.clamp(MIN_BOARD_SCALE, MAX_BOARD_SCALE)
+    }
+}
+
+fn is_valid_value(key: &str, value: &str) -> bool {
+    let one_of = |options: &[&str]| options.contains(&value);
+    let off_or_in_range = |min: u64, max: u64| {
+        value == "off" || value.parse::<u64>().is_ok_and(|v| (min..=max).contains(&v))
+    };
+    match key {
+        "chat-reference-tooltip" => one_of(&["show", "hide"]),
+        "move-input-method" => one_of(&["any", "drag", "click"]),
+        "board-flip-animation" => one_of(&["on", "off"]),
+        "highlight-updates" => one_of(&["incremental", "full"]),
+        "turn-log-scroll" => one_of(&["freeze", "follow"]),
+        "reserve-restock-alert" => one_of(&["sound", "flash", "announce", "both"]),
+        "coordinate-style" => one_of(&["off", "edge", "all"]),
+        "drop-highlight" => one_of(&["fill", "marker"]),
+        "show-rating" => one_of(&["show", "hide"]),
+        "move-announcements" => one_of(&["on", "off"]),
+        "time-trouble-threshold" => off_or_in_range(1, 20),
+        "frame-budget" => off_or_in_range(1, 10_000),
+        // Individual colors are validated by the web client.
+        "theme-colors" => serde_json::from_str::<BTreeMap<String, String>>(value).is_ok(),
+        "board-scale" => value
+            .parse::<f64>()
+            .is_ok_and(|v| (MIN_BOARD_SCALE..=MAX_BOARD_SCALE).contains(&v)),
+        _ => false,
+    }
+}
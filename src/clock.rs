@@ -16,18 +16,38 @@ pub struct TimeControl {
     // Must be a whole number of seconds.
     // Improvement potential. A Duration type that statically guarantees this.
     pub starting_time: Duration,
-    // Improvement potential. Support increment, delay, etc.
+    // Added to the player's clock after each turn. Must be a whole number of seconds.
+    pub increment: Duration,
+    // Increment is not granted for the first `no_increment_turns` full turns on each board. This
+    // discourages premove spamming in the opening.
+    pub no_increment_turns: u32,
+    // Improvement potential. Support delay, etc.
     //   Note that `Clock::total_time_elapsed` should be adjusted in this case.
 }
 
+impl TimeControl {
+    pub fn sudden_death(starting_time: Duration) -> Self {
+        TimeControl {
+            starting_time,
+            increment: Duration::ZERO,
+            no_increment_turns: 0,
+        }
+    }
+}
+
 impl fmt::Display for TimeControl {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        format_duration_to_mss(self.starting_time, f)
+        format_duration_to_mss(self.starting_time, f)?;
+        if !self.increment.is_zero() {
+            write!(f, "+{}", self.increment.as_secs())?;
+        }
+        Ok(())
     }
 }
 
 const MILLIS_PER_SEC: u64 = 1000;
 const MILLIS_PER_DECI: u64 = MILLIS_PER_SEC / 10;
+const SECS_PER_HOUR: u64 = 3600;
 
 pub fn duration_to_mss(d: Duration) -> String {
     let mut ret = String::new();
@@ -287,9 +307,10 @@ pub struct ClockShowing {
     pub time_breakdown: TimeBreakdown,
 }
 
-// Improvement potential: Support longer time controls (with hours).
+// Ranges: `LowTime` below 20 seconds, `NormalTime` below an hour, `LongTime` from an hour onwards.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TimeBreakdown {
+    LongTime { hours: u32, minutes: u32, seconds: u32 },
     NormalTime { minutes: u32, seconds: u32 },
     LowTime { seconds: u32, deciseconds: u32 },
     Unknown,
@@ -315,6 +336,10 @@ impl ClockShowing {
     pub fn ui_string(&self) -> String {
         let separator = |s| if self.show_separator { s } else { " " };
         match self.time_breakdown {
+            TimeBreakdown::LongTime { hours, minutes, seconds } => {
+                let sep = separator(":");
+                format!("{}{}{:02}{}{:02}", hours, sep, minutes, sep, seconds)
+            }
             TimeBreakdown::NormalTime { minutes, seconds } => {
                 format!("{:02}{}{:02}", minutes, separator(":"), seconds)
             }
@@ -324,6 +349,22 @@ impl ClockShowing {
             TimeBreakdown::Unknown => "-:--".to_string(),
         }
     }
+
+    // Whether the clock shows less than `threshold`. Based on the displayed value rather than the
+    // exact time, so that the alert appears in sync with the digits. Thresholds beyond the
+    // `LowTime` range are effectively capped by it. A flagged clock is not in time trouble: it's
+    // already too late.
+    pub fn in_time_trouble(&self, threshold: Duration) -> bool {
+        match self.time_breakdown {
+            TimeBreakdown::LowTime { seconds, deciseconds } if !self.out_of_time => {
+                let shown = Duration::from_millis(
+                    u64::from(seconds) * MILLIS_PER_SEC + u64::from(deciseconds) * MILLIS_PER_DECI,
+                );
+                shown < threshold
+            }
+            _ => false,
+        }
+    }
 }
 
 impl ClockDifference {
@@ -363,9 +404,17 @@ impl From<GameDuration> for TimeBreakdown {
             TimeBreakdown::LowTime { seconds, deciseconds }
         } else {
             let s_ceil = millis.div_ceil(MILLIS_PER_SEC);
-            let minutes = (s_ceil / 60).try_into().unwrap();
-            let seconds = (s_ceil % 60).try_into().unwrap();
-            TimeBreakdown::NormalTime { minutes, seconds }
+            if s_ceil < SECS_PER_HOUR {
+                let minutes = (s_ceil / 60).try_into().unwrap();
+                let seconds = (s_ceil % 60).try_into().unwrap();
+                TimeBreakdown::NormalTime { minutes, seconds }
+            } else {
+                // Saturate rather than panic: absurdly long times are still better than a crash.
+                let hours = (s_ceil / SECS_PER_HOUR).try_into().unwrap_or(u32::MAX);
+                let minutes = (s_ceil % SECS_PER_HOUR / 60).try_into().unwrap();
+                let seconds = (s_ceil % 60).try_into().unwrap();
+                TimeBreakdown::LongTime { hours, minutes, seconds }
+            }
         }
     }
 }
@@ -399,6 +448,9 @@ pub struct Clock {
     measurement: TimeMeasurement,
     turn_state: Option<(Force, GameInstant)>, // force, start time
     remaining_time: EnumMap<Force, GameDuration>,
+    increment_granted: EnumMap<Force, GameDuration>,
+    // Time since game start during which the clock doesn't run for the given force.
+    start_delay: EnumMap<Force, GameDuration>,
 }
 
 impl Clock {
@@ -409,9 +461,15 @@ impl Clock {
             measurement,
             turn_state: None,
             remaining_time,
+            increment_granted: enum_map! { _ => GameDuration::ZERO },
+            start_delay: enum_map! { _ => GameDuration::ZERO },
         }
     }
 
+    pub fn set_start_delay(&mut self, force: Force, delay: GameDuration) {
+        self.start_delay[force] = delay;
+    }
+
     pub fn is_active(&self) -> bool { self.turn_state.is_some() }
     pub fn active_force(&self) -> Option<Force> { self.turn_state.map(|st| st.0) }
     pub fn turn_start(&self) -> Option<GameInstant> { self.turn_state.map(|st| st.1) }
@@ -420,7 +478,7 @@ impl Clock {
         let mut ret = self.remaining_time[force];
         if let Some((current_force, current_start)) = self.turn_state {
             if force == current_force {
-                ret = ret.saturating_sub(now.duration_since(current_start, self.measurement));
+                ret = ret.saturating_sub(self.time_spent(force, current_start, now));
             }
         }
         ret
@@ -429,8 +487,8 @@ impl Clock {
     pub fn time_excess(&self, force: Force, now: GameInstant) -> Option<GameDuration> {
         if let Some((current_force, current_start)) = self.turn_state {
             if force == current_force {
-                return now
-                    .duration_since(current_start, self.measurement)
+                return self
+                    .time_spent(force, current_start, now)
                     .checked_sub(self.remaining_time[force]);
             }
         } else if self.remaining_time[force].is_zero() {
@@ -459,7 +517,9 @@ impl Clock {
 
         let show_separator = match (is_active, time_breakdown) {
             (false, _) => true,
-            (true, TimeBreakdown::NormalTime { .. }) => time.subsec_millis().unwrap_or(0) >= 500,
+            (true, TimeBreakdown::LongTime { .. } | TimeBreakdown::NormalTime { .. }) => {
+                time.subsec_millis().unwrap_or(0) >= 500
+            }
             (true, TimeBreakdown::LowTime { .. }) => true,
             (true, TimeBreakdown::Unknown) => true,
         };
@@ -498,14 +558,21 @@ impl Clock {
     }
 
     pub fn total_time_elapsed(&self) -> GameDuration {
-        // Note. This assumes no time increments, delays, etc.
+        // Note. This assumes no time delays, etc.
         Force::iter()
             .map(|force| {
-                GameDuration::from(self.control.starting_time) - self.remaining_time[force]
+                (GameDuration::from(self.control.starting_time) + self.increment_granted[force])
+                    - self.remaining_time[force]
             })
             .sum()
     }
 
+    // Time counted against `force` for the turn that started at `turn_start`.
+    fn time_spent(&self, force: Force, turn_start: GameInstant, now: GameInstant) -> GameDuration {
+        let uncounted = self.start_delay[force].saturating_sub(turn_start.elapsed_since_start());
+        now.duration_since(turn_start, self.measurement).saturating_sub(uncounted)
+    }
+
     pub fn new_turn(&mut self, new_force: Force, now: GameInstant) {
         if let Some((prev_force, _)) = self.turn_state {
             let remaining = self.time_left(prev_force, now);
@@ -522,6 +589,18 @@ impl Clock {
         self.turn_state = Some((new_force, now));
     }
 
+    // Grants increment to `force` for the turn they've just finished. `full_turn_index` is the
+    // number of that turn on this board, as in FEN.
+    pub fn add_increment(&mut self, force: Force, full_turn_index: u32) {
+        if self.control.increment.is_zero() || full_turn_index <= self.control.no_increment_turns {
+            return;
+        }
+        assert_ne!(self.active_force(), Some(force));
+        let increment = GameDuration::from(self.control.increment);
+        self.remaining_time[force] = self.remaining_time[force] + increment;
+        self.increment_granted[force] = self.increment_granted[force] + increment;
+    }
+
     pub fn stop(&mut self, now: GameInstant) {
         if let Some((prev_force, _)) = self.turn_state {
             let remaining = self.time_left(prev_force, now);
@@ -573,7 +652,6 @@ mod tests {
     #[test]
     fn time_breakdown() {
         use TimeBreakdown::*;
-        let inf = GameDuration::from_millis(1_000_000);
         let cases = [
             (0, LowTime { seconds: 0, deciseconds: 0 }),
             (1, LowTime { seconds: 0, deciseconds: 1 }),
@@ -593,7 +671,13 @@ mod tests {
             (60_001, NormalTime { minutes: 1, seconds: 1 }),
             (119_000, NormalTime { minutes: 1, seconds: 59 }),
             (119_001, NormalTime { minutes: 2, seconds: 0 }),
+            (3_599_000, NormalTime { minutes: 59, seconds: 59 }),
+            (3_599_001, LongTime { hours: 1, minutes: 0, seconds: 0 }),
+            (3_600_000, LongTime { hours: 1, minutes: 0, seconds: 0 }),
+            (3_600_001, LongTime { hours: 1, minutes: 0, seconds: 1 }),
+            (37_230_000, LongTime { hours: 10, minutes: 20, seconds: 30 }),
         ];
+        let inf = GameDuration::from_millis(100_000_000);
         for (millis, breakdown) in cases {
             let time_left = GameDuration::from_millis(millis);
             assert_eq!(TimeBreakdown::from(time_left), breakdown);
@@ -616,6 +700,123 @@ mod tests {
         }
     }
 
+    #[test]
+    fn clock_showing_at_zero() {
+        let control = TimeControl::sudden_death(Duration::from_secs(60));
+        let mut clock = Clock::new(control, TimeMeasurement::Exact);
+        clock.new_turn(Force::White, game_t!(0));
+
+        // An active player is never shown as out of time: the game might have ended on the other
+        // board earlier.
+        let showing = clock.showing_for(Force::White, game_t!(60 s));
+        assert!(!showing.out_of_time);
+        assert_eq!(showing.time_breakdown, TimeBreakdown::LowTime { seconds: 0, deciseconds: 1 });
+        assert_eq!(showing.ui_string(), "00.1 ");
+
+        // Time past the flag is clamped to zero.
+        let showing = clock.showing_for(Force::White, game_t!(61 s));
+        assert!(!showing.out_of_time);
+        assert_eq!(showing.ui_string(), "00.1 ");
+
+        clock.stop(game_t!(61 s));
+        let showing = clock.showing_for(Force::White, game_t!(61 s));
+        assert!(showing.out_of_time);
+        assert_eq!(showing.time_breakdown, TimeBreakdown::LowTime { seconds: 0, deciseconds: 0 });
+        assert_eq!(showing.ui_string(), "00.0 ");
+    }
+
+    #[test]
+    fn clock_time_trouble() {
+        let control = TimeControl::sudden_death(Duration::from_secs(60));
+        let threshold = Duration::from_secs(10);
+        let mut clock = Clock::new(control, TimeMeasurement::Exact);
+        clock.new_turn(Force::White, game_t!(0));
+
+        let in_time_trouble =
+            |clock: &Clock, force, now| clock.showing_for(force, now).in_time_trouble(threshold);
+        assert!(!in_time_trouble(&clock, Force::White, game_t!(45 s)));
+        assert!(!in_time_trouble(&clock, Force::White, game_t!(50 s)));
+        assert!(in_time_trouble(&clock, Force::White, game_t!(50_100 ms)));
+        assert!(in_time_trouble(&clock, Force::White, game_t!(59_999 ms)));
+        // The other player's clock is not affected.
+        assert!(!in_time_trouble(&clock, Force::Black, game_t!(59_999 ms)));
+
+        // The alert persists while the clock is stopped below the threshold...
+        clock.new_turn(Force::Black, game_t!(55 s));
+        assert!(in_time_trouble(&clock, Force::White, game_t!(56 s)));
+        // ...and is gone once the time is back above it.
+        clock.remaining_time[Force::White] = GameDuration::from_secs(15);
+        assert!(!in_time_trouble(&clock, Force::White, game_t!(56 s)));
+
+        // Thresholds beyond the `LowTime` range are capped.
+        assert!(!clock.showing_for(Force::Black, game_t!(56 s)).in_time_trouble(Duration::MAX));
+
+        // A flagged clock is not in time trouble.
+        clock.remaining_time[Force::White] = GameDuration::ZERO;
+        assert!(!in_time_trouble(&clock, Force::White, game_t!(56 s)));
+    }
+
+    #[test]
+    fn clock_increment() {
+        let control = TimeControl {
+            starting_time: Duration::from_secs(60),
+            increment: Duration::from_secs(2),
+            no_increment_turns: 2,
+        };
+        let mut clock = Clock::new(control, TimeMeasurement::Exact);
+        let turn = |clock: &mut Clock, force: Force, full_turn_index, now| {
+            clock.new_turn(force.opponent(), now);
+            clock.add_increment(force, full_turn_index);
+        };
+        clock.new_turn(Force::White, game_t!(0));
+        turn(&mut clock, Force::White, 1, game_t!(1 s));
+        turn(&mut clock, Force::Black, 1, game_t!(2 s));
+        turn(&mut clock, Force::White, 2, game_t!(3 s));
+        turn(&mut clock, Force::Black, 2, game_t!(4 s));
+        assert_eq!(clock.time_left(Force::White, game_t!(4 s)), game_d!(58 s));
+        assert_eq!(clock.time_left(Force::Black, game_t!(4 s)), game_d!(58 s));
+
+        turn(&mut clock, Force::White, 3, game_t!(5 s));
+        assert_eq!(clock.time_left(Force::White, game_t!(5 s)), game_d!(59 s));
+        assert_eq!(clock.time_left(Force::Black, game_t!(5 s)), game_d!(58 s));
+        assert_eq!(clock.total_time_elapsed(), game_d!(5 s));
+    }
+
+    #[test]
+    fn clock_start_delay() {
+        let control = TimeControl::sudden_death(Duration::from_secs(60));
+        let mut clock = Clock::new(control, TimeMeasurement::Exact);
+        clock.set_start_delay(Force::Black, game_d!(10 s));
+        clock.new_turn(Force::White, game_t!(0));
+        assert_eq!(clock.time_left(Force::White, game_t!(4 s)), game_d!(56 s));
+
+        // Black turn starts before the delay is over: only time after the delay counts.
+        clock.new_turn(Force::Black, game_t!(4 s));
+        assert_eq!(clock.time_left(Force::Black, game_t!(8 s)), game_d!(60 s));
+        assert_eq!(clock.time_left(Force::Black, game_t!(13 s)), game_d!(57 s));
+        assert_eq!(clock.time_excess(Force::Black, game_t!(69 s)), None);
+        assert_eq!(clock.time_excess(Force::Black, game_t!(71 s)), Some(game_d!(1 s)));
+
+        // Later turns are not affected.
+        clock.new_turn(Force::White, game_t!(13 s));
+        clock.new_turn(Force::Black, game_t!(14 s));
+        assert_eq!(clock.time_left(Force::White, game_t!(14 s)), game_d!(55 s));
+        assert_eq!(clock.time_left(Force::Black, game_t!(16 s)), game_d!(55 s));
+    }
+
+    #[test]
+    fn clock_showing_long_time() {
+        let control = TimeControl::sudden_death(Duration::from_secs(2 * 3600));
+        let mut clock = Clock::new(control, TimeMeasurement::Exact);
+        assert_eq!(clock.showing_for(Force::White, game_t!(0)).ui_string(), "2:00:00");
+
+        clock.new_turn(Force::White, game_t!(0));
+        assert_eq!(clock.showing_for(Force::White, game_t!(1_200 ms)).ui_string(), "1:59:59");
+        assert_eq!(clock.showing_for(Force::White, game_t!(1_700 ms)).ui_string(), "1 59 59");
+        assert_eq!(clock.showing_for(Force::White, game_t!(3_600_400 ms)).ui_string(), "1:00:00");
+        assert_eq!(clock.showing_for(Force::White, game_t!(3_601_500 ms)).ui_string(), "59:59");
+    }
+
     #[test]
     fn time_difference_breakdown() {
         use TimeDifferenceBreakdown::*;
@@ -1,17 +1,22 @@
 // Improvement potential: Standardize naming.
 // Improvement potential: Add tests verifying inverse and commutative relations.
 
+use std::collections::HashSet;
 use std::ops;
 
+use enum_map::{Enum, EnumMap, enum_map};
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 
+use crate::altered_game::{compute_fog_of_war_area, see_though_fog};
 use crate::coord::{BoardShape, Col, Coord, Row};
 use crate::force::Force;
-use crate::game::{BughouseBoard, BughouseParticipant, BughousePlayer, get_bughouse_board};
+use crate::game::{
+    BughouseBoard, BughouseGame, BughouseParticipant, BughousePlayer, get_bughouse_board,
+};
 
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Enum, EnumIter)]
 pub enum DisplayBoard {
     Primary,
     Secondary,
@@ -23,6 +28,18 @@ pub enum DisplayPlayer {
     Bottom,
 }
 
+// Decides whether turn logs should follow new turns. With `freeze_when_scrolled_up`, a log sticks
+// to the bottom unless the user has scrolled up to review earlier turns: we don't want to yank them
+// away. Measuring the scroll position is expensive, so the UI reports it only when the log is
+// actually scrolled. Wayback takes precedence over both modes: it controls the scroll position
+// while active and re-attaches the logs when it's over.
+#[derive(Clone, Debug)]
+pub struct TurnLogScroll {
+    freeze_when_scrolled_up: bool,
+    scrolled_up: EnumMap<DisplayBoard, bool>,
+    wayback_active: bool,
+}
+
 // Lens through which to view the game: the corresponding envoy will be rendered in
 // bottom left.
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -31,6 +48,21 @@ pub struct Perspective {
     pub force: Force,
 }
 
+// The game as seen by a given participant: where each board goes, how it is oriented and which
+// squares are hidden. Does not account for local turns: see `AlteredGame` for that.
+#[derive(Clone, Debug)]
+pub struct BughouseGameView {
+    pub perspective: Perspective,
+    pub boards: EnumMap<DisplayBoard, BughouseBoardView>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BughouseBoardView {
+    pub board_idx: BughouseBoard,
+    pub orientation: BoardOrientation,
+    pub fog_of_war_area: HashSet<Coord>, // empty unless playing fog-of-war
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum BoardOrientation {
     Normal,  // White at bottom
@@ -82,6 +114,33 @@ impl Perspective {
     }
 }
 
+impl BughouseGameView {
+    pub fn for_participant(game: &BughouseGame, participant: BughouseParticipant) -> Self {
+        let perspective = Perspective::for_participant(participant);
+        let boards = enum_map! { display_board => {
+            let board_idx = get_board_index(display_board, perspective);
+            BughouseBoardView {
+                board_idx,
+                orientation: get_board_orientation(display_board, perspective),
+                fog_of_war_area: if see_though_fog(game, participant) {
+                    HashSet::new()
+                } else {
+                    compute_fog_of_war_area(game, game, board_idx, participant)
+                },
+            }
+        }};
+        BughouseGameView { perspective, boards }
+    }
+
+    pub fn board(&self, display_board: DisplayBoard) -> &BughouseBoardView {
+        &self.boards[display_board]
+    }
+}
+
+impl BughouseBoardView {
+    pub fn is_visible(&self, coord: Coord) -> bool { !self.fog_of_war_area.contains(&coord) }
+}
+
 pub fn get_board_index(board: DisplayBoard, perspective: Perspective) -> BughouseBoard {
     match board {
         DisplayBoard::Primary => perspective.board_idx,
@@ -189,6 +248,49 @@ pub fn display_to_fcoord(
     }
 }
 
+impl TurnLogScroll {
+    pub fn new() -> Self {
+        TurnLogScroll {
+            freeze_when_scrolled_up: true,
+            scrolled_up: enum_map! { _ => false },
+            wayback_active: false,
+        }
+    }
+
+    pub fn set_freeze_when_scrolled_up(&mut self, enabled: bool) {
+        self.freeze_when_scrolled_up = enabled;
+    }
+
+    // Should be called whenever the log is scrolled, both by the user and programmatically.
+    pub fn on_scroll(&mut self, board: DisplayBoard, at_bottom: bool) {
+        self.scrolled_up[board] = !at_bottom;
+    }
+
+    // Should be called whenever wayback state changes. Returns true if wayback has just been
+    // turned off, in which case the logs should be scrolled to bottom.
+    pub fn on_wayback(&mut self, active: bool) -> bool {
+        let left_wayback = self.wayback_active && !active;
+        self.wayback_active = active;
+        if left_wayback {
+            self.scrolled_up = enum_map! { _ => false };
+        }
+        left_wayback
+    }
+
+    // Should be called when the logs are re-rendered from scratch, e.g. when a new game starts.
+    pub fn reset(&mut self) {
+        self.scrolled_up = enum_map! { _ => false };
+        self.wayback_active = false;
+    }
+
+    pub fn follow_new_turns(&self, board: DisplayBoard) -> bool {
+        if self.wayback_active {
+            return false;
+        }
+        !(self.freeze_when_scrolled_up && self.scrolled_up[board])
+    }
+}
+
 impl FCoord {
     // Returns the closes valid board square.
     pub fn to_coord_snapped(self, board_shape: BoardShape) -> Coord {
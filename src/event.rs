@@ -3,15 +3,19 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 
-use crate::board::TurnInput;
+use crate::board::{Reserve, TurnInput};
 use crate::chalk::{ChalkDrawing, Chalkboard};
 use crate::chat::{ChatMessage, OutgoingChatMessage};
 use crate::clock::GameInstant;
-use crate::game::{BughouseBoard, BughouseGameStatus, PlayerInGame, TurnIndex, TurnRecord};
+use crate::game::{
+    BughouseBoard, BughouseEnvoy, BughouseGameStatus, PlayerInGame, TurnIndex, TurnRecord,
+};
+use crate::lobby::{SeatSwap, SeatSwapError};
 use crate::meter::MeterStats;
 use crate::player::{Faction, Participant};
+use crate::player_name::PlayerNameError;
 use crate::rules::Rules;
-use crate::scores::Scores;
+use crate::scores::{MatchResult, Scores};
 use crate::session::Session;
 use crate::starter::EffectiveStartingPosition;
 use crate::utc_time::UtcDateTime;
@@ -19,13 +23,27 @@ use crate::utc_time::UtcDateTime;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum BughouseServerRejection {
-    MaxStartingTimeExceeded { requested: Duration, allowed: Duration },
+    MaxStartingTimeExceeded {
+        requested: Duration,
+        allowed: Duration,
+    },
+    // Cannot create match: scheduled start is in the past or too far in the future.
+    InvalidScheduledStart {
+        max_delay: Duration,
+    },
     // Cannot join: a match with given ID does not exist.
-    NoSuchMatch { match_id: String },
+    NoSuchMatch {
+        match_id: String,
+    },
     // Cannot join match: there already is a player with this name and an active client.
-    PlayerAlreadyExists { player_name: String },
+    PlayerAlreadyExists {
+        player_name: String,
+    },
     // Cannot create account or join as a guest with a given name.
-    InvalidPlayerName { player_name: String, reason: String },
+    InvalidPlayerName {
+        player_name: String,
+        reason: PlayerNameError,
+    },
     // Registered user kicked out of a match, because they joined in another client (e.g. another
     // browser tab). We never send this for guest users, because we cannot be sure if it's them or not.
     JoinedInAnotherClient,
@@ -33,14 +51,49 @@ pub enum BughouseServerRejection {
     NameClashWithRegisteredUser,
     // Trying to participate in a rated match with a guest account.
     GuestInRatedMatch,
+    // Trying to participate in a registered-users-only match with a guest account.
+    GuestInRegisteredOnlyMatch,
+    // Cannot change faction: a fixed team would be left without players.
+    FactionChangeLeavesTeamEmpty,
+    // Only the match owner can do this.
+    NotMatchOwner,
+    // There is no participant with this name in the match (or they are offline when it matters).
+    NoSuchParticipant {
+        player_name: String,
+    },
+    // Cannot set handicap: it's forbidden in the match or contains pieces that cannot be dropped.
+    InvalidHandicap {
+        reason: String,
+    },
+    // Cannot start a rematch in swapped seats.
+    CannotSwapSeats {
+        error: SeatSwapError,
+    },
+    // Cannot spectate: the match has reached the limit on the number of spectators.
+    TooManySpectators,
     // Only registered users can view personal game history.
     MustRegisterForGameArchive,
+    // Cannot abort the game: aborting is disabled or the game has progressed too far.
+    AbortNotAllowed {
+        window: Option<u8>,
+    },
+    // Cannot resign a single board: it's not allowed by match rules.
+    BoardResignationNotAllowed,
+    // Chat message not sent: the match is in slow mode and the sender has to wait.
+    ChatSlowMode {
+        local_message_id: u64,
+        wait: Duration,
+    },
     // Server couldn't fetch game list. Probably transient DB error.
-    ErrorFetchingData { message: String },
+    ErrorFetchingData {
+        message: String,
+    },
     // Server is shutting down for maintenance.
     ShuttingDown,
     // Internal error. Should be investigated.
-    UnknownError { message: String },
+    UnknownError {
+        message: String,
+    },
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -57,6 +110,12 @@ pub enum GameUpdate {
     TurnMade {
         turn_record: TurnRecord,
     },
+    // A player conceded their board, but the game goes on. If this also ends the game, must be
+    // followed by `GameOver`.
+    BoardResigned {
+        time: GameInstant,
+        envoy: BughouseEnvoy,
+    },
     // Sent when game is ended for any reason. If the game ended due to checkmate, must be sent
     // together with the corresponding `TurnMade` event and `game_status` must match the status
     // resulting from the turn (in other words, registered turn always taked priority over flag,
@@ -99,6 +158,7 @@ pub enum BughouseServerEvent {
     ServerWelcome {
         expected_git_version: Option<String>,
         max_starting_time: Option<Duration>,
+        max_player_name_length: usize,
     },
     UpdateSession {
         session: Session,
@@ -109,10 +169,23 @@ pub enum BughouseServerEvent {
     MatchWelcome {
         match_id: String,
         rules: Rules,
+        // Participant name as recorded by the server. Differs from the requested one for anonymous
+        // guests. `None` for spectators.
+        my_name: Option<String>,
     },
     LobbyUpdated {
         participants: Vec<Participant>,
+        // Name of the participant who can moderate the match. `None` only if everybody left.
+        owner: Option<String>,
         countdown_elapsed: Option<Duration>,
+        // Time left until the scheduled start. Relative rather than absolute in order to be
+        // independent of clock drift between server and clients.
+        scheduled_start_in: Option<Duration>,
+    },
+    // Sent every second while the countdown before the first game is running. The server starts
+    // the game when the countdown ends; clients only display the value.
+    FirstGameCountdown {
+        time_left: Duration,
     },
     // Improvement potential: Rename `GameStarted` to take reconnection into account.
     GameStarted {
@@ -129,6 +202,11 @@ pub enum BughouseServerEvent {
     GameUpdated {
         updates: Vec<GameUpdate>,
     },
+    // Sent when the match reaches its target (see `MatchTarget`) and to clients joining a match
+    // that is already complete. No more games can be started after that.
+    MatchCompleted {
+        result: MatchResult,
+    },
     ChatMessages {
         messages: Vec<ChatMessage>,
         confirmed_local_message_id: u64,
@@ -146,6 +224,15 @@ pub enum BughouseServerEvent {
         game_id: i64,
         bpgn: String,
     },
+    // Sent to clients following the player when the player starts a game in another match.
+    FollowedPlayerGameStarted {
+        player_name: String,
+        match_id: String,
+    },
+    // Sent to all clients right before the server exits, e.g. for a restart.
+    ServerShuttingDown {
+        reconnect_in: Duration,
+    },
     Pong,
 }
 
@@ -174,14 +261,31 @@ pub enum BughouseClientEvent {
     NewMatch {
         rules: Rules,
         player_name: String,
+        // Join as a guest with a server-assigned name. `player_name` is ignored.
+        anonymous: bool,
+        // Whether other participants can see the sender's rating. Can be changed later via
+        // `SetShowRating`.
+        show_rating: bool,
+        // If set, the first game starts at this time rather than when all players are ready.
+        scheduled_start: Option<UtcDateTime>,
     },
     Join {
         match_id: String,
         player_name: String,
+        // Same as in `NewMatch`.
+        anonymous: bool,
+        show_rating: bool,
     },
     HotReconnect {
         match_id: String,
         player_name: String,
+        // Same as in `NewMatch`. Matters if the server no longer knows the participant.
+        show_rating: bool,
+    },
+    // Watch a match read-only without becoming a participant: the client receives game updates,
+    // but does not appear in the lobby and does not receive chat messages.
+    Spectate {
+        match_id: String,
     },
     SetFaction {
         faction: Faction,
@@ -197,9 +301,31 @@ pub enum BughouseClientEvent {
         board_idx: BughouseBoard,
     },
     Resign,
+    // Concede own board only. The game goes on until the other board is over. Requires
+    // `BughouseRules::board_resignation`.
+    ResignBoard,
+    // Vote to abort the game. The game is aborted once both teams have voted.
+    RequestAbort,
     SetReady {
         is_ready: bool,
     },
+    // Changes the setting passed in `NewMatch`/`Join`.
+    SetShowRating {
+        show_rating: bool,
+    },
+    // Play the next game with the same players in swapped seats. Also marks the sender as ready.
+    RequestRematch {
+        swap: SeatSwap,
+    },
+    // Extra reserve pieces for a participant in the following games. Can only be sent by the owner.
+    SetHandicap {
+        player_name: String,
+        handicap: Reserve,
+    },
+    // Pass match ownership to another online participant. Can only be sent by the owner.
+    TransferMatchOwnership {
+        to: String,
+    },
     LeaveMatch,
     LeaveServer,
     SendChatMessage {
@@ -215,6 +341,11 @@ pub enum BughouseClientEvent {
     GetArchiveGameBpgn {
         game_id: i64,
     },
+    // Follow a player: when they start a game in a different match, the client is notified and
+    // can switch to spectating it. Only one player can be followed at a time; `None` unfollows.
+    FollowPlayer {
+        player_name: Option<String>,
+    },
     ReportPerformace(BughouseClientPerformance),
     ReportError(BughouseClientErrorReport),
     Ping,
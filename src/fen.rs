@@ -14,8 +14,8 @@ use crate::piece::{
     CastleDirection, PieceId, PieceKind, PieceOnBoard, PieceOrigin, piece_from_ascii,
     piece_to_ascii,
 };
-use crate::rules::ChessRules;
-use crate::starter::{BoardSetup, assign_piece_ids};
+use crate::rules::{Castling, ChessRules};
+use crate::starter::{BoardSetup, assign_piece_ids, validate_setup};
 use crate::util::as_single_char;
 
 
@@ -196,6 +196,12 @@ pub fn board_to_shredder_fen(board: &Board) -> String {
 }
 
 pub fn shredder_fen_to_board(rules: &ChessRules, fen: &str) -> Result<BoardSetup, String> {
+    let setup = parse_shredder_fen(rules, fen)?;
+    validate_setup(rules, &setup).map_err(|err| format!("invalid FEN: {err}"))?;
+    Ok(setup)
+}
+
+fn parse_shredder_fen(rules: &ChessRules, fen: &str) -> Result<BoardSetup, String> {
     let reserve_re = once_cell_regex!(r"^(.*)\[(.*)\]$");
 
     let (
@@ -273,7 +279,10 @@ pub fn shredder_fen_to_board(rules: &ChessRules, fen: &str) -> Result<BoardSetup
     assign_piece_ids(&mut grid, &mut next_piece_id);
 
     let active_force = force_from_fen(active_force_notation)?;
-    let castling_rights = castling_rights_from_fen(&grid, castling_notation)?;
+    let mut castling_rights = castling_rights_from_fen(&grid, castling_notation)?;
+    if rules.castling == Castling::Forbidden {
+        castling_rights = BoardCastlingRights::default();
+    }
     let en_passant_target = en_passant_target_from_fen(en_passant_target_notation)?;
     // Ignore `half_turn_clock`: we don't use the fifty-move rule.
     let _ = half_turn_clock
@@ -302,7 +311,8 @@ mod tests {
     use super::*;
     use crate::game::{BughouseBoard, BughouseGame};
     use crate::role::Role;
-    use crate::rules::{MatchRules, Rules};
+    use crate::rules::{MatchRules, PawnDropRanks, Rules};
+    use crate::starter::SetupProblem;
     use crate::test_util::{replay_bughouse_log, sample_bughouse_players};
 
     fn comparable(setup: BoardSetup) -> BoardSetup {
@@ -370,4 +380,130 @@ mod tests {
         let parsed_board = shredder_fen_to_board(&rules.chess_rules, &fen).unwrap();
         assert_eq!(comparable(parsed_board), comparable(board.clone().into()));
     }
+
+    #[test]
+    fn castling_forbidden() {
+        let rules = Rules {
+            match_rules: MatchRules::unrated_public(),
+            chess_rules: ChessRules {
+                castling: Castling::Forbidden,
+                ..ChessRules::bughouse_international5()
+            },
+        };
+        let game =
+            BughouseGame::new(rules.clone(), Role::ServerOrStandalone, &sample_bughouse_players());
+        let board = game.board(BughouseBoard::A);
+        let fen = board_to_shredder_fen(board);
+        assert_eq!(fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
+        let parsed_board = shredder_fen_to_board(&rules.chess_rules, &fen).unwrap();
+        assert_eq!(comparable(parsed_board), comparable(board.clone().into()));
+
+        // Castling rights from FEN are dropped as well.
+        let parsed_board = shredder_fen_to_board(
+            &rules.chess_rules,
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah - 0 1",
+        )
+        .unwrap();
+        assert_eq!(parsed_board.castling_rights, BoardCastlingRights::default());
+    }
+
+    fn setup_problems(fen: &str) -> Vec<SetupProblem> {
+        setup_problems_with_rules(&ChessRules::bughouse_international5(), fen)
+    }
+
+    fn setup_problems_with_rules(rules: &ChessRules, fen: &str) -> Vec<SetupProblem> {
+        let setup = parse_shredder_fen(rules, fen).unwrap();
+        validate_setup(rules, &setup).err().map_or(vec![], |err| err.problems)
+    }
+
+    #[test]
+    fn valid_setup() {
+        assert!(
+            setup_problems("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah - 0 1").is_empty()
+        );
+        assert!(
+            setup_problems("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b AHah e3 0 1")
+                .is_empty()
+        );
+        assert!(setup_problems("4k3/8/8/8/8/8/8/R3K3[Qq] w A - 0 1").is_empty());
+    }
+
+    #[test]
+    fn setup_wrong_number_of_kings() {
+        assert_eq!(setup_problems("8/8/8/8/8/8/8/4K3 w - - 0 1"), vec![
+            SetupProblem::WrongNumberOfKings { force: Force::Black, count: 0 }
+        ]);
+        assert_eq!(setup_problems("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), vec![
+            SetupProblem::WrongNumberOfKings { force: Force::White, count: 2 }
+        ]);
+    }
+
+    #[test]
+    fn setup_pawn_on_back_rank() {
+        assert_eq!(setup_problems("4k2P/8/8/8/8/8/8/p3K3 w - - 0 1"), vec![
+            SetupProblem::PawnOnBackRank { coord: Coord::A1 },
+            SetupProblem::PawnOnBackRank { coord: Coord::H8 },
+        ]);
+    }
+
+    #[test]
+    fn setup_pawn_on_first_rank_with_drops_allowed() {
+        let mut rules = ChessRules::bughouse_international5();
+        rules.bughouse_rules.as_mut().unwrap().pawn_drop_ranks =
+            PawnDropRanks::from_one_based(1, 7);
+        assert!(setup_problems_with_rules(&rules, "p3k3/8/8/8/8/8/8/P3K3 w - - 0 1").is_empty());
+        assert_eq!(setup_problems_with_rules(&rules, "P3k3/8/8/8/8/8/8/p3K3 w - - 0 1"), vec![
+            SetupProblem::PawnOnBackRank { coord: Coord::A1 },
+            SetupProblem::PawnOnBackRank { coord: Coord::A8 },
+        ]);
+    }
+
+    #[test]
+    fn setup_invalid_en_passant_target() {
+        // No pawn in front of the target.
+        assert_eq!(setup_problems("4k3/8/8/8/8/8/8/4K3 b - e3 0 1"), vec![
+            SetupProblem::InvalidEnPassantTarget { coord: Coord::E3 }
+        ]);
+        // Target on the wrong side: White has just moved, so Black cannot be the one to capture.
+        assert_eq!(setup_problems("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1"), vec![
+            SetupProblem::InvalidEnPassantTarget { coord: Coord::E3 }
+        ]);
+        // Starting square is occupied.
+        assert_eq!(setup_problems("4k3/8/8/8/4P3/8/4N3/4K3 b - e3 0 1"), vec![
+            SetupProblem::InvalidEnPassantTarget { coord: Coord::E3 }
+        ]);
+    }
+
+    #[test]
+    fn setup_invalid_castling_rights() {
+        // No rook.
+        assert_eq!(setup_problems("4k3/8/8/8/8/8/8/4K3 w A - 0 1"), vec![
+            SetupProblem::InvalidCastlingRights {
+                force: Force::White,
+                dir: CastleDirection::ASide
+            }
+        ]);
+        // Opponent's rook.
+        assert_eq!(setup_problems("4k3/8/8/8/8/8/8/4K2r w H - 0 1"), vec![
+            SetupProblem::InvalidCastlingRights {
+                force: Force::White,
+                dir: CastleDirection::HSide
+            }
+        ]);
+    }
+
+    #[test]
+    fn setup_reports_all_problems() {
+        let fen = "8/8/8/8/8/8/8/P3K3 w A e6 0 1";
+        assert_eq!(setup_problems(fen), vec![
+            SetupProblem::WrongNumberOfKings { force: Force::Black, count: 0 },
+            SetupProblem::PawnOnBackRank { coord: Coord::A1 },
+            SetupProblem::InvalidEnPassantTarget { coord: Coord::E6 },
+            SetupProblem::InvalidCastlingRights {
+                force: Force::White,
+                dir: CastleDirection::ASide
+            },
+        ]);
+        assert!(shredder_fen_to_board(&ChessRules::bughouse_international5(), fen).is_err());
+    }
 }
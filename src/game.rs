@@ -25,6 +25,7 @@
 
 #![allow(unused_parens)]
 
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::hash::Hash;
@@ -36,20 +37,22 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
 
-use crate::algebraic::{AlgebraicDetails, AlgebraicTurn};
+use crate::algebraic::{
+    AlgebraicCharset, AlgebraicDetails, AlgebraicPromotionTarget, AlgebraicTurn,
+};
 use crate::board::{
     Board, ChessGameStatus, DrawReason, Reserve, Turn, TurnError, TurnExpanded, TurnFacts,
     TurnInput, TurnMode, VictoryReason,
 };
 use crate::clock::{GameDuration, GameInstant, MillisDuration};
-use crate::coord::BoardShape;
+use crate::coord::{BoardShape, Coord};
 use crate::force::Force;
 use crate::once_cell_regex;
-use crate::piece::PieceKind;
+use crate::piece::{CastleDirection, PieceKind};
 use crate::player::Team;
 use crate::role::Role;
-use crate::rules::{BughouseRules, ChessRules, MatchRules, Rules};
-use crate::starter::{EffectiveStartingPosition, generate_starting_position};
+use crate::rules::{BughouseRules, ChessRules, MatchRules, Rules, SimultaneousFlagPolicy};
+use crate::starter::{BoardSetup, EffectiveStartingPosition, generate_starting_position};
 
 
 pub const MIN_PLAYERS: usize = TOTAL_TEAMS;
@@ -79,6 +82,22 @@ pub struct TurnRecordExpanded {
     pub time: GameInstant,
 }
 
+// A turn in standard algebraic notation, as returned by `BughouseGame::move_list_san`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SanMove {
+    pub index: TurnIndex,
+    pub number: u32, // see `TurnRecordExpanded::local_number`
+    pub envoy: BughouseEnvoy,
+    pub algebraic: String,
+}
+
+impl Display for SanMove {
+    // Formats as "12A.Nxe5", the way game logs are written in tests.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}.{}", self.number, self.envoy.to_notation(), self.algebraic)
+    }
+}
+
 impl Display for TurnIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { write!(f, "{}", self.0) }
 }
@@ -169,6 +188,54 @@ pub struct GameOutcome {
     pub losers: Vec<String>,
 }
 
+// Games with fewer turns get a minimal review: there isn't much to say about them.
+pub const MIN_TURNS_FOR_FULL_REVIEW: usize = 8;
+
+// How far `BughouseGame::detect_perpetual` looks ahead, in checks by the attacking side. Enough to
+// spot a two-check cycle that doesn't include the current position.
+pub const PERPETUAL_CHECK_HORIZON: u32 = 3;
+
+// Full turns (see `Board::full_turn_index`) that are considered opening, unless material is low.
+pub const OPENING_FULL_TURNS: u32 = 10;
+
+// Combined material of both sides (see `Board::material`) at or below which the position is
+// considered an endgame. For reference, each side starts with 39 in classic chess.
+pub const ENDGAME_MATERIAL: u32 = 26;
+
+// Rough classification of a board position. A heuristic for UI hints and bots; doesn't affect rules.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+// Post-game summary. Computed purely from the turn log, so it could be built for any game,
+// including archive games.
+#[derive(Clone, Debug)]
+pub struct GameReview {
+    pub outcome: GameOutcome,
+    pub duration: GameDuration,
+    // Duck relocations are not counted.
+    pub num_turns: EnumMap<BughouseBoard, usize>,
+    // Lowest and highest material balance (see `Board::material_balance`) reached on each board.
+    pub material_balance_range: EnumMap<BughouseBoard, (i32, i32)>,
+    // The turn made with the least time on the clock. `None` if time is unknown.
+    pub time_pressure: Option<TimePressureMoment>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimePressureMoment {
+    pub envoy: BughouseEnvoy,
+    pub turn_index: TurnIndex,
+    pub time_left: MillisDuration,
+}
+
+impl GameReview {
+    pub fn total_turns(&self) -> usize { self.num_turns.values().sum() }
+    pub fn is_minimal(&self) -> bool { self.total_turns() < MIN_TURNS_FOR_FULL_REVIEW }
+}
+
 impl BughouseBoard {
     pub fn other(self) -> Self {
         match self {
@@ -191,16 +258,18 @@ impl GameOutcome {
         let losers = self.losers.join(" & ");
         match self.status {
             Active => "Unterminated".to_owned(),
-            Victory(_, Checkmate) => {
+            Victory(_, Checkmate) => format!("{winners} won: {losers} checkmated"),
+            Victory(_, KingCaptured) => {
                 if rules.bughouse_rules.as_ref().is_some_and(|r| r.koedem) {
                     format!("{winners} won: {losers} lost all kings")
-                } else if rules.regicide() {
-                    format!("{winners} won: {losers} lost a king")
                 } else {
-                    format!("{winners} won: {losers} checkmated")
+                    format!("{winners} won: {losers} lost a king")
                 }
             }
             Victory(_, Flag) => format!("{winners} won: {losers} lost on time"),
+            Victory(_, SimultaneousFlagMaterial) => {
+                format!("{winners} won: simultaneous flags, {losers} had less material")
+            }
             Victory(_, Resignation) => format!("{winners} won: {losers} resigned"),
             Victory(_, UnknownVictory) => format!("{winners} won, {losers} lost"),
             Draw(SimultaneousCheckmate) => {
@@ -213,6 +282,10 @@ impl GameOutcome {
             }
             Draw(SimultaneousFlag) => "Draw: simultaneous flags".to_owned(),
             Draw(ThreefoldRepetition) => "Draw: threefold repetition".to_owned(),
+            Draw(InsufficientMaterial) => "Draw: insufficient material".to_owned(),
+            Draw(Stalemate) => "Draw: stalemate".to_owned(),
+            Draw(Aborted) => "Game aborted".to_owned(),
+            Draw(SplitBoards) => "Draw: each team won a board".to_owned(),
             Draw(UnknownDraw) => "Draw".to_owned(),
         }
     }
@@ -221,15 +294,22 @@ impl GameOutcome {
         use BughouseGameStatus::*;
         use DrawReason::*;
         use VictoryReason::*;
-        let checkmate_victory_re =
-            once_cell_regex!("^(.+) won: (.+) (?:lost all kings|lost a king|checkmated)$");
+        let checkmate_victory_re = once_cell_regex!("^(.+) won: (.+) checkmated$");
+        let king_captured_victory_re =
+            once_cell_regex!("^(.+) won: (.+) (?:lost all kings|lost a king)$");
         let flag_victory_re = once_cell_regex!("^(.+) won: (.+) lost on time$");
+        let simultaneous_flag_material_victory_re =
+            once_cell_regex!("^(.+) won: simultaneous flags, (.+) had less material$");
         let resignation_victory_re = once_cell_regex!("^(.+) won: (.+) resigned$");
         let unknown_victory_re = once_cell_regex!("^(.+) won, (.+) lost$");
         let simultaneous_checkmate_draw_re =
             once_cell_regex!("^Draw: both kings lost|Draw: both players checkmated$");
         let simultaneous_flag_draw_re = once_cell_regex!("^Draw: simultaneous flags$");
         let threefold_repetition_draw_re = once_cell_regex!("^Draw: threefold repetition$");
+        let insufficient_material_draw_re = once_cell_regex!("^Draw: insufficient material$");
+        let stalemate_draw_re = once_cell_regex!("^Draw: stalemate$");
+        let aborted_re = once_cell_regex!("^Game aborted$");
+        let split_boards_draw_re = once_cell_regex!("^Draw: each team won a board$");
         let unknown_draw_re = once_cell_regex!("^Draw$");
 
         if s == "Unterminated" {
@@ -241,7 +321,9 @@ impl GameOutcome {
         }
         for (regex, reason) in [
             (checkmate_victory_re, Checkmate),
+            (king_captured_victory_re, KingCaptured),
             (flag_victory_re, Flag),
+            (simultaneous_flag_material_victory_re, SimultaneousFlagMaterial),
             (resignation_victory_re, Resignation),
             (unknown_victory_re, UnknownVictory),
         ] {
@@ -271,6 +353,10 @@ impl GameOutcome {
             (simultaneous_checkmate_draw_re, SimultaneousCheckmate),
             (simultaneous_flag_draw_re, SimultaneousFlag),
             (threefold_repetition_draw_re, ThreefoldRepetition),
+            (insufficient_material_draw_re, InsufficientMaterial),
+            (stalemate_draw_re, Stalemate),
+            (aborted_re, Aborted),
+            (split_boards_draw_re, SplitBoards),
             (unknown_draw_re, UnknownDraw),
         ] {
             if regex.is_match(s) {
@@ -400,6 +486,29 @@ impl BughouseEnvoy {
         })
     }
 
+    // Player notation used in BPGN: "A" and "a" for White and Black on board A, etc.
+    pub fn to_notation(self) -> &'static str {
+        use BughouseBoard::*;
+        use Force::*;
+        match (self.board_idx, self.force) {
+            (A, White) => "A",
+            (A, Black) => "a",
+            (B, White) => "B",
+            (B, Black) => "b",
+        }
+    }
+    pub fn from_notation(s: &str) -> Option<Self> {
+        use BughouseBoard::*;
+        use Force::*;
+        match s {
+            "A" => Some(BughouseEnvoy { board_idx: A, force: White }),
+            "a" => Some(BughouseEnvoy { board_idx: A, force: Black }),
+            "B" => Some(BughouseEnvoy { board_idx: B, force: White }),
+            "b" => Some(BughouseEnvoy { board_idx: B, force: Black }),
+            _ => None,
+        }
+    }
+
     pub fn team(self) -> Team { get_bughouse_team(self.board_idx, self.force) }
     pub fn opponent(self) -> Self {
         BughouseEnvoy {
@@ -560,7 +669,7 @@ impl BughouseGame {
         players: &[PlayerInGame],
     ) -> Self {
         let player_map = make_player_map(players);
-        let boards = if let EffectiveStartingPosition::ManualSetup(setup) = &starting_position {
+        let mut boards = if let EffectiveStartingPosition::ManualSetup(setup) = &starting_position {
             player_map.map(|board_idx, board_players| {
                 Board::new_from_setup(rules.clone(), role, board_players, setup[&board_idx].clone())
             })
@@ -569,13 +678,51 @@ impl BughouseGame {
                 Board::new(rules.clone(), role, board_players, &starting_position)
             })
         };
-        BughouseGame {
+        if let Some(delayed_start) = rules.bughouse_rules().and_then(|r| r.delayed_start) {
+            for (board_idx, board) in boards.iter_mut() {
+                let force = get_bughouse_force(delayed_start.team, board_idx);
+                board.clock_mut().set_start_delay(force, delayed_start.delay.into());
+            }
+        }
+        let mut game = BughouseGame {
             role,
             starting_position,
             boards,
             status: BughouseGameStatus::Active,
             turn_log: Vec::new(),
+        };
+        game.update_capture_room();
+        game
+    }
+
+    // Like `new`, but players get extra reserve pieces. The starting position is converted to a
+    // manual setup in this case, so that the reserves are known to clients and recorded in PGN.
+    // Double players get the pieces on both boards.
+    pub fn new_with_handicaps(
+        rules: Rules, role: Role, players: &[PlayerInGame], handicaps: &HashMap<String, Reserve>,
+    ) -> Self {
+        let game = Self::new(rules.clone(), role, players);
+        if handicaps.values().all(|h| h.values().all(|&n| n == 0)) {
+            return game;
         }
+        let player_map = game.player_map();
+        let setup = BughouseBoard::iter()
+            .map(|board_idx| {
+                let mut setup = BoardSetup::from(game.board(board_idx).clone());
+                for (name, handicap) in handicaps {
+                    let Some(envoy) = player_map.get(name).and_then(|p| p.envoy_for(board_idx))
+                    else {
+                        continue;
+                    };
+                    for (piece_kind, &amount) in handicap {
+                        setup.reserves[envoy.force][piece_kind] += amount;
+                    }
+                }
+                (board_idx, setup)
+            })
+            .collect();
+        let starting_position = EffectiveStartingPosition::ManualSetup(setup);
+        Self::new_with_starting_position(rules, role, starting_position, players)
     }
 
     pub fn clone_from_start(&self) -> Self {
@@ -612,12 +759,83 @@ impl BughouseGame {
     pub fn board_shape(&self) -> BoardShape { self.chess_rules().board_shape() }
     pub fn board_mut(&mut self, idx: BughouseBoard) -> &mut Board { &mut self.boards[idx] }
     pub fn board(&self, idx: BughouseBoard) -> &Board { &self.boards[idx] }
+    // Whether the player to move on the board is stalemated. See `Board::is_stalemate`.
+    pub fn is_stalemate(&self, idx: BughouseBoard) -> bool {
+        let board = self.board(idx);
+        board.is_stalemate(board.active_force())
+    }
     pub fn boards(&self) -> &EnumMap<BughouseBoard, Board> { &self.boards }
     pub fn reserve(&self, envoy: BughouseEnvoy) -> &Reserve {
         self.boards[envoy.board_idx].reserve(envoy.force)
     }
     pub fn turn_log(&self) -> &Vec<TurnRecordExpanded> { &self.turn_log }
     pub fn turn_log_mut(&mut self) -> &mut Vec<TurnRecordExpanded> { &mut self.turn_log }
+    // Turns from both boards in the order they were made. Each board is numbered independently.
+    pub fn move_list_san(&self, charset: AlgebraicCharset) -> Vec<SanMove> {
+        let board_shape = self.board_shape();
+        self.turn_log
+            .iter()
+            .map(|record| SanMove {
+                index: record.index,
+                number: record.local_number,
+                envoy: record.envoy,
+                algebraic: record.turn_expanded.algebraic.format(board_shape, charset),
+            })
+            .collect()
+    }
+    // Spoken description of the latest turn on the board, e.g. "White bishop takes knight on f6,
+    // check". Meant for screen readers, so it avoids notation symbols.
+    pub fn last_turn_announcement(&self, board_idx: BughouseBoard) -> Option<String> {
+        let record = self.turn_log.iter().rev().find(|r| r.envoy.board_idx == board_idx)?;
+        let board = self.board(board_idx);
+        let board_shape = self.board_shape();
+        let force = record.envoy.force;
+        let force_name = match force {
+            Force::White => "White",
+            Force::Black => "Black",
+        };
+        let piece_name = |kind: PieceKind| format!("{kind:?}").to_lowercase();
+        let square = |coord: Coord| coord.to_algebraic(board_shape);
+        let mut text = match &record.turn_expanded.algebraic {
+            AlgebraicTurn::Move(mv) => {
+                let mut text = format!("{force_name} {}", piece_name(mv.piece_kind));
+                match record.turn_expanded.captures.first() {
+                    Some(capture) => text.push_str(&format!(
+                        " takes {} on {}",
+                        piece_name(capture.piece_kind),
+                        square(mv.to)
+                    )),
+                    None => text.push_str(&format!(" {}", square(mv.to))),
+                }
+                match mv.promote_to {
+                    Some(AlgebraicPromotionTarget::Upgrade(kind)) => {
+                        text.push_str(&format!(", promotes to {}", piece_name(kind)))
+                    }
+                    Some(AlgebraicPromotionTarget::Discard) => text.push_str(", promotes"),
+                    Some(AlgebraicPromotionTarget::Steal((kind, coord))) => text
+                        .push_str(&format!(", steals {} from {}", piece_name(kind), square(coord))),
+                    None => {}
+                }
+                text
+            }
+            AlgebraicTurn::Drop(drop) => {
+                format!("{force_name} drops {} on {}", piece_name(drop.piece_kind), square(drop.to))
+            }
+            AlgebraicTurn::Castle(CastleDirection::HSide) => {
+                format!("{force_name} castles kingside")
+            }
+            AlgebraicTurn::Castle(CastleDirection::ASide) => {
+                format!("{force_name} castles queenside")
+            }
+            AlgebraicTurn::PlaceDuck(to) => format!("{force_name} places duck on {}", square(*to)),
+        };
+        if board.status() == ChessGameStatus::Victory(force, VictoryReason::Checkmate) {
+            text.push_str(", checkmate");
+        } else if board.is_in_check(force.opponent()) {
+            text.push_str(", check");
+        }
+        Some(text)
+    }
     pub fn turn_record(&self, index: TurnIndex) -> &TurnRecordExpanded { &self.turn_log[index.0] }
     pub fn last_turn_record(&self) -> Option<&TurnRecordExpanded> { self.turn_log.last() }
     pub fn started(&self) -> bool { !self.turn_log.is_empty() }
@@ -669,10 +887,26 @@ impl BughouseGame {
         self.players().iter().find(|p| p.name == player_name).map(|p| p.id)
     }
     pub fn is_envoy_active(&self, envoy: BughouseEnvoy) -> bool {
-        self.status.is_active() && self.boards[envoy.board_idx].active_force() == envoy.force
+        let board = &self.boards[envoy.board_idx];
+        self.status.is_active()
+            && board.status() == ChessGameStatus::Active
+            && board.active_force() == envoy.force
+    }
+    // Clocks are per board and never depend on each other. In particular, a double player can have
+    // both clocks running at the same time: the clock on each board ticks whenever it's their turn
+    // there, regardless of what happens on the other board. Differs from `is_envoy_active` in that
+    // clocks don't tick before the first turn, so White is not "ticking" before the game starts.
+    pub fn ticking_envoys(&self, participant: BughouseParticipant) -> Vec<BughouseEnvoy> {
+        participant
+            .envoys()
+            .into_iter()
+            .filter(|envoy| {
+                self.boards[envoy.board_idx].clock().active_force() == Some(envoy.force)
+            })
+            .collect()
     }
     pub fn turn_mode_for_envoy(&self, envoy: BughouseEnvoy) -> Result<TurnMode, TurnError> {
-        if self.status.is_active() {
+        if self.status.is_active() && !self.is_board_resigned(envoy.board_idx) {
             Ok(if self.is_envoy_active(envoy) {
                 TurnMode::InOrder
             } else {
@@ -698,13 +932,24 @@ impl BughouseGame {
         use BughouseGameStatus::*;
         use VictoryReason::Flag;
         assert_eq!(self.status, Active);
-        let game_duration = BughouseBoard::iter()
-            .filter_map(|board| self.boards[board].flag_defeat_moment(now))
+        let boards_in_play = BughouseBoard::iter()
+            .filter(|&board_idx| !self.is_board_resigned(board_idx))
+            .collect_vec();
+        let game_duration = boards_in_play
+            .iter()
+            .filter_map(|&board_idx| self.boards[board_idx].flag_defeat_moment(now))
             .filter_map(|d| MillisDuration::try_from(d.elapsed_since_start()).ok())
             .min()?;
         let game_over_time = GameInstant::from_millis_duration(game_duration);
-        self.boards[A].test_flag(game_over_time);
-        self.boards[B].test_flag(game_over_time);
+        for &board_idx in &boards_in_play {
+            self.boards[board_idx].test_flag(game_over_time);
+        }
+        if BughouseBoard::iter().any(|board_idx| self.is_board_resigned(board_idx)) {
+            let status = self.combined_board_status();
+            assert!(!status.is_active());
+            self.set_status(status, game_over_time);
+            return Some(game_over_time);
+        }
         let status_a = self.game_status_for_board(A);
         let status_b = self.game_status_for_board(B);
         let status = match (status_a, status_b) {
@@ -712,7 +957,7 @@ impl BughouseGame {
                 if winner_a == winner_b {
                     Victory(winner_a, Flag)
                 } else {
-                    Draw(DrawReason::SimultaneousFlag)
+                    self.resolve_simultaneous_flag()
                 }
             }
             (Victory(winner, Flag), Active) => Victory(winner, Flag),
@@ -732,16 +977,42 @@ impl BughouseGame {
         Some(game_over_time)
     }
 
+    fn resolve_simultaneous_flag(&self) -> BughouseGameStatus {
+        use BughouseGameStatus::*;
+        match self.bughouse_rules().simultaneous_flag {
+            SimultaneousFlagPolicy::Draw => Draw(DrawReason::SimultaneousFlag),
+            SimultaneousFlagPolicy::MaterialAdvantage => {
+                let material = |team| -> u32 {
+                    BughouseBoard::iter()
+                        .map(|board_idx| {
+                            let force = get_bughouse_force(team, board_idx);
+                            self.boards[board_idx].material(force)
+                        })
+                        .sum()
+                };
+                match material(Team::Red).cmp(&material(Team::Blue)) {
+                    Ordering::Greater => {
+                        Victory(Team::Red, VictoryReason::SimultaneousFlagMaterial)
+                    }
+                    Ordering::Less => Victory(Team::Blue, VictoryReason::SimultaneousFlagMaterial),
+                    Ordering::Equal => Draw(DrawReason::SimultaneousFlag),
+                }
+            }
+        }
+    }
+
     // Should `test_flag` first!
     pub fn try_turn(
         &mut self, board_idx: BughouseBoard, turn_input: &TurnInput, mode: TurnMode,
         now: GameInstant,
     ) -> Result<Turn, TurnError> {
-        if !self.status.is_active() {
+        if !self.status.is_active() || self.is_board_resigned(board_idx) {
             // `Board::try_turn` will also test game status, but that's not enough: the game
             // may have ended earlier on the other board.
             return Err(TurnError::GameOver);
         }
+        // Boards could have been modified directly.
+        self.update_capture_room();
         let board = &self.boards[board_idx];
         let other_board = &self.boards[board_idx.other()];
         let envoy = BughouseEnvoy { board_idx, force: board.turn_owner(mode) };
@@ -777,6 +1048,7 @@ impl BughouseGame {
             TurnMode::Preturn => {}
         }
         other_board.apply_sibling_turn(&turn_facts, mode);
+        self.update_capture_room();
 
         let turn_expanded = make_turn_expanded(turn, turn_algebraic, turn_facts);
         self.turn_log.push(TurnRecordExpanded {
@@ -790,6 +1062,8 @@ impl BughouseGame {
         assert!(self.status.is_active());
         if self.bughouse_rules().koedem {
             self.check_koedem_victory(now);
+        } else if self.is_board_resigned(board_idx.other()) {
+            self.set_status(self.combined_board_status(), now);
         } else {
             self.set_status(self.game_status_for_board(board_idx), now);
         }
@@ -812,6 +1086,20 @@ impl BughouseGame {
         self.try_turn_by_envoy(turn_record.envoy, &turn_record.turn_input, mode, turn_record.time)
     }
 
+    // Concedes the envoy's board without ending the game (requires `BughouseRules::board_resignation`).
+    // The game goes on until the other board is over as well, see `combined_board_status`.
+    pub fn resign_board(&mut self, envoy: BughouseEnvoy, now: GameInstant) {
+        assert!(self.status.is_active());
+        self.boards[envoy.board_idx].resign(envoy.force, now);
+        self.set_status(self.combined_board_status(), now);
+    }
+    pub fn is_board_resigned(&self, board_idx: BughouseBoard) -> bool {
+        matches!(
+            self.boards[board_idx].status(),
+            ChessGameStatus::Victory(_, VictoryReason::Resignation)
+        )
+    }
+
     pub fn check_koedem_victory(&mut self, now: GameInstant) {
         let mut num_kings = enum_map! { _ => 0 };
         for (board_idx, board) in &self.boards {
@@ -833,7 +1121,10 @@ impl BughouseGame {
         assert!(num_kings.values().sum::<u8>() as usize <= TOTAL_ENVOYS);
         for team in Team::iter() {
             if num_kings[team] as usize == TOTAL_ENVOYS {
-                self.set_status(BughouseGameStatus::Victory(team, VictoryReason::Checkmate), now);
+                self.set_status(
+                    BughouseGameStatus::Victory(team, VictoryReason::KingCaptured),
+                    now,
+                );
             }
         }
     }
@@ -859,6 +1150,115 @@ impl BughouseGame {
         GameOutcome { status, winners, losers }
     }
 
+    // Returns the force to move on the board if it can force a repetition by giving check on every
+    // turn. Meant for bots; doesn't change game rules. See `Board::can_force_perpetual_check`.
+    pub fn detect_perpetual(&self, board_idx: BughouseBoard) -> Option<Force> {
+        if !self.is_active() {
+            return None;
+        }
+        let board = self.board(board_idx);
+        board
+            .can_force_perpetual_check(PERPETUAL_CHECK_HORIZON)
+            .then_some(board.active_force())
+    }
+
+    // Reserve pieces count as material: they can be dropped at any moment.
+    pub fn phase(&self, board_idx: BughouseBoard) -> GamePhase {
+        let board = self.board(board_idx);
+        let material: u32 = Force::iter().map(|force| board.material(force)).sum();
+        if material <= ENDGAME_MATERIAL {
+            GamePhase::Endgame
+        } else if board.full_turn_index() <= OPENING_FULL_TURNS {
+            GamePhase::Opening
+        } else {
+            GamePhase::Middlegame
+        }
+    }
+
+    pub fn review(&self) -> GameReview {
+        let mut replay_game = self.clone_from_start();
+        let mut num_turns = enum_map! { _ => 0 };
+        let mut material_balance_range = enum_map! { board_idx =>
+            (
+                replay_game.board(board_idx).material_balance(),
+                replay_game.board(board_idx).material_balance(),
+            )
+        };
+        let mut time_pressure: Option<TimePressureMoment> = None;
+        for turn in self.turn_log() {
+            let board_idx = turn.envoy.board_idx;
+            if !matches!(turn.turn_expanded.turn, Turn::PlaceDuck(_)) {
+                num_turns[board_idx] += 1;
+            }
+            let time_left =
+                replay_game.board(board_idx).clock().time_left(turn.envoy.force, turn.time);
+            if let Ok(time_left) = MillisDuration::try_from(time_left)
+                && time_pressure.is_none_or(|moment| time_left < moment.time_left)
+            {
+                time_pressure = Some(TimePressureMoment {
+                    envoy: turn.envoy,
+                    turn_index: turn.index,
+                    time_left,
+                });
+            }
+            // Unwrap ok: the turn has already been applied to the original game.
+            replay_game.apply_turn_record(&turn.trim(), TurnMode::InOrder).unwrap();
+            // A capture changes material on both boards.
+            for (board_idx, (min, max)) in material_balance_range.iter_mut() {
+                let balance = replay_game.board(board_idx).material_balance();
+                *min = (*min).min(balance);
+                *max = (*max).max(balance);
+            }
+        }
+        GameReview {
+            outcome: self.outcome(),
+            duration: self.total_time_elapsed(),
+            num_turns,
+            material_balance_range,
+            time_pressure,
+        }
+    }
+
+    // Game result once each board is over on its own, which is only possible with board
+    // resignation. The team that won more boards wins; a drawn board doesn't count for either team.
+    fn combined_board_status(&self) -> BughouseGameStatus {
+        use BughouseGameStatus::*;
+        let statuses = BughouseBoard::iter().map(|board_idx| self.game_status_for_board(board_idx));
+        let mut boards_won = enum_map! { _ => 0 };
+        let mut victory_reasons = enum_map! { _ => vec![] };
+        for status in statuses {
+            match status {
+                Active => return Active,
+                Victory(team, reason) => {
+                    boards_won[team] += 1;
+                    victory_reasons[team].push(reason);
+                }
+                Draw(_) => {}
+            }
+        }
+        let winner = match boards_won[Team::Red].cmp(&boards_won[Team::Blue]) {
+            Ordering::Greater => Team::Red,
+            Ordering::Less => Team::Blue,
+            Ordering::Equal => return Draw(DrawReason::SplitBoards),
+        };
+        // Prefer the reason the game was won on the board that was played out.
+        let reason = victory_reasons[winner]
+            .iter()
+            .copied()
+            .find(|&reason| reason != VictoryReason::Resignation)
+            .unwrap_or(VictoryReason::Resignation);
+        Victory(winner, reason)
+    }
+
+    // Pieces captured on one board go to the other one, so it's the other board that limits captures
+    // under `PieceCapPolicy::Refuse`.
+    fn update_capture_room(&mut self) {
+        for board_idx in BughouseBoard::iter() {
+            let room = self.boards[board_idx.other()].reserve_room();
+            self.boards[board_idx].set_capture_room(room);
+        }
+    }
+
     fn game_status_for_board(&self, board_idx: BughouseBoard) -> BughouseGameStatus {
         match self.boards[board_idx].status() {
             ChessGameStatus::Active => BughouseGameStatus::Active,
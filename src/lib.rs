@@ -11,10 +11,12 @@ pub mod algebraic;
 pub mod altered_game;
 pub mod analysis_engine;
 pub mod board;
+pub mod censor;
 pub mod chalk;
 pub mod chat;
 pub mod client;
 pub mod client_chat;
+pub mod client_settings;
 pub mod clock;
 pub mod coord;
 pub mod dirty;
@@ -35,6 +37,7 @@ pub mod pgn;
 pub mod piece;
 pub mod ping_pong;
 pub mod player;
+pub mod player_name;
 pub mod role;
 pub mod rules;
 pub mod scores;
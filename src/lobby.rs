@@ -1,6 +1,6 @@
 // TODO: Rename this module.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{cmp, mem};
 
 use enum_map::{EnumMap, enum_map};
@@ -29,6 +29,8 @@ pub enum ParticipantsError {
     NotEnoughPlayers,
     EmptyTeam,
     RatedDoublePlay,
+    RatedGuestPlayer,
+    GuestPlayer,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -56,6 +58,25 @@ impl ParticipantsStatus {
     }
 }
 
+// Seating change for a rematch with the same players.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SeatSwap {
+    // Each player moves to the other board and stays in the same team. Partners swap places, so
+    // everybody gets the other color.
+    Boards,
+    // Each player stays on the same board, but gets the other color. Teams stay together, but
+    // switch sides, so this is impossible if a player is fixed to their team.
+    Colors,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SeatSwapError {
+    // One of the players from the previous game is no longer playing.
+    PlayerLeft,
+    // The new seat is in a team other than the one the player has chosen.
+    FixedTeam,
+}
+
 pub fn num_fixed_players_per_team<'a>(
     participants: impl Iterator<Item = &'a Participant>,
 ) -> EnumMap<Team, usize> {
@@ -96,6 +117,16 @@ pub fn verify_participants<'a>(
         return ParticipantsStatus::CannotStart(ParticipantsError::RatedDoublePlay);
     }
 
+    // Normally guests cannot join such matches in the first place, but the game must not start
+    // even if one slipped through.
+    if participants.clone().any(|p| p.faction.is_player() && !p.is_registered_user) {
+        if rules.match_rules.rated {
+            return ParticipantsStatus::CannotStart(ParticipantsError::RatedGuestPlayer);
+        } else if rules.match_rules.registered_only {
+            return ParticipantsStatus::CannotStart(ParticipantsError::GuestPlayer);
+        }
+    }
+
     let players_ready = participants.filter(|p| p.faction.is_player()).all(|p| p.is_ready);
     let warning = match (need_to_double_play, need_to_seat_out) {
         (true, true) => Some(ParticipantsWarning::NeedToDoublePlayAndSeatOut),
@@ -106,6 +137,32 @@ pub fn verify_participants<'a>(
     ParticipantsStatus::CanStart { players_ready, warning }
 }
 
+// Checks that changing a participant faction from `old_faction` to `new_faction` keeps the roster
+// playable. With fixed teams nobody can be reassigned, so a team left without players would make
+// every subsequent game impossible.
+pub fn verify_faction_change<'a>(
+    teaming: Option<Teaming>, participants: impl Iterator<Item = &'a Participant>,
+    old_faction: Faction, new_faction: Faction,
+) -> Result<(), ParticipantsError> {
+    if teaming != Some(Teaming::FixedTeams) {
+        return Ok(());
+    }
+    let players_before = num_fixed_players_per_team(participants);
+    let mut players_after = players_before;
+    if let Faction::Fixed(team) = old_faction {
+        players_after[team] -= 1;
+    }
+    if let Faction::Fixed(team) = new_faction {
+        players_after[team] += 1;
+    }
+    for team in Team::iter() {
+        if players_before[team] > 0 && players_after[team] == 0 {
+            return Err(ParticipantsError::EmptyTeam);
+        }
+    }
+    Ok(())
+}
+
 // If teams are bound to be the same every game, sets a fixed team for every participant with
 // Faction::Random and returns Teaming::FixedTeams. Otherwise, returns Teaming::DynamicTeams.
 //
@@ -315,6 +372,118 @@ pub fn assign_boards<'a>(
         .collect_vec()
 }
 
+// "Winner stays" seating for individual matches: the team that won the previous game keeps their
+// seats, and the other team's seats go to challengers. Players who sat out the previous game are
+// preferred, so the losers rotate out whenever there is somebody to replace them.
+//
+// Falls back to `assign_boards` if there is no winning pair to keep: before the first game, after
+// a draw, if a winner has left or cannot play for their team anymore, or if the winning team was
+// a single player playing on two boards.
+pub fn assign_boards_winner_stays<'a>(
+    participants: impl Iterator<Item = &'a Participant> + Clone, previous_players: &[PlayerInGame],
+    winner: Option<Team>, current_assignment: Option<&[PlayerInGame]>, rng: &mut impl Rng,
+) -> Vec<PlayerInGame> {
+    let Some(winner) = winner else {
+        return assign_boards(participants, current_assignment, rng);
+    };
+    let factions: HashMap<_, _> =
+        participants.clone().map(|p| (p.name.as_str(), p.faction)).collect();
+    let can_play_for = |name: &str, team: Team| match factions.get(name) {
+        Some(Faction::Random) => true,
+        Some(&Faction::Fixed(t)) => t == team,
+        Some(Faction::Observer) | None => false,
+    };
+    let winners = previous_players.iter().filter(|p| p.id.team() == winner).cloned().collect_vec();
+    if winners.len() != TOTAL_ENVOYS_PER_TEAM
+        || !winners.iter().all(|p| can_play_for(&p.name, winner))
+    {
+        return assign_boards(participants, current_assignment, rng);
+    }
+
+    let loser = winner.opponent();
+    let previous_names: HashSet<_> = previous_players.iter().map(|p| p.name.as_str()).collect();
+    // Keep challengers stable once the next game players have been announced.
+    let announced_names: HashSet<_> = current_assignment
+        .into_iter()
+        .flatten()
+        .filter(|p| p.id.team() == loser)
+        .map(|p| p.name.as_str())
+        .collect();
+    let mut challengers = participants
+        .clone()
+        .filter(|p| !winners.iter().any(|w| w.name == p.name) && can_play_for(&p.name, loser))
+        .collect_vec();
+    // Shuffle first: sorting is stable, so the order within each priority bucket stays random.
+    challengers.shuffle(rng);
+    challengers.sort_by_key(|p| {
+        (
+            cmp::Reverse(announced_names.contains(p.name.as_str())),
+            previous_names.contains(p.name.as_str()),
+            cmp::Reverse(p.scheduling_priority),
+        )
+    });
+
+    let mut players = winners;
+    match challengers.as_slice() {
+        [] => return assign_boards(participants, current_assignment, rng),
+        [challenger] => players.push(PlayerInGame {
+            name: challenger.name.clone(),
+            id: BughousePlayer::DoublePlayer(loser),
+        }),
+        _ => {
+            for (board_idx, challenger) in BughouseBoard::iter().zip(&challengers) {
+                players.push(PlayerInGame {
+                    name: challenger.name.clone(),
+                    id: BughousePlayer::SinglePlayer(BughouseEnvoy {
+                        board_idx,
+                        force: get_bughouse_force(loser, board_idx),
+                    }),
+                });
+            }
+        }
+    }
+    players
+}
+
+// Returns the previous game players in swapped seats. Unlike `assign_boards`, this is
+// deterministic and never changes who plays: players with `Faction::Random` keep the team from the
+// previous game (modulo `SeatSwap::Colors` switching the sides) instead of being shuffled again.
+pub fn swap_seats<'a>(
+    participants: impl Iterator<Item = &'a Participant>, previous_players: &[PlayerInGame],
+    swap: SeatSwap,
+) -> Result<Vec<PlayerInGame>, SeatSwapError> {
+    let factions: HashMap<_, _> = participants.map(|p| (p.name.as_str(), p.faction)).collect();
+    previous_players
+        .iter()
+        .map(|p| {
+            let id = match (p.id, swap) {
+                (BughousePlayer::SinglePlayer(envoy), SeatSwap::Boards) => {
+                    BughousePlayer::SinglePlayer(envoy.partner())
+                }
+                (BughousePlayer::SinglePlayer(envoy), SeatSwap::Colors) => {
+                    BughousePlayer::SinglePlayer(envoy.opponent())
+                }
+                (BughousePlayer::DoublePlayer(team), SeatSwap::Boards) => {
+                    BughousePlayer::DoublePlayer(team)
+                }
+                (BughousePlayer::DoublePlayer(team), SeatSwap::Colors) => {
+                    BughousePlayer::DoublePlayer(team.opponent())
+                }
+            };
+            match factions.get(p.name.as_str()) {
+                Some(Faction::Random) => {}
+                Some(&Faction::Fixed(team)) => {
+                    if team != id.team() {
+                        return Err(SeatSwapError::FixedTeam);
+                    }
+                }
+                Some(Faction::Observer) | None => return Err(SeatSwapError::PlayerLeft),
+            }
+            Ok(PlayerInGame { name: p.name.clone(), id })
+        })
+        .collect()
+}
+
 pub fn post_game_update_participant_counters(
     participants: &mut impl IterableMut<Participant>,
     get_player_id: impl Fn(&str) -> Option<BughousePlayer>,
@@ -376,12 +545,13 @@ mod tests {
     use std::collections::HashMap;
 
     use super::*;
+    use crate::board::Reserve;
     use crate::envoy;
     use crate::force::Force;
     use crate::game::{double_player, single_player};
     use crate::half_integer::HalfU32;
     use crate::player::PlayerSchedulingPriority;
-    use crate::rules::{ChessRules, MatchRules};
+    use crate::rules::{ChessRules, MatchRules, RatedGameChat};
     use crate::test_util::deterministic_rng;
 
     // Number of times to run a calculation in order to make sure an invariant holds for any random
@@ -413,6 +583,9 @@ mod tests {
                 scheduling_priority: PlayerSchedulingPriority::default(),
                 is_online: true,
                 is_ready: true,
+                handicap: Reserve::default(),
+                rating: None,
+                show_rating: true,
             });
         }
 
@@ -428,6 +601,9 @@ mod tests {
                 scheduling_priority: PlayerSchedulingPriority::default(),
                 is_online: true,
                 is_ready,
+                handicap: Reserve::default(),
+                rating: None,
+                show_rating: true,
             });
         }
     }
@@ -450,7 +626,17 @@ mod tests {
     fn make_rules(rated: bool) -> Rules {
         Rules {
             chess_rules: ChessRules::bughouse_international5(),
-            match_rules: MatchRules { rated, public: true },
+            match_rules: MatchRules {
+                rated,
+                public: true,
+                registered_only: false,
+                rated_game_chat: RatedGameChat::Everyone,
+                winner_stays: false,
+                rated_preturn_limit: None,
+                chat_slow_mode: None,
+                abort_window: None,
+                match_target: None,
+            },
         }
     }
 
@@ -531,6 +717,69 @@ mod tests {
         assert_eq!(fix_teams_if_needed(&mut participants), Teaming::FixedTeams);
     }
 
+    #[test]
+    fn registered_only() {
+        let rules = Rules {
+            chess_rules: ChessRules::bughouse_international5(),
+            match_rules: MatchRules {
+                rated: false,
+                public: true,
+                registered_only: true,
+                rated_game_chat: RatedGameChat::Everyone,
+                winner_stays: false,
+                rated_preturn_limit: None,
+                chat_slow_mode: None,
+                abort_window: None,
+                match_target: None,
+            },
+        };
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Fixed(Team::Red));
+        participants.add("p2", Faction::Fixed(Team::Red));
+        participants.add("p3", Faction::Fixed(Team::Blue));
+        participants.add("p4", Faction::Fixed(Team::Blue));
+        participants.add("guest", Faction::Observer);
+        for name in ["p1", "p2", "p3", "p4"] {
+            participants.get_mut(name).unwrap().is_registered_user = true;
+        }
+        assert_eq!(
+            verify_participants(&rules, participants.values()),
+            ParticipantsStatus::CanStart { players_ready: true, warning: None }
+        );
+        participants.get_mut("guest").unwrap().faction = Faction::Random;
+        assert_eq!(
+            verify_participants(&rules, participants.values()),
+            ParticipantsStatus::CannotStart(ParticipantsError::GuestPlayer)
+        );
+        assert_eq!(
+            verify_participants(&make_rules(false), participants.values()),
+            ParticipantsStatus::CanStart {
+                players_ready: true,
+                warning: Some(ParticipantsWarning::NeedToSeatOut),
+            }
+        );
+    }
+
+    #[test]
+    fn rated_guest_player() {
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Fixed(Team::Red));
+        participants.add("p2", Faction::Fixed(Team::Red));
+        participants.add("p3", Faction::Fixed(Team::Blue));
+        participants.add("guest", Faction::Fixed(Team::Blue));
+        for name in ["p1", "p2", "p3"] {
+            participants.get_mut(name).unwrap().is_registered_user = true;
+        }
+        assert_eq!(
+            verify_participants(&make_rules(true), participants.values()),
+            ParticipantsStatus::CannotStart(ParticipantsError::RatedGuestPlayer)
+        );
+        assert_eq!(
+            verify_participants(&make_rules(false), participants.values()),
+            ParticipantsStatus::CanStart { players_ready: true, warning: None }
+        );
+    }
+
     #[test]
     fn two_players_fixable() {
         let mut participants = Participants::new();
@@ -1009,6 +1258,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn faction_change_keeps_teams_non_empty() {
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Fixed(Team::Red));
+        participants.add("p2", Faction::Fixed(Team::Red));
+        participants.add("p3", Faction::Fixed(Team::Blue));
+        participants.add("p4", Faction::Observer);
+        let fixed = Some(Teaming::FixedTeams);
+        let red = Faction::Fixed(Team::Red);
+        let blue = Faction::Fixed(Team::Blue);
+        assert_eq!(
+            verify_faction_change(fixed, participants.values(), red, Faction::Observer),
+            Ok(())
+        );
+        assert_eq!(verify_faction_change(fixed, participants.values(), red, blue), Ok(()));
+        assert_eq!(
+            verify_faction_change(fixed, participants.values(), blue, Faction::Observer),
+            Err(ParticipantsError::EmptyTeam)
+        );
+        assert_eq!(
+            verify_faction_change(fixed, participants.values(), blue, red),
+            Err(ParticipantsError::EmptyTeam)
+        );
+        assert_eq!(
+            verify_faction_change(fixed, participants.values(), Faction::Observer, blue),
+            Ok(())
+        );
+        assert_eq!(
+            verify_faction_change(
+                Some(Teaming::DynamicTeams),
+                participants.values(),
+                blue,
+                Faction::Observer
+            ),
+            Ok(())
+        );
+    }
+
     #[test]
     fn reassignment_adds_players_if_double_play() {
         let rng = &mut deterministic_rng();
@@ -1033,4 +1320,180 @@ mod tests {
             assert_close!(participants[name].games_played, 500, "{participants:?}");
         }
     }
+
+    #[test]
+    fn winner_stays_across_consecutive_games() {
+        let rng = &mut deterministic_rng();
+        let mut participants = Participants::new();
+        for name in ["p1", "p2", "p3", "p4", "p5", "p6"] {
+            participants.add(name, Faction::Random);
+        }
+        let first_game = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            single_player("p3", envoy!(Black A)),
+            single_player("p4", envoy!(White B)),
+        ];
+        for _ in 0..SINGLE_TEST_ITERATIONS {
+            let second_game = assign_boards_winner_stays(
+                participants.values(),
+                &first_game,
+                Some(Team::Red),
+                None,
+                rng,
+            );
+            let second_game_map = players_to_map(second_game.clone());
+            assert_eq!(second_game_map.len(), 4);
+            assert_eq!(second_game_map["p1"], BughousePlayer::SinglePlayer(envoy!(White A)));
+            assert_eq!(second_game_map["p2"], BughousePlayer::SinglePlayer(envoy!(Black B)));
+            assert_eq!(second_game_map["p5"].team(), Team::Blue);
+            assert_eq!(second_game_map["p6"].team(), Team::Blue);
+
+            // Challengers win and keep their seats. The players who sat out take the other seats.
+            let third_game = players_to_map(assign_boards_winner_stays(
+                participants.values(),
+                &second_game,
+                Some(Team::Blue),
+                None,
+                rng,
+            ));
+            assert_eq!(third_game.len(), 4);
+            assert_eq!(third_game["p5"], second_game_map["p5"]);
+            assert_eq!(third_game["p6"], second_game_map["p6"]);
+            assert_eq!(third_game["p3"].team(), Team::Red);
+            assert_eq!(third_game["p4"].team(), Team::Red);
+        }
+    }
+
+    #[test]
+    fn winner_stays_single_challenger_double_plays() {
+        let rng = &mut deterministic_rng();
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            double_player("p3", Team::Blue),
+        ];
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Random);
+        participants.add("p2", Faction::Random);
+        participants.add("p3", Faction::Random);
+        assert_eq!(
+            assign_boards_winner_stays(
+                participants.values(),
+                &previous_players,
+                Some(Team::Red),
+                None,
+                rng
+            ),
+            vec![
+                single_player("p1", envoy!(White A)),
+                single_player("p2", envoy!(Black B)),
+                double_player("p3", Team::Blue),
+            ]
+        );
+    }
+
+    #[test]
+    fn winner_stays_falls_back_after_draw() {
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            single_player("p3", envoy!(Black A)),
+            single_player("p4", envoy!(White B)),
+        ];
+        let mut participants = Participants::new();
+        for name in ["p1", "p2", "p3", "p4", "p5"] {
+            participants.add(name, Faction::Random);
+        }
+        assert_eq!(
+            assign_boards_winner_stays(
+                participants.values(),
+                &previous_players,
+                None,
+                None,
+                &mut deterministic_rng()
+            ),
+            assign_boards(participants.values(), None, &mut deterministic_rng())
+        );
+    }
+
+    #[test]
+    fn swap_seats_boards() {
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            single_player("p3", envoy!(Black A)),
+            single_player("p4", envoy!(White B)),
+        ];
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Fixed(Team::Red));
+        participants.add("p2", Faction::Random);
+        participants.add("p3", Faction::Random);
+        participants.add("p4", Faction::Fixed(Team::Blue));
+        assert_eq!(
+            swap_seats(participants.values(), &previous_players, SeatSwap::Boards),
+            Ok(vec![
+                single_player("p1", envoy!(Black B)),
+                single_player("p2", envoy!(White A)),
+                single_player("p3", envoy!(White B)),
+                single_player("p4", envoy!(Black A)),
+            ])
+        );
+    }
+
+    #[test]
+    fn swap_seats_colors() {
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            double_player("p3", Team::Blue),
+        ];
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Random);
+        participants.add("p2", Faction::Random);
+        participants.add("p3", Faction::Random);
+        assert_eq!(
+            swap_seats(participants.values(), &previous_players, SeatSwap::Colors),
+            Ok(vec![
+                single_player("p1", envoy!(Black A)),
+                single_player("p2", envoy!(White B)),
+                double_player("p3", Team::Red),
+            ])
+        );
+    }
+
+    #[test]
+    fn swap_seats_respects_fixed_teams() {
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            double_player("p3", Team::Blue),
+        ];
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Random);
+        participants.add("p2", Faction::Random);
+        participants.add("p3", Faction::Fixed(Team::Blue));
+        assert_eq!(
+            swap_seats(participants.values(), &previous_players, SeatSwap::Colors),
+            Err(SeatSwapError::FixedTeam)
+        );
+        assert!(swap_seats(participants.values(), &previous_players, SeatSwap::Boards).is_ok());
+    }
+
+    #[test]
+    fn swap_seats_requires_same_players() {
+        let previous_players = [
+            single_player("p1", envoy!(White A)),
+            single_player("p2", envoy!(Black B)),
+            double_player("p3", Team::Blue),
+        ];
+        let mut participants = Participants::new();
+        participants.add("p1", Faction::Random);
+        participants.add("p2", Faction::Observer);
+        participants.add("p3", Faction::Random);
+        assert_eq!(
+            swap_seats(participants.values(), &previous_players, SeatSwap::Boards),
+            Err(SeatSwapError::PlayerLeft)
+        );
+    }
 }
@@ -1,17 +1,22 @@
 // TODO: Replace with prometheus::local.
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 use std::time::Duration;
 use std::{cmp, fmt};
 
 use hdrhistogram::Histogram;
+use instant::Instant;
 use serde::{Deserialize, Serialize};
 
 
 pub const METER_SIGNIFICANT_DIGITS: u8 = 3;
 
+// Do not report slow frames more often than this: when the client is slow, it's usually slow for a
+// while and repeating the warning every frame is not helpful.
+pub const SLOW_FRAME_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MeterStats {
     pub p50: u64,
@@ -95,3 +100,111 @@ impl Meter {
         }
     }
 }
+
+
+// Breakdown of frame rendering time by section. Keeps the durations from the last frame (for a dev
+// overlay) together with the distribution over all frames. Values are in microseconds, because
+// most sections take less than a millisecond.
+//
+// Uses interior mutability so that rendering code that takes `&self` could record timings.
+#[derive(Clone, Debug, Default)]
+pub struct FrameTimings {
+    sections: RefCell<BTreeMap<&'static str, SectionTiming>>,
+}
+
+#[derive(Clone, Debug)]
+struct SectionTiming {
+    last: Duration,
+    meter: Meter,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SectionTimingReport {
+    pub last_us: u64,
+    pub stats_us: MeterStats,
+}
+
+impl FrameTimings {
+    pub fn new() -> Self { FrameTimings::default() }
+
+    pub fn record(&self, section: &'static str, duration: Duration) {
+        let mut sections = self.sections.borrow_mut();
+        let timing = sections.entry(section).or_insert_with(|| SectionTiming {
+            last: Duration::ZERO,
+            meter: Meter::new(),
+        });
+        timing.last = duration;
+        timing.meter.record(duration_to_micros(duration));
+    }
+
+    pub fn measure<T>(&self, section: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(section, start.elapsed());
+        result
+    }
+
+    pub fn report(&self) -> BTreeMap<&'static str, SectionTimingReport> {
+        self.sections
+            .borrow()
+            .iter()
+            .map(|(&section, timing)| {
+                let report = SectionTimingReport {
+                    last_us: duration_to_micros(timing.last),
+                    stats_us: timing.meter.stats(),
+                };
+                (section, report)
+            })
+            .collect()
+    }
+}
+
+
+// Detects frames that take longer than the budget. Reports are rate-limited, see
+// `SLOW_FRAME_REPORT_INTERVAL`. Frames that are known to be heavy (e.g. rendering boards from
+// scratch) can be exempted via `expect_heavy_frame`.
+#[derive(Clone, Debug)]
+pub struct FrameBudget {
+    budget: Option<Duration>,
+    last_report: Option<Instant>,
+    skip_next_frame: bool,
+}
+
+impl FrameBudget {
+    pub fn new(budget: Option<Duration>) -> Self {
+        FrameBudget {
+            budget,
+            last_report: None,
+            skip_next_frame: false,
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: Option<Duration>) { self.budget = budget; }
+
+    pub fn expect_heavy_frame(&mut self) { self.skip_next_frame = true; }
+
+    // Returns whether the frame should be reported as slow.
+    pub fn check(&mut self, duration: Duration, now: Instant) -> bool {
+        if std::mem::take(&mut self.skip_next_frame) {
+            return false;
+        }
+        let Some(budget) = self.budget else {
+            return false;
+        };
+        if duration <= budget {
+            return false;
+        }
+        if self
+            .last_report
+            .is_some_and(|t| now.duration_since(t) < SLOW_FRAME_REPORT_INTERVAL)
+        {
+            return false;
+        }
+        self.last_report = Some(now);
+        true
+    }
+}
+
+fn duration_to_micros(duration: Duration) -> u64 {
+    cmp::min(duration.as_micros(), u64::MAX.into()).try_into().unwrap()
+}
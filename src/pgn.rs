@@ -6,7 +6,8 @@ use std::time::Duration;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
-use time::macros::format_description;
+use time::OffsetDateTime;
+use time::macros::{datetime, format_description};
 
 use crate::algebraic::AlgebraicCharset;
 use crate::board::{DrawReason, TurnInput, TurnMode, VictoryReason};
@@ -21,8 +22,9 @@ use crate::game::{
 use crate::player::Team;
 use crate::role::Role;
 use crate::rules::{
-    BughouseRules, ChessRules, ChessVariant, DropAggression, FairyPieces, MatchRules,
-    PawnDropRanks, Promotion, Rules, StartingPosition,
+    BughouseRules, Castling, ChessRules, ChessVariant, DelayedStart, DropAggression, FairyPieces,
+    FogOfWarVisibility, MatchRules, PawnDropRanks, PieceCap, PieceValues, Promotion, RatedGameChat,
+    Rules, SimultaneousFlagPolicy, Stalemate, StartingPosition,
 };
 use crate::starter::EffectiveStartingPosition;
 use crate::utc_time::UtcDateTime;
@@ -35,6 +37,13 @@ pub struct BpgnMetadata {
     pub round: u64,
 }
 
+// Before this date our BPGNs didn't contain enough information to parse the game.
+pub const GAME_VIEW_AVAILABLE_SINCE: OffsetDateTime = datetime!(2023-03-01 00:00:00 UTC);
+
+pub fn game_view_available(game_start_time: UtcDateTime) -> bool {
+    OffsetDateTime::from(game_start_time) >= GAME_VIEW_AVAILABLE_SINCE
+}
+
 // Other possible formats:
 //
 //   - https://bughousedb.com/Lieven_BPGN_Standard.txt shows remaining clock time as whole
@@ -289,7 +298,7 @@ impl BpgnHeader {
 
 impl BpgnTurn {
     fn render_without_addenda(&self) -> String {
-        format!("{}{}. {}", self.number, envoy_notation(self.envoy), self.algebraic)
+        format!("{}{}. {}", self.number, self.envoy.to_notation(), self.algebraic)
     }
     fn render(&self) -> String {
         let mut result = self.render_without_addenda();
@@ -305,7 +314,7 @@ impl BpgnTurn {
         let pre = pre.strip_suffix('.').ok_or("turn number must end with a dot")?;
         let (number, envoy) = pre.split_at(pre.len() - 1); // ok: envoy notation is always ASCII
         let number = number.parse().map_err(|_| "invalid turn number")?;
-        let envoy = parse_envoy_notation(envoy).ok_or("invalid player notation")?;
+        let envoy = BughouseEnvoy::from_notation(envoy).ok_or("invalid player notation")?;
         let Some(Token::Word(algebraic)) = tokens.next() else {
             return Err("missing algebraic notation");
         };
@@ -382,13 +391,24 @@ impl TagMap {
     }
 }
 
+// Follows the PGN standard: "<starting seconds>" or "<starting seconds>+<increment seconds>".
+// `TimeControl::no_increment_turns` is stored in a separate tag.
 fn render_time_control(control: &TimeControl) -> String {
-    control.starting_time.as_secs().to_string()
+    let starting_time = control.starting_time.as_secs();
+    if control.increment.is_zero() {
+        starting_time.to_string()
+    } else {
+        format!("{starting_time}+{}", control.increment.as_secs())
+    }
 }
-fn parse_time_control(s: &str) -> Result<TimeControl, &'static str> {
-    let seconds = s.parse().map_err(|_| "invalid time control")?;
+fn parse_time_control(s: &str, no_increment_turns: u32) -> Result<TimeControl, &'static str> {
+    let (starting_time, increment) = s.split_once('+').unwrap_or((s, "0"));
+    let starting_time = starting_time.parse().map_err(|_| "invalid time control")?;
+    let increment = increment.parse().map_err(|_| "invalid time control")?;
     Ok(TimeControl {
-        starting_time: Duration::from_secs(seconds),
+        starting_time: Duration::from_secs(starting_time),
+        increment: Duration::from_secs(increment),
+        no_increment_turns,
     })
 }
 
@@ -426,7 +446,9 @@ fn make_termination_string(status: BughouseGameStatus) -> Option<&'static str> {
     match status {
         Active => Some("unterminated"),
         Victory(_, Checkmate) => Some("normal"),
+        Victory(_, KingCaptured) => Some("normal"),
         Victory(_, Flag) => Some("time forfeit"),
+        Victory(_, SimultaneousFlagMaterial) => Some("time forfeit"),
         // There is no "resign" Termination, should use "normal" apparently:
         // https://lichess.org/forum/general-chess-discussion/how-do-i-make-it-say-that-one-side-resigned#4
         Victory(_, Resignation) => Some("normal"),
@@ -435,33 +457,14 @@ fn make_termination_string(status: BughouseGameStatus) -> Option<&'static str> {
         // Somehow I'm skeptical many chess engines would be prepared for a "time forfeit" draw
         Draw(SimultaneousFlag) => Some("normal"),
         Draw(ThreefoldRepetition) => Some("normal"),
+        Draw(InsufficientMaterial) => Some("normal"),
+        Draw(DrawReason::Stalemate) => Some("normal"),
+        Draw(Aborted) => Some("abandoned"),
+        Draw(SplitBoards) => Some("normal"),
         Draw(UnknownDraw) => None,
     }
 }
 
-fn envoy_notation(envoy: BughouseEnvoy) -> &'static str {
-    use BughouseBoard::*;
-    use Force::*;
-    match (envoy.board_idx, envoy.force) {
-        (A, White) => "A",
-        (A, Black) => "a",
-        (B, White) => "B",
-        (B, Black) => "b",
-    }
-}
-
-fn parse_envoy_notation(s: &str) -> Option<BughouseEnvoy> {
-    use BughouseBoard::*;
-    use Force::*;
-    match s {
-        "A" => Some(BughouseEnvoy { board_idx: A, force: White }),
-        "a" => Some(BughouseEnvoy { board_idx: A, force: Black }),
-        "B" => Some(BughouseEnvoy { board_idx: B, force: White }),
-        "b" => Some(BughouseEnvoy { board_idx: B, force: Black }),
-        _ => None,
-    }
-}
-
 fn total_game_duration(game: &BughouseGame) -> Option<GameInstant> {
     // Note. Cannot use `turn_log()` because it does not record time forfeits and resignations.
     // TODO: Store latest game event time and use it here and in `current_game_time` in `server.rs`.
@@ -495,10 +498,51 @@ fn make_bughouse_bpng_header(game: &BughouseGame, meta: BpgnMetadata) -> BpgnHea
     h.push_tag("WhiteB", game.board(B).player_name(White));
     h.push_tag("BlackB", game.board(B).player_name(Black));
     h.push_tag("TimeControl", render_time_control(&game.chess_rules().time_control));
+    if game.chess_rules().time_control.no_increment_turns > 0 {
+        h.push_tag(
+            "NoIncrementTurns",
+            game.chess_rules().time_control.no_increment_turns.to_string(),
+        );
+    }
     h.push_tag("Variant", variants.join(" "));
     h.push_tag("Promotion", game.bughouse_rules().promotion.to_pgn());
     h.push_tag("DropAggression", game.bughouse_rules().drop_aggression.to_pgn());
     h.push_tag("PawnDropRanks", game.bughouse_rules().pawn_drop_ranks.to_pgn());
+    if game.bughouse_rules().no_drop_turns > 0 {
+        h.push_tag("NoDropTurns", game.bughouse_rules().no_drop_turns.to_string());
+    }
+    if game.bughouse_rules().no_interposing_drops {
+        h.push_tag("InterposingDrops", "Forbidden");
+    }
+    if game.bughouse_rules().simultaneous_flag != SimultaneousFlagPolicy::Draw {
+        h.push_tag("SimultaneousFlag", game.bughouse_rules().simultaneous_flag.to_pgn());
+    }
+    if game.bughouse_rules().stalemate != Stalemate::Wait {
+        h.push_tag("Stalemate", game.bughouse_rules().stalemate.to_pgn());
+    }
+    if game.bughouse_rules().board_resignation {
+        h.push_tag("BoardResignation", "Allowed");
+    }
+    if let Some(piece_cap) = game.bughouse_rules().piece_cap {
+        h.push_tag("PieceCap", piece_cap.to_pgn());
+    }
+    if let Some(delayed_start) = game.bughouse_rules().delayed_start {
+        h.push_tag("DelayedStart", delayed_start.to_pgn());
+    }
+    if game.chess_rules().castling_through_check {
+        h.push_tag("CastlingThroughCheck", "Allowed");
+    }
+    if game.chess_rules().castling != Castling::Allowed {
+        h.push_tag("Castling", game.chess_rules().castling.to_pgn());
+    }
+    if game.chess_rules().fog_of_war
+        && game.chess_rules().fog_of_war_visibility != FogOfWarVisibility::Movement
+    {
+        h.push_tag("FogOfWarVisibility", game.chess_rules().fog_of_war_visibility.to_pgn());
+    }
+    if game.chess_rules().fog_of_war && game.chess_rules().fog_of_war_hide_reserves {
+        h.push_tag("FogOfWarReserves", "Hidden");
+    }
     match game.chess_rules().starting_position {
         StartingPosition::Classic => {}
         StartingPosition::FischerRandom => {
@@ -531,29 +575,30 @@ fn make_bughouse_bpng_header(game: &BughouseGame, meta: BpgnMetadata) -> BpgnHea
 //   - "Variant" - follow chess.com example;
 //   - "Outcome" - human-readable game result description; this is addition to "Result"
 //     and "Termination" fields, which follow PGN standard, but are less informative.
-//   - "Promotion", "DropAggression", "PawnDropRanks" - bughouse-specific rules.
+//   - "Promotion", "DropAggression", "PawnDropRanks", "NoDropTurns", "InterposingDrops",
+//     "SimultaneousFlag", "Stalemate", "BoardResignation", "PieceCap", "DelayedStart" -
+//     bughouse-specific rules.
+//   - "CastlingThroughCheck" - castling rules relaxation.
+//   - "NoIncrementTurns" - time control extension.
 pub fn export_to_bpgn(format: BpgnExportFormat, game: &BughouseGame, meta: BpgnMetadata) -> String {
     let header = make_bughouse_bpng_header(game, meta);
     let turns = game
-        .turn_log()
-        .iter()
-        .map(|r| {
+        .move_list_san(AlgebraicCharset::Ascii)
+        .into_iter()
+        .map(|san| {
             let mut addenda = vec![];
             match format.time_format {
                 BpgnTimeFormat::NoTime => {}
                 BpgnTimeFormat::Timestamp => {
-                    if let Some(ts) = r.time.to_pgn_timestamp() {
+                    if let Some(ts) = game.turn_record(san.index).time.to_pgn_timestamp() {
                         addenda.push(("ts".to_owned(), ts));
                     }
                 }
             }
             BpgnTurn {
-                number: r.local_number,
-                envoy: r.envoy,
-                algebraic: r
-                    .turn_expanded
-                    .algebraic
-                    .format(game.board_shape(), AlgebraicCharset::Ascii),
+                number: san.number,
+                envoy: san.envoy,
+                algebraic: san.algebraic,
                 addenda,
             }
         })
@@ -596,7 +641,16 @@ fn parse_variants(s: &str) -> Result<HashSet<ChessVariant>, String> {
 fn parse_rules(tags: &TagMap) -> Result<Rules, String> {
     let rated = tags.get("Event")?.starts_with("Rated");
     let public = true; // TODO: Parse when this is serialized.
-    let time_control = parse_time_control(tags.get("TimeControl")?)?;
+    let registered_only = false; // TODO: Parse when this is serialized.
+    let rated_game_chat = RatedGameChat::Everyone; // TODO: Parse when this is serialized.
+    let winner_stays = false; // TODO: Parse when this is serialized.
+    let rated_preturn_limit = None; // TODO: Parse when this is serialized.
+    let chat_slow_mode = None; // TODO: Parse when this is serialized.
+    let abort_window = None; // TODO: Parse when this is serialized.
+    let match_target = None; // TODO: Parse when this is serialized.
+    // No increment delay by default, because the tag is omitted in this case.
+    let no_increment_turns = tags.get_and_parse_or("NoIncrementTurns", str::parse, 0)?;
+    let time_control = parse_time_control(tags.get("TimeControl")?, no_increment_turns)?;
     let variants = parse_variants(tags.get("Variant")?)?;
     let starting_position = if variants.contains(&ChessVariant::FischerRandom) {
         StartingPosition::FischerRandom
@@ -613,6 +667,16 @@ fn parse_rules(tags: &TagMap) -> Result<Rules, String> {
     //   - For Promotion: use Upgrade, because these are standard chess rules.
     //   - For PawnDropRanks and DropAggression: use the most permissive setting, so that games
     //     don't fail to parse.
+    //   - For NoDropTurns: zero, because the tag is omitted when drops are allowed from the start.
+    //   - For InterposingDrops: allowed, because the tag is omitted in this case.
+    //   - For SimultaneousFlag: draw, because the tag is omitted in this case.
+    //   - For Stalemate: wait, because the tag is omitted in this case.
+    //   - For BoardResignation: forbidden, because the tag is omitted in this case.
+    //   - For PieceCap: no cap, because the tag is omitted in this case.
+    //   - For DelayedStart: no delay, because the tag is omitted in this case.
+    //   - For CastlingThroughCheck: forbidden, because the tag is omitted in this case.
+    //   - For FogOfWarVisibility: movement, because the tag is omitted in this case.
+    //   - For FogOfWarReserves: visible, because the tag is omitted in this case.
     let promotion = tags.get_and_parse_or("Promotion", Promotion::from_pgn, Promotion::Upgrade)?;
     let pawn_drop_ranks = tags.get_and_parse_or(
         "PawnDropRanks",
@@ -624,20 +688,94 @@ fn parse_rules(tags: &TagMap) -> Result<Rules, String> {
         DropAggression::from_pgn,
         DropAggression::MateAllowed,
     )?;
+    let no_drop_turns = tags.get_and_parse_or("NoDropTurns", str::parse, 0)?;
+    let no_interposing_drops = tags.get_and_parse_or(
+        "InterposingDrops",
+        |s| match s {
+            "Allowed" => Ok(false),
+            "Forbidden" => Ok(true),
+            _ => Err(()),
+        },
+        false,
+    )?;
+    let simultaneous_flag = tags.get_and_parse_or(
+        "SimultaneousFlag",
+        SimultaneousFlagPolicy::from_pgn,
+        SimultaneousFlagPolicy::Draw,
+    )?;
+    let stalemate = tags.get_and_parse_or("Stalemate", Stalemate::from_pgn, Stalemate::Wait)?;
+    let board_resignation = tags.get_and_parse_or(
+        "BoardResignation",
+        |s| match s {
+            "Allowed" => Ok(true),
+            "Forbidden" => Ok(false),
+            _ => Err(()),
+        },
+        false,
+    )?;
+    let piece_cap = tags.get_and_parse_or("PieceCap", |s| PieceCap::from_pgn(s).map(Some), None)?;
+    let delayed_start =
+        tags.get_and_parse_or("DelayedStart", |s| DelayedStart::from_pgn(s).map(Some), None)?;
+    let castling_through_check = tags.get_and_parse_or(
+        "CastlingThroughCheck",
+        |s| match s {
+            "Allowed" => Ok(true),
+            "Forbidden" => Ok(false),
+            _ => Err(()),
+        },
+        false,
+    )?;
+    let castling = tags.get_and_parse_or("Castling", Castling::from_pgn, Castling::Allowed)?;
+    let fog_of_war_visibility = tags.get_and_parse_or(
+        "FogOfWarVisibility",
+        FogOfWarVisibility::from_pgn,
+        FogOfWarVisibility::Movement,
+    )?;
+    let fog_of_war_hide_reserves = tags.get_and_parse_or(
+        "FogOfWarReserves",
+        |s| match s {
+            "Visible" => Ok(false),
+            "Hidden" => Ok(true),
+            _ => Err(()),
+        },
+        false,
+    )?;
     Ok(Rules {
-        match_rules: MatchRules { rated, public },
+        match_rules: MatchRules {
+            rated,
+            public,
+            registered_only,
+            rated_game_chat,
+            winner_stays,
+            rated_preturn_limit,
+            chat_slow_mode,
+            abort_window,
+            match_target,
+        },
         chess_rules: ChessRules {
             fairy_pieces,
             starting_position,
             duck_chess: variants.contains(&ChessVariant::DuckChess),
             atomic_chess: variants.contains(&ChessVariant::AtomicChess),
             fog_of_war: variants.contains(&ChessVariant::FogOfWar),
+            fog_of_war_visibility,
+            fog_of_war_hide_reserves,
+            castling_through_check,
+            castling,
             time_control,
+            piece_values: PieceValues::default(),
             bughouse_rules: Some(BughouseRules {
                 koedem: variants.contains(&ChessVariant::Koedem),
                 promotion,
                 pawn_drop_ranks,
                 drop_aggression,
+                no_drop_turns,
+                no_interposing_drops,
+                simultaneous_flag,
+                stalemate,
+                board_resignation,
+                piece_cap,
+                delayed_start,
             }),
         },
     })
@@ -781,6 +919,22 @@ pub fn import_from_bpgn(s: &str, role: Role) -> Result<(BughouseGame, BpgnMetada
     Ok((game, meta))
 }
 
+// Parses only the header. Much cheaper than `import_from_bpgn` when only the rules are needed.
+pub fn import_rules_from_bpgn(s: &str) -> Result<Rules, String> {
+    let tokens = tokenize_bpgn(s);
+    let header = BpgnHeader::parse(&mut tokens.into_iter().peekable())?;
+    let tags = TagMap { map: header.tags.into_iter().collect() };
+    parse_rules(&tags)
+}
+
+// Parses only the header. Works for old BPGNs that `import_from_bpgn` can no longer parse.
+pub fn import_meta_from_bpgn(s: &str) -> Result<BpgnMetadata, String> {
+    let tokens = tokenize_bpgn(s);
+    let header = BpgnHeader::parse(&mut tokens.into_iter().peekable())?;
+    let tags = TagMap { map: header.tags.into_iter().collect() };
+    parse_meta(&tags)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -229,6 +229,32 @@ impl PieceKind {
         }
     }
 
+    // Classic values in pawns. Fairy piece values follow the common consensus for Capablanca chess.
+    pub fn default_value(self) -> u32 {
+        use PieceKind::*;
+        match self {
+            Pawn => 1,
+            Knight => 3,
+            Bishop => 3,
+            Rook => 5,
+            Queen => 9,
+            Cardinal => 8,
+            Empress => 9,
+            Amazon => 12,
+            King => 0,
+            Duck => 0,
+        }
+    }
+
+    // The single source of piece values for material counting. The duck belongs to neither side,
+    // so it is worth nothing regardless of overrides.
+    pub fn value(self, rules: &ChessRules) -> u32 {
+        if self == PieceKind::Duck {
+            return 0;
+        }
+        rules.piece_values.overrides[self].unwrap_or_else(|| self.default_value())
+    }
+
     pub fn is_neutral(self) -> bool {
         use PieceKind::*;
         match self {
@@ -7,7 +7,6 @@ use instant::Instant;
 // TODO: How often should we send pings?
 pub const PING_INTERVAL: Duration = Duration::from_millis(500);
 pub const OTHER_PARTY_TEMPORARY_LOST_THRESHOLD: Duration = Duration::from_secs(3);
-pub const OTHER_PARTY_PERMANENTLY_LOST_THRESHOLD: Duration = Duration::from_secs(60);
 
 // These many ping values will be excluded from statistics after each (re)connection, as they are
 // usually outliers. This does not affect ping displayed to the user.
@@ -18,6 +17,7 @@ pub const FIRST_PINGS_TO_EXCLUDE: usize = 10;
 #[derive(Debug)]
 pub struct PassiveConnectionMonitor {
     latest_incoming: Instant,
+    permanently_lost_threshold: Duration,
 }
 
 // Connection monitor for the party that sends pings.
@@ -42,7 +42,7 @@ pub enum PassiveConnectionStatus {
     // the other party comes back.
     TemporaryLost,
 
-    // The other party hasn't responded for a long time.
+    // The other party hasn't responded for longer than `permanently_lost_threshold`.
     // They should be considered irrevocably lost.
     PermanentlyLost,
 }
@@ -64,7 +64,12 @@ impl PassiveConnectionStatus {
 }
 
 impl PassiveConnectionMonitor {
-    pub fn new(now: Instant) -> Self { PassiveConnectionMonitor { latest_incoming: now } }
+    pub fn new(now: Instant, permanently_lost_threshold: Duration) -> Self {
+        PassiveConnectionMonitor {
+            latest_incoming: now,
+            permanently_lost_threshold,
+        }
+    }
 
     pub fn latest_incoming(&self) -> Instant { self.latest_incoming }
 
@@ -74,7 +79,7 @@ impl PassiveConnectionMonitor {
 
     pub fn status(&self, now: Instant) -> PassiveConnectionStatus {
         let d = now.saturating_duration_since(self.latest_incoming);
-        if d >= OTHER_PARTY_PERMANENTLY_LOST_THRESHOLD {
+        if d >= self.permanently_lost_threshold {
             PassiveConnectionStatus::PermanentlyLost
         } else if d >= OTHER_PARTY_TEMPORARY_LOST_THRESHOLD {
             PassiveConnectionStatus::TemporaryLost
@@ -2,6 +2,7 @@ use enum_map::Enum;
 use serde::{Deserialize, Serialize};
 use strum::EnumIter;
 
+use crate::board::Reserve;
 use crate::game::BughousePlayer;
 use crate::half_integer::HalfU32;
 
@@ -62,6 +63,14 @@ pub struct Participant {
     pub scheduling_priority: PlayerSchedulingPriority,
     pub is_online: bool,
     pub is_ready: bool,
+    // Extra reserve pieces given at game start. Configured by the match owner. Ignored in rated
+    // matches.
+    pub handicap: Reserve,
+    // Current rating in the match rating pool. Only known for registered users. The server does not
+    // send it to clients if `show_rating` is false.
+    pub rating: Option<u32>,
+    // Whether the participant agrees to show their rating to others.
+    pub show_rating: bool,
 }
 
 pub const ALL_FACTIONS: &[Faction] = &[
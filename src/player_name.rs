@@ -0,0 +1,200 @@
+// Player name validation. Shared by the server and the client, so that the client can reject
+// invalid names before submitting them.
+
+use std::fmt;
+
+use censor::Censor;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::censor::profanity_censor;
+
+
+pub const MIN_PLAYER_NAME_LENGTH: usize = 2;
+
+// Upper bound for the maximum name length that a server can be configured to accept. UI layout
+// assumes that names fit into this.
+pub const MAX_PLAYER_NAME_LENGTH: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PlayerNameError {
+    InvalidCharacters,
+    NoLetters,
+    ConsecutivePunctuation,
+    InvalidFirstCharacter,
+    InvalidLastCharacter,
+    TooShort { min_length: usize },
+    TooLong { max_length: usize },
+    // The name is offensive or could be confused with system messages. We don't tell which one.
+    Censored,
+}
+
+impl fmt::Display for PlayerNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerNameError::InvalidCharacters => write!(
+                f,
+                "Player name may consist of Latin letters, digits, dashes ('-') and underscores ('_')."
+            ),
+            PlayerNameError::NoLetters => {
+                write!(f, "Player name must contain at least one letter.")
+            }
+            PlayerNameError::ConsecutivePunctuation => {
+                write!(f, "Player name cannot contain several punctuation marks in a row.")
+            }
+            PlayerNameError::InvalidFirstCharacter => {
+                write!(f, "Player name must start with a letter or number.")
+            }
+            PlayerNameError::InvalidLastCharacter => {
+                write!(f, "Player name must end with a letter or number.")
+            }
+            PlayerNameError::TooShort { min_length } => {
+                write!(f, "Minimum name length is {min_length}.")
+            }
+            PlayerNameError::TooLong { max_length } => {
+                write!(f, "Maximum name length is {max_length}.")
+            }
+            PlayerNameError::Censored => write!(f, "Please try another player name."),
+        }
+    }
+}
+
+// `max_length` comes from server config and should not exceed `MAX_PLAYER_NAME_LENGTH`.
+pub fn validate_player_name(name: &str, max_length: usize) -> Result<(), PlayerNameError> {
+    // These words cannot be used inside player names, even with slight variations.
+    const CUSTOM_CENSOR: &[&str] = &["admin", "guest"];
+
+    // TODO: Consider reserving names like "bot", "stockfish", etc.
+    // These words cannot be used as player names to avoid confusion in system messages.
+    // They can be used inside player names, though.
+    #[rustfmt::skip]
+    const CUSTOM_BAN: &[&str] = &[
+        // Pronouns
+        "I", "me", "myself", "mine", "my",
+        "we", "us", "ourselves", "ourself", "ours", "our",
+        "you", "yourselves", "yourself", "yours", "your",
+        "he", "him", "himself", "his",
+        "she", "her", "herself", "hers",
+        "it", "itself", "its",
+        "they", "them", "themselves", "themself", "theirs", "their",
+        "one", "oneself",
+        "all", "another", "any", "anybody", "anyone", "anything",
+        "both", "each", "either", "everybody", "everyone", "everything",
+        "few", "many", "most", "neither", "nobody", "none", "nothing",
+        "other", "others",
+        "several", "some", "somebody", "someone", "something", "such",
+        "what", "whatever", "which", "whichever", "who", "whoever", "whom", "whomever", "whose",
+        "as", "that",
+        // Common prepositions
+        "and", "as", "at", "by", "for", "from", "if", "in", "like", "of", "off", "on", "or",
+        "than", "then", "to", "via", "versus", "vs", "with",
+        // Directions
+        "up", "down", "left", "right", "top", "bottom", "front", "back", "forward", "backward",
+        // Chess terms
+        "chess", "bughouse",
+        "board", "piece", "turn", "move",
+        "check", "mate", "stalemate", "resign", "resigned",
+        "win", "won", "victory", "lost", "loss", "defeat", "draw", "drew", "tie", "tied",
+        "participant", "player", "observer", "spectator", "watcher",
+        "white", "black",
+        "pawn", "knight", "bishop", "rook", "queen", "king", "duck",
+    ];
+
+    if !name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_') {
+        return Err(PlayerNameError::InvalidCharacters);
+    }
+    if !name.chars().any(|ch| ch.is_ascii_alphabetic()) {
+        // Requiring that the name contains a letter reduces the risk of collision if
+        // e.g. we decide to have a DB column that stores either guest name or registered
+        // user ID. Also it just makes sense.
+        return Err(PlayerNameError::NoLetters);
+    }
+    if !name.chars().tuple_windows().all(valid_consecutive_letters) {
+        return Err(PlayerNameError::ConsecutivePunctuation);
+    }
+    if !name.chars().next().unwrap().is_ascii_alphanumeric() {
+        return Err(PlayerNameError::InvalidFirstCharacter);
+    }
+    if !name.chars().last().unwrap().is_ascii_alphanumeric() {
+        return Err(PlayerNameError::InvalidLastCharacter);
+    }
+    let len = name.chars().count();
+    if len < MIN_PLAYER_NAME_LENGTH {
+        return Err(PlayerNameError::TooShort { min_length: MIN_PLAYER_NAME_LENGTH });
+    }
+    if len > max_length {
+        return Err(PlayerNameError::TooLong { max_length });
+    }
+    if profanity_censor().check(name)
+        || Censor::custom(CUSTOM_CENSOR.iter().copied()).check(name)
+        || contains_ignoring_ascii_case(CUSTOM_BAN, name)
+    {
+        return Err(PlayerNameError::Censored);
+    }
+    Ok(())
+}
+
+fn contains_ignoring_ascii_case(haystack: &[&str], needle: &str) -> bool {
+    haystack.iter().any(|&s| s.eq_ignore_ascii_case(needle))
+}
+
+fn valid_consecutive_letters((a, b): (char, char)) -> bool {
+    a.is_ascii_alphanumeric() || b.is_ascii_alphanumeric()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis_engine::{
+        ANALYSIS_ENGINE_NAME_BLACK, ANALYSIS_ENGINE_NAME_GENERIC, ANALYSIS_ENGINE_NAME_WHITE,
+    };
+
+    fn validate(name: &str) -> Result<(), PlayerNameError> {
+        validate_player_name(name, MAX_PLAYER_NAME_LENGTH)
+    }
+
+    #[test]
+    fn validate_player_name_rules() {
+        use PlayerNameError::*;
+        assert_eq!(validate("a"), Err(TooShort { min_length: 2 }));
+        validate("123456789o123456789o").unwrap();
+        assert_eq!(validate("123456789o123456789o1"), Err(TooLong { max_length: 20 }));
+
+        validate("ab").unwrap();
+        assert_eq!(validate("12"), Err(NoLetters));
+        validate("a1").unwrap();
+        assert_eq!(validate("a#"), Err(InvalidCharacters));
+        assert_eq!(validate("игрок"), Err(InvalidCharacters));
+        assert_eq!(validate("my name"), Err(InvalidCharacters));
+
+        assert_eq!(validate("some"), Err(Censored)); // reserved word
+        validate("some_player").unwrap(); // reserved word inside
+        assert_eq!(validate("admin"), Err(Censored)); // banned word
+        assert_eq!(validate("MainAdmin"), Err(Censored)); // banned word inside
+        assert_eq!(validate("fuck"), Err(Censored)); // profanity
+        assert_eq!(validate("xFuCkx"), Err(Censored)); // profanity inside
+
+        validate("Ok-c_oo_l-name").unwrap(); // special characters OK
+        assert_eq!(validate("too-_-cool"), Err(ConsecutivePunctuation));
+        assert_eq!(validate("still__bad"), Err(ConsecutivePunctuation));
+        assert_eq!(validate("_bad"), Err(InvalidFirstCharacter));
+        assert_eq!(validate("bad_"), Err(InvalidLastCharacter));
+    }
+
+    #[test]
+    fn configurable_max_length() {
+        validate_player_name("abcdefghij", 10).unwrap();
+        assert_eq!(
+            validate_player_name("abcdefghijk", 10),
+            Err(PlayerNameError::TooLong { max_length: 10 })
+        );
+    }
+
+    #[test]
+    fn engine_cannot_coincide_with_human() {
+        validate(ANALYSIS_ENGINE_NAME_GENERIC).unwrap_err();
+        validate(ANALYSIS_ENGINE_NAME_WHITE).unwrap_err();
+        validate(ANALYSIS_ENGINE_NAME_BLACK).unwrap_err();
+    }
+}
@@ -1,11 +1,18 @@
 use std::time::Duration;
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use bincode::Options;
 use chain_cmp::chmp;
+use enum_map::EnumMap;
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use strum::{AsRefStr, EnumIter, IntoEnumIterator};
 
 use crate::clock::TimeControl;
 use crate::coord::{BoardShape, SubjectiveRow};
+use crate::piece::PieceKind;
+use crate::player::Team;
 
 
 // Time spent in the lobby before starting the first game after all players signal readiness.
@@ -24,6 +31,11 @@ use crate::coord::{BoardShape, SubjectiveRow};
 // we don't wait for observer readiness (and the latter is definitely not changing).
 pub const FIRST_GAME_COUNTDOWN_DURATION: Duration = Duration::from_secs(3);
 
+// Format version for `Rules::to_share_code`. Bincode relies on field order and enum variant
+// indices, so any change to `Rules` or the types it contains must bump this. Codes with a different
+// version are rejected rather than misread.
+const SHARE_CODE_VERSION: u8 = 1;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, EnumIter, AsRefStr, Serialize, Deserialize)]
 pub enum RulesPreset {
     International3,
@@ -88,6 +100,96 @@ pub enum DropAggression {
     MateAllowed,
 }
 
+// What players can see in fog of war.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum FogOfWarVisibility {
+    // Squares that are legal move destinations for your pieces.
+    Movement,
+    // Squares within the given distance from your pieces (diagonals count as one step).
+    Radius(u8),
+    // Union of `Movement` and `Radius`.
+    Hybrid(u8),
+}
+
+// Which ways to castle are available. Regardless of the policy, only an innate king and rook that
+// haven't moved can castle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Castling {
+    Allowed,
+    // Castling requires moving the king towards the rook. Dragging the king onto a rook is treated
+    // as a regular move, so it's illegal.
+    KingMoveOnly,
+    // No castling rights are granted at setup.
+    Forbidden,
+}
+
+// Outcome when both boards run out of time at the same moment and the players who flagged are on
+// different teams.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum SimultaneousFlagPolicy {
+    Draw,
+    // The team with more material on both boards combined wins. Draw if material is equal.
+    MaterialAdvantage,
+}
+
+// What happens when a player is not in check, but has no legal moves or drops. In chess this is
+// always a draw.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Stalemate {
+    // The player waits until the partner passes a piece that can be dropped. If it doesn't happen
+    // in time, the player loses on time.
+    Wait,
+    // The game ends in a draw.
+    Draw,
+}
+
+// Limits the total number of pieces (on the board and in reserve) that a player can have. Prevents
+// stalling by hoarding drops.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PieceCap {
+    pub max_pieces: u8,
+    pub policy: PieceCapPolicy,
+}
+
+// What happens when a capture would give the partner more pieces than `PieceCap::max_pieces`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum PieceCapPolicy {
+    // The capture is illegal.
+    Refuse,
+    // The capture is legal, but excess pieces are removed from the game instead of going to
+    // reserve.
+    Discard,
+}
+
+// Clock handicap: clocks of one team start `delay` after the game begins, on both boards. Until
+// then the team can think for free. Unlike time odds, this doesn't change the starting time, so
+// the clocks still look symmetric. Not allowed in rated matches.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DelayedStart {
+    pub team: Team,
+    pub delay: Duration,
+}
+
+// Chat messages that players see while a rated game is in progress. Limiting the chat prevents
+// coaching, e.g. by observers. Players always see their own messages and system messages. Hidden
+// messages are revealed when the game is over.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum RatedGameChat {
+    Everyone,
+    // Only messages sent by teammates in the current game.
+    TeamOnly,
+    Muted,
+}
+
+// When the match is declared complete. No more games can be played after that.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MatchTarget {
+    // The first team or player to score this many points wins.
+    Points(u32),
+    // The match ends after this many games. Aborted games don't count.
+    Games(u32),
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct MatchRules {
     pub rated: bool,
@@ -96,6 +198,27 @@ pub struct MatchRules {
     //   - public lobby (allow joining before the match has started),
     //   - public game (allow joining after the match has started).
     pub public: bool,
+    // Only registered users can join. Always the case for rated matches; this allows to require
+    // registration for unrated matches as well.
+    pub registered_only: bool,
+    // Ignored in unrated matches.
+    pub rated_game_chat: RatedGameChat,
+    // The team that won keeps their seats for the next game, while others rotate in. Applies only
+    // to matches with dynamic teams.
+    pub winner_stays: bool,
+    // Maximum number of premoves per board in rated games; zero disables premoves. Prevents using
+    // premoves to play bullet without thinking. `None` means no extra limit. Ignored in unrated
+    // matches.
+    pub rated_preturn_limit: Option<u8>,
+    // Slow mode: each participant can send at most one chat message per this interval. A
+    // moderation tool set by the match creator.
+    pub chat_slow_mode: Option<Duration>,
+    // Players can abort a game that started by mistake while no board has more than this many
+    // turns. Both teams must agree. Aborted games have no result and are not recorded. `None` means
+    // games cannot be aborted.
+    pub abort_window: Option<u8>,
+    // `None` means the match is open-ended.
+    pub match_target: Option<MatchTarget>,
 }
 
 // Some thoughts on relationship options between `ChessRules` and `BughouseRules`. The goal is to
@@ -169,20 +292,60 @@ pub struct ChessRules {
     // just doesn't make sense.
     pub atomic_chess: bool,
 
-    // Can only see squares that are legal move destinations for your pieces.
+    // Can only see squares near your pieces, as defined by `fog_of_war_visibility`.
     pub fog_of_war: bool,
 
+    // Only relevant if `fog_of_war` is true.
+    pub fog_of_war_visibility: FogOfWarVisibility,
+
+    // If true, players cannot see the reserves of their opponents. Only relevant if `fog_of_war`
+    // is true. Like the rest of fog of war, this is enforced when rendering the game on the client.
+    // TODO: Enforce on the server. Reserves follow from the turn log, which the server sends to
+    // everybody in full, so a modified client can reveal them. Fixing this requires filtering game
+    // updates per participant, which is equally missing for the fog on the board.
+    pub fog_of_war_hide_reserves: bool,
+
+    // If true, the king may castle out of check and through attacked squares. It still cannot end
+    // up in check. Irrelevant in regicide variants, where there are no checks anyway.
+    pub castling_through_check: bool,
+
+    pub castling: Castling,
+
     pub time_control: TimeControl,
 
+    // Used for material counting and as a rough evaluation when no engine is available.
+    pub piece_values: PieceValues,
+
     pub bughouse_rules: Option<BughouseRules>,
 }
 
+// Piece values in pawns. Overrides take priority over `PieceKind::default_value`. Use
+// `PieceKind::value` to get the effective value.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct PieceValues {
+    pub overrides: EnumMap<PieceKind, Option<u32>>,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BughouseRules {
     pub koedem: bool,
     pub promotion: Promotion,
     pub pawn_drop_ranks: PawnDropRanks, // TODO: Update when board shape changes
     pub drop_aggression: DropAggression,
+    // Drops are forbidden during the first `no_drop_turns` full turns on each board. Captured
+    // pieces still go to reserve during this phase.
+    pub no_drop_turns: u32,
+    // Forbids dropping a piece to block a check: the player in check must move the king or capture
+    // the checking piece. Since a legal drop always resolves the check, this is equivalent to
+    // forbidding all drops while in check.
+    pub no_interposing_drops: bool,
+    pub simultaneous_flag: SimultaneousFlagPolicy,
+    pub stalemate: Stalemate,
+    // House rule: a player can resign their board only, without ending the game. The game goes on
+    // until the other board is over as well; then the team that won more boards wins.
+    pub board_resignation: bool,
+    pub piece_cap: Option<PieceCap>,
+    pub delayed_start: Option<DelayedStart>,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
@@ -191,7 +354,7 @@ pub struct Rules {
     pub chess_rules: ChessRules,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, EnumIter, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, EnumIter, AsRefStr, Serialize, Deserialize)]
 pub enum ChessVariant {
     Capablanca,
     Accolade,
@@ -203,7 +366,26 @@ pub enum ChessVariant {
 }
 
 impl MatchRules {
-    pub fn unrated_public() -> Self { Self { rated: false, public: true } }
+    pub fn unrated_public() -> Self {
+        Self {
+            rated: false,
+            public: true,
+            registered_only: false,
+            rated_game_chat: RatedGameChat::Everyone,
+            winner_stays: false,
+            rated_preturn_limit: None,
+            chat_slow_mode: None,
+            abort_window: None,
+            match_target: None,
+        }
+    }
+    pub fn requires_registration(&self) -> bool { self.rated || self.registered_only }
+    pub fn limits_game_chat(&self) -> bool {
+        self.rated && self.rated_game_chat != RatedGameChat::Everyone
+    }
+    pub fn preturn_limit(&self) -> Option<u8> {
+        if self.rated { self.rated_preturn_limit } else { None }
+    }
 }
 
 // Improvement potential. Precompute `variants` and `regicide_reason`. Note that this would mean
@@ -215,6 +397,13 @@ impl ChessRules {
             promotion: Promotion::Upgrade,
             pawn_drop_ranks: PawnDropRanks::from_one_based(2, 7),
             drop_aggression: DropAggression::MateAllowed,
+            no_drop_turns: 0,
+            no_interposing_drops: false,
+            simultaneous_flag: SimultaneousFlagPolicy::Draw,
+            stalemate: Stalemate::Wait,
+            board_resignation: false,
+            piece_cap: None,
+            delayed_start: None,
         };
         match preset {
             RulesPreset::International3 => Self {
@@ -232,6 +421,13 @@ impl ChessRules {
                     promotion: Promotion::Steal,
                     pawn_drop_ranks: PawnDropRanks::from_one_based(2, 6),
                     drop_aggression: DropAggression::NoChessMate,
+                    no_drop_turns: 0,
+                    no_interposing_drops: false,
+                    simultaneous_flag: SimultaneousFlagPolicy::Draw,
+                    stalemate: Stalemate::Wait,
+                    board_resignation: false,
+                    piece_cap: None,
+                    delayed_start: None,
                 }),
                 ..Self::chess_blitz_5()
             },
@@ -249,14 +445,19 @@ impl ChessRules {
             duck_chess: false,
             atomic_chess: false,
             fog_of_war: false,
-            time_control: TimeControl { starting_time: Duration::from_secs(180) },
+            fog_of_war_visibility: FogOfWarVisibility::Movement,
+            fog_of_war_hide_reserves: false,
+            castling_through_check: false,
+            castling: Castling::Allowed,
+            time_control: TimeControl::sudden_death(Duration::from_secs(180)),
+            piece_values: PieceValues::default(),
             bughouse_rules: None,
         }
     }
 
     pub fn chess_blitz_5() -> Self {
         Self {
-            time_control: TimeControl { starting_time: Duration::from_secs(300) },
+            time_control: TimeControl::sudden_death(Duration::from_secs(300)),
             ..Self::chess_blitz_3()
         }
     }
@@ -287,9 +488,12 @@ impl ChessRules {
     // If true, there are no checks and mates. The game ends when the king is captured.
     pub fn regicide(&self) -> bool { !self.regicide_reason().is_empty() }
 
+    // Number of separate turns that make up a full turn, e.g. a piece move and a duck move.
+    pub fn stages_per_turn(&self) -> usize { if self.duck_chess { 2 } else { 1 } }
+
     // Conceptually we always allow a single preturn, but this may technically require several
     // preturns in game modes where each turn has multiple stages.
-    pub fn max_preturns_per_board(&self) -> usize { if self.duck_chess { 2 } else { 1 } }
+    pub fn max_preturns_per_board(&self) -> usize { self.stages_per_turn() }
 
     pub fn variants(&self) -> Vec<ChessVariant> {
         let mut v = vec![];
@@ -326,6 +530,9 @@ impl ChessRules {
     }
 
     pub fn verify(&self) -> Result<(), String> {
+        if let FogOfWarVisibility::Radius(0) = self.fog_of_war_visibility {
+            return Err("Fog of war radius must be positive".to_owned());
+        }
         if let Some(bughouse_rules) = &self.bughouse_rules {
             let num_ranks = self.board_shape().num_rows as i8;
             let min_pawn_drop_rank = bughouse_rules.pawn_drop_ranks.min.to_one_based();
@@ -340,6 +547,23 @@ impl ChessRules {
                     Drop aggression must be set to \"mate allowed\""
                     .to_owned());
             }
+            if self.regicide() && bughouse_rules.no_interposing_drops {
+                return Err("The game is played until a king is captured. \
+                    Drops cannot be restricted based on checks"
+                    .to_owned());
+            }
+            if bughouse_rules.koedem && bughouse_rules.board_resignation {
+                return Err("In Koedem kings travel between boards, \
+                    so a single board cannot be resigned"
+                    .to_owned());
+            }
+            if let Some(piece_cap) = bughouse_rules.piece_cap {
+                // Each player starts with two full rows of pieces.
+                let min_pieces = 2 * self.board_shape().num_cols;
+                if piece_cap.max_pieces < min_pieces {
+                    return Err(format!("Piece cap must be at least {min_pieces}"));
+                }
+            }
         }
         Ok(())
     }
@@ -353,7 +577,234 @@ impl Rules {
         self.chess_rules.bughouse_rules.as_mut()
     }
 
-    pub fn verify(&self) -> Result<(), String> { self.chess_rules.verify() }
+    pub fn verify(&self) -> Result<(), String> {
+        self.chess_rules.verify()?;
+        if self.match_rules.rated
+            && self.bughouse_rules().is_some_and(|r| r.delayed_start.is_some())
+        {
+            return Err("Delayed clock start is not allowed in rated matches".to_owned());
+        }
+        Ok(())
+    }
+
+    // Compact string that allows to reproduce the exact match setup, e.g. by pasting it into the
+    // match creation UI: base64 of a version byte followed by bincode-serialized rules.
+    pub fn to_share_code(&self) -> String {
+        let mut data = vec![SHARE_CODE_VERSION];
+        bincode::DefaultOptions::new().serialize_into(&mut data, self).unwrap();
+        URL_SAFE_NO_PAD.encode(data)
+    }
+    pub fn from_share_code(code: &str) -> Result<Self, String> {
+        let invalid = || "Invalid rules code".to_owned();
+        let data = URL_SAFE_NO_PAD.decode(code.trim()).map_err(|_| invalid())?;
+        let (&version, payload) = data.split_first().ok_or_else(invalid)?;
+        if version != SHARE_CODE_VERSION {
+            return Err("Rules code was created by an incompatible version".to_owned());
+        }
+        let rules: Rules =
+            bincode::DefaultOptions::new().deserialize(payload).map_err(|_| invalid())?;
+        rules.verify()?;
+        Ok(rules)
+    }
+
+    // Takes into account the premove limit for rated matches, see `MatchRules`.
+    pub fn max_preturns_per_board(&self) -> usize {
+        let max_preturns = self.chess_rules.max_preturns_per_board();
+        match self.match_rules.preturn_limit() {
+            Some(limit) => {
+                max_preturns.min(usize::from(limit) * self.chess_rules.stages_per_turn())
+            }
+            None => max_preturns,
+        }
+    }
+
+    // One-sentence summary for newcomers, e.g. "5:00 rated bughouse with fog of war, pawn drop
+    // ranks: 2-6". Mentions only the `to_human_readable` entries that differ from international
+    // bughouse with the same time control and match rules.
+    pub fn describe(&self) -> String {
+        let chess_rules = &self.chess_rules;
+        let rating = if self.match_rules.rated { "rated" } else { "unrated" };
+        let mut s = format!("{} {rating} bughouse", chess_rules.time_control);
+        let variants = chess_rules.variants();
+        if let Some((last, init)) = variants.split_last() {
+            let mut variant_list = init.iter().map(|v| v.to_inline_text()).join(", ");
+            if !variant_list.is_empty() {
+                variant_list.push_str(" and ");
+            }
+            variant_list.push_str(last.to_inline_text());
+            s.push_str(&format!(" with {variant_list}"));
+        }
+        let baseline = Rules {
+            match_rules: self.match_rules.clone(),
+            chess_rules: ChessRules {
+                time_control: chess_rules.time_control.clone(),
+                ..ChessRules::bughouse_international5()
+            },
+        };
+        let baseline_entries = baseline.human_readable_entries();
+        for entry in self.human_readable_entries() {
+            // Variants are already listed above.
+            if entry.0 == "Variants" || baseline_entries.contains(&entry) {
+                continue;
+            }
+            let (caption, value) = entry;
+            // Keep capitalized values like "Q=10" intact.
+            let mut chars = value.chars();
+            let value = match (chars.next(), chars.next()) {
+                (Some(first), Some(second)) if second.is_lowercase() => {
+                    first.to_lowercase().chain(value[first.len_utf8()..].chars()).collect()
+                }
+                _ => value,
+            };
+            s.push_str(&format!(", {}: {value}", caption.to_lowercase()));
+        }
+        s
+    }
+
+    // Plain text description of all rules, one rule per line. Unlike the PGN header, this is
+    // meant for humans, e.g. for sharing in chat.
+    pub fn to_human_readable(&self) -> String {
+        self.human_readable_entries()
+            .into_iter()
+            .map(|(caption, value)| format!("{caption}: {value}"))
+            .join("\n")
+    }
+
+    // (caption, value) pairs behind `to_human_readable` and `describe`.
+    fn human_readable_entries(&self) -> Vec<(&'static str, String)> {
+        let chess_rules = &self.chess_rules;
+        let mut entries = vec![];
+        let variants = chess_rules.variants();
+        if variants.is_empty() {
+            entries.push(("Variants", "—".to_owned()));
+        } else {
+            entries.push(("Variants", variants.iter().map(|v| v.to_human_readable()).join(", ")));
+        }
+        if chess_rules.fog_of_war {
+            entries.push((
+                "Fog of war visibility",
+                chess_rules.fog_of_war_visibility.to_human_readable(),
+            ));
+            if chess_rules.fog_of_war_hide_reserves {
+                entries.push(("Opponent reserves", "Hidden".to_owned()));
+            }
+        }
+        entries.push(("Time control", chess_rules.time_control.to_string()));
+        let time_control = &chess_rules.time_control;
+        if !time_control.increment.is_zero() && time_control.no_increment_turns > 0 {
+            entries.push(("Moves without increment", time_control.no_increment_turns.to_string()));
+        }
+        if let Some(bughouse_rules) = self.bughouse_rules() {
+            entries.push(("Promotion", bughouse_rules.promotion.to_human_readable().to_owned()));
+            entries.push(("Pawn drop ranks", bughouse_rules.pawn_drop_ranks.to_human_readable()));
+            if bughouse_rules.no_drop_turns > 0 {
+                entries.push(("Moves without drops", bughouse_rules.no_drop_turns.to_string()));
+            }
+        }
+        if chess_rules.regicide() {
+            entries.push(("Win condition", "King capture".to_owned()));
+        } else if let Some(bughouse_rules) = self.bughouse_rules() {
+            entries.push((
+                "Drop aggression",
+                bughouse_rules.drop_aggression.to_human_readable().to_owned(),
+            ));
+            if bughouse_rules.no_interposing_drops {
+                entries.push(("Blocking check by drop", "Forbidden".to_owned()));
+            }
+        }
+        if !chess_rules.regicide() && chess_rules.castling_through_check {
+            entries.push(("Castling through check", "Allowed".to_owned()));
+        }
+        if chess_rules.castling != Castling::Allowed {
+            entries.push(("Castling", chess_rules.castling.to_human_readable().to_owned()));
+        }
+        if let Some(bughouse_rules) = self.bughouse_rules() {
+            entries.push((
+                "Simultaneous flags",
+                bughouse_rules.simultaneous_flag.to_human_readable().to_owned(),
+            ));
+            if bughouse_rules.stalemate != Stalemate::Wait {
+                entries
+                    .push(("Stalemate", bughouse_rules.stalemate.to_human_readable().to_owned()));
+            }
+            if bughouse_rules.board_resignation {
+                entries.push(("Board resignation", "Allowed".to_owned()));
+            }
+            if let Some(piece_cap) = bughouse_rules.piece_cap {
+                entries.push(("Piece cap", piece_cap.to_human_readable()));
+            }
+            if let Some(delayed_start) = bughouse_rules.delayed_start {
+                entries.push(("Delayed start", delayed_start.to_human_readable()));
+            }
+        }
+        let piece_value_overrides = chess_rules
+            .piece_values
+            .overrides
+            .iter()
+            .filter_map(|(kind, value)| value.map(|v| format!("{}={v}", kind.to_full_algebraic())))
+            .collect_vec();
+        if !piece_value_overrides.is_empty() {
+            entries.push(("Piece values", piece_value_overrides.join(", ")));
+        }
+        let rating = if self.match_rules.rated { "Rated" } else { "Unrated" };
+        entries.push(("Rating", rating.to_owned()));
+        if self.match_rules.requires_registration() {
+            entries.push(("Players", "Registered only".to_owned()));
+        }
+        if self.match_rules.limits_game_chat() {
+            entries.push((
+                "Chat during games",
+                self.match_rules.rated_game_chat.to_human_readable().to_owned(),
+            ));
+        }
+        if let Some(limit) = self.match_rules.preturn_limit() {
+            entries.push(("Premoves", preturn_limit_to_human_readable(limit)));
+        }
+        if self.match_rules.winner_stays {
+            entries.push(("Seating", "Winner stays".to_owned()));
+        }
+        if let Some(interval) = self.match_rules.chat_slow_mode {
+            entries.push(("Chat slow mode", format!("{} s", interval.as_secs())));
+        }
+        if let Some(window) = self.match_rules.abort_window {
+            entries.push(("Abort", abort_window_to_human_readable(window)));
+        }
+        if let Some(target) = self.match_rules.match_target {
+            entries.push(("Match", target.to_human_readable()));
+        }
+        entries
+    }
+}
+
+pub fn abort_window_to_human_readable(window: u8) -> String {
+    format!("During the first {window} turns on each board")
+}
+
+pub fn preturn_limit_to_human_readable(limit: u8) -> String {
+    if limit == 0 {
+        "Disabled".to_owned()
+    } else {
+        format!("At most {limit}")
+    }
+}
+
+impl MatchTarget {
+    pub fn to_human_readable(&self) -> String {
+        match self {
+            MatchTarget::Points(points) => format!("First to {points} points"),
+            MatchTarget::Games(games) => format!("{games} games"),
+        }
+    }
+}
+
+impl RatedGameChat {
+    pub fn to_human_readable(&self) -> &'static str {
+        match self {
+            RatedGameChat::Everyone => "Everyone",
+            RatedGameChat::TeamOnly => "Team only",
+            RatedGameChat::Muted => "Muted",
+        }
+    }
 }
 
 impl Promotion {
@@ -397,6 +848,148 @@ impl DropAggression {
     pub fn to_human_readable(&self) -> &'static str { self.to_pgn() }
 }
 
+impl SimultaneousFlagPolicy {
+    pub fn to_pgn(&self) -> &'static str {
+        match self {
+            SimultaneousFlagPolicy::Draw => "Draw",
+            SimultaneousFlagPolicy::MaterialAdvantage => "Material advantage",
+        }
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        match s {
+            "Draw" => Ok(SimultaneousFlagPolicy::Draw),
+            "Material advantage" => Ok(SimultaneousFlagPolicy::MaterialAdvantage),
+            _ => Err(()),
+        }
+    }
+    pub fn to_human_readable(&self) -> &'static str { self.to_pgn() }
+}
+
+impl Stalemate {
+    pub fn to_pgn(&self) -> &'static str {
+        match self {
+            Stalemate::Wait => "Wait",
+            Stalemate::Draw => "Draw",
+        }
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        match s {
+            "Wait" => Ok(Stalemate::Wait),
+            "Draw" => Ok(Stalemate::Draw),
+            _ => Err(()),
+        }
+    }
+    pub fn to_human_readable(&self) -> &'static str { self.to_pgn() }
+}
+
+impl Castling {
+    pub fn to_pgn(&self) -> &'static str {
+        match self {
+            Castling::Allowed => "Allowed",
+            Castling::KingMoveOnly => "King move only",
+            Castling::Forbidden => "Forbidden",
+        }
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        match s {
+            "Allowed" => Ok(Castling::Allowed),
+            "King move only" => Ok(Castling::KingMoveOnly),
+            "Forbidden" => Ok(Castling::Forbidden),
+            _ => Err(()),
+        }
+    }
+    pub fn to_human_readable(&self) -> &'static str { self.to_pgn() }
+}
+
+impl PieceCapPolicy {
+    pub fn to_pgn(&self) -> &'static str {
+        match self {
+            PieceCapPolicy::Refuse => "Refuse",
+            PieceCapPolicy::Discard => "Discard",
+        }
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        match s {
+            "Refuse" => Ok(PieceCapPolicy::Refuse),
+            "Discard" => Ok(PieceCapPolicy::Discard),
+            _ => Err(()),
+        }
+    }
+    pub fn to_human_readable(&self) -> &'static str {
+        match self {
+            PieceCapPolicy::Refuse => "Capture refused",
+            PieceCapPolicy::Discard => "Excess discarded",
+        }
+    }
+}
+
+impl PieceCap {
+    pub fn to_pgn(&self) -> String { format!("{} {}", self.max_pieces, self.policy.to_pgn()) }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        let (max_pieces, policy) = s.split_once(' ').ok_or(())?;
+        let max_pieces = max_pieces.parse().map_err(|_| ())?;
+        let policy = PieceCapPolicy::from_pgn(policy)?;
+        Ok(PieceCap { max_pieces, policy })
+    }
+    pub fn to_human_readable(&self) -> String {
+        format!("{} ({})", self.max_pieces, self.policy.to_human_readable())
+    }
+}
+
+impl DelayedStart {
+    pub fn to_pgn(&self) -> String {
+        format!("{} {}", self.team_to_human_readable(), self.delay.as_secs())
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        let (team, delay) = s.split_once(' ').ok_or(())?;
+        let team = match team {
+            "Red" => Team::Red,
+            "Blue" => Team::Blue,
+            _ => return Err(()),
+        };
+        let delay = Duration::from_secs(delay.parse().map_err(|_| ())?);
+        Ok(DelayedStart { team, delay })
+    }
+    pub fn to_human_readable(&self) -> String {
+        format!("{} team, {} s", self.team_to_human_readable(), self.delay.as_secs())
+    }
+    fn team_to_human_readable(&self) -> &'static str {
+        match self.team {
+            Team::Red => "Red",
+            Team::Blue => "Blue",
+        }
+    }
+}
+
+impl FogOfWarVisibility {
+    pub fn to_pgn(&self) -> String {
+        match self {
+            FogOfWarVisibility::Movement => "Movement".to_owned(),
+            FogOfWarVisibility::Radius(r) => format!("Radius {r}"),
+            FogOfWarVisibility::Hybrid(r) => format!("Hybrid {r}"),
+        }
+    }
+    pub fn from_pgn(s: &str) -> Result<Self, ()> {
+        if s == "Movement" {
+            return Ok(FogOfWarVisibility::Movement);
+        }
+        let (kind, radius) = s.split_once(' ').ok_or(())?;
+        let radius = radius.parse().map_err(|_| ())?;
+        match kind {
+            "Radius" => Ok(FogOfWarVisibility::Radius(radius)),
+            "Hybrid" => Ok(FogOfWarVisibility::Hybrid(radius)),
+            _ => Err(()),
+        }
+    }
+    pub fn to_human_readable(&self) -> String {
+        match self {
+            FogOfWarVisibility::Movement => "Legal moves".to_owned(),
+            FogOfWarVisibility::Radius(r) => format!("Radius {r}"),
+            FogOfWarVisibility::Hybrid(r) => format!("Legal moves and radius {r}"),
+        }
+    }
+}
+
 impl PawnDropRanks {
     pub fn from_one_based(min: i8, max: i8) -> Self {
         assert!(min <= max, "Bad PawnDropRanks range: {min}-{max}");
@@ -458,6 +1051,19 @@ impl ChessVariant {
         }
     }
 
+    // For use in the middle of a sentence.
+    pub fn to_inline_text(self) -> &'static str {
+        match self {
+            ChessVariant::Capablanca => "Capablanca pieces",
+            ChessVariant::Accolade => "Accolade pieces",
+            ChessVariant::FischerRandom => "Fischer random",
+            ChessVariant::DuckChess => "duck chess",
+            ChessVariant::AtomicChess => "atomic chess",
+            ChessVariant::FogOfWar => "fog of war",
+            ChessVariant::Koedem => "Koedem",
+        }
+    }
+
     pub fn to_human_readable(self) -> &'static str {
         match self {
             ChessVariant::Capablanca => "Capablanca chess",
@@ -1,8 +1,13 @@
+use std::cmp::Reverse;
+
 use enum_map::EnumMap;
 use serde::{Deserialize, Serialize};
 
+use crate::board::DrawReason;
+use crate::game::{BughouseGame, BughouseGameStatus};
 use crate::half_integer::HalfU32;
-use crate::player::Team;
+use crate::player::{Participant, Team};
+use crate::rules::MatchTarget;
 
 
 // Victory is scored as 1 : 0, draw is 1/2 : 1/2.
@@ -11,3 +16,106 @@ pub enum Scores {
     PerTeam(EnumMap<Team, HalfU32>), // for Teaming::FixedTeams
     PerPlayer,                       // for Teaming::IndividualMode; score is in `Participant`
 }
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Contestant {
+    Team(Team),
+    Player(String),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MatchStanding {
+    pub contestant: Contestant,
+    pub score: HalfU32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MatchResult {
+    // From first place to last, with tie-breaks applied.
+    pub standings: Vec<MatchStanding>,
+    // Usually one contestant. Several if they are tied even after tie-breaks.
+    pub winners: Vec<Contestant>,
+}
+
+// Returns the final standings if the match has reached its target, `None` otherwise.
+//
+// `games` must contain all finished games of the match. Participants who left the match keep their
+// scores. Ties are broken by (in order):
+//   - fewer games played (individual mode only: both teams always play the same number of games);
+//   - more victories;
+//   - the most recent victory.
+pub fn match_result<'a>(
+    target: MatchTarget, scores: &Scores, participants: impl Iterator<Item = &'a Participant>,
+    games: &[&BughouseGame],
+) -> Option<MatchResult> {
+    let games: Vec<_> = games
+        .iter()
+        .filter(|game| {
+            !matches!(
+                game.status(),
+                BughouseGameStatus::Active | BughouseGameStatus::Draw(DrawReason::Aborted)
+            )
+        })
+        .collect();
+    let standings: Vec<_> = match scores {
+        Scores::PerTeam(score_map) => score_map
+            .iter()
+            .map(|(team, &score)| MatchStanding {
+                contestant: Contestant::Team(team),
+                score,
+            })
+            .collect(),
+        Scores::PerPlayer => participants
+            .filter(|p| p.games_played > 0)
+            .map(|p| MatchStanding {
+                contestant: Contestant::Player(p.name.clone()),
+                score: p.individual_score,
+            })
+            .collect(),
+    };
+    let reached = match target {
+        MatchTarget::Points(points) => standings.iter().any(|s| s.score >= HalfU32::whole(points)),
+        MatchTarget::Games(num_games) => games.len() >= num_games as usize,
+    };
+    if !reached {
+        return None;
+    }
+
+    // For each game: whether the contestant played it and whether they won it.
+    let game_results = |contestant: &Contestant| {
+        games
+            .iter()
+            .map(|game| {
+                let team = match contestant {
+                    Contestant::Team(team) => Some(*team),
+                    Contestant::Player(name) => game.player_map().get(name).map(|p| p.team()),
+                };
+                let won = match game.status() {
+                    BughouseGameStatus::Victory(winner, _) => team == Some(winner),
+                    _ => false,
+                };
+                (team.is_some(), won)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut standings: Vec<_> = standings
+        .into_iter()
+        .map(|standing| {
+            let results = game_results(&standing.contestant);
+            let games_played = results.iter().filter(|(played, _)| *played).count();
+            let victories = results.iter().filter(|(_, won)| *won).count();
+            let last_victory = results.iter().rposition(|(_, won)| *won);
+            let key = (standing.score, Reverse(games_played), victories, last_victory);
+            (key, standing)
+        })
+        .collect();
+    standings.sort_by(|(a, _), (b, _)| b.cmp(a));
+    let best_key = standings.first().map(|(key, _)| *key);
+    let winners = standings
+        .iter()
+        .take_while(|(key, _)| Some(*key) == best_key)
+        .map(|(_, standing)| standing.contestant.clone())
+        .collect();
+    let standings = standings.into_iter().map(|(_, standing)| standing).collect();
+    Some(MatchResult { standings, winners })
+}
@@ -8,19 +8,23 @@ use std::time::Duration;
 use std::{iter, mem, ops};
 
 use async_std::sync::{Mutex, RwLock};
-use enum_map::enum_map;
+use enum_map::{EnumMap, enum_map};
 use indoc::printdoc;
 use instant::Instant;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use log::{info, warn};
-use prometheus::{HistogramVec, register_histogram_vec};
+use prometheus::{HistogramVec, IntCounterVec, register_histogram_vec, register_int_counter_vec};
 use rand::prelude::*;
+use serde::Serialize;
+use strum::IntoEnumIterator;
+use time::OffsetDateTime;
 
-use crate::board::{TurnInput, TurnMode, VictoryReason};
+use crate::board::{DrawReason, Reserve, TurnInput, TurnMode, VictoryReason};
 use crate::chalk::{ChalkDrawing, Chalkboard};
 use crate::chat::{ChatMessage, ChatMessageBody, ChatRecipient, OutgoingChatMessage};
-use crate::clock::GameInstant;
+use crate::client::{self, RulesVerificationError};
+use crate::clock::{GameInstant, MillisDuration};
 use crate::event::{
     BughouseClientErrorReport, BughouseClientEvent, BughouseClientPerformance, BughouseServerEvent,
     BughouseServerRejection, GameUpdate, MatchDescription,
@@ -32,14 +36,17 @@ use crate::game::{
 use crate::half_integer::HalfU32;
 use crate::iterable_mut::IterableMut;
 use crate::lobby::{
-    ParticipantsStatus, ParticipantsWarning, Teaming, assign_boards, fix_teams_if_needed,
-    post_game_update_participant_counters, verify_participants,
+    ParticipantsStatus, ParticipantsWarning, SeatSwap, Teaming, assign_boards,
+    assign_boards_winner_stays, fix_teams_if_needed, post_game_update_participant_counters,
+    swap_seats, verify_faction_change, verify_participants,
 };
+use crate::piece::PieceReservable;
 use crate::ping_pong::{PassiveConnectionMonitor, PassiveConnectionStatus};
-use crate::player::{Faction, Participant, PlayerSchedulingPriority};
+use crate::player::{Faction, Participant, PlayerSchedulingPriority, Team};
+use crate::player_name::PlayerNameError;
 use crate::role::Role;
-use crate::rules::{FIRST_GAME_COUNTDOWN_DURATION, Rules};
-use crate::scores::Scores;
+use crate::rules::{FIRST_GAME_COUNTDOWN_DURATION, RatedGameChat, Rules};
+use crate::scores::{MatchResult, Scores, match_result};
 use crate::server_chat::{ChatRecipientExpanded, ServerChat};
 use crate::server_helpers::ServerHelpers;
 use crate::server_hooks::{NoopServerHooks, ServerHooks};
@@ -59,8 +66,18 @@ pub const MATCH_ID_ALPHABET: [char; 33] = [
 
 const DOUBLE_TERMINATION_ABORT_THRESHOLD: Duration = Duration::from_secs(1);
 const TERMINATION_WAITING_PERIOD: Duration = Duration::from_secs(60);
+// Time between notifying clients about shutdown and exiting, so that the notice reaches them.
+const SHUTDOWN_NOTICE_PERIOD: Duration = Duration::from_secs(1);
+// Suggested delay before clients try to reconnect after a shutdown. Typical restart time.
+const SHUTDOWN_RECONNECT_DELAY: Duration = Duration::from_secs(30);
 const MATCH_GC_INACTIVITY_THRESHOLD: Duration = Duration::from_secs(3600 * 24);
+// A match without online participants is removed after this time. Gives everybody a chance to
+// reconnect after a network hiccup.
+const MATCH_GC_ABANDONED_THRESHOLD: Duration = Duration::from_secs(60 * 5);
 const MATCH_HIDE_INACTIVITY_THRESHOLD: Duration = Duration::from_secs(60);
+const MAX_SCHEDULED_START_DELAY: Duration = Duration::from_secs(3600 * 24 * 7);
+const MAX_HANDICAP_PIECES: u32 = 8;
+const ANONYMOUS_GUEST_PREFIX: &str = "Guest-";
 
 lazy_static! {
     static ref EVENT_PROCESSING_HISTOGRAM: HistogramVec = register_histogram_vec!(
@@ -72,6 +89,20 @@ lazy_static! {
         ],
     )
     .unwrap();
+    // Note. Label values come from closed sets (events, presets, variants), so the number of time
+    // series is bounded. Don't add match-specific labels here.
+    static ref RULES_PRESET_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "rules_preset_total",
+        "Number of matches created and games started/finished per rules preset.",
+        &["event", "preset"],
+    )
+    .unwrap();
+    static ref CHESS_VARIANT_COUNTER: IntCounterVec = register_int_counter_vec!(
+        "chess_variant_total",
+        "Number of matches created and games started/finished per chess variant.",
+        &["event", "variant"],
+    )
+    .unwrap();
 }
 
 macro_rules! unknown_error {
@@ -84,6 +115,16 @@ macro_rules! unknown_error {
 pub struct ServerOptions {
     pub check_git_version: bool,
     pub max_starting_time: Option<Duration>,
+    // Passed to clients, so that they could validate guest names. Enforced by `ServerHelpers`.
+    pub max_player_name_length: usize,
+    // Limit on the number of read-only viewers (see `BughouseClientEvent::Spectate`) per match.
+    pub max_spectators_per_match: usize,
+    // Clients that send nothing (not even a ping) for this long are disconnected. Any incoming
+    // event resets the timer.
+    pub client_heartbeat_timeout: Duration,
+    // How many latest chat messages each match keeps. Participants receive the history when they
+    // join. Clients don't store more than `MAX_CHAT_MESSAGES` anyway.
+    pub max_chat_messages_per_match: usize,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -101,6 +142,12 @@ enum Execution {
         // upon two termination requests come within `DOUBLE_TERMINATION_ABORT_THRESHOLD` period.
         last_termination_request: Instant,
     },
+
+    // All matches are over (or shutdown was forced) and clients have been notified about the
+    // shutdown. The server will exit after `SHUTDOWN_NOTICE_PERIOD`.
+    ShutdownAnnounced {
+        since: Instant,
+    },
 }
 
 #[derive(Debug)]
@@ -115,6 +162,32 @@ pub enum IncomingEvent {
 pub struct ServerInfo {
     pub num_clients: usize,
     pub num_active_matches: usize,
+    // Games in progress in public matches. Refreshed on ticks only.
+    pub live_games: Vec<LiveGameDescription>,
+    // Public matches shown in the lobby. Refreshed on ticks only.
+    pub open_matches: Vec<OpenMatchDescription>,
+}
+
+// Enough information to render a thumbnail of an ongoing game.
+#[derive(Clone, Debug, Serialize)]
+pub struct LiveGameDescription {
+    pub match_id: String,
+    pub game_index: u64,
+    pub rules: Rules,
+    pub players: Vec<PlayerInGame>,
+    // Time since the clock started; `None` if nobody has made a turn yet.
+    pub elapsed_ms: Option<u64>,
+}
+
+// Lobby entry for external consumers. Unlike `MatchDescription`, contains a human-readable rules
+// summary instead of the full rules.
+#[derive(Clone, Debug, Serialize)]
+pub struct OpenMatchDescription {
+    pub match_id: String,
+    pub rules_summary: String,
+    pub num_players: u32,
+    pub num_observers: u32,
+    pub started: bool,
 }
 
 impl ServerInfo {
@@ -150,6 +223,8 @@ pub struct GameState {
     turn_requests: Vec<TurnRequest>,
     chalkboard: Chalkboard,
     shared_wayback_turn_index: Option<TurnIndex>,
+    // Teams that voted to abort the game.
+    abort_votes: EnumMap<Team, bool>,
 }
 
 
@@ -163,6 +238,14 @@ struct ParticipantId(usize);
 #[derive(Clone, Debug)]
 struct ParticipantExtra {
     confirmed_local_message_id: u64,
+    // The latest chat message accepted from the current client session. Clients replay
+    // unacknowledged events after a hot reconnect, so the same message could arrive twice.
+    latest_local_message_id: Option<u64>,
+    // Used to enforce `MatchRules::chat_slow_mode`.
+    last_chat_message_time: Option<Instant>,
+    // When the current client session joined the match. Survives hot reconnects. Used to pass
+    // match ownership to the longest-connected participant.
+    session_start: Option<Instant>,
 }
 
 #[derive(Debug)]
@@ -170,10 +253,23 @@ struct Participants {
     // Use an ordered map to show lobby players in joining order.
     map: BTreeMap<ParticipantId, (Participant, ParticipantExtra)>,
     next_id: usize,
+    // Labels are never reused within a match, so that different anonymous guests are never
+    // confused in chat history or game archive.
+    next_anonymous_guest_index: usize,
+    // Labels issued in this match. Only these can be reclaimed on hot reconnect: clients choose
+    // the name they reconnect with, so a label alone proves nothing.
+    issued_anonymous_guest_names: HashSet<String>,
 }
 
 impl Participants {
-    fn new() -> Self { Self { map: BTreeMap::new(), next_id: 1 } }
+    fn new() -> Self {
+        Self {
+            map: BTreeMap::new(),
+            next_id: 1,
+            next_anonymous_guest_index: 0,
+            issued_anonymous_guest_names: HashSet::new(),
+        }
+    }
     fn len(&self) -> usize { self.map.len() }
     fn iter(&self) -> impl Iterator<Item = &Participant> + Clone {
         self.map.values().map(|(p, _)| p)
@@ -190,10 +286,49 @@ impl Participants {
             .iter()
             .find_map(|(id, (p, _))| if p.name == name { Some(*id) } else { None })
     }
+    // Anonymous guests get a fresh label. An anonymous guest that has been removed from the match
+    // (e.g. went offline before the first game) keeps their label on hot reconnect. Labels cannot
+    // be taken as regular names: this doesn't depend on server-specific name validation.
+    fn new_participant_name(
+        &mut self, helpers: &dyn ServerHelpers, player_name: String, anonymous: bool,
+        hot_reconnect: bool,
+    ) -> Result<String, BughouseServerRejection> {
+        if anonymous {
+            return Ok(self.new_anonymous_guest_name());
+        }
+        if is_anonymous_guest_name(&player_name) {
+            if hot_reconnect && self.issued_anonymous_guest_names.contains(&player_name) {
+                return Ok(player_name);
+            }
+            return Err(BughouseServerRejection::InvalidPlayerName {
+                player_name,
+                reason: PlayerNameError::Censored,
+            });
+        }
+        if let Err(reason) = helpers.validate_player_name(&player_name) {
+            return Err(BughouseServerRejection::InvalidPlayerName { player_name, reason });
+        }
+        Ok(player_name)
+    }
+    fn new_anonymous_guest_name(&mut self) -> String {
+        loop {
+            let name = anonymous_guest_name(self.next_anonymous_guest_index);
+            self.next_anonymous_guest_index += 1;
+            if self.find_by_name(&name).is_none() {
+                self.issued_anonymous_guest_names.insert(name.clone());
+                return name;
+            }
+        }
+    }
     fn add_participant(&mut self, participant: Participant) -> ParticipantId {
         let id = ParticipantId(self.next_id);
         self.next_id += 1;
-        let extra = ParticipantExtra { confirmed_local_message_id: 0 };
+        let extra = ParticipantExtra {
+            confirmed_local_message_id: 0,
+            latest_local_message_id: None,
+            last_chat_message_time: None,
+            session_start: None,
+        };
         assert!(self.map.insert(id, (participant, extra)).is_none());
         id
     }
@@ -226,6 +361,7 @@ pub struct Client {
     logging_id: String,
     need_match_list: bool,
     connection_monitor: PassiveConnectionMonitor,
+    following: Option<String>, // name of the followed player
 }
 
 impl Client {
@@ -240,6 +376,7 @@ pub struct Clients {
     map: RwLock<HashMap<ClientId, Client>>,
     next_id: AtomicUsize,
     welcome_event: BughouseServerEvent,
+    heartbeat_timeout: Duration,
 }
 
 impl Clients {
@@ -249,14 +386,18 @@ impl Clients {
                 .check_git_version
                 .then(|| my_git_version!().to_owned()),
             max_starting_time: server_options.max_starting_time,
+            max_player_name_length: server_options.max_player_name_length,
         };
         Clients {
             map: RwLock::new(HashMap::new()),
             next_id: AtomicUsize::new(1),
             welcome_event,
+            heartbeat_timeout: server_options.client_heartbeat_timeout,
         }
     }
 
+    pub fn heartbeat_timeout(&self) -> Duration { self.heartbeat_timeout }
+
     pub async fn add_client(
         &self, events_tx: async_std::channel::Sender<BughouseServerEvent>,
         session_id: Option<SessionId>, logging_id: String,
@@ -268,7 +409,8 @@ impl Clients {
             session_id,
             logging_id,
             need_match_list: true,
-            connection_monitor: PassiveConnectionMonitor::new(now),
+            connection_monitor: PassiveConnectionMonitor::new(now, self.heartbeat_timeout),
+            following: None,
         };
         let id = ClientId(self.next_id.fetch_add(1, atomic::Ordering::SeqCst));
         let old_entry = self.map.write().await.insert(id, client);
@@ -313,6 +455,13 @@ impl Clients {
             self.send(id, event.clone()).await;
         }
     }
+
+    // Sends the event to each connected client, whether in a match or not.
+    async fn broadcast_to_all(&self, event: &BughouseServerEvent) {
+        for client in self.map.read().await.values() {
+            client.send(event.clone());
+        }
+    }
 }
 
 
@@ -338,13 +487,27 @@ struct Match {
     rules: Rules,
     participants: Participants,
     clients: HashMap<ClientId, ParticipantId>,
+    spectators: HashSet<ClientId>, // read-only viewers; not participants
     chat: ServerChat,
     teaming: Option<Teaming>, // `Some` since the first game begins
     scores: Option<Scores>,   // `Some` since the first game begins
     game_history: Vec<GameHistoryRecord>,
     first_game_countdown_since: Option<Instant>,
+    // Whole seconds left until the first game as last broadcast to clients.
+    first_game_countdown_seconds_left: Option<u64>,
+    // If `Some`, the first game starts at this time. Reset when the time comes.
+    scheduled_start: Option<Instant>,
     next_board_assignment: Option<Vec<PlayerInGame>>,
     game_state: Option<GameState>, // active game or latest game
+    // Participant who can moderate the match. Always online if `Some`. Passed to the
+    // longest-connected participant when the owner leaves.
+    owner: Option<ParticipantId>,
+    // When the match was left without an owner, i.e. without online participants.
+    ownerless_since: Option<Instant>,
+    // Whether chat was limited for players during the last `post_process`, see `RatedGameChat`.
+    game_chat_limited: bool,
+    // Set when the match reaches `MatchRules::match_target`. Final: no more games after that.
+    result: Option<MatchResult>,
 }
 
 // Improvement potential: Dedup against `ServerState`.
@@ -435,6 +598,9 @@ impl ServerState {
     #[allow(non_snake_case)]
     pub fn TEST_disable_countdown(&mut self) { self.disable_countdown = true; }
 
+    #[allow(non_snake_case)]
+    pub fn TEST_enable_countdown(&mut self) { self.disable_countdown = false; }
+
     #[allow(non_snake_case)]
     pub fn TEST_disable_connection_health_check(&mut self) {
         self.disable_connection_health_check = true;
@@ -470,21 +636,37 @@ impl CoreServerState {
     }
 
     fn make_match(
-        &mut self, now: Instant, rules: Rules,
+        &mut self, now: Instant, utc_now: UtcDateTime, rules: Rules,
+        scheduled_start: Option<UtcDateTime>,
     ) -> Result<MatchId, BughouseServerRejection> {
-        // Client should verify rules according to the very same logic, so this shouldn't happen:
-        rules.verify().map_err(|err| unknown_error!("Invalid match rules: {err}"))?;
-
-        if let Some(max_starting_time) = self.server_options.max_starting_time {
-            let starting_time = rules.chess_rules.time_control.starting_time;
-            if starting_time > max_starting_time {
-                // TODO: Log to see if this is a popular request.
-                return Err(BughouseServerRejection::MaxStartingTimeExceeded {
-                    requested: rules.chess_rules.time_control.starting_time,
-                    allowed: max_starting_time,
-                });
+        let client_server_options = client::ServerOptions {
+            max_starting_time: self.server_options.max_starting_time,
+            max_player_name_length: self.server_options.max_player_name_length,
+        };
+        client_server_options.verify_rules(&rules).map_err(|err| match err {
+            // Client should verify rules according to the very same logic, so this shouldn't
+            // happen:
+            RulesVerificationError::Invalid(err) => unknown_error!("Invalid match rules: {err}"),
+            // TODO: Log to see if this is a popular request.
+            RulesVerificationError::MaxStartingTimeExceeded { requested, allowed } => {
+                BughouseServerRejection::MaxStartingTimeExceeded { requested, allowed }
             }
-        }
+        })?;
+
+        let scheduled_start = match scheduled_start {
+            None => None,
+            Some(start) => {
+                let delay = OffsetDateTime::from(start) - OffsetDateTime::from(utc_now);
+                match Duration::try_from(delay) {
+                    Ok(delay) if delay <= MAX_SCHEDULED_START_DELAY => Some(now + delay),
+                    _ => {
+                        return Err(BughouseServerRejection::InvalidScheduledStart {
+                            max_delay: MAX_SCHEDULED_START_DELAY,
+                        });
+                    }
+                }
+            }
+        };
 
         const MIN_ID_LEN: usize = 4;
         const MAX_ATTEMPTS_PER_LEN: usize = 100;
@@ -514,14 +696,22 @@ impl CoreServerState {
             rules,
             participants: Participants::new(),
             clients: HashMap::new(),
-            chat: ServerChat::new(),
+            spectators: HashSet::new(),
+            chat: ServerChat::new(self.server_options.max_chat_messages_per_match),
             teaming: None,
             scores: None,
             game_history: Vec::new(),
             first_game_countdown_since: None,
+            first_game_countdown_seconds_left: None,
+            scheduled_start,
             next_board_assignment: None,
             game_state: None,
+            owner: None,
+            ownerless_since: Some(now),
+            game_chat_limited: false,
+            result: None,
         };
+        record_rules_popularity("match_created", &mtch.rules);
         assert!(self.matches.insert(id.clone(), mtch).is_none());
         Ok(id)
     }
@@ -531,6 +721,7 @@ impl CoreServerState {
             .with_label_values(&[event_name(&event)])
             .start_timer();
 
+        let is_tick = matches!(event, IncomingEvent::Tick);
         match event {
             IncomingEvent::Network(client_id, event) => {
                 self.on_client_event(ctx, client_id, event).await
@@ -539,11 +730,16 @@ impl CoreServerState {
             IncomingEvent::Terminate => self.on_terminate(ctx).await,
         }
 
-        let info = ServerInfo {
-            num_clients: ctx.clients.map.read().await.len(),
-            num_active_matches: self.num_active_matches(ctx.now),
-        };
-        *ctx.info.lock().await = info;
+        let num_clients = ctx.clients.map.read().await.len();
+        let mut info = ctx.info.lock().await;
+        info.num_clients = num_clients;
+        info.num_active_matches = self.num_active_matches(ctx.now);
+        // Collecting game details is relatively expensive, so there is no need to do it more
+        // often than the clients are going to poll it.
+        if is_tick {
+            info.live_games = self.live_games(ctx.now);
+            info.open_matches = self.open_matches(ctx.now);
+        }
 
         timer.observe_duration();
     }
@@ -574,6 +770,10 @@ impl CoreServerState {
                 process_get_archive_game_bpng(ctx, client_id, *game_id).await;
                 return;
             }
+            BughouseClientEvent::FollowPlayer { player_name } => {
+                process_follow_player(ctx, client_id, player_name.clone()).await;
+                return;
+            }
             BughouseClientEvent::ReportPerformace(perf) => {
                 process_report_performance(ctx, perf.clone()).await;
                 return;
@@ -590,7 +790,7 @@ impl CoreServerState {
         };
 
         let match_id = match &event {
-            BughouseClientEvent::NewMatch { rules, .. } => {
+            BughouseClientEvent::NewMatch { rules, scheduled_start, .. } => {
                 if !matches!(self.execution, Execution::Running) {
                     ctx.clients
                         .send_rejection(client_id, BughouseServerRejection::ShuttingDown)
@@ -604,18 +804,20 @@ impl CoreServerState {
                 } else {
                     return;
                 }
-                let match_id = match self.make_match(ctx.now, rules.clone()) {
-                    Ok(id) => id,
-                    Err(err) => {
-                        ctx.clients.send_rejection(client_id, err).await;
-                        return;
-                    }
-                };
+                let match_id =
+                    match self.make_match(ctx.now, ctx.utc_now, rules.clone(), *scheduled_start) {
+                        Ok(id) => id,
+                        Err(err) => {
+                            ctx.clients.send_rejection(client_id, err).await;
+                            return;
+                        }
+                    };
                 info!("Match {} created by client {}", match_id.0, logging_id);
                 Some(match_id)
             }
             BughouseClientEvent::Join { match_id, .. }
-            | BughouseClientEvent::HotReconnect { match_id, .. } => {
+            | BughouseClientEvent::HotReconnect { match_id, .. }
+            | BughouseClientEvent::Spectate { match_id } => {
                 // Improvement potential: Log cases when a client reconnects to their current
                 //   match. This likely indicates a client error.
 
@@ -648,6 +850,7 @@ impl CoreServerState {
             assert!(
                 matches!(event, BughouseClientEvent::Join { .. })
                     || matches!(event, BughouseClientEvent::HotReconnect { .. })
+                    || matches!(event, BughouseClientEvent::Spectate { .. })
             );
             ctx.clients
                 .send_rejection(client_id, BughouseServerRejection::NoSuchMatch {
@@ -657,6 +860,16 @@ impl CoreServerState {
             return;
         };
 
+        if matches!(event, BughouseClientEvent::Spectate { .. })
+            && !mtch.spectators.contains(&client_id)
+            && mtch.spectators.len() >= self.server_options.max_spectators_per_match
+        {
+            ctx.clients
+                .send_rejection(client_id, BughouseServerRejection::TooManySpectators)
+                .await;
+            return;
+        }
+
         // Test flags first. Thus we make sure that turns and other actions are
         // not allowed after the time is over.
         mtch.test_flags(ctx).await;
@@ -681,9 +894,19 @@ impl CoreServerState {
             mtch.gc_inactive_players(ctx, &client_matches).await;
         }
         self.update_match_list(ctx).await;
-        if !matches!(self.execution, Execution::Running) && self.num_active_matches(ctx.now) == 0 {
-            println!("No more active matches left. Shutting down.");
-            shutdown();
+        match self.execution {
+            Execution::Running => {}
+            Execution::ShuttingDown { .. } => {
+                if self.num_active_matches(ctx.now) == 0 {
+                    println!("No more active matches left. Shutting down.");
+                    self.announce_shutdown(ctx).await;
+                }
+            }
+            Execution::ShutdownAnnounced { since } => {
+                if ctx.now.duration_since(since) >= SHUTDOWN_NOTICE_PERIOD {
+                    shutdown();
+                }
+            }
         }
     }
 
@@ -694,7 +917,7 @@ impl CoreServerState {
             Execution::Running => {
                 if num_active_matches == 0 {
                     println!("There are no active matches. Shutting down immediately!");
-                    shutdown();
+                    self.announce_shutdown(ctx).await;
                 } else {
                     printdoc!("
                         Shutdown requested!
@@ -736,8 +959,9 @@ impl CoreServerState {
                 if ctx.now.duration_since(*last_termination_request)
                     <= DOUBLE_TERMINATION_ABORT_THRESHOLD
                 {
-                    println!("Aborting!");
-                    shutdown();
+                    println!("Aborting! Games in progress will be saved, but not finished.");
+                    self.persist_interrupted_games(ctx).await;
+                    self.announce_shutdown(ctx).await;
                 } else {
                     let shutdown_duration_sec =
                         ctx.now.duration_since(shutting_down_since).as_secs();
@@ -745,26 +969,69 @@ impl CoreServerState {
                         "Shutdown was requested {}s ago. Waiting for {} active matches to finish.\n{}",
                         shutdown_duration_sec, num_active_matches, ABORT_INSTRUCTION,
                     );
+                    *last_termination_request = ctx.now;
                 }
-                *last_termination_request = ctx.now;
+            }
+            Execution::ShutdownAnnounced { .. } => {
+                println!("Aborting!");
+                shutdown();
             }
         }
     }
 
-    async fn update_match_list(&mut self, ctx: &mut Context) {
-        let match_list = self
-            .matches
+    async fn announce_shutdown(&mut self, ctx: &mut Context) {
+        ctx.clients
+            .broadcast_to_all(&BughouseServerEvent::ServerShuttingDown {
+                reconnect_in: SHUTDOWN_RECONNECT_DELAY,
+            })
+            .await;
+        self.execution = Execution::ShutdownAnnounced { since: ctx.now };
+    }
+
+    // Normally the server waits for all games to finish before shutting down. If shutdown is
+    // forced, save the games that are still in progress instead.
+    async fn persist_interrupted_games(&self, ctx: &Context) {
+        for mtch in self.matches.values() {
+            let Some(game_state) = &mtch.game_state else {
+                continue;
+            };
+            // Games that haven't started yet have nothing worth saving.
+            let Some(game_start_utc_time) = game_state.game_start_utc_time else {
+                continue;
+            };
+            if !game_state.game.is_active() {
+                continue;
+            }
+            ctx.hooks
+                .record_interrupted_game(
+                    &game_state.game,
+                    game_start_utc_time,
+                    ctx.utc_now,
+                    game_state.game_index + 1,
+                )
+                .await;
+        }
+    }
+
+    // Matches that are shown in the lobby.
+    fn listed_matches(&self, now: Instant) -> impl Iterator<Item = &Match> {
+        self.matches
             .values()
             .filter(|mtch| mtch.rules.match_rules.public)
-            .filter(|mtch| {
+            .filter(move |mtch| {
                 mtch.participants.len() > 0
                     || match mtch.latest_activity() {
                         MatchActivity::Present => true,
                         MatchActivity::Past(t) => {
-                            ctx.now.duration_since(t) <= MATCH_HIDE_INACTIVITY_THRESHOLD
+                            now.duration_since(t) <= MATCH_HIDE_INACTIVITY_THRESHOLD
                         }
                     }
             })
+    }
+
+    async fn update_match_list(&mut self, ctx: &mut Context) {
+        let match_list = self
+            .listed_matches(ctx.now)
             .map(|mtch| {
                 let num_players =
                     mtch.participants.iter().filter(|p| p.faction.is_player()).count();
@@ -798,11 +1065,57 @@ impl CoreServerState {
             .count()
     }
 
+    fn live_games(&self, now: Instant) -> Vec<LiveGameDescription> {
+        self.matches
+            .values()
+            .filter(|mtch| mtch.rules.match_rules.public)
+            .filter_map(|mtch| {
+                let game_state = mtch.game_state.as_ref()?;
+                if !game_state.game.is_active() {
+                    return None;
+                }
+                Some(LiveGameDescription {
+                    match_id: mtch.match_id.0.clone(),
+                    game_index: game_state.game_index,
+                    rules: mtch.rules.clone(),
+                    players: game_state.game.players(),
+                    elapsed_ms: game_state
+                        .game_start
+                        .map(|t| now.duration_since(t).as_millis() as u64),
+                })
+            })
+            .sorted_by(|a, b| a.match_id.cmp(&b.match_id))
+            .collect()
+    }
+
+    fn open_matches(&self, now: Instant) -> Vec<OpenMatchDescription> {
+        self.listed_matches(now)
+            .map(|mtch| {
+                let (players, observers): (Vec<_>, Vec<_>) =
+                    mtch.participants.iter().partition(|p| p.faction.is_player());
+                OpenMatchDescription {
+                    match_id: mtch.match_id.0.clone(),
+                    rules_summary: mtch.rules.describe(),
+                    num_players: players.len() as u32,
+                    num_observers: (observers.len() + mtch.spectators.len()) as u32,
+                    started: mtch.game_state.is_some(),
+                }
+            })
+            .sorted_by(|a, b| a.match_id.cmp(&b.match_id))
+            .collect()
+    }
+
     fn gc_old_matches(&mut self, now: Instant) {
-        // Improvement potential. GC unused matches (zero games and/or no players) sooner.
+        // Improvement potential. GC matches with zero games sooner.
         self.matches.retain(|_, mtch| match mtch.latest_activity() {
             MatchActivity::Present => true,
-            MatchActivity::Past(t) => now.duration_since(t) <= MATCH_GC_INACTIVITY_THRESHOLD,
+            MatchActivity::Past(t) => {
+                // Note. `t` could be in the future for scheduled matches.
+                let abandoned = mtch.ownerless_since.is_some_and(|ownerless_since| {
+                    now.duration_since(ownerless_since.max(t)) > MATCH_GC_ABANDONED_THRESHOLD
+                });
+                !abandoned && now.duration_since(t) <= MATCH_GC_INACTIVITY_THRESHOLD
+            }
         });
     }
 
@@ -814,7 +1127,10 @@ impl CoreServerState {
         ctx.clients.map.write().await.retain(|_, client| {
             match client.connection_monitor.status(ctx.now) {
                 Healthy | TemporaryLost => true,
-                PermanentlyLost => false,
+                PermanentlyLost => {
+                    info!("Client {} missed heartbeats; disconnecting", client.logging_id);
+                    false
+                }
             }
         });
     }
@@ -839,7 +1155,8 @@ impl Match {
                 MatchActivity::Past(game_creation)
             }
         } else {
-            MatchActivity::Past(self.match_creation)
+            // Scheduled matches must not be GCed while waiting for the start.
+            MatchActivity::Past(self.scheduled_start.unwrap_or(self.match_creation))
         }
     }
 
@@ -862,7 +1179,7 @@ impl Match {
         if !game.is_active() {
             return;
         }
-        let game_now = GameInstant::from_now_game_active(game_start, ctx.now);
+        let game_now = monotonic_game_time(game, Some(game_start), ctx.now);
         if let Some(game_over_time) = game.test_flag(game_now) {
             assert!(!game.is_active());
             let update = update_on_game_over(
@@ -894,8 +1211,13 @@ impl Match {
         self.broadcast(ctx, &ev).await;
     }
 
+    // Sends the event to participants and spectators. Note that chat messages are sent separately
+    // via `send_messages`, so spectators never see the chat.
     async fn broadcast(&self, ctx: &mut Context, event: &BughouseServerEvent) {
         ctx.clients.broadcast(&self.clients, event).await;
+        for &client_id in &self.spectators {
+            ctx.clients.send(client_id, event.clone()).await;
+        }
     }
 
     async fn process_client_event(
@@ -903,15 +1225,50 @@ impl Match {
         event: BughouseClientEvent,
     ) {
         let result = match event {
-            BughouseClientEvent::NewMatch { player_name, .. } => {
+            BughouseClientEvent::NewMatch { player_name, anonymous, show_rating, .. } => {
                 // The match was created earlier.
-                self.join_participant(ctx, client_id, execution, player_name, false).await
+                self.join_participant(
+                    ctx,
+                    client_id,
+                    execution,
+                    player_name,
+                    anonymous,
+                    show_rating,
+                    false,
+                )
+                .await
             }
-            BughouseClientEvent::Join { match_id: _, player_name } => {
-                self.join_participant(ctx, client_id, execution, player_name, false).await
+            BughouseClientEvent::Join {
+                match_id: _,
+                player_name,
+                anonymous,
+                show_rating,
+            } => {
+                self.join_participant(
+                    ctx,
+                    client_id,
+                    execution,
+                    player_name,
+                    anonymous,
+                    show_rating,
+                    false,
+                )
+                .await
+            }
+            BughouseClientEvent::HotReconnect { match_id: _, player_name, show_rating } => {
+                self.join_participant(
+                    ctx,
+                    client_id,
+                    execution,
+                    player_name,
+                    false,
+                    show_rating,
+                    true,
+                )
+                .await
             }
-            BughouseClientEvent::HotReconnect { match_id: _, player_name } => {
-                self.join_participant(ctx, client_id, execution, player_name, true).await
+            BughouseClientEvent::Spectate { match_id: _ } => {
+                self.join_spectator(ctx, client_id).await
             }
             BughouseClientEvent::SetFaction { faction } => {
                 self.process_set_faction(ctx, client_id, faction).await
@@ -926,9 +1283,23 @@ impl Match {
                 self.process_cancel_preturn(ctx, client_id, board_idx).await
             }
             BughouseClientEvent::Resign => self.process_resign(ctx, client_id).await,
+            BughouseClientEvent::ResignBoard => self.process_resign_board(ctx, client_id).await,
+            BughouseClientEvent::RequestAbort => self.process_request_abort(ctx, client_id).await,
             BughouseClientEvent::SetReady { is_ready } => {
                 self.process_set_ready(ctx, client_id, is_ready).await
             }
+            BughouseClientEvent::SetShowRating { show_rating } => {
+                self.process_set_show_rating(ctx, client_id, show_rating).await
+            }
+            BughouseClientEvent::RequestRematch { swap } => {
+                self.process_request_rematch(ctx, client_id, swap).await
+            }
+            BughouseClientEvent::SetHandicap { player_name, handicap } => {
+                self.process_set_handicap(ctx, client_id, player_name, handicap).await
+            }
+            BughouseClientEvent::TransferMatchOwnership { to } => {
+                self.process_transfer_match_ownership(ctx, client_id, to).await
+            }
             BughouseClientEvent::LeaveMatch => self.process_leave_match(ctx, client_id).await,
             BughouseClientEvent::LeaveServer => self.process_leave_server(ctx, client_id).await,
             BughouseClientEvent::SendChatMessage { message } => {
@@ -938,12 +1309,13 @@ impl Match {
                 self.process_update_chalk_drawing(ctx, client_id, drawing).await
             }
             BughouseClientEvent::SetSharedWayback { turn_index } => {
-                self.process_set_shared_wayback(ctx, turn_index).await
+                self.process_set_shared_wayback(ctx, client_id, turn_index).await
             }
             // Match-independent events must be processed separately. Keep the event entities
             // separate, so that we know which one it was if it crashes.
             BughouseClientEvent::GetArchiveGameList => unreachable!(),
             BughouseClientEvent::GetArchiveGameBpgn { .. } => unreachable!(),
+            BughouseClientEvent::FollowPlayer { .. } => unreachable!(),
             BughouseClientEvent::ReportPerformace(..) => unreachable!(),
             BughouseClientEvent::ReportError(..) => unreachable!(),
             BughouseClientEvent::Ping => unreachable!(),
@@ -955,7 +1327,7 @@ impl Match {
 
     async fn join_participant(
         &mut self, ctx: &mut Context, client_id: ClientId, execution: Execution,
-        player_name: String, hot_reconnect: bool,
+        player_name: String, anonymous: bool, show_rating: bool, hot_reconnect: bool,
     ) -> EventResult {
         let session_id;
         {
@@ -968,6 +1340,7 @@ impl Match {
             assert!(client.match_id.is_none());
             session_id = client.session_id.clone();
         }
+        self.spectators.remove(&client_id);
         let registered_user_name =
             get_registered_user_name(&*ctx.session_store.lock().await, session_id.as_ref());
 
@@ -979,9 +1352,14 @@ impl Match {
                 ));
             }
         }
+        let anonymous = anonymous && !is_registered_user;
         // Improvement potential: Reject earlier if a guest is trying to create a rated match.
-        if self.rules.match_rules.rated && !is_registered_user {
-            return Err(BughouseServerRejection::GuestInRatedMatch);
+        if !is_registered_user {
+            if self.rules.match_rules.rated {
+                return Err(BughouseServerRejection::GuestInRatedMatch);
+            } else if self.rules.match_rules.registered_only {
+                return Err(BughouseServerRejection::GuestInRegisteredOnlyMatch);
+            }
         }
 
         let find_client = |participant_id| {
@@ -1039,11 +1417,16 @@ impl Match {
                 return Ok(());
             }
             let participant_id = if let Some(id) = existing_participant_id {
+                self.participants[id].show_rating = show_rating;
                 id
             } else {
-                if let Err(reason) = ctx.helpers.validate_player_name(&player_name) {
-                    return Err(BughouseServerRejection::InvalidPlayerName { player_name, reason });
-                }
+                let player_name = self.participants.new_participant_name(
+                    ctx.helpers.as_ref(),
+                    player_name,
+                    anonymous,
+                    hot_reconnect,
+                )?;
+                let rating = self.fetch_rating(ctx, &player_name, is_registered_user).await;
                 self.participants.add_participant(Participant {
                     name: player_name,
                     is_registered_user,
@@ -1055,10 +1438,24 @@ impl Match {
                     scheduling_priority: PlayerSchedulingPriority::default(),
                     is_online: true,
                     is_ready: false,
+                    handicap: Reserve::default(),
+                    rating,
+                    show_rating,
                 })
             };
             self.clients.insert(client_id, participant_id);
-            ctx.clients.send(client_id, self.make_match_welcome_event()).await;
+            let extra = self.participants.extra_mut(participant_id);
+            if !hot_reconnect {
+                // Local message IDs restart from scratch in a new client session.
+                extra.latest_local_message_id = None;
+                extra.session_start = None;
+            }
+            extra.session_start.get_or_insert(ctx.now);
+            let chalkboard = game_state.chalkboard.clone();
+            let shared_wayback_turn_index = game_state.shared_wayback_turn_index;
+            self.update_owner(ctx.now);
+            let my_name = self.participants[participant_id].name.clone();
+            ctx.clients.send(client_id, self.make_match_welcome_event(Some(my_name))).await;
             // LobbyUpdated should precede GameStarted, because this is how the client gets their
             // team in FixedTeam mode.
             self.send_lobby_updated(ctx).await;
@@ -1067,15 +1464,14 @@ impl Match {
                 .await;
             self.send_messages(ctx, Some(client_id), self.chat.all_messages()).await;
             ctx.clients
-                .send(client_id, BughouseServerEvent::ChalkboardUpdated {
-                    chalkboard: game_state.chalkboard.clone(),
-                })
+                .send(client_id, BughouseServerEvent::ChalkboardUpdated { chalkboard })
                 .await;
             ctx.clients
                 .send(client_id, BughouseServerEvent::SharedWaybackUpdated {
-                    turn_index: game_state.shared_wayback_turn_index,
+                    turn_index: shared_wayback_turn_index,
                 })
                 .await;
+            self.send_match_result(ctx, client_id).await;
             Ok(())
         } else {
             let existing_participant_id = self.participants.find_by_name(&player_name);
@@ -1128,11 +1524,16 @@ impl Match {
                 return Ok(());
             }
             let participant_id = if let Some(id) = existing_participant_id {
+                self.participants[id].show_rating = show_rating;
                 id
             } else {
-                if let Err(reason) = ctx.helpers.validate_player_name(&player_name) {
-                    return Err(BughouseServerRejection::InvalidPlayerName { player_name, reason });
-                }
+                let player_name = self.participants.new_participant_name(
+                    ctx.helpers.as_ref(),
+                    player_name,
+                    anonymous,
+                    hot_reconnect,
+                )?;
+                let rating = self.fetch_rating(ctx, &player_name, is_registered_user).await;
                 info!(
                     "Client {} join match {} as {}",
                     client_logging_id, self.match_id.0, player_name
@@ -1148,15 +1549,61 @@ impl Match {
                     scheduling_priority: PlayerSchedulingPriority::default(),
                     is_online: true,
                     is_ready: false,
+                    handicap: Reserve::default(),
+                    rating,
+                    show_rating,
                 })
             };
             self.clients.insert(client_id, participant_id);
-            ctx.clients.send(client_id, self.make_match_welcome_event()).await;
+            let extra = self.participants.extra_mut(participant_id);
+            if !hot_reconnect {
+                // Local message IDs restart from scratch in a new client session.
+                extra.latest_local_message_id = None;
+                extra.session_start = None;
+            }
+            extra.session_start.get_or_insert(ctx.now);
+            self.update_owner(ctx.now);
+            let my_name = self.participants[participant_id].name.clone();
+            ctx.clients.send(client_id, self.make_match_welcome_event(Some(my_name))).await;
             self.send_lobby_updated(ctx).await;
+            self.send_messages(ctx, Some(client_id), self.chat.all_messages()).await;
             Ok(())
         }
     }
 
+    // Spectators get the same game state as observers, but they are not registered as participants:
+    // they are not shown in the lobby, cannot chat and cannot affect the match in any way.
+    async fn join_spectator(&mut self, ctx: &mut Context, client_id: ClientId) -> EventResult {
+        let client_logging_id;
+        if let Some(ref mut client) = ctx.clients.map.write().await.get_mut(&client_id) {
+            assert!(client.match_id.is_none());
+            client.match_id = Some(self.match_id.clone());
+            client_logging_id = client.logging_id.clone();
+        } else {
+            return Ok(());
+        }
+        info!("Client {} spectates match {}", client_logging_id, self.match_id.0);
+        self.clients.remove(&client_id);
+        self.spectators.insert(client_id);
+        ctx.clients.send(client_id, self.make_match_welcome_event(None)).await;
+        ctx.clients.send(client_id, self.make_lobby_updated_event(ctx.now)).await;
+        if let Some(ref game_state) = self.game_state {
+            ctx.clients.send(client_id, self.make_game_start_event(ctx.now, None)).await;
+            ctx.clients
+                .send(client_id, BughouseServerEvent::ChalkboardUpdated {
+                    chalkboard: game_state.chalkboard.clone(),
+                })
+                .await;
+            ctx.clients
+                .send(client_id, BughouseServerEvent::SharedWaybackUpdated {
+                    turn_index: game_state.shared_wayback_turn_index,
+                })
+                .await;
+            self.send_match_result(ctx, client_id).await;
+        }
+        Ok(())
+    }
+
     async fn process_set_faction(
         &mut self, ctx: &mut Context, client_id: ClientId, faction: Faction,
     ) -> EventResult {
@@ -1164,6 +1611,26 @@ impl Match {
             return Err(unknown_error!("Cannot set random faction in fixed teams mode"));
         }
         let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        let old_faction = self.participants[participant_id].faction;
+        if faction == old_faction {
+            // Could be replayed after a hot reconnect. Don't announce it in chat again.
+            return Ok(());
+        }
+        if verify_faction_change(self.teaming, self.participants.iter(), old_faction, faction)
+            .is_err()
+        {
+            // Resend the lobby so that the client could revert optimistic faction update.
+            self.send_lobby_updated(ctx).await;
+            return Err(BughouseServerRejection::FactionChangeLeavesTeamEmpty);
+        }
+        self.apply_faction_change(ctx, participant_id, faction).await;
+        Ok(())
+    }
+
+    // Does not verify the change: leaving the match is allowed even if it leaves a team empty.
+    async fn apply_faction_change(
+        &mut self, ctx: &mut Context, participant_id: ParticipantId, faction: Faction,
+    ) {
         let participant = &mut self.participants[participant_id];
         let old_faction = participant.faction;
         let name = participant.name.clone();
@@ -1186,13 +1653,13 @@ impl Match {
                     &self.rules,
                     self.teaming.unwrap(),
                     game_index,
+                    game,
                     &self.participants,
                     &mut self.next_board_assignment,
                     &mut self.chat,
                 );
             }
         }
-        Ok(())
     }
 
     async fn process_set_turns(
@@ -1234,11 +1701,13 @@ impl Match {
             .ok_or_else(|| unknown_error!())?;
         let envoy = player_bughouse_id.envoy_for(board_idx).ok_or_else(|| unknown_error!())?;
 
-        if turn_requests.iter().filter(|r| r.envoy == envoy).count()
-            > self.rules.chess_rules.max_preturns_per_board()
-        {
+        let max_preturns = self.rules.max_preturns_per_board();
+        if turn_requests.iter().filter(|r| r.envoy == envoy).count() > max_preturns {
             return Err(unknown_error!("Premove limit reached"));
         }
+        if max_preturns == 0 && game.turn_mode_for_envoy(envoy) == Ok(TurnMode::Preturn) {
+            return Err(unknown_error!("Premoves are disabled"));
+        }
         let request = TurnRequest { envoy, turn_input };
         turn_requests.push(request);
 
@@ -1253,7 +1722,7 @@ impl Match {
                 *game_start_utc_time = Some(UtcDateTime::now());
             }
             if !game.is_active() {
-                let game_now = GameInstant::from_now_game_active(game_start.unwrap(), ctx.now);
+                let game_now = monotonic_game_time(game, *game_start, ctx.now);
                 game_over_update = Some(update_on_game_over(
                     ctx,
                     &self.rules,
@@ -1356,6 +1825,128 @@ impl Match {
         Ok(())
     }
 
+    async fn process_resign_board(
+        &mut self, ctx: &mut Context, client_id: ClientId,
+    ) -> EventResult {
+        let Some(GameState {
+            game_index,
+            ref mut game,
+            ref mut turn_requests,
+            game_start,
+            ref mut game_start_utc_time,
+            ref mut game_end,
+            ..
+        }) = self.game_state
+        else {
+            return Err(unknown_error!());
+        };
+        if !game.is_active() {
+            return Ok(());
+        }
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        let player_bughouse_id = game
+            .find_player(&self.participants[participant_id].name)
+            .ok_or_else(|| unknown_error!())?;
+        if !game.bughouse_rules().board_resignation {
+            return Err(BughouseServerRejection::BoardResignationNotAllowed);
+        }
+        // Double players cannot resign one board: they are playing both.
+        let BughousePlayer::SinglePlayer(envoy) = player_bughouse_id else {
+            return Err(BughouseServerRejection::BoardResignationNotAllowed);
+        };
+        if game.is_board_resigned(envoy.board_idx) {
+            return Ok(());
+        }
+        let game_now = GameInstant::from_now_game_maybe_active(game_start, ctx.now);
+        game.resign_board(envoy, game_now);
+        turn_requests.retain(|r| r.envoy.board_idx != envoy.board_idx);
+        let mut updates = vec![GameUpdate::BoardResigned { time: game_now, envoy }];
+        let game_over = !game.is_active();
+        if game_over {
+            updates.push(update_on_game_over(
+                ctx,
+                &self.rules,
+                self.teaming.unwrap(),
+                game_index,
+                game,
+                turn_requests,
+                &mut self.participants,
+                self.scores.as_mut().unwrap(),
+                &mut self.next_board_assignment,
+                &mut self.chat,
+                game_now,
+                game_start_utc_time,
+                game_end,
+            ));
+        }
+        self.add_game_updates(ctx, updates).await;
+        if game_over {
+            self.send_lobby_updated(ctx).await;
+        }
+        Ok(())
+    }
+
+    async fn process_request_abort(
+        &mut self, ctx: &mut Context, client_id: ClientId,
+    ) -> EventResult {
+        let Some(GameState {
+            game_index,
+            ref mut game,
+            ref mut turn_requests,
+            game_start,
+            ref mut game_start_utc_time,
+            ref mut game_end,
+            ref mut abort_votes,
+            ..
+        }) = self.game_state
+        else {
+            return Err(unknown_error!());
+        };
+        if !game.is_active() {
+            return Ok(());
+        }
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        let name = self.participants[participant_id].name.clone();
+        let team = game.find_player(&name).ok_or_else(|| unknown_error!())?.team();
+        let window = self.rules.match_rules.abort_window;
+        if !window.is_some_and(|w| abort_window_open(game, w)) {
+            return Err(BughouseServerRejection::AbortNotAllowed { window });
+        }
+        if abort_votes[team] {
+            return Ok(());
+        }
+        abort_votes[team] = true;
+        if !abort_votes.values().all(|&v| v) {
+            self.chat.add(
+                Some(game_index),
+                ctx.utc_now,
+                ChatRecipientExpanded::All,
+                ChatMessageBody::AbortRequested { participant: name },
+            );
+            return Ok(());
+        }
+        let game_now = GameInstant::from_now_game_maybe_active(game_start, ctx.now);
+        game.set_status(BughouseGameStatus::Draw(DrawReason::Aborted), game_now);
+        let update = update_on_game_over(
+            ctx,
+            &self.rules,
+            self.teaming.unwrap(),
+            game_index,
+            game,
+            turn_requests,
+            &mut self.participants,
+            self.scores.as_mut().unwrap(),
+            &mut self.next_board_assignment,
+            &mut self.chat,
+            game_now,
+            game_start_utc_time,
+            game_end,
+        );
+        self.add_game_updates(ctx, vec![update]).await;
+        self.send_lobby_updated(ctx).await;
+        Ok(())
+    }
+
     async fn process_set_ready(
         &mut self, ctx: &mut Context, client_id: ClientId, is_ready: bool,
     ) -> EventResult {
@@ -1366,16 +1957,119 @@ impl Match {
                 return Ok(());
             }
         }
+        if self.result.is_some() {
+            // No error: the client may not have learned that the match is complete yet.
+            return Ok(());
+        }
         self.participants[participant_id].is_ready = is_ready;
         self.send_lobby_updated(ctx).await;
         Ok(())
     }
 
+    async fn process_set_show_rating(
+        &mut self, ctx: &mut Context, client_id: ClientId, show_rating: bool,
+    ) -> EventResult {
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        self.participants[participant_id].show_rating = show_rating;
+        self.send_lobby_updated(ctx).await;
+        Ok(())
+    }
+
+    async fn process_request_rematch(
+        &mut self, ctx: &mut Context, client_id: ClientId, swap: SeatSwap,
+    ) -> EventResult {
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        let Some(GameState { game_index, ref game, .. }) = self.game_state else {
+            return Err(unknown_error!("Cannot request a rematch before the first game"));
+        };
+        if game.is_active() {
+            // No error: the next game could've started.
+            return Ok(());
+        }
+        if game.find_player(&self.participants[participant_id].name).is_none() {
+            return Err(unknown_error!("Only players can request a rematch"));
+        }
+        let players = swap_seats(self.participants.iter(), &game.players(), swap)
+            .map_err(|error| BughouseServerRejection::CannotSwapSeats { error })?;
+        if self.next_board_assignment.as_ref() != Some(&players) {
+            // Other players might have agreed to a different seating, so ask them again.
+            self.reset_readiness();
+            self.chat.add(
+                Some(game_index),
+                ctx.utc_now,
+                ChatRecipientExpanded::All,
+                ChatMessageBody::NextGamePlayers { players: players.clone() },
+            );
+            self.next_board_assignment = Some(players);
+        }
+        self.participants[participant_id].is_ready = true;
+        self.send_lobby_updated(ctx).await;
+        Ok(())
+    }
+
+    async fn process_set_handicap(
+        &mut self, ctx: &mut Context, client_id: ClientId, player_name: String, handicap: Reserve,
+    ) -> EventResult {
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        if self.owner != Some(participant_id) {
+            return Err(BughouseServerRejection::NotMatchOwner);
+        }
+        let invalid =
+            |reason: &str| BughouseServerRejection::InvalidHandicap { reason: reason.to_owned() };
+        if self.rules.match_rules.rated {
+            return Err(invalid("Handicaps are not allowed in rated matches."));
+        }
+        let chess_rules = &self.rules.chess_rules;
+        for (piece_kind, &amount) in handicap.iter() {
+            if amount > 0 && piece_kind.reservable(chess_rules) != PieceReservable::Always {
+                return Err(invalid("Handicap can only include pieces that can be dropped."));
+            }
+        }
+        if handicap.values().map(|&n| n as u32).sum::<u32>() > MAX_HANDICAP_PIECES {
+            return Err(invalid(&format!("Handicap cannot exceed {MAX_HANDICAP_PIECES} pieces.")));
+        }
+        let target_id = self
+            .participants
+            .find_by_name(&player_name)
+            .ok_or(BughouseServerRejection::NoSuchParticipant { player_name })?;
+        self.participants[target_id].handicap = handicap;
+        self.send_lobby_updated(ctx).await;
+        Ok(())
+    }
+
+    async fn process_transfer_match_ownership(
+        &mut self, ctx: &mut Context, client_id: ClientId, to: String,
+    ) -> EventResult {
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        if self.owner != Some(participant_id) {
+            return Err(BughouseServerRejection::NotMatchOwner);
+        }
+        let new_owner = self
+            .participants
+            .find_by_name(&to)
+            .filter(|id| self.clients.values().any(|p| p == id))
+            .ok_or(BughouseServerRejection::NoSuchParticipant { player_name: to })?;
+        self.owner = Some(new_owner);
+        self.send_lobby_updated(ctx).await;
+        Ok(())
+    }
+
     async fn process_leave_match(&mut self, ctx: &mut Context, client_id: ClientId) -> EventResult {
+        if self.spectators.remove(&client_id) {
+            if let Some(ref mut client) = ctx.clients.map.write().await.get_mut(&client_id) {
+                client.match_id = None;
+                client.need_match_list = true;
+            }
+            return Ok(());
+        }
         // TODO: Better chat message ("X left" rather than "X became an observer"). Note that the
         // message could also be sent in `update_on_game_over`.
-        self.process_set_faction(ctx, client_id, Faction::Observer).await?;
+        let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        self.apply_faction_change(ctx, participant_id, Faction::Observer).await;
         self.clients.remove(&client_id);
+        if self.update_owner(ctx.now) {
+            self.send_lobby_updated(ctx).await;
+        }
         if let Some(ref mut client) = ctx.clients.map.write().await.get_mut(&client_id) {
             client.match_id = None;
             client.need_match_list = true;
@@ -1401,6 +2095,23 @@ impl Match {
         &mut self, ctx: &mut Context, client_id: ClientId, message: OutgoingChatMessage,
     ) -> EventResult {
         let participant_id = *self.clients.get(&client_id).ok_or_else(|| unknown_error!())?;
+        let extra = self.participants.extra_mut(participant_id);
+        if extra.latest_local_message_id.is_some_and(|id| message.local_message_id <= id) {
+            // Already received before a reconnect.
+            return Ok(());
+        }
+        let last_time = extra.last_chat_message_time;
+        if let (Some(interval), Some(last_time)) =
+            (self.rules.match_rules.chat_slow_mode, last_time)
+        {
+            let elapsed = ctx.now.duration_since(last_time);
+            if elapsed < interval {
+                return Err(BughouseServerRejection::ChatSlowMode {
+                    local_message_id: message.local_message_id,
+                    wait: interval - elapsed,
+                });
+            }
+        }
         let sender = &self.participants[participant_id];
         let recipient_expanded = match &message.recipient {
             ChatRecipient::All => ChatRecipientExpanded::All,
@@ -1438,10 +2149,10 @@ impl Match {
                 recipient: message.recipient,
                 text: message.text,
             });
-        self.participants
-            .extra_mut(participant_id)
-            .confirmed_local_message_id
-            .relax_max(message.local_message_id);
+        let extra = self.participants.extra_mut(participant_id);
+        extra.confirmed_local_message_id.relax_max(message.local_message_id);
+        extra.latest_local_message_id = Some(message.local_message_id);
+        extra.last_chat_message_time = Some(ctx.now);
         Ok(())
     }
 
@@ -1465,8 +2176,11 @@ impl Match {
     }
 
     async fn process_set_shared_wayback(
-        &mut self, ctx: &mut Context, turn_index: Option<TurnIndex>,
+        &mut self, ctx: &mut Context, client_id: ClientId, turn_index: Option<TurnIndex>,
     ) -> EventResult {
+        if !self.clients.contains_key(&client_id) {
+            return Err(unknown_error!("Only participants can control shared wayback"));
+        }
         let Some(GameState { ref mut shared_wayback_turn_index, .. }) = self.game_state else {
             // No error: the next game could've started.
             return Ok(());
@@ -1483,19 +2197,64 @@ impl Match {
         //   and one from `self.start_game`. Note also `gc_inactive_players`.
         //   Idea: Add `ctx.should_update_lobby` bit and check it in the end.
 
-        let new_chat_messages = fetch_new_chat_messages!(self.chat);
-        self.send_messages(ctx, None, new_chat_messages).await;
+        if self.result.is_none() {
+            self.update_match_result(ctx).await;
+        }
+
+        let game_chat_limited = self.is_game_chat_limited();
+        if self.game_chat_limited && !game_chat_limited {
+            // Reveal messages withheld during the game. Clients skip messages they already have.
+            self.chat.reset_first_new_message_id();
+            self.send_messages(ctx, None, self.chat.all_messages()).await;
+        } else {
+            let new_chat_messages = fetch_new_chat_messages!(self.chat);
+            self.send_messages(ctx, None, new_chat_messages).await;
+        }
+        self.game_chat_limited = game_chat_limited;
+
+        if self.result.is_some() {
+            return;
+        }
+
+        if let Some(scheduled_start) = self.scheduled_start {
+            // Hold the participants until the scheduled time, regardless of their readiness.
+            if ctx.now < scheduled_start {
+                return;
+            }
+            self.scheduled_start = None;
+            let status = verify_participants(&self.rules, self.participants.iter());
+            if matches!(status, ParticipantsStatus::CanStart { .. })
+                && matches!(execution, Execution::Running)
+            {
+                // Players have agreed to the time in advance, so readiness is not required.
+                self.start_game(ctx).await;
+            } else {
+                // Fall back to the regular flow: start as soon as enough players are ready.
+                self.send_lobby_updated(ctx).await;
+            }
+            return;
+        }
 
         let can_start_game =
             verify_participants(&self.rules, self.participants.iter()).can_start_now();
         if let Some(first_game_countdown_start) = self.first_game_countdown_since {
+            let time_left = FIRST_GAME_COUNTDOWN_DURATION
+                .saturating_sub(ctx.now.duration_since(first_game_countdown_start));
             if !can_start_game {
                 self.first_game_countdown_since = None;
+                self.first_game_countdown_seconds_left = None;
                 self.send_lobby_updated(ctx).await;
-            } else if ctx.now.duration_since(first_game_countdown_start)
-                >= FIRST_GAME_COUNTDOWN_DURATION
-            {
+            } else if time_left.is_zero() {
                 self.start_game(ctx).await;
+            } else {
+                // The server owns the countdown. Broadcast it every second, so that all clients
+                // show the same value regardless of local timing.
+                let seconds_left = time_left.as_secs_f64().ceil() as u64;
+                if self.first_game_countdown_seconds_left != Some(seconds_left) {
+                    self.first_game_countdown_seconds_left = Some(seconds_left);
+                    self.broadcast(ctx, &BughouseServerEvent::FirstGameCountdown { time_left })
+                        .await;
+                }
             }
         } else if can_start_game {
             if !matches!(execution, Execution::Running) {
@@ -1553,6 +2312,11 @@ impl Match {
                 .get(&client_id)
                 .is_some_and(|match_id| match_id.as_ref() == Some(&self.match_id))
         });
+        self.spectators.retain(|client_id| {
+            client_matches
+                .get(client_id)
+                .is_some_and(|match_id| match_id.as_ref() == Some(&self.match_id))
+        });
         let online_participant_ids: HashSet<_> = self.clients.values().copied().collect();
         let mut lobby_updated = false;
         let mut chalkboard_updated = false;
@@ -1579,6 +2343,7 @@ impl Match {
             }
             true
         });
+        lobby_updated |= self.update_owner(ctx.now);
         if lobby_updated {
             self.send_lobby_updated(ctx).await;
         }
@@ -1591,6 +2356,8 @@ impl Match {
 
     async fn start_game(&mut self, ctx: &mut Context) {
         self.reset_readiness();
+        self.first_game_countdown_since = None;
+        self.first_game_countdown_seconds_left = None;
 
         // Non-trivial only in the beginning of a match, when the first game starts. Moreover, we've
         // already called `fix_teams_if_needed` when the countdown began, but calling it again to be
@@ -1604,7 +2371,21 @@ impl Match {
         let players = self.next_board_assignment.take().unwrap_or_else(|| {
             assign_boards(self.participants.iter(), None, &mut rand::thread_rng())
         });
-        let game = BughouseGame::new(self.rules.clone(), Role::ServerOrStandalone, &players);
+        let handicaps = if self.rules.match_rules.rated {
+            HashMap::new()
+        } else {
+            self.participants.iter().map(|p| (p.name.clone(), p.handicap)).collect()
+        };
+        let game = BughouseGame::new_with_handicaps(
+            self.rules.clone(),
+            Role::ServerOrStandalone,
+            &players,
+            &handicaps,
+        );
+        record_rules_popularity("game_started", &self.rules);
+        if self.rules.match_rules.rated {
+            self.refresh_ratings(ctx).await;
+        }
         let player_map = game.player_map();
         for p in self.participants.iter_mut() {
             p.active_player = player_map.get(&p.name).copied();
@@ -1621,9 +2402,37 @@ impl Match {
             turn_requests: Vec::new(),
             chalkboard: Chalkboard::new(),
             shared_wayback_turn_index: None,
+            abort_votes: enum_map! { _ => false },
         });
         self.broadcast(ctx, &self.make_game_start_event(ctx.now, None)).await;
         self.send_lobby_updated(ctx).await; // update readiness flags and player statuses
+        self.notify_followers(ctx).await;
+    }
+
+    // Notifies clients following one of the players unless they are already in this match.
+    async fn notify_followers(&self, ctx: &mut Context) {
+        // Following must not reveal private match IDs.
+        if !self.rules.match_rules.public {
+            return;
+        }
+        let Some(game_state) = &self.game_state else {
+            return;
+        };
+        let player_names: HashSet<_> =
+            game_state.game.players().into_iter().map(|p| p.name).collect();
+        for client in ctx.clients.map.read().await.values() {
+            let Some(player_name) = &client.following else {
+                continue;
+            };
+            if player_names.contains(player_name)
+                && client.match_id.as_ref() != Some(&self.match_id)
+            {
+                client.send(BughouseServerEvent::FollowedPlayerGameStarted {
+                    player_name: player_name.clone(),
+                    match_id: self.match_id.0.clone(),
+                });
+            }
+        }
     }
 
     fn init_scores(&mut self, teaming: Teaming) {
@@ -1641,10 +2450,49 @@ impl Match {
         }
     }
 
-    fn make_match_welcome_event(&self) -> BughouseServerEvent {
+    // Checks whether the match has reached its target after the latest game. Participants stay in
+    // the match after it's complete, so that they could chat and review the games.
+    async fn update_match_result(&mut self, ctx: &mut Context) {
+        let Some(target) = self.rules.match_rules.match_target else {
+            return;
+        };
+        let (Some(game_state), Some(scores)) = (&self.game_state, &self.scores) else {
+            return;
+        };
+        if game_state.game.is_active() {
+            return;
+        }
+        let games = self
+            .game_history
+            .iter()
+            .map(|record| &record.game)
+            .chain(std::iter::once(&game_state.game))
+            .collect_vec();
+        let Some(result) = match_result(target, scores, self.participants.iter(), &games) else {
+            return;
+        };
+        self.result = Some(result.clone());
+        self.first_game_countdown_since = None;
+        self.first_game_countdown_seconds_left = None;
+        self.scheduled_start = None;
+        self.reset_readiness();
+        self.send_lobby_updated(ctx).await;
+        self.broadcast(ctx, &BughouseServerEvent::MatchCompleted { result }).await;
+    }
+
+    async fn send_match_result(&self, ctx: &mut Context, client_id: ClientId) {
+        if let Some(result) = &self.result {
+            ctx.clients
+                .send(client_id, BughouseServerEvent::MatchCompleted { result: result.clone() })
+                .await;
+        }
+    }
+
+    fn make_match_welcome_event(&self, my_name: Option<String>) -> BughouseServerEvent {
         BughouseServerEvent::MatchWelcome {
             match_id: self.match_id.0.clone(),
             rules: self.rules.clone(),
+            my_name,
         }
     }
 
@@ -1673,15 +2521,109 @@ impl Match {
         }
     }
 
+    async fn fetch_rating(
+        &self, ctx: &Context, player_name: &str, is_registered_user: bool,
+    ) -> Option<u32> {
+        if !is_registered_user {
+            return None;
+        }
+        ctx.hooks.get_player_rating(player_name, &self.rules.chess_rules).await
+    }
+
+    // Ratings change after each rated game. Hooks cache ratings, so this is cheap.
+    async fn refresh_ratings(&mut self, ctx: &Context) {
+        let mut ratings = vec![];
+        for p in self.participants.iter() {
+            ratings.push(self.fetch_rating(ctx, &p.name, p.is_registered_user).await);
+        }
+        for (p, rating) in self.participants.iter_mut().zip_eq(ratings) {
+            p.rating = rating;
+        }
+    }
+
+    fn make_lobby_updated_event(&self, now: Instant) -> BughouseServerEvent {
+        let participants = self
+            .participants
+            .iter()
+            .map(|p| Participant {
+                rating: p.rating.filter(|_| p.show_rating),
+                ..p.clone()
+            })
+            .collect();
+        let countdown_elapsed = self.first_game_countdown_since.map(|t| now.duration_since(t));
+        let scheduled_start_in = self.scheduled_start.map(|t| t.saturating_duration_since(now));
+        let owner = self.owner.map(|id| self.participants[id].name.clone());
+        BughouseServerEvent::LobbyUpdated {
+            participants,
+            owner,
+            countdown_elapsed,
+            scheduled_start_in,
+        }
+    }
+
     async fn send_lobby_updated(&self, ctx: &mut Context) {
-        let participants = self.participants.iter().cloned().collect();
-        let countdown_elapsed = self.first_game_countdown_since.map(|t| ctx.now.duration_since(t));
-        self.broadcast(ctx, &BughouseServerEvent::LobbyUpdated { participants, countdown_elapsed })
-            .await;
+        self.broadcast(ctx, &self.make_lobby_updated_event(ctx.now)).await;
+    }
+
+    // Makes sure the owner is online. If the owner left, passes ownership to the participant who
+    // has been connected the longest among those who are still here. Returns whether the owner has
+    // changed.
+    fn update_owner(&mut self, now: Instant) -> bool {
+        let online_participant_ids: HashSet<_> = self.clients.values().copied().collect();
+        if self.owner.is_some_and(|id| online_participant_ids.contains(&id)) {
+            return false;
+        }
+        let new_owner = online_participant_ids
+            .into_iter()
+            .min_by_key(|&id| (self.participants.extra(id).session_start, id));
+        if new_owner.is_some() {
+            self.ownerless_since = None;
+        } else {
+            self.ownerless_since.get_or_insert(now);
+        }
+        let changed = new_owner != self.owner;
+        self.owner = new_owner;
+        changed
     }
 
     fn reset_readiness(&mut self) { self.participants.iter_mut().for_each(|p| p.is_ready = false); }
 
+    fn is_game_chat_limited(&self) -> bool {
+        self.rules.match_rules.limits_game_chat()
+            && self.game_state.as_ref().is_some_and(|s| s.game.is_active())
+    }
+
+    // Whether the message must not be shown to the participant until the game is over.
+    fn is_chat_message_withheld(&self, recipient: &Participant, message: &ChatMessage) -> bool {
+        if !self.is_game_chat_limited() {
+            return false;
+        }
+        // Only limit messages sent during the current game.
+        let game_index = self.game_state.as_ref().map(|s| s.game_index);
+        if message.game_index != game_index {
+            return false;
+        }
+        // Observers see everything.
+        let Some(recipient_team) = recipient.active_team() else {
+            return false;
+        };
+        // System messages are always shown.
+        let ChatMessageBody::Regular { sender, .. } = &message.body else {
+            return false;
+        };
+        match self.rules.match_rules.rated_game_chat {
+            RatedGameChat::Everyone => false,
+            RatedGameChat::TeamOnly => {
+                let sender_team = self
+                    .participants
+                    .find_by_name(sender)
+                    .and_then(|id| self.participants[id].active_team());
+                sender_team != Some(recipient_team)
+            }
+            RatedGameChat::Muted => true,
+        }
+    }
+
     // Improvement potential. Instead of traversing all messages for each client we could keep a map
     // from client_id to (messages, message_confirmations) and update it as we traverse the messages
     // in one go. In practice it doesn't matter much, since we either have one message (in a regular
@@ -1703,6 +2645,7 @@ impl Match {
                     ChatMessageBody::Regular { sender, .. } => &p.name == sender,
                     ChatMessageBody::FactionChanged { .. } => false,
                     ChatMessageBody::GameOver { .. } => false,
+                    ChatMessageBody::AbortRequested { .. } => false,
                     ChatMessageBody::NextGamePlayers { .. } => false,
                     ChatMessageBody::CannotStartGame { .. } => false,
                 };
@@ -1711,7 +2654,7 @@ impl Match {
                     ChatRecipientExpanded::FixedTeam(team) => p.team_affiliation() == Some(*team),
                     ChatRecipientExpanded::Participants(names) => names.contains(&p.name),
                 };
-                if is_sender || is_recipient {
+                if is_sender || (is_recipient && !self.is_chat_message_withheld(p, m)) {
                     messages.push(m.clone());
                 }
             }
@@ -1738,6 +2681,25 @@ impl Match {
     }
 }
 
+// "Guest-A", ..., "Guest-Z", "Guest-AA", "Guest-AB", ... Regular player names cannot contain the
+// word "guest", so the labels never clash with them.
+fn anonymous_guest_name(mut index: usize) -> String {
+    let mut letters = vec![];
+    loop {
+        letters.push(char::from(b'A' + (index % 26) as u8));
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    format!("{ANONYMOUS_GUEST_PREFIX}{}", letters.into_iter().rev().collect::<String>())
+}
+
+fn is_anonymous_guest_name(name: &str) -> bool {
+    name.strip_prefix(ANONYMOUS_GUEST_PREFIX)
+        .is_some_and(|s| !s.is_empty() && s.chars().all(|ch| ch.is_ascii_uppercase()))
+}
+
 fn get_registered_user_name(
     session_store: &SessionStore, session_id: Option<&SessionId>,
 ) -> Option<String> {
@@ -1754,11 +2716,39 @@ fn current_game_time(game_state: &GameState, now: Instant) -> Option<GameInstant
     }
 }
 
+// Returns current game time, making sure it doesn't precede the latest turn.
+//
+// Turn time is always determined by the server: clients never send timestamps, so there is nothing
+// to check against in the other direction. Server time is monotonic, so the clamping should never
+// kick in. But if it did, an earlier time would make `Clock` panic (it uses exact time measurement
+// on the server) or corrupt the clock, so it's safer to clamp and log the anomaly.
+fn monotonic_game_time(
+    game: &BughouseGame, game_start: Option<Instant>, now: Instant,
+) -> GameInstant {
+    let game_now = GameInstant::from_now_game_maybe_active(game_start, now);
+    let Some(last_turn) = game.last_turn_record() else {
+        return game_now;
+    };
+    if let (Ok(now_t), Ok(last_turn_t)) = (
+        MillisDuration::try_from(game_now.elapsed_since_start()),
+        MillisDuration::try_from(last_turn.time.elapsed_since_start()),
+    ) && now_t < last_turn_t
+    {
+        warn!(
+            "Game time {}ms precedes the latest turn at {}ms. Clamping.",
+            now_t.as_millis(),
+            last_turn_t.as_millis()
+        );
+        return last_turn.time;
+    }
+    game_now
+}
+
 fn resolve_one_turn(
     now: Instant, game_start: Option<Instant>, game: &mut BughouseGame,
     turn_requests: &mut Vec<TurnRequest>,
 ) -> Option<TurnRecord> {
-    let game_now = GameInstant::from_now_game_maybe_active(game_start, now);
+    let game_now = monotonic_game_time(game, game_start, now);
     let mut iter = mem::take(turn_requests).into_iter();
     while let Some(r) = iter.next() {
         match game.turn_mode_for_envoy(r.envoy) {
@@ -1793,8 +2783,8 @@ fn resolve_one_turn(
 
 fn update_board_assigment(
     ctx: &mut Context, rules: &Rules, teaming: Teaming, game_index: u64,
-    participants: &Participants, next_board_assignment: &mut Option<Vec<PlayerInGame>>,
-    chat: &mut ServerChat,
+    previous_game: &BughouseGame, participants: &Participants,
+    next_board_assignment: &mut Option<Vec<PlayerInGame>>, chat: &mut ServerChat,
 ) {
     let participants_status = verify_participants(rules, participants.iter());
     let need_to_seat_out = match participants_status {
@@ -1815,11 +2805,25 @@ fn update_board_assigment(
             return;
         }
     };
-    let next_players = assign_boards(
-        participants.iter(),
-        next_board_assignment.as_deref(),
-        &mut rand::thread_rng(),
-    );
+    let next_players = if rules.match_rules.winner_stays && teaming == Teaming::DynamicTeams {
+        let winner = match previous_game.status() {
+            BughouseGameStatus::Victory(team, _) => Some(team),
+            BughouseGameStatus::Active | BughouseGameStatus::Draw(_) => None,
+        };
+        assign_boards_winner_stays(
+            participants.iter(),
+            &previous_game.players(),
+            winner,
+            next_board_assignment.as_deref(),
+            &mut rand::thread_rng(),
+        )
+    } else {
+        assign_boards(
+            participants.iter(),
+            next_board_assignment.as_deref(),
+            &mut rand::thread_rng(),
+        )
+    };
     if next_board_assignment.as_ref() == Some(&next_players) {
         return;
     }
@@ -1850,11 +2854,27 @@ fn update_on_game_over(
 ) -> GameUpdate {
     assert!(game_end.is_none());
     *game_end = Some(ctx.now);
+    record_rules_popularity("game_finished", rules);
     turn_requests.clear();
     let player_map = game.player_map();
     for p in participants.iter_mut() {
         p.active_player = None;
     }
+    chat.add(
+        Some(game_index),
+        ctx.utc_now,
+        ChatRecipientExpanded::All,
+        ChatMessageBody::GameOver { outcome: game.outcome() },
+    );
+    // Aborted games don't count: they affect neither scores nor who plays next, and they have no
+    // result to record.
+    if game.status() == BughouseGameStatus::Draw(DrawReason::Aborted) {
+        return GameUpdate::GameOver {
+            time: game_now,
+            game_status: game.status(),
+            scores: scores.clone(),
+        };
+    }
     let registered_users: HashSet<_> = participants
         .iter()
         .filter(|p| p.is_registered_user)
@@ -1906,17 +2926,12 @@ fn update_on_game_over(
         });
     }
     post_game_update_participant_counters(participants, |name| player_map.get(name).copied());
-    chat.add(
-        Some(game_index),
-        ctx.utc_now,
-        ChatRecipientExpanded::All,
-        ChatMessageBody::GameOver { outcome: game.outcome() },
-    );
     update_board_assigment(
         ctx,
         rules,
         teaming,
         game_index,
+        game,
         participants,
         next_board_assignment,
         chat,
@@ -1928,6 +2943,24 @@ fn update_on_game_over(
     }
 }
 
+// Whether the game is early enough to be aborted.
+fn abort_window_open(game: &BughouseGame, window: u8) -> bool {
+    BughouseBoard::iter().all(|board_idx| {
+        let num_turns = game.turn_log().iter().filter(|r| r.envoy.board_idx == board_idx).count();
+        num_turns <= window.into()
+    })
+}
+
+fn record_rules_popularity(event: &str, rules: &Rules) {
+    let chess_rules = &rules.chess_rules;
+    let preset = chess_rules.get_preset();
+    let preset = preset.as_ref().map_or("Custom", |preset| preset.as_ref());
+    RULES_PRESET_COUNTER.with_label_values(&[event, preset]).inc();
+    for variant in chess_rules.variants() {
+        CHESS_VARIANT_COUNTER.with_label_values(&[event, variant.as_ref()]).inc();
+    }
+}
+
 fn player_turn_requests(
     turn_requests: &[TurnRequest], player: BughousePlayer,
 ) -> Vec<(BughouseBoard, TurnInput)> {
@@ -1977,6 +3010,14 @@ async fn process_get_archive_game_bpng(ctx: &mut Context, client_id: ClientId, g
     }
 }
 
+async fn process_follow_player(
+    ctx: &mut Context, client_id: ClientId, player_name: Option<String>,
+) {
+    if let Some(ref mut client) = ctx.clients.map.write().await.get_mut(&client_id) {
+        client.following = player_name;
+    }
+}
+
 async fn process_report_performance(ctx: &Context, perf: BughouseClientPerformance) {
     let hooks = Arc::clone(&ctx.hooks);
     hooks.record_client_performance(&perf).await;
@@ -2014,12 +3055,19 @@ fn event_name(event: &IncomingEvent) -> &'static str {
             BughouseClientEvent::NewMatch { .. } => "Client_NewMatch",
             BughouseClientEvent::Join { .. } => "Client_Join",
             BughouseClientEvent::HotReconnect { .. } => "Client_HotReconnect",
+            BughouseClientEvent::Spectate { .. } => "Client_Spectate",
             BughouseClientEvent::SetFaction { .. } => "Client_SetFaction",
             BughouseClientEvent::SetTurns { .. } => "Client_SetTurns",
             BughouseClientEvent::MakeTurn { .. } => "Client_MakeTurn",
             BughouseClientEvent::CancelPreturn { .. } => "Client_CancelPreturn",
             BughouseClientEvent::Resign => "Client_Resign",
+            BughouseClientEvent::ResignBoard => "Client_ResignBoard",
+            BughouseClientEvent::RequestAbort => "Client_RequestAbort",
             BughouseClientEvent::SetReady { .. } => "Client_SetReady",
+            BughouseClientEvent::SetShowRating { .. } => "Client_SetShowRating",
+            BughouseClientEvent::RequestRematch { .. } => "Client_RequestRematch",
+            BughouseClientEvent::SetHandicap { .. } => "Client_SetHandicap",
+            BughouseClientEvent::TransferMatchOwnership { .. } => "Client_TransferMatchOwnership",
             BughouseClientEvent::LeaveMatch => "Client_LeaveMatch",
             BughouseClientEvent::LeaveServer => "Client_LeaveServer",
             BughouseClientEvent::SendChatMessage { .. } => "Client_SendChatMessage",
@@ -2027,6 +3075,7 @@ fn event_name(event: &IncomingEvent) -> &'static str {
             BughouseClientEvent::SetSharedWayback { .. } => "Client_SetSharedWayback",
             BughouseClientEvent::GetArchiveGameList { .. } => "Client_GetArchiveGameList",
             BughouseClientEvent::GetArchiveGameBpgn { .. } => "Client_GetArchiveGameBpgn",
+            BughouseClientEvent::FollowPlayer { .. } => "Client_FollowPlayer",
             BughouseClientEvent::ReportPerformace(_) => "Client_ReportPerformace",
             BughouseClientEvent::ReportError(_) => "Client_ReportError",
             BughouseClientEvent::Ping => "Client_Ping",
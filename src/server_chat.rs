@@ -1,6 +1,6 @@
 use std::collections::{HashSet, VecDeque};
 
-use crate::chat::{ChatMessage, ChatMessageBody, MAX_CHAT_MESSAGE_LENGTH, MAX_CHAT_MESSAGES};
+use crate::chat::{ChatMessage, ChatMessageBody, MAX_CHAT_MESSAGE_LENGTH};
 use crate::player::Team;
 use crate::utc_time::UtcDateTime;
 
@@ -21,17 +21,20 @@ pub enum ChatRecipientExpanded {
     Participants(HashSet<String>),
 }
 
+// Keeps the latest `max_messages` messages, so that participants who join later can catch up.
 #[derive(Clone, Debug)]
 pub struct ServerChat {
     messages: VecDeque<(ChatRecipientExpanded, ChatMessage)>,
+    max_messages: usize,
     first_new_message_id: u64,
     next_id: u64,
 }
 
 impl ServerChat {
-    pub fn new() -> Self {
+    pub fn new(max_messages: usize) -> Self {
         ServerChat {
             messages: VecDeque::new(),
+            max_messages,
             first_new_message_id: 0,
             next_id: 0,
         }
@@ -41,9 +44,11 @@ impl ServerChat {
     pub fn reset_first_new_message_id(&mut self) { self.first_new_message_id = self.next_id; }
 
     pub fn messages_since(
-        &self, start: usize,
+        &self, start_id: u64,
     ) -> impl Iterator<Item = &(ChatRecipientExpanded, ChatMessage)> {
-        self.messages.range(start..)
+        // Message IDs are consecutive, but old messages could've been evicted.
+        let oldest_id = self.messages.front().map_or(self.next_id, |(_, m)| m.message_id);
+        self.messages.range(start_id.saturating_sub(oldest_id) as usize..)
     }
     pub fn all_messages(&self) -> impl Iterator<Item = &(ChatRecipientExpanded, ChatMessage)> {
         self.messages.iter()
@@ -64,6 +69,7 @@ impl ServerChat {
             }
             ChatMessageBody::FactionChanged { .. } => {}
             ChatMessageBody::GameOver { .. } => {}
+            ChatMessageBody::AbortRequested { .. } => {}
             ChatMessageBody::NextGamePlayers { .. } => {}
             ChatMessageBody::CannotStartGame { .. } => {}
         }
@@ -71,7 +77,7 @@ impl ServerChat {
         self.next_id += 1;
         let message = ChatMessage { message_id, game_index, time, body };
         self.messages.push_back((recipient_expanded, message));
-        while self.messages.len() > MAX_CHAT_MESSAGES {
+        while self.messages.len() > self.max_messages {
             self.messages.pop_front();
         }
     }
@@ -87,7 +93,7 @@ impl ServerChat {
 #[macro_export]
 macro_rules! fetch_new_chat_messages {
     ($chat:expr) => {{
-        let start = $chat.first_new_message_id() as usize;
+        let start = $chat.first_new_message_id();
         $chat.reset_first_new_message_id();
         $chat.messages_since(start)
     }};
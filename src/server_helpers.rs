@@ -3,12 +3,16 @@
 //   - Can potentially blow up WASM size, e.g. due to new Carge dependencies. Rust linker
 //     seems to do a good job filtering these out, but it still feels safer. And improves
 //     client build time.
+
+use crate::player_name::PlayerNameError;
+
+
 pub trait ServerHelpers {
-    fn validate_player_name(&self, name: &str) -> Result<(), String>;
+    fn validate_player_name(&self, name: &str) -> Result<(), PlayerNameError>;
 }
 
 pub struct TestServerHelpers;
 
 impl ServerHelpers for TestServerHelpers {
-    fn validate_player_name(&self, _name: &str) -> Result<(), String> { Ok(()) }
+    fn validate_player_name(&self, _name: &str) -> Result<(), PlayerNameError> { Ok(()) }
 }
@@ -4,6 +4,7 @@ use async_trait::async_trait;
 
 use crate::event::{BughouseClientPerformance, FinishedGameDescription};
 use crate::game::BughouseGame;
+use crate::rules::ChessRules;
 use crate::utc_time::UtcDateTime;
 
 
@@ -14,10 +15,19 @@ pub trait ServerHooks {
         &self, game: &BughouseGame, registered_users: &HashSet<String>,
         game_start_time: UtcDateTime, game_end_time: UtcDateTime, round: u64,
     );
+    // Called for games that were still in progress when the server was forcefully shut down, so
+    // that they are not lost. Awaited before exiting.
+    async fn record_interrupted_game(
+        &self, game: &BughouseGame, game_start_time: UtcDateTime,
+        game_interruption_time: UtcDateTime, round: u64,
+    );
     async fn get_games_by_user(
         &self, user_name: &str,
     ) -> Result<Vec<FinishedGameDescription>, String>;
     async fn get_game_bpgn(&self, game_id: i64) -> Result<String, String>;
+    // Current rating of a registered user in the rating pool for the given rules. Called whenever
+    // a registered user joins a match and before each rated game, so it should be cheap.
+    async fn get_player_rating(&self, user_name: &str, rules: &ChessRules) -> Option<u32>;
 }
 
 pub struct NoopServerHooks {}
@@ -30,6 +40,11 @@ impl ServerHooks for NoopServerHooks {
         _game_start_time: UtcDateTime, _game_end_time: UtcDateTime, _round: u64,
     ) {
     }
+    async fn record_interrupted_game(
+        &self, _game: &BughouseGame, _game_start_time: UtcDateTime,
+        _game_interruption_time: UtcDateTime, _round: u64,
+    ) {
+    }
     async fn get_games_by_user(
         &self, _user_name: &str,
     ) -> Result<Vec<FinishedGameDescription>, String> {
@@ -38,4 +53,5 @@ impl ServerHooks for NoopServerHooks {
     async fn get_game_bpgn(&self, _game_id: i64) -> Result<String, String> {
         Err("Server hooks not available".to_owned())
     }
+    async fn get_player_rating(&self, _user_name: &str, _rules: &ChessRules) -> Option<u32> { None }
 }
@@ -1,17 +1,18 @@
 use std::collections::HashMap;
+use std::fmt;
 
-use enum_map::EnumMap;
+use enum_map::{EnumMap, enum_map};
 use itertools::Itertools;
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 
 use crate::board::{BoardCastlingRights, Reserve};
-use crate::coord::{Col, Coord, Row};
+use crate::coord::{Col, Coord, Row, SubjectiveRow};
 use crate::force::Force;
 use crate::game::BughouseBoard;
 use crate::grid::Grid;
-use crate::piece::{PieceForce, PieceId, PieceKind, PieceOnBoard, PieceOrigin};
+use crate::piece::{CastleDirection, PieceForce, PieceId, PieceKind, PieceOnBoard, PieceOrigin};
 use crate::rules::{ChessRules, FairyPieces, StartingPosition};
 
 
@@ -44,6 +45,20 @@ impl EffectiveStartingPosition {
     }
 }
 
+// A reason why a manually specified position could not have occurred in a real game.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SetupProblem {
+    WrongNumberOfKings { force: Force, count: usize },
+    PawnOnBackRank { coord: Coord },
+    InvalidEnPassantTarget { coord: Coord },
+    InvalidCastlingRights { force: Force, dir: CastleDirection },
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvalidSetup {
+    pub problems: Vec<SetupProblem>,
+}
+
 fn new_white(kind: PieceKind) -> PieceOnBoard {
     PieceOnBoard::new(PieceId::tmp(), kind, PieceOrigin::Innate, PieceForce::White)
 }
@@ -145,3 +160,141 @@ pub fn generate_starting_grid(
     assign_piece_ids(&mut grid, piece_id);
     grid
 }
+
+// Checks that the position is internally consistent. Reports all problems rather than the first
+// one, so that a hand-written position could be fixed in one go.
+pub fn validate_setup(rules: &ChessRules, setup: &BoardSetup) -> Result<(), InvalidSetup> {
+    let grid = &setup.grid;
+    let shape = grid.shape();
+    let mut problems = vec![];
+
+    let mut kings = enum_map! { _ => vec![] };
+    for coord in shape.coords() {
+        if let Some(piece) = grid[coord] {
+            if let Ok(force) = Force::try_from(piece.force) {
+                if piece.kind == PieceKind::King {
+                    kings[force].push(coord);
+                }
+            }
+        }
+    }
+    // In regicide variants kings can be captured (and, in Koedem, dropped), so any number goes.
+    if !rules.regicide() {
+        for (force, coords) in kings.iter() {
+            if coords.len() != 1 {
+                problems.push(SetupProblem::WrongNumberOfKings { force, count: coords.len() });
+            }
+        }
+    }
+
+    // Pawns are promoted upon reaching the last rank. A pawn cannot get to its own first rank by
+    // moving, but it can be dropped there if the rules allow.
+    let first_rank_drops_allowed = rules
+        .bughouse_rules
+        .as_ref()
+        .is_some_and(|r| r.pawn_drop_ranks.min == SubjectiveRow::first());
+    for coord in shape.coords() {
+        let Some(piece) = grid[coord] else {
+            continue;
+        };
+        let Ok(force) = Force::try_from(piece.force) else {
+            continue;
+        };
+        let is_promotion_rank = coord.row == SubjectiveRow::last(shape).to_row(shape, force);
+        let is_first_rank = coord.row == SubjectiveRow::first().to_row(shape, force);
+        if piece.kind == PieceKind::Pawn
+            && (is_promotion_rank || (is_first_rank && !first_rank_drops_allowed))
+        {
+            problems.push(SetupProblem::PawnOnBackRank { coord });
+        }
+    }
+
+    if let Some(coord) = setup.en_passant_target {
+        // In duck chess the player who moved the pawn also places the duck afterwards.
+        let movers = if rules.duck_chess {
+            vec![Force::White, Force::Black]
+        } else {
+            vec![setup.active_force.opponent()]
+        };
+        if !movers.into_iter().any(|mover| is_valid_en_passant_target(grid, coord, mover)) {
+            problems.push(SetupProblem::InvalidEnPassantTarget { coord });
+        }
+    }
+
+    for (force, rights) in setup.castling_rights.iter() {
+        let home_row = SubjectiveRow::first().to_row(shape, force);
+        for (dir, col) in rights.iter() {
+            let Some(col) = *col else {
+                continue;
+            };
+            let king = match kings[force].as_slice() {
+                &[king] if king.row == home_row => Some(king),
+                _ => None,
+            };
+            let rook_coord = Coord::new(home_row, col);
+            let is_own_rook = shape.contains_coord(rook_coord)
+                && grid[rook_coord].is_some_and(|piece| {
+                    piece.kind == PieceKind::Rook && piece.force == force.into()
+                });
+            let is_on_correct_side = king.is_some_and(|king| match dir {
+                CastleDirection::ASide => col < king.col,
+                CastleDirection::HSide => col > king.col,
+            });
+            if !is_own_rook || !is_on_correct_side {
+                problems.push(SetupProblem::InvalidCastlingRights { force, dir });
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidSetup { problems })
+    }
+}
+
+// A double pawn move by `mover` must have crossed `coord`: the pawn stands right behind it and both
+// the target and the starting square are empty.
+fn is_valid_en_passant_target(grid: &Grid, coord: Coord, mover: Force) -> bool {
+    let shape = grid.shape();
+    if !shape.contains_coord(coord)
+        || SubjectiveRow::from_row(shape, coord.row, mover) != SubjectiveRow::from_one_based(3)
+    {
+        return false;
+    }
+    let is_empty = |row: SubjectiveRow| {
+        grid[Coord::new(row.to_row(shape, mover), coord.col)]
+            .is_none_or(|piece| piece.kind == PieceKind::Duck)
+    };
+    let pawn_coord = Coord::new(SubjectiveRow::from_one_based(4).to_row(shape, mover), coord.col);
+    let has_pawn = grid[pawn_coord]
+        .is_some_and(|piece| piece.kind == PieceKind::Pawn && piece.force == mover.into());
+    has_pawn
+        && is_empty(SubjectiveRow::from_one_based(3))
+        && is_empty(SubjectiveRow::from_one_based(2))
+}
+
+impl fmt::Display for SetupProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetupProblem::WrongNumberOfKings { force, count } => {
+                write!(f, "{force:?} has {count} kings, expected 1")
+            }
+            SetupProblem::PawnOnBackRank { coord } => {
+                write!(f, "pawn on back rank at {}", coord.to_debug_algebraic())
+            }
+            SetupProblem::InvalidEnPassantTarget { coord } => {
+                write!(f, "invalid en passant target {}", coord.to_debug_algebraic())
+            }
+            SetupProblem::InvalidCastlingRights { force, dir } => {
+                write!(f, "{force:?} cannot have {dir:?} castling rights")
+            }
+        }
+    }
+}
+
+impl fmt::Display for InvalidSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid position: {}", self.problems.iter().join("; "))
+    }
+}
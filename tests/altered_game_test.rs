@@ -4,12 +4,14 @@ use std::time::Duration;
 
 use BughouseBoard::{A, B};
 use bughouse_chess::altered_game::{
-    AlteredGame, ReservePieceHighlight, SquareHighlight, TurnHighlightFamily, TurnHighlightItem,
-    TurnHighlightLayer, TurnInputResult, WaybackDestination,
+    AlteredGame, Location, PartialTurnInput, ReservePieceHighlight, SquareHighlight,
+    TurnHighlightFamily, TurnHighlightItem, TurnHighlightLayer, TurnInputResult,
+    WaybackDestination,
 };
 use bughouse_chess::board::{TurnError, TurnInput, VictoryReason};
 use bughouse_chess::clock::GameInstant;
 use bughouse_chess::coord::Coord;
+use bughouse_chess::display::{DisplayBoard, get_display_board_index};
 use bughouse_chess::envoy;
 use bughouse_chess::force::Force;
 use bughouse_chess::game::{
@@ -71,6 +73,13 @@ fn fog_of_war_bughouse_game() -> BughouseGame {
     BughouseGame::new(rules, Role::Client, &sample_bughouse_players())
 }
 
+fn rated_game(preturn_limit: Option<u8>) -> BughouseGame {
+    let mut rules = default_rules();
+    rules.match_rules.rated = true;
+    rules.match_rules.rated_preturn_limit = preturn_limit;
+    BughouseGame::new(rules, Role::Client, &sample_bughouse_players())
+}
+
 fn koedem_game() -> BughouseGame {
     let mut rules = default_rules();
     rules.bughouse_rules_mut().unwrap().koedem = true;
@@ -203,6 +212,45 @@ fn preturn_invalidated() {
     assert!(alt_game.local_game().board(A).grid()[Coord::E5].is(piece!(Black Pawn)));
 }
 
+#[test]
+fn predrop_onto_vacated_square() {
+    let game_log = "1B.e4 1b.d5 2B.exd5 1A.e4 1a.a6";
+    let mut game = default_game();
+    replay_bughouse_log(&mut game, game_log, Duration::ZERO).unwrap();
+
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), game);
+    alt_game.try_local_turn(A, alg("P@e4"), T0).unwrap();
+    assert!(alt_game.local_game().board(A).grid()[Coord::E4].is(piece!(Black Pawn)));
+
+    alt_game.apply_remote_turn(envoy!(White A), &alg("e5"), T0).unwrap();
+    assert_eq!(alt_game.local_turns().len(), 1);
+    assert!(alt_game.local_game().board(A).grid()[Coord::E4].is(piece!(Black Pawn)));
+    assert!(alt_game.local_game().board(A).grid()[Coord::E5].is(piece!(White Pawn)));
+}
+
+#[test]
+fn predrop_invalidated() {
+    let game_log = "1B.e4 1b.d5 2B.exd5 1A.e4 1a.a6";
+    let mut game = default_game();
+    replay_bughouse_log(&mut game, game_log, Duration::ZERO).unwrap();
+
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), game);
+    alt_game.try_local_turn(A, alg("P@e4"), T0).unwrap();
+    alt_game.apply_remote_turn(envoy!(White A), &alg("d3"), T0).unwrap();
+    assert!(alt_game.local_turns().is_empty());
+    assert!(alt_game.local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+}
+
+#[test]
+fn predrop_onto_own_piece_forbidden() {
+    let game_log = "1B.e4 1b.d5 2B.exd5 1A.e4 1a.a6";
+    let mut game = default_game();
+    replay_bughouse_log(&mut game, game_log, Duration::ZERO).unwrap();
+
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), game);
+    assert_eq!(alt_game.try_local_turn(A, alg("P@a6"), T0), Err(TurnError::DropBlocked));
+}
+
 #[test]
 fn preturn_after_local_turn_persistent() {
     let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
@@ -228,6 +276,39 @@ fn two_preturns_forbidden() {
     );
 }
 
+#[test]
+fn rated_preturn_limit_caps_preturns() {
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), rated_game(Some(1)));
+    alt_game.try_local_turn(A, drag_move!(E2 -> E4), T0).unwrap();
+    alt_game.try_local_turn(A, drag_move!(D2 -> D4), T0).unwrap();
+    assert_eq!(
+        alt_game.try_local_turn(A, drag_move!(F2 -> F4), T0),
+        Err(TurnError::PreturnLimitReached)
+    );
+}
+
+#[test]
+fn rated_preturn_limit_zero_disables_preturns() {
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), rated_game(Some(0)));
+    alt_game.try_local_turn(A, drag_move!(E2 -> E4), T0).unwrap();
+    assert_eq!(
+        alt_game.try_local_turn(A, drag_move!(D2 -> D4), T0),
+        Err(TurnError::PreturnLimitReached)
+    );
+    alt_game.apply_remote_turn(envoy!(White A), &drag_move!(E2 -> E4), T0).unwrap();
+    alt_game.apply_remote_turn(envoy!(Black A), &drag_move!(E7 -> E5), T0).unwrap();
+    alt_game.try_local_turn(A, drag_move!(D2 -> D4), T0).unwrap();
+}
+
+#[test]
+fn unrated_game_ignores_preturn_limit() {
+    let mut rules = default_rules();
+    rules.match_rules.rated_preturn_limit = Some(0);
+    let game = BughouseGame::new(rules, Role::Client, &sample_bughouse_players());
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), game);
+    alt_game.try_local_turn(A, drag_move!(E7 -> E5), T0).unwrap();
+}
+
 #[test]
 fn turn_highlights() {
     let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
@@ -246,6 +327,34 @@ fn turn_highlights() {
     ]);
 }
 
+// Drops don't have a source square, so they must have a highlight of their own.
+#[test]
+fn drop_turn_highlights() {
+    let game_log = "1B.e4 1b.d5 2B.exd5 1A.e4 1a.a6 2A.d3 2a.P@e5";
+    let mut game = default_game();
+    replay_bughouse_log(&mut game, game_log, Duration::ZERO).unwrap();
+    let alt_game = AlteredGame::new(as_single_player(envoy!(White A)), game);
+    assert_eq!(square_highlights_sorted(&alt_game), vec![
+        square_highlight!(A E5 : BelowFog LatestTurn Drop),
+        square_highlight!(B E4 : BelowFog LatestTurn MoveFrom),
+        square_highlight!(B D5 : BelowFog LatestTurn Capture),
+    ]);
+}
+
+#[test]
+fn predrop_highlights() {
+    let game_log = "1B.e4 1b.d5 2B.exd5 1A.e4 1a.a6";
+    let mut game = default_game();
+    replay_bughouse_log(&mut game, game_log, Duration::ZERO).unwrap();
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), game);
+    alt_game.try_local_turn(A, alg("P@d5"), T0).unwrap();
+    assert_eq!(square_highlights_sorted(&alt_game), vec![
+        square_highlight!(A D5 : BelowFog Preturn Drop),
+        square_highlight!(B E4 : BelowFog LatestTurn MoveFrom),
+        square_highlight!(B D5 : BelowFog LatestTurn Capture),
+    ]);
+}
+
 #[test]
 fn multiple_turn_highlights_per_square() {
     let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), accolade_game());
@@ -312,6 +421,16 @@ fn click_moves() {
     assert_eq!(alt_game.click(A, loc!(D5)), TurnInputResult::Turn((A, drag_move!(Pawn @ D5))));
 }
 
+#[test]
+fn abort_click_move() {
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
+    assert_eq!(alt_game.click(A, loc!(E2)), TurnInputResult::Noop);
+    alt_game.abort_click_move();
+    assert!(square_highlights_sorted(&alt_game).is_empty());
+    assert_eq!(alt_game.click(A, loc!(E4)), TurnInputResult::Noop);
+    assert!(square_highlights_sorted(&alt_game).is_empty());
+}
+
 #[test]
 fn cannot_make_turns_on_other_board() {
     let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), default_game());
@@ -325,6 +444,29 @@ fn double_play() {
     alt_game.try_local_turn(B, drag_move!(D7 -> D5), T0).unwrap();
 }
 
+// A double player should see their preturns on both boards.
+#[test]
+fn double_play_preturn_highlights() {
+    let mut alt_game = AlteredGame::new(as_double_player(Team::Red), default_game());
+    alt_game.try_local_turn(A, alg("e4"), T0).unwrap();
+    alt_game.try_local_turn(A, alg("d4"), T0).unwrap();
+    alt_game.try_local_turn(B, alg("e5"), T0).unwrap();
+    let highlights = square_highlights_sorted(&alt_game);
+    assert_eq!(highlights, vec![
+        square_highlight!(A D2 : BelowFog Preturn MoveFrom),
+        square_highlight!(A D4 : BelowFog Preturn MoveTo),
+        square_highlight!(B E5 : BelowFog Preturn MoveTo),
+        square_highlight!(B E7 : BelowFog Preturn MoveFrom),
+    ]);
+    let perspective = alt_game.perspective();
+    let mut display_boards: Vec<_> = highlights
+        .iter()
+        .map(|h| get_display_board_index(h.board_idx, perspective))
+        .collect();
+    display_boards.dedup();
+    assert_eq!(display_boards, vec![DisplayBoard::Primary, DisplayBoard::Secondary]);
+}
+
 #[test]
 fn stealing_promotion() {
     let mut alt_game =
@@ -393,6 +535,58 @@ fn stealing_promotion_cannot_move_pawn_onto_piece() {
     assert!(alt_game.local_game().board(A).grid()[Coord::G8].is(piece!(Black Knight)));
 }
 
+// Stealing the queen on board B would expose the king, so the knight is stolen instead.
+#[test]
+fn auto_steal_promotion_picks_legal_target() {
+    let game_str = "
+        k . . . . . . .     N . . . . . . K
+        . . . . . . P .     . . . . . . . Q
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . K . . .     k . . . . . . r
+    ";
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().promotion = Promotion::Steal;
+    let game = parse_ascii_bughouse(rules, Role::Client, game_str).unwrap();
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), game);
+    let knight = alt_game.local_game().board(B).grid()[Coord::H1].unwrap();
+    alt_game.start_drag_piece(A, loc!(G7)).unwrap();
+    assert_eq!(alt_game.drag_piece_drop(A, Coord::G8), TurnInputResult::Noop);
+    assert_eq!(
+        alt_game.auto_steal_promotion(),
+        TurnInputResult::Turn((A, drag_move!(G7 -> G8 = knight)))
+    );
+}
+
+#[test]
+fn auto_steal_promotion_without_targets() {
+    let game_str = "
+        k . . . . . . .     . . . . . . . K
+        . . . . . . P .     . . . . . . . Q
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . K . . .     k . . . . . . r
+    ";
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().promotion = Promotion::Steal;
+    let game = parse_ascii_bughouse(rules, Role::Client, game_str).unwrap();
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), game);
+    alt_game.start_drag_piece(A, loc!(G7)).unwrap();
+    assert_eq!(alt_game.drag_piece_drop(A, Coord::G8), TurnInputResult::Noop);
+    assert_eq!(alt_game.auto_steal_promotion(), TurnInputResult::Noop);
+    // The user can still choose the target manually.
+    assert!(matches!(
+        alt_game.partial_turn_input(),
+        Some((A, PartialTurnInput::StealPromotion { .. }))
+    ));
+}
+
 #[test]
 // Stealing promotion is unique in that it can make a local in-order turn invalid.
 fn stealing_promotion_invalidates_local_turn() {
@@ -583,6 +777,45 @@ fn wayback_navigation() {
     assert_eq!(alt_game.wayback_to(Previous, Some(A)), Some(TurnIndex(2)));
 }
 
+#[test]
+fn wayback_my_turns() {
+    use WaybackDestination::*;
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
+    alt_game.apply_remote_turn(envoy!(White A), &alg("a4"), T0).unwrap(); // TurnIndex == 0
+    alt_game.apply_remote_turn(envoy!(White B), &alg("h4"), T0).unwrap(); // TurnIndex == 1
+    alt_game.apply_remote_turn(envoy!(Black A), &alg("a5"), T0).unwrap(); // TurnIndex == 2
+    alt_game.apply_remote_turn(envoy!(White A), &alg("b4"), T0).unwrap(); // TurnIndex == 3
+    alt_game.apply_remote_turn(envoy!(Black B), &alg("h5"), T0).unwrap(); // TurnIndex == 4
+    alt_game.apply_remote_turn(envoy!(Black A), &alg("b5"), T0).unwrap(); // TurnIndex == 5
+    alt_game.set_status(BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation), T0);
+
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(3)));
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(0)));
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(0)));
+    assert_eq!(alt_game.wayback_to(MyNext, None), Some(TurnIndex(3)));
+    assert_eq!(alt_game.wayback_to(MyNext, None), Some(TurnIndex(3)));
+
+    // Starting from a turn made by somebody else.
+    assert_eq!(alt_game.wayback_to(Index(Some(TurnIndex(2))), None), Some(TurnIndex(2)));
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(0)));
+    assert_eq!(alt_game.wayback_to(Index(Some(TurnIndex(2))), None), Some(TurnIndex(2)));
+    assert_eq!(alt_game.wayback_to(MyNext, None), Some(TurnIndex(3)));
+}
+
+#[test]
+fn wayback_my_turns_observer() {
+    use WaybackDestination::*;
+    let mut alt_game = AlteredGame::new(BughouseParticipant::default_observer(), default_game());
+    alt_game.apply_remote_turn(envoy!(White A), &alg("a4"), T0).unwrap(); // TurnIndex == 0
+    alt_game.apply_remote_turn(envoy!(White B), &alg("h4"), T0).unwrap(); // TurnIndex == 1
+    alt_game.apply_remote_turn(envoy!(Black A), &alg("a5"), T0).unwrap(); // TurnIndex == 2
+    alt_game.set_status(BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation), T0);
+
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(1)));
+    assert_eq!(alt_game.wayback_to(MyPrevious, None), Some(TurnIndex(0)));
+    assert_eq!(alt_game.wayback_to(MyNext, None), Some(TurnIndex(1)));
+}
+
 #[test]
 fn wayback_turn_highlight() {
     let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
@@ -620,7 +853,7 @@ fn wayback_affects_fog_of_war() {
     alt_game.apply_remote_turn(envoy!(White A), &alg("Qxe8"), T0).unwrap();
     assert_eq!(
         alt_game.status(),
-        BughouseGameStatus::Victory(Team::Red, VictoryReason::Checkmate)
+        BughouseGameStatus::Victory(Team::Red, VictoryReason::KingCaptured)
     );
     assert!(!alt_game.fog_of_war_area(A).contains(&Coord::D8));
     alt_game.wayback_to(WaybackDestination::Index(Some(TurnIndex(2))), None);
@@ -640,3 +873,123 @@ fn invalidate_own_preturn() {
     alt_game.try_local_turn(B, drag_move!(C7 -> E8), T0).unwrap();
     alt_game.local_game();
 }
+
+#[test]
+fn study_mode_ignores_turn_order() {
+    let mut alt_game = AlteredGame::new_study(default_game());
+    alt_game.try_study_turn(A, alg("e4")).unwrap();
+    alt_game.try_study_turn(A, alg("d4")).unwrap();
+    alt_game.try_study_turn(B, alg("e5")).unwrap();
+    alt_game.try_study_turn(B, alg("d5")).unwrap();
+    alt_game.try_study_turn(A, alg("Nf6")).unwrap();
+    let game = alt_game.local_game();
+    assert!(game.board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+    assert!(game.board(A).grid()[Coord::D4].is(piece!(White Pawn)));
+    assert!(game.board(A).grid()[Coord::F6].is(piece!(Black Knight)));
+    assert!(game.board(B).grid()[Coord::E5].is(piece!(Black Pawn)));
+    assert!(game.board(B).grid()[Coord::D5].is(piece!(Black Pawn)));
+}
+
+#[test]
+fn study_mode_drag_any_piece() {
+    let mut alt_game = AlteredGame::new_study(default_game());
+    for (from, to) in [
+        (Coord::E7, Coord::E5),
+        (Coord::D7, Coord::D5),
+        (Coord::G1, Coord::F3),
+    ] {
+        alt_game.start_drag_piece(A, Location::Square(from)).unwrap();
+        let TurnInputResult::Turn((board_idx, turn_input)) = alt_game.drag_piece_drop(A, to) else {
+            panic!("Drag from {from:?} to {to:?} failed");
+        };
+        alt_game.try_study_turn(board_idx, turn_input).unwrap();
+    }
+    let game = alt_game.local_game();
+    assert!(game.board(A).grid()[Coord::E5].is(piece!(Black Pawn)));
+    assert!(game.board(A).grid()[Coord::D5].is(piece!(Black Pawn)));
+    assert!(game.board(A).grid()[Coord::F3].is(piece!(White Knight)));
+}
+
+#[test]
+fn study_mode_rejects_illegal_turns() {
+    let mut alt_game = AlteredGame::new_study(default_game());
+    assert!(alt_game.try_study_turn(A, alg("Ke2")).is_err());
+    assert!(alt_game.try_study_turn(A, alg("e5")).is_ok());
+    alt_game.start_drag_piece(A, loc!(E5)).unwrap();
+    let TurnInputResult::Turn((board_idx, turn_input)) = alt_game.drag_piece_drop(A, Coord::E3)
+    else {
+        panic!("Drag failed");
+    };
+    assert_eq!(
+        alt_game.try_study_turn(board_idx, turn_input),
+        Err(TurnError::ImpossibleTrajectory)
+    );
+}
+
+#[test]
+fn study_mode_edit_reserve() {
+    let mut alt_game = AlteredGame::new_study(default_game());
+    alt_game.set_study_reserve(A, Force::Black, PieceKind::Knight, 1);
+    alt_game.try_study_turn(A, alg("N@e4")).unwrap();
+    assert!(alt_game.local_game().board(A).grid()[Coord::E4].is(piece!(Black Knight)));
+    assert_eq!(alt_game.try_study_turn(A, alg("N@e5")), Err(TurnError::DropPieceMissing));
+}
+
+#[test]
+fn scratch_turns_isolated() {
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(Black A)), default_game());
+    alt_game.apply_remote_turn(envoy!(White A), &alg("e4"), T0).unwrap();
+    alt_game.try_local_turn(A, alg("e5"), T0).unwrap();
+    assert!(alt_game.enter_scratch_mode());
+
+    // Local turns are hidden in scratch mode. Turns are made for the side to move.
+    assert!(alt_game.local_game().board(A).grid()[Coord::E7].is(piece!(Black Pawn)));
+    alt_game.try_scratch_turn(A, alg("c5"), T0).unwrap();
+    alt_game.try_scratch_turn(A, alg("Nf3"), T0).unwrap();
+    alt_game.try_scratch_turn(B, alg("d4"), T0).unwrap();
+    assert!(alt_game.local_game().board(A).grid()[Coord::C5].is(piece!(Black Pawn)));
+    assert!(alt_game.local_game().board(A).grid()[Coord::F3].is(piece!(White Knight)));
+    assert!(alt_game.local_game().board(B).grid()[Coord::D4].is(piece!(White Pawn)));
+    assert!(alt_game.game_confirmed().board(A).grid()[Coord::C5].is_none());
+    assert!(alt_game.game_confirmed().board(B).grid()[Coord::D4].is_none());
+
+    alt_game.clear_scratch_turns();
+    assert!(alt_game.is_scratch_mode());
+    assert!(alt_game.local_game().board(A).grid()[Coord::C5].is_none());
+    assert!(alt_game.local_game().board(A).grid()[Coord::E7].is(piece!(Black Pawn)));
+
+    // The preturn is back after leaving scratch mode.
+    alt_game.try_scratch_turn(A, alg("d5"), T0).unwrap();
+    alt_game.exit_scratch_mode();
+    assert!(alt_game.local_game().board(A).grid()[Coord::D5].is_none());
+    assert!(alt_game.local_game().board(A).grid()[Coord::E5].is(piece!(Black Pawn)));
+}
+
+#[test]
+fn scratch_mode_exited_on_real_update() {
+    let mut alt_game = AlteredGame::new(as_single_player(envoy!(White A)), default_game());
+    assert!(alt_game.enter_scratch_mode());
+    alt_game.try_scratch_turn(A, alg("d4"), T0).unwrap();
+    alt_game.apply_remote_turn(envoy!(White B), &alg("e4"), T0).unwrap();
+    assert!(!alt_game.is_scratch_mode());
+    assert!(alt_game.local_game().board(A).grid()[Coord::D4].is_none());
+
+    // A real turn applies to the real position.
+    assert!(alt_game.enter_scratch_mode());
+    alt_game.try_scratch_turn(A, alg("d4"), T0).unwrap();
+    alt_game.try_scratch_turn(A, alg("d5"), T0).unwrap();
+    alt_game.try_local_turn(A, alg("e4"), T0).unwrap();
+    assert!(!alt_game.is_scratch_mode());
+    let game = alt_game.local_game();
+    assert!(game.board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+    assert!(game.board(A).grid()[Coord::D4].is_none());
+    assert!(game.board(A).grid()[Coord::D5].is_none());
+}
+
+#[test]
+fn scratch_mode_unavailable_in_fog_of_war() {
+    let mut alt_game =
+        AlteredGame::new(as_single_player(envoy!(White A)), fog_of_war_bughouse_game());
+    assert!(!alt_game.enter_scratch_mode());
+    assert!(!alt_game.is_scratch_mode());
+}
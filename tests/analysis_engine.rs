@@ -0,0 +1,29 @@
+use bughouse_chess::analysis_engine::{TurnGlyph, classify_turn};
+use bughouse_chess::force::Force;
+
+
+#[test]
+fn turn_glyph_thresholds() {
+    assert_eq!(classify_turn(Force::White, 50.0, 55.0), None);
+    assert_eq!(classify_turn(Force::White, 50.0, 45.0), None);
+    assert_eq!(classify_turn(Force::White, 50.0, 60.0), Some(TurnGlyph::Good));
+    assert_eq!(classify_turn(Force::White, 50.0, 75.0), Some(TurnGlyph::Brilliant));
+    assert_eq!(classify_turn(Force::White, 50.0, 38.0), Some(TurnGlyph::Mistake));
+    assert_eq!(classify_turn(Force::White, 50.0, 20.0), Some(TurnGlyph::Blunder));
+}
+
+#[test]
+fn turn_glyph_from_black_perspective() {
+    assert_eq!(classify_turn(Force::Black, 50.0, 38.0), Some(TurnGlyph::Good));
+    assert_eq!(classify_turn(Force::Black, 50.0, 20.0), Some(TurnGlyph::Brilliant));
+    assert_eq!(classify_turn(Force::Black, 50.0, 60.0), Some(TurnGlyph::Mistake));
+    assert_eq!(classify_turn(Force::Black, 50.0, 100.0), Some(TurnGlyph::Blunder));
+}
+
+#[test]
+fn turn_glyph_strings() {
+    assert_eq!(TurnGlyph::Brilliant.to_str(), "!!");
+    assert_eq!(TurnGlyph::Good.to_str(), "!");
+    assert_eq!(TurnGlyph::Mistake.to_str(), "?");
+    assert_eq!(TurnGlyph::Blunder.to_str(), "??");
+}
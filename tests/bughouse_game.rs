@@ -3,19 +3,30 @@ mod common;
 use std::cmp::Ordering;
 use std::time::Duration;
 
+use bughouse_chess::algebraic::AlgebraicCharset;
 use bughouse_chess::board::{DrawReason, TurnError, TurnInput, TurnMode, VictoryReason};
-use bughouse_chess::clock::{ClockShowing, GameInstant, TimeBreakdown, TimeDifferenceBreakdown};
+use bughouse_chess::clock::{
+    ClockShowing, GameDuration, GameInstant, MillisDuration, TimeBreakdown, TimeDifferenceBreakdown,
+};
 use bughouse_chess::coord::Coord;
+use bughouse_chess::envoy;
 use bughouse_chess::force::Force;
-use bughouse_chess::game::{BughouseBoard, BughouseGame, BughouseGameStatus};
+use bughouse_chess::game::{
+    BughouseBoard, BughouseGame, BughouseGameStatus, BughouseParticipant, BughousePlayer,
+    GameOutcome, GamePhase, PlayerInGame, TurnIndex,
+};
 use bughouse_chess::grid::GridExt;
 use bughouse_chess::piece::PieceKind;
 use bughouse_chess::player::Team;
 use bughouse_chess::role::Role;
-use bughouse_chess::rules::{ChessRules, FairyPieces, MatchRules, Promotion, Rules};
+use bughouse_chess::rules::{
+    ChessRules, DelayedStart, DropAggression, FairyPieces, MatchRules, PieceCap, PieceCapPolicy,
+    Promotion, Rules, SimultaneousFlagPolicy,
+};
 use bughouse_chess::test_util::*;
 use common::*;
 use enum_map::EnumMap;
+use itertools::Itertools;
 use rand::Rng;
 use strum::IntoEnumIterator;
 
@@ -74,6 +85,9 @@ fn duration_abs_diff(a: Duration, b: Duration) -> Duration {
 
 fn time_breakdown_to_duration(time_breakdown: TimeBreakdown) -> Duration {
     match time_breakdown {
+        TimeBreakdown::LongTime { hours, minutes, seconds } => {
+            Duration::from_secs((hours * 3600 + minutes * 60 + seconds).into())
+        }
         TimeBreakdown::NormalTime { minutes, seconds } => {
             Duration::from_secs((minutes * 60 + seconds).into())
         }
@@ -195,6 +209,547 @@ fn discard_promotion() {
     assert_eq!(game.board(BughouseBoard::B).reserve(Force::White)[PieceKind::Pawn], 1);
 }
 
+#[test]
+fn no_drop_phase() {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().no_drop_turns = 2;
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    replay_log(&mut game, "1A.e4  1a.d5  2A.xd5  1B.e4").unwrap();
+    // Captures during the no-drop phase still go to reserve.
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 1);
+    assert!(game.board(BughouseBoard::B).is_no_drop_phase());
+    assert_eq!(
+        make_turn(&mut game, BughouseBoard::B, "P@h5").err().unwrap(),
+        TurnError::DropsNotYetAllowed
+    );
+    replay_log(&mut game, "1b.e6  2B.d4").unwrap();
+    assert_eq!(
+        make_turn(&mut game, BughouseBoard::B, "P@h5").err().unwrap(),
+        TurnError::DropsNotYetAllowed
+    );
+    replay_log(&mut game, "2b.d6  3B.Nf3").unwrap();
+    assert!(!game.board(BughouseBoard::B).is_no_drop_phase());
+    make_turn(&mut game, BughouseBoard::B, "P@h5").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 0);
+}
+
+#[test]
+fn drop_only_from_reserve() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4  1a.d5  2A.xd5  1B.e4").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 1);
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Knight], 0);
+    assert_eq!(
+        make_turn(&mut game, BughouseBoard::B, "N@f6").err().unwrap(),
+        TurnError::DropPieceMissing
+    );
+    // The refused drop must not affect the reserve or the turn order.
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 1);
+    assert_eq!(game.board(BughouseBoard::B).active_force(), Force::Black);
+    make_turn(&mut game, BughouseBoard::B, "P@h5").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 0);
+    replay_log(&mut game, "2B.d4").unwrap();
+    assert_eq!(
+        make_turn(&mut game, BughouseBoard::B, "P@h4").err().unwrap(),
+        TurnError::DropPieceMissing
+    );
+}
+
+fn no_interposing_drops_game(no_interposing_drops: bool) -> BughouseGame {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().no_interposing_drops = no_interposing_drops;
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    game.board_mut(BughouseBoard::A).reserve_mut(Force::Black)[PieceKind::Knight] = 2;
+    game
+}
+
+#[test]
+fn interposing_drop_allowed_by_default() {
+    let mut game = no_interposing_drops_game(false);
+    replay_log(&mut game, "1A.e4  1a.f5  2A.Qh5+  2a.N@g6").unwrap();
+}
+
+#[test]
+fn interposing_drop_forbidden() {
+    let mut game = no_interposing_drops_game(true);
+    replay_log(&mut game, "1A.e4  1a.f5  2A.Qh5+").unwrap();
+    assert_eq!(make_turn(&mut game, BughouseBoard::A, "N@g6"), Err(TurnError::InterposingDrop));
+    // Blocking with a move is still allowed.
+    make_turn(&mut game, BughouseBoard::A, "g6").unwrap();
+    make_turn(&mut game, BughouseBoard::A, "Qxg6+").unwrap();
+    assert_eq!(make_turn(&mut game, BughouseBoard::A, "N@f7"), Err(TurnError::InterposingDrop));
+    // Capturing the checking piece is allowed.
+    make_turn(&mut game, BughouseBoard::A, "hxg6").unwrap();
+    // Drops are allowed again once the check is resolved.
+    make_turn(&mut game, BughouseBoard::A, "Nf3").unwrap();
+    make_turn(&mut game, BughouseBoard::A, "N@e5").unwrap();
+}
+
+#[test]
+fn interposing_drop_forbidden_king_move() {
+    let mut game = no_interposing_drops_game(true);
+    replay_log(&mut game, "1A.e4  1a.e5  2A.Nf3  2a.d6  3A.Bb5+").unwrap();
+    assert_eq!(make_turn(&mut game, BughouseBoard::A, "N@d7"), Err(TurnError::InterposingDrop));
+    make_turn(&mut game, BughouseBoard::A, "Ke7").unwrap();
+}
+
+#[test]
+fn interposing_drop_forbidden_no_bughouse_mate() {
+    for no_interposing_drops in [false, true] {
+        let mut rules = default_rules();
+        let bughouse_rules = rules.bughouse_rules_mut().unwrap();
+        bughouse_rules.no_interposing_drops = no_interposing_drops;
+        bughouse_rules.drop_aggression = DropAggression::NoBughouseMate;
+        let mut game =
+            BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+        game.board_mut(BughouseBoard::A).reserve_mut(Force::Black)[PieceKind::Queen] = 1;
+        replay_log(&mut game, "1A.g4  1a.e5  2A.f3").unwrap();
+        // The mate could be blocked by a drop, unless interposing drops are forbidden.
+        let result = make_turn(&mut game, BughouseBoard::A, "Q@h4");
+        if no_interposing_drops {
+            assert_eq!(result, Err(TurnError::DropAggression));
+        } else {
+            result.unwrap();
+        }
+    }
+}
+
+fn piece_cap_game(max_pieces: u8, policy: PieceCapPolicy) -> BughouseGame {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().piece_cap = Some(PieceCap { max_pieces, policy });
+    BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players())
+}
+
+#[test]
+fn piece_cap_refuse() {
+    let mut game = piece_cap_game(17, PieceCapPolicy::Refuse);
+    replay_log(&mut game, "1A.e4  1a.d5  2A.exd5  2a.e5").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).num_pieces(Force::Black), 17);
+    replay_log(&mut game, "3A.Nf3  3a.Qxd5  4A.Nc3  4a.Qa5").unwrap();
+    assert_eq!(make_turn(&mut game, BughouseBoard::A, "Nxe5"), Err(TurnError::PieceCapExceeded));
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 1);
+    // Captures going to the other player are not affected.
+    replay_log(&mut game, "1B.e4  1b.d5").unwrap();
+    make_turn(&mut game, BughouseBoard::B, "exd5").unwrap();
+}
+
+// A capture refused because of the cap cannot be used to escape a check.
+#[test]
+fn piece_cap_refuse_mate() {
+    for max_pieces in [16, 17] {
+        let mut game = piece_cap_game(max_pieces, PieceCapPolicy::Refuse);
+        game.board_mut(BughouseBoard::A).reserve_mut(Force::White)[PieceKind::Queen] = 1;
+        replay_log(&mut game, "1A.e4  1a.e5  2A.Q@e7").unwrap();
+        if max_pieces == 16 {
+            assert_eq!(
+                game.status(),
+                BughouseGameStatus::Victory(Team::Red, VictoryReason::Checkmate)
+            );
+        } else {
+            make_turn(&mut game, BughouseBoard::A, "Kxe7").unwrap();
+        }
+    }
+}
+
+#[test]
+fn piece_cap_discard() {
+    let mut game = piece_cap_game(16, PieceCapPolicy::Discard);
+    replay_log(&mut game, "1A.e4  1a.d5  2A.exd5").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 0);
+    assert_eq!(game.board(BughouseBoard::B).num_pieces(Force::Black), 16);
+    // Once the player has fewer pieces, captures go to reserve again.
+    replay_log(&mut game, "1B.e4  1b.d5  2B.exd5").unwrap();
+    replay_log(&mut game, "2a.c6  3A.dxc6").unwrap();
+    assert_eq!(game.board(BughouseBoard::B).reserve(Force::Black)[PieceKind::Pawn], 1);
+}
+
+// With interposing drops forbidden, a check that cannot be answered on the board is a mate even
+// if the opponent might get a piece to drop.
+#[test]
+fn interposing_drop_forbidden_mate() {
+    let mut game = no_interposing_drops_game(true);
+    replay_log(&mut game, "1A.f3  1a.e5  2A.g4  2a.Qh4#").unwrap();
+    assert_eq!(game.status(), BughouseGameStatus::Victory(Team::Blue, VictoryReason::Checkmate));
+}
+
+// Increment is granted based on the move number on the player's own board.
+#[test]
+fn increment_after_no_increment_turns() {
+    use BughouseBoard::*;
+    use Force::*;
+    let mut rules = default_rules();
+    rules.chess_rules.time_control.increment = Duration::from_secs(2);
+    rules.chess_rules.time_control.no_increment_turns = 1;
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    replay_bughouse_log(
+        &mut game,
+        "1A.e4  1a.e5  2A.Nf3  2a.Nc6  1B.d4  1b.d5  2B.c4",
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let now = GameInstant::from_duration(Duration::from_secs(6));
+    let time_left = |board_idx, force| game.board(board_idx).clock().time_left(force, now);
+    assert_eq!(time_left(A, White), GameDuration::from_secs(298));
+    assert_eq!(time_left(A, Black), GameDuration::from_secs(300));
+    assert_eq!(time_left(B, White), GameDuration::from_secs(297));
+    assert_eq!(time_left(B, Black), GameDuration::from_secs(299));
+}
+
+#[test]
+fn delayed_start() {
+    use BughouseBoard::*;
+    use Force::*;
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().delayed_start = Some(DelayedStart {
+        team: Team::Blue,
+        delay: Duration::from_secs(30),
+    });
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    let t = |secs| GameInstant::from_duration(Duration::from_secs(secs));
+    let turn = |game: &mut BughouseGame, board_idx, notation: &str, secs| {
+        game.try_turn(board_idx, &alg(notation), TurnMode::InOrder, t(secs)).unwrap();
+    };
+    let time_left = |game: &BughouseGame, board_idx, force, secs| {
+        game.board(board_idx).clock().time_left(force, t(secs))
+    };
+
+    // Blue clocks don't run during the delay.
+    turn(&mut game, A, "e4", 0);
+    turn(&mut game, A, "e5", 10);
+    assert_eq!(time_left(&game, A, Black, 10), GameDuration::from_secs(300));
+    assert_eq!(time_left(&game, B, White, 20), GameDuration::from_secs(300));
+
+    // Red clocks run from the first move.
+    assert_eq!(time_left(&game, A, White, 20), GameDuration::from_secs(290));
+    turn(&mut game, A, "Nf3", 20);
+
+    // Blue clocks start when the delay is over.
+    assert_eq!(time_left(&game, A, Black, 35), GameDuration::from_secs(295));
+    assert_eq!(time_left(&game, B, White, 35), GameDuration::from_secs(295));
+    assert_eq!(time_left(&game, A, White, 35), GameDuration::from_secs(290));
+}
+
+#[test]
+fn delayed_start_forbidden_in_rated_matches() {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().delayed_start = Some(DelayedStart {
+        team: Team::Red,
+        delay: Duration::from_secs(30),
+    });
+    assert!(rules.verify().is_ok());
+    rules.match_rules.rated = true;
+    assert!(rules.verify().is_err());
+}
+
+// A double player can have both clocks running at the same time.
+#[test]
+fn double_player_clocks_tick_simultaneously() {
+    use BughouseBoard::*;
+    use Force::*;
+    let players = vec![
+        PlayerInGame {
+            name: "p1".to_owned(),
+            id: BughousePlayer::DoublePlayer(Team::Red),
+        },
+        PlayerInGame {
+            name: "p3".to_owned(),
+            id: BughousePlayer::SinglePlayer(envoy!(Black A)),
+        },
+        PlayerInGame {
+            name: "p4".to_owned(),
+            id: BughousePlayer::SinglePlayer(envoy!(White B)),
+        },
+    ];
+    let mut game = BughouseGame::new(default_rules(), Role::ServerOrStandalone, &players);
+    let red = BughouseParticipant::Player(BughousePlayer::DoublePlayer(Team::Red));
+    let t = |secs| GameInstant::from_duration(Duration::from_secs(secs));
+    let turn = |game: &mut BughouseGame, board_idx, notation: &str, secs| {
+        game.try_turn(board_idx, &alg(notation), TurnMode::InOrder, t(secs)).unwrap();
+    };
+    let time_left = |game: &BughouseGame, board_idx, force, secs| {
+        game.board(board_idx).clock().time_left(force, t(secs))
+    };
+
+    // Clocks don't run before the first turn.
+    assert!(game.ticking_envoys(red).is_empty());
+    turn(&mut game, A, "e4", 0);
+    assert!(game.ticking_envoys(red).is_empty());
+    turn(&mut game, B, "e4", 1);
+    assert_eq!(game.ticking_envoys(red), [envoy!(Black B)]);
+    turn(&mut game, A, "e5", 2);
+    assert_eq!(game.ticking_envoys(red), [envoy!(White A), envoy!(Black B)]);
+    assert_eq!(time_left(&game, A, White, 10), GameDuration::from_secs(292));
+    assert_eq!(time_left(&game, B, Black, 10), GameDuration::from_secs(291));
+
+    // Moving on one board doesn't affect the clock on the other one.
+    turn(&mut game, A, "Nf3", 10);
+    assert_eq!(game.ticking_envoys(red), [envoy!(Black B)]);
+    assert_eq!(time_left(&game, A, White, 20), GameDuration::from_secs(292));
+    assert_eq!(time_left(&game, B, Black, 20), GameDuration::from_secs(281));
+}
+
+#[test]
+fn perpetual_check() {
+    let game_str = "
+        . . . . . . k .     . . . . . . . K
+        . . . . . . p .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . Q     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        K . . . . . . .     . . . . . . . k
+    ";
+    let mut game =
+        parse_ascii_bughouse(default_rules(), Role::ServerOrStandalone, game_str).unwrap();
+    // Qe8+ Kh7 Qh5+ Kg8 repeats the position.
+    assert_eq!(game.detect_perpetual(BughouseBoard::A), Some(Force::White));
+    assert_eq!(game.detect_perpetual(BughouseBoard::B), None);
+
+    // A drop on f8 blocks the check for good.
+    game.board_mut(BughouseBoard::A).reserve_mut(Force::Black)[PieceKind::Knight] = 1;
+    assert_eq!(game.detect_perpetual(BughouseBoard::A), None);
+}
+
+#[test]
+fn game_phase_opening() {
+    let mut game = default_game();
+    assert_eq!(game.phase(BughouseBoard::A), GamePhase::Opening);
+    replay_log(&mut game, "1A.e4  1a.e5  2A.Nf3  2a.Nc6").unwrap();
+    assert_eq!(game.phase(BughouseBoard::A), GamePhase::Opening);
+    assert_eq!(game.phase(BughouseBoard::B), GamePhase::Opening);
+}
+
+#[test]
+fn game_phase_endgame() {
+    let game_str = "
+        . . . . k . . .     . . . . k . . .
+        . . . . p . . .     . . . . p . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . R P . . .     . . . . P . . .
+        . . . . K . . .     . . . . K . . .
+    ";
+    let mut game =
+        parse_ascii_bughouse(default_rules(), Role::ServerOrStandalone, game_str).unwrap();
+    assert_eq!(game.phase(BughouseBoard::A), GamePhase::Endgame);
+    assert_eq!(game.phase(BughouseBoard::B), GamePhase::Endgame);
+
+    // Pieces in reserve can be dropped, so they count too.
+    game.board_mut(BughouseBoard::B).reserve_mut(Force::Black)[PieceKind::Queen] = 3;
+    assert_ne!(game.phase(BughouseBoard::B), GamePhase::Endgame);
+}
+
+#[test]
+fn last_turn_announcement_move() {
+    let mut game = default_game();
+    assert_eq!(game.last_turn_announcement(BughouseBoard::A), None);
+    replay_log(&mut game, "1A.Nf3  1B.e4").unwrap();
+    assert_eq!(game.last_turn_announcement(BughouseBoard::A).unwrap(), "White knight f3");
+    assert_eq!(game.last_turn_announcement(BughouseBoard::B).unwrap(), "White pawn e4");
+}
+
+#[test]
+fn last_turn_announcement_capture() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4  1a.d5  2A.exd5").unwrap();
+    assert_eq!(
+        game.last_turn_announcement(BughouseBoard::A).unwrap(),
+        "White pawn takes pawn on d5"
+    );
+}
+
+#[test]
+fn last_turn_announcement_drop() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4  1a.d5  2A.exd5  1B.e4  1b.P@f3").unwrap();
+    assert_eq!(game.last_turn_announcement(BughouseBoard::B).unwrap(), "Black drops pawn on f3");
+}
+
+#[test]
+fn last_turn_announcement_check() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4  1a.f6  2A.Qh5").unwrap();
+    assert_eq!(game.last_turn_announcement(BughouseBoard::A).unwrap(), "White queen h5, check");
+}
+
+#[test]
+fn move_list_san_interleaves_boards() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4  1a.Nc6  1B.d4  2A.Nc3  1b.Nf6  2a.Nf6  2B.d5").unwrap();
+    let san = game.move_list_san(AlgebraicCharset::Ascii);
+    assert_eq!(san.iter().join(" "), "1A.e4 1a.Nc6 1B.d4 2A.Nc3 1b.Nf6 2a.Nf6 2B.d5");
+    assert_eq!(san[3].number, 2);
+    assert_eq!(san[3].envoy, envoy!(White A));
+    assert_eq!(san[3].index, TurnIndex(3));
+}
+
+#[test]
+fn move_list_san_steal_promotion() {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().promotion = Promotion::Steal;
+    let game_str = "
+        . . . . k . . .     . . . . . K . .
+        P . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . . . . .     . . . . . . . .
+        . . . . K . . .     . . . N . k . .
+    ";
+    let mut game = parse_ascii_bughouse(rules, Role::ServerOrStandalone, game_str).unwrap();
+    replay_log(&mut game, "1A.a8=Ne8  1B.Kc2  1a.Kd7  1b.Kd7  2A.Nb6").unwrap();
+    // Turn numbers continue from the parsed position, which starts at full turn 42.
+    assert_eq!(
+        game.move_list_san(AlgebraicCharset::Ascii).iter().join(" "),
+        "42A.a8=Ne8 42B.Kc2 42a.Kd7 42b.Kd7 43A.Nb6"
+    );
+    assert_eq!(game.move_list_san(AlgebraicCharset::AuxiliaryUnicode)[0].algebraic, "a8/Ne8");
+}
+
+fn simultaneous_flag_game(policy: SimultaneousFlagPolicy, log: &str) -> BughouseGame {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().simultaneous_flag = policy;
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    replay_log(&mut game, log).unwrap();
+    // Black is to move on both boards, so players from different teams flag at the same time.
+    game.test_flag(GameInstant::from_duration(Duration::from_secs(301)));
+    game
+}
+
+#[test]
+fn simultaneous_flag_draw() {
+    let game = simultaneous_flag_game(SimultaneousFlagPolicy::Draw, "1A.e4  1a.d5  2A.xd5  1B.e4");
+    assert_eq!(game.status(), BughouseGameStatus::Draw(DrawReason::SimultaneousFlag));
+}
+
+#[test]
+fn simultaneous_flag_material_advantage() {
+    // Red captured a pawn.
+    let game = simultaneous_flag_game(
+        SimultaneousFlagPolicy::MaterialAdvantage,
+        "1A.e4  1a.d5  2A.xd5  1B.e4",
+    );
+    assert_eq!(
+        game.status(),
+        BughouseGameStatus::Victory(Team::Red, VictoryReason::SimultaneousFlagMaterial)
+    );
+    let outcome = game.outcome();
+    let outcome_str = outcome.to_pgn(game.chess_rules());
+    assert_eq!(
+        outcome_str,
+        "Alice & Dave won: simultaneous flags, Bob & Charlie had less material"
+    );
+    assert_eq!(
+        GameOutcome::from_pgn(&game.players(), &outcome_str).unwrap().status,
+        outcome.status
+    );
+
+    // Equal material.
+    let game = simultaneous_flag_game(SimultaneousFlagPolicy::MaterialAdvantage, "1A.e4  1B.e4");
+    assert_eq!(game.status(), BughouseGameStatus::Draw(DrawReason::SimultaneousFlag));
+}
+
+fn board_resignation_game() -> BughouseGame {
+    let mut rules = default_rules();
+    rules.bughouse_rules_mut().unwrap().board_resignation = true;
+    BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players())
+}
+
+#[test]
+fn board_resignation_game_goes_on() {
+    let mut game = board_resignation_game();
+    replay_log(&mut game, "1A.e4  1a.e5").unwrap();
+    game.resign_board(envoy!(White A), T0);
+    assert!(game.is_active());
+    assert!(game.is_board_resigned(BughouseBoard::A));
+    assert!(!game.is_board_resigned(BughouseBoard::B));
+    assert_eq!(make_turn(&mut game, BughouseBoard::A, "Nf3"), Err(TurnError::GameOver));
+    replay_log(&mut game, "1B.e4  1b.e5  2B.Bc4  2b.Nc6  3B.Qh5  3b.Nf6  4B.Qxf7#").unwrap();
+    assert_eq!(game.status(), BughouseGameStatus::Victory(Team::Blue, VictoryReason::Checkmate));
+}
+
+#[test]
+fn board_resignation_split_boards() {
+    let mut game = board_resignation_game();
+    game.resign_board(envoy!(Black A), T0);
+    replay_log(&mut game, "1B.e4  1b.e5  2B.Bc4  2b.Nc6  3B.Qh5  3b.Nf6  4B.Qxf7#").unwrap();
+    assert_eq!(game.status(), BughouseGameStatus::Draw(DrawReason::SplitBoards));
+    let outcome = game.outcome();
+    let outcome_str = outcome.to_pgn(game.chess_rules());
+    assert_eq!(outcome_str, "Draw: each team won a board");
+    assert_eq!(
+        GameOutcome::from_pgn(&game.players(), &outcome_str).unwrap().status,
+        outcome.status
+    );
+}
+
+#[test]
+fn board_resignation_flag() {
+    let mut game = board_resignation_game();
+    replay_log(&mut game, "1A.e4  1B.e4").unwrap();
+    game.resign_board(envoy!(White A), T0);
+    // Black is to move on both boards, but the clock on the resigned board is stopped.
+    game.test_flag(GameInstant::from_duration(Duration::from_secs(301)));
+    assert_eq!(game.status(), BughouseGameStatus::Victory(Team::Blue, VictoryReason::Flag));
+}
+
+#[test]
+fn board_resignation_forbidden_in_koedem() {
+    let mut rules = default_rules();
+    let bughouse_rules = rules.bughouse_rules_mut().unwrap();
+    bughouse_rules.koedem = true;
+    bughouse_rules.drop_aggression = DropAggression::MateAllowed;
+    bughouse_rules.board_resignation = true;
+    assert!(rules.verify().unwrap_err().contains("Koedem"));
+}
+
+#[test]
+fn game_review() {
+    let mut game = default_game();
+    replay_bughouse_log(
+        &mut game,
+        "1A.e4  1a.d5  2A.xd5  2a.Qxd5  1B.Nf3  1b.Nc6  2B.Nc3  2b.Nf6  3A.Nc3  3a.Qa5",
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let t = GameInstant::from_duration(Duration::from_secs(10));
+    game.set_status(BughouseGameStatus::Victory(Team::Blue, VictoryReason::Resignation), t);
+
+    let review = game.review();
+    assert_eq!(
+        review.outcome.status,
+        BughouseGameStatus::Victory(Team::Blue, VictoryReason::Resignation)
+    );
+    assert_eq!(review.duration, GameDuration::from_secs(10));
+    assert_eq!(review.num_turns[BughouseBoard::A], 6);
+    assert_eq!(review.num_turns[BughouseBoard::B], 4);
+    assert!(!review.is_minimal());
+    // The pawn captured on A was briefly in Black's reserve on B.
+    assert_eq!(review.material_balance_range[BughouseBoard::A], (0, 1));
+    assert_eq!(review.material_balance_range[BughouseBoard::B], (-1, 0));
+    // White on A thought from 3s to 8s before playing Nc3.
+    let time_pressure = review.time_pressure.unwrap();
+    assert_eq!(time_pressure.envoy, envoy!(White A));
+    assert_eq!(time_pressure.turn_index, TurnIndex(8));
+    assert_eq!(time_pressure.time_left, MillisDuration::from_secs(294));
+}
+
+#[test]
+fn game_review_early_resignation() {
+    let mut game = default_game();
+    replay_log(&mut game, "1A.e4").unwrap();
+    game.set_status(BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation), T0);
+
+    let review = game.review();
+    assert_eq!(review.total_turns(), 1);
+    assert!(review.is_minimal());
+    assert_eq!(review.material_balance_range[BughouseBoard::A], (0, 0));
+}
+
 // Test that promoted piece is not downgraded to a pawn on capture if it's promoted by stealing.
 #[test]
 fn steal_promotion_piece_goes_back_unchanged() {
@@ -381,7 +936,29 @@ fn koedem_basic() {
         ",
     )
     .unwrap();
-    assert_eq!(game.status(), BughouseGameStatus::Victory(Team::Blue, VictoryReason::Checkmate));
+    assert_eq!(
+        game.status(),
+        BughouseGameStatus::Victory(Team::Blue, VictoryReason::KingCaptured)
+    );
+}
+
+#[test]
+fn regicide_king_capture() {
+    let mut rules = default_rules();
+    rules.chess_rules.fog_of_war = true;
+    let mut game = BughouseGame::new(rules, Role::ServerOrStandalone, &sample_bughouse_players());
+    replay_log(&mut game, "1A.Nc3  1a.a6  2A.Nd5  2a.a5  3A.Nxc7  3a.a4  4A.Nxe8").unwrap();
+    assert_eq!(
+        game.status(),
+        BughouseGameStatus::Victory(Team::Red, VictoryReason::KingCaptured)
+    );
+    let outcome = game.outcome();
+    let outcome_str = outcome.to_pgn(game.chess_rules());
+    assert_eq!(outcome_str, "Alice & Dave won: Bob & Charlie lost a king");
+    assert_eq!(
+        GameOutcome::from_pgn(&game.players(), &outcome_str).unwrap().status,
+        outcome.status
+    );
 }
 
 #[test]
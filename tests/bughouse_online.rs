@@ -14,26 +14,35 @@ use std::sync::Arc;
 use BughouseBoard::{A, B};
 use Force::{Black, White};
 use async_std::sync::Mutex;
+use async_trait::async_trait;
 use bughouse_chess::altered_game::{AlteredGame, WaybackDestination};
-use bughouse_chess::board::{Board, TurnError, TurnInput, VictoryReason};
-use bughouse_chess::chat::ChatRecipient;
-use bughouse_chess::clock::GameInstant;
+use bughouse_chess::board::{Board, DrawReason, Reserve, TurnError, TurnInput, VictoryReason};
+use bughouse_chess::chalk::ChalkMark;
+use bughouse_chess::chat::{ChatRecipient, OutgoingChatMessage};
+use bughouse_chess::clock::{GameDuration, GameInstant};
 use bughouse_chess::coord::{Coord, SubjectiveRow};
 use bughouse_chess::display::{DisplayBoard, Perspective, get_display_board_index};
-use bughouse_chess::event::{BughouseClientEvent, BughouseServerEvent};
+use bughouse_chess::event::{
+    BughouseClientEvent, BughouseClientPerformance, BughouseServerEvent, FinishedGameDescription,
+};
 use bughouse_chess::force::Force;
 use bughouse_chess::game::{
     BughouseBoard, BughouseEnvoy, BughouseGame, BughouseGameStatus, BughouseParticipant,
     BughousePlayer, TurnIndex, double_player, single_player,
 };
+use bughouse_chess::lobby::SeatSwap;
 use bughouse_chess::piece::PieceKind;
 use bughouse_chess::player::{Faction, Team};
+use bughouse_chess::player_name::MAX_PLAYER_NAME_LENGTH;
 use bughouse_chess::rules::{
-    BughouseRules, ChessRules, DropAggression, MatchRules, PawnDropRanks, Promotion, Rules,
+    BughouseRules, ChessRules, DropAggression, FIRST_GAME_COUNTDOWN_DURATION, FairyPieces,
+    MatchRules, MatchTarget, PawnDropRanks, Promotion, RatedGameChat, Rules,
+    SimultaneousFlagPolicy, Stalemate,
 };
-use bughouse_chess::scores::Scores;
+use bughouse_chess::scores::{Contestant, Scores};
 use bughouse_chess::server::{ServerInfo, ServerOptions};
 use bughouse_chess::server_helpers::TestServerHelpers;
+use bughouse_chess::server_hooks::ServerHooks;
 use bughouse_chess::session::{RegistrationMethod, Session, UserInfo};
 use bughouse_chess::session_store::{SessionId, SessionStore};
 use bughouse_chess::utc_time::UtcDateTime;
@@ -41,7 +50,7 @@ use bughouse_chess::{client, envoy, server};
 use common::*;
 use instant::Instant;
 use itertools::Itertools;
-use time::Duration;
+use time::{Duration, OffsetDateTime};
 
 
 fn default_chess_rules() -> ChessRules {
@@ -54,26 +63,84 @@ fn default_chess_rules() -> ChessRules {
                 max: SubjectiveRow::from_one_based(6),
             },
             drop_aggression: DropAggression::NoChessMate,
+            no_drop_turns: 0,
+            no_interposing_drops: false,
+            simultaneous_flag: SimultaneousFlagPolicy::Draw,
+            stalemate: Stalemate::Wait,
+            board_resignation: false,
+            piece_cap: None,
+            delayed_start: None,
         }),
         ..ChessRules::chess_blitz_5()
     }
 }
 
 
+// Remembers the status of each finished game reported by the server. Reports preset ratings.
+#[derive(Default)]
+struct RecordingServerHooks {
+    finished_games: std::sync::Mutex<Vec<BughouseGameStatus>>,
+    interrupted_games: std::sync::Mutex<Vec<BughouseGameStatus>>,
+    ratings: HashMap<String, u32>,
+}
+
+#[async_trait]
+impl ServerHooks for RecordingServerHooks {
+    async fn record_client_performance(&self, _perf: &BughouseClientPerformance) {}
+    async fn record_finished_game(
+        &self, game: &BughouseGame, _registered_users: &HashSet<String>,
+        _game_start_time: UtcDateTime, _game_end_time: UtcDateTime, _round: u64,
+    ) {
+        self.finished_games.lock().unwrap().push(game.status());
+    }
+    async fn record_interrupted_game(
+        &self, game: &BughouseGame, _game_start_time: UtcDateTime,
+        _game_interruption_time: UtcDateTime, _round: u64,
+    ) {
+        self.interrupted_games.lock().unwrap().push(game.status());
+    }
+    async fn get_games_by_user(
+        &self, _user_name: &str,
+    ) -> Result<Vec<FinishedGameDescription>, String> {
+        Err("Not implemented".to_owned())
+    }
+    async fn get_game_bpgn(&self, _game_id: i64) -> Result<String, String> {
+        Err("Not implemented".to_owned())
+    }
+    async fn get_player_rating(&self, user_name: &str, _rules: &ChessRules) -> Option<u32> {
+        self.ratings.get(user_name).copied()
+    }
+}
+
+const MAX_CHAT_MESSAGES_PER_MATCH: usize = 100;
+
 struct Server {
     creation_instant: Instant,
     time_elapsed: Duration,
     next_session_id: usize,
     session_store: Arc<Mutex<SessionStore>>,
     clients: Arc<server::Clients>,
+    info: Arc<Mutex<ServerInfo>>,
     state: server::ServerState,
 }
 
 impl Server {
-    fn new() -> Self {
+    fn new() -> Self { Self::new_impl(false, None) }
+    fn new_with_connection_health_check() -> Self { Self::new_impl(true, None) }
+    fn new_with_hooks(hooks: Arc<dyn ServerHooks + Send + Sync>) -> Self {
+        Self::new_impl(false, Some(hooks))
+    }
+
+    fn new_impl(
+        connection_health_check: bool, hooks: Option<Arc<dyn ServerHooks + Send + Sync>>,
+    ) -> Self {
         let options = ServerOptions {
             check_git_version: false,
             max_starting_time: None,
+            max_player_name_length: MAX_PLAYER_NAME_LENGTH,
+            max_spectators_per_match: 2,
+            client_heartbeat_timeout: std::time::Duration::from_secs(60),
+            max_chat_messages_per_match: MAX_CHAT_MESSAGES_PER_MATCH,
         };
         let clients = Arc::new(server::Clients::new(&options));
         let session_store = Arc::new(Mutex::new(SessionStore::new()));
@@ -82,18 +149,21 @@ impl Server {
             options,
             Arc::clone(&clients),
             Arc::clone(&session_store),
-            server_info,
+            Arc::clone(&server_info),
             Arc::new(TestServerHelpers {}),
-            None,
+            hooks,
         );
         state.TEST_disable_countdown();
-        state.TEST_disable_connection_health_check();
+        if !connection_health_check {
+            state.TEST_disable_connection_health_check();
+        }
         Server {
             creation_instant: Instant::now(),
             time_elapsed: Duration::ZERO,
             next_session_id: 1,
             session_store,
             clients,
+            info: server_info,
             state,
         }
     }
@@ -140,6 +210,13 @@ impl Server {
             UtcDateTime::now(),
         ));
     }
+    fn terminate(&mut self) {
+        async_std::task::block_on(self.state.apply_event(
+            server::IncomingEvent::Terminate,
+            self.current_instant(),
+            UtcDateTime::now(),
+        ));
+    }
 }
 
 
@@ -156,6 +233,12 @@ impl Client {
         let state = client::ClientState::new(user_agent, time_zone);
         Client { id: None, incoming_rx: None, state }
     }
+    pub fn new_read_only() -> Self {
+        let user_agent = "Test".to_owned();
+        let time_zone = "?".to_owned();
+        let state = client::ClientState::new_read_only(user_agent, time_zone);
+        Client { id: None, incoming_rx: None, state }
+    }
 
     fn connect(&mut self, server: &mut Server, session_id: Option<SessionId>) {
         let (incoming_tx, incoming_rx) = async_std::channel::unbounded();
@@ -210,6 +293,9 @@ impl Client {
     }
     fn cancel_preturn(&mut self) { self.state.cancel_preturn(self.my_display_board_idx()) }
 
+    // Simulates events that were dispatched, but never reached the server.
+    fn lose_outgoing_events(&mut self) { while self.state.next_outgoing_event().is_some() {} }
+
     fn process_outgoing_events(&mut self, server: &mut Server) -> bool {
         let mut something_changed = false;
         while let Some(event) = self.state.next_outgoing_event() {
@@ -271,6 +357,18 @@ struct World {
 
 impl World {
     fn new() -> Self { World { server: Server::new(), clients: vec![] } }
+    fn new_with_connection_health_check() -> Self {
+        World {
+            server: Server::new_with_connection_health_check(),
+            clients: vec![],
+        }
+    }
+    fn new_with_hooks(hooks: Arc<dyn ServerHooks + Send + Sync>) -> Self {
+        World {
+            server: Server::new_with_hooks(hooks),
+            clients: vec![],
+        }
+    }
 
     fn set_time(&mut self, time: Duration) { self.server.set_time(time); }
 
@@ -305,6 +403,13 @@ impl World {
         self.clients.push(client);
         idx
     }
+    fn new_read_only_client(&mut self) -> TestClientId {
+        let idx = TestClientId(self.clients.len());
+        let mut client = Client::new_read_only();
+        client.connect(&mut self.server, None);
+        self.clients.push(client);
+        idx
+    }
     fn new_client_registered_user(&mut self, user_name: &str) -> TestClientId {
         let idx = TestClientId(self.clients.len());
         let mut client = Client::new();
@@ -533,6 +638,29 @@ fn play_online_misc() {
     world.process_all_events();
 }
 
+#[test]
+fn move_list_text() {
+    let mut world = World::new();
+    let (_, cl1, cl2, cl3, cl4) = world.default_clients();
+    for (client, turn) in [
+        (cl1, "e4"),
+        (cl3, "e5"),
+        (cl4, "d4"),
+        (cl2, "d5"),
+        (cl1, "Nf3"),
+        (cl3, "Nc6"),
+    ] {
+        world[client].make_turn(turn).unwrap();
+        world.process_all_events();
+    }
+    assert_eq!(world[cl1].state.move_list_text(A).unwrap(), "1. e4 e5 2. Nf3 Nc6");
+    assert_eq!(world[cl1].state.move_list_text(B).unwrap(), "1. d4 d5");
+    assert_eq!(
+        world[cl2].state.move_list_text_both_boards().unwrap(),
+        "1A.e4 1a.e5 1B.d4 1b.d5 2A.Nf3 2a.Nc6"
+    );
+}
+
 #[test]
 fn score_valid() {
     let mut world = World::new();
@@ -548,6 +676,54 @@ fn score_valid() {
     assert_eq!(scores[Team::Blue].as_f64(), 0.5);
 }
 
+#[test]
+fn reserve_restocked_event() {
+    fn restocked_pieces(
+        world: &mut World, client_id: TestClientId,
+    ) -> Vec<(BughouseBoard, Vec<PieceKind>)> {
+        let mut restocks = vec![];
+        while let Some(event) = world[client_id].state.next_notable_event() {
+            if let client::NotableEvent::MyReserveRestocked(board_idx, pieces) = event {
+                restocks.push((board_idx, pieces.into_iter().sorted().collect()));
+            }
+        }
+        restocks
+    }
+
+    let mut world = World::new();
+    let chess_rules = ChessRules {
+        fairy_pieces: FairyPieces::Accolade,
+        ..default_chess_rules()
+    };
+    let (_, cl1, cl2, cl3, cl4) = world.default_clients_with_rules(chess_rules);
+    for (client_id, turn) in [(cl1, "e4"), (cl3, "d5"), (cl1, "xd5")] {
+        world[client_id].make_turn(turn).unwrap();
+        world.process_all_events();
+    }
+    assert_eq!(restocked_pieces(&mut world, cl2), [(B, vec![PieceKind::Pawn])]);
+    assert!(restocked_pieces(&mut world, cl4).is_empty());
+
+    // A combined piece falls apart when captured, so the partner gets both parts at once.
+    let turns = [
+        (cl3, "b5"),
+        (cl1, "a4"),
+        (cl3, "b4"),
+        (cl1, "Ra3"),
+        (cl3, "a6"),
+        (cl1, "Na3"),
+        (cl3, "xa3"),
+    ];
+    for (client_id, turn) in turns {
+        world[client_id].make_turn(turn).unwrap();
+        world.process_all_events();
+    }
+    assert_eq!(restocked_pieces(&mut world, cl4), [(B, vec![
+        PieceKind::Knight,
+        PieceKind::Rook
+    ])]);
+    assert!(restocked_pieces(&mut world, cl2).is_empty());
+}
+
 // Regression test for turn preview bug: turns from the other boards could've been
 // reverted when a local turn was confirmed.
 #[test]
@@ -923,19 +1099,65 @@ fn hot_reconnect_lobby() {
     world[cl2].state.hot_reconnect();
     world.process_all_events();
 
-    // TODO:
-    // {
-    //     let p = world[cl1]
-    //         .state
-    //         .mtch()
-    //         .unwrap()
-    //         .participants
-    //         .iter()
-    //         .find(|p| p.name == "p2")
-    //         .unwrap();
-    //     assert_eq!(p.faction, Faction::Fixed(Team::Blue));
-    //     assert!(p.is_ready);
-    // }
+    {
+        let p = world[cl1]
+            .state
+            .mtch()
+            .unwrap()
+            .participants
+            .iter()
+            .find(|p| p.name == "p2")
+            .unwrap();
+        assert_eq!(p.faction, Faction::Fixed(Team::Blue));
+        assert!(p.is_ready);
+    }
+}
+
+// Test a situation when events were dispatched, but the connection dropped before they reached
+// the server. The client should replay them after reconnecting.
+#[test]
+fn hot_reconnect_replays_lost_events() {
+    let mut world = World::new();
+    let [cl1, cl2] = world.new_clients();
+
+    let mtch = world.new_match(cl1, "p1");
+    world[cl2].join(&mtch, "p2");
+    world.process_all_events();
+
+    world[cl2].state.set_faction(Faction::Fixed(Team::Blue));
+    world[cl2].state.send_chat_message("hi".to_owned(), ChatRecipient::All);
+    world[cl2].lose_outgoing_events();
+    world.reconnect_client(cl2);
+
+    world[cl2].state.hot_reconnect();
+    world.process_all_events();
+
+    let p = world[cl1].mtch().participants.iter().find(|p| p.name == "p2").unwrap();
+    assert_eq!(p.faction, Faction::Fixed(Team::Blue));
+    assert_eq!(world[cl1].chat_item_text(), ["hi"]);
+    assert_eq!(world[cl2].chat_item_text(), ["hi"]);
+}
+
+// Test a situation when events reached the server, but the connection dropped before the client
+// got the confirmation. Replaying the events must not apply them twice.
+#[test]
+fn hot_reconnect_does_not_duplicate_delivered_events() {
+    let mut world = World::new();
+    let (_, cl1, cl2, _cl3, _cl4) = world.default_clients();
+
+    world[cl1].make_turn("e4").unwrap();
+    world[cl1].state.send_chat_message("hi".to_owned(), ChatRecipient::All);
+    world.process_outgoing_events_for(cl1);
+    world.reconnect_client(cl1);
+
+    world[cl1].state.hot_reconnect();
+    world.process_all_events();
+
+    for cl in [cl1, cl2] {
+        assert_eq!(world[cl].local_game().turn_log().len(), 1);
+        assert!(world[cl].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+        assert_eq!(world[cl].chat_item_text(), ["hi"]);
+    }
 }
 
 // Test a situation when WebSocket connection was lost due to a network issue, but the client is
@@ -1042,6 +1264,226 @@ fn hot_reconnect_observer() {
     assert!(world[cl5].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
 }
 
+#[test]
+fn spectate() {
+    let mut world = World::new();
+    let (mtch, cl1, _cl2, _cl3, _cl4) = world.default_clients();
+
+    let [spec1, spec2, spec3] = world.new_clients();
+    world[spec1].state.spectate(mtch.clone());
+    world[spec2].state.spectate(mtch.clone());
+    world.process_all_events();
+    assert!(world[spec1].state.is_spectator());
+    assert_eq!(world[spec1].state.my_name(), None);
+
+    // Spectators are not participants.
+    for client_id in [cl1, spec1] {
+        let participants = world[client_id]
+            .mtch()
+            .participants
+            .iter()
+            .map(|p| p.name.clone())
+            .sorted()
+            .collect_vec();
+        assert_eq!(participants, ["p1", "p2", "p3", "p4"]);
+    }
+
+    // Spectators receive game updates, but not chat messages.
+    world[cl1].make_turn("e4").unwrap();
+    world[cl1].state.send_chat_message("hi all".to_owned(), ChatRecipient::All);
+    world.process_all_events();
+    assert!(world[spec1].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+    let chess_rules = world[spec1].mtch().rules.chess_rules.clone();
+    let game_index = world[spec1].state.game_state().map(|state| state.game_index);
+    let spectator_chat = world[spec1].mtch().chat.items("", &chess_rules, game_index);
+    assert!(!spectator_chat.iter().any(|item| item.text.contains("hi all")));
+
+    // The number of spectators is limited.
+    world[spec3].state.spectate(mtch.clone());
+    assert!(matches!(
+        world.process_events_for(spec3),
+        Err(client::ClientError::Ignorable(_))
+    ));
+
+    world[spec1].state.leave_match();
+    world.process_all_events();
+    world[spec3].state.spectate(mtch.clone());
+    world.process_all_events();
+    assert!(world[spec3].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+    assert_eq!(world[cl1].mtch().participants.len(), 4);
+}
+
+#[test]
+fn read_only_client() {
+    let mut world = World::new();
+    let (mtch, cl1, _cl2, cl3, _cl4) = world.default_clients();
+    let broadcast = world.new_read_only_client();
+    world[broadcast].state.spectate(mtch.clone());
+    world.process_all_events();
+    assert!(world[broadcast].state.is_read_only());
+    assert!(world[broadcast].state.is_spectator());
+
+    // Input methods are no-ops.
+    let state = &mut world[broadcast].state;
+    assert_eq!(
+        state.make_turn(DisplayBoard::Primary, TurnInput::Algebraic("e4".to_owned())),
+        Err(TurnError::NotPlayer)
+    );
+    state.cancel_preturn(DisplayBoard::Primary);
+    state.execute_input("hi all");
+    state.send_chat_message("hi all".to_owned(), ChatRecipient::All);
+    state.set_ready(false);
+    state.set_faction(Faction::Fixed(Team::Red));
+    state.set_show_rating(false);
+    state.resign();
+    state.resign_board();
+    state.request_abort();
+    state.join(mtch.clone());
+    assert!(state.next_outgoing_event().is_none());
+    assert!(state.game_state().unwrap().alt_game.local_turns().is_empty());
+    // Spectating events are still sent.
+    state.follow_player(None);
+    assert!(matches!(
+        state.next_outgoing_event(),
+        Some(BughouseClientEvent::FollowPlayer { player_name: None })
+    ));
+    world.process_all_events();
+    let chat = world[broadcast].mtch().chat.items("", &default_chess_rules(), None);
+    assert!(!chat.iter().any(|item| item.text.contains("hi all")));
+
+    // State updates are still applied.
+    world.replay_white_checkmates_black(cl1, cl3);
+    assert!(world[broadcast].local_game().board(A).grid()[Coord::F7].is(piece!(White Queen)));
+    assert!(!world[broadcast].alt_game().is_active());
+
+    // Chalk is neither drawn nor received.
+    let arrow = ChalkMark::Arrow { from: Coord::D2, to: Coord::D4 };
+    world[cl1].state.add_chalk_mark(DisplayBoard::Primary, arrow.clone());
+    world[broadcast].state.add_chalk_mark(DisplayBoard::Primary, arrow);
+    assert!(world[broadcast].state.next_outgoing_event().is_none());
+    let broadcast_name = world[broadcast].mtch().my_name.clone();
+    let chalkboard = &world[broadcast].state.game_state().unwrap().chalkboard;
+    assert!(chalkboard.drawings_by(&broadcast_name).is_none());
+    world.process_all_events();
+    let chalkboard = &world[broadcast].state.game_state().unwrap().chalkboard;
+    assert!(chalkboard.drawings_by("p1").is_none());
+}
+
+#[test]
+fn first_game_countdown() {
+    let mut world = World::new();
+    world.server.state.TEST_enable_countdown();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    for cl in [cl1, cl2, cl3, cl4] {
+        world[cl].state.set_ready(true);
+    }
+    world.process_all_events();
+    for cl in [cl1, cl2, cl3, cl4] {
+        assert_eq!(
+            world[cl].state.first_game_countdown_left(),
+            Some(FIRST_GAME_COUNTDOWN_DURATION)
+        );
+    }
+
+    // Un-readying cancels the countdown.
+    world[cl4].state.set_ready(false);
+    world.process_all_events();
+    for cl in [cl1, cl2, cl3, cl4] {
+        assert_eq!(world[cl].state.first_game_countdown_left(), None);
+    }
+    world.set_time(Duration::seconds(10));
+    world.process_all_events();
+    assert!(world[cl1].state.game_state().is_none());
+
+    // Clients show the value provided by the server.
+    world[cl4].state.set_ready(true);
+    world.process_all_events();
+    world.set_time(Duration::seconds(11));
+    world.process_all_events();
+    let time_left = FIRST_GAME_COUNTDOWN_DURATION - std::time::Duration::from_secs(1);
+    for cl in [cl1, cl2, cl3, cl4] {
+        assert_eq!(world[cl].state.first_game_countdown_left(), Some(time_left));
+        assert!(world[cl].state.game_state().is_none());
+    }
+
+    // The server starts the game when the countdown ends.
+    world.set_time(Duration::seconds(13));
+    world.process_all_events();
+    for cl in [cl1, cl2, cl3, cl4] {
+        assert!(world[cl].state.game_state().is_some());
+        assert_eq!(world[cl].state.first_game_countdown_left(), None);
+    }
+}
+
+#[test]
+fn simulated_latency() {
+    let mut world = World::new();
+    let (_, cl1, _cl2, cl3, _cl4) = world.default_clients();
+    let latency = std::time::Duration::from_secs(60);
+    world[cl1].state.set_simulated_latency(Some(latency));
+
+    world[cl1].make_turn("e4").unwrap();
+    world[cl1].state.send_chat_message("hi".to_owned(), ChatRecipient::All);
+    world.process_all_events();
+    assert!(world[cl1].my_board().grid()[Coord::E4].is(piece!(White Pawn)));
+    assert!(world[cl3].my_board().grid()[Coord::E4].is_none());
+
+    let later = Instant::now() + latency;
+    let events =
+        std::iter::from_fn(|| world[cl1].state.next_outgoing_event_at(later)).collect_vec();
+    let significant_events = events
+        .iter()
+        .filter(|event| !matches!(event, BughouseClientEvent::Ping))
+        .collect_vec();
+    assert!(matches!(significant_events.as_slice(), [
+        BughouseClientEvent::MakeTurn { .. },
+        BughouseClientEvent::SendChatMessage { .. },
+    ]));
+    let cl1_id = world[cl1].id.unwrap();
+    for event in events {
+        world.server.send_network_event(cl1_id, event);
+    }
+    world.process_all_events();
+    assert!(world[cl3].my_board().grid()[Coord::E4].is(piece!(White Pawn)));
+}
+
+#[test]
+fn simulated_latency_charged_to_clock() {
+    let mut world = World::new();
+    let (_, cl1, _cl2, cl3, _cl4) = world.default_clients();
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+
+    let latency = std::time::Duration::from_secs(10);
+    world[cl3].state.set_simulated_latency(Some(latency));
+    world[cl3].make_turn("e5").unwrap();
+    world.process_all_events();
+    assert!(world[cl1].my_board().grid()[Coord::E5].is_none());
+
+    world.set_time(Duration::seconds(10));
+    let later = Instant::now() + latency;
+    let events =
+        std::iter::from_fn(|| world[cl3].state.next_outgoing_event_at(later)).collect_vec();
+    let cl3_id = world[cl3].id.unwrap();
+    for event in events {
+        world.server.send_network_event(cl3_id, event);
+    }
+    world.process_all_events();
+
+    let t10 = GameInstant::from_game_duration(GameDuration::from_secs(10));
+    let alt_game = world[cl1].alt_game();
+    let game = alt_game.game_confirmed();
+    assert!(game.board(A).grid()[Coord::E5].is(piece!(Black Pawn)));
+    assert_eq!(game.last_turn_record().unwrap().time, t10);
+    assert_eq!(game.board(A).clock().time_left(Black, t10), GameDuration::from_secs(290));
+}
+
 #[test]
 fn hot_reconnect_game_over() {
     let mut world = World::new();
@@ -1058,6 +1500,146 @@ fn hot_reconnect_game_over() {
     );
 }
 
+#[test]
+fn missed_heartbeats_disconnect_client() {
+    let mut world = World::new_with_connection_health_check();
+    let [cl1, cl2] = world.new_clients();
+    world.process_all_events();
+
+    // A laggy client is kept as long as something arrives from it every now and then.
+    world.set_time(Duration::seconds(40));
+    let cl1_server_id = world[cl1].id.unwrap();
+    world.server.send_network_event(cl1_server_id, BughouseClientEvent::Ping);
+    world.set_time(Duration::seconds(80));
+    world.server.tick();
+    assert!(!world[cl1].incoming_rx.as_ref().unwrap().is_closed());
+    assert!(world[cl2].incoming_rx.as_ref().unwrap().is_closed());
+}
+
+#[test]
+fn switch_watched_matches() {
+    let mut world = World::new();
+    let (mtch1, m1_cl1, ..) = world.default_clients();
+    let (mtch2, m2_cl1, ..) = world.default_clients();
+    let watcher = world.new_client();
+    world[m1_cl1].make_turn("e4").unwrap();
+    world[m2_cl1].make_turn("d4").unwrap();
+    world.process_all_events();
+
+    world[watcher].state.watch_match(mtch1.clone());
+    world[watcher].state.watch_match(mtch2.clone());
+    world.process_all_events();
+    assert_eq!(world[watcher].state.watched_matches(), &[mtch1.clone(), mtch2.clone()]);
+    assert_eq!(world[watcher].state.match_id(), Some(&mtch2));
+    assert!(world[watcher].local_game().board(A).grid()[Coord::D4].is(piece!(White Pawn)));
+
+    world[watcher].state.next_watched_match();
+    world.process_all_events();
+    assert_eq!(world[watcher].state.match_id(), Some(&mtch1));
+    assert!(world[watcher].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+
+    // A finished game stays in the list and shows the final position.
+    world[m1_cl1].state.resign();
+    world.process_all_events();
+    world[watcher].state.previous_watched_match();
+    world.process_all_events();
+    assert_eq!(world[watcher].state.match_id(), Some(&mtch2));
+    world[watcher].state.previous_watched_match();
+    world.process_all_events();
+    assert_eq!(world[watcher].state.match_id(), Some(&mtch1));
+    assert!(!world[watcher].alt_game().is_active());
+    assert!(world[watcher].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+
+    // Removing the displayed match switches to the next one.
+    world[watcher].state.unwatch_match(&mtch1);
+    world.process_all_events();
+    assert_eq!(world[watcher].state.watched_matches(), std::slice::from_ref(&mtch2));
+    assert_eq!(world[watcher].state.match_id(), Some(&mtch2));
+
+    world[watcher].state.unwatch_match(&mtch2);
+    world.process_all_events();
+    assert!(world[watcher].state.mtch().is_none());
+}
+
+#[test]
+fn watched_match_does_not_pull_out_participant() {
+    let mut world = World::new();
+    let (mtch1, ..) = world.default_clients();
+    let (mtch2, m2_cl1, ..) = world.default_clients();
+    world[m2_cl1].state.watch_match(mtch1);
+    world.process_all_events();
+    assert_eq!(world[m2_cl1].state.match_id(), Some(&mtch2));
+    assert!(!world[m2_cl1].state.is_spectator());
+}
+
+#[test]
+fn follow_player() {
+    let mut world = World::new();
+    let [follower, bystander] = world.new_clients();
+    world[follower].state.follow_player(Some("p1".to_owned()));
+    world[bystander].state.follow_player(Some("p1".to_owned()));
+    world[bystander].state.follow_player(None);
+    world.process_all_events();
+
+    let (mtch1, cl1, cl2, cl3, cl4) = world.default_clients();
+    world.process_all_events();
+    assert!(world[follower].state.is_spectator());
+    assert_eq!(world[follower].state.match_id(), Some(&mtch1));
+    assert!(world[follower].state.game_state().is_some());
+    assert!(world[bystander].state.mtch().is_none());
+
+    // The followed player finishes the game and starts a new one in another match.
+    world[cl1].state.resign();
+    world.process_all_events();
+    for cl in [cl1, cl2, cl3, cl4] {
+        world[cl].state.leave_match();
+    }
+    world.process_all_events();
+    let mtch2 = world.new_match(cl1, "p1");
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch2, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch2, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch2, "p4", Team::Blue);
+    world.process_all_events();
+    assert_eq!(world[follower].state.match_id(), Some(&mtch1));
+    world.new_game_with_default_board_assignment(mtch2.clone(), cl1, cl2, cl3, cl4);
+    world.process_all_events();
+    assert!(world[follower].state.is_spectator());
+    assert_eq!(world[follower].state.match_id(), Some(&mtch2));
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+    assert!(world[follower].local_game().board(A).grid()[Coord::E4].is(piece!(White Pawn)));
+}
+
+#[test]
+fn follow_player_private_match() {
+    let mut world = World::new();
+    let [follower, cl1, cl2, cl3, cl4] = world.new_clients();
+    world[follower].state.follow_player(Some("p1".to_owned()));
+    world.process_all_events();
+
+    let private_rules = Rules {
+        match_rules: MatchRules {
+            public: false,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(private_rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    world.process_all_events();
+    assert!(world[cl1].state.game_state().is_some());
+    assert!(world[follower].state.mtch().is_none());
+}
+
 // Regression test: client used to report match id mismatch when trying to join a match on slow
 // internet and then trying to join another match before the first request was processed.
 #[test]
@@ -1176,11 +1758,46 @@ fn time_forfeit() {
     assert!(!game.board(B).clock().time_left(White, t).is_zero());
 }
 
+// Server time is monotonic, but if it ever goes backwards, turns must not be recorded before the
+// previous turn: this would corrupt the clocks.
 #[test]
-fn three_players() {
-    use DisplayBoard::*;
-
+fn turn_time_clamped_to_previous_turn() {
     let mut world = World::new();
+    let (_, cl1, _cl2, cl3, _cl4) = world.default_clients();
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+    world.set_time(Duration::seconds(10));
+    world[cl3].make_turn("e5").unwrap();
+    world.process_all_events();
+    world.set_time(Duration::seconds(5));
+    world[cl1].make_turn("Nf3").unwrap();
+    world.process_all_events();
+
+    let t10 = GameInstant::from_game_duration(GameDuration::from_secs(10));
+    {
+        let alt_game = world[cl1].alt_game();
+        let game = alt_game.game_confirmed();
+        assert_eq!(game.last_turn_record().unwrap().time, t10);
+        let clock = game.board(A).clock();
+        assert_eq!(clock.time_left(White, t10), GameDuration::from_secs(300));
+        assert_eq!(clock.time_left(Black, t10), GameDuration::from_secs(290));
+    }
+
+    // The game goes on normally.
+    world.set_time(Duration::seconds(12));
+    world[cl3].make_turn("Nc6").unwrap();
+    world.process_all_events();
+    let t12 = GameInstant::from_game_duration(GameDuration::from_secs(12));
+    let alt_game = world[cl1].alt_game();
+    let game = alt_game.game_confirmed();
+    assert_eq!(game.last_turn_record().unwrap().time, t12);
+    assert_eq!(game.board(A).clock().time_left(Black, t12), GameDuration::from_secs(288));
+}
+
+// Starts a game where "p1" plays White on board A, "p2" plays Black on board B and "p3" plays both
+// boards for the Blue team.
+fn three_players_game(world: &mut World) -> [TestClientId; 3] {
     let [cl1, cl2, cl3] = world.new_clients();
 
     let mtch = world.new_match(cl1, "p1");
@@ -1199,6 +1816,15 @@ fn three_players() {
         world[*cl].state.set_ready(true);
     }
     world.process_all_events();
+    [cl1, cl2, cl3]
+}
+
+#[test]
+fn three_players() {
+    use DisplayBoard::*;
+
+    let mut world = World::new();
+    let [cl1, cl2, cl3] = three_players_game(&mut world);
 
     // For a double-player the board where they play White is always primary, thus
     // for p3: A is Secondary, B is Primary.
@@ -1217,6 +1843,48 @@ fn three_players() {
     assert!(world[cl2].local_game().board(B).grid()[Coord::C3].is(piece!(White Knight)));
 }
 
+#[test]
+fn board_focus_routes_turn_commands() {
+    use DisplayBoard::*;
+
+    let mut world = World::new();
+    let [cl1, cl2, cl3] = three_players_game(&mut world);
+
+    // For p3: A is Secondary, B is Primary.
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+    assert_eq!(world[cl3].state.focused_board(), None);
+    world[cl3].state.focus_board(Some(Secondary));
+    world[cl3].state.execute_input("=e5");
+    assert_eq!(world[cl3].state.focus_next_board(), Primary);
+    world[cl3].state.execute_input("=Nc3");
+    world.process_all_events();
+    assert!(world[cl2].local_game().board(A).grid()[Coord::E5].is(piece!(Black Pawn)));
+    assert!(world[cl2].local_game().board(B).grid()[Coord::C3].is(piece!(White Knight)));
+
+    // Without focus "=" targets the primary board.
+    world[cl3].state.focus_board(None);
+    world[cl2].make_turn("e5").unwrap();
+    world.process_all_events();
+    world[cl3].state.execute_input("=Nf3");
+    world.process_all_events();
+    assert!(world[cl2].local_game().board(B).grid()[Coord::F3].is(piece!(White Knight)));
+
+    // Preturn cancellation only affects the focused board. Focus is what tapping a clock sets.
+    world[cl3].state.focus_board(Some(Secondary));
+    world[cl3].state.execute_input("=Nc6");
+    world[cl3].state.focus_board(Some(Primary));
+    world[cl3].state.execute_input("=d4");
+    world[cl3].state.focus_board(Some(Secondary));
+    world[cl3].state.execute_input("=-");
+    world.process_all_events();
+    world[cl1].make_turn("d4").unwrap();
+    world[cl2].make_turn("d6").unwrap();
+    world.process_all_events();
+    assert!(world[cl2].local_game().board(A).grid()[Coord::C6].is_none());
+    assert!(world[cl2].local_game().board(B).grid()[Coord::D4].is(piece!(White Pawn)));
+}
+
 #[test]
 fn five_players() {
     let mut world = World::new();
@@ -1295,6 +1963,116 @@ fn two_matches() {
     assert!(world[cl6].local_game().board(A).grid()[Coord::C3].is(piece!(White Knight)));
 }
 
+#[test]
+fn live_games_exclude_private_matches() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4, cl5, cl6, cl7, cl8] = world.new_clients();
+
+    let public_match = world.new_match(cl1, "p1");
+    let private_rules = Rules {
+        match_rules: MatchRules {
+            public: false,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl5].state.set_guest_player_name(Some("p5".to_owned()));
+    world[cl5].state.new_match(private_rules);
+    world.process_all_events();
+    let private_match = world[cl5].state.match_id().unwrap().clone();
+
+    world.join_and_set_team(cl2, &public_match, "p2", Team::Red);
+    world.join_and_set_team(cl3, &public_match, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &public_match, "p4", Team::Blue);
+    world.join_and_set_team(cl6, &private_match, "p6", Team::Red);
+    world.join_and_set_team(cl7, &private_match, "p7", Team::Blue);
+    world.join_and_set_team(cl8, &private_match, "p8", Team::Blue);
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world[cl5].state.set_faction(Faction::Fixed(Team::Red));
+    world.process_all_events();
+    let live_games =
+        |world: &World| async_std::task::block_on(world.server.info.lock()).live_games.clone();
+    assert!(live_games(&world).is_empty());
+
+    for cl in [cl1, cl2, cl3, cl4, cl5, cl6, cl7, cl8] {
+        world[cl].state.set_ready(true);
+    }
+    world.process_all_events();
+    let games = live_games(&world);
+    assert_eq!(games.len(), 1);
+    assert_eq!(games[0].match_id, public_match);
+    assert_eq!(games[0].players.len(), 4);
+    assert!(games[0].elapsed_ms.is_none());
+    assert!(games.iter().all(|g| g.match_id != private_match));
+}
+
+#[test]
+fn open_matches_exclude_private_matches() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+
+    let public_match = world.new_match(cl1, "p1");
+    let private_rules = Rules {
+        match_rules: MatchRules {
+            public: false,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl3].state.set_guest_player_name(Some("p3".to_owned()));
+    world[cl3].state.new_match(private_rules);
+    world.process_all_events();
+    let private_match = world[cl3].state.match_id().unwrap().clone();
+
+    world.join_and_set_team(cl2, &public_match, "p2", Team::Red);
+    world[cl4].join(&private_match, "p4");
+    world[cl1].state.set_faction(Faction::Observer);
+    world.process_all_events();
+
+    let matches = async_std::task::block_on(world.server.info.lock()).open_matches.clone();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].match_id, public_match);
+    assert_eq!(matches[0].num_players, 1);
+    assert_eq!(matches[0].num_observers, 1);
+    assert!(!matches[0].started);
+}
+
+#[test]
+fn clone_match_rules() {
+    let mut world = World::new();
+    let [cl1, cl2] = world.new_clients();
+
+    let mut rules = Rules {
+        match_rules: MatchRules {
+            rated_game_chat: RatedGameChat::TeamOnly,
+            abort_window: Some(3),
+            match_target: Some(MatchTarget::Games(5)),
+            winner_stays: true,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: ChessRules {
+            duck_chess: true,
+            ..default_chess_rules()
+        },
+    };
+    rules.chess_rules.bughouse_rules.as_mut().unwrap().drop_aggression =
+        DropAggression::MateAllowed;
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules.clone());
+    world.process_all_events();
+    assert_eq!(world[cl1].state.clone_match_rules(), Ok(rules.clone()));
+
+    world[cl1].state.leave_match();
+    world.process_all_events();
+    let cloned_rules = world[cl1].state.clone_match_rules().unwrap();
+    assert_eq!(cloned_rules, rules);
+
+    world[cl2].state.set_guest_player_name(Some("p2".to_owned()));
+    world[cl2].state.new_match(cloned_rules);
+    world.process_all_events();
+    assert_eq!(world[cl2].mtch().rules, rules);
+}
+
 #[test]
 fn seating_assignment_is_fair() {
     let mut world = World::new();
@@ -1354,6 +2132,44 @@ fn shared_wayback() {
     assert_eq!(world[cl3].alt_game().wayback_state().turn_index(), None);
 }
 
+#[test]
+fn chalk_bound_to_wayback_position() {
+    let mut world = World::new();
+    let (_, cl1, cl2, cl3, _cl4) = world.default_clients();
+    world.replay_white_checkmates_black(cl1, cl3);
+    let arrow_1 = ChalkMark::Arrow { from: Coord::E2, to: Coord::E4 };
+    let arrow_3 = ChalkMark::Arrow { from: Coord::D2, to: Coord::D4 };
+    let marks = |world: &World, cl: TestClientId| {
+        let game_state = world[cl].state.game_state().unwrap();
+        game_state
+            .chalkboard
+            .drawings_by("p1")
+            .map_or(vec![], |d| d.board(BughouseBoard::A).clone())
+    };
+
+    world[cl1].state.wayback_to(WaybackDestination::Index(Some(TurnIndex(1))), None);
+    world[cl1].state.add_chalk_mark(DisplayBoard::Primary, arrow_1.clone());
+    world[cl1].state.wayback_to(WaybackDestination::Index(Some(TurnIndex(3))), None);
+    assert!(marks(&world, cl1).is_empty());
+    world[cl1].state.add_chalk_mark(DisplayBoard::Primary, arrow_3.clone());
+    world.process_all_events();
+    assert_eq!(marks(&world, cl2), vec![arrow_3.clone()]);
+
+    world[cl1].state.wayback_to(WaybackDestination::Index(Some(TurnIndex(1))), None);
+    world.process_all_events();
+    assert_eq!(marks(&world, cl1), vec![arrow_1.clone()]);
+    assert_eq!(marks(&world, cl2), vec![arrow_1]);
+
+    world[cl1].state.wayback_to(WaybackDestination::Index(Some(TurnIndex(3))), None);
+    world.process_all_events();
+    assert_eq!(marks(&world, cl1), vec![arrow_3.clone()]);
+    assert_eq!(marks(&world, cl2), vec![arrow_3]);
+
+    world[cl1].state.wayback_to(WaybackDestination::Last, None);
+    world.process_all_events();
+    assert!(marks(&world, cl2).is_empty());
+}
+
 #[test]
 fn chat_basic() {
     let mut world = World::new();
@@ -1389,6 +2205,62 @@ fn chat_ephemeral_message() {
     assert_eq!(world[cl1].chat_item_text(), ["hi"]);
 }
 
+#[test]
+fn chat_history_on_join_lobby() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3] = world.new_clients();
+
+    let mtch = world.new_match(cl1, "p1");
+    world[cl2].join(&mtch, "p2");
+    world.process_all_events();
+    world[cl1].state.send_chat_message("hi all".to_owned(), ChatRecipient::All);
+    world[cl1]
+        .state
+        .send_chat_message("hi p2".to_owned(), ChatRecipient::Participant("p2".to_owned()));
+    world.process_all_events();
+
+    world[cl3].join(&mtch, "p3");
+    world.process_all_events();
+    assert_eq!(world[cl3].chat_item_text(), ["hi all"]);
+}
+
+#[test]
+fn chat_history_on_join_game() {
+    let mut world = World::new();
+    let (mtch, cl1, _cl2, cl3, _cl4) = world.default_clients();
+
+    world[cl1].state.send_chat_message("hi all".to_owned(), ChatRecipient::All);
+    world.process_all_events();
+    world[cl1].state.send_chat_message("hi red".to_owned(), ChatRecipient::Team);
+    world.process_all_events();
+    world[cl3].state.send_chat_message("hi blue".to_owned(), ChatRecipient::Team);
+    world.process_all_events();
+
+    let cl5 = world.new_client();
+    world[cl5].join(&mtch, "p5");
+    world.process_all_events();
+    assert_eq!(world[cl5].chat_item_text(), ["hi all"]);
+}
+
+#[test]
+fn chat_history_is_bounded() {
+    let mut world = World::new();
+    let [cl1, cl2] = world.new_clients();
+
+    let mtch = world.new_match(cl1, "p1");
+    let num_messages = MAX_CHAT_MESSAGES_PER_MATCH + 5;
+    for i in 0..num_messages {
+        world[cl1].state.send_chat_message(i.to_string(), ChatRecipient::All);
+        world.process_all_events();
+    }
+    assert_eq!(world[cl1].chat_item_text().len(), num_messages);
+
+    world[cl2].join(&mtch, "p2");
+    world.process_all_events();
+    let expected = (5..num_messages).map(|i| i.to_string()).collect_vec();
+    assert_eq!(world[cl2].chat_item_text(), expected);
+}
+
 // All clients should eventually see chat messages in the same order determined by the server.
 #[test]
 fn chat_message_order() {
@@ -1624,117 +2496,1113 @@ fn registered_user_reconnect() {
     world.process_all_events();
 }
 
-// Register user can kick a guest user with the same name.
 #[test]
-fn registered_user_after_guest_user() {
+fn registered_only_match() {
     let mut world = World::new();
+    let cl1 = world.new_client_registered_user("p1");
+    let rules = Rules {
+        match_rules: MatchRules {
+            registered_only: true,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
 
-    let cl_old = world.new_client();
-    let mtch = world.new_match(cl_old, "Alice");
-
-    let cl_new = world.new_client_registered_user("Alice");
-    world[cl_new].join(&mtch, "Alice");
-
-    world.process_events_for(cl_new).unwrap();
+    let guest = world.new_client();
+    world[guest].join(&mtch, "guest");
     assert!(matches!(
-        world.process_events_for(cl_old),
-        Err(client::ClientError::KickedFromMatch(_))
+        world.process_events_for(guest),
+        Err(client::ClientError::Ignorable(_))
     ));
     world.process_all_events();
-}
 
-// Guest user cannot connect if there is a registered user with the same name.
-#[test]
-fn guest_user_after_registered_user() {
-    let mut world = World::new();
+    let cl2 = world.new_client_registered_user("p2");
+    let cl3 = world.new_client_registered_user("p3");
+    let cl4 = world.new_client_registered_user("p4");
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    assert!(!world[cl1].mtch().participants.iter().any(|p| p.name == "guest"));
 
-    let cl_old = world.new_client_registered_user("Alice");
-    let mtch = world.new_match(cl_old, "Alice");
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    assert!(world[cl1].state.game_state().is_some());
+}
 
-    let cl_new = world.new_client();
-    world[cl_new].join(&mtch, "Alice");
+#[test]
+fn player_ratings_visible_unless_hidden() {
+    let hooks = RecordingServerHooks {
+        ratings: HashMap::from([("p1".to_owned(), 1650), ("p2".to_owned(), 1420)]),
+        ..Default::default()
+    };
+    let mut world = World::new_with_hooks(Arc::new(hooks));
+    let [cl1, cl2] = ["p1", "p2"].map(|name| world.new_client_registered_user(name));
+    let cl3 = world.new_client();
+    world[cl2].state.set_show_rating(false);
 
-    assert!(matches!(
-        world.process_events_for(cl_new),
-        Err(client::ClientError::Ignorable(_))
-    ));
+    let mtch = world.new_match(cl1, "p1");
+    world[cl3].join(&mtch, "p3");
     world.process_all_events();
-}
 
-// Guest user cannot kick out other guest user if their client is still alive. Of course, usually
-// this would be ok. Most likely this is the same person trying to reconnect before the game
-// realized that there was a problem with the old connection. However we cannot allow this, because
-// it would allow others to steal guest user identities.
-#[test]
-fn guest_user_reconnect_early() {
-    let mut world = World::new();
-    let [cl_old, cl_new] = world.new_clients();
+    let rating = |world: &World, name: &str| {
+        world[cl3].mtch().participants.iter().find(|p| p.name == name).unwrap().rating
+    };
+    // The setting is sent with the join request, so the rating is not revealed even briefly.
+    world[cl2].join(&mtch, "p2");
+    world.process_outgoing_events_for(cl2);
+    world.process_incoming_events_for(cl3).1.unwrap();
+    assert_eq!(rating(&world, "p2"), None);
 
-    let mtch = world.new_match(cl_old, "Alice");
-    world[cl_new].join(&mtch, "Alice");
+    world.process_all_events();
+    assert_eq!(rating(&world, "p1"), Some(1650));
+    assert_eq!(rating(&world, "p2"), None); // hidden by the player
+    assert_eq!(rating(&world, "p3"), None); // guests have no rating
 
-    assert!(matches!(
-        world.process_events_for(cl_new),
-        Err(client::ClientError::Ignorable(_))
-    ));
+    world[cl2].state.set_show_rating(true);
     world.process_all_events();
+    assert_eq!(rating(&world, "p2"), Some(1420));
 }
 
-// Guest user can reconnect if the old client is dead.
 #[test]
-fn guest_user_reconnect_ok() {
+fn anonymous_guests_get_stable_labels() {
     let mut world = World::new();
-    let [cl_old, cl_new] = world.new_clients();
-
-    let mtch = world.new_match(cl_old, "Alice");
-    world.disconnect_client(cl_old);
+    let [cl1, cl2, cl3] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    for cl in [cl2, cl3] {
+        world[cl].state.set_anonymous_guest(true);
+        world[cl].state.join(mtch.clone());
+    }
     world.process_all_events();
+    assert_eq!(world[cl2].state.my_name(), Some("Guest-A"));
+    assert_eq!(world[cl3].state.my_name(), Some("Guest-B"));
+    let participant_names = |world: &World| {
+        world[cl1]
+            .mtch()
+            .participants
+            .iter()
+            .map(|p| p.name.clone())
+            .sorted()
+            .collect_vec()
+    };
+    assert_eq!(participant_names(&world), ["Guest-A", "Guest-B", "p1"]);
 
-    world[cl_new].join(&mtch, "Alice");
+    // The label survives reconnection.
+    world.reconnect_client(cl2);
+    world[cl2].state.hot_reconnect();
     world.process_all_events();
+    assert_eq!(world[cl2].state.my_name(), Some("Guest-A"));
+    assert_eq!(participant_names(&world), ["Guest-A", "Guest-B", "p1"]);
 }
 
-// Regression test: server should not panic when an active client tries to join their current match.
 #[test]
-fn rejoin_from_live_client() {
+fn anonymous_guest_label_cannot_be_forged() {
     let mut world = World::new();
-    let (mtch, cl1, cl2, _cl3, _cl4) = world.default_clients();
-    world[cl1].join(&mtch, "p1");
-    world.process_all_events();
-    assert_eq!(world[cl2].mtch().participants.len(), 4);
-    assert!(world[cl2].mtch().participants.iter().all(|p| p.is_online));
+    let [cl1, cl2] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world[cl2].state.set_anonymous_guest(true);
+    world[cl2].state.join(mtch.clone());
+    world.process_all_events();
+    assert_eq!(world[cl2].state.my_name(), Some("Guest-A"));
+
+    // Reconnecting with a label that was never issued is treated as a regular name and rejected.
+    let forger = world.new_client();
+    let forger_server_id = world[forger].id.unwrap();
+    world
+        .server
+        .send_network_event(forger_server_id, BughouseClientEvent::HotReconnect {
+            match_id: mtch.clone(),
+            player_name: "Guest-Q".to_owned(),
+            show_rating: true,
+        });
+    assert!(world.process_incoming_events_for(forger).1.is_err());
+    world.process_all_events();
+    let names = world[cl1]
+        .mtch()
+        .participants
+        .iter()
+        .map(|p| p.name.clone())
+        .sorted()
+        .collect_vec();
+    assert_eq!(names, ["Guest-A", "p1"]);
 }
 
+// Players should only see their team's messages during a rated game, but everything afterwards.
 #[test]
-fn mid_match_faction_change() {
+fn rated_game_chat_team_only() {
     let mut world = World::new();
-    let (mtch, cl1, _cl2, _cl3, _cl4) = world.default_clients();
-
-    world[cl1].make_turn("e4").unwrap();
+    let [cl1, cl2, cl3, cl4, cl5] =
+        ["p1", "p2", "p3", "p4", "p5"].map(|name| world.new_client_registered_user(name));
+    let rules = Rules {
+        match_rules: MatchRules {
+            rated: true,
+            rated_game_chat: RatedGameChat::TeamOnly,
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
     world.process_all_events();
-
-    let cl5 = world.new_client();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
     world[cl5].join(&mtch, "p5");
+    world.process_events_for(cl5).unwrap();
+    world[cl5].state.set_faction(Faction::Observer);
     world.process_all_events();
-    let p5_view = world[cl1].mtch().participants.last().unwrap();
-    assert_eq!(p5_view.name, "p5");
-    assert_eq!(p5_view.faction, Faction::Observer);
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
 
-    world[cl5].state.set_faction(Faction::Fixed(Team::Red));
+    world[cl5].state.send_chat_message("coach".to_owned(), ChatRecipient::All);
+    world.process_all_events();
+    world[cl2].state.send_chat_message("team".to_owned(), ChatRecipient::Team);
+    world.process_all_events();
+    world[cl3].state.send_chat_message("hi".to_owned(), ChatRecipient::All);
     world.process_all_events();
 
+    assert_eq!(world[cl1].chat_item_text(), ["team"]);
+    assert_eq!(world[cl2].chat_item_text(), ["team"]);
+    assert_eq!(world[cl3].chat_item_text(), ["hi"]);
+    assert_eq!(world[cl4].chat_item_text(), ["hi"]);
+    assert_eq!(world[cl5].chat_item_text(), ["coach", "hi"]);
+
     world[cl1].state.resign();
     world.process_all_events();
 
-    world[cl1].state.set_faction(Faction::Observer);
+    let over = "Game over! p3 & p4 won: p1 & p2 resigned.";
+    assert_eq!(world[cl1].chat_item_text(), ["coach", "team", "hi", over]);
+    assert_eq!(world[cl2].chat_item_text(), ["coach", "team", "hi", over]);
+    assert_eq!(world[cl3].chat_item_text(), ["coach", "hi", over]);
+    assert_eq!(world[cl4].chat_item_text(), ["coach", "hi", over]);
+    assert_eq!(world[cl5].chat_item_text(), ["coach", "hi", over]);
+}
+
+#[test]
+fn chat_slow_mode() {
+    let mut world = World::new();
+    let [cl1, cl2] = world.new_clients();
+    let rules = Rules {
+        match_rules: MatchRules {
+            chat_slow_mode: Some(std::time::Duration::from_secs(10)),
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl2].join(&mtch, "p2");
     world.process_all_events();
 
-    let mut chat_iter = world[cl1].chat_item_text().into_iter();
-    assert_eq!(chat_iter.next().unwrap(), "p5 joined team Red");
-    assert_eq!(chat_iter.next().unwrap(), "Game over! p3 & p4 won: p1 & p2 resigned.");
-    assert!(chat_iter.next().unwrap().starts_with("Next up:"));
-    assert_eq!(chat_iter.next().unwrap(), "p1 became an observer");
-    assert!(chat_iter.next().is_none());
-    // Ideally we would want to get another "Next up" message at this point, but this not
+    world[cl1].state.send_chat_message("hi".to_owned(), ChatRecipient::All);
+    world.process_all_events();
+    assert_eq!(world[cl2].chat_item_text(), ["hi"]);
+
+    // The client doesn't send the message while on cooldown.
+    world[cl1].state.send_chat_message("again".to_owned(), ChatRecipient::All);
+    assert!(world[cl1].state.chat_cooldown_left().is_some());
+    world.process_all_events();
+    assert_eq!(world[cl2].chat_item_text(), ["hi"]);
+
+    // The server rejects the message even if the client sends it anyway.
+    let cl1_server_id = world[cl1].id.unwrap();
+    let send_raw = |world: &mut World, local_message_id, text: &str| {
+        world
+            .server
+            .send_network_event(cl1_server_id, BughouseClientEvent::SendChatMessage {
+                message: OutgoingChatMessage {
+                    local_message_id,
+                    recipient: ChatRecipient::All,
+                    text: text.to_owned(),
+                },
+            });
+        world.process_all_events();
+    };
+    send_raw(&mut world, 100, "sneaky");
+    assert_eq!(world[cl2].chat_item_text(), ["hi"]);
+
+    world.set_time(Duration::seconds(10));
+    send_raw(&mut world, 101, "later");
+    assert_eq!(world[cl2].chat_item_text(), ["hi", "later"]);
+}
+
+// The server must enforce the premove limit even if a client ignores it.
+#[test]
+fn rated_preturns_disabled() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] =
+        ["p1", "p2", "p3", "p4"].map(|name| world.new_client_registered_user(name));
+    let rules = Rules {
+        match_rules: MatchRules {
+            rated: true,
+            rated_preturn_limit: Some(0),
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+
+    assert_eq!(world[cl3].make_turn("e5"), Err(TurnError::PreturnLimitReached));
+    let cl3_server_id = world[cl3].id.unwrap();
+    world.server.send_network_event(cl3_server_id, BughouseClientEvent::MakeTurn {
+        board_idx: A,
+        turn_input: algebraic_turn("e5"),
+    });
+    assert!(world.process_incoming_events_for(cl3).1.is_err());
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+    assert!(world[cl1].my_board().grid()[Coord::E7].is(piece!(Black Pawn)));
+    assert!(world[cl1].my_board().grid()[Coord::E5].is_none());
+    world[cl3].make_turn("e5").unwrap();
+    world.process_all_events();
+    assert!(world[cl1].my_board().grid()[Coord::E5].is(piece!(Black Pawn)));
+}
+
+fn abort_match(world: &mut World, abort_window: u8) -> [TestClientId; 4] {
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let rules = Rules {
+        match_rules: MatchRules {
+            abort_window: Some(abort_window),
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    [cl1, cl2, cl3, cl4]
+}
+
+#[test]
+fn abort_within_window() {
+    let hooks = Arc::new(RecordingServerHooks::default());
+    let mut world = World::new_with_hooks(Arc::clone(&hooks) as _);
+    let [cl1, cl2, cl3, cl4] = abort_match(&mut world, 2);
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+
+    // One team is not enough.
+    world[cl1].state.request_abort();
+    world[cl2].state.request_abort();
+    world.process_all_events();
+    assert!(world[cl1].alt_game().is_active());
+
+    world[cl3].state.request_abort();
+    world.process_all_events();
+    assert_eq!(world[cl1].alt_game().status(), BughouseGameStatus::Draw(DrawReason::Aborted));
+    let scores = match world[cl1].state.mtch().as_ref().unwrap().scores.as_ref().unwrap() {
+        Scores::PerTeam(v) => *v,
+        _ => panic!("Expected Scores::PerTeam"),
+    };
+    assert_eq!(scores[Team::Red].as_f64(), 0.0);
+    assert_eq!(scores[Team::Blue].as_f64(), 0.0);
+    let participants = &world[cl1].state.mtch().unwrap().participants;
+    assert!(participants.iter().all(|p| p.games_played == 0));
+
+    // Play another game to make sure the hooks had a chance to record the previous one.
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    world[cl1].state.resign();
+    world.process_all_events();
+    let deadline = Instant::now() + std::time::Duration::from_secs(10);
+    while hooks.finished_games.lock().unwrap().is_empty() && Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(*hooks.finished_games.lock().unwrap(), [BughouseGameStatus::Victory(
+        Team::Blue,
+        VictoryReason::Resignation
+    )]);
+}
+
+#[test]
+fn abort_refused_after_window() {
+    let mut world = World::new();
+    let [cl1, _cl2, cl3, _cl4] = abort_match(&mut world, 1);
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+    world[cl3].make_turn("e5").unwrap();
+    world.process_all_events();
+    world[cl1].make_turn("Nf3").unwrap();
+    world.process_all_events();
+
+    world[cl1].state.request_abort();
+    world.process_outgoing_events_for(cl1);
+    assert!(world.process_incoming_events_for(cl1).1.is_err());
+    world[cl3].state.request_abort();
+    world.process_outgoing_events_for(cl3);
+    assert!(world.process_incoming_events_for(cl3).1.is_err());
+    assert!(world[cl1].alt_game().is_active());
+}
+
+#[test]
+fn resign_board() {
+    let mut world = World::new();
+    let mut chess_rules = default_chess_rules();
+    chess_rules.bughouse_rules.as_mut().unwrap().board_resignation = true;
+    let (_, cl1, cl2, cl3, cl4) = world.default_clients_with_rules(chess_rules);
+
+    world[cl3].make_turn("e5").unwrap();
+    world.process_all_events();
+    assert!(world[cl3].state.can_resign_board());
+    world[cl3].state.resign_board();
+    world.process_all_events();
+    assert!(world[cl1].alt_game().is_active());
+    assert!(world[cl1].alt_game().game_confirmed().is_board_resigned(BughouseBoard::A));
+    assert!(!world[cl3].state.can_resign_board());
+    // The preturn on the resigned board is discarded.
+    assert!(world[cl3].my_board().grid()[Coord::E5].is_none());
+    assert!(world[cl1].make_turn("e4").is_err());
+
+    // Each team won a board.
+    world.replay_white_checkmates_black(cl4, cl2);
+    for cl in [cl1, cl2, cl3, cl4] {
+        assert_eq!(
+            world[cl].alt_game().status(),
+            BughouseGameStatus::Draw(DrawReason::SplitBoards)
+        );
+    }
+}
+
+#[test]
+fn resign_board_not_allowed() {
+    let mut world = World::new();
+    let (_, cl1, _cl2, _cl3, _cl4) = world.default_clients();
+
+    assert!(!world[cl1].state.can_resign_board());
+    let cl1_server_id = world[cl1].id.unwrap();
+    world.server.send_network_event(cl1_server_id, BughouseClientEvent::ResignBoard);
+    assert!(world.process_incoming_events_for(cl1).1.is_err());
+    assert!(world[cl1].alt_game().is_active());
+}
+
+#[test]
+fn scheduled_match() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules: default_chess_rules(),
+    };
+    let start = UtcDateTime::from(OffsetDateTime::now_utc() + Duration::minutes(10));
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_scheduled_match(rules, start);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.process_all_events();
+    assert!(world[cl2].state.scheduled_start_left().is_some());
+
+    // Participants are held until the scheduled start, even if everybody is ready.
+    for cl in [cl1, cl2, cl3] {
+        world[cl].state.set_ready(true);
+    }
+    world.process_all_events();
+    world.set_time(Duration::minutes(5));
+    world.process_all_events();
+    assert!(world[cl1].state.game_state().is_none());
+
+    // Late joiners are allowed until the start.
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    assert!(world[cl4].state.game_state().is_none());
+
+    // Readiness is not required at the scheduled time.
+    world[cl1].state.set_ready(false);
+    world.set_time(Duration::minutes(11));
+    world.process_all_events();
+    assert!(world[cl4].state.game_state().is_some());
+    assert!(world[cl4].state.scheduled_start_left().is_none());
+    assert_eq!(world[cl1].local_game().players().len(), 4);
+}
+
+#[test]
+fn scheduled_match_not_enough_players() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules: default_chess_rules(),
+    };
+
+    let past = UtcDateTime::from(OffsetDateTime::now_utc() - Duration::minutes(1));
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_scheduled_match(rules.clone(), past);
+    assert!(matches!(world.process_events_for(cl1), Err(client::ClientError::Ignorable(_))));
+
+    let start = UtcDateTime::from(OffsetDateTime::now_utc() + Duration::minutes(10));
+    world[cl1].state.new_scheduled_match(rules, start);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.process_all_events();
+
+    // Not enough players at the scheduled time: keep waiting.
+    world.set_time(Duration::minutes(11));
+    world.process_all_events();
+    assert!(world[cl1].state.game_state().is_none());
+    assert!(world[cl1].state.scheduled_start_left().is_none());
+
+    // From now on the match behaves as a regular one.
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    assert!(world[cl1].state.game_state().is_none());
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    assert!(world[cl1].state.game_state().is_some());
+}
+
+// Register user can kick a guest user with the same name.
+#[test]
+fn registered_user_after_guest_user() {
+    let mut world = World::new();
+
+    let cl_old = world.new_client();
+    let mtch = world.new_match(cl_old, "Alice");
+
+    let cl_new = world.new_client_registered_user("Alice");
+    world[cl_new].join(&mtch, "Alice");
+
+    world.process_events_for(cl_new).unwrap();
+    assert!(matches!(
+        world.process_events_for(cl_old),
+        Err(client::ClientError::KickedFromMatch(_))
+    ));
+    world.process_all_events();
+}
+
+// Guest user cannot connect if there is a registered user with the same name.
+#[test]
+fn guest_user_after_registered_user() {
+    let mut world = World::new();
+
+    let cl_old = world.new_client_registered_user("Alice");
+    let mtch = world.new_match(cl_old, "Alice");
+
+    let cl_new = world.new_client();
+    world[cl_new].join(&mtch, "Alice");
+
+    assert!(matches!(
+        world.process_events_for(cl_new),
+        Err(client::ClientError::Ignorable(_))
+    ));
+    world.process_all_events();
+}
+
+// Guest user cannot kick out other guest user if their client is still alive. Of course, usually
+// this would be ok. Most likely this is the same person trying to reconnect before the game
+// realized that there was a problem with the old connection. However we cannot allow this, because
+// it would allow others to steal guest user identities.
+#[test]
+fn guest_user_reconnect_early() {
+    let mut world = World::new();
+    let [cl_old, cl_new] = world.new_clients();
+
+    let mtch = world.new_match(cl_old, "Alice");
+    world[cl_new].join(&mtch, "Alice");
+
+    assert!(matches!(
+        world.process_events_for(cl_new),
+        Err(client::ClientError::Ignorable(_))
+    ));
+    world.process_all_events();
+}
+
+// Guest user can reconnect if the old client is dead.
+#[test]
+fn guest_user_reconnect_ok() {
+    let mut world = World::new();
+    let [cl_old, cl_new] = world.new_clients();
+
+    let mtch = world.new_match(cl_old, "Alice");
+    world.disconnect_client(cl_old);
+    world.process_all_events();
+
+    world[cl_new].join(&mtch, "Alice");
+    world.process_all_events();
+}
+
+// Regression test: server should not panic when an active client tries to join their current match.
+#[test]
+fn rejoin_from_live_client() {
+    let mut world = World::new();
+    let (mtch, cl1, cl2, _cl3, _cl4) = world.default_clients();
+    world[cl1].join(&mtch, "p1");
+    world.process_all_events();
+    assert_eq!(world[cl2].mtch().participants.len(), 4);
+    assert!(world[cl2].mtch().participants.iter().all(|p| p.is_online));
+}
+
+#[test]
+fn mid_match_faction_change() {
+    let mut world = World::new();
+    let (mtch, cl1, _cl2, _cl3, _cl4) = world.default_clients();
+
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+
+    let cl5 = world.new_client();
+    world[cl5].join(&mtch, "p5");
+    world.process_all_events();
+    let p5_view = world[cl1].mtch().participants.last().unwrap();
+    assert_eq!(p5_view.name, "p5");
+    assert_eq!(p5_view.faction, Faction::Observer);
+
+    world[cl5].state.set_faction(Faction::Fixed(Team::Red));
+    world.process_all_events();
+
+    world[cl1].state.resign();
+    world.process_all_events();
+
+    world[cl1].state.set_faction(Faction::Observer);
+    world.process_all_events();
+
+    let mut chat_iter = world[cl1].chat_item_text().into_iter();
+    assert_eq!(chat_iter.next().unwrap(), "p5 joined team Red");
+    assert_eq!(chat_iter.next().unwrap(), "Game over! p3 & p4 won: p1 & p2 resigned.");
+    assert!(chat_iter.next().unwrap().starts_with("Next up:"));
+    assert_eq!(chat_iter.next().unwrap(), "p1 became an observer");
+    assert!(chat_iter.next().is_none());
+    // Ideally we would want to get another "Next up" message at this point, but this not
     // implemented yet.
 }
+
+fn got_shutdown_notice(world: &mut World, client_id: TestClientId) -> bool {
+    let mut got_notice = false;
+    while let Some(event) = world[client_id].state.next_notable_event() {
+        if let client::NotableEvent::ServerShuttingDown(_) = event {
+            got_notice = true;
+        }
+    }
+    got_notice
+}
+
+// The server should wait for active games to finish and notify all clients before exiting.
+#[test]
+fn shutdown_notice() {
+    let mut world = World::new();
+    let (_, cl1, cl2, _cl3, _cl4) = world.default_clients();
+    let cl5 = world.new_client();
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+
+    world.server.terminate();
+    world.process_all_events();
+    assert!(!got_shutdown_notice(&mut world, cl1));
+
+    world[cl1].state.resign();
+    world.process_all_events();
+    world.set_time(Duration::seconds(600));
+    world.process_all_events();
+    assert!(got_shutdown_notice(&mut world, cl1));
+    assert!(got_shutdown_notice(&mut world, cl2));
+    // Clients outside of matches are notified as well.
+    assert!(got_shutdown_notice(&mut world, cl5));
+}
+
+// Aborting shutdown should still notify clients and save the games that are in progress.
+#[test]
+fn shutdown_aborted() {
+    let hooks = Arc::new(RecordingServerHooks::default());
+    let mut world = World::new_with_hooks(Arc::clone(&hooks) as _);
+    let (_, cl1, cl2, _cl3, _cl4) = world.default_clients();
+    world[cl1].make_turn("e4").unwrap();
+    world.process_all_events();
+
+    world.server.terminate();
+    world.process_all_events();
+    assert!(!got_shutdown_notice(&mut world, cl1));
+    assert!(hooks.interrupted_games.lock().unwrap().is_empty());
+
+    world.server.terminate();
+    world.process_all_events();
+    assert!(got_shutdown_notice(&mut world, cl1));
+    assert!(got_shutdown_notice(&mut world, cl2));
+    assert_eq!(*hooks.interrupted_games.lock().unwrap(), [BughouseGameStatus::Active]);
+    assert!(hooks.finished_games.lock().unwrap().is_empty());
+}
+
+#[test]
+fn participant_change_events() {
+    use client::ParticipantChange::*;
+    fn participant_changes(
+        world: &mut World, client_id: TestClientId,
+    ) -> Vec<client::ParticipantChange> {
+        let mut changes = vec![];
+        while let Some(event) = world[client_id].state.next_notable_event() {
+            if let client::NotableEvent::ParticipantChanged(change) = event {
+                changes.push(change);
+            }
+        }
+        changes
+    }
+
+    let mut world = World::new();
+    let [cl1, cl2] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world.process_all_events();
+    assert!(participant_changes(&mut world, cl1).is_empty());
+
+    world[cl2].join(&mtch, "p2");
+    world.process_all_events();
+    assert_eq!(participant_changes(&mut world, cl1), [Joined("p2".to_owned())]);
+    // The initial participant list is not reported as a change.
+    assert!(participant_changes(&mut world, cl2).is_empty());
+
+    world[cl2].state.set_faction(Faction::Observer);
+    world.process_all_events();
+    for cl in [cl1, cl2] {
+        // Own changes are reported too, so that the UI could highlight them the same way.
+        assert_eq!(participant_changes(&mut world, cl), [FactionChanged(
+            "p2".to_owned(),
+            Faction::Observer
+        )]);
+    }
+
+    world[cl1].state.set_ready(true);
+    world.process_all_events();
+    for cl in [cl1, cl2] {
+        assert_eq!(participant_changes(&mut world, cl), [ReadinessChanged("p1".to_owned(), true)]);
+    }
+
+    world[cl2].state.leave_match();
+    world.process_all_events();
+    assert_eq!(participant_changes(&mut world, cl1), [Left("p2".to_owned())]);
+}
+
+#[test]
+fn transfer_match_ownership() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world[cl2].join(&mtch, "p2");
+    world[cl3].join(&mtch, "p3");
+    world.process_all_events();
+    assert_eq!(world[cl3].mtch().owner.as_deref(), Some("p1"));
+
+    // Only the owner can transfer ownership.
+    world[cl2].state.transfer_match_ownership("p2".to_owned());
+    assert!(matches!(world.process_events_for(cl2), Err(client::ClientError::Ignorable(_))));
+
+    // Cannot transfer ownership to somebody who is not in the match.
+    world[cl1].state.transfer_match_ownership("p4".to_owned());
+    assert!(matches!(world.process_events_for(cl1), Err(client::ClientError::Ignorable(_))));
+
+    world[cl1].state.transfer_match_ownership("p3".to_owned());
+    world.process_all_events();
+    assert_eq!(world[cl2].mtch().owner.as_deref(), Some("p3"));
+}
+
+#[test]
+fn handicap_reserve() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+
+    let mut handicap = Reserve::default();
+    handicap[PieceKind::Knight] = 2;
+    handicap[PieceKind::Pawn] = 1;
+
+    // Only the match owner can set handicaps.
+    world[cl2].state.set_handicap("p2".to_owned(), handicap);
+    assert!(matches!(world.process_events_for(cl2), Err(client::ClientError::Ignorable(_))));
+
+    world[cl1].state.set_handicap("p3".to_owned(), handicap);
+    world.process_all_events();
+    let p3 = world[cl4].mtch().participants.iter().find(|p| p.name == "p3").unwrap();
+    assert_eq!(p3.handicap, handicap);
+
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+    for cl in [cl1, cl3] {
+        let game = world[cl].local_game();
+        // p3 plays Black on board A.
+        assert_eq!(*game.board(A).reserve(Black), handicap);
+        assert!(game.board(A).reserve(White).values().all(|&n| n == 0));
+        assert!(game.board(B).reserves().values().all(|r| r.values().all(|&n| n == 0)));
+    }
+}
+
+#[test]
+fn match_ownership_passes_on_owner_disconnect() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let mtch = world.new_match(cl1, "p1");
+    world[cl2].join(&mtch, "p2");
+    world[cl3].join(&mtch, "p3");
+    world.process_all_events();
+
+    // Ownership goes to the participant who's been in the match the longest.
+    world.disconnect_client(cl1);
+    world.process_all_events();
+    assert_eq!(world[cl3].mtch().owner.as_deref(), Some("p2"));
+
+    world.disconnect_client(cl2);
+    world.process_all_events();
+    assert_eq!(world[cl3].mtch().owner.as_deref(), Some("p3"));
+
+    // The match is removed when everybody has left and nobody came back.
+    world.disconnect_client(cl3);
+    world.process_all_events();
+    world.set_time(Duration::minutes(10));
+    world.process_all_events();
+    world[cl4].join(&mtch, "p4");
+    assert!(matches!(world.process_events_for(cl4), Err(client::ClientError::Ignorable(_))));
+}
+
+#[test]
+fn match_ownership_passes_to_longest_connected() {
+    let mut world = World::new();
+    let (mtch, cl1, cl2, cl3, _cl4) = world.default_clients();
+
+    // p2 joined before p3, but reconnected later.
+    world.set_time(Duration::seconds(10));
+    world.disconnect_client(cl2);
+    world.process_all_events();
+    let cl2_new = world.new_client();
+    world[cl2_new].join(&mtch, "p2");
+    world.process_all_events();
+
+    world.disconnect_client(cl1);
+    world.process_all_events();
+    assert_eq!(world[cl3].mtch().owner.as_deref(), Some("p3"));
+}
+
+#[test]
+fn faction_change_applied_at_next_game() {
+    fn faction_changes(world: &mut World, client_id: TestClientId) -> Vec<Faction> {
+        let mut changes = vec![];
+        while let Some(event) = world[client_id].state.next_notable_event() {
+            if let client::NotableEvent::FactionChangeApplied(faction) = event {
+                changes.push(faction);
+            }
+        }
+        changes
+    }
+
+    let mut world = World::new();
+    let (mtch, cl1, cl2, cl3, cl4) = world.default_clients();
+    let cl5 = world.new_client();
+    world[cl5].join(&mtch, "p5");
+    world.process_all_events();
+
+    // Observer -> player and player -> observer.
+    world[cl5].state.set_faction(Faction::Fixed(Team::Red));
+    world[cl1].state.set_faction(Faction::Observer);
+    world.process_all_events();
+    assert!(world[cl1].my_id().is_player());
+    assert!(world[cl5].my_id().is_observer());
+    assert!(faction_changes(&mut world, cl1).is_empty());
+    assert!(faction_changes(&mut world, cl5).is_empty());
+
+    world[cl1].state.resign();
+    world.process_all_events();
+    world.server.state.TEST_override_board_assignment(mtch.clone(), vec![
+        single_player("p5", envoy!(White A)),
+        single_player("p2", envoy!(Black B)),
+        single_player("p3", envoy!(Black A)),
+        single_player("p4", envoy!(White B)),
+    ]);
+    for cl in [cl2, cl3, cl4, cl5] {
+        world[cl].state.set_ready(true);
+    }
+    world.process_all_events();
+    assert!(world[cl1].my_id().is_observer());
+    assert!(world[cl5].my_id().is_player());
+    assert_eq!(faction_changes(&mut world, cl1), vec![Faction::Observer]);
+    assert_eq!(faction_changes(&mut world, cl5), vec![Faction::Fixed(Team::Red)]);
+    assert!(faction_changes(&mut world, cl2).is_empty());
+
+    // A fixed team cannot be left without players.
+    world[cl3].state.set_faction(Faction::Observer);
+    world.process_all_events();
+    world[cl4].state.set_faction(Faction::Observer);
+    assert!(matches!(world.process_events_for(cl4), Err(client::ClientError::Ignorable(_))));
+    world.process_all_events();
+    assert_eq!(world[cl4].mtch().my_faction, Faction::Fixed(Team::Blue));
+    let p4_view = world[cl1].mtch().participants.iter().find(|p| p.name == "p4").unwrap();
+    assert_eq!(p4_view.faction, Faction::Fixed(Team::Blue));
+}
+
+#[test]
+fn rules_summary() {
+    let mut world = World::new();
+    let cl1 = world.new_client();
+    assert_eq!(world[cl1].state.rules_summary(), None);
+
+    world.new_match(cl1, "p1");
+    let summary = world[cl1].state.rules_summary().unwrap();
+    assert_eq!(summary, world[cl1].mtch().rules.to_human_readable());
+    assert!(summary.contains("Time control: 5:00"), "{summary}");
+}
+
+#[test]
+fn rematch_swap_boards() {
+    let mut world = World::new();
+    let (_mtch, cl1, cl2, cl3, cl4) = world.default_clients();
+
+    world[cl1].state.resign();
+    world.process_all_events();
+
+    world[cl1].state.request_rematch(SeatSwap::Boards);
+    world.process_all_events();
+    assert_eq!(world[cl1].state.is_ready(), Some(true));
+    assert_eq!(world[cl2].state.is_ready(), Some(false));
+    for cl in [cl2, cl3, cl4] {
+        world[cl].state.request_rematch(SeatSwap::Boards);
+    }
+    world.process_all_events();
+
+    assert_eq!(world[cl1].my_envoy(), envoy!(Black B));
+    assert_eq!(world[cl2].my_envoy(), envoy!(White A));
+    assert_eq!(world[cl3].my_envoy(), envoy!(White B));
+    assert_eq!(world[cl4].my_envoy(), envoy!(Black A));
+    let active_players = world[cl1]
+        .mtch()
+        .participants
+        .iter()
+        .map(|p| (p.name.as_str(), p.active_player))
+        .collect_vec();
+    assert_eq!(active_players, [
+        ("p1", Some(BughousePlayer::SinglePlayer(envoy!(Black B)))),
+        ("p2", Some(BughousePlayer::SinglePlayer(envoy!(White A)))),
+        ("p3", Some(BughousePlayer::SinglePlayer(envoy!(White B)))),
+        ("p4", Some(BughousePlayer::SinglePlayer(envoy!(Black A)))),
+    ]);
+}
+
+#[test]
+fn rematch_swap_colors() {
+    let mut world = World::new();
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+
+    let mtch = world.new_match(cl1, "p1");
+    world.process_all_events();
+    world[cl2].join(&mtch, "p2");
+    world[cl3].join(&mtch, "p3");
+    world[cl4].join(&mtch, "p4");
+    world.process_all_events();
+    world.new_game_with_default_board_assignment(mtch, cl1, cl2, cl3, cl4);
+
+    world[cl1].state.resign();
+    world.process_all_events();
+
+    for cl in [cl1, cl2, cl3, cl4] {
+        world[cl].state.request_rematch(SeatSwap::Colors);
+    }
+    world.process_all_events();
+
+    assert_eq!(world[cl1].my_envoy(), envoy!(Black A));
+    assert_eq!(world[cl2].my_envoy(), envoy!(White B));
+    assert_eq!(world[cl3].my_envoy(), envoy!(White A));
+    assert_eq!(world[cl4].my_envoy(), envoy!(Black B));
+}
+
+#[test]
+fn rematch_swap_colors_fixed_teams() {
+    let mut world = World::new();
+    let (_mtch, cl1, _cl2, _cl3, _cl4) = world.default_clients();
+
+    world[cl1].state.resign();
+    world.process_all_events();
+
+    world[cl1].state.request_rematch(SeatSwap::Colors);
+    assert!(matches!(world.process_events_for(cl1), Err(client::ClientError::Ignorable(_))));
+    world.process_all_events();
+    assert!(!world[cl1].state.is_ready().unwrap());
+}
+
+#[test]
+fn variant_popularity_metrics() {
+    fn counter_value(metric_name: &str, event: &str, label_value: &str) -> u64 {
+        prometheus::gather()
+            .iter()
+            .filter(|family| family.get_name() == metric_name)
+            .flat_map(|family| family.get_metric())
+            .filter(|metric| {
+                let labels = metric.get_label();
+                labels.iter().any(|l| l.get_name() == "event" && l.get_value() == event)
+                    && labels.iter().any(|l| l.get_value() == label_value)
+            })
+            .map(|metric| metric.get_counter().get_value() as u64)
+            .sum()
+    }
+    let fog_started = || counter_value("chess_variant_total", "game_started", "FogOfWar");
+    let fog_finished = || counter_value("chess_variant_total", "game_finished", "FogOfWar");
+
+    // Use inequalities, because metrics are global and tests could run in parallel.
+    let started_before = fog_started();
+    let mut world = World::new();
+    let mut rules = ChessRules {
+        fog_of_war: true,
+        ..default_chess_rules()
+    };
+    rules.bughouse_rules.as_mut().unwrap().drop_aggression = DropAggression::MateAllowed;
+    let (_, cl1, _cl2, _cl3, _cl4) = world.default_clients_with_rules(rules);
+    assert!(fog_started() > started_before);
+
+    let finished_before = fog_finished();
+    world[cl1].state.resign();
+    world.process_all_events();
+    assert!(fog_finished() > finished_before);
+}
+
+#[test]
+fn fog_of_war_hidden_reserves() {
+    let mut world = World::new();
+    let mut rules = ChessRules {
+        fog_of_war: true,
+        fog_of_war_hide_reserves: true,
+        ..default_chess_rules()
+    };
+    rules.bughouse_rules.as_mut().unwrap().drop_aggression = DropAggression::MateAllowed;
+    let (mtch, cl1, _cl2, _cl3, _cl4) = world.default_clients_with_rules(rules);
+    let cl5 = world.new_client();
+    world[cl5].join(&mtch, "p5");
+    world.process_all_events();
+
+    // Players see their own and their partner's reserves, but not the opponents'.
+    assert!(!world[cl1].alt_game().is_reserve_hidden(A, White));
+    assert!(!world[cl1].alt_game().is_reserve_hidden(B, Black));
+    assert!(world[cl1].alt_game().is_reserve_hidden(A, Black));
+    assert!(world[cl1].alt_game().is_reserve_hidden(B, White));
+    // Observers see everything.
+    for (board_idx, force) in [(A, White), (A, Black), (B, White), (B, Black)] {
+        assert!(!world[cl5].alt_game().is_reserve_hidden(board_idx, force));
+    }
+
+    // The fog lifts when the game is over.
+    world[cl1].state.resign();
+    world.process_all_events();
+    assert!(!world[cl1].alt_game().is_reserve_hidden(A, Black));
+    assert!(!world[cl1].alt_game().is_reserve_hidden(B, White));
+}
+
+#[test]
+fn fog_of_war_visible_reserves() {
+    let mut world = World::new();
+    let mut rules = ChessRules {
+        fog_of_war: true,
+        ..default_chess_rules()
+    };
+    rules.bughouse_rules.as_mut().unwrap().drop_aggression = DropAggression::MateAllowed;
+    let (_, cl1, _cl2, _cl3, _cl4) = world.default_clients_with_rules(rules);
+    for (board_idx, force) in [(A, White), (A, Black), (B, White), (B, Black)] {
+        assert!(!world[cl1].alt_game().is_reserve_hidden(board_idx, force));
+    }
+}
+
+fn match_with_target(world: &mut World, target: MatchTarget) -> (String, [TestClientId; 4]) {
+    let [cl1, cl2, cl3, cl4] = world.new_clients();
+    let rules = Rules {
+        match_rules: MatchRules {
+            match_target: Some(target),
+            ..MatchRules::unrated_public()
+        },
+        chess_rules: default_chess_rules(),
+    };
+    world[cl1].state.set_guest_player_name(Some("p1".to_owned()));
+    world[cl1].state.new_match(rules);
+    world.process_all_events();
+    let mtch = world[cl1].state.match_id().unwrap().clone();
+    world[cl1].state.set_faction(Faction::Fixed(Team::Red));
+    world.join_and_set_team(cl2, &mtch, "p2", Team::Red);
+    world.join_and_set_team(cl3, &mtch, "p3", Team::Blue);
+    world.join_and_set_team(cl4, &mtch, "p4", Team::Blue);
+    world.process_all_events();
+    (mtch, [cl1, cl2, cl3, cl4])
+}
+
+#[test]
+fn match_target_points() {
+    let mut world = World::new();
+    let (mtch, [cl1, cl2, cl3, cl4]) = match_with_target(&mut world, MatchTarget::Points(2));
+
+    world.new_game_with_default_board_assignment(mtch.clone(), cl1, cl2, cl3, cl4);
+    world[cl1].state.resign();
+    world.process_all_events();
+    assert!(world[cl1].mtch().result.is_none());
+
+    world.new_game_with_default_board_assignment(mtch.clone(), cl1, cl2, cl3, cl4);
+    world[cl1].state.resign();
+    world.process_all_events();
+    let mut completed = false;
+    while let Some(event) = world[cl1].state.next_notable_event() {
+        completed |= matches!(event, client::NotableEvent::MatchCompleted(_));
+    }
+    assert!(completed);
+    let result = world[cl1].mtch().result.clone().unwrap();
+    assert_eq!(result.winners, [Contestant::Team(Team::Blue)]);
+    let standings = result.standings.iter().map(|s| (s.contestant.clone(), s.score.as_f64()));
+    assert_eq!(standings.collect_vec(), [
+        (Contestant::Team(Team::Blue), 2.0),
+        (Contestant::Team(Team::Red), 0.0)
+    ]);
+
+    // No more games after the match is complete.
+    world.new_game_with_default_board_assignment(mtch.clone(), cl1, cl2, cl3, cl4);
+    assert!(!world[cl1].alt_game().is_active());
+    assert_eq!(world[cl1].state.game_state().unwrap().game_index, 1);
+
+    // Late joiners see the result too.
+    let cl5 = world.new_client();
+    world[cl5].join(&mtch, "p5");
+    world.process_all_events();
+    assert_eq!(world[cl5].mtch().result, Some(result));
+}
+
+#[test]
+fn match_target_tie_break() {
+    let mut world = World::new();
+    let (mtch, [cl1, cl2, cl3, cl4]) = match_with_target(&mut world, MatchTarget::Games(2));
+
+    world.new_game_with_default_board_assignment(mtch.clone(), cl1, cl2, cl3, cl4);
+    world[cl1].state.resign();
+    world.process_all_events();
+    assert!(world[cl1].mtch().result.is_none());
+
+    world.new_game_with_default_board_assignment(mtch.clone(), cl1, cl2, cl3, cl4);
+    world[cl3].state.resign();
+    world.process_all_events();
+
+    // Equal scores and equal number of victories: the most recent victory decides.
+    let result = world[cl1].mtch().result.clone().unwrap();
+    assert_eq!(result.winners, [Contestant::Team(Team::Red)]);
+    let standings = result.standings.iter().map(|s| (s.contestant.clone(), s.score.as_f64()));
+    assert_eq!(standings.collect_vec(), [
+        (Contestant::Team(Team::Red), 1.0),
+        (Contestant::Team(Team::Blue), 1.0)
+    ]);
+}
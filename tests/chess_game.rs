@@ -1,15 +1,21 @@
 mod common;
 
+use std::collections::HashSet;
 use std::time::Duration;
 
-use bughouse_chess::board::{ChessGameStatus, TurnError, TurnMode, VictoryReason};
+use bughouse_chess::board::{
+    AmbiguityError, Board, ChessGameStatus, DrawReason, Pin, Turn, TurnDrop, TurnError, TurnInput,
+    TurnMode, TurnMove, VictoryReason,
+};
 use bughouse_chess::clock::GameInstant;
 use bughouse_chess::coord::Coord;
 use bughouse_chess::force::Force;
 use bughouse_chess::game::ChessGame;
-use bughouse_chess::piece::PieceKind;
+use bughouse_chess::piece::{CastleDirection, PieceForce, PieceKind};
 use bughouse_chess::role::Role;
-use bughouse_chess::rules::{ChessRules, MatchRules, Rules, StartingPosition};
+use bughouse_chess::rules::{
+    Castling, ChessRules, FogOfWarVisibility, MatchRules, Rules, Stalemate, StartingPosition,
+};
 use bughouse_chess::starter::EffectiveStartingPosition;
 use bughouse_chess::test_util::*;
 use common::*;
@@ -118,6 +124,93 @@ fn chess960_drag_king_onto_rook_castle() {
     assert!(game.board().grid()[Coord::G1].is(piece!(White King)));
 }
 
+#[test]
+fn castle_blocked_by_piece() {
+    let mut game = chess_classic();
+    replay_log(&mut game, "1.e4 e5 2.Nf3 Nc6").unwrap();
+    let short_castle = Turn::Castle(CastleDirection::HSide);
+    assert!(!game.board().potential_moves().contains(&short_castle));
+    assert!(!game.board().turn_destinations(Coord::E1).contains(&Coord::G1));
+    assert_eq!(replay_log(&mut game, "3.O-O"), Err(TurnError::PathBlocked));
+
+    replay_log(&mut game, "3.Bc4 Nf6").unwrap();
+    assert!(game.board().potential_moves().contains(&short_castle));
+    assert!(game.board().turn_destinations(Coord::E1).contains(&Coord::G1));
+    replay_log(&mut game, "4.O-O").unwrap();
+}
+
+#[test]
+fn castling_forbidden() {
+    let rules = ChessRules {
+        castling: Castling::Forbidden,
+        ..ChessRules::chess_blitz_5()
+    };
+    let mut game = chess_with_rules(rules);
+    replay_log(&mut game, "1.e4 e5 2.Nf3 Nc6 3.Bc4 Nf6").unwrap();
+    assert!(
+        !game
+            .board()
+            .potential_moves()
+            .iter()
+            .any(|turn| matches!(turn, Turn::Castle(_)))
+    );
+    assert!(!game.board().turn_destinations(Coord::E1).contains(&Coord::G1));
+    assert_eq!(replay_log(&mut game, "4.O-O"), Err(TurnError::CastlingForbidden));
+    assert_eq!(
+        game.try_turn(&drag_move!(E1 -> G1), TurnMode::InOrder, GameInstant::game_start()),
+        Err(TurnError::CastlingForbidden)
+    );
+}
+
+#[test]
+fn castling_by_king_move_only() {
+    let rules = ChessRules {
+        castling: Castling::KingMoveOnly,
+        ..ChessRules::chess_blitz_5()
+    };
+    let mut game = chess_with_rules(rules);
+    replay_log(&mut game, "1.e4 e5 2.Nf3 Nc6 3.Bc4 Nf6").unwrap();
+    assert!(game.board().potential_moves().contains(&Turn::Castle(CastleDirection::HSide)));
+    assert!(
+        game.try_turn(&drag_move!(E1 -> H1), TurnMode::InOrder, GameInstant::game_start())
+            .is_err()
+    );
+    game.try_turn(&drag_move!(E1 -> G1), TurnMode::InOrder, GameInstant::game_start())
+        .unwrap();
+    assert!(game.board().grid()[Coord::F1].is(piece!(White Rook)));
+    assert!(game.board().grid()[Coord::G1].is(piece!(White King)));
+}
+
+#[test]
+fn castle_through_attacked_square() {
+    // Black bishop on a6 attacks f1.
+    let log = "1.e4 b6 2.g3 Ba6 3.Bg2 Nc6 4.Nf3 Nf6 5.O-O";
+    assert_eq!(replay_log_from_start(log), Err(TurnError::UnprotectedKing));
+
+    let rules = ChessRules {
+        castling_through_check: true,
+        ..ChessRules::chess_blitz_5()
+    };
+    let mut game = chess_with_rules(rules);
+    replay_log(&mut game, log).unwrap();
+    assert!(game.board().grid()[Coord::G1].is(piece!(White King)));
+    assert!(game.board().grid()[Coord::F1].is(piece!(White Rook)));
+}
+
+#[test]
+fn castle_into_check_with_relaxed_rules() {
+    // Black knight on h3 attacks g1.
+    let rules = ChessRules {
+        castling_through_check: true,
+        ..ChessRules::chess_blitz_5()
+    };
+    let mut game = chess_with_rules(rules);
+    assert_eq!(
+        replay_log(&mut game, "1.e4 Nh6 2.Bc4 Ng4 3.Ne2 N×f2 4.a3 Nh3 5.O-O"),
+        Err(TurnError::UnprotectedKing)
+    );
+}
+
 #[test]
 fn king_capture() {
     let rules = ChessRules {
@@ -126,7 +219,10 @@ fn king_capture() {
     };
     let mut game = chess_with_rules(rules);
     replay_log(&mut game, "1.Nc3 a6 2.Nd5 a5 3.N×c7 a4 4.N×e8").unwrap();
-    assert_eq!(game.status(), ChessGameStatus::Victory(Force::White, VictoryReason::Checkmate));
+    assert_eq!(
+        game.status(),
+        ChessGameStatus::Victory(Force::White, VictoryReason::KingCaptured)
+    );
 }
 
 #[test]
@@ -158,3 +254,572 @@ fn duck_cannot_stay_in_place() {
     let mut game = chess_with_rules(rules);
     assert_eq!(replay_log(&mut game, "1.e4 @d4 d5 @d4"), Err(TurnError::MustChangeDuckPosition));
 }
+
+#[test]
+fn attackers_of_square() {
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules: ChessRules::chess_blitz_5(),
+    };
+    let board = parse_ascii_board(
+        rules,
+        Role::ServerOrStandalone,
+        "
+        . . . . k . . .
+        . . . . . . . .
+        . . . r . . . .
+        . . b . . . . .
+        . . . N . . . .
+        . . P . . N . .
+        . . . . . . . .
+        . . . R K . . .
+    ",
+    )
+    .unwrap();
+    assert_eq!(board.attackers_of(Coord::D4, PieceForce::Black), vec![Coord::C5, Coord::D6]);
+    assert_eq!(board.attackers_of(Coord::D4, PieceForce::White), vec![
+        Coord::D1,
+        Coord::C3,
+        Coord::F3
+    ]);
+    assert!(board.attackers_of(Coord::D4, PieceForce::Neutral).is_empty());
+}
+
+#[test]
+fn attackers_of_en_passant() {
+    let mut game = chess_classic();
+    replay_log(&mut game, "1.e4 a6 2.e5 d5").unwrap();
+    let board = game.board();
+    assert_eq!(board.attackers_of(Coord::D5, PieceForce::White), vec![Coord::E5]);
+    assert!(board.attackers_of(Coord::F5, PieceForce::White).is_empty());
+}
+
+fn board_from_ascii(chess_rules: ChessRules, board_str: &str) -> Board {
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules,
+    };
+    parse_ascii_board(rules, Role::ServerOrStandalone, board_str).unwrap()
+}
+
+fn try_turn_on_copy(board: &Board, turn: &str) -> Result<(), TurnError> {
+    let mut board = board.clone();
+    let turn_input = TurnInput::Algebraic(turn.to_owned());
+    let turn = board.parse_turn_input(&turn_input, TurnMode::InOrder, None)?;
+    board.try_turn(turn, TurnMode::InOrder, GameInstant::game_start())?;
+    Ok(())
+}
+
+#[test]
+fn bishop_pin() {
+    let board_str = "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        b . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . N . . . .
+        . . . . K . . .
+    ";
+    let board = board_from_ascii(ChessRules::chess_blitz_5(), board_str);
+    assert_eq!(board.pinned_pieces(), vec![Pin {
+        pinned: Coord::D2,
+        pinner: Coord::A5,
+        king: Coord::E1,
+    }]);
+    assert!(try_turn_on_copy(&board, "Nf3").is_err());
+
+    // No pins without checks.
+    let fog_of_war_rules = ChessRules {
+        fog_of_war: true,
+        ..ChessRules::chess_blitz_5()
+    };
+    let board = board_from_ascii(fog_of_war_rules, board_str);
+    assert!(board.pinned_pieces().is_empty());
+}
+
+#[test]
+fn pinned_piece_can_capture_pinner() {
+    let board = board_from_ascii(
+        ChessRules::chess_blitz_5(),
+        "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        b . . . . . . .
+        . B . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . K . . .
+    ",
+    );
+    assert_eq!(board.pinned_pieces(), vec![Pin {
+        pinned: Coord::B4,
+        pinner: Coord::A5,
+        king: Coord::E1,
+    }]);
+    assert!(try_turn_on_copy(&board, "Bc5").is_err());
+    try_turn_on_copy(&board, "Bxa5").unwrap();
+}
+
+#[test]
+fn checking_moves() {
+    let board_str = "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        R . . . K . . .
+    ";
+    let mut board = board_from_ascii(ChessRules::bughouse_international5(), board_str);
+    board.reserve_mut(Force::White)[PieceKind::Knight] = 1;
+    let knight_drop = |to| Turn::Drop(TurnDrop { piece_kind: PieceKind::Knight, to });
+    let mut expected = vec![
+        Turn::Move(TurnMove {
+            from: Coord::A1,
+            to: Coord::A8,
+            promote_to: None,
+        }),
+        knight_drop(Coord::C7),
+        knight_drop(Coord::D6),
+        knight_drop(Coord::F6),
+        knight_drop(Coord::G7),
+    ];
+    let mut checking_moves = board.checking_moves(Force::White);
+    let sort_key = |turn: &Turn| format!("{turn:?}");
+    expected.sort_by_key(sort_key);
+    checking_moves.sort_by_key(sort_key);
+    assert_eq!(checking_moves, expected);
+    assert!(board.checking_moves(Force::Black).is_empty());
+
+    // No checks in regicide variants.
+    let fog_of_war_rules = ChessRules {
+        fog_of_war: true,
+        ..ChessRules::bughouse_international5()
+    };
+    let mut board = board_from_ascii(fog_of_war_rules, board_str);
+    board.reserve_mut(Force::White)[PieceKind::Knight] = 1;
+    assert!(board.checking_moves(Force::White).is_empty());
+}
+
+#[test]
+fn check_relieving_drops() {
+    let board_str = "
+        R . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . K . . .
+    ";
+    let mut board = board_from_ascii(ChessRules::bughouse_international5(), board_str);
+    board.reserve_mut(Force::Black)[PieceKind::Pawn] = 1;
+    board.reserve_mut(Force::Black)[PieceKind::Knight] = 1;
+    // Pawns cannot be dropped on the first rank, so only the knight can block.
+    let knight_drop = |to| Turn::Drop(TurnDrop { piece_kind: PieceKind::Knight, to });
+    let expected = vec![
+        knight_drop(Coord::B8),
+        knight_drop(Coord::C8),
+        knight_drop(Coord::D8),
+    ];
+    let sort_key = |turn: &Turn| format!("{turn:?}");
+    let mut drops = board.check_relieving_drops(Force::Black);
+    drops.sort_by_key(sort_key);
+    assert_eq!(drops, expected);
+    // White is not in check.
+    board.reserve_mut(Force::White)[PieceKind::Knight] = 1;
+    assert!(board.check_relieving_drops(Force::White).is_empty());
+
+    // Drops cannot help against a contact check.
+    let mut board = board_from_ascii(
+        ChessRules::bughouse_international5(),
+        "
+        . . . R k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . K . . .
+        ",
+    );
+    board.reserve_mut(Force::Black)[PieceKind::Knight] = 1;
+    assert!(board.check_relieving_drops(Force::Black).is_empty());
+
+    // No checks in regicide variants.
+    let fog_of_war_rules = ChessRules {
+        fog_of_war: true,
+        ..ChessRules::bughouse_international5()
+    };
+    let mut board = board_from_ascii(fog_of_war_rules, board_str);
+    board.reserve_mut(Force::Black)[PieceKind::Knight] = 1;
+    assert!(board.check_relieving_drops(Force::Black).is_empty());
+}
+
+#[test]
+fn stalemate() {
+    let board_str = "
+        k . . . . . . .
+        . . . . . . . .
+        . K . . . . . .
+        . . . . . . . .
+        . . Q . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+    ";
+    let stalemate_turn = Turn::Move(TurnMove {
+        from: Coord::C4,
+        to: Coord::C7,
+        promote_to: None,
+    });
+    let play = |chess_rules: ChessRules, black_reserve: &[PieceKind]| {
+        let mut board = board_from_ascii(chess_rules, board_str);
+        for &piece_kind in black_reserve {
+            board.reserve_mut(Force::Black)[piece_kind] += 1;
+        }
+        board
+            .try_turn(stalemate_turn, TurnMode::InOrder, GameInstant::game_start())
+            .unwrap();
+        board
+    };
+
+    // Chess: stalemate is a draw.
+    let board = play(ChessRules::chess_blitz_5(), &[]);
+    assert!(board.is_stalemate(Force::Black));
+    assert_eq!(board.status(), ChessGameStatus::Draw(DrawReason::Stalemate));
+
+    // Bughouse: by default the player waits for a piece to drop.
+    let board = play(ChessRules::bughouse_international5(), &[]);
+    assert!(board.is_stalemate(Force::Black));
+    assert_eq!(board.status(), ChessGameStatus::Active);
+
+    // Bughouse: any piece in reserve can be dropped, so there is no stalemate.
+    let board = play(ChessRules::bughouse_international5(), &[PieceKind::Knight]);
+    assert!(!board.is_stalemate(Force::Black));
+
+    // Bughouse with stalemate as a draw.
+    let mut chess_rules = ChessRules::bughouse_international5();
+    chess_rules.bughouse_rules.as_mut().unwrap().stalemate = Stalemate::Draw;
+    let board = play(chess_rules, &[]);
+    assert_eq!(board.status(), ChessGameStatus::Draw(DrawReason::Stalemate));
+
+    // Not a stalemate in regicide variants: the king can walk into an attack.
+    let fog_of_war_rules = ChessRules {
+        fog_of_war: true,
+        ..ChessRules::bughouse_international5()
+    };
+    let board = play(fog_of_war_rules, &[]);
+    assert!(!board.is_stalemate(Force::Black));
+    assert_eq!(board.status(), ChessGameStatus::Active);
+}
+
+#[test]
+fn disambiguate_san() {
+    let knight_move = |from, to| Turn::Move(TurnMove { from, to, promote_to: None });
+
+    // Knights on the same rank: file is required.
+    let board = board_from_ascii(
+        ChessRules::chess_blitz_5(),
+        "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . N . . K N . .
+        ",
+    );
+    assert_eq!(
+        board.disambiguate_san("Nd2"),
+        Err(AmbiguityError::Ambiguous(vec![Coord::B1, Coord::F1]))
+    );
+    assert_eq!(board.disambiguate_san("Nbd2"), Ok(knight_move(Coord::B1, Coord::D2)));
+    assert_eq!(board.disambiguate_san("Nfd2"), Ok(knight_move(Coord::F1, Coord::D2)));
+    assert_eq!(board.disambiguate_san("Nc3"), Ok(knight_move(Coord::B1, Coord::C3)));
+
+    // Knights on the same file: rank is required.
+    let board = board_from_ascii(
+        ChessRules::chess_blitz_5(),
+        "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . N . . . . . .
+        . . . . . . . .
+        . N . . K . . .
+        ",
+    );
+    assert_eq!(
+        board.disambiguate_san("Nbd2"),
+        Err(AmbiguityError::Ambiguous(vec![Coord::B1, Coord::B3]))
+    );
+    assert_eq!(board.disambiguate_san("N3d2"), Ok(knight_move(Coord::B3, Coord::D2)));
+
+    // A pinned knight cannot move, so there is no ambiguity.
+    let board = board_from_ascii(
+        ChessRules::chess_blitz_5(),
+        "
+        . . . . k . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . N . . K N . r
+        ",
+    );
+    assert_eq!(board.disambiguate_san("Nd2"), Ok(knight_move(Coord::B1, Coord::D2)));
+    assert_eq!(
+        board.disambiguate_san("Nfd2"),
+        Err(AmbiguityError::Illegal(TurnError::UnprotectedKing))
+    );
+
+    // Illegal moves.
+    assert_eq!(
+        board.disambiguate_san("Ne5"),
+        Err(AmbiguityError::Illegal(TurnError::ImpossibleTrajectory))
+    );
+    assert_eq!(
+        board.disambiguate_san("Qd2"),
+        Err(AmbiguityError::Illegal(TurnError::PieceMissing))
+    );
+    assert_eq!(
+        board.disambiguate_san("Nz9"),
+        Err(AmbiguityError::Illegal(TurnError::InvalidNotation))
+    );
+}
+
+#[test]
+fn fog_of_war_visibility() {
+    let board_str = "
+        . . . . . . . k
+        . . . . . . . .
+        . . . . . . . .
+        . . . . . . . .
+        . . . R . . . .
+        . . . . . . . .
+        . . . . . . . .
+        K . . . . . . .
+    ";
+    let fog_free_area = |visibility| {
+        let rules = ChessRules {
+            fog_of_war: true,
+            fog_of_war_visibility: visibility,
+            ..ChessRules::chess_blitz_5()
+        };
+        board_from_ascii(rules, board_str).fog_free_area(Force::White)
+    };
+    let movement = fog_free_area(FogOfWarVisibility::Movement);
+    let radius = fog_free_area(FogOfWarVisibility::Radius(1));
+    let hybrid = fog_free_area(FogOfWarVisibility::Hybrid(1));
+
+    // The rook sees the whole file and rank, but not the diagonal neighbors.
+    assert!(movement.contains(&Coord::D8));
+    assert!(movement.contains(&Coord::H4));
+    assert!(!movement.contains(&Coord::C3));
+    assert!(!movement.contains(&Coord::H8));
+
+    #[rustfmt::skip]
+    let expected_radius = HashSet::from([
+        Coord::A1, Coord::A2, Coord::B1, Coord::B2,
+        Coord::C3, Coord::C4, Coord::C5,
+        Coord::D3, Coord::D4, Coord::D5,
+        Coord::E3, Coord::E4, Coord::E5,
+    ]);
+    assert_eq!(radius, expected_radius);
+
+    assert_eq!(hybrid, movement.union(&radius).copied().collect());
+}
+
+fn status_after_turn(chess_rules: ChessRules, board_str: &str, turn: &str) -> ChessGameStatus {
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules,
+    };
+    let mut board = parse_ascii_board(rules, Role::ServerOrStandalone, board_str).unwrap();
+    let turn_input = TurnInput::Algebraic(turn.to_owned());
+    let turn = board.parse_turn_input(&turn_input, TurnMode::InOrder, None).unwrap();
+    board.try_turn(turn, TurnMode::InOrder, GameInstant::game_start()).unwrap();
+    board.status()
+}
+
+#[test]
+fn insufficient_material_draw() {
+    const DRAW: ChessGameStatus = ChessGameStatus::Draw(DrawReason::InsufficientMaterial);
+    let chess = ChessRules::chess_blitz_5;
+    // Bare kings.
+    assert_eq!(
+        status_after_turn(
+            chess(),
+            "
+            k . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . n . . .
+            . . . . K . . .
+        ",
+            "Kxe2"
+        ),
+        DRAW
+    );
+    // King and knight vs king.
+    assert_eq!(
+        status_after_turn(
+            chess(),
+            "
+            k . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . p . .
+            . . . . . . . .
+            . . . . K . N .
+        ",
+            "Nxf3"
+        ),
+        DRAW
+    );
+    // King and bishop vs king and bishop, bishops on the same color.
+    assert_eq!(
+        status_after_turn(
+            chess(),
+            "
+            k . . . . b . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . p . . . .
+            . . B . K . . .
+        ",
+            "Kxd2"
+        ),
+        DRAW
+    );
+}
+
+#[test]
+fn sufficient_material_no_draw() {
+    let chess = ChessRules::chess_blitz_5;
+    // Bishops on different colors.
+    assert_eq!(
+        status_after_turn(
+            chess(),
+            "
+            k . . . . . b .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . p . . . .
+            . . B . K . . .
+        ",
+            "Kxd2"
+        ),
+        ChessGameStatus::Active
+    );
+    // King and rook vs king.
+    assert_eq!(
+        status_after_turn(
+            chess(),
+            "
+            k . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . n . . .
+            . . . . K . . R
+        ",
+            "Kxe2"
+        ),
+        ChessGameStatus::Active
+    );
+    // Bare kings on a bughouse board: the partner can always pass more material.
+    assert_eq!(
+        status_after_turn(
+            ChessRules::bughouse_international5(),
+            "
+            k . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . . . . .
+            . . . . n . . .
+            . . . . K . . .
+        ",
+            "Kxe2"
+        ),
+        ChessGameStatus::Active
+    );
+}
+
+#[test]
+fn default_piece_values() {
+    let rules = ChessRules::chess_blitz_5();
+    let value = |kind: PieceKind| kind.value(&rules);
+    assert_eq!(value(PieceKind::Pawn), 1);
+    assert_eq!(value(PieceKind::Knight), 3);
+    assert_eq!(value(PieceKind::Bishop), 3);
+    assert_eq!(value(PieceKind::Rook), 5);
+    assert_eq!(value(PieceKind::Queen), 9);
+    assert_eq!(value(PieceKind::Cardinal), 8);
+    assert_eq!(value(PieceKind::Empress), 9);
+    assert_eq!(value(PieceKind::Amazon), 12);
+    assert_eq!(value(PieceKind::King), 0);
+    assert_eq!(value(PieceKind::Duck), 0);
+
+    let mut game = chess_classic();
+    assert_eq!(game.board().material(Force::White), 39);
+    assert_eq!(game.board().material_balance(), 0);
+    replay_log(&mut game, "1.e4 d5 2.xd5").unwrap();
+    assert_eq!(game.board().material_balance(), 1);
+}
+
+#[test]
+fn piece_value_override() {
+    let mut chess_rules = ChessRules::chess_blitz_5();
+    chess_rules.piece_values.overrides[PieceKind::Bishop] = Some(4);
+    assert_eq!(PieceKind::Bishop.value(&chess_rules), 4);
+    assert_eq!(PieceKind::Knight.value(&chess_rules), 3);
+
+    let game = chess_with_rules(chess_rules);
+    assert_eq!(game.board().material(Force::Black), 41);
+}
+
+#[test]
+fn duck_is_never_counted() {
+    let mut rules = ChessRules {
+        duck_chess: true,
+        ..ChessRules::chess_blitz_5()
+    };
+    rules.piece_values.overrides[PieceKind::Duck] = Some(5);
+    assert_eq!(PieceKind::Duck.value(&rules), 0);
+
+    let mut game = chess_with_rules(rules);
+    replay_log(&mut game, "1.e4 @e6").unwrap();
+    assert_eq!(game.board().material(Force::White), 39);
+    assert_eq!(game.board().material(Force::Black), 39);
+    assert_eq!(game.board().material_balance(), 0);
+}
@@ -0,0 +1,47 @@
+use bughouse_chess::client_settings::{
+    ClientSettings, DEFAULT_BOARD_SCALE, MAX_BOARD_SCALE, MIN_BOARD_SCALE, clamp_board_scale,
+};
+
+
+#[test]
+fn round_trip() {
+    let mut settings = ClientSettings::new();
+    settings.set("coordinate-style", "all".to_owned()).unwrap();
+    settings.set("time-trouble-threshold", "off".to_owned()).unwrap();
+    settings
+        .set("theme-colors", r##"{"board_dark":"#769656"}"##.to_owned())
+        .unwrap();
+    let exported = settings.export();
+    assert_eq!(ClientSettings::import(&exported).unwrap(), settings);
+}
+
+#[test]
+fn unknown_key_ignored() {
+    let imported = ClientSettings::import(
+        r#"bughouse-settings-v1:{"coordinate-style":"off","hologram-mode":"on"}"#,
+    )
+    .unwrap();
+    assert_eq!(imported.get("coordinate-style"), Some("off"));
+    assert_eq!(imported.get("hologram-mode"), None);
+    assert_eq!(imported.iter().count(), 1);
+}
+
+#[test]
+fn invalid_settings_rejected() {
+    assert!(ClientSettings::import("").is_err());
+    assert!(ClientSettings::import(r#"{"coordinate-style":"off"}"#).is_err());
+    assert!(ClientSettings::import(r#"bughouse-settings-v1:{"coordinate-style":"x"}"#).is_err());
+    assert!(ClientSettings::import(r#"bughouse-settings-v1:{"frame-budget":"0"}"#).is_err());
+    assert!(ClientSettings::import(r#"bughouse-settings-v99:{}"#).is_err());
+}
+
+#[test]
+fn board_scale_clamped() {
+    assert_eq!(clamp_board_scale(1.25), 1.25);
+    assert_eq!(clamp_board_scale(0.01), MIN_BOARD_SCALE);
+    assert_eq!(clamp_board_scale(100.0), MAX_BOARD_SCALE);
+    assert_eq!(clamp_board_scale(f64::INFINITY), MAX_BOARD_SCALE);
+    assert_eq!(clamp_board_scale(f64::NAN), DEFAULT_BOARD_SCALE);
+    assert!(ClientSettings::import(r#"bughouse-settings-v1:{"board-scale":"1.5"}"#).is_ok());
+    assert!(ClientSettings::import(r#"bughouse-settings-v1:{"board-scale":"10"}"#).is_err());
+}
@@ -0,0 +1,130 @@
+use BughouseBoard::{A, B};
+use bughouse_chess::board::VictoryReason;
+use bughouse_chess::clock::GameInstant;
+use bughouse_chess::coord::Coord;
+use bughouse_chess::display::{BoardOrientation, BughouseGameView, DisplayBoard, TurnLogScroll};
+use bughouse_chess::envoy;
+use bughouse_chess::game::{
+    BughouseBoard, BughouseGame, BughouseGameStatus, BughouseParticipant, BughousePlayer,
+};
+use bughouse_chess::player::Team;
+use bughouse_chess::role::Role;
+use bughouse_chess::rules::{ChessRules, MatchRules, Rules};
+use bughouse_chess::test_util::sample_bughouse_players;
+
+
+fn fog_of_war_game() -> BughouseGame {
+    let mut chess_rules = ChessRules::bughouse_international5();
+    chess_rules.fog_of_war = true;
+    let rules = Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules,
+    };
+    BughouseGame::new(rules, Role::Client, &sample_bughouse_players())
+}
+
+
+#[test]
+fn turn_log_follows_new_turns_only_at_bottom() {
+    use DisplayBoard::*;
+    let mut scroll = TurnLogScroll::new();
+    assert!(scroll.follow_new_turns(Primary));
+    assert!(scroll.follow_new_turns(Secondary));
+
+    // The user scrolls up to review the primary board log.
+    scroll.on_scroll(Primary, false);
+    assert!(!scroll.follow_new_turns(Primary));
+    assert!(scroll.follow_new_turns(Secondary));
+
+    // Scrolling within the log keeps it detached until the bottom is reached.
+    scroll.on_scroll(Primary, false);
+    assert!(!scroll.follow_new_turns(Primary));
+    scroll.on_scroll(Primary, true);
+    assert!(scroll.follow_new_turns(Primary));
+
+    // Programmatic scroll to bottom reports `at_bottom` and keeps the log attached.
+    scroll.on_scroll(Secondary, true);
+    assert!(scroll.follow_new_turns(Secondary));
+
+    // New game view re-attaches all logs.
+    scroll.on_scroll(Primary, false);
+    scroll.on_scroll(Secondary, false);
+    scroll.reset();
+    assert!(scroll.follow_new_turns(Primary));
+    assert!(scroll.follow_new_turns(Secondary));
+}
+
+#[test]
+fn fog_of_war_view_for_player() {
+    use DisplayBoard::*;
+    let game = fog_of_war_game();
+    let participant = BughouseParticipant::Player(BughousePlayer::SinglePlayer(envoy!(White A)));
+    let view = BughouseGameView::for_participant(&game, participant);
+
+    let primary = view.board(Primary);
+    assert_eq!(primary.board_idx, A);
+    assert_eq!(primary.orientation, BoardOrientation::Normal);
+    assert!(primary.is_visible(Coord::E2));
+    assert!(primary.is_visible(Coord::E4));
+    assert!(!primary.is_visible(Coord::E5));
+    assert!(!primary.is_visible(Coord::E7));
+
+    // On the partner's board we play Black.
+    let secondary = view.board(Secondary);
+    assert_eq!(secondary.board_idx, B);
+    assert_eq!(secondary.orientation, BoardOrientation::Rotated);
+    assert!(secondary.is_visible(Coord::E7));
+    assert!(secondary.is_visible(Coord::E5));
+    assert!(!secondary.is_visible(Coord::E4));
+    assert!(!secondary.is_visible(Coord::E2));
+}
+
+#[test]
+fn fog_of_war_view_lifted() {
+    use DisplayBoard::*;
+    let mut game = fog_of_war_game();
+    let observer = BughouseParticipant::Observer(envoy!(Black B));
+    let view = BughouseGameView::for_participant(&game, observer);
+    assert_eq!(view.board(Primary).board_idx, B);
+    assert!(view.boards.values().all(|board_view| board_view.fog_of_war_area.is_empty()));
+
+    let player = BughouseParticipant::Player(BughousePlayer::DoublePlayer(Team::Red));
+    game.set_status(
+        BughouseGameStatus::Victory(Team::Red, VictoryReason::Resignation),
+        GameInstant::game_start(),
+    );
+    let view = BughouseGameView::for_participant(&game, player);
+    assert!(view.boards.values().all(|board_view| board_view.fog_of_war_area.is_empty()));
+}
+
+#[test]
+fn turn_log_always_follows_without_freeze() {
+    use DisplayBoard::*;
+    let mut scroll = TurnLogScroll::new();
+    scroll.set_freeze_when_scrolled_up(false);
+    scroll.on_scroll(Primary, false);
+    assert!(scroll.follow_new_turns(Primary));
+    scroll.set_freeze_when_scrolled_up(true);
+    assert!(!scroll.follow_new_turns(Primary));
+}
+
+#[test]
+fn turn_log_wayback_overrides_freeze() {
+    use DisplayBoard::*;
+    let mut scroll = TurnLogScroll::new();
+    scroll.set_freeze_when_scrolled_up(false);
+
+    // Wayback keeps the logs at the turn being viewed even when they normally always follow.
+    assert!(!scroll.on_wayback(true));
+    scroll.on_scroll(Primary, false);
+    assert!(!scroll.follow_new_turns(Primary));
+    assert!(!scroll.follow_new_turns(Secondary));
+    assert!(!scroll.on_wayback(true));
+
+    // Leaving wayback re-attaches all logs, even in freeze mode.
+    scroll.set_freeze_when_scrolled_up(true);
+    assert!(scroll.on_wayback(false));
+    assert!(scroll.follow_new_turns(Primary));
+    assert!(scroll.follow_new_turns(Secondary));
+    assert!(!scroll.on_wayback(false));
+}
@@ -0,0 +1,51 @@
+use std::thread;
+use std::time::Duration;
+
+use bughouse_chess::meter::{FrameBudget, FrameTimings, SLOW_FRAME_REPORT_INTERVAL};
+use instant::Instant;
+
+
+#[test]
+fn frame_timings_record_sections() {
+    let timings = FrameTimings::new();
+    assert!(timings.report().is_empty());
+
+    timings.measure("board", || thread::sleep(Duration::from_millis(1)));
+    timings.record("chat", Duration::from_micros(250));
+    timings.record("chat", Duration::from_micros(300));
+
+    let report = timings.report();
+    assert_eq!(report.keys().copied().collect::<Vec<_>>(), ["board", "chat"]);
+    assert!(report["board"].last_us >= 1000);
+    assert_eq!(report["board"].stats_us.num_values, 1);
+    assert_eq!(report["chat"].last_us, 300);
+    assert_eq!(report["chat"].stats_us.num_values, 2);
+    assert!(report["chat"].stats_us.p50 > 0);
+}
+
+#[test]
+fn frame_budget_threshold() {
+    let ms = Duration::from_millis;
+    let t0 = Instant::now();
+    let mut budget = FrameBudget::new(Some(ms(50)));
+    assert!(!budget.check(ms(50), t0));
+    assert!(budget.check(ms(51), t0));
+
+    // Reports are rate-limited.
+    assert!(!budget.check(ms(100), t0 + ms(1)));
+    assert!(budget.check(ms(100), t0 + SLOW_FRAME_REPORT_INTERVAL));
+
+    budget.set_budget(None);
+    assert!(!budget.check(ms(1000), t0 + SLOW_FRAME_REPORT_INTERVAL * 2));
+}
+
+#[test]
+fn frame_budget_skips_heavy_frame() {
+    let ms = Duration::from_millis;
+    let t0 = Instant::now();
+    let mut budget = FrameBudget::new(Some(ms(50)));
+    budget.expect_heavy_frame();
+    assert!(!budget.check(ms(500), t0));
+    // Only one frame is exempted.
+    assert!(budget.check(ms(500), t0 + ms(1)));
+}
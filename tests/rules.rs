@@ -0,0 +1,201 @@
+use std::time::Duration;
+
+use bughouse_chess::piece::PieceKind;
+use bughouse_chess::player::Team;
+use bughouse_chess::rules::{
+    ChessRules, ChessVariant, DelayedStart, DropAggression, FairyPieces, FogOfWarVisibility,
+    MatchRules, MatchTarget, PawnDropRanks, PieceCap, PieceCapPolicy, Promotion, RatedGameChat,
+    Rules, SimultaneousFlagPolicy, StartingPosition,
+};
+use strum::IntoEnumIterator;
+
+
+fn make_rules(chess_rules: ChessRules) -> Rules {
+    Rules {
+        match_rules: MatchRules::unrated_public(),
+        chess_rules,
+    }
+}
+
+fn summary_lines(rules: &Rules) -> Vec<String> {
+    rules.to_human_readable().lines().map(str::to_owned).collect()
+}
+
+#[test]
+fn rules_summary_default() {
+    let rules = make_rules(ChessRules::bughouse_international5());
+    assert_eq!(summary_lines(&rules), [
+        "Variants: —",
+        "Time control: 5:00",
+        "Promotion: Upgrade",
+        "Pawn drop ranks: 2-7",
+        "Drop aggression: Mate allowed",
+        "Simultaneous flags: Draw",
+        "Rating: Unrated",
+    ]);
+}
+
+#[test]
+fn rules_summary_lists_each_variant() {
+    for variant in ChessVariant::iter() {
+        let mut chess_rules = ChessRules::bughouse_international5();
+        match variant {
+            ChessVariant::Capablanca => chess_rules.fairy_pieces = FairyPieces::Capablanca,
+            ChessVariant::Accolade => chess_rules.fairy_pieces = FairyPieces::Accolade,
+            ChessVariant::FischerRandom => {
+                chess_rules.starting_position = StartingPosition::FischerRandom
+            }
+            ChessVariant::DuckChess => chess_rules.duck_chess = true,
+            ChessVariant::AtomicChess => chess_rules.atomic_chess = true,
+            ChessVariant::FogOfWar => chess_rules.fog_of_war = true,
+            ChessVariant::Koedem => chess_rules.bughouse_rules.as_mut().unwrap().koedem = true,
+        }
+        assert_eq!(chess_rules.variants(), [variant]);
+        let lines = summary_lines(&make_rules(chess_rules));
+        assert_eq!(lines[0], format!("Variants: {}", variant.to_human_readable()));
+        assert_eq!(
+            lines.contains(&"Win condition: King capture".to_owned()),
+            variant.enables_regicide(),
+            "{variant:?}"
+        );
+    }
+}
+
+#[test]
+fn rules_summary_bughouse_rules() {
+    let mut chess_rules = ChessRules::bughouse_modern();
+    chess_rules.piece_values.overrides[PieceKind::Queen] = Some(10);
+    let bughouse_rules = chess_rules.bughouse_rules.as_mut().unwrap();
+    bughouse_rules.promotion = Promotion::Discard;
+    bughouse_rules.drop_aggression = DropAggression::NoCheck;
+    bughouse_rules.no_drop_turns = 3;
+    bughouse_rules.no_interposing_drops = true;
+    bughouse_rules.simultaneous_flag = SimultaneousFlagPolicy::MaterialAdvantage;
+    bughouse_rules.piece_cap = Some(PieceCap {
+        max_pieces: 24,
+        policy: PieceCapPolicy::Refuse,
+    });
+    let mut rules = make_rules(chess_rules);
+    rules.match_rules.rated = true;
+    assert_eq!(summary_lines(&rules), [
+        "Variants: Fischer random",
+        "Time control: 5:00",
+        "Promotion: Discard",
+        "Pawn drop ranks: 2-6",
+        "Moves without drops: 3",
+        "Drop aggression: No check",
+        "Blocking check by drop: Forbidden",
+        "Simultaneous flags: Material advantage",
+        "Piece cap: 24 (Capture refused)",
+        "Piece values: Q=10",
+        "Rating: Rated",
+        "Players: Registered only",
+    ]);
+}
+
+#[test]
+fn rules_description_presets() {
+    assert_eq!(
+        make_rules(ChessRules::bughouse_international3()).describe(),
+        "3:00 unrated bughouse"
+    );
+    assert_eq!(
+        make_rules(ChessRules::bughouse_international5()).describe(),
+        "5:00 unrated bughouse"
+    );
+    assert_eq!(
+        make_rules(ChessRules::bughouse_modern()).describe(),
+        "5:00 unrated bughouse with Fischer random, promotion: steal, pawn drop ranks: 2-6, \
+        drop aggression: no chess mate"
+    );
+}
+
+#[test]
+fn rules_description_customized() {
+    let mut rules = make_rules(ChessRules::bughouse_international3());
+    rules.match_rules.rated = true;
+    rules.chess_rules.duck_chess = true;
+    rules.chess_rules.fog_of_war = true;
+    let bughouse_rules = rules.bughouse_rules_mut().unwrap();
+    bughouse_rules.promotion = Promotion::Discard;
+    bughouse_rules.pawn_drop_ranks = PawnDropRanks::from_one_based(1, 5);
+    bughouse_rules.no_drop_turns = 3;
+    bughouse_rules.drop_aggression = DropAggression::NoCheck;
+    bughouse_rules.simultaneous_flag = SimultaneousFlagPolicy::MaterialAdvantage;
+    bughouse_rules.piece_cap = Some(PieceCap {
+        max_pieces: 20,
+        policy: PieceCapPolicy::Discard,
+    });
+    assert_eq!(
+        rules.describe(),
+        "3:00 rated bughouse with duck chess and fog of war, fog of war visibility: legal moves, \
+        promotion: discard, pawn drop ranks: 1-5, moves without drops: 3, \
+        win condition: king capture, simultaneous flags: material advantage, \
+        piece cap: 20 (Excess discarded)"
+    );
+}
+
+#[test]
+fn share_code_round_trip() {
+    let mut capablanca_fog = make_rules(ChessRules::bughouse_international3());
+    capablanca_fog.chess_rules.fairy_pieces = FairyPieces::Capablanca;
+    capablanca_fog.chess_rules.fog_of_war = true;
+    capablanca_fog.chess_rules.fog_of_war_visibility = FogOfWarVisibility::Hybrid(2);
+    capablanca_fog.chess_rules.fog_of_war_hide_reserves = true;
+
+    let mut accolade_duck = make_rules(ChessRules::bughouse_modern());
+    accolade_duck.chess_rules.fairy_pieces = FairyPieces::Accolade;
+    accolade_duck.chess_rules.duck_chess = true;
+    accolade_duck.chess_rules.piece_values.overrides[PieceKind::Knight] = Some(4);
+    let bughouse_rules = accolade_duck.bughouse_rules_mut().unwrap();
+    bughouse_rules.drop_aggression = DropAggression::MateAllowed;
+    bughouse_rules.no_drop_turns = 2;
+    bughouse_rules.piece_cap = Some(PieceCap {
+        max_pieces: 24,
+        policy: PieceCapPolicy::Discard,
+    });
+    bughouse_rules.delayed_start = Some(DelayedStart {
+        team: Team::Blue,
+        delay: Duration::from_secs(30),
+    });
+
+    let mut rated = make_rules(ChessRules::bughouse_international5());
+    rated.match_rules.rated = true;
+    rated.match_rules.registered_only = true;
+    rated.match_rules.rated_game_chat = RatedGameChat::TeamOnly;
+    rated.match_rules.rated_preturn_limit = Some(1);
+    rated.match_rules.match_target = Some(MatchTarget::Points(5));
+
+    for rules in [
+        make_rules(ChessRules::bughouse_international5()),
+        make_rules(ChessRules::chess_blitz_3()),
+        capablanca_fog,
+        accolade_duck,
+        rated,
+    ] {
+        let code = rules.to_share_code();
+        assert!(code.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_'));
+        assert_eq!(Rules::from_share_code(&code), Ok(rules));
+    }
+}
+
+#[test]
+fn share_code_invalid() {
+    let code = make_rules(ChessRules::bughouse_international5()).to_share_code();
+    assert!(Rules::from_share_code("").is_err());
+    assert!(Rules::from_share_code("not a code!").is_err());
+    assert!(Rules::from_share_code(&code[..code.len() - 2]).is_err());
+    assert!(Rules::from_share_code(&format!("{code}AA")).is_err());
+
+    // Unknown format version.
+    let mut other_version = code.clone();
+    other_version.replace_range(0..1, "B");
+    assert!(Rules::from_share_code(&other_version).is_err());
+}
+
+#[test]
+fn share_code_rejects_invalid_rules() {
+    let mut rules = make_rules(ChessRules::bughouse_international5());
+    rules.chess_rules.fog_of_war_visibility = FogOfWarVisibility::Radius(0);
+    assert!(Rules::from_share_code(&rules.to_share_code()).is_err());
+}
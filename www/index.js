@@ -90,14 +90,29 @@ function log_time() {
 log_time(); // start the counter
 
 // Improvement potential. Similarly group other global variables.
+// Settings exported by `/export-settings` must also be listed in `CLIENT_SETTINGS` in Rust.
 const Storage = {
   cookies_accepted: "cookies-accepted", // values: null, "essential", "all"
   chat_reference_tooltip: "chat-reference-tooltip", // values: "show" (default), "hide"
   player_name: "player-name",
+  move_input_method: "move-input-method", // values: "any" (default), "drag", "click"
+  board_flip_animation: "board-flip-animation", // values: "off" (default), "on"
+  highlight_updates: "highlight-updates", // values: "incremental" (default), "full"
+  turn_log_scroll: "turn-log-scroll", // values: "freeze" (default), "follow"
+  reserve_restock_alert: "reserve-restock-alert", // values: "sound", "flash" (default), "announce", "both"
+  coordinate_style: "coordinate-style", // values: "off", "edge" (default), "all"
+  drop_highlight: "drop-highlight", // values: "fill" (default), "marker"
+  show_rating: "show-rating", // values: "show" (default), "hide"
+  move_announcements: "move-announcements", // values: "off" (default), "on"
+  time_trouble_threshold: "time-trouble-threshold", // values: "off", seconds (default: "10")
+  frame_budget: "frame-budget", // values: "off", milliseconds (default: "200")
+  theme_colors: "theme-colors", // values: JSON, see `WebClient::set_theme_colors` (default: "{}")
+  board_scale: "board-scale", // values: number, managed by `WebClient::set_board_scale` (default: "1")
 };
 
 const SearchParams = {
   match_id: "match-id",
+  spectate_match_id: "spectate",
   archive_game_id: "archive-game-id",
   server: "server",
 };
@@ -125,6 +140,14 @@ const menu_change_account_page = document.getElementById("menu-change-account-pa
 const menu_delete_account_page = document.getElementById("menu-delete-account-page");
 const menu_create_match_name_page = document.getElementById("menu-create-match-name-page");
 const menu_create_match_page = document.getElementById("menu-create-match-page");
+const create_match_same_rules_button = document.getElementById("create-match-same-rules-button");
+const create_match_rules_code = document.getElementById("create-match-rules-code");
+const create_match_apply_rules_code_button = document.getElementById(
+  "create-match-apply-rules-code-button"
+);
+const create_match_copy_rules_code_button = document.getElementById(
+  "create-match-copy-rules-code-button"
+);
 const menu_join_match_page = document.getElementById("menu-join-match-page");
 const menu_lobby_page = document.getElementById("menu-lobby-page");
 const menu_game_archive_page = document.getElementById("menu-game-archive-page");
@@ -163,6 +186,7 @@ const resign_button = document.getElementById("resign-button");
 const toggle_faction_button = document.getElementById("toggle-faction-button");
 const rules_button = document.getElementById("rules-button");
 const export_button = document.getElementById("export-button");
+const copy_moves_button = document.getElementById("copy-moves-button");
 const volume_button = document.getElementById("volume-button");
 const shared_wayback_button = document.getElementById("shared-wayback-button");
 const toggle_analysis_button = document.getElementById("toggle-analysis-button");
@@ -273,8 +297,12 @@ wasm_client().init_page();
 
 let fatal_error_shown = false;
 
+// Must be in sync with `.board-flipping` transition duration in CSS.
+const board_flip_duration_ms = 300;
+
 let last_socket_connection_attempt = null;
 let consecutive_socket_connection_attempts = 0;
+let server_restart_reconnect_time = null; // set when the server announced a restart
 let socket = null;
 open_socket();
 
@@ -319,6 +347,7 @@ set_up_drag_and_drop();
 set_up_chalk_drawing();
 set_up_menu_pointers();
 set_up_log_navigation();
+set_up_clock_taps();
 
 update();
 
@@ -349,6 +378,7 @@ resign_button.addEventListener("click", request_resign);
 toggle_faction_button.addEventListener("click", toggle_faction_ingame);
 rules_button.addEventListener("click", () => execute_input("/rules"));
 export_button.addEventListener("click", () => execute_input("/save"));
+copy_moves_button.addEventListener("click", () => execute_input("/moves primary"));
 volume_button.addEventListener("click", next_volume);
 shared_wayback_button.addEventListener("click", toggle_shared_wayback);
 toggle_analysis_button.addEventListener("click", toggle_analysis);
@@ -384,6 +414,16 @@ create_unrated_match_button.addEventListener("click", (event) =>
 join_match_button.addEventListener("click", on_join_match_submenu);
 menu_create_match_name_page.addEventListener("submit", create_match_as_guest);
 menu_create_match_page.addEventListener("submit", on_create_match_confirm);
+create_match_same_rules_button.addEventListener("click", on_create_match_same_rules);
+create_match_apply_rules_code_button.addEventListener("click", on_apply_rules_code);
+create_match_copy_rules_code_button.addEventListener("click", on_copy_rules_code);
+create_match_rules_code.addEventListener("keydown", (event) => {
+  // Don't submit the form: that would create the match.
+  if (event.key === "Enter") {
+    event.preventDefault();
+    on_apply_rules_code(event);
+  }
+});
 menu_join_match_page.addEventListener("submit", on_join_match_confirm);
 lobby_leave_button.addEventListener("click", leave_active_match);
 game_archive_button.addEventListener("click", view_archive_game_list);
@@ -472,7 +512,27 @@ function wasm_client() {
 function make_wasm_client() {
   const user_agent = window.navigator.userAgent;
   const time_zone = Intl.DateTimeFormat().resolvedOptions().timeZone;
-  return wasm.WebClient.new_client(user_agent, time_zone);
+  const client = wasm.WebClient.new_client(user_agent, time_zone);
+  apply_stored_settings(client);
+  return client;
+}
+
+function apply_stored_settings(client) {
+  client.set_move_input_method(window.localStorage.getItem(Storage.move_input_method) || "any");
+  client.set_board_flip_animation(window.localStorage.getItem(Storage.board_flip_animation) === "on");
+  client.set_incremental_turn_highlights(window.localStorage.getItem(Storage.highlight_updates) !== "full");
+  client.set_turn_log_freeze(window.localStorage.getItem(Storage.turn_log_scroll) !== "follow");
+  client.set_reserve_restock_flash(reserve_restock_alert_includes("flash"));
+  client.set_coordinate_style(window.localStorage.getItem(Storage.coordinate_style) || "edge");
+  client.set_drop_highlight_style(window.localStorage.getItem(Storage.drop_highlight) || "fill");
+  client.set_show_rating(window.localStorage.getItem(Storage.show_rating) !== "hide");
+  client.set_move_announcements(window.localStorage.getItem(Storage.move_announcements) === "on");
+  client.set_time_trouble_threshold(
+    window.localStorage.getItem(Storage.time_trouble_threshold) || "10"
+  );
+  client.set_theme_colors(window.localStorage.getItem(Storage.theme_colors) || "{}");
+  client.set_frame_budget(window.localStorage.getItem(Storage.frame_budget) || "200");
+  client.apply_stored_board_scale();
 }
 
 function make_meters() {
@@ -480,7 +540,6 @@ function make_meters() {
     process_outgoing_events: wasm_client().meter("process_outgoing_events"),
     process_notable_events: wasm_client().meter("process_notable_events"),
     refresh: wasm_client().meter("refresh"),
-    update_state: wasm_client().meter("update_state"),
     update_clock: wasm_client().meter("update_clock"),
     update_drag_state: wasm_client().meter("update_drag_state"),
   };
@@ -503,6 +562,18 @@ function on_socket_open(event) {
   });
 }
 function on_socket_close(event) {
+  if (server_restart_reconnect_time !== null) {
+    // Don't waste reconnection attempts while the server is restarting.
+    const delay = Math.max(0, server_restart_reconnect_time - performance.now());
+    server_restart_reconnect_time = null;
+    cut_off_socket();
+    setTimeout(function () {
+      last_socket_connection_attempt = null;
+      consecutive_socket_connection_attempts = 0;
+      open_socket("server restarted");
+    }, delay);
+    return;
+  }
   open_socket("closed");
 }
 function on_socker_error(event) {
@@ -650,6 +721,16 @@ function on_document_keydown(event) {
         );
         update();
         setTimeout(() => (is_processing_wayback = false), 10);
+      } else if (["ArrowLeft", "ArrowRight"].includes(event.key) && chat_input.value === "") {
+        // Board focus: `=` turns and unmodified arrow up/down apply to the focused board.
+        // Pressing the key for the board that is already focused removes the focus.
+        event.preventDefault();
+        const board_id = event.key === "ArrowLeft" ? "primary" : "secondary";
+        wasm_client().focus_board(wasm_client().focused_board() === board_id ? "none" : board_id);
+        update();
+      } else if (!event.repeat && event.key === "Escape") {
+        wasm_client().abort_click_move();
+        update();
       }
     }
   });
@@ -679,6 +760,8 @@ function execute_chat_input() {
     chat_input.value = "<";
   } else if (input.startsWith(">")) {
     chat_input.value = ">";
+  } else if (input.startsWith("=")) {
+    chat_input.value = "=";
   } else {
     chat_input.value = "";
   }
@@ -693,9 +776,9 @@ function on_chat_input_keydown(event) {
   } else if (!event.repeat && event.key === "Escape") {
     // Remove focus thus hiding the chat reference tooltip.
     chat_input.blur();
-  } else if (["<", ">", "/"].includes(chat_input.value) && [">", "<", "/"].includes(event.key)) {
+  } else if (["<", ">", "=", "/"].includes(chat_input.value) && [">", "<", "=", "/"].includes(event.key)) {
     chat_input.value = "";
-  } else if (["<", ">", "/", ""].includes(chat_input.value) && event.key === " ") {
+  } else if (["<", ">", "=", "/", ""].includes(chat_input.value) && event.key === " ") {
     chat_input.value = "";
     event.preventDefault();
   }
@@ -730,18 +813,306 @@ function execute_input(input) {
           wasm_client().show_command_result(`Applied: sound volume ${volume}.`);
           break;
         }
+        case "input": {
+          const expected_args = ["any:drag:click"];
+          const [value] = get_args(args, expected_args);
+          if (!["any", "drag", "click"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_move_input_method(value);
+          window.localStorage.setItem(Storage.move_input_method, value);
+          wasm_client().show_command_result(`Applied: move input method "${value}".`);
+          break;
+        }
+        case "flip-animation": {
+          const expected_args = ["on:off"];
+          const [value] = get_args(args, expected_args);
+          if (!["on", "off"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_board_flip_animation(value === "on");
+          window.localStorage.setItem(Storage.board_flip_animation, value);
+          wasm_client().show_command_result(`Applied: board flip animation ${value}.`);
+          break;
+        }
+        case "highlight-updates": {
+          const expected_args = ["incremental:full"];
+          const [value] = get_args(args, expected_args);
+          if (!["incremental", "full"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_incremental_turn_highlights(value === "incremental");
+          window.localStorage.setItem(Storage.highlight_updates, value);
+          wasm_client().show_command_result(`Applied: ${value} highlight updates.`);
+          break;
+        }
+        case "log-scroll": {
+          const expected_args = ["freeze:follow"];
+          const [value] = get_args(args, expected_args);
+          if (!["freeze", "follow"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_turn_log_freeze(value === "freeze");
+          window.localStorage.setItem(Storage.turn_log_scroll, value);
+          wasm_client().show_command_result(`Applied: turn log scroll "${value}".`);
+          break;
+        }
+        case "restock-alert": {
+          const expected_args = ["sound:flash:announce:both"];
+          const [value] = get_args(args, expected_args);
+          if (!["sound", "flash", "announce", "both"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          window.localStorage.setItem(Storage.reserve_restock_alert, value);
+          wasm_client().set_reserve_restock_flash(reserve_restock_alert_includes("flash"));
+          wasm_client().show_command_result(`Applied: reserve restock alert "${value}".`);
+          break;
+        }
+        case "coordinates": {
+          const expected_args = ["off:edge:all"];
+          const [value] = get_args(args, expected_args);
+          if (!["off", "edge", "all"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_coordinate_style(value);
+          window.localStorage.setItem(Storage.coordinate_style, value);
+          wasm_client().show_command_result(`Applied: coordinate style "${value}".`);
+          break;
+        }
+        case "drop-highlight": {
+          const expected_args = ["fill:marker"];
+          const [value] = get_args(args, expected_args);
+          if (!["fill", "marker"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_drop_highlight_style(value);
+          window.localStorage.setItem(Storage.drop_highlight, value);
+          wasm_client().show_command_result(`Applied: drop highlight "${value}".`);
+          break;
+        }
+        case "rating": {
+          const expected_args = ["show:hide"];
+          const [value] = get_args(args, expected_args);
+          if (!["show", "hide"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_show_rating(value === "show");
+          window.localStorage.setItem(Storage.show_rating, value);
+          wasm_client().show_command_result(`Applied: rating "${value}".`);
+          break;
+        }
+        case "announce-moves": {
+          const expected_args = ["on:off"];
+          const [value] = get_args(args, expected_args);
+          if (!["on", "off"].includes(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_move_announcements(value === "on");
+          window.localStorage.setItem(Storage.move_announcements, value);
+          wasm_client().show_command_result(`Applied: move announcements ${value}.`);
+          break;
+        }
+        case "timetrouble": {
+          const expected_args = ["off:<seconds>"];
+          const [value] = get_args(args, expected_args);
+          const valid_seconds = /^\d+$/.test(value) && Number(value) >= 1 && Number(value) <= 20;
+          if (value !== "off" && !valid_seconds) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_time_trouble_threshold(value);
+          window.localStorage.setItem(Storage.time_trouble_threshold, value);
+          const description = value === "off" ? "off" : `${value}s`;
+          wasm_client().show_command_result(`Applied: time trouble alert ${description}.`);
+          break;
+        }
+        case "frame-budget": {
+          const expected_args = ["off:<milliseconds>"];
+          const [value] = get_args(args, expected_args);
+          if (value !== "off" && !/^\d+$/.test(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_frame_budget(value);
+          window.localStorage.setItem(Storage.frame_budget, value);
+          const description = value === "off" ? "off" : `${value} ms`;
+          wasm_client().show_command_result(`Applied: frame budget ${description}.`);
+          break;
+        }
+        case "board-scale": {
+          const expected_args = ["<factor>"];
+          const [value] = get_args(args, expected_args);
+          if (!/^\d+(\.\d+)?$/.test(value)) {
+            throw usage_error(args, expected_args);
+          }
+          const scale = wasm_client().set_board_scale(Number(value));
+          wasm_client().show_command_result(`Applied: board scale ${scale}.`);
+          break;
+        }
+        case "boardcolors": {
+          let colors;
+          if (args.length === 2 && args[1] === "reset") {
+            colors = "{}";
+          } else {
+            const expected_args = ["<light>", "<dark>", "<highlight>"];
+            const [board_light, board_dark, highlight] = get_args(args, expected_args);
+            colors = JSON.stringify({ board_light, board_dark, highlight });
+          }
+          wasm_client().set_theme_colors(colors);
+          window.localStorage.setItem(Storage.theme_colors, colors);
+          wasm_client().show_command_result("Applied: board colors.");
+          break;
+        }
+        case "export-settings": {
+          get_args(args, []);
+          const settings = wasm_client().export_settings();
+          navigator.clipboard?.writeText(settings);
+          wasm_client().show_command_result(`Settings (copied to clipboard):\n${settings}`);
+          break;
+        }
+        case "import-settings": {
+          const [settings] = get_args(args, ["<settings>"]);
+          wasm_client().import_settings(settings);
+          apply_stored_settings(wasm_client());
+          update_chat_reference_tooltip();
+          wasm_client().show_command_result("Applied: imported settings.");
+          break;
+        }
         case "resign":
           get_args(args, []);
           wasm_client().resign();
           break;
+        case "resign-board":
+          get_args(args, []);
+          wasm_client().resign_board();
+          break;
+        case "abort":
+          get_args(args, []);
+          wasm_client().request_abort();
+          break;
         case "ready":
           get_args(args, []);
           wasm_client().toggle_ready();
           break;
+        case "rematch": {
+          const expected_args = ["boards:colors"];
+          const [swap] = get_args(args, expected_args);
+          if (!["boards", "colors"].includes(swap)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().request_rematch(swap);
+          break;
+        }
         case "rules":
           get_args(args, []);
           show_match_rules();
           break;
+        case "rules-text": {
+          get_args(args, []);
+          const summary = wasm_client().rules_summary();
+          if (!summary) {
+            throw new InvalidCommand("Not in a match.");
+          }
+          navigator.clipboard?.writeText(summary);
+          wasm_client().show_command_result(`Rules (copied to clipboard):\n${summary}`);
+          break;
+        }
+        case "moves": {
+          const expected_args = ["primary:secondary:both"];
+          const [board] = get_args(args, expected_args);
+          if (!["primary", "secondary", "both"].includes(board)) {
+            throw usage_error(args, expected_args);
+          }
+          const moves = wasm_client().move_list_text(board);
+          if (moves === undefined) {
+            throw new InvalidCommand("No moves to copy.");
+          }
+          navigator.clipboard?.writeText(moves);
+          wasm_client().show_command_result(`Moves (copied to clipboard):\n${moves}`);
+          break;
+        }
+        case "spectate-link": {
+          get_args(args, []);
+          const match_id = wasm_client().match_id();
+          if (!match_id) {
+            throw new InvalidCommand("Not in a match.");
+          }
+          const url = new URL(window.location);
+          url.search = "";
+          url.searchParams.set(SearchParams.spectate_match_id, match_id);
+          navigator.clipboard?.writeText(url.toString());
+          wasm_client().show_command_result(`Read-only link: ${url}`);
+          break;
+        }
+        case "follow": {
+          const [player_name] = get_args(args, ["player"]);
+          wasm_client().follow_player(player_name);
+          wasm_client().show_command_result(
+            `Following ${player_name}: their next game will be shown automatically.`,
+          );
+          break;
+        }
+        case "unfollow":
+          get_args(args, []);
+          wasm_client().follow_player(null);
+          wasm_client().show_command_result("Stopped following.");
+          break;
+        case "handicap": {
+          const expected_args = ["<player>", "off:<pieces>"];
+          const [player_name, pieces] = get_args(args, expected_args);
+          if (pieces !== "off" && !/^[A-Z]+$/.test(pieces)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_handicap(player_name, pieces);
+          break;
+        }
+        case "transfer-ownership": {
+          const [player_name] = get_args(args, ["player"]);
+          wasm_client().transfer_match_ownership(player_name);
+          break;
+        }
+        case "pins": {
+          get_args(args, []);
+          const enabled = wasm_client().toggle_pins();
+          update();
+          wasm_client().show_command_result(enabled ? "Showing pins." : "Hiding pins.");
+          break;
+        }
+        case "focus": {
+          const expected_args = ["primary:secondary:next:off"];
+          const [value] = get_args(args, expected_args);
+          if (value === "next") {
+            wasm_client().focus_next_board();
+          } else if (["primary", "secondary", "off"].includes(value)) {
+            wasm_client().focus_board(value === "off" ? "none" : value);
+          } else {
+            throw usage_error(args, expected_args);
+          }
+          update();
+          break;
+        }
+        case "scratch":
+          get_args(args, []);
+          wasm_client().toggle_scratch_mode();
+          update();
+          break;
+        case "study":
+          get_args(args, []);
+          wasm_client().enter_study_mode();
+          update();
+          wasm_client().show_command_result(
+            "Study mode: move freely for all sides on both boards. Use /reserve to edit reserves.",
+          );
+          break;
+        case "reserve": {
+          const expected_args = ["primary:secondary", "white:black", "piece", "amount"];
+          const [board, force, piece, amount_str] = get_args(args, expected_args);
+          const amount = parseInt(amount_str);
+          if (isNaN(amount) || amount < 0 || amount > 255) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_study_reserve(board, force, piece, amount);
+          update();
+          break;
+        }
         case "save": {
           get_args(args, []);
           const content = wasm_client().get_game_bpgn();
@@ -758,6 +1129,29 @@ function execute_input(input) {
           wasm_client().show_command_result(stats);
           break;
         }
+        // Internal.
+        case "frame-timings": {
+          get_args(args, []);
+          const timings = JSON.parse(wasm_client().frame_timings());
+          console.table(timings);
+          const text = Object.entries(timings)
+            .map(([section, t]) => `${section}: ${t.last_us}us (P90=${t.stats_us.p90}us)`)
+            .join("\n");
+          wasm_client().show_command_result(text);
+          break;
+        }
+        // Internal. For testing preturns and lag handling. Not persisted.
+        case "latency": {
+          const expected_args = ["off:<milliseconds>"];
+          const [value] = get_args(args, expected_args);
+          if (value !== "off" && !/^\d+$/.test(value)) {
+            throw usage_error(args, expected_args);
+          }
+          wasm_client().set_simulated_latency(value);
+          const description = value === "off" ? "off" : `${value} ms`;
+          wasm_client().show_command_result(`Applied: simulated latency ${description}.`);
+          break;
+        }
         // Internal. For testing WebSocket re-connection.
         case "reconnect":
           socket.close();
@@ -795,6 +1189,7 @@ function on_tick() {
     process_notable_events();
     timer.meter(Meter.process_notable_events);
     update_lobby_countdown();
+    update_chat_cooldown();
     update_connection_status();
   });
 }
@@ -806,8 +1201,8 @@ function update() {
     timer.meter(Meter.refresh);
     process_outgoing_events();
     timer.meter(Meter.process_outgoing_events);
-    wasm_client().update_state();
-    timer.meter(Meter.update_state);
+    wasm_client().update_state(); // metered internally, including frame budget check
+    timer.finish();
     process_notable_events();
     timer.meter(Meter.process_notable_events);
     update_drag_state();
@@ -841,21 +1236,49 @@ function process_notable_events() {
     } else if (js_event_type === "JsEventMatchStarted") {
       const url = new URL(window.location);
       url.search = "";
-      url.searchParams.set(SearchParams.match_id, js_event.match_id);
-      window.history.pushState({}, "", url);
-      push_menu_page(menu_lobby_page);
+      if (wasm_client().is_spectator()) {
+        url.searchParams.set(SearchParams.spectate_match_id, js_event.match_id);
+        window.history.pushState({}, "", url);
+        close_menu();
+      } else {
+        url.searchParams.set(SearchParams.match_id, js_event.match_id);
+        window.history.pushState({}, "", url);
+        push_menu_page(menu_lobby_page);
+      }
     } else if (js_event_type === "JsEventGameStarted") {
       close_menu();
     } else if (js_event_type === "JsEventGameOver") {
       play_audio(Sound[js_event.result]);
+    } else if (js_event_type === "JsEventGameReview") {
+      info_dialog(js_event.text);
+    } else if (js_event_type === "JsEventMatchCompleted") {
+      info_dialog(js_event.text);
     } else if (js_event_type === "JsEventPlaySound") {
       play_audio(Sound[js_event.audio], js_event.pan);
+    } else if (js_event_type === "JsEventReserveRestocked") {
+      play_audio(Sound.reserve_restocked, js_event.pan);
+      if (reserve_restock_alert_includes("announce")) {
+        announce(js_event.pieces);
+      }
     } else if (js_event_type === "JsEventArchiveGameLoaded") {
       update();
+    } else if (js_event_type === "JsEventServerShuttingDown") {
+      server_restart_reconnect_time = performance.now() + js_event.reconnect_in_ms;
+    } else if (js_event_type === "JsEventSlowFrame") {
+      console.warn(log_time(), `Slow frame: update took ${Math.round(js_event.ms)} ms`);
     } else {
       throw "Unexpected notable event: " + js_event_type;
     }
   }
+  if (wasm_client().take_board_flip()) {
+    // Pieces have been moved to their new positions, but other layers need to be re-rendered.
+    update();
+    setTimeout(function () {
+      for (const board of document.querySelectorAll(".board-flipping")) {
+        board.classList.remove("board-flipping");
+      }
+    }, board_flip_duration_ms);
+  }
 }
 
 function update_drag_state() {
@@ -888,10 +1311,16 @@ function update_lobby_countdown() {
   const lobby_countdown_seconds = document.getElementById("lobby-countdown-seconds");
   const s = wasm_client().lobby_countdown_seconds_left();
   lobby_footer.classList.toggle("countdown", !!s);
-  lobby_waiting.textContent = wasm_client().lobby_waiting_explanation();
+  const scheduled_start = wasm_client().lobby_scheduled_start();
+  lobby_waiting.textContent = scheduled_start ?? wasm_client().lobby_waiting_explanation();
   lobby_countdown_seconds.textContent = s;
 }
 
+function update_chat_cooldown() {
+  const s = wasm_client().chat_cooldown_seconds_left();
+  chat_input.placeholder = s ? `Slow mode: ${s} s` : "";
+}
+
 function update_connection_status() {
   const s = wasm_client().current_turnaround_time();
   const ms = Math.round(s * 1000);
@@ -956,6 +1385,7 @@ function update_buttons() {
       set_displayed(ready_button, false);
       set_displayed(toggle_faction_button, true);
       set_displayed(export_button, false);
+      set_displayed(copy_moves_button, false);
       set_displayed(shared_wayback_button, false);
       set_displayed(toggle_analysis_button, false);
       break;
@@ -966,6 +1396,7 @@ function update_buttons() {
       set_displayed(toggle_faction_button, true);
       // TODO: Add "get game permalink" button.
       set_displayed(export_button, false);
+      set_displayed(copy_moves_button, true);
       set_displayed(shared_wayback_button, true);
       set_displayed(toggle_analysis_button, engine_status === "ready");
       break;
@@ -975,15 +1406,27 @@ function update_buttons() {
       set_displayed(ready_button, false);
       set_displayed(toggle_faction_button, false);
       set_displayed(export_button, true);
+      set_displayed(copy_moves_button, true);
       set_displayed(shared_wayback_button, false); // TODO: allow watching archive games together and set to `true`
       set_displayed(toggle_analysis_button, engine_status === "ready");
       break;
+    case "study":
+      set_displayed(leave_match_button, true);
+      set_displayed(resign_button, false);
+      set_displayed(ready_button, false);
+      set_displayed(toggle_faction_button, false);
+      set_displayed(export_button, false);
+      set_displayed(copy_moves_button, true);
+      set_displayed(shared_wayback_button, false);
+      set_displayed(toggle_analysis_button, engine_status === "ready");
+      break;
     case "none":
       set_displayed(leave_match_button, false);
       set_displayed(resign_button, false);
       set_displayed(ready_button, false);
       set_displayed(toggle_faction_button, false);
       set_displayed(export_button, false);
+      set_displayed(copy_moves_button, false);
       set_displayed(shared_wayback_button, false);
       set_displayed(toggle_analysis_button, false);
       break;
@@ -1003,6 +1446,10 @@ async function leave_match() {
     window.history.pushState({}, "", url);
     open_menu();
     view_archive_game_list();
+  } else if (wasm_client().game_status() === "study") {
+    wasm_client().leave_match();
+    update();
+    open_menu();
   } else {
     const need_confirmation = wasm_client().observer_status() !== "permanently";
     const leave = need_confirmation
@@ -1018,12 +1465,16 @@ async function leave_match() {
 }
 
 async function request_resign() {
-  const ret = await text_dialog("Are you sure you want to resign?", [
-    new MyButton("Keep playing", MyButton.HIDE),
-    new MyButton("🏳 Resign", MyButton.DO),
-  ]);
+  const buttons = [new MyButton("Keep playing", MyButton.HIDE)];
+  if (wasm_client().can_resign_board()) {
+    buttons.push(new MyButton("Resign board", MyButton.OPTION_1));
+  }
+  buttons.push(new MyButton("🏳 Resign", MyButton.DO));
+  const ret = await text_dialog("Are you sure you want to resign?", buttons);
   if (ret === MyButton.DO) {
     execute_input("/resign");
+  } else if (ret === MyButton.OPTION_1) {
+    execute_input("/resign-board");
   }
 }
 
@@ -1050,7 +1501,15 @@ async function toggle_faction_ingame() {
       wasm_client().change_faction_ingame("random");
     }
   } else {
-    wasm_client().change_faction_ingame("observer");
+    const confirmed =
+      wasm_client().game_status() !== "active" ||
+      (await text_dialog("Become an observer? You will finish the current game and observe starting from the next one.", [
+        new MyButton("Keep playing", MyButton.HIDE),
+        new MyButton("Observe", MyButton.DO),
+      ])) === MyButton.DO;
+    if (confirmed) {
+      wasm_client().change_faction_ingame("observer");
+    }
   }
 }
 
@@ -1140,7 +1599,8 @@ function set_up_drag_and_drop() {
     if (
       pointer_down_position &&
       distance(pointer_down_position, pos) > drag_start_threshold &&
-      pointer_down_is_main_pointer
+      pointer_down_is_main_pointer &&
+      wasm_client().drag_enabled()
     ) {
       start_drag(pos, pointer_down_element);
       pointer_down_position = null;
@@ -1157,11 +1617,11 @@ function set_up_drag_and_drop() {
     const pos = pointer_position(event);
     if (drag_element) {
       if (is_main_pointer(event)) {
-        end_drag(pos);
+        end_drag(pos, event.shiftKey);
       }
     } else if (pointer_down_position) {
       if (is_main_pointer(event)) {
-        click(pointer_down_position, pointer_down_element);
+        click(pointer_down_position, pointer_down_element, event.shiftKey);
         board_hover(pos, event.target);
       }
     }
@@ -1170,7 +1630,9 @@ function set_up_drag_and_drop() {
     pointer_down_is_main_pointer = null;
   }
 
-  function click(pos, element) {
+  // Holding Shift while completing a pawn move auto-steals the most valuable piece in steal
+  // promotion.
+  function click(pos, element, auto_steal) {
     with_error_handling(function () {
       const promotion_target = element.getAttribute("data-promotion-target");
       if (promotion_target) {
@@ -1179,13 +1641,13 @@ function set_up_drag_and_drop() {
       } else {
         const source = element.getAttribute("data-bughouse-location");
         if (source) {
-          wasm_client().click_element(source);
+          wasm_client().click_element(source, auto_steal);
           update();
         } else {
           const board_id = element.closest("[data-board-id]")?.getAttribute("data-board-id");
           if (board_id) {
             const coord = position_relative_to_board(pos, board_svg(board_id));
-            wasm_client().click_board(board_id, coord.x, coord.y);
+            wasm_client().click_board(board_id, coord.x, coord.y, auto_steal);
             update();
           }
         }
@@ -1254,11 +1716,11 @@ function set_up_drag_and_drop() {
     });
   }
 
-  function end_drag(pos) {
+  function end_drag(pos, auto_steal) {
     with_error_handling(function () {
       console.assert(drag_element);
       const coord = position_relative_to_board(pos, drag_source_board());
-      wasm_client().drag_piece_drop(drag_source_board_id, coord.x, coord.y);
+      wasm_client().drag_piece_drop(drag_source_board_id, coord.x, coord.y, auto_steal);
       drag_element.remove();
       drag_element = null;
       drag_source_board_id = null;
@@ -1440,6 +1902,21 @@ function set_up_menu_pointers() {
   menu_dialog.addEventListener("contextmenu", context_menu);
 }
 
+function set_up_clock_taps() {
+  // Tapping a clock makes its board the target for keyboard input (see `/focus`).
+  for (const board_id of ["primary", "secondary"]) {
+    for (const player_id of ["top", "bottom"]) {
+      const clock_container = document.getElementById(`clock-container-${board_id}-${player_id}`);
+      clock_container.addEventListener("click", () => {
+        with_error_handling(function () {
+          wasm_client().set_active_board(board_id);
+          update();
+        });
+      });
+    }
+  }
+}
+
 function set_up_log_navigation() {
   for (const board_id of ["primary", "secondary"]) {
     const area_node = document.getElementById(`turn-log-scroll-area-${board_id}`);
@@ -1451,6 +1928,17 @@ function set_up_log_navigation() {
         update();
       });
     });
+    area_node.addEventListener(
+      "scroll",
+      () => {
+        with_error_handling(function () {
+          const at_bottom =
+            area_node.scrollTop >= area_node.scrollHeight - area_node.clientHeight - 1;
+          wasm_client().on_log_scroll(board_id, at_bottom);
+        });
+      },
+      { passive: true },
+    );
   }
 }
 
@@ -1610,10 +2098,15 @@ function auto_open_menu() {
   with_error_handling(function () {
     const search_params = new URLSearchParams(window.location.search);
     const archive_game_id = search_params.get(SearchParams.archive_game_id);
+    const spectate_match_id = search_params.get(SearchParams.spectate_match_id);
     if (archive_game_id) {
       close_menu();
       wasm_client().view_archive_game_content(archive_game_id);
       update_events();
+    } else if (spectate_match_id) {
+      close_menu();
+      wasm_client().spectate(spectate_match_id.toUpperCase());
+      update_events();
     } else {
       open_menu();
     }
@@ -2002,6 +2495,7 @@ async function delete_account(event) {
 function show_create_match_page() {
   with_error_handling(function () {
     wasm_client().init_new_match_rules_body();
+    set_displayed(create_match_same_rules_button, wasm_client().has_last_match_rules());
   });
   push_menu_page(menu_create_match_page);
 }
@@ -2010,6 +2504,7 @@ function create_match_as_guest(event) {
   with_error_handling(function () {
     const player_name = document.getElementById("create-match-player-name").value;
     wasm_client().set_guest_player_name(player_name);
+    wasm_client().set_anonymous_guest(document.getElementById("create-match-anonymous").checked);
     show_create_match_page();
   });
 }
@@ -2030,9 +2525,30 @@ function on_join_match_submenu(event) {
   push_menu_page(menu_join_match_page);
 }
 
+function on_create_match_same_rules(event) {
+  with_error_handling(function () {
+    wasm_client().prefill_rules_from_current_match();
+  });
+}
+
+function on_apply_rules_code(event) {
+  with_error_handling(function () {
+    wasm_client().apply_rules_share_code(create_match_rules_code.value);
+  });
+}
+
+function on_copy_rules_code(event) {
+  with_error_handling(function () {
+    const code = wasm_client().new_match_rules_share_code();
+    create_match_rules_code.value = code;
+    navigator.clipboard?.writeText(code);
+  });
+}
+
 function on_create_match_confirm(event) {
   with_error_handling(function () {
-    wasm_client().new_match();
+    const scheduled_start = document.getElementById("create-match-scheduled-start").value;
+    wasm_client().new_match(scheduled_start ? new Date(scheduled_start).getTime() : null);
     update();
   });
 }
@@ -2041,6 +2557,7 @@ function on_join_match_confirm(event) {
   with_error_handling(function () {
     const data = new FormData(event.target);
     wasm_client().set_guest_player_name(data.get("player_name"));
+    wasm_client().set_anonymous_guest(data.get("anonymous") === "on");
     wasm_client().join(data.get("match_id").toUpperCase());
     update();
   });
@@ -2052,6 +2569,7 @@ function leave_active_match() {
     update();
     const url = new URL(window.location);
     url.searchParams.delete(SearchParams.match_id);
+    url.searchParams.delete(SearchParams.spectate_match_id);
     window.history.pushState({}, "", url);
     open_menu();
   });
@@ -2160,6 +2678,20 @@ function ensure_audio_context_running() {
   audio_context.resume();
 }
 
+// Reserve restock sound is always played. On top of that, the newly arrived pieces could be
+// flashed in the reserve and/or announced by voice.
+function reserve_restock_alert_includes(alert) {
+  const value = window.localStorage.getItem(Storage.reserve_restock_alert) || "flash";
+  return value === alert || value === "both";
+}
+
+function announce(text) {
+  if (!window.speechSynthesis) {
+    return;
+  }
+  window.speechSynthesis.speak(new SpeechSynthesisUtterance(text));
+}
+
 function play_audio(audio_buffer, pan) {
   ensure_audio_context_running();
   pan = pan || 0;
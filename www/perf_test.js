@@ -7,10 +7,10 @@ function sleep(time) {
 async function run_perf_test() {
   const SLEEP = 50;
   for (let i = 0; i < 10; ++i) {
-    wasm_client().click_element(`secondary-p6p4`);
+    wasm_client().click_element(`secondary-p6p4`, false);
     update();
     await sleep(SLEEP);
-    wasm_client().click_element(`secondary-p4p4`);
+    wasm_client().click_element(`secondary-p4p4`, false);
     update();
     await sleep(SLEEP);
     wasm_client().cancel_preturn("secondary");